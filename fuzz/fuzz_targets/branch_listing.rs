@@ -0,0 +1,24 @@
+//! Feed arbitrary `git branch -a` output to the branch parsers.
+//!
+//! The first line is taken as the remote name, and the rest as the listing. Besides not panicking,
+//! every PR name found must come from a line that really is a PR branch on that remote.
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use libgitpr::parse::{self, LocalBranch, RemoteBranch};
+
+fuzz_target!(|text: &str| {
+    let (remote, listing) = text.split_once('\n').unwrap_or((text, ""));
+    for name in parse::extract_pr_names(listing, remote) {
+        assert!(!name.is_empty() && !name.contains(char::is_whitespace));
+    }
+    parse::extract_deletable_branches(listing, remote);
+    for line in listing.lines() {
+        if let Some(branch) = RemoteBranch::parse(line, remote) {
+            assert!(LocalBranch::parse(line).is_some());
+            if let Some(name) = branch.pr_name() {
+                assert!(parse::pr_branch(&branch.name).is_some());
+                assert!(branch.name.starts_with(name));
+            }
+        }
+    }
+});
@@ -0,0 +1,12 @@
+//! Feed arbitrary `git push --porcelain` output to the push status parsers.
+//!
+//! The first line is taken as the ref we asked to push, and the rest as git's report.
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use libgitpr::parse;
+
+fuzz_target!(|text: &str| {
+    let (refname, porcelain) = text.split_once('\n').unwrap_or((text, ""));
+    parse::parse_push_status(porcelain, refname);
+    parse::parse_push_rejections(porcelain);
+});
@@ -0,0 +1,66 @@
+//! Define, remove, and list shorthands for git-pr commands
+//!
+//! `git pr-alias <name>=<command>` makes `git pr <name>` run `git pr <command>` (see
+//! `libgitpr::alias`). `--unset <name>` removes an alias, and with no arguments every alias is
+//! listed. Aliases are stored in the repository's config, or in your global config with
+//! `--global`.
+use clap::Parser;
+use libgitpr::{alias, tr, GitError};
+
+
+/// Define, remove, and list shorthands for git-pr commands
+#[derive(Parser)]
+#[command(name = "git pr-alias")]
+struct Alias {
+    /// Change your global config, rather than this repository's
+    #[arg(long)]
+    global: bool,
+
+    /// Remove the alias with this name
+    #[arg(long, value_name = "name", conflicts_with = "definition")]
+    unset: Option<String>,
+
+    /// Make `git pr <name>` run `git pr <command>`
+    #[arg(value_name = "name>=<command", value_parser = definition)]
+    definition: Option<(String,String)>,
+}
+
+// Alias names become the last part of a config key, so they have to follow git's rules for those.
+fn valid_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn definition(arg: &str) -> Result<(String,String),String> {
+    match arg.split_once('=') {
+        Some((name, command)) if valid_name(name) => Ok((name.to_string(), command.to_string())),
+        _ => Err(tr!("alias-bad-name")),
+    }
+}
+
+fn main() {
+    git_pr_cli::main(Alias::run)
+}
+
+impl Alias {
+    fn run(self) -> Result<(),GitError> {
+        let git = libgitpr::Git::discover()?;
+        libgitpr::policy::enforce(&git, "alias")?;
+
+        match (self.definition, self.unset) {
+            (Some((name, command)), _) => {
+                // Catch unbalanced quotes now, rather than every time the alias is used
+                alias::split(&command)?;
+                git.config_set(&format!("pr.alias.{}", name), &command, self.global)?;
+            },
+            (None, Some(name)) => git.config_unset(&format!("pr.alias.{}", name), self.global)?,
+            (None, None) => {
+                for (name, command) in alias::load(&git)? {
+                    println!("{} = {}", name, command);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+//! Show the audit log of mutating git-pr operations
+//!
+//! Each entry shows when it happened, who ran which command, the refs it changed, and how it
+//! turned out. Entries with a note, such as emergency merges, have it printed in an extra column.
+//! With `--command <name>`, only entries for that git-pr program (such as "clean") are shown.
+//!
+//! `--porcelain`, `--json`, and `--format <template>` work as described in `libgitpr::render`; the
+//! fields are listed in `libgitpr::audit::FIELDS`.
+use clap::Parser;
+use libgitpr::audit::{self, AuditLog};
+use libgitpr::render::Output;
+use libgitpr::GitError;
+
+
+/// Show the audit log of mutating git-pr operations
+#[derive(Parser)]
+#[command(name = "git pr-audit")]
+struct Audit {
+    /// Show only entries for this git-pr program, such as "clean"
+    #[arg(long, value_name = "name")]
+    command: Option<String>,
+
+    /// Print every column, in a stable form for scripts
+    #[arg(long, group = "output")]
+    porcelain: bool,
+
+    /// Print every field of each entry as a JSON object
+    #[arg(long, group = "output")]
+    json: bool,
+
+    /// Print each entry by filling in a template, like '{time}\t{who}'
+    #[arg(long, group = "output", value_name = "template")]
+    format: Option<String>,
+}
+
+fn main() {
+    git_pr_cli::main(Audit::run)
+}
+
+impl Audit {
+    // The output flags are exclusive, which clap has already checked.
+    fn output(&self) -> Output {
+        match (self.porcelain, self.json, &self.format) {
+            (true, _, _) => Output::Porcelain,
+            (_, true, _) => Output::Json,
+            (_, _, Some(template)) => Output::Template(template.clone()),
+            _ => Output::Pretty,
+        }
+    }
+
+    fn run(self) -> Result<(),GitError> {
+        let git = libgitpr::Git::discover()?;
+        libgitpr::policy::enforce(&git, "audit")?;
+        let config = libgitpr::config::Config::load(&git)?;
+        let columns = ["time", "who", "command", "refs", "outcome", "note"];
+        let renderer = self.output().renderer(&columns, audit::FIELDS, &config.date_format.value)?;
+
+        let entries = AuditLog::open(&git)?.entries()?;
+        let wanted = |command: &String| self.command.as_ref().is_none_or(|c| c == command);
+        for entry in entries.iter().filter(|e| wanted(&e.command)) {
+            println!("{}", renderer.render(&entry.to_record()));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+//! Measure git-pr's performance on this machine
+//!
+//! Builds a scratch repository with many PR branches (10,000 unless `--refs` says otherwise) under
+//! the system's temporary directory, and reports how long listing them takes, how fast the ref
+//! parsers get through git's output, and how much quicker a repeated `git diff --stat` is than the
+//! first, and than one git-pr has cached. Each timing is the median of several runs (`--runs`, 5 by
+//! default).
+//!
+//! This is a tool for git-pr's developers, and for users reporting a performance problem, so `git
+//! pr` doesn't list it among its commands. `cargo bench` measures the same workloads in more
+//! detail.
+use clap::Parser;
+use libgitpr::bench;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{tr, GitError};
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::num::NonZeroUsize;
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+
+/// Measure git-pr's performance on this machine
+#[derive(Parser)]
+#[command(name = "git pr-bench")]
+struct Bench {
+    /// How many PR branches the scratch repository has
+    #[arg(long, value_name = "N", default_value = "10000")]
+    refs: NonZeroUsize,
+
+    /// How many times each workload runs; the median is reported
+    #[arg(long, value_name = "N", default_value = "5")]
+    runs: NonZeroUsize,
+}
+
+fn millis(time: Duration) -> String {
+    format!("{:.1}", time.as_secs_f64() * 1000.0)
+}
+
+fn megabytes_per_second(bytes: usize, time: Duration) -> String {
+    format!("{:.1}", bytes as f64 / 1e6 / time.as_secs_f64())
+}
+
+fn main() {
+    git_pr_cli::main(run)
+}
+
+fn run(args: Bench) -> Result<(),GitError> {
+    let (refs, runs) = (args.refs.get(), args.runs.get());
+
+    // Like the self-test, this never involves the user's repository.
+    for var in ["GIT_DIR", "GIT_WORK_TREE", "GIT_INDEX_FILE"] {
+        env::remove_var(var);
+    }
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+    let dir = env::temp_dir().join(format!("git-pr-bench-{}-{}", process::id(), nanos));
+    fs::create_dir(&dir)?;
+    let repo = bench::synthetic_repo(&dir, refs);
+    let result = repo.and_then(|repo| {
+        let time = bench::median(runs, || {
+            black_box(PrIndex::load(&repo.git, "origin").map(|index| index.len()).ok());
+        });
+        println!("{}", tr!("bench-list", count = refs, millis = millis(time)));
+
+        let listing = bench::branch_listing(refs);
+        let time = bench::median(runs, || {
+            black_box(libgitpr::extract_pr_names(black_box(&listing), "origin"));
+        });
+        let speed = megabytes_per_second(listing.len(), time);
+        println!("{}", tr!("bench-parse-branches", speed = speed));
+
+        let listing = bench::ref_listing(refs);
+        let time = bench::median(runs, || {
+            black_box(PrIndex::from_refs("origin", black_box(&listing)));
+        });
+        let speed = megabytes_per_second(listing.len(), time);
+        println!("{}", tr!("bench-parse-refs", speed = speed));
+
+        let start = Instant::now();
+        repo.git.diff_stat(&repo.base, &repo.tip)?;
+        println!("{}", tr!("bench-diffstat-first", millis = millis(start.elapsed())));
+        let time = bench::median(runs, || {
+            black_box(repo.git.diff_stat(&repo.base, &repo.tip).ok());
+        });
+        println!("{}", tr!("bench-diffstat-repeated", millis = millis(time)));
+        let mut index = PrIndex::load(&repo.git, "origin")?;
+        let time = bench::median(runs, || {
+            black_box(index.diff_stat(&repo.git, "pr-0", &repo.base).ok());
+        });
+        println!("{}", tr!("bench-diffstat-cached", millis = millis(time)));
+        Ok(())
+    });
+
+    fs::remove_dir_all(&dir)?;
+    result
+}
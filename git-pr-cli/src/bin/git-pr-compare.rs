@@ -0,0 +1,64 @@
+//! Compare two pull requests with one another
+//!
+//! Useful when two contributors have accidentally started the same work. For each PR this shows
+//! its tip and how many commits it has that the other lacks; then it lists the files both PRs
+//! change, the files only one of them changes, and a `git diff --stat` between the two tips.
+//!
+//! PRs may be given by name ("hotfix") or, when several PRs share a name, by branch
+//! ("hotfix/1234567").
+use clap::Parser;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{compare, tr, GitError};
+
+
+/// Compare two pull requests with one another
+#[derive(Parser)]
+#[command(name = "git pr-compare")]
+struct Compare {
+    /// The first PR, by name or branch
+    #[arg(value_name = "pr-name")]
+    left: String,
+
+    /// The PR to compare it with
+    #[arg(value_name = "pr-name")]
+    right: String,
+}
+
+fn print_paths(heading: &str, paths: &[String]) {
+    println!("{} ({}):", heading, paths.len());
+    for path in paths {
+        println!("  {}", path);
+    }
+}
+
+fn main() {
+    git_pr_cli::main(run)
+}
+
+fn run(args: Compare) -> Result<(),GitError> {
+    let git = libgitpr::Git::discover()?;
+    libgitpr::policy::enforce(&git, "compare")?;
+    let config = libgitpr::config::Config::load(&git)?;
+    let index = PrIndex::load(&git, &config.remote.value)?;
+    let left = index.lookup(&args.left)?;
+    let right = index.lookup(&args.right)?;
+
+    let (ahead, behind) = git.divergence(&left.tip, &right.tip)?;
+    println!("{}\t{}\t{}", left.branch, left.tip,
+             tr!("compare-not-in", count = ahead, branch = right.branch));
+    println!("{}\t{}\t{}", right.branch, right.tip,
+             tr!("compare-not-in", count = behind, branch = left.branch));
+    println!();
+
+    let trunk = &config.trunk.value;
+    let paths = compare::overlap(&git.changed_paths(trunk, &left.tip)?,
+                                 &git.changed_paths(trunk, &right.tip)?);
+    print_paths(&tr!("compare-both"), &paths.shared);
+    print_paths(&tr!("compare-only", branch = left.branch), &paths.only_left);
+    print_paths(&tr!("compare-only", branch = right.branch), &paths.only_right);
+    println!("{}", tr!("compare-overlap", percent = format!("{:.0}", paths.similarity() * 100.0)));
+    println!();
+
+    print!("{}", git.diff_stat(&left.tip, &right.tip)?);
+    Ok(())
+}
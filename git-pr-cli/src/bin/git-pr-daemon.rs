@@ -0,0 +1,69 @@
+//! Answer JSON-RPC queries from editors and IDEs
+//!
+//! Run as `git pr-daemon --stdio`. See the `rpc` module for the protocol and supported methods.
+use clap::Parser;
+use libgitpr::rpc::{self, Outcome, Session};
+use libgitpr::watch::RefWatcher;
+use libgitpr::GitError;
+use std::io::{stdin, stdout};
+use std::sync::{Arc, Mutex};
+
+
+/// Answer JSON-RPC queries from editors and IDEs
+#[derive(Parser)]
+#[command(name = "git pr-daemon")]
+struct Daemon {
+    /// Talk to the client over stdin and stdout
+    // Only stdio is supported for now, but we require the flag so that other transports can be
+    // added later without changing what a bare `git pr-daemon` means.
+    #[arg(long, required = true)]
+    stdio: bool,
+}
+
+fn main() {
+    git_pr_cli::main(|_: Daemon| run())
+}
+
+fn run() -> Result<(),GitError> {
+    let git = libgitpr::Git::discover()?;
+    libgitpr::policy::enforce(&git, "daemon")?;
+    let mut session = Session::new(git);
+    let output = Arc::new(Mutex::new(stdout()));
+
+    // Responses and change notifications are written from different threads, so they share a lock
+    // on stdout to keep messages from interleaving.
+    let changes = session.changes();
+    let notify_output = Arc::clone(&output);
+    let _watcher = RefWatcher::start(session.git(), move |change| {
+        // A single ref update produces several filesystem events. Once the client has been told,
+        // there is no need to tell it again until it has asked for the new list.
+        let mut pending = match changes.lock() {
+            Ok(pending) => pending,
+            Err(_) => return
+        };
+        let already_notified = !pending.is_empty();
+        pending.push(change);
+        drop(pending);
+        if already_notified {
+            return;
+        }
+        if let Ok(mut output) = notify_output.lock() {
+            let _ = rpc::write_message(&mut *output, &rpc::changed_notification());
+        }
+    })?;
+
+    let stdin = stdin();
+    let mut input = stdin.lock();
+    while let Some(message) = rpc::read_message(&mut input)? {
+        match session.handle(&message) {
+            Outcome::Exit => break,
+            Outcome::Continue(None) => continue,
+            Outcome::Continue(Some(response)) => {
+                let mut output = output.lock().expect("stdout lock poisoned");
+                rpc::write_message(&mut *output, &response)?
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! Merge a PR straight into trunk, for genuine emergencies
+//!
+//! Run as `git pr-emergency-merge [<pr-name>] [--reason <justification>]`. This is the break-glass
+//! path: it merges and pushes trunk immediately, without waiting for CI checks or approvals. The
+//! merge is otherwise made like `git pr merge`'s: on top of the remote's trunk, in one atomic push
+//! which also records the PR as merged, retried if trunk moves in the meantime (see
+//! `libgitpr::merge::push_onto_trunk`).
+//!
+//! In exchange, a justification is mandatory. If `--reason` isn't given, it is asked for
+//! interactively. The justification is written into the merge commit as an `Emergency-Merge`
+//! trailer and recorded in the audit log, so that every use of this command can be reviewed
+//! afterwards.
+//!
+//! If `pr.webhookUrl` is set, an `emergency-merge` event is sent to it once trunk is pushed, with
+//! the PR's `name` and `branch`, the `reason`, and `who` merged it. That needs git-pr built with
+//! the `webhook` feature.
+//!
+//! Without a PR name, the PR is chosen interactively (see `libgitpr::picker`).
+use clap::Parser;
+use libgitpr::identity::Identity;
+use libgitpr::merge::{self, FastForward};
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, config, picker, tr, Git, GitError};
+use std::io::{self, BufRead, Write};
+
+
+/// Merge a PR straight into trunk, for genuine emergencies
+#[derive(Parser)]
+#[command(name = "git pr-emergency-merge")]
+struct EmergencyMerge {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "pr-name")]
+    name: Option<String>,
+
+    /// Why this can't wait for review; asked for if not given
+    #[arg(long, value_name = "justification")]
+    reason: Option<String>,
+
+    /// Refuse to run, since this pushes trunk
+    #[arg(long)]
+    read_only: bool,
+}
+
+fn main() {
+    git_pr_cli::main(run)
+}
+
+fn run(args: EmergencyMerge) -> Result<(),GitError> {
+    let EmergencyMerge { name, reason, read_only } = args;
+    // Without a name, we can only ask which PR was meant if there's someone to ask
+    if name.is_none() && !picker::interactive() {
+        git_pr_cli::usage_error::<EmergencyMerge>(tr!("pick-needs-name"));
+    }
+
+    let git = libgitpr::Git::discover()?;
+    libgitpr::policy::enforce(&git, "emergency-merge")?;
+    let config = config::Config::load(&git)?;
+    config.ensure_writable("emergency-merge", read_only)?;
+
+    git.fetch_prune()?;
+    let index = PrIndex::load(&git, &config.remote.value)?;
+    let pr = match name {
+        Some(name) => index.lookup(&name)?,
+        None => picker::pick_pr(&index)?
+            .ok_or_else(|| GitError::Refused(tr!("pick-cancelled")))?,
+    };
+
+    let reason = match reason {
+        Some(reason) => reason,
+        None => {
+            eprint!("{} ", tr!("emergency-prompt"));
+            io::stderr().flush()?;
+            let mut line = String::new();
+            io::stdin().lock().read_line(&mut line)?;
+            line
+        }
+    };
+    let reason = reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(GitError::Refused(tr!("emergency-needs-reason")));
+    }
+
+    let who = Identity::current(&git)?;
+    let trunk = &config.trunk.value;
+    let message = merge::merge_message(pr, &[
+        ("Emergency-Merge", reason.clone()),
+        ("Emergency-Merged-By", who.to_string()),
+    ]);
+    let result = merge::push_onto_trunk(&git, &config, pr, false, |base| {
+        merge::merge_commit(&git, trunk, base, pr, &message, FastForward::Never)
+    });
+    audit::record_with_note(&git, "emergency-merge", &[pr.branch.clone(), trunk.clone()],
+                            &result, Some(&format!("EMERGENCY: {}", reason)))?;
+    let commit = result?;
+
+    notify(&git, &[("name", &pr.name), ("branch", &pr.branch), ("reason", &reason),
+                   ("who", &who.to_string())])?;
+
+    merge::catch_up_trunk(&git, trunk, &commit, &config.remote.value)?;
+    eprintln!("{}", tr!("emergency-recorded", branch = pr.branch, trunk = trunk));
+    Ok(())
+}
+
+
+// Tell pr.webhookUrl about the emergency merge, if it is set. Trunk has already been pushed, so a
+// failure here is only a warning.
+#[cfg(feature = "webhook")]
+fn notify(git: &Git, fields: &[(&str, &str)]) -> Result<(),GitError> {
+    use libgitpr::webhook;
+    if let Err(e) = webhook::notify(git, &webhook::event("emergency-merge", fields)) {
+        eprintln!("{}", tr!("webhook-failed", error = e));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "webhook"))]
+fn notify(git: &Git, _fields: &[(&str, &str)]) -> Result<(),GitError> {
+    if git.config_get("pr.webhookUrl")?.is_some() {
+        eprintln!("{}", tr!("webhook-unsupported"));
+    }
+    Ok(())
+}
@@ -0,0 +1,27 @@
+//! Print git-pr's resolved configuration as JSON
+//!
+//! Wrapper scripts and editor plugins can use this to learn the trunk branch, the PR remote, and
+//! so on, rather than re-deriving them (and perhaps disagreeing with git-pr). Every value is
+//! accompanied by its source, such as "default" or "git config pr.trunk".
+use clap::Parser;
+use libgitpr::config::Config;
+use libgitpr::GitError;
+
+
+/// Print git-pr's resolved configuration as JSON
+#[derive(Parser)]
+#[command(name = "git pr-env")]
+struct Env {}
+
+fn main() {
+    git_pr_cli::main(|_: Env| run())
+}
+
+fn run() -> Result<(),GitError> {
+    let git = libgitpr::Git::discover()?;
+    libgitpr::policy::enforce(&git, "env")?;
+    let config = Config::load(&git)?;
+    println!("{:#}", config.to_json());
+
+    Ok(())
+}
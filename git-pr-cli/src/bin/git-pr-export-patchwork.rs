@@ -0,0 +1,49 @@
+//! Print a PR as a Patchwork series
+//!
+//! Run as `git pr-export-patchwork [<pr-name>] [-v <version>]`. The JSON printed follows the shape
+//! of Patchwork's series API, with one patch per commit between trunk and the PR's tip. As with
+//! `git format-patch`, `-v` marks the series as a revision of an earlier submission. Without a PR
+//! name, the PR is chosen interactively (see `libgitpr::picker`).
+use clap::Parser;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{patchwork, picker, tr, GitError};
+use std::num::NonZeroU32;
+
+
+/// Print a PR as a Patchwork series
+#[derive(Parser)]
+#[command(name = "git pr-export-patchwork")]
+struct ExportPatchwork {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "pr-name")]
+    name: Option<String>,
+
+    /// Mark the series as this revision of an earlier submission
+    #[arg(short = 'v', long = "reroll-count", value_name = "version", default_value = "1")]
+    version: NonZeroU32,
+}
+
+fn main() {
+    git_pr_cli::main(run)
+}
+
+fn run(args: ExportPatchwork) -> Result<(),GitError> {
+    // Without a name, we can only ask which PR was meant if there's someone to ask
+    if args.name.is_none() && !picker::interactive() {
+        git_pr_cli::usage_error::<ExportPatchwork>(tr!("pick-needs-name"));
+    }
+
+    let git = libgitpr::Git::discover()?;
+    libgitpr::policy::enforce(&git, "export-patchwork")?;
+    let config = libgitpr::config::Config::load(&git)?;
+    let index = PrIndex::load(&git, &config.remote.value)?;
+    let pr = match args.name {
+        Some(name) => index.lookup(&name)?,
+        None => picker::pick_pr(&index)?
+            .ok_or_else(|| GitError::Refused(tr!("pick-cancelled")))?,
+    };
+
+    let patches = patchwork::patches(&git, &config.trunk.value, &pr.tip)?;
+    println!("{:#}", patchwork::series(&pr.name, args.version.get(), &patches));
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! Mirror pull requests between git-pr and a Gitea or Forgejo server
+//!
+//! * `git pr-gitea list` shows the forge's open PRs.
+//! * `git pr-gitea pull` copies them into git-pr, as PRs named `gitea-<number>`.
+//! * `git pr-gitea push` opens a forge PR for every git-pr PR that doesn't have one yet.
+//!
+//! Only available when git-pr is built with the `gitea` feature. See the `gitea` module for
+//! configuration.
+use clap::{Parser, ValueEnum};
+use libgitpr::gitea::{self, Client};
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, config, metadata, Git, GitError};
+
+
+/// Mirror pull requests between git-pr and a Gitea or Forgejo server
+#[derive(Parser)]
+#[command(name = "git pr-gitea")]
+struct Gitea {
+    /// What to do: show the forge's PRs, copy them here, or publish ours there
+    #[arg(value_enum)]
+    action: Action,
+
+    /// Refuse to pull or push, since those change the repository and the forge
+    #[arg(long)]
+    read_only: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Action {
+    List,
+    Pull,
+    Push,
+}
+
+// Bring the forge's PRs into git-pr, returning the branches created.
+fn pull(git: &Git, client: &Client, remote: &str) -> Result<Vec<String>,GitError> {
+    let pulls = client.open_pulls()?;
+    let refspecs: Vec<String> = pulls.iter()
+        .map(|pull| format!("+{}:refs/gitea/pull/{}", pull.head_ref(), pull.number))
+        .collect();
+    if refspecs.is_empty() {
+        return Ok(vec![]);
+    }
+    git.fetch_refs(&client.remote, &refspecs)?;
+
+    let mut branches = vec![];
+    for pull in &pulls {
+        // PRs which started life in git-pr are already here
+        if gitea::mirrored_as(&metadata::lines(git, &pull.head_sha)?) == Some(pull.number) {
+            continue;
+        }
+        git.update_ref(&format!("refs/heads/{}", pull.branch()), &pull.head_sha)?;
+        branches.push(pull.branch());
+    }
+    if branches.is_empty() {
+        return Ok(branches);
+    }
+    git.push_branches(remote, &branches)?;
+
+    metadata::update(git, remote, |git| {
+        for pull in &pulls {
+            let existing = metadata::lines(git, &pull.head_sha)?;
+            if gitea::mirrored_as(&existing).is_none() {
+                git.append_note(metadata::NOTES_REF, &pull.head_sha, &metadata::format_line(
+                    &format!("{} by {}: {}", gitea::mirror_line(pull.number), pull.author,
+                             pull.title)
+                ))?;
+            }
+        }
+        Ok(())
+    })?;
+    Ok(branches)
+}
+
+// Open forge PRs for git-pr's PRs, returning the branches published.
+fn push(git: &Git, client: &Client, remote: &str, trunk: &str) -> Result<Vec<String>,GitError> {
+    let mut published = vec![];
+    for pr in PrIndex::load(git, remote)?.iter() {
+        if gitea::mirrored_as(&metadata::lines(git, &pr.tip)?).is_some() {
+            continue;
+        }
+        git.push_branches(&client.remote,
+                          &[format!("{}:refs/heads/{}", pr.tip, pr.branch)])?;
+        let number = client.create_pull(&pr.branch, trunk, &pr.name)?;
+        metadata::append(git, remote, &pr.tip, &gitea::mirror_line(number))?;
+        println!("{}\t#{}", pr.branch, number);
+        published.push(pr.branch.clone());
+    }
+    Ok(published)
+}
+
+fn main() {
+    git_pr_cli::main(run)
+}
+
+fn run(args: Gitea) -> Result<(),GitError> {
+    let git = Git::discover()?;
+    libgitpr::policy::enforce(&git, "gitea")?;
+    let config = config::Config::load(&git)?;
+    if args.action != Action::List {
+        config.ensure_writable("gitea", args.read_only)?;
+    }
+    let client = Client::from_config(&git)?;
+    let remote = &config.remote.value;
+
+    let (command, result) = match args.action {
+        Action::List => {
+            for pull in client.open_pulls()? {
+                println!("#{}\t{}\t{}", pull.number, pull.author, pull.title);
+            }
+            return Ok(());
+        },
+        Action::Pull => ("gitea-pull", pull(&git, &client, remote)),
+        Action::Push => ("gitea-push", push(&git, &client, remote, &config.trunk.value)),
+    };
+    let refs = result.as_ref().cloned().unwrap_or_default();
+    audit::record(&git, command, &refs, &result)?;
+    result.map(|_| ())
+}
@@ -0,0 +1,101 @@
+//! Bring open Gerrit changes across as pull requests
+//!
+//! Run as `git pr-import-gerrit <changes.json>`, or with `-` to read the JSON from stdin. See the
+//! `gerrit` module for the expected input and for how changes are mapped onto PRs. The resulting
+//! branches and metadata are published to the PR remote. Importing the same changes again is
+//! harmless: existing branches are left where they are and metadata isn't duplicated.
+use clap::Parser;
+use libgitpr::{audit, config, gerrit, metadata, tr, GitError};
+use std::fs;
+use std::io::{self, Read};
+
+
+/// Bring open Gerrit changes across as pull requests
+#[derive(Parser)]
+#[command(name = "git pr-import-gerrit")]
+struct ImportGerrit {
+    /// Gerrit's JSON description of the changes, or - to read it from stdin
+    #[arg(value_name = "changes.json")]
+    path: String,
+
+    /// Refuse to run, since this changes the repository and the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+fn main() {
+    git_pr_cli::main(run)
+}
+
+fn run(args: ImportGerrit) -> Result<(),GitError> {
+    let text = match args.path.as_str() {
+        "-" => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            text
+        },
+        path => fs::read_to_string(path)?
+    };
+    let changes = gerrit::parse_changes(&text).map_err(|e| {
+        GitError::Refused(tr!("gerrit-unreadable", path = args.path, error = e))
+    })?;
+
+    let git = libgitpr::Git::discover()?;
+    libgitpr::policy::enforce(&git, "import-gerrit")?;
+    let config = config::Config::load(&git)?;
+    config.ensure_writable("import-gerrit", args.read_only)?;
+
+    // Check everything is present before creating anything, so a partial fetch can't leave a
+    // half-imported change behind.
+    let open: Vec<&gerrit::Change> = changes.iter().filter(|c| c.is_open()).collect();
+    for change in &open {
+        for patch_set in &change.patch_sets {
+            if git.resolve_ref(&patch_set.commit)?.is_none() {
+                return Err(GitError::Refused(format!(
+                    "patch set {} of change {} ({}) has not been fetched", patch_set.number,
+                    change.number, patch_set.commit
+                )));
+            }
+        }
+    }
+
+    let mut branches = vec![];
+    for change in &open {
+        for patch_set in &change.patch_sets {
+            let branch = change.branch(patch_set);
+            if git.resolve_ref(&format!("refs/heads/{}", branch))?.is_none() {
+                git.update_ref(&format!("refs/heads/{}", branch), &patch_set.commit)?;
+            }
+            branches.push(branch);
+        }
+    }
+    let result = match branches.is_empty() {
+        true => Ok(()),
+        false => git.push_branches(&config.remote.value, &branches),
+    };
+    let result = result.and_then(|_| metadata::update(&git, &config.remote.value, |git| {
+        for change in &open {
+            for patch_set in &change.patch_sets {
+                let mut lines = vec![format!(
+                    "iteration {} of gerrit change {}: {}", patch_set.number, change.number,
+                    change.subject
+                )];
+                if Some(patch_set) == change.patch_sets.last() {
+                    lines.extend(change.votes.iter().cloned());
+                }
+
+                let existing = metadata::lines(git, &patch_set.commit)?;
+                for line in lines.iter().filter(|l| !existing.contains(l)) {
+                    git.append_note(metadata::NOTES_REF, &patch_set.commit,
+                                    &metadata::format_line(line))?;
+                }
+            }
+        }
+        Ok(())
+    }));
+    audit::record(&git, "import-gerrit", &branches, &result)?;
+    result?;
+
+    println!("{}", tr!("gerrit-imported", count = open.len(), total = changes.len()));
+    Ok(())
+}
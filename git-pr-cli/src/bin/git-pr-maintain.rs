@@ -0,0 +1,150 @@
+//! Expire old archived PRs and prune orphaned PR metadata
+//!
+//! Archive tags older than `pr.archiveRetentionDays` are deleted; if that isn't configured, they
+//! are kept forever. Metadata notes whose commits no longer exist are removed as well.
+//!
+//! The remote is maintained along with this clone: its archive tags are fetched, and the expired
+//! ones are deleted from it in one atomic push, so either all of them go or none do. Pruned notes
+//! are published like any other change to metadata (see `libgitpr::metadata::update`), so that
+//! nobody's newer metadata is lost.
+//!
+//! Everything that is about to be deleted is listed first. With `--dry-run`, nothing is actually
+//! deleted, so the list can be reviewed before committing to it.
+//!
+//! On repos with years of archives, `--limit N` expires at most N of them per run. Progress is
+//! remembered between runs, as `git pr clean` does, so repeating the command works through the
+//! backlog one chunk at a time.
+//!
+//! `--metrics-file <path>` writes Prometheus metrics describing the run to the given path, for the
+//! node exporter's textfile collector to pick up.
+use clap::Parser;
+use libgitpr::cursor::{self, Cursor};
+use libgitpr::metrics::{self, Metric};
+use libgitpr::retention::ARCHIVE_PREFIX;
+use libgitpr::{audit, config, metadata, retention, tr, GitError};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// Expire old archived PRs and prune orphaned PR metadata
+#[derive(Parser)]
+#[command(name = "git pr-maintain")]
+struct Maintain {
+    /// List what would be deleted, without deleting anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Expire at most N archives, picking up where the last run stopped
+    #[arg(long, value_name = "N")]
+    limit: Option<NonZeroUsize>,
+
+    /// Write Prometheus metrics about the run to this file
+    #[arg(long, value_name = "path")]
+    metrics_file: Option<PathBuf>,
+
+    /// Refuse to run, since this deletes archives and metadata
+    #[arg(long)]
+    read_only: bool,
+}
+
+fn main() {
+    git_pr_cli::main(Maintain::run)
+}
+
+impl Maintain {
+    fn run(self) -> Result<(),GitError> {
+        let git = libgitpr::Git::discover()?;
+        libgitpr::policy::enforce(&git, "maintain")?;
+        let config = config::Config::load(&git)?;
+        // A dry run only looks, so read-only accounts may still use it
+        if !self.dry_run {
+            config.ensure_writable("maintain", self.read_only)?;
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+
+        // Notes are only orphaned if nothing the remote has needs them, so fetch all of it first
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        git.fetch_refs(remote, &[format!("+{}*:{}*", ARCHIVE_PREFIX, ARCHIVE_PREFIX)])?;
+        metadata::sync(&git, remote)?;
+
+        let mut archives = match config.archive_retention_days.value {
+            Some(days) => retention::expired_archives(&git, now, days)?,
+            None => vec![],
+        };
+        archives.sort();
+        let cursor = Cursor::open(&git, "maintain")?;
+        let limit = self.limit.map(NonZeroUsize::get);
+        let chunk = cursor::next_chunk(&archives, cursor.position().as_deref(), limit);
+        let orphans = git.prune_notes(retention::NOTES_REF, true)?;
+
+        for tag in chunk {
+            println!("expire {}", tag);
+        }
+        for object in &orphans {
+            println!("prune note for {}", object);
+        }
+        if self.dry_run {
+            return Ok(());
+        }
+
+        // Tags which only this clone still has are deleted here, but can't be deleted there
+        let published = git.ls_remote(remote, ARCHIVE_PREFIX)?;
+        let deletions: Vec<String> = chunk.iter()
+            .filter(|tag| published.lines().any(|line| line.ends_with(&format!(" {}", tag))))
+            .map(|tag| format!(":{}", tag))
+            .collect();
+        let mut expired = vec![];
+        let mut result = match deletions.is_empty() {
+            true => Ok(()),
+            false => git.push_atomic(remote, &deletions),
+        };
+        if result.is_ok() {
+            for tag in chunk {
+                result = git.delete_ref(tag);
+                if result.is_err() {
+                    break;
+                }
+                expired.push(tag.clone());
+                cursor.advance(tag)?;
+            }
+        }
+        if result.is_ok() && !orphans.is_empty() {
+            result = metadata::update(&git, remote, |git| {
+                git.prune_notes(retention::NOTES_REF, false).map(|_| ())
+            });
+            if result.is_ok() {
+                expired.push(retention::NOTES_REF.to_string());
+            }
+        }
+        if !expired.is_empty() || result.is_err() {
+            audit::record(&git, "maintain", &expired, &result)?;
+        }
+        result?;
+
+        // Only once we've reached the end of the list is it safe to start over from the beginning
+        if chunk.is_empty() || chunk.last() == archives.last() {
+            cursor.finish()?;
+        } else {
+            eprintln!("{}", tr!("maintain-stopped", count = chunk.len()));
+        }
+
+        if let Some(path) = self.metrics_file {
+            metrics::write_file(&path, &[
+                Metric::gauge("gitpr_maintain_expired_archives",
+                              "Archived PRs expired by the last git pr-maintain run",
+                              chunk.len() as f64),
+                Metric::gauge("gitpr_maintain_remaining_archives",
+                              "Expired archived PRs left for future git pr-maintain runs",
+                              (archives.len() - chunk.len()) as f64),
+                Metric::gauge("gitpr_maintain_pruned_notes",
+                              "Orphaned metadata notes pruned by the last git pr-maintain run",
+                              orphans.len() as f64),
+                Metric::gauge("gitpr_maintain_last_run_timestamp_seconds",
+                              "When git pr-maintain last completed", now as f64),
+            ])?;
+        }
+        Ok(())
+    }
+}
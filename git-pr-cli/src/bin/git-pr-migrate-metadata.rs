@@ -0,0 +1,38 @@
+//! Upgrade shared PR metadata to the current schema version
+//!
+//! Metadata written by older versions of git-pr is read correctly without this, but migrating it
+//! in place means older formats don't have to be supported forever. The upgrade is published to
+//! the shared remote the same way any other metadata change is, so it can't overwrite updates that
+//! other people make while it runs. If the remote holds metadata from a newer git-pr, nothing is
+//! changed.
+use clap::Parser;
+use libgitpr::{config, metadata, tr, GitError};
+
+
+/// Upgrade shared PR metadata to the current schema version
+#[derive(Parser)]
+#[command(name = "git pr-migrate-metadata")]
+struct MigrateMetadata {
+    /// Refuse to run, since this rewrites the shared metadata
+    #[arg(long)]
+    read_only: bool,
+}
+
+fn main() {
+    git_pr_cli::main(run)
+}
+
+fn run(args: MigrateMetadata) -> Result<(),GitError> {
+    let git = libgitpr::Git::discover()?;
+    libgitpr::policy::enforce(&git, "migrate-metadata")?;
+    let config = config::Config::load(&git)?;
+    config.ensure_writable("migrate-metadata", args.read_only)?;
+
+    let mut migrated = 0;
+    metadata::update(&git, &config.remote.value, |git| {
+        migrated = metadata::migrate_local(git)?;
+        Ok(())
+    })?;
+    println!("{}", tr!("metadata-migrated", count = migrated, version = metadata::CURRENT_VERSION));
+    Ok(())
+}
@@ -0,0 +1,67 @@
+//! Replicate PR branches and metadata from one remote to another
+//!
+//! Run as `git pr-mirror <from> <to> [--prune]`, for instance to keep an offsite backup of the
+//! review state. With `--prune`, PR branches that have disappeared from `<from>` are also deleted
+//! from `<to>`. Branches belonging to other tools are left alone on both sides, unless
+//! `pr.allowConvention` says otherwise.
+use clap::Parser;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, config, metadata, mirror, tr, GitError};
+
+
+/// Replicate PR branches and metadata from one remote to another
+#[derive(Parser)]
+#[command(name = "git pr-mirror")]
+struct Mirror {
+    /// The remote to copy PRs from
+    #[arg(value_name = "from-remote")]
+    from: String,
+
+    /// The remote to copy them to
+    #[arg(value_name = "to-remote")]
+    to: String,
+
+    /// Also delete PR branches which have disappeared from <from-remote>
+    #[arg(long)]
+    prune: bool,
+
+    /// Refuse to run, since this changes the destination remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+fn main() {
+    git_pr_cli::main(run)
+}
+
+fn run(args: Mirror) -> Result<(),GitError> {
+    let Mirror { from, to, prune, read_only } = args;
+    if from == to {
+        return Err(GitError::Refused(tr!("mirror-same-remote", remote = from)));
+    }
+
+    let git = libgitpr::Git::discover()?;
+    libgitpr::policy::enforce(&git, "mirror")?;
+    let config = config::Config::load(&git)?;
+    config.ensure_writable("mirror", read_only)?;
+    git.fetch_refs(&from, &[format!("+refs/heads/*:refs/remotes/{}/*", from)])?;
+    git.fetch_notes(&from)?;
+
+    let source = PrIndex::load(&git, &from)?;
+    let destination = mirror::destination_index(
+        &to, &git.ls_remote(&to, "refs/heads/")?, config.guard()
+    );
+    // The source may not have any metadata yet
+    let notes = metadata::tracking_ref(&from);
+    let notes = git.resolve_ref(&notes)?.map(|_| notes);
+    let refspecs = mirror::refspecs(&source, &destination, notes.as_deref(), prune);
+    if refspecs.is_empty() {
+        return Ok(());
+    }
+
+    let result = git.push_branches(&to, &refspecs);
+    audit::record(&git, "mirror", &refspecs, &result)?;
+    result?;
+    println!("{}", tr!("mirrored", count = source.len(), from = from, to = to));
+    Ok(())
+}
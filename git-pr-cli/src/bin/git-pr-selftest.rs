@@ -0,0 +1,63 @@
+//! Check that git-pr works with this git, and this configuration, before trusting it
+//!
+//! Runs a throwaway PR through create, list, comment, approve, merge, and clean, in scratch
+//! repositories under the system's temporary directory, and reports how each step went. The exit
+//! status is non-zero if any step failed. Git's own messages are interleaved with the report, whose
+//! lines all begin with "ok", "FAIL", or "skip".
+//!
+//! The scratch repositories are deleted afterwards, unless `--keep` is given, in which case their
+//! location is printed so that a failure can be investigated.
+use clap::Parser;
+use libgitpr::selftest::{self, Outcome};
+use libgitpr::{exit, tr, GitError};
+use std::env;
+use std::fs;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// Check that git-pr works with this git, and this configuration, before trusting it
+#[derive(Parser)]
+#[command(name = "git pr-selftest")]
+struct Selftest {
+    /// Keep the scratch repositories, and print where they are
+    #[arg(long)]
+    keep: bool,
+}
+
+fn main() {
+    git_pr_cli::main(run)
+}
+
+fn run(args: Selftest) -> Result<(),GitError> {
+    // The self-test doesn't involve the user's repository at all (so there is no policy to
+    // enforce), and must not be pointed at it by the environment.
+    for var in ["GIT_DIR", "GIT_WORK_TREE", "GIT_INDEX_FILE"] {
+        env::remove_var(var);
+    }
+
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+    let dir = env::temp_dir().join(format!("git-pr-selftest-{}-{}", process::id(), nanos));
+    fs::create_dir(&dir)?;
+    let steps = selftest::run(&dir);
+
+    let mut passed = 0;
+    for step in &steps {
+        println!("{}", step);
+        if let Outcome::Passed(_) = step.outcome {
+            passed += 1;
+        }
+    }
+    println!("{}", tr!("selftest-summary", passed = passed, total = steps.len()));
+
+    if args.keep {
+        eprintln!("{}", tr!("selftest-kept", path = dir.display()));
+    } else {
+        fs::remove_dir_all(&dir)?;
+    }
+    if passed < steps.len() {
+        process::exit(exit::REFUSED)
+    }
+
+    Ok(())
+}
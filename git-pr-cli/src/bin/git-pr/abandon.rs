@@ -0,0 +1,56 @@
+//! Withdraw a pull request
+//!
+//! The PR's branch is deleted from the remote, so it no longer shows up in `git pr list`. PRs may
+//! be given by name ("hotfix") or, when several PRs share a name, by branch ("hotfix/1234567").
+//! Local branches are left alone; `git branch -D` removes one that is no longer wanted. The PR is
+//! recorded as abandoned (see `libgitpr::state`) in the same push which deletes its branch, unless
+//! it was merged already.
+//!
+//! The remote's branches are listed directly (see `Git::ls_remote_heads`) instead of fetched, so
+//! only PRs which are still on the remote can be abandoned.
+use crate::Shared;
+use clap::Args;
+use libgitpr::audit;
+use libgitpr::pull_request::{PrIndex, PullRequest};
+use libgitpr::state::{self, State};
+use libgitpr::{tr, Git, GitError};
+use std::slice;
+
+
+#[derive(Args)]
+pub struct Abandon {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Refuse to run, since this changes the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Abandon {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("abandon")?;
+        config.ensure_writable("abandon", self.read_only)?;
+
+        let remote = &config.remote.value;
+        let index = PrIndex::probe(&git, remote, "*")?;
+        let pr = index.lookup(&self.name)?;
+
+        let result = abandon_pr(&git, remote, pr);
+        audit::record(&git, "abandon", slice::from_ref(&pr.branch), &result)?;
+        result?;
+
+        if shared.verbose {
+            eprintln!("{}", tr!("abandon-deleted", branch = pr.branch, remote = remote));
+        }
+        Ok(())
+    }
+}
+
+
+/// Delete `pr`'s branch from `remote`, recording that it was abandoned.
+pub fn abandon_pr(git: &Git, remote: &str, pr: &PullRequest) -> Result<(),GitError> {
+    state::push_with(git, remote, &pr.branch, &pr.tip, State::Abandoned,
+                     &[format!(":refs/heads/{}", pr.branch)])
+}
@@ -0,0 +1,126 @@
+//! Publish a new revision of the current pull request
+//!
+//! After addressing review feedback by amending or adding commits, `git pr amend` publishes the
+//! current commit as a new `<name>/<hash>` branch, under the same name as the PR checked out, and
+//! switches to it, as `git pr create` would. The old revision stays on the remote, so reviewers can
+//! compare the two (see `git pr compare`), unless `--retire` is given: then it is archived, as `git
+//! pr archive` does, in the same atomic push which publishes the new one, and recorded as abandoned
+//! (see `libgitpr::state`). Either way, the new revision's metadata names the branch it replaces
+//! (see `libgitpr::revision`), and `git pr show` and `git pr timeline` mention it.
+//!
+//! The PR's description (see `git pr describe`) carries over to the new revision, as does a base
+//! other than trunk, given to `git pr create --base`.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::retention::ARCHIVE_PREFIX;
+use libgitpr::state::{self, State};
+use libgitpr::{audit, claim, description, metadata, parse, revision, tr, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Amend {
+    /// Archive the old revision, rather than leaving it on the remote
+    #[arg(long)]
+    retire: bool,
+
+    /// Refuse to run, since this changes the repository and the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Amend {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("amend")?;
+        config.ensure_writable("amend", self.read_only)?;
+
+        let remote = &config.remote.value;
+        let old = git.current_branch()?
+            .and_then(|branch| branch.strip_prefix("refs/heads/").map(|b| b.to_string()))
+            .filter(|branch| parse::pr_branch(branch).is_some())
+            .ok_or_else(|| GitError::Refused(tr!("amend-not-a-pr")))?;
+        let name = parse::pr_branch(&old).map_or("", |(name, _)| name);
+        let hash = git.rev_parse_head()?;
+        let new = format!("{}/{}", name, hash);
+        if new == old {
+            return Err(GitError::Refused(tr!("amend-unchanged", branch = old)));
+        }
+
+        git.fetch_prune()?;
+        if !PrIndex::probe(&git, remote, &new)?.is_empty() {
+            return Err(GitError::Refused(tr!("create-duplicate", branch = new,
+                                             remote = remote)));
+        }
+        let published = git.resolve_ref(&format!("refs/remotes/{}/{}", remote, old))?;
+        let tag = format!("{}{}", ARCHIVE_PREFIX, old);
+        let retiring = match &published {
+            Some(tip) if self.retire => {
+                if git.resolve_ref(&tag)?.is_some() {
+                    return Err(GitError::Refused(tr!("archive-exists", tag = tag)));
+                }
+                git.create_tag(tag.trim_start_matches("refs/tags/"), tip,
+                               &tr!("archive-message", branch = old))?;
+                true
+            },
+            _ => false,
+        };
+
+        let base = git.config_get(&format!("branch.{}.prBase", old))?;
+        let result = git.create_branch(&new)
+            .and_then(|_| match &base {
+                Some(base) => git.config_set(&format!("branch.{}.prBase", new), base, false),
+                None => Ok(()),
+            })
+            .and_then(|_| git.resolve_ref("HEAD"))
+            .and_then(|tip| {
+                let mut refspecs = vec![format!("refs/heads/{0}:refs/heads/{0}", new)];
+                refspecs.extend(state::transition(&git, remote, &new, &tip.unwrap_or_default(),
+                                                  State::Open)?);
+                if let (true, Some(old_tip)) = (retiring, &published) {
+                    refspecs.push(format!("{}:{}", tag, tag));
+                    refspecs.push(format!(":refs/heads/{}", old));
+                    refspecs.extend(state::transition(&git, remote, &old, old_tip,
+                                                      State::Abandoned)?);
+                }
+                git.push_atomic(remote, &refspecs)
+            })
+            .and_then(|_| git.set_upstream(remote, &new));
+        let mut refs = vec![old.clone(), new.clone()];
+        if retiring {
+            refs.push(tag.clone());
+        }
+        audit::record(&git, "amend", &refs, &result)?;
+        if result.is_err() && retiring {
+            // Nothing was archived, so don't leave a tag which says otherwise
+            git.delete_ref(&tag)?;
+        }
+        result?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        let mut applied = false;
+        metadata::update(&git, remote, |git| {
+            let at = claim::next_time(&metadata::lines(git, &hash)?, now);
+            if !applied {
+                let line = revision::revises_line(&old, at);
+                git.append_note(metadata::NOTES_REF, &hash, &metadata::format_line(&line))?;
+                applied = true;
+            }
+            Ok(())
+        })?;
+
+        if let Some(old_tip) = &published {
+            description::sync(&git, remote)?;
+            if let Some(text) = description::get(&git, old_tip)? {
+                description::set(&git, remote, &hash, &text)?;
+            }
+        }
+
+        eprintln!("{}", tr!("amend-done", new = new, old = old, remote = remote));
+        if retiring {
+            eprintln!("{}", tr!("archive-done", branch = old,
+                                tag = tag.trim_start_matches("refs/tags/"), remote = remote));
+        }
+        Ok(())
+    }
+}
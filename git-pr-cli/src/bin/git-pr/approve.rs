@@ -0,0 +1,54 @@
+//! Approve a pull request
+//!
+//! `git pr approve <name>` records that you approve the PR as it stands, by publishing an approval
+//! (see `libgitpr::approval`) on its tip. If the PR changes afterwards, the approval stays with the
+//! commit you reviewed, and `git pr reviews` reports it as being for an earlier revision.
+//!
+//! A bot listed in `pr.botIdentity` can approve as itself with `--as` (see `libgitpr::identity`).
+//! With `pr.signMetadata` set, the approval is signed (see `libgitpr::approval`), so that a merge
+//! gate can tell it really came from the approver.
+use crate::Shared;
+use clap::Args;
+use libgitpr::identity::Identity;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{approval, audit, tr, GitError};
+use std::slice;
+
+
+#[derive(Args)]
+pub struct Approve {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Approve as this bot identity, from pr.botIdentity, instead of as yourself
+    #[arg(long = "as", value_name = "identity")]
+    bot: Option<String>,
+
+    /// Refuse to run, since this publishes metadata to the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Approve {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("approve")?;
+        config.ensure_writable("approve", self.read_only)?;
+
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        let me = Identity::resolve(&git, self.bot.as_deref())?.to_string();
+        let result = approval::approve(&git, remote, &pr.tip, &me);
+        audit::record(&git, "approve", slice::from_ref(&pr.branch), &result)?;
+        let applied = result?;
+
+        let short: String = pr.tip.chars().take(7).collect();
+        match applied {
+            true => eprintln!("{}", tr!("approve-done", branch = pr.branch, commit = short)),
+            false => eprintln!("{}", tr!("approve-already", branch = pr.branch, commit = short)),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+//! Put a pull request away, where it can be found again
+//!
+//! Like `git pr abandon`, this deletes the PR's branch from the remote, but first it tags the PR's
+//! tip as `pr-archive/<branch>` (see `libgitpr::retention`), and the tag is pushed in the same
+//! atomic push as the deletion, so the work is never only in one place. The PR is recorded as
+//! abandoned in that push too (see `libgitpr::state`). `git pr reopen` brings it back, or by hand:
+//!
+//! ```console
+//! $ git fetch origin tag pr-archive/hotfix/1234567
+//! $ git push origin 'pr-archive/hotfix/1234567^{commit}:refs/heads/hotfix/1234567'
+//! ```
+//!
+//! With `--bundle <file>`, the PR's commits are also written to a `git bundle` (leaving out those
+//! already on trunk), which can be kept anywhere, and fetched from like a remote. Archive tags are
+//! expired by `git pr-maintain` once they are older than `pr.archiveRetentionDays`.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::retention::ARCHIVE_PREFIX;
+use libgitpr::state::{self, State};
+use libgitpr::{audit, tr, GitError};
+use std::env;
+use std::path::PathBuf;
+
+
+#[derive(Args)]
+pub struct Archive {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Also write the PR's commits to this bundle file
+    #[arg(long, value_name = "file")]
+    bundle: Option<PathBuf>,
+
+    /// Refuse to run, since this changes the repository and the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Archive {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("archive")?;
+        config.ensure_writable("archive", self.read_only)?;
+
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+
+        let tag = format!("{}{}", ARCHIVE_PREFIX, pr.branch);
+        if git.resolve_ref(&tag)?.is_some() {
+            return Err(GitError::Refused(tr!("archive-exists", tag = tag)));
+        }
+        let name = tag.trim_start_matches("refs/tags/");
+        git.create_tag(name, &pr.tip, &tr!("archive-message", branch = pr.branch))?;
+
+        let result = self.bundle.as_ref().map_or(Ok(()), |path| {
+            let path = env::current_dir()?.join(path);
+            let trunk = format!("{}/{}", remote, config.trunk.value);
+            let not_trunk = format!("^{}", trunk);
+            let revisions = match git.resolve_ref(&trunk)? {
+                Some(_) => vec![tag.as_str(), not_trunk.as_str()],
+                None => vec![tag.as_str()],
+            };
+            git.bundle_create(&path, &revisions)
+        }).and_then(|_| {
+            let refspecs = [format!("{}:{}", tag, tag), format!(":refs/heads/{}", pr.branch)];
+            state::push_with(&git, remote, &pr.branch, &pr.tip, State::Abandoned, &refspecs)
+        });
+        audit::record(&git, "archive", &[pr.branch.clone(), tag.clone()], &result)?;
+        if result.is_err() {
+            // Nothing was archived, so don't leave a tag which says otherwise
+            git.delete_ref(&tag)?;
+        }
+        result?;
+
+        eprintln!("{}", tr!("archive-done", branch = pr.branch, tag = name, remote = remote));
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Ask people to review a pull request
+//!
+//! `git pr assign <name> <user>...` assigns the PR to each user, as "Name <email>" (see
+//! `libgitpr::assignment`); `git pr unassign` takes them off again. Assignments are published as
+//! metadata, and shown by `git pr timeline`.
+//!
+//! `--auto` shares reviews out instead: of the reviewers listed in `pr.reviewer` (which may be
+//! given several times), it assigns whoever has the fewest open PRs assigned to them. The PR's
+//! owner, anyone it is already assigned to, and anyone away (see `git pr away`) are left out.
+use crate::Shared;
+use clap::Args;
+use libgitpr::identity::{self, Identity};
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{assignment, audit, availability, claim, metadata, owner, tr, Git, GitError};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Assign {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Who to assign, as "Name <email>"
+    #[arg(value_name = "user", required_unless_present = "auto", conflicts_with = "auto")]
+    users: Vec<String>,
+
+    /// Assign the reviewer from pr.reviewer with the fewest open assignments
+    #[arg(long)]
+    auto: bool,
+
+    /// Refuse to run, since this publishes metadata to the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Assign {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("assign")?;
+        config.ensure_writable("assign", self.read_only)?;
+
+        let users = match self.auto {
+            true => people(&git, &git.config_get_all("pr.reviewer")?)?,
+            false => people(&git, &self.users)?,
+        };
+        if users.is_empty() {
+            return Err(GitError::Refused(tr!("assign-no-reviewers")));
+        }
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+
+        // Decide again on every attempt, in case someone else's assignments have just arrived
+        let mut assigned = vec![];
+        let mut applied = false;
+        let result = metadata::update(&git, remote, |git| {
+            let lines = metadata::lines(git, &pr.tip)?;
+            let already = assignment::current(&lines);
+            assigned = match self.auto {
+                true => {
+                    let owner = match owner::current(&lines) {
+                        Some(owner) => owner,
+                        None => git.author_of(&pr.tip)?,
+                    };
+                    let away = availability::away(&availability::lines(git)?, now);
+                    let candidates: Vec<String> = users.iter()
+                        .filter(|who| **who != owner && !already.contains(who))
+                        .filter(|who| !away.contains_key(who.as_str()))
+                        .cloned()
+                        .collect();
+                    match assignment::least_loaded(&candidates, &load(git, &index)?) {
+                        Some(chosen) => vec![chosen.clone()],
+                        None => return Err(GitError::Refused(tr!("assign-no-reviewers"))),
+                    }
+                }
+                false => users.iter().filter(|who| !already.contains(who)).cloned().collect(),
+            };
+            if assigned.is_empty() {
+                return Err(GitError::Refused(tr!("assign-already", branch = pr.branch)));
+            }
+            let at = claim::next_time(&lines, now);
+            if !applied {
+                for who in &assigned {
+                    let line = assignment::assign_line(who, at);
+                    git.append_note(metadata::NOTES_REF, &pr.tip, &metadata::format_line(&line))?;
+                }
+                applied = true;
+            }
+            Ok(())
+        });
+        let mut refs = vec![pr.branch.clone()];
+        refs.extend(assigned.iter().cloned());
+        audit::record(&git, "assign", &refs, &result)?;
+        result?;
+
+        for who in assigned {
+            eprintln!("{}", tr!("assign-done", branch = pr.branch, user = who));
+        }
+        Ok(())
+    }
+}
+
+/// Parse each of `users` as "Name <email>", in the form the mailmap gives it.
+pub fn people(git: &Git, users: &[String]) -> Result<Vec<String>,GitError> {
+    let mut people = vec![];
+    for user in users {
+        people.push(Identity::parse(user)
+            .ok_or_else(|| GitError::Refused(tr!("handoff-bad-user", user = user)))?);
+    }
+    Ok(identity::canonicalize(git, &people)?.iter().map(Identity::to_string).collect())
+}
+
+/// How many of the open PRs in `index` each person is assigned to.
+pub fn load(git: &Git, index: &PrIndex) -> Result<BTreeMap<String, usize>,GitError> {
+    let mut open = vec![];
+    for pr in index.iter() {
+        open.push(metadata::lines(git, &pr.tip)?);
+    }
+    Ok(assignment::load(open.iter().map(Vec::as_slice)))
+}
@@ -0,0 +1,68 @@
+//! Attach files to a pull request
+//!
+//! `git pr attach <name> <file>...` publishes small files, like screenshots of a UI change or the
+//! log of a failing test, alongside the PR's metadata (see `libgitpr::attachment`), where `git pr
+//! attachments` can list and save them. Files larger than `pr.maxAttachmentKiB` (1024 unless
+//! configured) are refused, before anything is published.
+use crate::Shared;
+use clap::Args;
+use libgitpr::attachment::{self, Attachment};
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, metadata, tr, GitError};
+use std::fs;
+use std::path::PathBuf;
+
+
+#[derive(Args)]
+pub struct Attach {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// The files to attach
+    #[arg(value_name = "file", required = true)]
+    files: Vec<PathBuf>,
+
+    /// Refuse to run, since this publishes metadata to the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Attach {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("attach")?;
+        config.ensure_writable("attach", self.read_only)?;
+
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+
+        // Check every file before publishing any, so that one bad file attaches nothing
+        let mut files = vec![];
+        for path in &self.files {
+            let shown = path.display().to_string();
+            let name = match (fs::metadata(path), path.file_name()) {
+                (Ok(meta), Some(name)) if meta.is_file() => {
+                    attachment::check_size(&shown, meta.len(), config.max_attachment_kib.value)?;
+                    Attachment{
+                        blob: String::new(), size: meta.len(),
+                        name: name.to_string_lossy().to_string(),
+                    }
+                }
+                _ => return Err(GitError::Refused(tr!("attach-not-a-file", file = shown))),
+            };
+            files.push((fs::canonicalize(path)?, name));
+        }
+
+        let result = files.into_iter().try_for_each(|(path, mut attached)| {
+            attached.blob = attachment::store(&git, remote, &path)?;
+            metadata::append(&git, remote, &pr.tip, &attached.line())?;
+            println!("{}", tr!("attach-attached", attachment = attached, branch = pr.branch));
+            Ok(())
+        });
+        let refs = [metadata::NOTES_REF.to_string(), attachment::NOTES_REF.to_string()];
+        audit::record(&git, "attach", &refs, &result)?;
+        result
+    }
+}
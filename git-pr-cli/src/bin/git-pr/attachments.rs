@@ -0,0 +1,72 @@
+//! List, and save, the files attached to a pull request
+//!
+//! Shows each file attached with `git pr attach` to any of the PR's commits, with its size.
+//! `--save <dir>` writes them into a directory, under the names they were attached with.
+use crate::Shared;
+use clap::Args;
+use libgitpr::attachment;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::timeline;
+use libgitpr::{metadata, tr, GitError};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+
+#[derive(Args)]
+pub struct Attachments {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Save the attached files into this directory
+    #[arg(long, value_name = "dir")]
+    save: Option<PathBuf>,
+}
+
+impl Attachments {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("attachments")?;
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+        metadata::sync(&git, remote)?;
+        metadata::sync_ref(&git, remote, attachment::NOTES_REF)?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        if git.resolve_ref(&trunk)?.is_none() {
+            return Err(GitError::Refused(tr!("status-no-trunk", trunk = trunk)));
+        }
+
+        // Attachments stay with the commit that was the tip when they were made. A PR with no
+        // commits of its own has them on its tip, which is on trunk.
+        let history = git.commit_history(&format!("{}..{}", trunk, pr.tip))?;
+        let mut commits: Vec<String> = timeline::parse_commits(&history).into_iter()
+            .map(|commit| commit.hash)
+            .collect();
+        if commits.is_empty() {
+            commits.push(pr.tip.clone());
+        }
+        let mut lines = vec![];
+        for commit in &commits {
+            lines.extend(metadata::lines(&git, commit)?);
+        }
+        let attachments = attachment::find(&lines);
+        if attachments.is_empty() {
+            eprintln!("{}", tr!("attachments-none", branch = pr.branch));
+            return Ok(());
+        }
+
+        for attached in &attachments {
+            println!("{}  {}", git.abbreviate(&attached.blob)?, attached);
+            if let Some(dir) = &self.save {
+                // Never let a name written by someone else place a file outside `dir`
+                let name = Path::new(&attached.name).file_name().unwrap_or_default();
+                let path = dir.join(name);
+                fs::create_dir_all(dir)?;
+                fs::write(&path, git.read_blob(&attached.blob)?)?;
+                eprintln!("{}", tr!("attachments-saved", file = path.display()));
+            }
+        }
+        Ok(())
+    }
+}
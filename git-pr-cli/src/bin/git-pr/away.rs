@@ -0,0 +1,61 @@
+//! Say you are away, so that reviews stop coming your way
+//!
+//! `git pr away --until <date>` publishes that you are out until then (see
+//! `libgitpr::availability`), with the date as git understands it, like "2026-10-24".
+//! `git pr assign --auto` passes you over in the meantime, and `git pr reviewers` shows when you
+//! will be back. `--back` says you are back early.
+use crate::Shared;
+use clap::Args;
+use libgitpr::date::DateFormat;
+use libgitpr::identity::{self, Identity};
+use libgitpr::{audit, availability, metadata, tr, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Away {
+    /// When you will be back, like 2026-10-24
+    #[arg(long, value_name = "date", required_unless_present = "back", conflicts_with = "back")]
+    until: Option<String>,
+
+    /// Say you are back
+    #[arg(long)]
+    back: bool,
+
+    /// Refuse to run, since this publishes metadata to the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Away {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("away")?;
+        config.ensure_writable("away", self.read_only)?;
+
+        let me = identity::canonicalize(&git, &[Identity::current(&git)?])?.remove(0).to_string();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        let until = match &self.until {
+            Some(date) => {
+                let until = git.parse_date(date)?;
+                if until <= now {
+                    return Err(GitError::Refused(tr!("away-not-future", date = date)));
+                }
+                until
+            }
+            None => now,
+        };
+
+        let result = availability::record(&git, &config.remote.value, &me, until, now);
+        audit::record(&git, "away", &[metadata::NOTES_REF.to_string()], &result)?;
+        result?;
+        match self.back {
+            true => eprintln!("{}", tr!("away-back", user = me)),
+            false => {
+                let until = DateFormat::configured(&config.date_format.value, false)
+                    .render(until, now);
+                eprintln!("{}", tr!("away-done", user = me, until = until));
+            }
+        }
+        Ok(())
+    }
+}
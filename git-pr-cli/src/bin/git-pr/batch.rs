@@ -0,0 +1,203 @@
+//! Do the same thing to every pull request with a label
+//!
+//! For sweeps, like abandoning every PR labeled "superseded": `git pr batch --label <label>
+//! <operation>` lists the PRs with that label (see `git pr label`), asks before going on, and then
+//! works through them, reporting how each one went. One PR failing doesn't stop the rest, but
+//! makes the command fail at the end. The operations are:
+//!
+//! - `merge`, which merges each PR into trunk as `git pr merge` does, and pushes trunk.
+//! - `abandon`, which deletes each PR's branch from the remote, as `git pr abandon` does.
+//! - `rebase`, which rebases each PR onto trunk and force-pushes it, as `git pr update` does, but
+//!   without checking anything out: the rebase happens in a scratch worktree. PRs which don't
+//!   rebase cleanly are left as they were. Metadata stays with the commit it was written about, so
+//!   the rebased PR is labeled again, to keep it in later sweeps.
+//!
+//! Without a terminal to ask on, `--yes` must be given. Each operation is allowed or refused by
+//! `pr.role.*` as the command it stands for, and is recorded in the audit log as that command.
+use crate::abandon::abandon_pr;
+use crate::merge::merge_pr;
+use crate::Shared;
+use clap::{Args, ValueEnum};
+use libgitpr::config::Config;
+use libgitpr::merge::{catch_up_trunk, FastForward};
+use libgitpr::pull_request::{PrIndex, PullRequest};
+use libgitpr::{audit, claim, label, metadata, picker, policy, tr, Git, GitError};
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Operation {
+    Merge,
+    Abandon,
+    Rebase,
+}
+
+impl Operation {
+    // The command this operation does the work of, for policies and the audit log.
+    fn command(self) -> &'static str {
+        match self {
+            Operation::Merge => "merge",
+            Operation::Abandon => "abandon",
+            Operation::Rebase => "update",
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct Batch {
+    /// Work on the PRs with this label
+    #[arg(long, value_name = "label")]
+    label: String,
+
+    /// What to do to each PR
+    #[arg(value_enum)]
+    operation: Operation,
+
+    /// Go ahead without asking
+    #[arg(long)]
+    yes: bool,
+
+    /// Refuse to run, since this changes the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Batch {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("batch")?;
+        config.ensure_writable("batch", self.read_only)?;
+        let command = self.operation.command();
+        policy::enforce(&git, command)?;
+
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        metadata::sync(&git, remote)?;
+        let index = PrIndex::load(&git, remote)?;
+        let mut prs = vec![];
+        for pr in index.iter() {
+            if label::current(&metadata::lines(&git, &pr.tip)?).contains(&self.label) {
+                prs.push(pr);
+            }
+        }
+        if prs.is_empty() {
+            eprintln!("{}", tr!("batch-none", label = self.label));
+            return Ok(());
+        }
+
+        eprintln!("{}", tr!("batch-listing", count = prs.len(), label = self.label));
+        for pr in &prs {
+            eprintln!("\t{}", pr.branch);
+        }
+        if !self.yes && !confirm()? {
+            eprintln!("{}", tr!("batch-cancelled"));
+            return Ok(());
+        }
+
+        let trunk = &config.trunk.value;
+        let mut merged = None;
+        let mut failed = 0;
+        for pr in &prs {
+            let result = match self.operation {
+                Operation::Merge => {
+                    merge_pr(&git, &config, pr, FastForward::Allowed, false, shared.verbose)
+                        .map(|commit| {
+                            merged = Some(commit);
+                            tr!("batch-merged", branch = pr.branch, trunk = trunk)
+                        })
+                }
+                Operation::Abandon => abandon_pr(&git, remote, pr)
+                    .map(|_| tr!("batch-abandoned", branch = pr.branch)),
+                Operation::Rebase => rebase_one(&git, &config, pr),
+            };
+            let refs = match self.operation {
+                Operation::Merge => vec![pr.branch.clone(), trunk.clone()],
+                _ => vec![pr.branch.clone()],
+            };
+            audit::record(&git, command, &refs, &result)?;
+            match result {
+                Ok(outcome) => println!("{}", outcome),
+                Err(e) => {
+                    println!("{}", tr!("batch-failed", branch = pr.branch, error = e));
+                    failed += 1;
+                }
+            }
+        }
+
+        if let Some(commit) = merged {
+            catch_up_trunk(&git, trunk, &commit, remote)?;
+        }
+        match failed {
+            0 => Ok(()),
+            _ => Err(GitError::Refused(tr!("batch-some-failed", failed = failed,
+                                           count = prs.len()))),
+        }
+    }
+}
+
+
+// Ask whether to go on, if there is someone to ask.
+fn confirm() -> Result<bool,GitError> {
+    if !picker::interactive() {
+        return Err(GitError::Refused(tr!("batch-needs-yes")));
+    }
+    eprint!("{} ", tr!("batch-prompt"));
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+// Rebase `pr` onto trunk in a scratch worktree, and push it if anything changed.
+fn rebase_one(git: &Git, config: &Config, pr: &PullRequest) -> Result<String,GitError> {
+    let remote = &config.remote.value;
+    let trunk = format!("{}/{}", remote, config.trunk.value);
+    if git.resolve_ref(&trunk)?.is_none() {
+        return Err(GitError::Refused(tr!("status-no-trunk", trunk = trunk)));
+    }
+    if git.divergence(&pr.tip, &trunk)?.1 == 0 {
+        return Ok(tr!("update-up-to-date", branch = pr.branch, trunk = trunk));
+    }
+
+    // Clean up the scratch worktree however the rebase goes
+    let scratch = git.git_dir()?.join("git-pr").join(format!("batch-{}", process::id()));
+    git.add_worktree(&scratch, &pr.tip)?;
+    let worktree = Git{
+        program: git.program.clone(), working_dir: Box::new(scratch.clone()),
+        git_dir: None, work_tree: None,
+    };
+    let rebased = worktree.rebase(&trunk).and_then(|clean| match clean {
+        true => worktree.rev_parse_head().map(Some),
+        false => Ok(None),
+    });
+    git.remove_worktree(&scratch)?;
+    let tip = rebased?.ok_or_else(|| GitError::Refused(
+        tr!("batch-conflict", branch = pr.branch, trunk = trunk)
+    ))?;
+
+    let refname = format!("refs/heads/{}", pr.branch);
+    let rejections = git.try_push_atomic(remote, &[format!("{}:{}", tip, refname)],
+                                         &[(&refname, &pr.tip)])?;
+    if !rejections.is_empty() {
+        return Err(GitError::Refused(tr!("update-lease-lost", branch = pr.branch,
+                                         remote = remote)));
+    }
+
+    let labels = label::current(&metadata::lines(git, &pr.tip)?);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+    let mut applied = false;
+    metadata::update(git, remote, |git| {
+        let at = claim::next_time(&metadata::lines(git, &tip)?, now);
+        if !applied {
+            for l in &labels {
+                let line = label::label_line(l, at);
+                git.append_note(metadata::NOTES_REF, &tip, &metadata::format_line(&line))?;
+            }
+            applied = true;
+        }
+        Ok(())
+    })?;
+    Ok(tr!("batch-rebased", branch = pr.branch, trunk = trunk))
+}
+
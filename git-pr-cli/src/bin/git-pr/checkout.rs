@@ -0,0 +1,53 @@
+//! Switch to a pull request's branch
+//!
+//! PRs may be given by name ("hotfix") or, when several PRs share a name, by branch
+//! ("hotfix/1234567"); a name which is ambiguous is refused, with the branches it could mean. The
+//! remote is fetched first, and a local branch tracking the PR's branch is created if there isn't
+//! one already. An existing local branch is switched to as it is, with a note if it no longer
+//! matches the remote.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::{PrIndex, PullRequest};
+use libgitpr::{tr, Git, GitError};
+
+
+#[derive(Args)]
+pub struct Checkout {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+}
+
+impl Checkout {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("checkout")?;
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+
+        if let Some(note) = switch_to(&git, remote, pr)? {
+            eprintln!("{}", note);
+        }
+        eprintln!("{}", tr!("checkout-switched", branch = pr.branch));
+        Ok(())
+    }
+}
+
+/// Switch to `pr`'s branch, creating a local branch tracking it on `remote` if there is none.
+///
+/// Returns a note to pass on if the local branch no longer matches the remote.
+pub fn switch_to(git: &Git, remote: &str, pr: &PullRequest) -> Result<Option<String>,GitError> {
+    let note = match git.resolve_ref(&format!("refs/heads/{}", pr.branch))? {
+        None => {
+            git.create_tracking_branch(remote, &pr.branch)?;
+            None
+        },
+        Some(tip) if tip != pr.tip => {
+            Some(tr!("checkout-differs", branch = pr.branch, remote = remote))
+        },
+        Some(_) => None,
+    };
+    git.checkout(&pr.branch)?;
+    Ok(note)
+}
@@ -0,0 +1,135 @@
+//! Record what continuous integration made of a pull request
+//!
+//! CI jobs run `git pr ci set <name>` once they have built a PR, with `--status` saying how the
+//! build went, `--check name=status` saying how one of its checks went, with any numbers it
+//! measured after it (`--check coverage=pass,lines=87.5`), and `--artifact name=url` saying where
+//! its log, coverage report, binaries and so on can be found. Checks and artifacts can be given as
+//! many times as needed, and giving a name again replaces what was reported under it before. Merges
+//! can wait for named checks to pass (see `pr.requiredCheck`). Reports are attached to the PR's tip
+//! as metadata (see `libgitpr::ci`), and shown by `git pr show` and `git pr serve`, so that
+//! reviewers can go straight from a PR to its build.
+//!
+//! Reports say who made them: CI reports as a bot listed in `pr.botIdentity` with `--as` (see
+//! `libgitpr::identity`). With `pr.signMetadata` set, each report is signed, and merges only wait
+//! on the reports which git trusts.
+use crate::Shared;
+use clap::{Args, Subcommand};
+use libgitpr::ci::{self, Status};
+use libgitpr::identity::Identity;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, claim, metadata, signing, tr, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Ci {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Report a build of a PR: how it went, and where its artifacts are
+    Set(Set),
+}
+
+impl Ci {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        match self.command {
+            Command::Set(set) => set.run(shared),
+        }
+    }
+}
+
+
+#[derive(Args)]
+struct Set {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// How the build went: pending, pass, or fail
+    #[arg(long, value_name = "status", required_unless_present_any = ["checks", "artifacts"])]
+    status: Option<Status>,
+
+    /// How one check went, and what it measured, like coverage=pass,lines=87.5
+    #[arg(long = "check", value_name = "name=status[,metric=value...]")]
+    checks: Vec<String>,
+
+    /// Where the build put something, like log=https://ci.example.com/42/log
+    #[arg(long = "artifact", value_name = "name=url")]
+    artifacts: Vec<String>,
+
+    /// Report as this bot identity, from pr.botIdentity, instead of as yourself
+    #[arg(long = "as", value_name = "identity")]
+    bot: Option<String>,
+
+    /// Refuse to run, since this publishes metadata to the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Set {
+    fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("ci")?;
+        config.ensure_writable("ci", self.read_only)?;
+
+        let mut checks = vec![];
+        for check in &self.checks {
+            checks.push(ci::parse_check(check).ok_or_else(|| GitError::Refused(
+                tr!("ci-bad-check", check = check)
+            ))?);
+        }
+        let mut artifacts = vec![];
+        for artifact in &self.artifacts {
+            artifacts.push(ci::parse_artifact(artifact).ok_or_else(|| GitError::Refused(
+                tr!("ci-bad-artifact", artifact = artifact)
+            ))?);
+        }
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        let me = Identity::resolve(&git, self.bot.as_deref())?.to_string();
+        let sign = signing::enabled(&git)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+
+        let mut applied = false;
+        let result = metadata::update(&git, remote, |git| {
+            let at = claim::next_time(&metadata::lines(git, &pr.tip)?, now);
+            if !applied {
+                let mut lines: Vec<String> = self.status.iter()
+                    .map(|status| ci::status_line(*status, at, &me))
+                    .collect();
+                lines.extend(checks.iter()
+                    .map(|(name, check)| ci::check_line(name, check, at, &me)));
+                lines.extend(artifacts.iter()
+                    .map(|(name, url)| ci::artifact_line(name, url, at, &me)));
+                for line in lines {
+                    let line = match sign {
+                        true => signing::seal_line(git, &line, &pr.tip)?,
+                        false => line,
+                    };
+                    git.append_note(metadata::NOTES_REF, &pr.tip, &metadata::format_line(&line))?;
+                }
+                applied = true;
+            }
+            Ok(())
+        });
+        audit::record(&git, "ci", &[pr.branch.clone(), pr.tip.clone()], &result)?;
+        result?;
+
+        if let Some(status) = self.status {
+            eprintln!("{}", tr!("ci-recorded", branch = pr.branch, status = status));
+        }
+        for (name, check) in checks {
+            eprintln!("{}", tr!("ci-check-recorded", branch = pr.branch, name = name,
+                                check = check));
+        }
+        for (name, url) in artifacts {
+            eprintln!("{}", tr!("ci-artifact-recorded", branch = pr.branch, name = name,
+                                url = url));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+//! Remove local branches which have been merged into trunk
+//!
+//! On repos with release branches, a branch merged into one of them but not into trunk isn't done
+//! yet. Each branch listed in `pr.base` must also contain a branch before it is deleted, unless
+//! the PR recorded a base of its own (see `git pr create --base`), in which case being merged there
+//! is enough. A base with no local branch is looked for on the remote.
+//!
+//! On repos with many stale branches, `--limit N` deletes at most N branches per run. Progress is
+//! remembered between runs, so repeating the command works through the backlog one chunk at a
+//! time.
+//!
+//! In a shallow clone, git may fail to notice that a branch was merged, so we warn that some
+//! branches may be left behind. `--deepen` fetches the missing history first.
+//!
+//! Branches belonging to other tools, like `dependabot/*` and `renovate/*`, are left for those
+//! tools to manage unless `pr.allowConvention` says otherwise.
+//!
+//! `--metrics-file <path>` writes Prometheus metrics describing the run (and the number of open
+//! PRs) to the given path, for the node exporter's textfile collector to pick up.
+use crate::Shared;
+use clap::Args;
+use libgitpr::audit;
+use libgitpr::cursor::{self, Cursor};
+use libgitpr::metrics::{self, Metric};
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{parse, tr, GitError};
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Clean {
+    /// Delete at most N branches, picking up where the last run stopped
+    #[arg(long, value_name = "N")]
+    limit: Option<NonZeroUsize>,
+
+    /// In a shallow clone, fetch the full history first
+    #[arg(long)]
+    deepen: bool,
+
+    /// Write Prometheus metrics about the run to this file
+    #[arg(long, value_name = "path")]
+    metrics_file: Option<PathBuf>,
+
+    /// Refuse to run, since this deletes branches
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Clean {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("clean")?;
+        config.ensure_writable("clean", self.read_only)?;
+        if git.is_shallow()? {
+            if self.deepen {
+                git.unshallow()?;
+            } else {
+                eprintln!("{}", tr!("clean-shallow"));
+            }
+        }
+
+        let trunk = &config.trunk.value;
+        let recorded = parse::parse_recorded_bases(
+            &git.config_get_regexp(r"^branch\..*\.prbase$")?
+        );
+        let mut deletable = match config.bases.value.is_empty() && recorded.is_empty() {
+            true => libgitpr::extract_deletable_branches(&git.merged_branches(trunk)?, trunk),
+            false => {
+                let mut targets = vec![trunk.clone()];
+                targets.extend(config.bases.value.iter().cloned());
+                let mut merged = BTreeMap::new();
+                for base in targets.iter().chain(recorded.iter().map(|(_, base)| base)) {
+                    if merged.contains_key(base) {
+                        continue;
+                    }
+                    let remote_base = format!("{}/{}", config.remote.value, base);
+                    let found = match git.resolve_ref(&format!("refs/heads/{}", base))? {
+                        Some(_) => Some(base.clone()),
+                        None => git.resolve_ref(&remote_base)?.map(|_| remote_base),
+                    };
+                    let branches = match found {
+                        Some(found) => git.merged_branches(&found)?,
+                        None => String::new(),
+                    };
+                    merged.insert(base.clone(), branches);
+                }
+                parse::extract_fully_merged_branches(&merged, &targets, &recorded)
+            }
+        };
+        let guard = config.guard();
+        deletable.retain(|branch| !guard.excludes(branch));
+        deletable.sort();
+
+        let cursor = Cursor::open(&git, "clean")?;
+        let limit = self.limit.map(NonZeroUsize::get);
+        let chunk = cursor::next_chunk(&deletable, cursor.position().as_deref(), limit);
+        let mut deleted = vec![];
+        let mut result = Ok(());
+        for branch in chunk {
+            // git only knows to check HEAD, so branches merged into their own base are forced
+            result = match recorded.iter().any(|(name, _)| name == branch) {
+                true => git.force_delete_branch(branch),
+                false => git.delete_branch(branch),
+            };
+            if result.is_err() {
+                break;
+            }
+            if shared.verbose {
+                eprintln!("{}", tr!("clean-deleted", branch = branch));
+            }
+            deleted.push(branch.clone());
+            cursor.advance(branch)?;
+        }
+        audit::record(&git, "clean", &deleted, &result)?;
+        result?;
+
+        // Only once we've reached the end of the list is it safe to start over from the beginning.
+        if chunk.is_empty() || chunk.last() == deletable.last() {
+            cursor.finish()?;
+        } else {
+            eprintln!("{}", tr!("clean-stopped", count = chunk.len()));
+        }
+
+        if let Some(path) = self.metrics_file {
+            let open_prs = PrIndex::load(&git, &config.remote.value)?.len();
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            metrics::write_file(&path, &[
+                Metric::gauge("gitpr_open_pull_requests",
+                              "PR branches currently known on the remote", open_prs as f64),
+                Metric::gauge("gitpr_clean_deleted_branches",
+                              "Merged branches deleted by the last git pr clean run",
+                              chunk.len() as f64),
+                Metric::gauge("gitpr_clean_remaining_branches",
+                              "Merged branches left for future git pr clean runs",
+                              (deletable.len() - chunk.len()) as f64),
+                Metric::gauge("gitpr_clean_last_run_timestamp_seconds",
+                              "When git pr clean last completed", now as f64),
+            ])?;
+        }
+
+        Ok(())
+    }
+}
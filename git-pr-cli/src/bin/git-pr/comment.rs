@@ -0,0 +1,47 @@
+//! Comment on a pull request
+//!
+//! `git pr comment <name> <text>...` publishes a comment as metadata on the PR's tip, where `git pr
+//! timeline` shows it. A comment is a single line; line breaks in the text become spaces. In a
+//! repository which encrypts metadata (`pr.encrypt`; see `libgitpr::encryption`), the text is
+//! encrypted for everyone in `pr.encryptTo` before it leaves this clone.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, comment, metadata, tr, GitError};
+
+
+#[derive(Args)]
+pub struct Comment {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// What to say
+    #[arg(value_name = "text", required = true)]
+    text: Vec<String>,
+
+    /// Refuse to run, since this publishes metadata to the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Comment {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("comment")?;
+        config.ensure_writable("comment", self.read_only)?;
+
+        let text = comment::normalize(&self.text.join(" "))?;
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+
+        let result = comment::add(&git, remote, &pr.tip, &text);
+        audit::record(&git, "comment", &[metadata::NOTES_REF.to_string()], &result)?;
+        result?;
+        if shared.verbose {
+            eprintln!("{}", tr!("comment-added", branch = pr.branch));
+        }
+        Ok(())
+    }
+}
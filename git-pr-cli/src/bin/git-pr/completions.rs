@@ -0,0 +1,158 @@
+//! Tab completion for `git pr`
+//!
+//! `git pr completions <shell>` prints a script which hooks `git pr` into git's own completion for
+//! bash, zsh, or fish. Load it from your shell's startup file:
+//!
+//! ```console
+//! $ source <(git pr completions bash --dynamic)
+//! ```
+//!
+//! The script completes command names, including other `git-pr-*` programs and aliases, as they
+//! were when it was generated. With `--dynamic`, it also completes the names of PRs for the
+//! commands which take one, by running the hidden `git pr __complete-pr-names`. That lists the PRs
+//! already known from the last fetch, without contacting the remote, so completion stays instant
+//! on a slow network.
+use crate::Shared;
+use clap::{Args, ValueEnum};
+use libgitpr::config::Config;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{alias, Git, GitError};
+use std::collections::BTreeSet;
+
+
+// The commands whose first argument is a PR's name.
+const TAKES_A_PR: &[&str] = &[
+    "exists", "take", "handoff", "checkout", "show", "log", "timeline", "merge", "land", "rename",
+    "abandon", "archive", "compare", "emergency-merge", "attach", "attachments", "export",
+    "comment", "squash-preview", "describe-request", "send",
+    "assign", "unassign", "label", "conflicts", "describe", "approve", "reviews",
+];
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(Args)]
+pub struct Completions {
+    /// The shell to print a completion script for
+    #[arg(value_enum)]
+    shell: Shell,
+
+    /// Also complete PR names, by asking git pr for them as you type
+    #[arg(long)]
+    dynamic: bool,
+}
+
+impl Completions {
+    pub fn run(self, _shared: &Shared) -> Result<(),GitError> {
+        // Aliases may be defined globally, so we look for them even outside of a repository
+        let git = Git::discover().unwrap_or_default();
+        let mut commands: BTreeSet<String> = crate::builtins().into_iter().collect();
+        commands.extend(crate::commands());
+        commands.extend(alias::load(&git)?.into_keys());
+        let commands = commands.into_iter().collect::<Vec<_>>().join(" ");
+        let takes_a_pr = match self.dynamic {
+            true => TAKES_A_PR,
+            false => &[],
+        };
+
+        print!("{}", match self.shell {
+            Shell::Bash => bash(&commands, takes_a_pr),
+            Shell::Zsh => zsh(&commands, takes_a_pr),
+            Shell::Fish => fish(&commands, takes_a_pr),
+        });
+        Ok(())
+    }
+}
+
+
+/// List the PRs known from the last fetch, one name per line, for completion scripts.
+#[derive(Args)]
+pub struct CompletePrNames {
+    /// Only list names which start with this
+    #[arg(default_value = "")]
+    prefix: String,
+}
+
+impl CompletePrNames {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        // Completion must be quick and quiet, so there is no fetch, policy, or error message here
+        let git = match Git::discover() {
+            Ok(git) => git,
+            Err(_) => return Ok(()),
+        };
+        let remote = match &shared.remote {
+            Some(remote) => remote.clone(),
+            None => Config::load(&git)?.remote.value,
+        };
+        let names: BTreeSet<String> = PrIndex::load(&git, &remote)?.names().into_iter()
+            .filter(|name| name.starts_with(&self.prefix))
+            .collect();
+        for name in names {
+            println!("{}", name);
+        }
+        Ok(())
+    }
+}
+
+
+// git's bash completion calls `_git_pr` to complete `git pr`.
+fn bash(commands: &str, takes_a_pr: &[&str]) -> String {
+    let mut script = format!("_git_pr ()\n\
+                              {{\n\
+                              \tif [ \"$cword\" -eq 2 ]; then\n\
+                              \t\t__gitcomp \"{}\"\n\
+                              \t\treturn\n\
+                              \tfi\n", commands);
+    if !takes_a_pr.is_empty() {
+        script.push_str(&format!("\tcase \"${{words[2]}}\" in\n\
+                                  \t{})\n\
+                                  \t\t__gitcomp_nl \"$(git pr __complete-pr-names \"$cur\" \
+                                  2>/dev/null)\"\n\
+                                  \t\t;;\n\
+                                  \tesac\n", takes_a_pr.join("|")));
+    }
+    script.push_str("}\n");
+    script
+}
+
+// zsh's _git calls `_git-pr` for user commands it has been told about.
+fn zsh(commands: &str, takes_a_pr: &[&str]) -> String {
+    let mut script = format!("zstyle ':completion:*:*:git:*' user-commands \
+                              pr:'pull requests without a forge'\n\
+                              _git-pr () {{\n\
+                              \tlocal -a commands\n\
+                              \tcommands=({})\n\
+                              \tif (( CURRENT == 2 )); then\n\
+                              \t\tcompadd -a commands\n\
+                              \t\treturn\n\
+                              \tfi\n", commands);
+    if !takes_a_pr.is_empty() {
+        script.push_str(&format!("\tcase $words[2] in\n\
+                                  \t{})\n\
+                                  \t\tcompadd -- ${{(f)\"$(git pr __complete-pr-names \
+                                  2>/dev/null)\"}}\n\
+                                  \t\t;;\n\
+                                  \tesac\n", takes_a_pr.join("|")));
+    }
+    script.push_str("}\n");
+    script
+}
+
+fn fish(commands: &str, takes_a_pr: &[&str]) -> String {
+    let mut script = format!("complete -f -c git -n __fish_git_needs_command -a pr \
+                              -d 'Pull requests without a forge'\n\
+                              complete -f -c git -n '__fish_git_using_command pr; \
+                              and test (count (commandline -opc)) -eq 2' -a '{}'\n", commands);
+    if !takes_a_pr.is_empty() {
+        script.push_str(&format!("complete -f -c git -n '__fish_git_using_command pr; \
+                                  and test (count (commandline -opc)) -eq 3; \
+                                  and contains -- (commandline -opc)[3] {}' \
+                                  -a '(git pr __complete-pr-names 2>/dev/null)'\n",
+                                 takes_a_pr.join(" ")));
+    }
+    script
+}
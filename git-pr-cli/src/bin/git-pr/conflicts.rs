@@ -0,0 +1,44 @@
+//! Check whether a pull request merges cleanly
+//!
+//! `git pr conflicts <name>` tries merging the PR into the remote's trunk, as `git pr merge` would,
+//! but only in git's object database (see `Git::merge_conflicts`): the working tree, the index,
+//! and every branch are left as they are, so it can be run in the middle of other work. The paths
+//! which conflict are printed one to a line, and the command fails if there are any, so that
+//! scripts can ask `if git pr conflicts hotfix >/dev/null; then ...`.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{tr, GitError};
+
+
+#[derive(Args)]
+pub struct Conflicts {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+}
+
+impl Conflicts {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("conflicts")?;
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        if git.resolve_ref(&trunk)?.is_none() {
+            return Err(GitError::Refused(tr!("status-no-trunk", trunk = trunk)));
+        }
+
+        let paths = git.merge_conflicts(&trunk, &pr.tip)?;
+        if paths.is_empty() {
+            eprintln!("{}", tr!("conflicts-clean", branch = pr.branch, trunk = trunk));
+            return Ok(());
+        }
+        for path in &paths {
+            println!("{}", path);
+        }
+        Err(GitError::Refused(tr!("conflicts-found", branch = pr.branch, trunk = trunk,
+                                  count = paths.len())))
+    }
+}
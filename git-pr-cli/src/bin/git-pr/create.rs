@@ -0,0 +1,118 @@
+//! Create a new local branch with an associated upstream tracking branch for a pull request.
+//!
+//! The branch is pushed to the remote named by `pr.remote` ('origin' unless configured otherwise).
+//! If the remote already has a PR of the same name starting from the same commit, nothing is
+//! created; the remote is asked directly, so this holds even if nothing has been fetched lately.
+//!
+//! A PR meant for a release branch rather than trunk can say so with `--base`. The base is
+//! remembered in the branch's config (`branch.<branch>.prBase`), and `git pr clean` keeps the
+//! branch until it has been merged there.
+//!
+//! The PR is recorded as open (see `libgitpr::state`) in the same push which publishes it.
+//!
+//! Without a name, one is suggested from the subject of HEAD's commit and the files the branch
+//! changes since trunk (see `libgitpr::naming`), numbered if the remote already has a PR by that
+//! name, and used once confirmed; `--yes` skips the question, which is needed when there is nobody
+//! to ask.
+use crate::Shared;
+use clap::Args;
+use libgitpr::config::Config;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::state::{self, State};
+use libgitpr::{audit, naming, picker, tr, Git, GitError};
+use std::io::{self, BufRead, Write};
+
+
+#[derive(Args)]
+pub struct Create {
+    /// What the PR is called, like "hotfix"; suggested from the changes if not given
+    #[arg(value_name = "name")]
+    name: Option<String>,
+
+    /// The branch this PR will be merged into, if not trunk
+    #[arg(long, value_name = "branch")]
+    base: Option<String>,
+
+    /// Use the suggested name without asking
+    #[arg(long)]
+    yes: bool,
+
+    /// Refuse to run, since this changes the repository and the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Create {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("create")?;
+        config.ensure_writable("create", self.read_only)?;
+
+        let remote = &config.remote.value;
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => match suggest(&git, &config, self.yes)? {
+                Some(name) => name,
+                None => {
+                    eprintln!("{}", tr!("create-cancelled"));
+                    return Ok(());
+                }
+            }
+        };
+
+        // Find the current hash of HEAD, and create a new branch called "name/hash"
+        let hash = git.rev_parse_head()?;
+        let branch_name = format!("{}/{}", name, hash);
+
+        // Push that branch to the shared remote, unless it is already there
+        if !PrIndex::probe(&git, remote, &branch_name)?.is_empty() {
+            return Err(GitError::Refused(tr!("create-duplicate", branch = branch_name,
+                                             remote = remote)));
+        }
+        let result = git.create_branch(&branch_name)
+            .and_then(|_| match &self.base {
+                Some(base) => git.config_set(&format!("branch.{}.prBase", branch_name), base,
+                                             false),
+                None => Ok(()),
+            })
+            .and_then(|_| git.resolve_ref("HEAD"))
+            .and_then(|tip| {
+                let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch_name);
+                state::push_with(&git, remote, &branch_name, &tip.unwrap_or_default(), State::Open,
+                                 &[refspec])
+            })
+            .and_then(|_| git.set_upstream(remote, &branch_name));
+        if shared.verbose && result.is_ok() {
+            eprintln!("{}", tr!("create-pushed", branch = branch_name, remote = remote));
+        }
+        audit::record(&git, "create", &[branch_name], &result)?;
+        result
+    }
+}
+
+// Suggest a name for a PR of HEAD, and ask whether to use it unless `yes`. Returns `None` if the
+// suggestion was turned down.
+fn suggest(git: &Git, config: &Config, yes: bool) -> Result<Option<String>,GitError> {
+    let remote = &config.remote.value;
+    let trunk = format!("{}/{}", remote, config.trunk.value);
+    let paths = match git.resolve_ref(&trunk)? {
+        Some(_) => git.changed_paths(&trunk, "HEAD")?,
+        None => Vec::new(),
+    };
+    let taken = PrIndex::probe(git, remote, "*")?.names();
+    let name = naming::unique(&naming::suggest(&git.subject("HEAD")?, &paths),
+                              |name| taken.iter().any(|other| other == name));
+    if yes {
+        return Ok(Some(name));
+    }
+    if !picker::interactive() {
+        return Err(GitError::Refused(tr!("create-needs-name", name = name)));
+    }
+    eprint!("{} ", tr!("create-suggest-prompt", name = name));
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    match answer.trim().to_lowercase().as_str() {
+        "" | "y" | "yes" => Ok(Some(name)),
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,91 @@
+//! Say what a pull request is for
+//!
+//! `git pr describe <name>` opens the PR's description in git's editor, and publishes whatever is
+//! saved (see `libgitpr::description`); `--message` gives the description on the command line
+//! instead, and `--file` reads it from a file, or from standard input if the file is `-`. `git pr
+//! show` prints the description above the PR's commits.
+use crate::Shared;
+use clap::Args;
+use libgitpr::description;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, tr, GitError};
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::Command;
+
+
+#[derive(Args)]
+pub struct Describe {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// The description, rather than editing it
+    #[arg(short, long, value_name = "text", conflicts_with = "file")]
+    message: Option<String>,
+
+    /// Read the description from this file, or standard input if it is -
+    #[arg(long, value_name = "file")]
+    file: Option<PathBuf>,
+
+    /// Refuse to run, since this publishes the description to the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Describe {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("describe")?;
+        config.ensure_writable("describe", self.read_only)?;
+
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        description::sync(&git, remote)?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+
+        let text = match (&self.message, &self.file) {
+            (Some(message), _) => message.clone(),
+            (None, Some(path)) if path.as_os_str() == "-" => {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text)?;
+                text
+            },
+            (None, Some(path)) => fs::read_to_string(path)?,
+            (None, None) => {
+                let path = git.git_dir()?.join("git-pr").join("DESCRIPTION_EDITMSG");
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                let current = description::get(&git, &pr.tip)?.unwrap_or_default();
+                let instructions = tr!("describe-instructions", branch = pr.branch);
+                fs::write(&path, format!("{}\n# {}\n", current, instructions))?;
+
+                let editor = git.editor()?;
+                let status = Command::new("sh")
+                    .arg("-c").arg(format!("{} \"$@\"", editor)).arg(&editor).arg(&path)
+                    .status()?;
+                if !status.success() {
+                    return Err(GitError::Refused(tr!("describe-editor-failed", editor = editor)));
+                }
+                let edited = fs::read_to_string(&path)?.lines()
+                    .filter(|line| !line.starts_with('#'))
+                    .map(|line| format!("{}\n", line))
+                    .collect();
+                fs::remove_file(&path)?;
+                edited
+            },
+        };
+        if text.trim().is_empty() {
+            return Err(GitError::Refused(tr!("describe-empty")));
+        }
+
+        let result = description::set(&git, remote, &pr.tip, &text);
+        audit::record(&git, "describe", &[pr.branch.clone(), description::NOTES_REF.to_string()],
+                      &result)?;
+        result?;
+        eprintln!("{}", tr!("describe-done", branch = pr.branch, remote = remote));
+        Ok(())
+    }
+}
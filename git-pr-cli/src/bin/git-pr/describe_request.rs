@@ -0,0 +1,56 @@
+//! Ask an upstream maintainer to pull a PR, the way kernel developers do
+//!
+//! `git pr describe-request <name>` prints what `git request-pull` would for the PR: the commit it
+//! starts from on the remote's trunk, the URL and branch to fetch it from, a shortlog of its
+//! commits and a diffstat. That is ready to paste into an email to whoever looks after the
+//! repository the PR should end up in. `--patch` adds the full diff.
+//!
+//! The URL is the remote's own (`remote.<remote>.url`). When people fetch from somewhere other
+//! than where the PR was pushed, like a read-only mirror, `--url` gives that instead.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{tr, GitError};
+
+
+#[derive(Args)]
+pub struct DescribeRequest {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Where the maintainer should fetch the PR from, instead of the remote's URL
+    #[arg(long, value_name = "url")]
+    url: Option<String>,
+
+    /// Include the full diff
+    #[arg(short, long)]
+    patch: bool,
+}
+
+impl DescribeRequest {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("describe-request")?;
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        if git.resolve_ref(&trunk)?.is_none() {
+            return Err(GitError::Refused(tr!("status-no-trunk", trunk = trunk)));
+        }
+        if git.commit_summaries(&format!("{}..{}", trunk, pr.tip))?.is_empty() {
+            return Err(GitError::Refused(tr!("land-nothing", branch = pr.branch, trunk = trunk)));
+        }
+
+        let url = match self.url {
+            Some(url) => url,
+            None => git.config_get(&format!("remote.{}.url", remote))?.ok_or_else(|| {
+                GitError::Refused(tr!("describe-request-no-url", remote = remote))
+            })?,
+        };
+        let end = format!("{}:{}", pr.tip, pr.branch);
+        print!("{}", git.request_pull(&trunk, &url, &end, self.patch)?);
+        Ok(())
+    }
+}
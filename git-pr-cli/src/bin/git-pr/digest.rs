@@ -0,0 +1,82 @@
+//! Summarize the PR activity of the last day or week, ready to be mailed
+//!
+//! The digest lists the PRs opened and merged during the period, the open ones which haven't
+//! changed in `--stale-days` days, and those waiting for you (see `libgitpr::digest`), as plain
+//! text or, with `--markdown`, as Markdown. It fetches first, so it is up to date when run from
+//! cron. `--to` puts `To:` and `Subject:` headers on top, so that the output can go straight to
+//! `sendmail -t`:
+//!
+//!     0 8 * * 1-5  cd ~/src/project && git pr digest --daily --to team@example.com | sendmail -t
+//!
+//! `--for` writes the digest for someone else, listing what is waiting for them instead of you.
+use crate::Shared;
+use clap::Args;
+use libgitpr::digest;
+use libgitpr::identity::{self, Identity};
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{metadata, tr, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAY: i64 = 86_400;
+
+
+#[derive(Args)]
+pub struct Digest {
+    /// Cover the last day (the default)
+    #[arg(long, conflicts_with = "weekly")]
+    daily: bool,
+
+    /// Cover the last week
+    #[arg(long)]
+    weekly: bool,
+
+    /// Write Markdown instead of plain text
+    #[arg(long)]
+    markdown: bool,
+
+    /// Count open PRs as gone quiet after this many days without changes
+    #[arg(long, value_name = "days", default_value_t = 14)]
+    stale_days: u32,
+
+    /// Start with email headers addressed to this address, for sendmail -t
+    #[arg(long, value_name = "address")]
+    to: Option<String>,
+
+    /// Write the digest for this person ("Name <email>") instead of you
+    #[arg(long = "for", value_name = "identity")]
+    reader: Option<String>,
+}
+
+impl Digest {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("digest")?;
+        let remote = &config.remote.value;
+        let reader = match &self.reader {
+            Some(text) => Identity::parse(text).ok_or_else(|| {
+                GitError::Refused(tr!("digest-bad-identity", identity = text))
+            })?,
+            None => Identity::current(&git)?,
+        };
+        // Owners and authors come out of git with the mailmap applied, so the reader must too
+        let me = identity::canonicalize(&git, &[reader])?.remove(0).to_string();
+
+        git.fetch_prune()?;
+        metadata::sync(&git, remote)?;
+        let index = PrIndex::load(&git, remote)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        let since = now - if self.weekly { 7 * DAY } else { DAY };
+        let digest = digest::Digest::gather(&git, &index, &config.trunk.value, &me, since, now,
+                                            i64::from(self.stale_days) * DAY)?;
+
+        if let Some(to) = &self.to {
+            println!("To: {}", to);
+            println!("Subject: {}", digest.title());
+            if self.markdown {
+                println!("Content-Type: text/markdown; charset=utf-8");
+            }
+            println!();
+        }
+        print!("{}", digest.render(self.markdown));
+        Ok(())
+    }
+}
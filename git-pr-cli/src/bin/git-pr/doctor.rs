@@ -0,0 +1,31 @@
+//! Check that this repository is set up for git-pr, and say how to fix it if not
+//!
+//! Checks, in order, that git is new enough, that this is a repository, that git-pr's settings
+//! make sense, that the remote exists and has trunk, and that it would accept a push (see
+//! `libgitpr::doctor`). Each check is reported on a line beginning "ok", "FAIL", or "skip", like
+//! `git pr-selftest`, and every failure says what to do about it. The exit status is non-zero if
+//! anything failed.
+use crate::Shared;
+use clap::Args;
+use libgitpr::doctor;
+use libgitpr::selftest::Outcome;
+use libgitpr::{tr, GitError};
+
+
+#[derive(Args)]
+pub struct Doctor {}
+
+impl Doctor {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        // Not shared.open, which would stop at the first of the problems we are looking for
+        let steps = doctor::run(shared.remote.as_deref(), shared.trunk.as_deref());
+        for step in &steps {
+            println!("{}", step);
+        }
+        let failed = steps.iter().filter(|step| matches!(step.outcome, Outcome::Failed(_))).count();
+        match failed {
+            0 => Ok(()),
+            _ => Err(GitError::Refused(tr!("doctor-failed", failed = failed, total = steps.len()))),
+        }
+    }
+}
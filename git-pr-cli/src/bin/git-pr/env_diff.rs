@@ -0,0 +1,88 @@
+//! Compare this clone's settings with the ones the team recommends
+//!
+//! `git pr env-diff` reads the settings committed to the remote's trunk at
+//! `.git-pr/recommended-config` (see `libgitpr::drift`), and lists every `pr.*` setting here which
+//! differs from them, one per line, along with what is recommended. It fails if anything does, so
+//! that scripts and hooks can check.
+//!
+//! With `--apply`, each of those settings is changed in the repository's config to match. A setting
+//! which comes from somewhere else, like the global config, is overridden there, where it can be;
+//! values that can only be removed where they were set are left, and reported.
+use crate::Shared;
+use clap::Args;
+use libgitpr::drift::{self, Drift};
+use libgitpr::{audit, tr, GitError};
+
+
+#[derive(Args)]
+pub struct EnvDiff {
+    /// Change the settings which differ, in this repository's config, to the recommended ones
+    #[arg(long)]
+    apply: bool,
+
+    /// Refuse to apply anything, since that changes the repository's config
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl EnvDiff {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("env-diff")?;
+        if self.apply {
+            config.ensure_writable("env-diff", self.read_only)?;
+        }
+
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+        if git.resolve_ref(&trunk)?.is_none() {
+            return Err(GitError::Refused(tr!("status-no-trunk", trunk = trunk)));
+        }
+        if git.file_at(&trunk, drift::PATH)?.is_none() {
+            return Err(GitError::Refused(tr!("env-diff-no-recommendation", path = drift::PATH,
+                                             trunk = trunk)));
+        }
+        let blob = format!("{}:{}", trunk, drift::PATH);
+        let recommended = git.config_get_regexp_in_blob(&blob, drift::PATTERN)?;
+        let drifted = drift::compare(&recommended, &git.config_get_regexp(drift::PATTERN)?);
+        for drift in &drifted {
+            println!("{}", describe(drift));
+        }
+        if drifted.is_empty() {
+            eprintln!("{}", tr!("env-diff-clean", path = drift::PATH));
+            return Ok(());
+        }
+        if !self.apply {
+            return Err(GitError::Refused(tr!("env-diff-drifted", count = drifted.len(),
+                                             path = drift::PATH)));
+        }
+
+        let keys: Vec<String> = drifted.iter().map(|drift| drift.key.clone()).collect();
+        let result = drifted.iter()
+            .try_for_each(|drift| git.config_replace_all(&drift.key, &drift.recommended));
+        audit::record(&git, "env-diff", &keys, &result)?;
+        result?;
+
+        let remaining = drift::compare(&recommended, &git.config_get_regexp(drift::PATTERN)?);
+        eprintln!("{}", tr!("env-diff-applied", count = drifted.len() - remaining.len()));
+        match remaining.is_empty() {
+            true => Ok(()),
+            false => {
+                let keys: Vec<&str> = remaining.iter().map(|drift| drift.key.as_str()).collect();
+                Err(GitError::Refused(tr!("env-diff-set-elsewhere", keys = keys.join(", "))))
+            },
+        }
+    }
+}
+
+
+// Say how `drift` differs from what is recommended.
+fn describe(drift: &Drift) -> String {
+    match (drift.recommended.is_empty(), drift.actual.is_empty()) {
+        (_, true) => tr!("env-diff-missing", key = drift.key,
+                         recommended = drift.recommended.join(", ")),
+        (true, _) => tr!("env-diff-extra", key = drift.key, actual = drift.actual.join(", ")),
+        _ => tr!("env-diff-differs", key = drift.key, actual = drift.actual.join(", "),
+                 recommended = drift.recommended.join(", ")),
+    }
+}
@@ -0,0 +1,30 @@
+//! Check whether a pull request is open on the remote
+//!
+//! Exits with status 0 if the remote has a PR by that name (or branch), and 1 if it doesn't, so
+//! scripts can write `if git pr exists hotfix; then ...`. The remote is asked directly (see
+//! `Git::ls_remote_heads`), rather than fetched from, so the answer is current even if nothing has
+//! been fetched for a while.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{tr, GitError};
+
+
+#[derive(Args)]
+pub struct Exists {
+    /// The PR's name, or its branch
+    #[arg(value_name = "name")]
+    name: String,
+}
+
+impl Exists {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("exists")?;
+        // The name, and anything beneath it: its own PR branches, or the branch it names
+        let index = PrIndex::probe(&git, &config.remote.value, &format!("{}*", self.name))?;
+        match index.find(&self.name).is_empty() {
+            true => Err(GitError::Refused(tr!("pr-not-found", name = self.name))),
+            false => Ok(()),
+        }
+    }
+}
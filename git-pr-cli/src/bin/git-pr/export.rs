@@ -0,0 +1,87 @@
+//! Write a pull request out as a patch series, for review over email
+//!
+//! `git pr export <name>` runs `git format-patch` on the commits the PR adds to the remote's
+//! trunk, writing them as a numbered series into a directory named after the PR, or with
+//! `--mbox`, into a single `<name>.mbox` file. `--reroll-count` marks the series as a revision of
+//! an earlier one (`login-v2/`, with subjects like "[PATCH v2 1/3]"), and `--cover-letter` adds a
+//! patch zero to describe the series in. `--output` picks another directory, or file, to write to.
+//!
+//! The series can then be sent with `git send-email`, or attached wherever it is needed.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{tr, GitError};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+
+#[derive(Args)]
+pub struct Export {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Write a single mbox file instead of a directory of patches
+    #[arg(long)]
+    mbox: bool,
+
+    /// Mark the series as this revision of an earlier one
+    #[arg(long, value_name = "n", value_parser = clap::value_parser!(u32).range(1..))]
+    reroll_count: Option<u32>,
+
+    /// Start the series with a cover letter
+    #[arg(long)]
+    cover_letter: bool,
+
+    /// Where to write the series, instead of after the PR's name
+    #[arg(short, long, value_name = "path")]
+    output: Option<PathBuf>,
+}
+
+impl Export {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("export")?;
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        if git.resolve_ref(&trunk)?.is_none() {
+            return Err(GitError::Refused(tr!("status-no-trunk", trunk = trunk)));
+        }
+        let range = format!("{}..{}", trunk, pr.tip);
+        if git.commit_summaries(&range)?.is_empty() {
+            return Err(GitError::Refused(tr!("export-empty", branch = pr.branch, trunk = trunk)));
+        }
+
+        let mut options = vec![];
+        let mut name = pr.name.clone();
+        if let Some(n) = self.reroll_count {
+            options.push(format!("--reroll-count={}", n));
+            if n > 1 {
+                name.push_str(&format!("-v{}", n));
+            }
+        }
+        if self.cover_letter {
+            options.push("--cover-letter".to_string());
+        }
+
+        // git runs at the top of the worktree, but paths are given relative to where we are
+        let here = env::current_dir()?;
+        if self.mbox {
+            let path = here.join(self.output.unwrap_or_else(|| format!("{}.mbox", name).into()));
+            options.push("--stdout".to_string());
+            let series = git.format_patch(&range, &options)?;
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&path, series)?;
+            println!("{}", path.display());
+        } else {
+            let dir = here.join(self.output.unwrap_or_else(|| name.into()));
+            options.push(format!("--output-directory={}", dir.display()));
+            print!("{}", String::from_utf8_lossy(&git.format_patch(&range, &options)?));
+        }
+        Ok(())
+    }
+}
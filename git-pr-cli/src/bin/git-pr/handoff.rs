@@ -0,0 +1,106 @@
+//! Hand a pull request over to someone else
+//!
+//! When a PR's author leaves partway through, `git pr handoff <name> <user>` records a teammate as
+//! its new owner (see `libgitpr::owner`), so that everyone knows who to ask about it. The PR keeps
+//! its branch and all of its metadata, so nothing said about it so far is lost. If the new owner
+//! had claimed the PR to review it (see `git pr take`), that claim is released, since people don't
+//! review their own PRs.
+//!
+//! If `pr.webhookUrl` is set, a `handoff` event is sent to it once the new owner is published,
+//! with the PR's `name` and `branch`, and who it was handed `from` and `to`, and `by`. That needs
+//! git-pr built with the `webhook` feature.
+use crate::Shared;
+use clap::Args;
+use libgitpr::identity::{self, Identity};
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, claim, metadata, owner, tr, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Handoff {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Who the PR should belong to, as "Name <email>"
+    #[arg(value_name = "user")]
+    user: String,
+
+    /// Refuse to run, since this publishes metadata to the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Handoff {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("handoff")?;
+        config.ensure_writable("handoff", self.read_only)?;
+
+        let to = Identity::parse(&self.user)
+            .ok_or_else(|| GitError::Refused(tr!("handoff-bad-user", user = self.user)))?;
+        let to = identity::canonicalize(&git, &[to])?.remove(0).to_string();
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+
+        let mut from = String::new();
+        let mut released = false;
+        let mut applied = false;
+        let result = metadata::update(&git, remote, |git| {
+            let lines = metadata::lines(git, &pr.tip)?;
+            from = match owner::current(&lines) {
+                Some(owner) => owner,
+                None => git.author_of(&pr.tip)?,
+            };
+            if from == to {
+                return Err(GitError::Refused(tr!("handoff-already", branch = pr.branch,
+                                                 user = to)));
+            }
+            let at = claim::next_time(&lines, now);
+            let mut added = vec![owner::owner_line(&to, at)];
+            released = claim::current(&lines, now).is_some_and(|held| held.who == to);
+            if released {
+                added.push(claim::release_line(&to, at));
+            }
+            if !applied {
+                for line in added {
+                    git.append_note(metadata::NOTES_REF, &pr.tip, &metadata::format_line(&line))?;
+                }
+                applied = true;
+            }
+            Ok(())
+        });
+        audit::record(&git, "handoff", &[pr.branch.clone(), to.clone()], &result)?;
+        result?;
+
+        if released {
+            eprintln!("{}", tr!("handoff-released", branch = pr.branch, user = to));
+        }
+        eprintln!("{}", tr!("handoff-done", branch = pr.branch, from = from, to = to));
+        notify(&git, &[("name", &pr.name), ("branch", &pr.branch), ("from", &from), ("to", &to),
+                       ("by", &Identity::current(&git)?.to_string())])
+    }
+}
+
+
+// Tell pr.webhookUrl about the handoff, if it is set. The handoff has already been published, so a
+// failure here is only a warning.
+#[cfg(feature = "webhook")]
+fn notify(git: &libgitpr::Git, fields: &[(&str, &str)]) -> Result<(),GitError> {
+    use libgitpr::webhook;
+    if let Err(e) = webhook::notify(git, &webhook::event("handoff", fields)) {
+        eprintln!("{}", tr!("webhook-failed", error = e));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "webhook"))]
+fn notify(git: &libgitpr::Git, _fields: &[(&str, &str)]) -> Result<(),GitError> {
+    if git.config_get("pr.webhookUrl")?.is_some() {
+        eprintln!("{}", tr!("webhook-unsupported"));
+    }
+    Ok(())
+}
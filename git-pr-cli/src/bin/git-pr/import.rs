@@ -0,0 +1,93 @@
+//! Turn an emailed patch series into a pull request
+//!
+//! The inverse of `git pr export`: `git pr import <name> <series>` applies a series, from an mbox
+//! file or a directory of patches, to the remote's trunk with `git am`, and publishes the result as
+//! a new PR called `<name>`. A series' cover letter, which changes nothing, is skipped.
+//!
+//! The patches are applied in a temporary worktree, so nothing here is checked out or disturbed;
+//! if any patch fails to apply, nothing is created at all.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, tr, Git, GitError};
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+
+#[derive(Args)]
+pub struct Import {
+    /// What the PR is called, like "hotfix"
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// An mbox file, or a directory of patches like git format-patch writes
+    #[arg(value_name = "series")]
+    series: PathBuf,
+
+    /// Refuse to run, since this changes the repository and the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Import {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("import")?;
+        config.ensure_writable("import", self.read_only)?;
+
+        let patches = match fs::metadata(&self.series)?.is_dir() {
+            true => {
+                let mut patches = vec![];
+                for entry in fs::read_dir(&self.series)? {
+                    let path = entry?.path();
+                    if path.extension().is_some_and(|extension| extension == "patch") {
+                        patches.push(fs::canonicalize(path)?);
+                    }
+                }
+                patches.sort();
+                patches
+            }
+            false => vec![fs::canonicalize(&self.series)?],
+        };
+        if patches.is_empty() {
+            return Err(GitError::Refused(tr!("import-no-patches",
+                                             series = self.series.display())));
+        }
+
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+        let base = git.resolve_ref(&trunk)?
+            .ok_or_else(|| GitError::Refused(tr!("status-no-trunk", trunk = trunk)))?;
+
+        // Apply the series away from the user's own work, and clean up however that goes
+        let scratch = git.git_dir()?.join("git-pr").join(format!("import-{}", process::id()));
+        git.add_worktree(&scratch, &base)?;
+        let worktree = Git{
+            program: git.program.clone(), working_dir: Box::new(scratch.clone()),
+            git_dir: None, work_tree: None,
+        };
+        let applied = worktree.am(&patches).and_then(|_| worktree.rev_parse_head());
+        git.remove_worktree(&scratch)?;
+        let tip = match applied {
+            Ok(tip) if tip != base => tip,
+            Ok(_) => return Err(GitError::Refused(tr!("import-no-patches",
+                                                      series = self.series.display()))),
+            Err(_) => return Err(GitError::Refused(tr!("import-failed",
+                                                       series = self.series.display()))),
+        };
+
+        let branch = format!("{}/{}", self.name, tip);
+        if !PrIndex::probe(&git, remote, &branch)?.is_empty() {
+            return Err(GitError::Refused(tr!("create-duplicate", branch = branch,
+                                             remote = remote)));
+        }
+        let result = git.create_branch_at(&branch, &tip)
+            .and_then(|_| git.push_upstream(remote, &branch));
+        let message = tr!("import-created", branch = branch, remote = remote);
+        audit::record(&git, "import", &[branch], &result)?;
+        result?;
+        println!("{}", message);
+        Ok(())
+    }
+}
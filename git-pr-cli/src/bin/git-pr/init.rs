@@ -0,0 +1,90 @@
+//! Set up a repository for pull requests
+//!
+//! `git pr init` records the remote and trunk branch in the repository's config (`pr.remote` and
+//! `pr.trunk`, taken from `--remote` and `--trunk` or their defaults), so that everyone's later
+//! commands agree on them. It checks that the remote exists and can be reached, adding it first if
+//! `--url` is given, and checks whether it has the trunk branch. With `--create-trunk`, a missing
+//! trunk is created from the local branch of that name, or from HEAD, and pushed.
+//!
+//! Running it again is harmless: the config is rewritten with the same values, and a trunk which
+//! exists is left alone.
+use crate::Shared;
+use clap::Args;
+use libgitpr::{audit, tr, Git, GitError};
+
+
+#[derive(Args)]
+pub struct Init {
+    /// Add the remote, fetching from and pushing to this URL, if it doesn't exist
+    #[arg(long, value_name = "url")]
+    url: Option<String>,
+
+    /// Create the trunk branch, and push it, if the remote doesn't have one
+    #[arg(long)]
+    create_trunk: bool,
+
+    /// Refuse to run, since this changes the config and may push
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Init {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("init")?;
+        config.ensure_writable("init", self.read_only)?;
+        let remote = &config.remote.value;
+        let trunk = &config.trunk.value;
+
+        let url = match (git.config_get(&format!("remote.{}.url", remote))?, &self.url) {
+            (Some(url), _) => url,
+            (None, Some(url)) => {
+                git.add_remote(remote, url)?;
+                println!("{}", tr!("init-added-remote", remote = remote, url = url));
+                url.clone()
+            }
+            (None, None) => return Err(GitError::Refused(tr!("init-no-remote", remote = remote))),
+        };
+        let has_trunk = match git.ls_remote_heads(remote, trunk) {
+            Ok(heads) => !heads.is_empty(),
+            Err(GitError::Io(e)) => return Err(GitError::Io(e)),
+            Err(_) => {
+                return Err(GitError::Refused(tr!("init-unreachable", remote = remote, url = url)))
+            }
+        };
+
+        git.config_set("pr.remote", remote, false)?;
+        git.config_set("pr.trunk", trunk, false)?;
+        println!("{}", tr!("init-recorded", remote = remote, trunk = trunk));
+
+        if has_trunk {
+            println!("{}", tr!("init-trunk-found", remote = remote, trunk = trunk));
+            return Ok(());
+        }
+        if !self.create_trunk {
+            println!("{}", tr!("init-no-trunk", remote = remote, trunk = trunk));
+            return Ok(());
+        }
+
+        let result = create_trunk(&git, remote, trunk);
+        audit::record(&git, "init", &[format!("refs/heads/{}", trunk)], &result)?;
+        let commit = result?;
+        println!("{}", tr!("init-trunk-created", trunk = trunk, commit = git.abbreviate(&commit)?,
+                           remote = remote));
+        Ok(())
+    }
+}
+
+// Push the local trunk, creating it from HEAD if there isn't one, and return its commit.
+fn create_trunk(git: &Git, remote: &str, trunk: &str) -> Result<String,GitError> {
+    let commit = match git.resolve_ref(&format!("refs/heads/{}", trunk))? {
+        Some(commit) => commit,
+        None => {
+            let head = git.resolve_ref("HEAD")?
+                .ok_or_else(|| GitError::Refused(tr!("init-no-commits", trunk = trunk)))?;
+            git.create_branch_at(trunk, &head)?;
+            head
+        }
+    };
+    git.push_upstream(remote, trunk)?;
+    Ok(commit)
+}
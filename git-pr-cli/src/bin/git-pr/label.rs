@@ -0,0 +1,88 @@
+//! Put labels on a pull request, or take them off
+//!
+//! `git pr label <name> <label>...` labels the PR (see `libgitpr::label`), and `--remove` takes the
+//! labels off again. Labels are published as metadata, shown by `git pr list --format '{labels}'`
+//! and `git pr timeline`, and pick out the PRs `git pr batch` works on.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, claim, label, metadata, tr, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Label {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// The labels, like "cleanup"
+    #[arg(value_name = "label", required = true)]
+    labels: Vec<String>,
+
+    /// Take the labels off instead
+    #[arg(long)]
+    remove: bool,
+
+    /// Refuse to run, since this publishes metadata to the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Label {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("label")?;
+        config.ensure_writable("label", self.read_only)?;
+
+        if let Some(bad) = self.labels.iter().find(|l| !label::is_valid(l)) {
+            return Err(GitError::Refused(tr!("label-bad-label", label = bad)));
+        }
+        let mut labels = self.labels.clone();
+        labels.sort();
+        labels.dedup();
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+
+        // Only write what would change anything, deciding again on every attempt
+        let mut changed = vec![];
+        let mut applied = false;
+        let result = metadata::update(&git, remote, |git| {
+            let lines = metadata::lines(git, &pr.tip)?;
+            let current = label::current(&lines);
+            changed = labels.iter()
+                .filter(|l| current.contains(l) == self.remove)
+                .cloned()
+                .collect();
+            let at = claim::next_time(&lines, now);
+            if !applied {
+                for l in &changed {
+                    let line = match self.remove {
+                        true => label::unlabel_line(l, at),
+                        false => label::label_line(l, at),
+                    };
+                    git.append_note(metadata::NOTES_REF, &pr.tip, &metadata::format_line(&line))?;
+                }
+                applied = true;
+            }
+            Ok(())
+        });
+        let mut refs = vec![pr.branch.clone()];
+        refs.extend(changed.iter().cloned());
+        audit::record(&git, "label", &refs, &result)?;
+        result?;
+
+        for l in &labels {
+            let message = match (self.remove, changed.contains(l)) {
+                (false, true) => tr!("label-added", branch = pr.branch, label = l),
+                (false, false) => tr!("label-already", branch = pr.branch, label = l),
+                (true, true) => tr!("label-removed", branch = pr.branch, label = l),
+                (true, false) => tr!("label-not-there", branch = pr.branch, label = l),
+            };
+            eprintln!("{}", message);
+        }
+        Ok(())
+    }
+}
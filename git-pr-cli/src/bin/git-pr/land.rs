@@ -0,0 +1,67 @@
+//! Squash a pull request onto trunk, and publish the result
+//!
+//! Where `git pr merge` keeps a PR's commits and joins them to trunk with a merge commit, `git pr
+//! land` squashes them into a single commit on top of trunk, so trunk's history stays linear. The
+//! commit's message names the PR and lists the commits it was squashed from (see
+//! `libgitpr::merge::squash_message`), or follows the team's template if trunk has one (see
+//! `libgitpr::merge_template`), unless `git pr squash-preview --edit` has stored another.
+//!
+//! Otherwise it works like `git pr merge --delete`: the commit is made on the remote's trunk and
+//! pushed together with the deletion of the PR's branch, retrying if trunk moves in the meantime,
+//! and the local branch is deleted unless it is checked out.
+use crate::merge::push_onto_trunk;
+use crate::Shared;
+use clap::Args;
+use libgitpr::merge::{self, catch_up_trunk};
+use libgitpr::merge_template::{self, Kind};
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, tr, GitError};
+
+
+#[derive(Args)]
+pub struct Land {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Refuse to run, since this changes trunk and the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Land {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("land")?;
+        config.ensure_writable("land", self.read_only)?;
+
+        let remote = &config.remote.value;
+        let trunk = &config.trunk.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+
+        let stored = merge::stored_squash_message(&git, &pr.tip)?;
+        let result = push_onto_trunk(&git, &config, pr, true, shared.verbose, |base| {
+            let commits = git.commit_messages(&format!("{}..{}", base, pr.tip))?;
+            if commits.is_empty() {
+                return Err(GitError::Refused(tr!("land-nothing", branch = pr.branch,
+                                                 trunk = trunk)));
+            }
+            let message = match &stored {
+                Some(message) => message.clone(),
+                None => merge_template::compose(&git, pr, base, Kind::Squash)?,
+            };
+            merge::squash_commit(&git, trunk, base, pr, &message)
+        });
+        audit::record(&git, "land", &[pr.branch.clone(), trunk.clone()], &result)?;
+        let commit = result?;
+        merge::forget_squash_message(&git, &pr.tip)?;
+
+        catch_up_trunk(&git, trunk, &commit, remote)?;
+        if shared.verbose {
+            eprintln!("{}", tr!("abandon-deleted", branch = pr.branch, remote = remote));
+        }
+        eprintln!("{}", tr!("land-done", branch = pr.branch, trunk = trunk, remote = remote));
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+//! Display a list of currently active Pull Requests
+//!
+//! By "currently active", we mean "not yet deleted from the remote". With `--authors`, each PR is
+//! followed by the author of its most recent commit, after applying the repository's mailmap.
+//!
+//! `--porcelain`, `--json`, and `--format <template>` work as described in `libgitpr::render`; the
+//! fields are listed in `libgitpr::pull_request::FIELDS`. For example, `--format
+//! '{name}\t{author}\t{age}'`.
+//!
+//! Whoever has claimed a PR with `git pr take` is shown after it, so that two people don't end up
+//! reviewing the same PR.
+//!
+//! With `--assignees`, each PR is also followed by whoever it has been assigned to with `git pr
+//! assign`. `--assigned-to-me` lists only the PRs assigned to you, after applying the mailmap, so
+//! a reviewer can see what is waiting on them.
+//!
+//! With `--state merged` or `--state abandoned`, it lists PRs which have been closed instead, from
+//! the states recorded under `refs/pr-state/` (see `libgitpr::state`), even once their branches
+//! are gone. `--state open` leaves out PRs whose branches are still on the remote but which have
+//! been merged, and `--state all` lists everything, with a column saying which state each PR is in.
+//!
+//! `--query` lists only the PRs matching a query, like `'age>30d and label:infra'`, as described in
+//! `libgitpr::query`. A query which asks about `state:` also considers closed PRs, as `--state all`
+//! would, unless `--state` says otherwise.
+//!
+//! Branches pushed by other tools, such as Dependabot, are not listed unless the repository has
+//! opted in with `pr.allowConvention`.
+use crate::Shared;
+use clap::{Args, ValueEnum};
+use libgitpr::state::{self, State};
+use libgitpr::ci::Report;
+use libgitpr::query::{Facts, Field, Query};
+use libgitpr::identity::{self, Identity};
+use libgitpr::{assignment, claim, date, label, metadata, owner, parse, tr};
+use libgitpr::pull_request::{self, PrIndex, PullRequest};
+use libgitpr::render::{Output, Record, Renderer, Value};
+use libgitpr::{Git, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct List {
+    /// Show who wrote each PR's most recent commit
+    #[arg(long)]
+    authors: bool,
+
+    /// Show who each PR has been assigned to for review
+    #[arg(long)]
+    assignees: bool,
+
+    /// List only PRs assigned to you for review
+    #[arg(long)]
+    assigned_to_me: bool,
+
+    /// Print every column, in a stable form for scripts
+    #[arg(long, group = "output")]
+    porcelain: bool,
+
+    /// Print every field of each PR as a JSON object
+    #[arg(long, group = "output")]
+    json: bool,
+
+    /// Print each PR by filling in a template, like '{name}\t{age}'
+    #[arg(long, group = "output", value_name = "template")]
+    format: Option<String>,
+
+    /// List only PRs in this state, rather than every PR on the remote
+    #[arg(long, value_enum, value_name = "state")]
+    state: Option<Which>,
+
+    /// List only PRs matching a query, like 'age>30d and label:infra'
+    #[arg(long, value_name = "query")]
+    query: Option<String>,
+}
+
+/// Which PRs `--state` lists.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Which {
+    Open,
+    Merged,
+    Abandoned,
+    All,
+}
+
+impl Which {
+    fn includes(self, state: State) -> bool {
+        match self {
+            Which::Open => state == State::Open,
+            Which::Merged => state == State::Merged,
+            Which::Abandoned => state == State::Abandoned,
+            Which::All => true,
+        }
+    }
+}
+
+impl List {
+    // The output flags are exclusive, which clap has already checked.
+    fn output(&self) -> Output {
+        match (self.porcelain, self.json, &self.format) {
+            (true, _, _) => Output::Porcelain,
+            (_, true, _) => Output::Json,
+            (_, _, Some(template)) => Output::Template(template.clone()),
+            _ => Output::Pretty,
+        }
+    }
+
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("list")?;
+        let query = self.query.as_deref().map(Query::parse).transpose()?;
+        let asks = |field| query.as_ref().is_some_and(|query: &Query| query.uses(field));
+        let which = match self.state {
+            None if asks(Field::State) => Some(Which::All),
+            which => which,
+        };
+        let mut columns = vec!["name"];
+        if self.state == Some(Which::All) {
+            columns.push("state");
+        }
+        if self.authors {
+            columns.push("author");
+        }
+        if self.assignees {
+            columns.push("assigned");
+        }
+        columns.push("claimed");
+        let renderer = self.output()
+            .renderer(&columns, pull_request::FIELDS, &config.date_format.value)?;
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let claims = renderer.shows("claimed") || renderer.shows("claimed_until");
+        let assignments = renderer.shows("assigned") || self.assigned_to_me;
+        if claims || assignments || renderer.shows("owner") || renderer.shows("labels")
+            || asks(Field::Label) || asks(Field::Ci) {
+            metadata::sync(&git, remote)?;
+        }
+        if which.is_some() || renderer.shows("state") {
+            state::fetch(&git, remote)?;
+        }
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        if asks(Field::Path) && git.resolve_ref(&trunk)?.is_none() {
+            return Err(GitError::Refused(tr!("status-no-trunk", trunk = trunk)));
+        }
+
+        let me = match self.assigned_to_me {
+            true => {
+                let me = identity::canonicalize(&git, &[Identity::current(&git)?])?.remove(0);
+                Some(me.to_string())
+            },
+            false => None,
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        let index = PrIndex::load(&git, remote)?;
+        let records = match which {
+            Some(_) => state::all(&git)?,
+            None => vec![],
+        };
+        for pr in index.iter() {
+            let state = records.iter().find(|record| record.branch == pr.branch)
+                .map_or(State::Open, |record| record.state);
+            if !which.is_none_or(|which| which.includes(state)) {
+                continue;
+            }
+            if let Some(query) = &query {
+                if !query.matches(&facts(&git, pr, state, query, &trunk, now)?) {
+                    continue;
+                }
+            }
+            if let Some(me) = &me {
+                if !assigned_to(&git, pr, me)? {
+                    continue;
+                }
+            }
+            println!("{}", renderer.render(&record(&git, pr, renderer.as_ref(), now)?));
+        }
+
+        // Closed PRs whose branches are gone from the remote
+        for closed in &records {
+            let on_remote = index.iter().any(|pr| pr.branch == closed.branch);
+            let wanted = which.is_some_and(|which| which.includes(closed.state));
+            let name = match parse::pr_branch(&closed.branch) {
+                Some((name, _)) if wanted && !on_remote && closed.state != State::Open => name,
+                _ => continue,
+            };
+            let pr = PullRequest{
+                name: name.to_string(), branch: closed.branch.clone(), tip: closed.tip.clone(),
+            };
+            // Without the tip's commits, there is nothing to look up beyond the record itself
+            let present = git.resolve_ref(&pr.tip)?.is_some();
+            if let Some(query) = &query {
+                let needs_tip = [Field::Age, Field::Author, Field::Label, Field::Path, Field::Ci]
+                    .iter().any(|field| query.uses(*field));
+                let matched = match (present, needs_tip) {
+                    (true, _) => {
+                        query.matches(&facts(&git, &pr, closed.state, query, &trunk, now)?)
+                    },
+                    (false, false) => {
+                        query.matches(&Facts{ state: closed.state, ..Facts::default() })
+                    },
+                    (false, true) => false,
+                };
+                if !matched {
+                    continue;
+                }
+            }
+            if let Some(me) = &me {
+                if !present || !assigned_to(&git, &pr, me)? {
+                    continue;
+                }
+            }
+            let record = match present {
+                true => record(&git, &pr, renderer.as_ref(), now)?,
+                false => Record::new()
+                    .with("name", pr.name.as_str())
+                    .with("branch", pr.branch.as_str())
+                    .with("tip", pr.tip.as_str())
+                    .with("short", pr.tip.chars().take(7).collect::<String>())
+                    .with("state", closed.state.to_string()),
+            };
+            println!("{}", renderer.render(&record));
+        }
+        Ok(())
+    }
+}
+
+
+/// Describe `pr` for `renderer` (see `libgitpr::pull_request::FIELDS`).
+///
+/// Claims, assignments and owners come from metadata, which should have been synced first if they
+/// are shown, and states come from `refs/pr-state/`, which should have been fetched.
+pub fn record(git: &Git, pr: &PullRequest, renderer: &dyn Renderer, now: i64)
+    -> Result<Record,GitError> {
+    let mut record = Record::new()
+        .with("name", pr.name.as_str())
+        .with("branch", pr.branch.as_str())
+        .with("tip", pr.tip.as_str())
+        .with("short", pr.tip.chars().take(7).collect::<String>());
+
+    // The rest cost a git call per PR, so only look them up if they will be shown
+    if renderer.shows("author") {
+        record = record.with("author", git.author_of(&pr.tip)?);
+    }
+    if renderer.shows("age") || renderer.shows("date") {
+        let time = git.commit_time(&pr.tip)?;
+        record = record.with("age", date::relative(now - time))
+            .with("date", Value::Time(time));
+    }
+    if renderer.shows("claimed") || renderer.shows("claimed_until") {
+        let claim = claim::current(&metadata::lines(git, &pr.tip)?, now);
+        record = match claim {
+            Some(claim) => record.with("claimed", claim.who)
+                .with("claimed_until", Value::Time(claim.until)),
+            None => record.with("claimed", Value::Absent)
+                .with("claimed_until", Value::Absent),
+        };
+    }
+    if renderer.shows("owner") {
+        let owner = match owner::current(&metadata::lines(git, &pr.tip)?) {
+            Some(owner) => owner,
+            None => git.author_of(&pr.tip)?,
+        };
+        record = record.with("owner", owner);
+    }
+    if renderer.shows("labels") {
+        record = record.with("labels", label::current(&metadata::lines(git, &pr.tip)?).join(","));
+    }
+    if renderer.shows("assigned") {
+        let assigned = assignment::current(&metadata::lines(git, &pr.tip)?);
+        record = match assigned.is_empty() {
+            true => record.with("assigned", Value::Absent),
+            false => record.with("assigned", assigned),
+        };
+    }
+    if renderer.shows("state") {
+        let state = state::current(git, &pr.branch)?.map_or(State::Open, |record| record.state);
+        record = record.with("state", state.to_string());
+    }
+    Ok(record)
+}
+
+// What `query` needs to know about `pr`, which is in `state`, and whose changes are compared with
+// `trunk`. Labels and CI come from metadata, which should have been synced first.
+fn facts(git: &Git, pr: &PullRequest, state: State, query: &Query, trunk: &str, now: i64)
+    -> Result<Facts,GitError> {
+    let mut facts = Facts{ state, ..Facts::default() };
+    if query.uses(Field::Age) {
+        facts.age = now - git.commit_time(&pr.tip)?;
+    }
+    if query.uses(Field::Author) {
+        facts.author = git.author_of(&pr.tip)?;
+    }
+    if query.uses(Field::Label) || query.uses(Field::Ci) {
+        let lines = metadata::lines(git, &pr.tip)?;
+        facts.labels = label::current(&lines);
+        facts.ci = Report::from_lines(&lines);
+    }
+    if query.uses(Field::Path) {
+        facts.paths = git.changed_paths(trunk, &pr.tip)?;
+    }
+    Ok(facts)
+}
+
+// Whether `pr` is assigned to `me`, as `Name <email>`. Metadata should have been synced first.
+fn assigned_to(git: &Git, pr: &PullRequest, me: &str) -> Result<bool,GitError> {
+    Ok(assignment::current(&metadata::lines(git, &pr.tip)?).iter().any(|who| who == me))
+}
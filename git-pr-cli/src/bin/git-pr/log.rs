@@ -0,0 +1,36 @@
+//! List the commits a pull request adds
+//!
+//! Only the commits on the PR which are not on trunk (on the remote) are shown, one per line,
+//! newest first, through the pager (see `pager`). This is `git log --oneline <trunk>..<branch>`
+//! without having to look up the branch; `git pr show` includes the diff as well.
+use crate::pager::Pager;
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{tr, GitError};
+
+
+#[derive(Args)]
+pub struct Log {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+}
+
+impl Log {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("log")?;
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        if git.resolve_ref(&trunk)?.is_none() {
+            return Err(GitError::Refused(tr!("status-no-trunk", trunk = trunk)));
+        }
+
+        let pager = Pager::new(&git)?;
+        let log = git.log(&format!("{}..{}", trunk, pr.tip), &[pager.color_flag(), "--oneline"])?;
+        pager.show(&log)
+    }
+}
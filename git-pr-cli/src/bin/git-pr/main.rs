@@ -0,0 +1,543 @@
+//! The `git pr` command
+//!
+//! Git runs any program called `git-<name>` on the PATH as `git <name>`, which is how this program
+//! becomes `git pr`. The everyday commands (`create`, `list`, `checkout`, `merge`, and the others
+//! listed by `git pr --help`) are built in. Any other command is run from a program called
+//! `git-pr-<command>`, looked for next to this program first and then on the PATH, so `git pr
+//! compare` runs `git-pr-compare`. Aliases defined with `git pr alias` are expanded along the way.
+//! `git pr completions` prints a script for tab-completing all of these in your shell.
+//!
+//! Every command accepts `--remote <name>` and `--trunk <branch>`, which override `pr.remote` and
+//! `pr.trunk` for one run, and `--verbose`. Other programs receive the first two as git config
+//! (through `GIT_CONFIG_COUNT`), so for them the flags must come before the command's name.
+//!
+//! So that a team can add commands of its own without reimplementing git-pr's settings, other
+//! programs run inside a repository also find the settings in effect in the environment:
+//! `GIT_PR_REMOTE` and `GIT_PR_TRUNK` hold the remote and trunk, after any overrides, and
+//! `GIT_PR_ROOT` holds the top of the working tree (or the git directory, in a bare repository).
+//!
+//! `git pr` exits as described in `libgitpr::exit`: 1 when git-pr refuses to do something, 128 when
+//! git fails, and 129 for a bad command line. Other programs' statuses are passed along.
+mod abandon;
+mod amend;
+mod approve;
+mod archive;
+mod assign;
+mod attach;
+mod attachments;
+mod away;
+mod batch;
+mod checkout;
+mod ci;
+mod clean;
+mod comment;
+mod completions;
+mod conflicts;
+mod create;
+mod describe;
+mod describe_request;
+mod digest;
+mod doctor;
+mod env_diff;
+mod exists;
+mod export;
+mod handoff;
+mod import;
+mod init;
+mod label;
+mod land;
+mod list;
+mod log;
+mod merge;
+mod orphans;
+mod pager;
+mod rename;
+mod reopen;
+mod reviewers;
+mod reviews;
+mod search;
+mod send;
+mod serve;
+mod show;
+mod snapshot;
+mod squash_preview;
+mod stats;
+mod status;
+mod sync;
+mod take;
+mod timeline;
+mod ui;
+mod unassign;
+mod update;
+mod watch;
+mod whoami;
+
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use libgitpr::config::{Config, Setting, Source};
+use libgitpr::{alias, exit, fuzzy, profile, tr, Git, GitError};
+use std::collections::BTreeSet;
+use std::env::{self, args, consts::EXE_SUFFIX};
+use std::iter::once;
+use std::path::PathBuf;
+use std::process::Command;
+
+
+#[derive(Parser)]
+#[command(name = "git-pr", bin_name = "git pr", version, about = "Pull requests without a forge",
+          subcommand_required = true, arg_required_else_help = true)]
+struct Cli {
+    #[command(flatten)]
+    shared: Shared,
+
+    #[command(subcommand)]
+    command: Builtin,
+}
+
+#[derive(Subcommand)]
+enum Builtin {
+    /// Record the remote and trunk for this repository, and check that they exist
+    Init(init::Init),
+
+    /// Check that git, this repository, and the remote are set up for git-pr
+    Doctor(doctor::Doctor),
+
+    /// Compare this clone's pr.* settings with those the team recommends in trunk
+    EnvDiff(env_diff::EnvDiff),
+
+    /// Start a PR from the current commit
+    Create(create::Create),
+
+    /// Publish the current commit as a new revision of the PR checked out
+    Amend(amend::Amend),
+
+    /// List the PRs on the remote
+    List(list::List),
+
+    /// Find PRs by name, author, or the subjects of their commits
+    Search(search::Search),
+
+    /// Fetch, fast-forward trunk, and report what changed on the remote
+    Sync(sync::Sync),
+
+    /// Poll the remote, reporting PRs as they are opened, updated, and closed
+    Watch(watch::Watch),
+
+    /// Summarize the current PR: how it compares with trunk, and whether it is pushed and merged
+    Status(status::Status),
+
+    /// Count open PRs by author, and report how quickly PRs are merged
+    Stats(stats::Stats),
+
+    /// Summarize the last day's or week's PR activity, ready to be mailed
+    Digest(digest::Digest),
+
+    /// Show the identity git-pr attributes your actions to, and where it came from
+    Whoami(whoami::Whoami),
+
+    /// Claim a PR, so that others know you are reviewing it or carrying it on
+    Take(take::Take),
+
+    /// Hand a PR over to someone else, as its new owner
+    Handoff(handoff::Handoff),
+
+    /// Ask people to review a PR, or share reviews out with --auto
+    Assign(assign::Assign),
+
+    /// Take reviewers off a PR
+    Unassign(unassign::Unassign),
+
+    /// List the team's reviewers, with their assignments and who is away
+    Reviewers(reviewers::Reviewers),
+
+    /// Say you are away, so that PRs are not assigned to you
+    Away(away::Away),
+
+    /// Put labels on a PR, or take them off
+    Label(label::Label),
+
+    /// Approve a PR as it stands
+    Approve(approve::Approve),
+
+    /// List who has approved which revision of each PR
+    Reviews(reviews::Reviews),
+
+    /// Check whether a PR is open on the remote
+    Exists(exists::Exists),
+
+    /// Switch to a PR's branch, tracking it if it is new here
+    Checkout(checkout::Checkout),
+
+    /// Show a PR's commits and what it changes
+    Show(show::Show),
+
+    /// List the commits a PR adds to trunk
+    Log(log::Log),
+
+    /// Check whether a PR merges cleanly into trunk, and list the paths which conflict
+    Conflicts(conflicts::Conflicts),
+
+    /// Write a PR out as a patch series, for review over email
+    Export(export::Export),
+
+    /// Create a PR from a patch series, like one received by email
+    Import(import::Import),
+
+    /// Browse PRs in the terminal, and check out, merge, or abandon them
+    Ui(ui::Ui),
+
+    /// Show PRs in a web browser, read-only
+    Serve(serve::Serve),
+
+    /// Mail a PR's commits as a patch series, with git send-email
+    Send(send::Send),
+
+    /// Summarize a PR for an upstream maintainer, as git request-pull does
+    DescribeRequest(describe_request::DescribeRequest),
+
+    /// Show what has happened to a PR: its commits, rebases, approvals, and comments
+    Timeline(timeline::Timeline),
+
+    /// Set the description of a PR, which git pr show prints
+    Describe(describe::Describe),
+
+    /// Attach files, like screenshots or logs, to a PR
+    Attach(attach::Attach),
+
+    /// Comment on a PR, encrypting the comment if the repository says to
+    Comment(comment::Comment),
+
+    /// Record what CI made of a PR: whether its build passed, and links to its artifacts
+    Ci(ci::Ci),
+
+    /// List the files attached to a PR, or save them
+    Attachments(attachments::Attachments),
+
+    /// Rebase the current PR onto trunk, and push it
+    Update(update::Update),
+
+    /// Merge a PR into trunk, and push trunk
+    Merge(merge::Merge),
+
+    /// Squash a PR into a single commit on trunk, push trunk, and delete the PR
+    Land(land::Land),
+
+    /// Show the commit git pr land would make for a PR, and optionally edit its message
+    SquashPreview(squash_preview::SquashPreview),
+
+    /// Give a PR a new name
+    Rename(rename::Rename),
+
+    /// Delete local branches which have been merged into trunk
+    Clean(clean::Clean),
+
+    /// List local PR branches which are no longer on the remote
+    Orphans(orphans::Orphans),
+
+    /// Withdraw a PR, deleting its branch from the remote
+    Abandon(abandon::Abandon),
+
+    /// Tag a PR so that it can be restored later, then delete it from the remote
+    Archive(archive::Archive),
+
+    /// Publish an abandoned, archived, or merged PR again
+    Reopen(reopen::Reopen),
+
+    /// Merge, abandon, or rebase every PR with a label
+    Batch(batch::Batch),
+
+    /// Save the remote's whole review state to a file, or restore it from one
+    Snapshot(snapshot::Snapshot),
+
+    /// Print a script which completes git pr commands in your shell
+    Completions(completions::Completions),
+
+    #[command(name = "__complete-pr-names", hide = true)]
+    CompletePrNames(completions::CompletePrNames),
+
+    #[command(external_subcommand)]
+    External(Vec<String>),
+}
+
+
+/// Flags which every command accepts.
+#[derive(Args)]
+pub struct Shared {
+    /// Publish and look for PRs on this remote, instead of pr.remote
+    #[arg(long, global = true, value_name = "name")]
+    pub remote: Option<String>,
+
+    /// Merge PRs into this branch, instead of pr.trunk
+    #[arg(long, global = true, value_name = "branch")]
+    pub trunk: Option<String>,
+
+    /// Use this profile's settings, instead of pr.profile's
+    #[arg(long, global = true, value_name = "name")]
+    pub profile: Option<String>,
+
+    /// Say which settings are in use, and what is being changed
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+}
+
+impl Shared {
+    /// Find the repository, check that the current user may run `command`, and load the config,
+    /// with any overrides from the command line.
+    pub fn open(&self, command: &str) -> Result<(Git, Config),GitError> {
+        let git = Git::discover()?;
+        libgitpr::policy::enforce(&git, command)?;
+        let config = self.config(&git)?;
+        Ok((git, config))
+    }
+
+    // Load `git`'s config, with any overrides from the command line.
+    fn config(&self, git: &Git) -> Result<Config,GitError> {
+        let mut config = Config::load(git)?;
+        if let Some(remote) = &self.remote {
+            config.remote = Setting{
+                value: remote.clone(), source: Source::CommandLine("--remote".into())
+            };
+        }
+        if let Some(trunk) = &self.trunk {
+            config.trunk = Setting{
+                value: trunk.clone(), source: Source::CommandLine("--trunk".into())
+            };
+        }
+        if self.verbose {
+            eprintln!("{}", tr!("verbose-settings",
+                                remote = config.remote.value, remote_source = config.remote.source,
+                                trunk = config.trunk.value, trunk_source = config.trunk.source));
+        }
+        Ok(config)
+    }
+
+    // Pass `--remote` and `--trunk` on to another program, as git config.
+    fn pass_on(&self, program: &mut Command) {
+        let mut count = env::var("GIT_CONFIG_COUNT").ok()
+            .and_then(|count| count.parse::<usize>().ok())
+            .unwrap_or(0);
+        for (key, value) in [("pr.remote", &self.remote), ("pr.trunk", &self.trunk)] {
+            if let Some(value) = value {
+                program.env(format!("GIT_CONFIG_KEY_{}", count), key)
+                    .env(format!("GIT_CONFIG_VALUE_{}", count), value)
+                    .env("GIT_CONFIG_COUNT", (count + 1).to_string());
+                count += 1;
+            }
+        }
+    }
+}
+
+
+// Commands for git-pr's developers, which work but aren't offered to everyone else.
+const HIDDEN: &[&str] = &["bench"];
+
+// The commands built into this program, except the hidden ones.
+fn builtins() -> Vec<String> {
+    Cli::command().get_subcommands().filter(|command| !command.is_hide_set())
+        .map(|command| command.get_name().to_string()).collect()
+}
+
+// The directories a command might be found in, in the order they are searched.
+fn search_path() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    if let Some(dir) = env::current_exe().ok().and_then(|exe| exe.parent().map(PathBuf::from)) {
+        dirs.push(dir);
+    }
+    if let Some(path) = env::var_os("PATH") {
+        dirs.extend(env::split_paths(&path));
+    }
+    dirs
+}
+
+// Find the program behind a git-pr command, if there is one.
+fn locate(command: &str) -> Option<PathBuf> {
+    // Anything else could be used to run programs which aren't git-pr commands
+    if command.is_empty() || !command.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+    let program = format!("git-pr-{}{}", command, EXE_SUFFIX);
+    search_path().into_iter().map(|dir| dir.join(&program)).find(|path| path.is_file())
+}
+
+// Every git-pr command we can find in a program of its own, except the hidden ones. Programs from
+// older versions of git-pr, which are now built in, are passed over.
+fn commands() -> BTreeSet<String> {
+    let builtins = builtins();
+    let mut commands = BTreeSet::new();
+    for dir in search_path() {
+        for entry in dir.read_dir().into_iter().flatten().flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let command = name.strip_prefix("git-pr-")
+                .and_then(|command| command.strip_suffix(EXE_SUFFIX));
+            // Skip anything which isn't a program, like the build's dependency files
+            let command = command.filter(|command| !HIDDEN.contains(command))
+                .filter(|command| !builtins.iter().any(|builtin| builtin == command));
+            if let Some(command) = command.filter(|command| locate(command).is_some()) {
+                commands.insert(command.to_string());
+            }
+        }
+    }
+    commands
+}
+
+// Where the command's name is, after any shared flags that come before it.
+fn command_position(argv: &[String]) -> usize {
+    let mut position = 0;
+    while let Some(arg) = argv.get(position) {
+        match arg.as_str() {
+            "--remote" | "--trunk" | "--profile" => position += 2,
+            "-v" | "--verbose" => position += 1,
+            _ if ["--remote=", "--trunk=", "--profile="].iter()
+                .any(|flag| arg.starts_with(flag)) => position += 1,
+            _ => break,
+        }
+    }
+    position.min(argv.len())
+}
+
+// Run a command which lives in a program of its own.
+fn external(argv: &[String], shared: &Shared, aliases: Vec<String>) -> Result<i32,GitError> {
+    let command = argv[0].as_str();
+    let program = match locate(command) {
+        Some(program) => program,
+        None => {
+            eprintln!("{}", tr!("not-a-command", command = command));
+
+            let known: Vec<String> = builtins().into_iter().chain(commands()).chain(aliases)
+                .collect();
+            let known: Vec<&str> = known.iter().map(|name| name.as_str()).collect();
+            let suggestions = fuzzy::closest(command, &known);
+            match suggestions.len() {
+                0 => {},
+                1 => eprintln!("\n{}", tr!("most-similar-command")),
+                _ => eprintln!("\n{}", tr!("most-similar-commands")),
+            }
+            for suggestion in suggestions {
+                eprintln!("\t{}", suggestion);
+            }
+            return Ok(exit::USAGE)
+        }
+    };
+
+    let mut program = Command::new(program);
+    program.args(&argv[1..]);
+    shared.pass_on(&mut program);
+    // Outside a repository, there are no settings to pass on
+    if let Ok(git) = Git::discover() {
+        let config = shared.config(&git)?;
+        program.env("GIT_PR_REMOTE", &config.remote.value)
+            .env("GIT_PR_TRUNK", &config.trunk.value)
+            .env("GIT_PR_ROOT", git.working_dir.as_ref().as_ref());
+    }
+    Ok(program.status()?.code().unwrap_or(exit::FATAL))
+}
+
+fn run(mut argv: Vec<String>) -> Result<i32,GitError> {
+    // Aliases may be defined globally, so we look for them even outside of a repository
+    let git = Git::discover().unwrap_or_default();
+    let aliases = alias::load(&git)?;
+    let start = command_position(&argv);
+    let builtins = builtins();
+    let expanded = alias::expand(&argv[start..], &aliases, |name| {
+        builtins.iter().any(|builtin| builtin == name) || locate(name).is_some()
+    })?;
+    argv.truncate(start);
+    argv.extend(expanded);
+
+    // Only list the other programs when someone might be looking for a command
+    let mut cli = Cli::command();
+    if matches!(argv.get(start).map(|arg| arg.as_str()), None | Some("-h" | "--help" | "help")) {
+        let mut more = tr!("other-commands");
+        for command in commands() {
+            more.push_str(&format!("\n  {}", command));
+        }
+        cli = cli.after_help(more);
+    }
+    let parsed = cli.try_get_matches_from(once("git-pr".to_string()).chain(argv))
+        .and_then(|matches| Cli::from_arg_matches(&matches));
+    let cli = match parsed {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            return Ok(match e.use_stderr() {
+                true => exit::USAGE,
+                false => exit::SUCCESS,
+            })
+        }
+    };
+
+    // Every git command from here on, and every program we start, should see the profile
+    if let Some(name) = &cli.shared.profile {
+        profile::select(name);
+    }
+
+    match cli.command {
+        Builtin::Init(init) => init.run(&cli.shared)?,
+        Builtin::Doctor(doctor) => doctor.run(&cli.shared)?,
+        Builtin::EnvDiff(env_diff) => env_diff.run(&cli.shared)?,
+        Builtin::Create(create) => create.run(&cli.shared)?,
+        Builtin::Amend(amend) => amend.run(&cli.shared)?,
+        Builtin::List(list) => list.run(&cli.shared)?,
+        Builtin::Search(search) => search.run(&cli.shared)?,
+        Builtin::Sync(sync) => sync.run(&cli.shared)?,
+        Builtin::Watch(watch) => watch.run(&cli.shared)?,
+        Builtin::Status(status) => status.run(&cli.shared)?,
+        Builtin::Stats(stats) => stats.run(&cli.shared)?,
+        Builtin::Digest(digest) => digest.run(&cli.shared)?,
+        Builtin::Whoami(whoami) => whoami.run(&cli.shared)?,
+        Builtin::Take(take) => take.run(&cli.shared)?,
+        Builtin::Handoff(handoff) => handoff.run(&cli.shared)?,
+        Builtin::Assign(assign) => assign.run(&cli.shared)?,
+        Builtin::Unassign(unassign) => unassign.run(&cli.shared)?,
+        Builtin::Reviewers(reviewers) => reviewers.run(&cli.shared)?,
+        Builtin::Away(away) => away.run(&cli.shared)?,
+        Builtin::Label(label) => label.run(&cli.shared)?,
+        Builtin::Approve(approve) => approve.run(&cli.shared)?,
+        Builtin::Reviews(reviews) => reviews.run(&cli.shared)?,
+        Builtin::Exists(exists) => exists.run(&cli.shared)?,
+        Builtin::Checkout(checkout) => checkout.run(&cli.shared)?,
+        Builtin::Show(show) => show.run(&cli.shared)?,
+        Builtin::Log(log) => log.run(&cli.shared)?,
+        Builtin::Conflicts(conflicts) => conflicts.run(&cli.shared)?,
+        Builtin::Export(export) => export.run(&cli.shared)?,
+        Builtin::Import(import) => import.run(&cli.shared)?,
+        Builtin::Ui(ui) => ui.run(&cli.shared)?,
+        Builtin::Serve(serve) => serve.run(&cli.shared)?,
+        Builtin::Send(send) => send.run(&cli.shared)?,
+        Builtin::DescribeRequest(describe) => describe.run(&cli.shared)?,
+        Builtin::Timeline(timeline) => timeline.run(&cli.shared)?,
+        Builtin::Describe(describe) => describe.run(&cli.shared)?,
+        Builtin::Attach(attach) => attach.run(&cli.shared)?,
+        Builtin::Comment(comment) => comment.run(&cli.shared)?,
+        Builtin::Ci(ci) => ci.run(&cli.shared)?,
+        Builtin::Attachments(attachments) => attachments.run(&cli.shared)?,
+        Builtin::Update(update) => update.run(&cli.shared)?,
+        Builtin::Merge(merge) => merge.run(&cli.shared)?,
+        Builtin::Land(land) => land.run(&cli.shared)?,
+        Builtin::SquashPreview(preview) => preview.run(&cli.shared)?,
+        Builtin::Rename(rename) => rename.run(&cli.shared)?,
+        Builtin::Clean(clean) => clean.run(&cli.shared)?,
+        Builtin::Orphans(orphans) => orphans.run(&cli.shared)?,
+        Builtin::Abandon(abandon) => abandon.run(&cli.shared)?,
+        Builtin::Archive(archive) => archive.run(&cli.shared)?,
+        Builtin::Reopen(reopen) => reopen.run(&cli.shared)?,
+        Builtin::Batch(batch) => batch.run(&cli.shared)?,
+        Builtin::Snapshot(snapshot) => snapshot.run(&cli.shared)?,
+        Builtin::Completions(completions) => completions.run(&cli.shared)?,
+        Builtin::CompletePrNames(names) => names.run(&cli.shared)?,
+        Builtin::External(argv) => {
+            return external(&argv, &cli.shared, aliases.into_keys().collect())
+        }
+    }
+    Ok(exit::SUCCESS)
+}
+
+fn main() {
+    let argv: Vec<String> = args().skip(1).collect();
+    std::process::exit(match run(argv) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+            e.exit_code()
+        }
+    })
+}
@@ -0,0 +1,188 @@
+//! Merge a pull request into trunk, and publish the result
+//!
+//! The PR is merged into the remote's trunk, as of the last fetch (see `libgitpr::merge`), and the
+//! result is pushed. As with `git merge`, a PR which already contains all of trunk is
+//! fast-forwarded unless `--no-ff` is given, and `--ff-only` refuses to make a merge commit at all.
+//! Once the push succeeds, the local trunk branch is brought up to date with it. The merge commit's
+//! message follows the team's template, if trunk has one (see `libgitpr::merge_template`).
+//!
+//! The push only goes through if trunk on the remote is still where it was when we fetched. If
+//! someone else pushed to trunk in the meantime, we fetch again, merge again on top of their work,
+//! and retry, up to `pr.mergeRetries` times (3 unless configured).
+//!
+//! If `pr.requiredCheck` names any CI checks, the merge is refused unless each of them has passed
+//! on the PR's tip, as reported by `git pr ci set --check`. Likewise, if `pr.minApprovals` is set,
+//! the merge is refused until that many people other than the PR's author have approved its tip
+//! with `git pr approve`. With `pr.signMetadata` set, only signed approvals and CI reports count,
+//! and only if git trusts the key of whoever made them (see `libgitpr::approval` and
+//! `libgitpr::ci`).
+//!
+//! The PR is recorded as merged (see `libgitpr::state`) in the same atomic push which updates
+//! trunk.
+//!
+//! With `--delete`, the PR's branch is deleted locally and from the remote. The local branch is
+//! only deleted once the push has gone through, and is left where it is if it is checked out or has
+//! commits which weren't merged. Trunk and the deletion are pushed together, atomically, so the
+//! remote never has the PR merged but not deleted, or deleted but not merged.
+use crate::Shared;
+use clap::Args;
+use libgitpr::ci::{self, Report};
+use libgitpr::config::Config;
+use libgitpr::merge::{self, FastForward};
+use libgitpr::merge_template::{self, Kind};
+use libgitpr::pull_request::{PrIndex, PullRequest};
+use libgitpr::{approval, audit, metadata, signing, tr, Git, GitError};
+
+
+#[derive(Args)]
+pub struct Merge {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Refuse to merge unless trunk can be fast-forwarded to the PR
+    #[arg(long, conflicts_with = "no_ff")]
+    ff_only: bool,
+
+    /// Make a merge commit even if trunk could be fast-forwarded
+    #[arg(long)]
+    no_ff: bool,
+
+    /// Delete the PR's branch, locally and from the remote, once it is merged
+    #[arg(long)]
+    delete: bool,
+
+    /// Refuse to run, since this changes trunk and the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Merge {
+    fn fast_forward(&self) -> FastForward {
+        match (self.ff_only, self.no_ff) {
+            (true, _) => FastForward::Only,
+            (_, true) => FastForward::Never,
+            _ => FastForward::Allowed,
+        }
+    }
+
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("merge")?;
+        config.ensure_writable("merge", self.read_only)?;
+
+        let remote = &config.remote.value;
+        let trunk = &config.trunk.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+
+        let result = merge_pr(&git, &config, pr, self.fast_forward(), self.delete,
+                              shared.verbose);
+        audit::record(&git, "merge", &[pr.branch.clone(), trunk.clone()], &result)?;
+        let commit = result?;
+
+        merge::catch_up_trunk(&git, trunk, &commit, remote)?;
+        if self.delete && shared.verbose {
+            eprintln!("{}", tr!("abandon-deleted", branch = pr.branch, remote = remote));
+        }
+        eprintln!("{}", tr!("merge-done", branch = pr.branch, trunk = trunk, remote = remote));
+        Ok(())
+    }
+}
+
+
+/// Merge `pr` into the remote's trunk, with the team's message template, and push trunk (see
+/// [`push_onto_trunk`]), returning the new trunk commit.
+pub fn merge_pr(git: &Git, config: &Config, pr: &PullRequest, ff: FastForward, delete: bool,
+                verbose: bool) -> Result<String,GitError> {
+    let trunk = &config.trunk.value;
+    push_onto_trunk(git, config, pr, delete, verbose, |base| {
+        let message = merge_template::compose(git, pr, base, Kind::Merge)?;
+        merge::merge_commit(git, trunk, base, pr, &message, ff)
+    })
+}
+
+/// Push the commit `make` builds on top of the remote's trunk to trunk, deleting the PR's branch
+/// too if `delete` is set, and return it (see `libgitpr::merge::push_onto_trunk`).
+///
+/// Nothing is made unless the checks `pr.requiredCheck` names have passed, and the tip has the
+/// approvals `pr.minApprovals` asks for.
+pub fn push_onto_trunk<F>(git: &Git, config: &Config, pr: &PullRequest, delete: bool,
+                          verbose: bool, make: F) -> Result<String,GitError>
+    where F: FnMut(&str) -> Result<String,GitError> {
+    ensure_checks_passed(git, config, pr)?;
+    ensure_approved(git, config, pr)?;
+    let commit = merge::push_onto_trunk(git, config, pr, delete, make)?;
+    if delete {
+        delete_local(git, pr, verbose)?;
+    }
+    Ok(commit)
+}
+
+// Refuse to merge `pr` until each of the CI checks `pr.requiredCheck` names has passed on its tip.
+fn ensure_checks_passed(git: &Git, config: &Config, pr: &PullRequest) -> Result<(),GitError> {
+    let required = &config.required_checks.value;
+    if required.is_empty() {
+        return Ok(());
+    }
+    metadata::sync(git, &config.remote.value)?;
+    let lines = metadata::lines(git, &pr.tip)?;
+    let report = match signing::enabled(git)? {
+        true => Report::from_lines(&ci::verified(git, &lines, &pr.tip)?),
+        false => Report::from_lines(&lines),
+    };
+    let unpassed: Vec<String> = report.unpassed(required).into_iter()
+        .map(|(name, status)| match status {
+            Some(status) => tr!("merge-check-status", check = name, status = status),
+            None => tr!("merge-check-missing", check = name),
+        })
+        .collect();
+    match unpassed.is_empty() {
+        true => Ok(()),
+        false => Err(GitError::Refused(tr!("merge-checks-required", branch = pr.branch,
+                                           checks = unpassed.join(", "),
+                                           source = config.required_checks.source))),
+    }
+}
+
+// Refuse to merge `pr` until `pr.minApprovals` people other than its author have approved its tip.
+fn ensure_approved(git: &Git, config: &Config, pr: &PullRequest) -> Result<(),GitError> {
+    let required = config.min_approvals.value as usize;
+    if required == 0 {
+        return Ok(());
+    }
+    metadata::sync(git, &config.remote.value)?;
+    let lines = metadata::lines(git, &pr.tip)?;
+    let approvers = match signing::enabled(git)? {
+        true => approval::verified(git, &lines, &pr.tip)?,
+        false => approval::approvers(&lines),
+    };
+    let reviewers = approval::reviewers(git, &approvers, &git.author_of(&pr.tip)?)?;
+    match reviewers.len() >= required {
+        true => Ok(()),
+        false => Err(GitError::Refused(tr!("merge-approvals-required", branch = pr.branch,
+                                           required = required, count = reviewers.len(),
+                                           source = config.min_approvals.source))),
+    }
+}
+
+// Delete a merged PR's local branch, if there is one, now that it has been pushed to trunk.
+fn delete_local(git: &Git, pr: &PullRequest, verbose: bool) -> Result<(),GitError> {
+    // The push deleted the upstream, so git can't tell the branch is merged; it is if it has
+    // nothing the PR's tip doesn't, whether that was merged or squashed
+    let local = format!("refs/heads/{}", pr.branch);
+    let tip = git.resolve_ref(&local)?;
+    if git.current_branch()?.as_deref() == Some(local.as_str()) {
+        eprintln!("{}", tr!("merge-kept-checked-out", branch = pr.branch));
+    } else if let Some(tip) = tip {
+        if git.merge_base(&tip, &pr.tip)?.as_deref() != Some(tip.as_str()) {
+            eprintln!("{}", tr!("merge-kept-unmerged", branch = pr.branch));
+            return Ok(());
+        }
+        git.force_delete_branch(&pr.branch)?;
+        if verbose {
+            eprintln!("{}", tr!("clean-deleted", branch = pr.branch));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Find local PR branches which are gone from the remote
+//!
+//! A PR abandoned, archived, or merged from some other clone leaves its `<name>/<hash>` branch
+//! behind here, and `git pr clean` only notices the ones that were merged into trunk. This lists
+//! every local PR branch the remote no longer has, marking those which trunk already contains.
+//! `--delete` deletes them, and `--push` publishes them again under the same names (`git pr
+//! reopen` publishes a PR under a fresh name instead). Branches which are checked out are never
+//! deleted.
+//!
+//! The remote's branches are listed directly (see `Git::ls_remote_heads`), so the answer doesn't
+//! depend on when this clone last fetched. Branches belonging to other tools are left out.
+use crate::Shared;
+use clap::Args;
+use libgitpr::parse::{self, LocalBranch};
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, tr, Config, Git, GitError};
+
+
+#[derive(Args)]
+pub struct Orphans {
+    /// Delete the orphaned branches
+    #[arg(long, conflicts_with = "push")]
+    delete: bool,
+
+    /// Push the orphaned branches to the remote again
+    #[arg(long)]
+    push: bool,
+
+    /// Refuse to delete or push anything
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Orphans {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("orphans")?;
+        if self.delete || self.push {
+            config.ensure_writable("orphans", self.read_only)?;
+        }
+
+        let remote = &config.remote.value;
+        let trunk = &config.trunk.value;
+        let orphans = find(&git, &config, &PrIndex::probe(&git, remote, "*")?)?;
+        let merged = match git.resolve_ref(&format!("refs/heads/{}", trunk))? {
+            Some(_) => libgitpr::extract_deletable_branches(&git.merged_branches(trunk)?, trunk),
+            None => vec![],
+        };
+
+        for orphan in &orphans {
+            match merged.contains(&orphan.name) {
+                true => println!("{}", tr!("orphans-merged", branch = orphan.name, trunk = trunk)),
+                false => println!("{}", orphan.name),
+            }
+        }
+
+        let mut changed = vec![];
+        let mut result = Ok(());
+        if self.delete {
+            for orphan in &orphans {
+                if orphan.current || orphan.elsewhere {
+                    eprintln!("{}", tr!("merge-kept-checked-out", branch = orphan.name));
+                    continue;
+                }
+                result = git.force_delete_branch(&orphan.name);
+                if result.is_err() {
+                    break;
+                }
+                if shared.verbose {
+                    eprintln!("{}", tr!("clean-deleted", branch = orphan.name));
+                }
+                changed.push(format!("refs/heads/{}", orphan.name));
+            }
+            audit::record(&git, "orphans", &changed, &result)?;
+        } else if self.push {
+            for orphan in &orphans {
+                result = git.push_upstream(remote, &orphan.name);
+                if result.is_err() {
+                    break;
+                }
+                if shared.verbose {
+                    eprintln!("{}", tr!("create-pushed", branch = orphan.name, remote = remote));
+                }
+                changed.push(format!("refs/heads/{}", orphan.name));
+            }
+            audit::record(&git, "orphans", &changed, &result)?;
+        }
+        result
+    }
+}
+
+
+/// The local PR branches which aren't among the `open` PRs, leaving out other tools' branches.
+pub fn find(git: &Git, config: &Config, open: &PrIndex) -> Result<Vec<LocalBranch>,GitError> {
+    let guard = config.guard();
+    Ok(git.local_branches()?.lines()
+        .filter_map(LocalBranch::parse)
+        .filter(|b| parse::pr_branch(&b.name).is_some() && !guard.excludes(&b.name))
+        .filter(|b| !open.iter().any(|pr| pr.branch == b.name))
+        .collect())
+}
@@ -0,0 +1,64 @@
+//! Show long output through a pager, the way git does
+//!
+//! When stdout is a terminal, output goes through the pager git itself would use (see
+//! `Git::pager`), with `LESS` and `LV` defaulted as git defaults them, so that output which fits on
+//! the screen doesn't wait for a keypress. Otherwise it is simply printed. Colors follow the same
+//! rule, unless `color.ui` turns them off.
+use libgitpr::{Git, GitError};
+use std::env;
+use std::io::{self, ErrorKind, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+
+pub struct Pager {
+    command: Option<String>,
+    color: bool,
+}
+
+impl Pager {
+    pub fn new(git: &Git) -> Result<Pager,GitError> {
+        if !io::stdout().is_terminal() {
+            return Ok(Pager{ command: None, color: false });
+        }
+        let command = Some(git.pager()?).filter(|pager| !pager.is_empty() && pager != "cat");
+        let color = !matches!(git.config_get("color.ui")?.as_deref(), Some("false" | "never"));
+        Ok(Pager{ command, color })
+    }
+
+    /// The flag which tells `git diff` or `git log` whether to color its output.
+    pub fn color_flag(&self) -> &'static str {
+        match self.color {
+            true => "--color=always",
+            false => "--color=never",
+        }
+    }
+
+    pub fn show(self, text: &str) -> Result<(),GitError> {
+        let command = match self.command {
+            Some(command) => command,
+            None => {
+                print!("{}", text);
+                return Ok(());
+            }
+        };
+
+        let mut pager = Command::new("sh");
+        pager.arg("-c").arg(&command).stdin(Stdio::piped());
+        if env::var_os("LESS").is_none() {
+            pager.env("LESS", "FRX");
+        }
+        if env::var_os("LV").is_none() {
+            pager.env("LV", "-c");
+        }
+        let mut pager = pager.spawn()?;
+        if let Some(mut stdin) = pager.stdin.take() {
+            // Quitting the pager before the end is not an error
+            match stdin.write_all(text.as_bytes()) {
+                Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(e.into()),
+                _ => {},
+            }
+        }
+        pager.wait()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+//! Give a pull request a new name
+//!
+//! A PR's branch is `<name>/<hash>`, so renaming it means publishing the same commit as
+//! `<new-name>/<hash>` and deleting the old branch. Both happen in one atomic push, so
+//! collaborators never see the PR twice, or not at all. A local branch for the PR is renamed too,
+//! and set to track the new branch.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{audit, parse, tr, GitError};
+
+
+#[derive(Args)]
+pub struct Rename {
+    /// The PR's current name, or its branch if several PRs share the name
+    #[arg(value_name = "old")]
+    old: String,
+
+    /// What the PR should be called instead
+    #[arg(value_name = "new")]
+    new: String,
+
+    /// Refuse to run, since this changes the repository and the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Rename {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("rename")?;
+        config.ensure_writable("rename", self.read_only)?;
+
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.old)?;
+
+        // The hash says where the PR started, so it stays the same
+        let hash = parse::pr_branch(&pr.branch).map(|(_, hash)| hash).unwrap_or_default();
+        let branch = format!("{}/{}", self.new, hash);
+        if parse::pr_branch(&branch).map(|(name, _)| name) != Some(self.new.as_str())
+            || !git.is_valid_branch_name(&branch)? {
+            return Err(GitError::Refused(tr!("rename-bad-name", name = self.new)));
+        }
+        let local = git.resolve_ref(&format!("refs/heads/{}", branch))?;
+        if !index.find(&branch).is_empty() || local.is_some() {
+            return Err(GitError::Refused(tr!("rename-exists", branch = branch)));
+        }
+
+        let refspecs = [format!("{}:refs/heads/{}", pr.tip, branch),
+                        format!(":refs/heads/{}", pr.branch)];
+        let result = git.push_atomic(remote, &refspecs);
+        audit::record(&git, "rename", &[pr.branch.clone(), branch.clone()], &result)?;
+        result?;
+
+        if git.resolve_ref(&format!("refs/heads/{}", pr.branch))?.is_some() {
+            git.rename_branch(&pr.branch, &branch)?;
+            git.set_upstream(remote, &branch)?;
+        }
+        eprintln!("{}", tr!("rename-done", old = pr.branch, new = branch, remote = remote));
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+//! Bring back a pull request which was closed too soon
+//!
+//! A PR which was abandoned, archived, or merged prematurely is no longer on the remote, but its
+//! commits usually are still somewhere: under an archive tag (see `git pr archive`), or in the
+//! remote-tracking branch this clone had before its next fetch pruned it. Reopening looks in both
+//! places, then publishes the PR's tip again as `<name>/<hash>`, where the hash is now the tip's
+//! own. A local branch is created for it too, tracking the new branch, unless one is left over
+//! from before. The new branch is recorded as open (see `libgitpr::state`).
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::retention::ARCHIVE_PREFIX;
+use libgitpr::state::{self, State};
+use libgitpr::{audit, tr, GitError};
+
+
+#[derive(Args)]
+pub struct Reopen {
+    /// The PR's name, or its old branch if several closed PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Refuse to run, since this changes the repository and the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Reopen {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("reopen")?;
+        config.ensure_writable("reopen", self.read_only)?;
+        let remote = &config.remote.value;
+
+        // Fetching prunes the branches of closed PRs, so note them beforehand
+        let before = git.remote_refs(remote)?;
+        git.fetch_prune()?;
+        git.fetch_refs(remote, &[format!("+{}*:{}*", ARCHIVE_PREFIX, ARCHIVE_PREFIX)])?;
+        let open = PrIndex::load(&git, remote)?;
+
+        let mut closed = PrIndex::with_guard(remote, config.guard());
+        for (tip, refname) in before.lines().filter_map(|line| line.trim().split_once(' ')) {
+            closed.update(refname, Some(tip));
+        }
+        for pr in open.iter() {
+            closed.update(&format!("refs/remotes/{}/{}", remote, pr.branch), None);
+        }
+        for line in git.ref_dates(ARCHIVE_PREFIX)?.lines() {
+            let Some((_, tag)) = line.trim().split_once(' ') else { continue };
+            if let Some(tip) = git.resolve_ref(tag)? {
+                let branch = tag.trim_start_matches(ARCHIVE_PREFIX);
+                closed.update(&format!("refs/remotes/{}/{}", remote, branch), Some(&tip));
+            }
+        }
+        let pr = match closed.lookup(&self.name) {
+            Ok(pr) => pr,
+            Err(e) => return Err(match open.find(&self.name).as_slice() {
+                [pr, ..] => GitError::Refused(
+                    tr!("reopen-still-open", branch = pr.branch, remote = remote)
+                ),
+                [] => e.into(),
+            }),
+        };
+
+        let branch = format!("{}/{}", pr.name, git.abbreviate(&pr.tip)?);
+        // A local branch left over from before can be published again, if it hasn't moved on
+        let local = git.resolve_ref(&format!("refs/heads/{}", branch))?;
+        if !open.find(&branch).is_empty() || local.as_ref().is_some_and(|tip| *tip != pr.tip) {
+            return Err(GitError::Refused(tr!("reopen-exists", branch = branch)));
+        }
+        let result = match local {
+            Some(_) => Ok(()),
+            None => git.create_branch_at(&branch, &pr.tip),
+        }.and_then(|_| {
+            let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+            state::push_with(&git, remote, &branch, &pr.tip, State::Open, &[refspec])
+        }).and_then(|_| git.set_upstream(remote, &branch));
+        audit::record(&git, "reopen", &[pr.branch.clone(), branch.clone()], &result)?;
+        result?;
+
+        eprintln!("{}", tr!("reopen-done", old = pr.branch, new = branch, remote = remote));
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+//! Show the team's reviewers
+//!
+//! `git pr reviewers` lists the reviewers in `pr.reviewer`, whom `git pr assign --auto` chooses
+//! between, with how many open PRs each is assigned to, and when anyone who is away (see `git pr
+//! away`) will be back.
+use crate::assign::{load, people};
+use crate::Shared;
+use clap::Args;
+use libgitpr::date::DateFormat;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{availability, metadata, tr, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Reviewers {}
+
+impl Reviewers {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("reviewers")?;
+        let reviewers = people(&git, &git.config_get_all("pr.reviewer")?)?;
+        if reviewers.is_empty() {
+            eprintln!("{}", tr!("assign-no-reviewers"));
+            return Ok(());
+        }
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        metadata::sync(&git, remote)?;
+        let index = PrIndex::load(&git, remote)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+
+        let load = load(&git, &index)?;
+        let away = availability::away(&availability::lines(&git)?, now);
+        let dates = DateFormat::configured(&config.date_format.value, false);
+        for who in reviewers {
+            let count = load.get(&who).copied().unwrap_or(0);
+            let mut line = format!("{}\t{}", who, tr!("reviewers-assigned", count = count));
+            if let Some(until) = away.get(&who) {
+                line.push('\t');
+                line.push_str(&tr!("reviewers-away", until = dates.render(*until, now)));
+            }
+            println!("{}", line);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+//! Show who has approved which revision of a pull request
+//!
+//! `git pr reviews` lists the approvals (see `git pr approve`) of every PR on the remote, or just
+//! the one named, one per line: the PR's branch, the commit which was approved, and who approved
+//! it. Approvals of the PR's tip come first. Approvals of its earlier commits, and of earlier
+//! revisions published with `git pr amend` which are still on the remote, follow, marked as being
+//! for an earlier revision, since the PR has changed since they were given.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::{PrIndex, PullRequest};
+use libgitpr::{approval, metadata, revision, tr, Git, GitError};
+
+
+#[derive(Args)]
+pub struct Reviews {
+    /// The PR's name, or its branch if several PRs share the name; every PR if not given
+    #[arg(value_name = "name")]
+    name: Option<String>,
+}
+
+impl Reviews {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("reviews")?;
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+        metadata::sync(&git, remote)?;
+        let index = PrIndex::load(&git, remote)?;
+        let prs: Vec<&PullRequest> = match &self.name {
+            Some(name) => vec![index.lookup(name)?],
+            None => index.iter().collect(),
+        };
+        let trunk = git.resolve_ref(&trunk)?.map(|_| trunk);
+
+        for pr in prs {
+            let approvals = approvals(&git, remote, trunk.as_deref(), pr)?;
+            for (branch, commit, who) in &approvals {
+                let short: String = commit.chars().take(7).collect();
+                let mut line = format!("{}\t{}\t{}", branch, short, who);
+                if *commit != pr.tip {
+                    line.push('\t');
+                    line.push_str(&tr!("reviews-earlier"));
+                }
+                println!("{}", line);
+            }
+            if approvals.is_empty() && self.name.is_some() {
+                eprintln!("{}", tr!("reviews-none", branch = pr.branch));
+            }
+        }
+        Ok(())
+    }
+}
+
+
+// Every approval of `pr`, as (branch, commit, who), starting with its tip's and working back
+// through its commits since `trunk` (if there is one), and then through its earlier revisions.
+fn approvals(git: &Git, remote: &str, trunk: Option<&str>, pr: &PullRequest)
+    -> Result<Vec<(String, String, String)>,GitError> {
+    let mut approvals = vec![];
+    let mut seen: Vec<String> = vec![];
+    let mut revisions = vec![(pr.branch.clone(), pr.tip.clone())];
+    while let Some((branch, tip)) = revisions.pop() {
+        let mut commits: Vec<String> = match trunk {
+            Some(trunk) => git.commit_summaries(&format!("{}..{}", trunk, tip))?.lines()
+                .rev()
+                .filter_map(|line| line.split('\t').next().map(String::from))
+                .collect(),
+            None => vec![],
+        };
+        // The tip counts even if it is a merge, or already on trunk
+        if !commits.contains(&tip) {
+            commits.insert(0, tip.clone());
+        }
+        for commit in commits {
+            // A revision which only added commits shares the earlier ones with the last
+            if seen.contains(&commit) {
+                continue;
+            }
+            for who in approval::approvers(&metadata::lines(git, &commit)?) {
+                approvals.push((branch.clone(), commit.clone(), who));
+            }
+            seen.push(commit);
+        }
+        for earlier in revision::previous(&metadata::lines(git, &tip)?).into_iter().rev() {
+            let tracking = format!("refs/remotes/{}/{}", remote, earlier);
+            if let Some(earlier_tip) = git.resolve_ref(&tracking)? {
+                if !seen.contains(&earlier_tip) {
+                    revisions.insert(0, (earlier, earlier_tip));
+                }
+            }
+        }
+    }
+    Ok(approvals)
+}
@@ -0,0 +1,102 @@
+//! Find PRs by name, author, or what their commits say
+//!
+//! The pattern is matched against each PR's name and branch, the author of its tip, its owner
+//! (see `git pr handoff`), and the subjects of the commits it adds to trunk; see
+//! `libgitpr::search`. It is a case-insensitive substring, or a regular expression with
+//! `--regex`. Each matching PR is shown like `git pr list` shows it, followed by the fields that
+//! matched, and the same `--porcelain`, `--json`, and `--format` flags apply, with `{matched}`
+//! among the fields.
+use crate::list;
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::{self, PrIndex};
+use libgitpr::render::Output;
+use libgitpr::search::{self, Pattern};
+use libgitpr::{metadata, owner, state, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Search {
+    /// What to look for
+    #[arg(value_name = "pattern")]
+    pattern: String,
+
+    /// Treat the pattern as a regular expression
+    #[arg(short = 'E', long)]
+    regex: bool,
+
+    /// Print every column, in a stable form for scripts
+    #[arg(long, group = "output")]
+    porcelain: bool,
+
+    /// Print every field of each PR as a JSON object
+    #[arg(long, group = "output")]
+    json: bool,
+
+    /// Print each PR by filling in a template, like '{name}\t{matched}'
+    #[arg(long, group = "output", value_name = "template")]
+    format: Option<String>,
+}
+
+impl Search {
+    // The output flags are exclusive, which clap has already checked.
+    fn output(&self) -> Output {
+        match (self.porcelain, self.json, &self.format) {
+            (true, _, _) => Output::Porcelain,
+            (_, true, _) => Output::Json,
+            (_, _, Some(template)) => Output::Template(template.clone()),
+            _ => Output::Pretty,
+        }
+    }
+
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("search")?;
+        let pattern = match self.regex {
+            true => Pattern::regex(&self.pattern)?,
+            false => Pattern::substring(&self.pattern),
+        };
+        let mut fields = pull_request::FIELDS.to_vec();
+        fields.push(search::MATCHED);
+        let renderer = self.output()
+            .renderer(&["name", "matched"], &fields, &config.date_format.value)?;
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+        metadata::sync(&git, remote)?;
+        if renderer.shows("state") {
+            state::fetch(&git, remote)?;
+        }
+        let has_trunk = git.resolve_ref(&trunk)?.is_some();
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        for pr in PrIndex::load(&git, remote)?.iter() {
+            let author = git.author_of(&pr.tip)?;
+            let owner = owner::current(&metadata::lines(&git, &pr.tip)?)
+                .unwrap_or_else(|| author.clone());
+            let mut haystack = vec![
+                ("name", pr.name.clone()),
+                ("branch", pr.branch.clone()),
+                ("author", author),
+                ("owner", owner),
+            ];
+            // Without trunk to compare with, there is no telling which commits are the PR's
+            if has_trunk {
+                let commits = git.commit_summaries(&format!("{}..{}", trunk, pr.tip))?;
+                for line in commits.lines() {
+                    if let Some(subject) = line.splitn(3, '\t').nth(2) {
+                        haystack.push(("commits", subject.to_string()));
+                    }
+                }
+            }
+
+            let matched = search::matching_fields(&pattern, &haystack);
+            if !matched.is_empty() {
+                let record = list::record(&git, pr, renderer.as_ref(), now)?
+                    .with("matched", matched.iter().map(|f| f.to_string()).collect::<Vec<_>>());
+                println!("{}", renderer.render(&record));
+            }
+        }
+        Ok(())
+    }
+}
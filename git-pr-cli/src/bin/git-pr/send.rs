@@ -0,0 +1,102 @@
+//! Mail a pull request to a mailing list
+//!
+//! For projects which review patches by email, `git pr send <name>` formats the commits the PR
+//! adds to the remote's trunk as a series, as `git pr export` does, and hands it to `git
+//! send-email`. The series starts with a cover letter: its subject is the PR's name, and its body
+//! is the PR's description (see `libgitpr::merge::description`), rather than the placeholders
+//! `git format-patch` leaves for a person to fill in.
+//!
+//! `--to` and `--cc` say who to send it to, and `--reroll-count` marks a revision of an earlier
+//! series. Anything after `--` is passed to send-email as it is, such as `--dry-run`, or
+//! `--in-reply-to` to thread a new revision under the last. Otherwise send-email's own
+//! configuration (`sendemail.*`) applies.
+use crate::Shared;
+use clap::Args;
+use libgitpr::merge;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{tr, GitError};
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+
+#[derive(Args)]
+pub struct Send {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Who to send the series to
+    #[arg(long, value_name = "address")]
+    to: Vec<String>,
+
+    /// Who to copy the series to
+    #[arg(long, value_name = "address")]
+    cc: Vec<String>,
+
+    /// Mark the series as this revision of an earlier one
+    #[arg(long, value_name = "n", value_parser = clap::value_parser!(u32).range(1..))]
+    reroll_count: Option<u32>,
+
+    /// Options for git send-email
+    #[arg(last = true, value_name = "send-email options")]
+    send_email: Vec<String>,
+}
+
+impl Send {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("send")?;
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        if git.resolve_ref(&trunk)?.is_none() {
+            return Err(GitError::Refused(tr!("status-no-trunk", trunk = trunk)));
+        }
+        let range = format!("{}..{}", trunk, pr.tip);
+        let commits = git.commit_messages(&range)?;
+        if commits.is_empty() {
+            return Err(GitError::Refused(tr!("send-empty", branch = pr.branch, trunk = trunk)));
+        }
+
+        // Write the series somewhere of our own, and clean up however sending goes
+        let dir = git.git_dir()?.join("git-pr").join(format!("send-{}", process::id()));
+        let mut options = vec!["--cover-letter".to_string(),
+                               format!("--output-directory={}", dir.display())];
+        if let Some(n) = self.reroll_count {
+            options.push(format!("--reroll-count={}", n));
+        }
+        let sent = git.format_patch(&range, &options).and_then(|listing| {
+            let patches: Vec<PathBuf> = String::from_utf8_lossy(&listing).lines()
+                .map(PathBuf::from)
+                .collect();
+            if let Some(cover) = patches.first() {
+                let letter = fs::read_to_string(cover)?;
+                fs::write(cover, fill_cover_letter(&letter, &pr.name,
+                                                   &merge::description(&commits)))?;
+            }
+
+            let mut options = vec![];
+            for address in &self.to {
+                options.push(format!("--to={}", address));
+            }
+            for address in &self.cc {
+                options.push(format!("--cc={}", address));
+            }
+            options.extend(self.send_email);
+            git.send_email(&options, &patches)
+        });
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        sent
+    }
+}
+
+// Fill in the subject and body git format-patch leaves blank in a cover letter. Without a
+// description, the body is left empty.
+fn fill_cover_letter(letter: &str, subject: &str, description: &str) -> String {
+    letter.replacen("*** SUBJECT HERE ***", subject, 1)
+        .replacen("*** BLURB HERE ***", description, 1)
+}
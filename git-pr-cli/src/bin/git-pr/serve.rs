@@ -0,0 +1,253 @@
+//! Show pull requests in a web browser
+//!
+//! `git pr serve` runs a small web server, for teams whose bare repository has no web interface
+//! at all, so that people who don't use the command line can follow along. It is strictly
+//! read-only, and shows the same things the commands do:
+//!
+//! - `/` lists the open PRs, as `git pr list --authors` does, and the most recent things that
+//!   happened to any of them, from their timelines (see `git pr timeline`).
+//! - `/pr/<branch>` shows one PR's timeline, what CI reported about it (linking to its
+//!   artifacts), its commits, and its diff, as `git pr show` does.
+//! - `/metrics` gives Prometheus the number of open PRs, how many of them are claimed, and the
+//!   age of the oldest, so a review backlog can be alerted on (see `libgitpr::metrics`).
+//!
+//! It listens on `127.0.0.1:8080` unless `--listen` says otherwise; only listen on other
+//! addresses on a network you trust, since there is no authentication. PRs and their metadata are
+//! fetched again whenever a page is asked for, at most every half a minute.
+use crate::list::record;
+use crate::timeline::events;
+use crate::Shared;
+use clap::Args;
+use libgitpr::ci::Report;
+use libgitpr::config::Config;
+use libgitpr::http::{escape, page, percent_encode, Request, Response};
+use libgitpr::metrics::{self, Metric};
+use libgitpr::pull_request::{self, PrIndex, PullRequest};
+use libgitpr::render::{Output, Value};
+use libgitpr::{claim, date, metadata, tr, Git, GitError};
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+
+// How long pages may show what was fetched before, rather than fetching again.
+const REFRESH: Duration = Duration::from_secs(30);
+
+// How many events the front page shows.
+const RECENT: usize = 20;
+
+// How long to wait for a browser to say what it wants.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// The content type of the Prometheus text exposition format.
+const METRICS: &str = "text/plain; version=0.0.4";
+
+
+#[derive(Args)]
+pub struct Serve {
+    /// The address and port to listen on
+    #[arg(long, value_name = "address", default_value = "127.0.0.1:8080")]
+    listen: String,
+}
+
+impl Serve {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("serve")?;
+        let listener = TcpListener::bind(&self.listen)?;
+        println!("{}", tr!("serve-listening", address = listener.local_addr()?));
+
+        let mut fetched: Option<Instant> = None;
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            if fetched.is_none_or(|at| at.elapsed() >= REFRESH) {
+                let refreshed = git.fetch_prune()
+                    .and_then(|_| metadata::sync(&git, &config.remote.value));
+                if let Err(e) = refreshed {
+                    eprintln!("{}", tr!("serve-fetch-failed", error = e));
+                }
+                fetched = Some(Instant::now());
+            }
+            // One browser hanging up early mustn't stop the server
+            if let Err(e) = answer(&git, &config, stream, shared.verbose) {
+                eprintln!("{}", tr!("serve-failed", error = e));
+            }
+        }
+        Ok(())
+    }
+}
+
+
+// Read one request from `stream`, and send back the page it asks for.
+fn answer(git: &Git, config: &Config, stream: TcpStream, verbose: bool) -> Result<(),GitError> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let request = match Request::read(&mut BufReader::new(stream))? {
+        Some(request) => request,
+        None => return Ok(()),
+    };
+    let response = match (request.method.as_str(), request.path.strip_prefix("/pr/")) {
+        ("GET", _) if request.path == "/" => front_page(git, config),
+        ("GET", _) if request.path == "/metrics" => metrics_page(git, config),
+        ("GET", Some(branch)) => pr_page(git, config, branch),
+        ("GET", None) => Ok(Response::error(404, &tr!("serve-not-found",
+                                                      path = request.path))),
+        _ => Ok(Response::error(405, &tr!("serve-read-only"))),
+    };
+    let response = response.unwrap_or_else(|e| Response::error(500, &e.to_string()));
+    if verbose {
+        eprintln!("{} {} {}", request.method, request.path, response.status);
+    }
+    response.write_to(&mut writer)?;
+    Ok(())
+}
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+fn trunk(config: &Config) -> String {
+    format!("{}/{}", config.remote.value, config.trunk.value)
+}
+
+// How big the review backlog is, for Prometheus to scrape.
+fn metrics_page(git: &Git, config: &Config) -> Result<Response,GitError> {
+    let index = PrIndex::load(git, &config.remote.value)?;
+    let now = now();
+    let mut claimed = 0;
+    let mut oldest = 0;
+    for pr in index.iter() {
+        if claim::current(&metadata::lines(git, &pr.tip)?, now).is_some() {
+            claimed += 1;
+        }
+        oldest = oldest.max(now - git.commit_time(&pr.tip)?);
+    }
+    Ok(Response::of_type(METRICS, metrics::render(&[
+        Metric::gauge("gitpr_open_pull_requests",
+                      "PR branches currently known on the remote", index.len() as f64),
+        Metric::gauge("gitpr_claimed_pull_requests",
+                      "Open PRs which someone has claimed with git pr take", claimed as f64),
+        Metric::gauge("gitpr_oldest_pull_request_age_seconds",
+                      "How long ago the tip of the oldest open PR was committed", oldest as f64),
+    ])))
+}
+
+fn link(pr: &PullRequest) -> String {
+    format!("<a href=\"/pr/{}\">{}</a>", percent_encode(&pr.branch), escape(&pr.name))
+}
+
+// The open PRs, and what happened to them lately.
+fn front_page(git: &Git, config: &Config) -> Result<Response,GitError> {
+    let remote = &config.remote.value;
+    let index = PrIndex::load(git, remote)?;
+    let now = now();
+    let columns = ["name", "branch", "author", "age", "claimed"];
+    let renderer = Output::Pretty.renderer(&columns, pull_request::FIELDS,
+                                           &config.date_format.value)?;
+
+    let mut body = format!("<h1>{}</h1>\n", escape(&tr!("serve-title", remote = remote)));
+    if index.is_empty() {
+        body.push_str(&format!("<p>{}</p>\n", escape(&tr!("serve-no-prs"))));
+    } else {
+        body.push_str("<table>\n<tr>");
+        for heading in ["serve-name", "serve-branch", "serve-author", "serve-updated",
+                        "serve-claimed"] {
+            body.push_str(&format!("<th>{}</th>", escape(&tr!(heading))));
+        }
+        body.push_str("</tr>\n");
+        for pr in index.iter() {
+            let record = record(git, pr, renderer.as_ref(), now)?;
+            body.push_str(&format!("<tr><td>{}</td>", link(pr)));
+            for column in &columns[1..] {
+                let text = match record.get(column) {
+                    Value::Text(text) => text.clone(),
+                    _ => String::new(),
+                };
+                body.push_str(&format!("<td>{}</td>", escape(&text)));
+            }
+            body.push_str("</tr>\n");
+        }
+        body.push_str("</table>\n");
+    }
+
+    let mut recent = vec![];
+    if git.resolve_ref(&trunk(config))?.is_some() {
+        for pr in index.iter() {
+            recent.extend(events(git, &trunk(config), pr)?.into_iter().map(|event| (event, pr)));
+        }
+    }
+    recent.sort_by_key(|(event, _)| -event.time);
+    if !recent.is_empty() {
+        body.push_str(&format!("<h2>{}</h2>\n<ul>\n", escape(&tr!("serve-recent"))));
+        for (event, pr) in recent.iter().take(RECENT) {
+            body.push_str(&format!("<li>{}: {} ({})</li>\n", link(pr), escape(&event.text),
+                                   escape(&date::relative(now - event.time))));
+        }
+        body.push_str("</ul>\n");
+    }
+    Ok(Response::html(page(&tr!("serve-title", remote = remote), &body)))
+}
+
+// Everything about one PR.
+fn pr_page(git: &Git, config: &Config, branch: &str) -> Result<Response,GitError> {
+    let index = PrIndex::load(git, &config.remote.value)?;
+    let pr = match index.iter().find(|pr| pr.branch == branch) {
+        Some(pr) => pr,
+        None => return Ok(Response::error(404, &tr!("serve-no-such-pr", branch = branch))),
+    };
+    let trunk = trunk(config);
+    let base = pr.base(git, &trunk)?;
+    let now = now();
+
+    let title = format!("{} ({})", pr.name, pr.branch);
+    let mut body = format!("<p><a href=\"/\">{}</a></p>\n<h1>{}</h1>\n",
+                           escape(&tr!("serve-all-prs")), escape(&title));
+    body.push_str(&format!("<h2>{}</h2>\n<ul>\n", escape(&tr!("serve-timeline"))));
+    for event in events(git, &trunk, pr)? {
+        body.push_str(&format!("<li>{} ({})</li>\n", escape(&event.text),
+                               escape(&date::relative(now - event.time))));
+    }
+    body.push_str("</ul>\n");
+    let report = Report::from_lines(&metadata::lines(git, &pr.tip)?);
+    if !report.is_empty() {
+        body.push_str(&format!("<h2>{}</h2>\n", escape(&tr!("serve-ci"))));
+        if let Some(status) = report.status {
+            body.push_str(&format!("<p>{}</p>\n",
+                                   escape(&tr!("ci-show-status", status = status))));
+        }
+        if !report.checks.is_empty() {
+            body.push_str(&format!("<table>\n<tr><th>{}</th><th>{}</th><th>{}</th></tr>\n",
+                                   escape(&tr!("ci-show-check")),
+                                   escape(&tr!("ci-show-check-status")),
+                                   escape(&tr!("ci-show-check-metrics"))));
+            for (name, check) in &report.checks {
+                let metrics: Vec<String> = check.metrics.iter()
+                    .map(|(metric, value)| format!("{}={}", metric, value))
+                    .collect();
+                body.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                                       escape(name), check.status,
+                                       escape(&metrics.join(" "))));
+            }
+            body.push_str("</table>\n");
+        }
+        body.push_str("<ul>\n");
+        for (name, url) in &report.artifacts {
+            // Only web links are made clickable; anything else could run script in the page
+            match url.starts_with("https://") || url.starts_with("http://") {
+                true => body.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", escape(url),
+                                               escape(name))),
+                false => body.push_str(&format!("<li>{}</li>\n", escape(&tr!(
+                    "ci-show-artifact", name = name, url = url)))),
+            }
+        }
+        body.push_str("</ul>\n");
+    }
+    let range = format!("{}..{}", base, pr.tip);
+    body.push_str(&format!("<h2>{}</h2>\n<pre>{}</pre>\n", escape(&tr!("serve-commits")),
+                           escape(&git.log(&range, &["--color=never"])?)));
+    body.push_str(&format!("<h2>{}</h2>\n<pre>{}</pre>\n", escape(&tr!("serve-changes")),
+                           escape(&git.diff(&base, &pr.tip, &["--color=never"])?)));
+    Ok(Response::html(page(&title, &body)))
+}
@@ -0,0 +1,165 @@
+//! Show what a pull request changes
+//!
+//! The PR is compared with the point where it left trunk (the merge base of the PR and trunk on the
+//! remote), so changes made to trunk since then are not part of it. The PR's commits are shown
+//! first, then its diff, through the pager (see `pager`). `--stat` shows a summary of the diff
+//! instead, and `--name-only` just the paths it changes.
+//!
+//! In a partial clone, the files the diff needs are fetched in one go beforehand (see
+//! `libgitpr::partial`). If that fails, a summary is shown instead, as with `--stat`.
+//!
+//! The PR's description, if it has one (see `git pr describe`), comes first. Whatever CI has
+//! reported about the PR's tip with `git pr ci set`, like whether the build passed, a table of its
+//! checks, and links to its artifacts, is shown before the commits, along with the earlier
+//! revisions the PR replaced (see `git pr amend`).
+//!
+//! `--porcelain`, `--json`, and `--format <template>` print the PR itself rather than its changes,
+//! as described in `libgitpr::render`, with every field in `libgitpr::pull_request::FIELDS`, like
+//! `git pr list` does for each PR.
+use crate::list;
+use crate::pager::Pager;
+use crate::Shared;
+use clap::Args;
+use libgitpr::ci::Report;
+use libgitpr::pull_request::{self, PrIndex};
+use libgitpr::render::Output;
+use libgitpr::{description, metadata, partial, revision, state, tr, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Show {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Summarize the diff, as git diff --stat does
+    #[arg(long, conflicts_with_all = ["name_only", "output"])]
+    stat: bool,
+
+    /// Show only the paths the PR changes
+    #[arg(long, conflicts_with = "output")]
+    name_only: bool,
+
+    /// Print every field of the PR, in a stable form for scripts
+    #[arg(long, group = "output")]
+    porcelain: bool,
+
+    /// Print every field of the PR as a JSON object
+    #[arg(long, group = "output")]
+    json: bool,
+
+    /// Print the PR by filling in a template, like '{name}\t{author}'
+    #[arg(long, group = "output", value_name = "template")]
+    format: Option<String>,
+}
+
+impl Show {
+    // The output flags are exclusive, which clap has already checked.
+    fn output(&self) -> Output {
+        match (self.porcelain, self.json, &self.format) {
+            (true, _, _) => Output::Porcelain,
+            (_, true, _) => Output::Json,
+            (_, _, Some(template)) => Output::Template(template.clone()),
+            _ => Output::Pretty,
+        }
+    }
+
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("show")?;
+        let output = self.output();
+        let columns: Vec<&str> = pull_request::FIELDS.iter().map(|(field, _)| *field).collect();
+        let renderer = output.renderer(&columns, pull_request::FIELDS, &config.date_format.value)?;
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+        metadata::sync(&git, remote)?;
+        description::sync(&git, remote)?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+
+        if output != Output::Pretty {
+            if renderer.shows("state") {
+                state::fetch(&git, remote)?;
+            }
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64);
+            println!("{}", renderer.render(&list::record(&git, pr, renderer.as_ref(), now)?));
+            return Ok(());
+        }
+        let base = pr.base(&git, &trunk)?;
+
+        let mut stat = self.stat;
+        if !self.stat && !self.name_only {
+            if let Err(e) = partial::prefetch(&git, &format!("{}..{}", base, pr.tip)) {
+                eprintln!("{}", tr!("show-prefetch-failed", error = e));
+                stat = true;
+            }
+        }
+
+        let pager = Pager::new(&git)?;
+        let color = pager.color_flag();
+        let text = match (stat, self.name_only) {
+            (_, true) => git.diff(&base, &pr.tip, &["--name-only"])?,
+            (stat, _) => {
+                let log = git.log(&format!("{}..{}", base, pr.tip), &[color])?;
+                let diff = match stat {
+                    true => git.diff(&base, &pr.tip, &[color, "--stat"])?,
+                    false => git.diff(&base, &pr.tip, &[color])?,
+                };
+                let described = description::get(&git, &pr.tip)?
+                    .map_or(String::new(), |text| format!("{}\n\n", text));
+                let lines = metadata::lines(&git, &pr.tip)?;
+                format!("{}{}{}{}\n{}", described, revisions(&lines),
+                        ci(&Report::from_lines(&lines)), log, diff)
+            }
+        };
+        pager.show(&text)
+    }
+}
+
+// Name the earlier revisions of the PR, ready to go before the commits.
+fn revisions(lines: &[String]) -> String {
+    let previous = revision::previous(lines);
+    if previous.is_empty() {
+        return String::new();
+    }
+    let mut text = String::new();
+    for branch in previous {
+        text.push_str(&format!("{}\n", tr!("show-revises", branch = branch)));
+    }
+    text.push('\n');
+    text
+}
+
+// Describe what CI reported, ready to go before the commits.
+fn ci(report: &Report) -> String {
+    if report.is_empty() {
+        return String::new();
+    }
+    let mut text = String::new();
+    if let Some(status) = report.status {
+        text.push_str(&format!("{}\n", tr!("ci-show-status", status = status)));
+    }
+    if !report.checks.is_empty() {
+        let mut rows = vec![[tr!("ci-show-check"), tr!("ci-show-check-status"),
+                             tr!("ci-show-check-metrics")]];
+        for (name, check) in &report.checks {
+            let metrics: Vec<String> = check.metrics.iter()
+                .map(|(metric, value)| format!("{}={}", metric, value))
+                .collect();
+            rows.push([name.clone(), check.status.to_string(), metrics.join(" ")]);
+        }
+        let width = |column: usize| rows.iter().map(|row| row[column].chars().count()).max();
+        let (names, statuses) = (width(0).unwrap_or(0), width(1).unwrap_or(0));
+        for [name, status, metrics] in &rows {
+            let row = format!("    {:<names$}  {:<statuses$}  {}", name, status, metrics);
+            text.push_str(&format!("{}\n", row.trim_end()));
+        }
+    }
+    for (name, url) in &report.artifacts {
+        text.push_str(&format!("    {}\n", tr!("ci-show-artifact", name = name, url = url)));
+    }
+    text.push('\n');
+    text
+}
@@ -0,0 +1,124 @@
+//! Save the remote's whole review state to a file, or restore it from one
+//!
+//! `git pr snapshot create <file>` writes every PR branch, trunk, the PRs' metadata, descriptions,
+//! attachments and states, and archived PRs, as they are on the remote, into a single git bundle
+//! (see `libgitpr::snapshot`). `git pr snapshot restore <file>` pushes them all back, to the remote
+//! or to whichever one `--remote` names, in one atomic push, so a remote is restored completely or
+//! not at all. Refs the remote already has at other commits are refused, unless `--force` is given.
+use crate::Shared;
+use clap::{Args, Subcommand};
+use libgitpr::{audit, snapshot, tr, Git, GitError};
+use std::env;
+use std::path::PathBuf;
+
+
+#[derive(Args)]
+pub struct Snapshot {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Save the remote's PRs, metadata, states, and archives to a file
+    Create(Create),
+
+    /// Push a snapshot's PRs, metadata, states, and archives to the remote
+    Restore(Restore),
+}
+
+impl Snapshot {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        match self.command {
+            Command::Create(create) => create.run(shared),
+            Command::Restore(restore) => restore.run(shared),
+        }
+    }
+}
+
+
+#[derive(Args)]
+struct Create {
+    /// Where to write the snapshot
+    #[arg(value_name = "file")]
+    file: PathBuf,
+}
+
+impl Create {
+    fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("snapshot")?;
+        let remote = &config.remote.value;
+        let refs = snapshot::refs(&git.ls_remote(remote, "refs/")?, &config.trunk.value);
+        if refs.is_empty() {
+            return Err(GitError::Refused(tr!("snapshot-nothing", remote = remote)));
+        }
+
+        // Fetch everything under the snapshot's own names, bundle it, and tidy up either way
+        let refspecs: Vec<String> = refs.iter()
+            .map(|refname| format!("+{}:{}", refname, snapshot::private(refname)))
+            .collect();
+        let private: Vec<String> = refs.iter().map(|refname| snapshot::private(refname)).collect();
+        let private: Vec<&str> = private.iter().map(String::as_str).collect();
+        let result = git.fetch_refs(remote, &refspecs)
+            .and_then(|_| git.bundle_create(&env::current_dir()?.join(&self.file), &private));
+        clear(&git)?;
+        result?;
+
+        eprintln!("{}", tr!("snapshot-created", count = refs.len(), remote = remote,
+                            file = self.file.display()));
+        Ok(())
+    }
+}
+
+
+#[derive(Args)]
+struct Restore {
+    /// The snapshot to restore
+    #[arg(value_name = "file")]
+    file: PathBuf,
+
+    /// Overwrite refs the remote already has at other commits
+    #[arg(long)]
+    force: bool,
+
+    /// Refuse to run, since this changes the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Restore {
+    fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("snapshot")?;
+        config.ensure_writable("snapshot", self.read_only)?;
+        let remote = &config.remote.value;
+        // git runs from the top of the repository, which may not be where we are
+        let file = env::current_dir()?.join(&self.file);
+        let refspecs = snapshot::restore_refspecs(&git.bundle_list_heads(&file)?, self.force);
+        if refspecs.is_empty() {
+            return Err(GitError::Refused(tr!("snapshot-not-a-snapshot",
+                                             file = self.file.display())));
+        }
+
+        let everything = format!("+{0}*:{0}*", snapshot::NAMESPACE);
+        let result = git.fetch_refs(&file.to_string_lossy(), &[everything])
+            .and_then(|_| git.push_atomic(remote, &refspecs));
+        audit::record(&git, "snapshot", &refspecs, &result)?;
+        clear(&git)?;
+        result?;
+
+        eprintln!("{}", tr!("snapshot-restored", count = refspecs.len(), remote = remote,
+                            file = self.file.display()));
+        Ok(())
+    }
+}
+
+
+// Delete the refs a snapshot was made from, or restored through.
+fn clear(git: &Git) -> Result<(),GitError> {
+    for line in git.ref_dates(snapshot::NAMESPACE)?.lines() {
+        if let Some((_, refname)) = line.split_once(' ') {
+            git.delete_ref(refname)?;
+        }
+    }
+    Ok(())
+}
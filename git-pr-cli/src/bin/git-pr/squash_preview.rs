@@ -0,0 +1,88 @@
+//! Show what landing a pull request would commit
+//!
+//! `git pr squash-preview <name>` prints the message of the commit `git pr land` would squash the
+//! PR into (see `libgitpr::merge_template`), followed by the diffstat of what that commit
+//! would change on the remote's trunk. Nothing is committed or pushed.
+//!
+//! `--edit` opens the message in git's editor first. The edited message is stored in this clone,
+//! and `git pr land` uses it in place of the one it would have composed, as long as the PR hasn't
+//! changed in the meantime.
+use crate::Shared;
+use clap::Args;
+use libgitpr::merge;
+use libgitpr::merge_template::{self, Kind};
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{tr, GitError};
+use std::fs;
+use std::process::Command;
+
+
+#[derive(Args)]
+pub struct SquashPreview {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Edit the message, and keep it for git pr land
+    #[arg(long)]
+    edit: bool,
+}
+
+impl SquashPreview {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("squash-preview")?;
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        let base = git.resolve_ref(&trunk)?
+            .ok_or_else(|| GitError::Refused(tr!("status-no-trunk", trunk = trunk)))?;
+
+        let commits = git.commit_messages(&format!("{}..{}", base, pr.tip))?;
+        if commits.is_empty() {
+            return Err(GitError::Refused(tr!("land-nothing", branch = pr.branch, trunk = trunk)));
+        }
+        let tree = git.merge_tree(&base, &pr.tip)?.ok_or_else(|| GitError::Refused(
+            tr!("merge-conflict", branch = pr.branch, trunk = trunk)
+        ))?;
+
+        let mut message = match merge::stored_squash_message(&git, &pr.tip)? {
+            Some(message) => message,
+            None => merge_template::compose(&git, pr, &base, Kind::Squash)?,
+        };
+        if self.edit {
+            let path = git.git_dir()?.join("git-pr").join("SQUASH_EDITMSG");
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let instructions = tr!("squash-preview-instructions", branch = pr.branch);
+            fs::write(&path, format!("{}\n# {}\n", message, instructions))?;
+
+            let editor = git.editor()?;
+            let status = Command::new("sh")
+                .arg("-c").arg(format!("{} \"$@\"", editor)).arg(&editor).arg(&path)
+                .status()?;
+            if !status.success() {
+                return Err(GitError::Refused(tr!("squash-preview-editor-failed", editor = editor)));
+            }
+            let edited: String = fs::read_to_string(&path)?.lines()
+                .filter(|line| !line.starts_with('#'))
+                .map(|line| format!("{}\n", line))
+                .collect();
+            fs::remove_file(&path)?;
+            if edited.trim().is_empty() {
+                return Err(GitError::Refused(tr!("squash-preview-empty")));
+            }
+            message = format!("{}\n", edited.trim());
+            merge::store_squash_message(&git, &pr.tip, &message)?;
+            if shared.verbose {
+                eprintln!("{}", tr!("squash-preview-stored", branch = pr.branch));
+            }
+        }
+
+        println!("{}", message);
+        print!("{}", git.diff_stat(&base, &tree)?);
+        Ok(())
+    }
+}
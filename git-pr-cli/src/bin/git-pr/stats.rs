@@ -0,0 +1,114 @@
+//! Report on the PRs which are open, and how quickly PRs are being merged
+//!
+//! Shows the number of open PRs, how many each author has open, their average age, and how many
+//! PRs have been merged into trunk this week (since Monday, UTC). Then come the number of PRs
+//! merged into trunk overall, the median time from a PR's first commit to its merge, and the
+//! number of merges per week. `--since` and `--until` restrict the merged PRs to a time window
+//! (accepting any date `git log` understands), and `--csv` prints one row per merged PR instead,
+//! for further analysis in a spreadsheet.
+//!
+//! `--paths` instead shows, for each top-level directory, how many open PRs touch it and how many
+//! recently merged PRs did (within the last 30 days, unless `--since` says otherwise). Directories
+//! with many open PRs are likely sources of merge conflicts.
+use crate::Shared;
+use clap::Args;
+use libgitpr::date::{self, DateFormat};
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{stats, tr, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Stats {
+    /// Only count PRs merged after this date
+    #[arg(long, value_name = "date")]
+    since: Option<String>,
+
+    /// Only count PRs merged before this date
+    #[arg(long, value_name = "date")]
+    until: Option<String>,
+
+    /// Print one row per merged PR, as CSV
+    #[arg(long, conflicts_with = "paths")]
+    csv: bool,
+
+    /// Show how many open and merged PRs touch each top-level directory
+    #[arg(long)]
+    paths: bool,
+}
+
+// Render a duration in seconds as a rough, human-friendly figure.
+fn humanize(seconds: i64) -> String {
+    match seconds {
+        s if s < 3_600 => tr!("stats-minutes", count = s / 60),
+        s if s < 86_400 => tr!("stats-hours", count = format!("{:.1}", s as f64 / 3_600.0)),
+        s => tr!("stats-days", count = format!("{:.1}", s as f64 / 86_400.0)),
+    }
+}
+
+impl Stats {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("stats")?;
+        let trunk = &config.trunk.value;
+        let remote = &config.remote.value;
+        let since = match (self.paths, self.since) {
+            (true, None) => Some("30 days ago".to_string()),
+            (_, since) => since,
+        };
+        let merged = stats::merged_prs(&git, trunk, remote, since.as_deref(),
+                                       self.until.as_deref())?;
+
+        if self.paths {
+            let mut open = vec![];
+            for pr in PrIndex::load(&git, remote)?.iter() {
+                open.push(git.changed_paths(trunk, &pr.tip)?);
+            }
+            let mut recent = vec![];
+            for pr in &merged {
+                recent.push(git.changed_paths(&pr.base, &pr.tip)?);
+            }
+
+            println!("{:<30} {:>6} {:>6}", "PATH", "OPEN", "MERGED");
+            for row in stats::path_heatmap(&open, &recent) {
+                println!("{:<30} {:>6} {:>6}", row.path, row.open, row.merged);
+            }
+            return Ok(());
+        }
+
+        if self.csv {
+            println!("name,started,merged,hours_to_merge");
+            for pr in &merged {
+                println!("{},{},{},{:.1}", pr.name, date::iso_date(pr.started_at),
+                         date::iso_date(pr.merged_at), pr.time_to_merge() as f64 / 3_600.0);
+            }
+            return Ok(());
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        let open = stats::open_prs(&git, &PrIndex::load(&git, remote)?, trunk)?;
+        println!("{}", tr!("stats-open", count = open.len()));
+        for (author, count) in stats::per_author(&open) {
+            println!("  {}  {}", author, count);
+        }
+        if let Some(age) = stats::average_age(&open, now) {
+            println!("{}", tr!("stats-average-age", duration = humanize(age)));
+        }
+        let this_week = stats::merged_this_week(&git, trunk, remote, now)?;
+        println!("{}", tr!("stats-merged-this-week", count = this_week.len()));
+
+        println!("{}", tr!("stats-merged", count = merged.len()));
+        let durations: Vec<i64> = merged.iter().map(|pr| pr.time_to_merge()).collect();
+        if let Some(median) = stats::median(&durations) {
+            println!("{}", tr!("stats-median", duration = humanize(median)));
+        }
+        if !merged.is_empty() {
+            let dates = DateFormat::configured(&config.date_format.value, false);
+            println!("{}", tr!("stats-per-week"));
+            for (week, count) in stats::weekly_throughput(&merged) {
+                println!("  {}  {}", dates.render_date(week), count);
+            }
+        }
+
+        Ok(())
+    }
+}
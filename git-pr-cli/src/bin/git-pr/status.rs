@@ -0,0 +1,59 @@
+//! Summarize where the current branch stands
+//!
+//! Shows the PR checked out (if any), how many commits it is ahead of and behind trunk on the
+//! remote, whether it has been pushed, and whether it has been merged (see `libgitpr::status`).
+//! Like `git status`, this uses what was known at the last fetch; `--fetch` fetches first.
+//!
+//! `--porcelain`, `--json`, and `--format <template>` work as described in `libgitpr::render`; the
+//! fields are listed in `libgitpr::status::FIELDS`.
+use crate::Shared;
+use clap::Args;
+use libgitpr::render::Output;
+use libgitpr::status;
+use libgitpr::GitError;
+
+
+#[derive(Args)]
+pub struct Status {
+    /// Fetch from the remote first
+    #[arg(long)]
+    fetch: bool,
+
+    /// Print every field on one line, in a stable form for scripts
+    #[arg(long, group = "output")]
+    porcelain: bool,
+
+    /// Print every field as a JSON object
+    #[arg(long, group = "output")]
+    json: bool,
+
+    /// Print the status by filling in a template, like '{name}\t{behind}'
+    #[arg(long, group = "output", value_name = "template")]
+    format: Option<String>,
+}
+
+impl Status {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("status")?;
+        if self.fetch {
+            git.fetch_prune()?;
+        }
+        let status = status::Status::gather(&git, &config.remote.value, &config.trunk.value)?;
+
+        let output = match (self.porcelain, self.json, self.format) {
+            (true, _, _) => Output::Porcelain,
+            (_, true, _) => Output::Json,
+            (_, _, Some(template)) => Output::Template(template),
+            _ => {
+                for line in status.describe() {
+                    println!("{}", line);
+                }
+                return Ok(());
+            }
+        };
+        let columns: Vec<&str> = status::FIELDS.iter().map(|(field, _)| *field).collect();
+        let renderer = output.renderer(&columns, status::FIELDS, &config.date_format.value)?;
+        println!("{}", renderer.render(&status.record()));
+        Ok(())
+    }
+}
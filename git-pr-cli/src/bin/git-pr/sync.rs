@@ -0,0 +1,88 @@
+//! Bring this clone up to date with the remote, in one step
+//!
+//! Sync fetches from the remote, pruning the remote-tracking branches of PRs which have been
+//! deleted there, merges the remote's PR metadata into ours (see `libgitpr::metadata`), and
+//! fast-forwards the local trunk branch, if there is one, to the remote's. It then prints what
+//! changed, and points out local PR branches which are no longer on the remote (see `git pr
+//! orphans`), without deleting them.
+//!
+//! The PRs are compared with those seen by the previous sync, which are remembered under `.git/`
+//! (see `PrIndex::remember`), so PRs which an ordinary `git fetch` brought in meanwhile are still
+//! reported. Each PR which was opened, updated, or closed since then gets a line of its own. The
+//! first sync in a clone compares with what was fetched before it.
+//!
+//! A local trunk with commits the remote lacks is left alone, as `git pr merge` leaves it, and so
+//! is one checked out in another worktree.
+use crate::orphans;
+use crate::Shared;
+use clap::Args;
+use libgitpr::metadata::{self, NOTES_REF};
+use libgitpr::pull_request::{Change, PrIndex};
+use libgitpr::{tr, GitError};
+
+
+#[derive(Args)]
+pub struct Sync {
+    /// Refuse to run, since this moves the local trunk branch
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Sync {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("sync")?;
+        config.ensure_writable("sync", self.read_only)?;
+        let remote = &config.remote.value;
+        let trunk = &config.trunk.value;
+
+        let before = match PrIndex::last_seen(&git, remote)? {
+            Some(seen) => seen,
+            None => PrIndex::load(&git, remote)?,
+        };
+        git.fetch_prune()?;
+        let after = PrIndex::load(&git, remote)?;
+        let changes = after.changes_since(&before);
+        let count = |f: fn(&Change) -> bool| changes.iter().filter(|c| f(c)).count();
+        println!("{}", tr!("sync-fetched", remote = remote, open = after.len(),
+                           new = count(|c| matches!(c, Change::Opened(_))),
+                           updated = count(|c| matches!(c, Change::Updated(_))),
+                           gone = count(|c| matches!(c, Change::Closed(_)))));
+        for change in &changes {
+            println!("  {}", match change {
+                Change::Opened(pr) => tr!("change-opened", name = pr.name),
+                Change::Updated(pr) => tr!("change-updated", name = pr.name,
+                                           commit = git.abbreviate(&pr.tip)?),
+                Change::Closed(pr) => tr!("change-closed", name = pr.name),
+            });
+        }
+        after.remember(&git)?;
+
+        let notes = git.resolve_ref(NOTES_REF)?;
+        metadata::sync(&git, remote)?;
+        if git.resolve_ref(NOTES_REF)? != notes {
+            println!("{}", tr!("sync-metadata", remote = remote));
+        }
+
+        let local_trunk = git.resolve_ref(&format!("refs/heads/{}", trunk))?;
+        let remote_trunk = git.resolve_ref(&format!("refs/remotes/{}/{}", remote, trunk))?;
+        if let (Some(local), Some(commit)) = (local_trunk, remote_trunk) {
+            let message = match local == commit {
+                true => tr!("sync-trunk-current", trunk = trunk),
+                false => match git.fast_forward(trunk, &commit) {
+                    Ok(true) => tr!("sync-trunk-advanced", trunk = trunk,
+                                    commit = git.abbreviate(&commit)?),
+                    Ok(false) => tr!("merge-local-trunk-diverged", trunk = trunk, remote = remote),
+                    Err(GitError::Refused(reason)) => reason,
+                    Err(e) => return Err(e),
+                },
+            };
+            println!("{}", message);
+        }
+
+        let orphans = orphans::find(&git, &config, &after)?;
+        if !orphans.is_empty() {
+            println!("{}", tr!("sync-orphans", count = orphans.len(), remote = remote));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+//! Claim a pull request
+//!
+//! `git pr take <name>` tells everyone that you are reviewing the PR, or carrying it on, so that
+//! nobody else does the same work; `git pr list` shows who holds each claim. Claims are published
+//! as metadata (see `libgitpr::claim`) and expire after `pr.claimHours`, 48 unless configured.
+//! Taking a PR you already hold renews your claim.
+//!
+//! A PR someone else has claimed is refused unless `--force` is given. `--release` gives up your
+//! own claim early.
+use crate::Shared;
+use clap::Args;
+use libgitpr::claim::{self, Claim};
+use libgitpr::date::DateFormat;
+use libgitpr::identity::Identity;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{metadata, tr, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Take {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Take the PR even if someone else has claimed it
+    #[arg(long, conflicts_with = "release")]
+    force: bool,
+
+    /// Give up your claim on the PR
+    #[arg(long)]
+    release: bool,
+
+    /// Refuse to run, since this publishes metadata to the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Take {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("take")?;
+        config.ensure_writable("take", self.read_only)?;
+
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        let me = Identity::current(&git)?.to_string();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        let hours = i64::from(config.claim_hours.value);
+
+        // Check again on every attempt, in case someone else's claim has just arrived
+        let mut applied = false;
+        metadata::update(&git, remote, |git| {
+            let lines = metadata::lines(git, &pr.tip)?;
+            let at = claim::next_time(&lines, now);
+            let line = match (self.release, claim::current(&lines, now)) {
+                (true, Some(held)) if held.who == me => claim::release_line(&me, at),
+                (true, _) => {
+                    return Err(GitError::Refused(tr!("take-not-held", branch = pr.branch)))
+                },
+                (false, Some(held)) if held.who != me && !self.force => {
+                    let until = DateFormat::configured(&config.date_format.value, false)
+                        .render(held.until, now);
+                    return Err(GitError::Refused(tr!("take-claimed", branch = pr.branch,
+                                                     who = held.who, until = until)));
+                },
+                (false, _) => claim::claim_line(&Claim{
+                    who: me.clone(), since: at, until: now + hours * 60 * 60
+                }),
+            };
+            if !applied {
+                git.append_note(metadata::NOTES_REF, &pr.tip, &metadata::format_line(&line))?;
+                applied = true;
+            }
+            Ok(())
+        })?;
+
+        match self.release {
+            true => eprintln!("{}", tr!("take-released", branch = pr.branch)),
+            false => eprintln!("{}", tr!("take-done", branch = pr.branch,
+                                         hours = config.claim_hours.value)),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+//! Tell the story of a pull request
+//!
+//! Shows what has happened to the PR, oldest first, with how long ago each thing happened: its
+//! commits, when they were rebased or amended, and the approvals, comments, claims, and handoffs
+//! recorded as metadata (see `libgitpr::timeline`), with encrypted comments decrypted if they can
+//! be (see `libgitpr::encryption`). `--graph` draws the commits along a line, with the metadata
+//! branching off it, much as `git log --graph` draws a merged branch.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::{PrIndex, PullRequest};
+use libgitpr::timeline::{self, Event, Kind};
+use libgitpr::{date, encryption, metadata, tr, Git, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Timeline {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Draw the timeline as a graph
+    #[arg(long)]
+    graph: bool,
+}
+
+impl Timeline {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("timeline")?;
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+        metadata::sync(&git, remote)?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        if git.resolve_ref(&trunk)?.is_none() {
+            return Err(GitError::Refused(tr!("status-no-trunk", trunk = trunk)));
+        }
+
+        let events = events(&git, &trunk, pr)?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        if self.graph {
+            for line in timeline::graph(&events, now) {
+                println!("{}", line);
+            }
+            return Ok(());
+        }
+        for event in &events {
+            let indent = match event.kind {
+                Kind::Metadata => "  ",
+                _ => "",
+            };
+            println!("{:<15} {}{}", date::relative(now - event.time), indent, event.text);
+        }
+        Ok(())
+    }
+}
+
+
+/// Everything that has happened to `pr` since it left `trunk`, oldest first.
+pub fn events(git: &Git, trunk: &str, pr: &PullRequest) -> Result<Vec<Event>,GitError> {
+    let commits = timeline::parse_commits(&git.commit_history(&format!("{}..{}", trunk,
+                                                                         pr.tip))?);
+    let mut notes = vec![];
+    for commit in &commits {
+        let lines = metadata::lines(git, &commit.hash)?.iter()
+            .map(|line| encryption::reveal_line(git, line))
+            .collect();
+        notes.push((commit.hash.clone(), lines));
+    }
+    Ok(timeline::events(&commits, &notes))
+}
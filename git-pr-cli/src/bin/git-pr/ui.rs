@@ -0,0 +1,95 @@
+//! Browse pull requests in the terminal
+//!
+//! `git pr ui` lists the open PRs, and shows the selected one's commits and diff, as `git pr show`
+//! does; keys check PRs out, merge them, and abandon them (see `libgitpr::tui` for the keys). Each
+//! of those works as the command of the same name does, with its defaults: merging fast-forwards
+//! trunk when it can, and leaves the PR's branch on the remote. They are refused in read-only mode,
+//! or when `pr.role.*` doesn't allow the command, and are recorded in the audit log.
+//!
+//! The list follows the PRs' refs as they change, whether by those keys or by a fetch elsewhere,
+//! re-reading only the refs which changed (see `crate::watch::Live`).
+use crate::checkout::switch_to;
+use crate::abandon::abandon_pr;
+use crate::merge::merge_pr;
+use crate::watch::Live;
+use crate::Shared;
+use clap::Args;
+use libgitpr::config::Config;
+use libgitpr::merge::{catch_up_trunk, FastForward};
+use libgitpr::pull_request::PullRequest;
+use libgitpr::tui::{self, Action, Screen};
+use libgitpr::{audit, picker, policy, tr, Git, GitError};
+use std::slice;
+
+
+#[derive(Args)]
+pub struct Ui {}
+
+impl Ui {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("ui")?;
+        if !picker::interactive() {
+            return Err(GitError::Refused(tr!("ui-not-a-terminal")));
+        }
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        git.fetch_prune()?;
+
+        let mut live = Live::start(&git, remote)?;
+        let mut screen = Screen::new(live.index().iter().cloned().collect());
+        tui::run(&mut screen, |pr| detail(&git, &trunk, pr), |action, pr| {
+            Ok(match (action, pr) {
+                (Action::Refresh, _) => {
+                    git.fetch_prune()?;
+                    tr!("ui-refreshed", remote = remote)
+                },
+                (_, None) => String::new(),
+                (action, Some(pr)) => act(&git, &config, action, pr, shared.verbose)?,
+            })
+        }, || {
+            Ok(match live.catch_up(&git)?.is_empty() {
+                true => None,
+                false => Some(live.index().iter().cloned().collect()),
+            })
+        })
+    }
+}
+
+// What the lower pane shows for `pr`: the commits it adds to `trunk`, and the changes they make.
+fn detail(git: &Git, trunk: &str, pr: &PullRequest) -> Result<String,GitError> {
+    let base = pr.base(git, trunk)?;
+    let range = format!("{}..{}", base, pr.tip);
+    Ok(format!("{}\n{}", git.log(&range, &["--color=never"])?,
+               git.diff(&base, &pr.tip, &["--color=never"])?))
+}
+
+// Check out, merge, or abandon `pr`, as those commands would, and say how it went.
+fn act(git: &Git, config: &Config, action: Action, pr: &PullRequest, verbose: bool)
+    -> Result<String,GitError> {
+    let remote = &config.remote.value;
+    let trunk = &config.trunk.value;
+    match action {
+        Action::Checkout => {
+            policy::enforce(git, "checkout")?;
+            let note = switch_to(git, remote, pr)?;
+            Ok(note.unwrap_or_else(|| tr!("checkout-switched", branch = pr.branch)))
+        },
+        Action::Merge => {
+            policy::enforce(git, "merge")?;
+            config.ensure_writable("merge", false)?;
+            let result = merge_pr(git, config, pr, FastForward::Allowed, false, verbose);
+            audit::record(git, "merge", &[pr.branch.clone(), trunk.clone()], &result)?;
+            catch_up_trunk(git, trunk, &result?, remote)?;
+            Ok(tr!("merge-done", branch = pr.branch, trunk = trunk, remote = remote))
+        },
+        Action::Abandon => {
+            policy::enforce(git, "abandon")?;
+            config.ensure_writable("abandon", false)?;
+            let result = abandon_pr(git, remote, pr);
+            audit::record(git, "abandon", slice::from_ref(&pr.branch), &result)?;
+            result?;
+            Ok(tr!("abandon-deleted", branch = pr.branch, remote = remote))
+        },
+        Action::Refresh | Action::Quit => Ok(String::new()),
+    }
+}
@@ -0,0 +1,68 @@
+//! Take reviewers off a pull request
+//!
+//! `git pr unassign <name> <user>...` withdraws assignments made with `git pr assign`, for instance
+//! when a reviewer is away, or the PR needs someone else's eyes instead.
+use crate::assign::people;
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::{assignment, audit, claim, metadata, tr, GitError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+#[derive(Args)]
+pub struct Unassign {
+    /// The PR's name, or its branch if several PRs share the name
+    #[arg(value_name = "name")]
+    name: String,
+
+    /// Who to take off the PR, as "Name <email>"
+    #[arg(value_name = "user", required = true)]
+    users: Vec<String>,
+
+    /// Refuse to run, since this publishes metadata to the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Unassign {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("unassign")?;
+        config.ensure_writable("unassign", self.read_only)?;
+
+        let users = people(&git, &self.users)?;
+        let remote = &config.remote.value;
+        git.fetch_prune()?;
+        let index = PrIndex::load(&git, remote)?;
+        let pr = index.lookup(&self.name)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+
+        let mut applied = false;
+        let result = metadata::update(&git, remote, |git| {
+            let lines = metadata::lines(git, &pr.tip)?;
+            let assigned = assignment::current(&lines);
+            if let Some(who) = users.iter().find(|who| !assigned.contains(who)) {
+                return Err(GitError::Refused(tr!("unassign-not-assigned", branch = pr.branch,
+                                                 user = who)));
+            }
+            let at = claim::next_time(&lines, now);
+            if !applied {
+                for who in &users {
+                    let line = assignment::unassign_line(who, at);
+                    git.append_note(metadata::NOTES_REF, &pr.tip, &metadata::format_line(&line))?;
+                }
+                applied = true;
+            }
+            Ok(())
+        });
+        let mut refs = vec![pr.branch.clone()];
+        refs.extend(users.iter().cloned());
+        audit::record(&git, "unassign", &refs, &result)?;
+        result?;
+
+        for who in users {
+            eprintln!("{}", tr!("unassign-done", branch = pr.branch, user = who));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+//! Bring the current pull request up to date with trunk
+//!
+//! The PR checked out is rebased onto the remote's trunk, after a fetch, and force-pushed. The push
+//! uses a lease, so it is refused if someone else pushed to the PR since we fetched, rather than
+//! throwing their work away. For the same reason, a PR which has commits on the remote that aren't
+//! here is refused before anything is rebased.
+//!
+//! If the rebase stops with conflicts, it is left in progress, and the conflicting paths are
+//! listed. Resolve them and run `git rebase --continue`, then `git pr update` again to push.
+use crate::Shared;
+use clap::Args;
+use libgitpr::{audit, parse, tr, GitError};
+
+
+#[derive(Args)]
+pub struct Update {
+    /// Refuse to run, since this rewrites the PR's branch here and on the remote
+    #[arg(long)]
+    read_only: bool,
+}
+
+impl Update {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("update")?;
+        config.ensure_writable("update", self.read_only)?;
+
+        let remote = &config.remote.value;
+        let trunk = format!("{}/{}", remote, config.trunk.value);
+        let branch = git.current_branch()?
+            .and_then(|branch| branch.strip_prefix("refs/heads/").map(|b| b.to_string()))
+            .filter(|branch| parse::pr_branch(branch).is_some())
+            .ok_or_else(|| GitError::Refused(tr!("update-not-a-pr")))?;
+
+        git.fetch_prune()?;
+        if git.resolve_ref(&trunk)?.is_none() {
+            return Err(GitError::Refused(tr!("status-no-trunk", trunk = trunk)));
+        }
+        let published = git.resolve_ref(&format!("refs/remotes/{}/{}", remote, branch))?;
+        if let Some(published) = &published {
+            if git.divergence("HEAD", published)?.1 > 0 {
+                return Err(GitError::Refused(tr!("update-behind-remote", branch = branch,
+                                                 remote = remote)));
+            }
+        }
+
+        let (_, behind) = git.divergence("HEAD", &trunk)?;
+        if behind == 0 && published == Some(git.rev_parse_head()?) {
+            eprintln!("{}", tr!("update-up-to-date", branch = branch, trunk = trunk));
+            return Ok(());
+        }
+        if behind > 0 && !git.rebase(&trunk)? {
+            let mut message = tr!("update-conflict", branch = branch, trunk = trunk);
+            for path in git.conflicted_paths()? {
+                message.push_str(&format!("\n\t{}", path));
+            }
+            return Err(GitError::Refused(message));
+        }
+
+        let refname = format!("refs/heads/{}", branch);
+        let result = git.push_with_lease(remote, &refname, published.as_deref())
+            .and_then(|pushed| match pushed {
+                true => Ok(()),
+                false => Err(GitError::Refused(tr!("update-lease-lost", branch = branch,
+                                                   remote = remote))),
+            });
+        audit::record(&git, "update", &[branch.clone(), trunk.clone()], &result)?;
+        result?;
+
+        if git.upstream(&branch)?.is_none() {
+            git.set_upstream(remote, &branch)?;
+        }
+        eprintln!("{}", tr!("update-done", branch = branch, trunk = trunk, remote = remote));
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+//! Keep an eye on the remote, reporting PRs as they are opened, updated, and closed
+//!
+//! Watch fetches from the remote (pruning, as `git pr sync` does) every `--interval` seconds, and
+//! prints a line for each PR which has appeared, moved, or disappeared. The first fetch only sets
+//! the baseline, so PRs which were already open aren't reported.
+//!
+//! Rather than listing every PR again after each fetch, watch notices which refs the fetch changed
+//! (see `libgitpr::watch::RefWatcher`) and re-reads only those, so a remote with thousands of PRs
+//! costs no more to watch than a quiet one. Refs changed by anything else, like a `git fetch` in
+//! another terminal, are reported as soon as they change too.
+//!
+//! A failed fetch is reported and the next poll goes ahead as usual, so a flaky network doesn't
+//! end the watch. Ctrl-C (or SIGTERM) stops it cleanly between polls, or in the middle of one.
+use crate::Shared;
+use clap::Args;
+use libgitpr::pull_request::{Change, PrIndex};
+use libgitpr::watch::{RefChange, RefWatcher};
+use libgitpr::{tr, Git, GitError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+
+#[derive(Args)]
+pub struct Watch {
+    /// How long to wait between fetches
+    #[arg(long, value_name = "seconds", default_value_t = 60,
+          value_parser = clap::value_parser!(u64).range(1..))]
+    interval: u64,
+}
+
+impl Watch {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, config) = shared.open("watch")?;
+        let remote = &config.remote.value;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+            signal_hook::flag::register(signal, Arc::clone(&stop))?;
+        }
+
+        let interval = Duration::from_secs(self.interval);
+        let mut live: Option<Live> = None;
+        while !stop.load(Ordering::Relaxed) {
+            let started = Instant::now();
+            match git.fetch_prune() {
+                // Ctrl-C reaches git too, so a fetch it interrupted is no cause for complaint.
+                Err(_) if stop.load(Ordering::Relaxed) => break,
+                Err(e) => eprintln!("{}", tr!("watch-fetch-failed", remote = remote, error = e)),
+                Ok(()) if live.is_none() => {
+                    let baseline = Live::start(&git, remote)?;
+                    eprintln!("{}", tr!("watch-started", remote = remote,
+                                        count = baseline.index().len()));
+                    live = Some(baseline);
+                },
+                Ok(()) => (),
+            }
+
+            // Sleep in short naps, so that Ctrl-C doesn't have to wait out the interval, and so
+            // that PRs are reported as soon as their refs change.
+            loop {
+                if let Some(live) = &mut live {
+                    for change in live.catch_up(&git)? {
+                        println!("{}", describe(&git, &change)?);
+                    }
+                }
+                if stop.load(Ordering::Relaxed) || started.elapsed() >= interval {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+        Ok(())
+    }
+}
+
+// One line about how a PR changed.
+fn describe(git: &Git, change: &Change) -> Result<String,GitError> {
+    Ok(match change {
+        Change::Opened(pr) => tr!("change-opened", name = pr.name),
+        Change::Updated(pr) => tr!("change-updated", name = pr.name,
+                                    commit = git.abbreviate(&pr.tip)?),
+        Change::Closed(pr) => tr!("change-closed", name = pr.name),
+    })
+}
+
+
+/// The PRs on a remote, kept up to date by re-reading only the refs which change.
+pub struct Live {
+    index: PrIndex,
+
+    // Refs which have changed since the last catch-up, reported by the watcher's thread
+    pending: Arc<Mutex<Vec<RefChange>>>,
+    _watcher: RefWatcher,
+}
+
+impl Live {
+    /// Start watching the repository's refs, beginning with the PRs `remote` has now.
+    pub fn start(git: &Git, remote: &str) -> Result<Live,GitError> {
+        let pending = Arc::new(Mutex::new(vec![]));
+        let queue = Arc::clone(&pending);
+        let watcher = RefWatcher::start(git, move |change| {
+            if let Ok(mut queue) = queue.lock() {
+                queue.push(change);
+            }
+        })?;
+        // The watch is already running, so nothing that changes while we read can be missed
+        Ok(Live{ index: PrIndex::load(git, remote)?, pending, _watcher: watcher })
+    }
+
+    pub fn index(&self) -> &PrIndex {
+        &self.index
+    }
+
+    /// Apply the ref changes noticed since last time, returning how the PRs changed.
+    pub fn catch_up(&mut self, git: &Git) -> Result<Vec<Change>,GitError> {
+        let changes: Vec<RefChange> = match self.pending.lock() {
+            Ok(mut pending) => pending.drain(..).collect(),
+            Err(_) => vec![RefChange::All]
+        };
+        if changes.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let before = self.index.clone();
+        if changes.contains(&RefChange::All) {
+            self.index = PrIndex::load(git, before.remote())?;
+        } else {
+            for change in &changes {
+                if let RefChange::Ref(refname) = change {
+                    self.index.refresh(git, refname)?;
+                }
+            }
+        }
+        Ok(self.index.changes_since(&before))
+    }
+}
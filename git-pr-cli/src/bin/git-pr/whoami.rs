@@ -0,0 +1,95 @@
+//! Show who git-pr will say did things
+//!
+//! Claims, handoffs, approvals, and audit entries all record who made them, so they are only as
+//! trustworthy as the identity behind them. This prints the identity git-pr acts as and where each
+//! part of it came from: the name and email (`user.name` and `user.email`, from whichever config
+//! file git found them in), how the mailmap shows them, whether the identity is a bot (see
+//! `libgitpr::identity`; `--as` checks the bot identity that a CI job would claim), and the key
+//! records are signed with (see `libgitpr::signing`).
+//!
+//! Warnings go to stderr when the identity looks like a placeholder (such as `you@example.com`),
+//! when `GIT_AUTHOR_NAME` and friends will put a different name on commits than git-pr puts on
+//! its records, and when metadata is to be signed without a key to sign it with. With no identity
+//! configured at all, this fails, as every command which records something would.
+use crate::Shared;
+use clap::Args;
+use libgitpr::identity::{self, Identity};
+use libgitpr::{signing, tr, GitError};
+
+
+#[derive(Args)]
+pub struct Whoami {
+    /// Check the bot identity that --as <identity> would claim instead
+    #[arg(long = "as", value_name = "identity")]
+    bot: Option<String>,
+}
+
+impl Whoami {
+    pub fn run(self, shared: &Shared) -> Result<(),GitError> {
+        let (git, _) = shared.open("whoami")?;
+
+        let me = match &self.bot {
+            Some(requested) => {
+                let bot = Identity::resolve(&git, Some(requested))?;
+                println!("{}", bot);
+                println!("  {}", tr!("whoami-bot"));
+                bot
+            }
+            None => {
+                let name = git.config_get_with_origin("user.name")?;
+                let email = git.config_get_with_origin("user.email")?;
+                for (key, setting) in [("user.name", &name), ("user.email", &email)] {
+                    if setting.is_none() {
+                        eprintln!("{}", tr!("whoami-unset", key = key));
+                    }
+                }
+                let me = Identity::current(&git)?;
+                println!("{}", me);
+                for (key, setting) in [("user.name", name), ("user.email", email)] {
+                    if let Some((_, origin)) = setting {
+                        println!("  {}", tr!("whoami-from", key = key, origin = origin));
+                    }
+                }
+                println!("  {}", tr!("whoami-person"));
+                me
+            }
+        };
+
+        let canonical = identity::canonicalize(&git, std::slice::from_ref(&me))?.remove(0);
+        if canonical != me {
+            println!("  {}", tr!("whoami-mailmap", identity = canonical));
+        }
+
+        let format = git.config_get("gpg.format")?.unwrap_or_else(|| "openpgp".to_string());
+        let key = git.config_get_with_origin("user.signingKey")?;
+        match &key {
+            Some((key, origin)) => println!("  {}", tr!("whoami-signing-key", key = key,
+                                                        format = format, origin = origin)),
+            None => println!("  {}", tr!("whoami-no-signing-key")),
+        }
+        let signs = signing::enabled(&git)?;
+        if signs {
+            println!("  {}", tr!("whoami-signs-metadata"));
+        }
+
+        if me.is_placeholder() {
+            eprintln!("{}", tr!("whoami-placeholder", identity = me));
+        }
+        if self.bot.is_none() {
+            let environment = [
+                ("GIT_AUTHOR_NAME", &me.name), ("GIT_AUTHOR_EMAIL", &me.email),
+                ("GIT_COMMITTER_NAME", &me.name), ("GIT_COMMITTER_EMAIL", &me.email),
+            ];
+            for (variable, value) in environment {
+                if let Some(set) = std::env::var(variable).ok().filter(|set| set != value) {
+                    eprintln!("{}", tr!("whoami-environment", variable = variable, value = set));
+                }
+            }
+        }
+        // GPG can find a key from the committer's email, but SSH must be told which key to use
+        if signs && key.is_none() && format == "ssh" {
+            eprintln!("{}", tr!("whoami-cannot-sign"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+//! What the standalone `git pr-*` programs have in common
+//!
+//! `git pr` runs these programs for commands it doesn't have built in, and passes their exit
+//! status back to its caller, so they have to use the same exit codes it does (see
+//! `libgitpr::exit`): [`main`] takes care of that for each of them.
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use libgitpr::{exit, GitError};
+use std::process;
+
+
+/// Parse the command line into a `P`, then `run` it.
+///
+/// A bad command line is reported by clap and exits with [`exit::USAGE`], while `--help` exits
+/// successfully. If `run` fails, its error is printed, and the program exits with the error's exit
+/// code (see [`GitError::exit_code`]).
+pub fn main<P: Parser>(run: impl FnOnce(P) -> Result<(),GitError>) {
+    let args = match P::try_parse() {
+        Ok(args) => args,
+        Err(e) => exit_with(e),
+    };
+    if let Err(e) = run(args) {
+        eprintln!("{}", e);
+        process::exit(e.exit_code())
+    }
+}
+
+/// Report a command line that `P` parsed, but which doesn't make sense, and exit with
+/// [`exit::USAGE`].
+pub fn usage_error<P: CommandFactory>(message: String) -> ! {
+    exit_with(P::command().error(ErrorKind::MissingRequiredArgument, message))
+}
+
+fn exit_with(e: clap::Error) -> ! {
+    let _ = e.print();
+    process::exit(match e.use_stderr() {
+        true => exit::USAGE,
+        false => exit::SUCCESS,
+    })
+}
@@ -0,0 +1,204 @@
+//! Compare what the commands print with checked-in golden files.
+//!
+//! Each case runs one `git pr` command in a fixture repository, and compares its stdout, stderr,
+//! and exit status with `tests/golden/<case>.txt`. A change to any command's
+//! output therefore shows up in review as a change to a golden file.
+//!
+//! When output changes on purpose, regenerate the golden files and review the difference:
+//!
+//! ```console
+//! $ GIT_PR_BLESS=1 cargo test --test golden
+//! $ git diff tests/golden
+//! ```
+//!
+//! The fixture is the same every time: commits are made by the same people at the same moments,
+//! so their hashes never change, and the commands run without the user's global git config or
+//! language settings. Paths to the fixture's temporary directories are replaced by `$ORIGIN` and
+//! `$CLONE`. Output that depends on the current time (like a PR's age) can't be golden, so the
+//! cases avoid it.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempdir::TempDir;
+
+
+// A bare origin with a few PRs on it, and a clone of it to run commands in.
+struct Fixture {
+    origin: TempDir,
+    clone: TempDir,
+}
+
+// The environment every git (and git-pr) command in these tests runs with.
+fn isolate(command: &mut Command) -> &mut Command {
+    command
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("LANGUAGE", "")
+        .env("LC_ALL", "C")
+        .env("TZ", "UTC")
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE")
+}
+
+// Run git in `dir`, as if at `day` of January 2024.
+fn git(dir: &Path, day: u32, args: &[&str]) {
+    let date = format!("2024-01-{:02}T12:00:00Z", day);
+    let status = isolate(&mut Command::new("git"))
+        .env("GIT_AUTHOR_DATE", &date)
+        .env("GIT_COMMITTER_DATE", &date)
+        .arg("-C").arg(dir).args(args)
+        .output().unwrap().status;
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn fixture() -> Fixture {
+    let origin = TempDir::new("git-pr-golden-origin").unwrap();
+    let clone = TempDir::new("git-pr-golden-clone").unwrap();
+    let work = clone.path();
+    git(origin.path(), 1, &["init","--quiet","--bare","--initial-branch=trunk"]);
+    git(work, 1, &["init","--quiet","--initial-branch=trunk"]);
+    git(work, 1, &["config","user.name","Alice Example"]);
+    git(work, 1, &["config","user.email","alice@example.com"]);
+    git(work, 1, &["remote","add","origin",&origin.path().to_string_lossy()]);
+    git(work, 1, &["commit","--quiet","--allow-empty","-m","Initial commit"]);
+    git(work, 1, &["push","--quiet","-u","origin","trunk"]);
+
+    // Each PR is a commit on top of trunk, pushed as <name>/<hash of trunk>
+    let prs = [("hotfix", 2), ("docs/typo", 3), ("feature", 4), ("dependabot/cargo/serde", 5)];
+    for (name, day) in prs {
+        let output = isolate(&mut Command::new("git"))
+            .arg("-C").arg(work).args(["rev-parse","--short","trunk"]).output().unwrap();
+        let branch = format!("{}/{}", name, String::from_utf8_lossy(&output.stdout).trim());
+        git(work, day, &["checkout","--quiet","-b",&branch,"trunk"]);
+        git(work, day, &["commit","--quiet","--allow-empty","-m",&format!("Work on {}", name)]);
+        git(work, day, &["push","--quiet","-u","origin",&branch]);
+    }
+    git(work, 6, &["checkout","--quiet","trunk"]);
+
+    Fixture{ origin, clone }
+}
+
+// Describe a run of `git pr-<command> <args>` the way the golden files do.
+fn run(fixture: &Fixture, command: &str, args: &[&str]) -> String {
+    let output = isolate(&mut Command::new(env!("CARGO_BIN_EXE_git-pr")))
+        .current_dir(fixture.clone.path()).arg(command).args(args)
+        .output().unwrap();
+
+    let command_line: Vec<String> = std::iter::once(format!("git pr {}", command))
+        .chain(args.iter().map(|arg| arg.to_string()))
+        .collect();
+    let report = format!(
+        "$ {}\n--- stdout\n{}--- stderr\n{}--- exit status: {}\n",
+        command_line.join(" "),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+        output.status.code().map_or("none".to_string(), |code| code.to_string()),
+    );
+    report
+        .replace(&*fixture.origin.path().to_string_lossy(), "$ORIGIN")
+        .replace(&*fixture.clone.path().to_string_lossy(), "$CLONE")
+}
+
+// Check a case against its golden file, or rewrite the golden file if asked to.
+fn golden(case: &str, command: &str, args: &[&str]) {
+    let actual = run(&fixture(), command, args);
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", &format!("{}.txt", case)]
+        .iter().collect();
+
+    if env::var_os("GIT_PR_BLESS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_default();
+    assert!(actual == expected,
+            "output differs from {}; if that's intended, rerun with GIT_PR_BLESS=1\n\
+             --- expected\n{}--- actual\n{}", path.display(), expected, actual);
+}
+
+
+#[test]
+fn list() {
+    golden("list", "list", &[]);
+}
+
+#[test]
+fn list_authors() {
+    golden("list-authors", "list", &["--authors"]);
+}
+
+#[test]
+fn list_porcelain() {
+    golden("list-porcelain", "list", &["--porcelain"]);
+}
+
+#[test]
+fn list_format() {
+    golden("list-format", "list", &["--format", r"{branch}\t{short}\t{author}"]);
+}
+
+#[test]
+fn list_bad_format() {
+    golden("list-bad-format", "list", &["--format", "{nmae}"]);
+}
+
+#[test]
+fn list_bad_query() {
+    golden("list-bad-query", "list", &["--query", "age>30d and colour:red"]);
+}
+
+#[test]
+fn list_conflicting_flags() {
+    golden("list-conflicting-flags", "list", &["--json", "--porcelain"]);
+}
+
+#[test]
+fn list_verbose() {
+    golden("list-verbose", "list", &["--verbose", "--trunk", "main"]);
+}
+
+#[test]
+fn status_on_trunk() {
+    golden("status-on-trunk", "status", &[]);
+}
+
+#[test]
+fn status_porcelain() {
+    golden("status-porcelain", "status", &["--porcelain"]);
+}
+
+#[test]
+fn env_as_json() {
+    golden("env", "env", &[]);
+}
+
+#[test]
+fn create_without_a_name() {
+    golden("create-without-a-name", "create", &[]);
+}
+
+#[test]
+fn compare_misspelled_pr() {
+    golden("compare-misspelled-pr", "compare", &["hotfx", "feature"]);
+}
+
+#[test]
+fn checkout_misspelled_pr() {
+    golden("checkout-misspelled-pr", "checkout", &["hotifx"]);
+}
+
+#[test]
+fn clean_bad_limit() {
+    golden("clean-bad-limit", "clean", &["--limit", "none"]);
+}
+
+#[test]
+fn clean_read_only() {
+    golden("clean-read-only", "clean", &["--read-only"]);
+}
+
+#[test]
+fn ui_without_a_terminal() {
+    golden("ui-without-a-terminal", "ui", &[]);
+}
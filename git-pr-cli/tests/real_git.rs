@@ -0,0 +1,2716 @@
+//! Run the git-pr commands against repositories made with the real git binary.
+use libgitpr::Git;
+use libgitpr::audit::AuditLog;
+use libgitpr::rpc;
+use libgitpr::metadata;
+use std::path::Path;
+use std::process::Command;
+use std::process::Output;
+use std::process::Stdio;
+use tempdir::TempDir;
+
+// Implementing this above produces a warning, since the function is (by design) never used by
+// other application code. Since it is only used in this module, we implement this function
+// local to this module, thus eliminating the dead code warning.
+fn temp_repo() -> Git {
+    let working_dir = Box::new(TempDir::new("git-pr").unwrap());
+    let dir = working_dir.as_ref().as_ref();
+
+    // git init in new unique dir
+    git(dir, &["init"]);
+
+    // Setup git config for email and name
+    git(dir, &["config","user.email","you@example.com"]);
+    git(dir, &["config","user.name","Your Name"]);
+
+    // create trunk branch, with an empty commit to actually create it
+    git(dir, &["checkout","-b","trunk"]);
+    git(dir, &["commit","--allow-empty","-m","hello"]);
+
+    // create a fake branch to test deletion
+    git(dir, &["branch","hotfix"]);
+
+    Git{ program: "git".to_string(), working_dir, git_dir: None, work_tree: None }
+}
+
+
+// git-pr programs run with GIT_DIR pointing elsewhere should act on that repository, not on
+// whatever directory they were started from.
+#[test]
+fn respect_git_dir_from_environment() {
+    let repo = temp_repo();
+    let elsewhere = TempDir::new("git-pr-elsewhere").unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_git-pr")).arg("clean")
+        .current_dir(elsewhere.path())
+        .env("GIT_DIR", repo.working_dir.as_ref().as_ref().join(".git"))
+        .stdout(Stdio::null())
+        .status().unwrap();
+    assert!(status.success());
+    assert!(!repo.all_branches().unwrap().contains("hotfix"));
+
+    // Relative paths are relative to where we started, not to the top of the repository
+    let dir = repo.working_dir.as_ref().as_ref().canonicalize().unwrap();
+    git(&dir, &["config","pr.trunk","custom-trunk"]);
+    let name = dir.file_name().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_git-pr-env"))
+        .current_dir(dir.parent().unwrap())
+        .env("GIT_DIR", Path::new(name).join(".git"))
+        .env("GIT_WORK_TREE", name)
+        .output().unwrap();
+    assert!(printed(output).contains("custom-trunk"));
+}
+
+// Worktree-private state must not leak between worktrees, and pr-clean must leave alone any branch
+// which is checked out in another worktree.
+#[test]
+fn secondary_worktrees_are_respected() {
+    let git = temp_repo();
+    let top = git.working_dir.as_ref().as_ref().canonicalize().unwrap();
+    let other_dir = TempDir::new("git-pr-worktree").unwrap();
+    let other_path = other_dir.path().join("hotfix");
+
+    let status = Command::new("git")
+        .arg("-C").arg(&top)
+        .args(["worktree","add","--quiet"]).arg(&other_path).arg("hotfix")
+        .status().unwrap();
+    assert!(status.success());
+    let other = Git{
+        program: "git".to_string(), working_dir: Box::new(other_path),
+        git_dir: None, work_tree: None,
+    };
+
+    assert_ne!(git.git_dir().unwrap(), other.git_dir().unwrap());
+    assert_eq!(
+        git.git_common_dir().unwrap().canonicalize().unwrap(),
+        other.git_common_dir().unwrap().canonicalize().unwrap()
+    );
+
+    let merged = git.merged_branches("trunk").unwrap();
+    assert!(libgitpr::extract_deletable_branches(&merged, "trunk").is_empty());
+
+    let status = git_pr(&top, &["clean"]).status;
+    assert!(status.success());
+    assert!(git.all_branches().unwrap().contains("hotfix"));
+}
+
+// Send one message to git pr-daemon.
+fn send(input: &mut impl std::io::Write, body: &str) {
+    write!(input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+}
+
+// Read git pr-daemon's response. Notifications may come at any time, so skip any which arrive
+// before it.
+fn reply(output: &mut impl std::io::BufRead) -> String {
+    loop {
+        let message = rpc::read_message(output).unwrap().unwrap();
+        if !message.contains("pullRequestsChanged") {
+            return message;
+        }
+    }
+}
+
+// Talk to the daemon the way an editor plugin would. When a new PR shows up, the daemon should
+// tell us without being asked, and then include it the next time we ask for the list.
+#[test]
+fn daemon_answers_over_stdio() {
+    use std::io::BufReader;
+
+    let repo = temp_repo();
+    let mut daemon = Command::new(env!("CARGO_BIN_EXE_git-pr-daemon"))
+        .arg("--stdio")
+        .current_dir(repo.working_dir.as_ref().as_ref())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn().unwrap();
+    let mut input = daemon.stdin.take().unwrap();
+    let mut output = BufReader::new(daemon.stdout.take().unwrap());
+
+    send(&mut input, r#"{"jsonrpc":"2.0","id":1,"method":"env"}"#);
+    let response = reply(&mut output);
+    assert!(response.contains(r#""id":1"#));
+    assert!(response.contains(r#""trunk""#));
+
+    send(&mut input, r#"{"jsonrpc":"2.0","id":2,"method":"list"}"#);
+    let response = reply(&mut output);
+    assert!(response.contains(r#""result":[]"#));
+
+    git(repo.working_dir.as_ref().as_ref(),
+        &["update-ref","refs/remotes/origin/new-idea/abc123","HEAD"]);
+    let notification = rpc::read_message(&mut output).unwrap().unwrap();
+    assert!(notification.contains("pullRequestsChanged"));
+
+    send(&mut input, r#"{"jsonrpc":"2.0","id":3,"method":"list"}"#);
+    let response = reply(&mut output);
+    assert!(response.contains(r#""result":["new-idea"]"#), "{}", response);
+
+    send(&mut input, r#"{"jsonrpc":"2.0","method":"exit"}"#);
+    assert!(daemon.wait().unwrap().success());
+}
+
+// An editor plugin can review a PR through the daemon, with the same checks as the commands each
+// method stands in for.
+#[test]
+fn daemon_reviews_prs() {
+    use serde_json::{json, Value};
+    use std::io::BufReader;
+
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    std::fs::write(dir.join("feature.txt"), "feature\n").unwrap();
+    git(dir, &["add","feature.txt"]);
+    git(dir, &["commit","--quiet","-m","Add a feature"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+
+    let mut daemon = Command::new(env!("CARGO_BIN_EXE_git-pr-daemon")).arg("--stdio")
+        .current_dir(dir).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    let mut input = daemon.stdin.take().unwrap();
+    let mut output = BufReader::new(daemon.stdout.take().unwrap());
+    let mut ask = |method: &str, params: Value| {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        send(&mut input, &request.to_string());
+        serde_json::from_str::<Value>(&reply(&mut output)).unwrap()
+    };
+
+    let diff = ask("diff", json!({ "name": "feature" }));
+    assert!(diff["result"].as_str().unwrap().contains("+feature"), "{}", diff);
+    let stat = ask("diffstat", json!({ "name": "feature" }));
+    assert!(stat["result"].as_str().unwrap().contains("feature.txt | 1 +"), "{}", stat);
+    assert_eq!(ask("diffstat", json!({ "name": "feature" })), stat);
+    assert_eq!(ask("comment", json!({ "name": "feature", "text": "Looks\ngood" }))["result"],
+               Value::Null);
+    assert_eq!(ask("approve", json!({ "name": "feature" }))["result"], true);
+    assert_eq!(ask("approve", json!({ "name": "feature" }))["result"], false);
+
+    // Errors read as they would on the command line
+    let missing = ask("approve", json!({ "name": "featrue" }));
+    let message = missing["error"]["message"].as_str().unwrap();
+    assert!(message.contains("featrue") && !message.contains("Refused("), "{}", message);
+    git(dir, &["config","pr.readOnly","true"]);
+    assert!(ask("comment", json!({ "name": "feature", "text": "Again" }))["error"].is_object());
+
+    send(&mut input, r#"{"jsonrpc":"2.0","method":"exit"}"#);
+    assert!(daemon.wait().unwrap().success());
+    assert!(printed(git_pr(dir, &["timeline","feature"])).contains("Looks good"));
+    assert!(printed(git_pr(dir, &["reviews","feature"])).contains("you@example.com"));
+    let entries = AuditLog::open(&clone).unwrap().entries().unwrap();
+    let commands: Vec<&str> = entries.iter().map(|entry| entry.command.as_str()).collect();
+    assert!(commands.ends_with(&["comment", "approve", "approve"]), "{:?}", commands);
+}
+
+// pr-clean should leave behind a metrics file describing what it did.
+#[test]
+fn clean_writes_metrics() {
+    let git = temp_repo();
+    let metrics = TempDir::new("git-pr-metrics").unwrap();
+    let path = metrics.path().join("git-pr.prom");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_git-pr")).arg("clean")
+        .current_dir(git.working_dir.as_ref().as_ref())
+        .arg("--metrics-file").arg(&path)
+        .stdout(Stdio::null())
+        .status().unwrap();
+    assert!(status.success());
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.contains("gitpr_open_pull_requests 0\n"));
+    assert!(text.contains("gitpr_clean_deleted_branches 1\n"));
+    assert!(text.contains("gitpr_clean_remaining_branches 0\n"));
+}
+
+// Mutating programs leave a trail in the audit log, whether or not they succeed.
+#[test]
+fn clean_is_audited() {
+    let git = temp_repo();
+    let status = git_pr(git.working_dir.as_ref().as_ref(), &["clean"]).status;
+    assert!(status.success());
+
+    let entries = AuditLog::open(&git).unwrap().entries().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].command, "clean");
+    assert_eq!(entries[0].who, "Your Name <you@example.com>");
+    assert_eq!(entries[0].refs, vec!["hotfix"]);
+    assert_eq!(entries[0].outcome, "ok");
+}
+
+// Two PRs touching the same file should be reported as overlapping.
+#[test]
+fn compare_two_prs() {
+    let repo = temp_repo();
+    let dir = repo.working_dir.as_ref().as_ref();
+
+    let prs = [("one/1", ["shared.txt", "one.txt"]), ("two/2", ["shared.txt", "two.txt"])];
+    for (pr, files) in prs {
+        git(dir, &["checkout","-q","trunk"]);
+        for file in files {
+            std::fs::write(dir.join(file), pr).unwrap();
+        }
+        git(dir, &["add","."]);
+        git(dir, &["commit","-q","-m",pr]);
+        git(dir, &["update-ref",&format!("refs/remotes/origin/{}", pr),"HEAD"]);
+        git(dir, &["reset","-q","--hard","HEAD~1"]);
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-pr-compare"))
+        .current_dir(dir).args(["one","two"]).output().unwrap();
+    assert!(output.status.success());
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("Changed by both (1):\n  shared.txt\n"));
+    assert!(text.contains("Only changed by one/1 (1):\n  one.txt\n"));
+    assert!(text.contains("Overlap: 33%"));
+}
+
+// The standalone programs report errors as git pr does, with the same exit codes.
+#[test]
+fn standalone_programs_exit_like_git_pr() {
+    let outside = TempDir::new("git-pr-outside").unwrap();
+    for program in [env!("CARGO_BIN_EXE_git-pr-env"), env!("CARGO_BIN_EXE_git-pr-audit")] {
+        let output = Command::new(program).current_dir(outside.path()).output().unwrap();
+        assert_eq!(output.status.code(), Some(libgitpr::exit::FATAL), "{}", program);
+        assert!(!String::from_utf8_lossy(&output.stderr).starts_with("Error: "), "{}", program);
+    }
+
+    let programs = [
+        env!("CARGO_BIN_EXE_git-pr-alias"), env!("CARGO_BIN_EXE_git-pr-audit"),
+        env!("CARGO_BIN_EXE_git-pr-bench"), env!("CARGO_BIN_EXE_git-pr-compare"),
+        env!("CARGO_BIN_EXE_git-pr-daemon"), env!("CARGO_BIN_EXE_git-pr-emergency-merge"),
+        env!("CARGO_BIN_EXE_git-pr-env"), env!("CARGO_BIN_EXE_git-pr-export-patchwork"),
+        env!("CARGO_BIN_EXE_git-pr-import-gerrit"), env!("CARGO_BIN_EXE_git-pr-maintain"),
+        env!("CARGO_BIN_EXE_git-pr-migrate-metadata"), env!("CARGO_BIN_EXE_git-pr-mirror"),
+        env!("CARGO_BIN_EXE_git-pr-selftest"),
+    ];
+    for program in programs {
+        let output = Command::new(program).current_dir(outside.path())
+            .arg("--no-such-flag").output().unwrap();
+        assert_eq!(output.status.code(), Some(libgitpr::exit::USAGE), "{}", program);
+    }
+}
+
+// Archive tags are only expired once a retention period is configured, and never in a dry run.
+// They are deleted from the remote too, as are notes about commits which no longer exist.
+#[test]
+fn maintain_expires_archives() {
+    let origin = temp_repo();
+    let repo = clone_repo(&origin);
+    let dir = repo.working_dir.as_ref().as_ref();
+    let maintain = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_git-pr-maintain"))
+            .current_dir(dir).args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    let archive = |name: &str| {
+        git(dir, &["tag","-a","-m","archived",&format!("pr-archive/{}", name)]);
+        git(dir, &["push","origin",&format!("refs/tags/pr-archive/{}", name)]);
+    };
+    archive("hotfix/1234567");
+
+    assert_eq!(maintain(&[]), "");
+    git(dir, &["config","pr.archiveRetentionDays","0"]);
+    assert_eq!(maintain(&["--dry-run"]), "expire refs/tags/pr-archive/hotfix/1234567\n");
+    assert!(repo.resolve_ref("refs/tags/pr-archive/hotfix/1234567").unwrap().is_some());
+
+    maintain(&[]);
+    assert!(repo.resolve_ref("refs/tags/pr-archive/hotfix/1234567").unwrap().is_none());
+    assert!(origin.resolve_ref("refs/tags/pr-archive/hotfix/1234567").unwrap().is_none());
+    let entries = AuditLog::open(&repo).unwrap().entries().unwrap();
+    assert_eq!(entries[0].refs, vec!["refs/tags/pr-archive/hotfix/1234567"]);
+
+    // A limited run expires one chunk, and the next picks up after it
+    archive("a/1");
+    archive("b/2");
+    let metrics = dir.join("maintain.prom");
+    let metrics_file = metrics.display().to_string();
+    assert_eq!(maintain(&["--limit","1","--metrics-file",&metrics_file]),
+               "expire refs/tags/pr-archive/a/1\n");
+    let text = std::fs::read_to_string(&metrics).unwrap();
+    assert!(text.contains("gitpr_maintain_expired_archives 1\n"), "{}", text);
+    assert!(text.contains("gitpr_maintain_remaining_archives 1\n"), "{}", text);
+    assert!(origin.resolve_ref("refs/tags/pr-archive/b/2").unwrap().is_some());
+    assert_eq!(maintain(&["--limit","1"]), "expire refs/tags/pr-archive/b/2\n");
+    assert!(origin.resolve_ref("refs/tags/pr-archive/b/2").unwrap().is_none());
+
+    // A commit which was never pushed is gone, but its metadata was published
+    git(dir, &["checkout","--quiet","-b","work-lost"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Lost"]);
+    let lost = repo.resolve_ref("HEAD").unwrap().unwrap();
+    metadata::append(&repo, "origin", &lost, "comment Never pushed").unwrap();
+    git(dir, &["checkout","--quiet","trunk"]);
+    git(dir, &["branch","-D","work-lost"]);
+    git(dir, &["reflog","expire","--expire=now","--all"]);
+    git(dir, &["gc","--quiet","--prune=now"]);
+    assert_eq!(maintain(&[]), format!("prune note for {}\n", lost));
+    let notes = Command::new("git").arg("-C").arg(origin.working_dir.as_ref().as_ref())
+        .args(["notes","--ref=pr","list"]).output().unwrap();
+    assert!(!String::from_utf8_lossy(&notes.stdout).contains(&lost));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-pr-maintain")).current_dir(dir)
+        .args(["--limit","0"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(libgitpr::exit::USAGE));
+}
+
+// Clone `origin` into a fresh directory, with an identity so that notes can be committed.
+fn clone_repo(origin: &Git) -> Git {
+    let url = format!("file://{}", origin.working_dir.as_ref().as_ref().display());
+    let dir = TempDir::new("git-pr-clone").unwrap();
+    git(Path::new("."), &["clone","--quiet",&url,&dir.path().to_string_lossy()]);
+    for (key, value) in [("user.name", "Your Name"), ("user.email", "you@example.com")] {
+        git(dir.path(), &["config",key,value]);
+    }
+    Git{ program: "git".to_string(), working_dir: Box::new(dir), git_dir: None, work_tree: None }
+}
+
+// Run git in `dir`, insisting that it succeeds, and return what it printed.
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output().unwrap();
+    assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+// Run git pr in `dir`, leaving it to the caller to check how that went.
+fn git_pr(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_git-pr")).current_dir(dir).args(args).output().unwrap()
+}
+
+// Insist that a git pr command succeeded, and return what it printed.
+fn printed(output: Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+// Have `repo` sign its metadata with a throwaway SSH key, which git trusts for each of
+// `principals`. The key lives as long as the directory returned.
+fn sign_metadata(repo: &Git, principals: &[&str]) -> TempDir {
+    let keys = TempDir::new("git-pr-keys").unwrap();
+    let key = keys.path().join("id_ed25519");
+    let status = Command::new("ssh-keygen").args(["-q","-t","ed25519","-N","","-f"]).arg(&key)
+        .status().unwrap();
+    assert!(status.success());
+    let public_key = std::fs::read_to_string(key.with_extension("pub")).unwrap();
+    let allowed = keys.path().join("allowed_signers");
+    std::fs::write(&allowed, format!("{} {}", principals.join(","), public_key)).unwrap();
+    for (name, value) in [
+        ("gpg.format", "ssh".to_string()),
+        ("user.signingKey", key.display().to_string()),
+        ("gpg.ssh.allowedSignersFile", allowed.display().to_string()),
+        ("pr.signMetadata", "true".to_string()),
+    ] {
+        git(repo.working_dir.as_ref().as_ref(), &["config",name,&value]);
+    }
+    keys
+}
+
+// A PR published with git pr create can be withdrawn with git pr abandon.
+#[test]
+fn create_then_abandon() {
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    assert!(git_pr(dir, &["create", "feature"]).status.success());
+    let branch = format!("refs/heads/feature/{}", clone.rev_parse_head().unwrap());
+    assert!(origin.resolve_ref(&branch).unwrap().is_some());
+
+    assert_eq!(git_pr(dir, &["abandon", "featrue"]).status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(git_pr(dir, &["abandon", "feature"]).status.success());
+    assert!(origin.resolve_ref(&branch).unwrap().is_none());
+}
+
+// exists, create, and abandon ask the remote what it has, rather than trusting the last fetch.
+#[test]
+fn probe_the_remote_without_fetching() {
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let refused = Some(libgitpr::exit::REFUSED);
+
+    // Published after the clone, and never fetched
+    git(origin.working_dir.as_ref().as_ref(), &["branch","late/abc123"]);
+    assert!(git_pr(dir, &["exists", "late"]).status.success());
+    assert!(git_pr(dir, &["exists", "late/abc123"]).status.success());
+    assert_eq!(git_pr(dir, &["exists", "lat"]).status.code(), refused);
+    assert!(git_pr(dir, &["abandon", "late"]).status.success());
+    assert_eq!(git_pr(dir, &["exists", "late"]).status.code(), refused);
+
+    // The same PR can't be published twice from the same commit
+    assert!(git_pr(dir, &["create", "feature"]).status.success());
+    let branch = format!("feature/{}", clone.rev_parse_head().unwrap());
+    git(dir, &["checkout","--quiet","trunk"]);
+    clone.delete_branch(&branch).unwrap();
+    assert_eq!(git_pr(dir, &["create", "feature"]).status.code(), refused);
+    assert!(clone.resolve_ref(&format!("refs/heads/{}", branch)).unwrap().is_none());
+}
+
+// Checking out a PR makes a local branch which tracks it, but only if the name is unambiguous.
+#[test]
+fn checkout_tracks_the_pr_branch() {
+    let origin = temp_repo();
+    for branch in ["feature/abc123", "feature/def456", "docs/abc123"] {
+        git(origin.working_dir.as_ref().as_ref(), &["branch",branch]);
+    }
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let upstream = || git(dir, &["rev-parse","--abbrev-ref","HEAD","@{upstream}"]);
+
+    let output = git_pr(dir, &["checkout", "feature"]);
+    assert_eq!(output.status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("feature/def456"));
+    assert_eq!(upstream(), "trunk\norigin/trunk\n");
+
+    assert!(git_pr(dir, &["checkout", "docs"]).status.success());
+    assert_eq!(upstream(), "docs/abc123\norigin/docs/abc123\n");
+
+    // The second time, the branch is already here
+    assert!(git_pr(dir, &["checkout", "feature/def456"]).status.success());
+    assert!(git_pr(dir, &["checkout", "docs/abc123"]).status.success());
+    assert_eq!(upstream(), "docs/abc123\norigin/docs/abc123\n");
+}
+
+// git pr show compares a PR with where it left trunk, so later changes to trunk aren't included.
+#[test]
+fn show_only_the_prs_changes() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","trunk"]);
+    git(dir, &["checkout","--quiet","-b","work"]);
+    std::fs::write(dir.join("feature.txt"), "feature").unwrap();
+    git(dir, &["add","feature.txt"]);
+    git(dir, &["commit","--quiet","-m","Add the feature"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    git(dir, &["checkout","--quiet","trunk"]);
+    std::fs::write(dir.join("unrelated.txt"), "unrelated").unwrap();
+    git(dir, &["add","unrelated.txt"]);
+    git(dir, &["commit","--quiet","-m","Change trunk"]);
+    git(dir, &["push","--quiet","origin","trunk"]);
+
+    assert_eq!(printed(git_pr(dir, &["show", "--name-only", "feature"])), "feature.txt\n");
+    let stat = printed(git_pr(dir, &["show", "--stat", "feature"]));
+    assert!(stat.contains("Add the feature") && stat.contains("feature.txt | 1 +"));
+    assert!(!stat.contains("Change trunk") && !stat.contains("unrelated.txt"));
+    assert!(printed(git_pr(dir, &["show", "feature"])).contains("+feature"));
+}
+
+// git pr log lists the PR's own commits, and none of trunk's.
+#[test]
+fn log_only_the_prs_commits() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","-b","work"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","First step"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Second step"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    git(dir, &["checkout","--quiet","trunk"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Change trunk"]);
+    git(dir, &["push","--quiet","origin","trunk"]);
+
+    let output = git_pr(dir, &["log", "feature"]);
+    assert!(output.status.success());
+    let subjects: Vec<String> = String::from_utf8_lossy(&output.stdout).lines()
+        .map(|line| line.split_once(' ').unwrap().1.to_string()).collect();
+    assert_eq!(subjects, vec!["Second step", "First step"]);
+    assert_eq!(git_pr(dir, &["log", "featrue"]).status.code(), Some(libgitpr::exit::REFUSED));
+}
+
+// git pr search finds PRs by name, or by what their commits say, as a substring or a regex.
+#[test]
+fn search_names_and_commits() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","trunk"]);
+    for (name, subject) in [("login", "Fix the Login page"), ("cache", "Cache sessions")] {
+        git(dir, &["checkout","--quiet","-b",&format!("work-on-{}", name),"trunk"]);
+        git(dir, &["commit","--quiet","--allow-empty","-m",subject]);
+        assert!(git_pr(dir, &["create",name]).status.success());
+    }
+
+    assert_eq!(printed(git_pr(dir, &["search", "--format", "{name} {matched}", "LOGIN"])),
+               "login name,branch,commits\n");
+    assert_eq!(printed(git_pr(dir, &["search", "--format", "{name}", "session"])), "cache\n");
+    let regex = "^(cache|login)$";
+    assert_eq!(printed(git_pr(dir, &["search", "--format", "{name}", "--regex", regex])),
+               "cache\nlogin\n");
+    assert_eq!(printed(git_pr(dir, &["search", "--format", "{name}", "you@example"])),
+               "cache\nlogin\n");
+    assert_eq!(printed(git_pr(dir, &["search", "nothing-like-this"])), "");
+    assert_eq!(git_pr(dir, &["search", "--regex", "(unclosed"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+}
+
+// git pr whoami says where the identity came from, and warns about placeholders and bots.
+#[test]
+fn whoami_explains_the_identity() {
+    let repo = temp_repo();
+    let dir = repo.working_dir.as_ref().as_ref();
+    let whoami = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_git-pr")).current_dir(dir)
+            .arg("whoami").args(args)
+            .env_remove("GIT_AUTHOR_NAME").env_remove("GIT_AUTHOR_EMAIL")
+            .env_remove("GIT_COMMITTER_NAME").env_remove("GIT_COMMITTER_EMAIL")
+            .output().unwrap();
+        (output.status.code(), String::from_utf8_lossy(&output.stdout).to_string(),
+         String::from_utf8_lossy(&output.stderr).to_string())
+    };
+
+    let (code, stdout, stderr) = whoami(&[]);
+    assert_eq!(code, Some(0));
+    assert!(stdout.starts_with("Your Name <you@example.com>\n"));
+    assert!(stdout.contains("user.email from local file:.git/config"));
+    assert!(stderr.contains("looks like a placeholder"));
+
+    git(dir, &["config", "user.email", "alice@tanglewood.test"]);
+    git(dir, &["config", "user.name", "Alice"]);
+    let (_, _, stderr) = whoami(&[]);
+    assert_eq!(stderr, "");
+
+    git(dir, &["config", "--add", "pr.botIdentity", "CI Bot <ci@tanglewood.test>"]);
+    let (code, stdout, _) = whoami(&["--as", "CI Bot"]);
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("a bot, listed in pr.botIdentity"));
+    assert_eq!(whoami(&["--as", "Mallory"]).0, Some(libgitpr::exit::REFUSED));
+
+    git(dir, &["config", "--unset", "user.email"]);
+    let (code, _, stderr) = whoami(&[]);
+    assert_eq!(code, Some(libgitpr::exit::REFUSED));
+    assert!(stderr.contains("user.email is not configured"));
+}
+
+// git pr init records the remote and trunk, adding the remote and pushing trunk if asked to.
+#[test]
+fn init_a_repository() {
+    let origin = TempDir::new("git-pr-origin").unwrap();
+    assert!(Command::new("git").args(["init","--quiet","--bare"]).arg(origin.path())
+        .status().unwrap().success());
+    let work = temp_repo();
+    let dir = work.working_dir.as_ref().as_ref();
+
+    assert_eq!(git_pr(dir, &["--trunk", "main", "init"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    let url = origin.path().to_str().unwrap();
+    assert!(git_pr(dir, &["--trunk", "main", "init", "--url", url]).status.success());
+    assert_eq!(work.config_get("pr.trunk").unwrap().as_deref(), Some("main"));
+    assert_eq!(work.config_get("pr.remote").unwrap().as_deref(), Some("origin"));
+    assert!(work.ls_remote_heads("origin", "main").unwrap().is_empty());
+
+    assert!(git_pr(dir, &["--trunk", "main", "init", "--create-trunk"]).status.success());
+    let head = work.rev_parse_head().unwrap();
+    assert!(work.ls_remote_heads("origin", "main").unwrap().starts_with(&head));
+    assert!(git_pr(dir, &["--trunk", "main", "init", "--create-trunk"]).status.success());
+
+    git(dir, &["remote","set-url","origin","/nowhere"]);
+    assert_eq!(git_pr(dir, &["--trunk", "main", "init"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+}
+
+// A profile's settings apply when pr.profile or --profile names it, and say where they came from.
+#[test]
+fn profiles_bundle_settings() {
+    let repo = temp_repo();
+    let dir = repo.working_dir.as_ref().as_ref();
+    let env = |args: &[&str]| {
+        let output = git_pr(dir, &[args, &["env"]].concat());
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    git(dir, &["config", "pr.profile.work.remote", "corp"]);
+    git(dir, &["config", "pr.profile.work.role.lead.member", "you@example.com"]);
+    git(dir, &["config", "pr.profile.work.role.lead.allow", "env"]);
+    assert_eq!(env(&[])["remote"]["value"], "origin");
+
+    let json = env(&["--profile", "work"]);
+    assert_eq!(json["remote"]["value"], "corp");
+    assert_eq!(json["remote"]["source"], "git config pr.profile.work.remote");
+    assert_eq!(json["profile"]["value"], "work");
+    assert_eq!(git_pr(dir, &["--profile", "work", "stats"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    assert!(git_pr(dir, &["stats"]).status.success());
+    assert_eq!(git_pr(dir, &["--profile", "nope", "env"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+
+    git(dir, &["config", "pr.profile", "work"]);
+    assert_eq!(env(&[])["remote"]["value"], "corp");
+    let json = env(&["--remote", "upstream"]);
+    assert_eq!(json["remote"]["value"], "upstream");
+    assert_eq!(json["remote"]["source"], "git config pr.remote");
+}
+
+// git pr doctor passes a working clone, and points out what is missing from other setups.
+#[test]
+fn doctor_diagnoses_setups() {
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let nowhere = TempDir::new("git-pr-nowhere").unwrap();
+    let doctor = |dir: &std::path::Path, args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_git-pr")).current_dir(dir)
+            .args(args).arg("doctor").env("GIT_CEILING_DIRECTORIES", nowhere.path())
+            .stderr(Stdio::null()).output().unwrap();
+        (output.status.code(), String::from_utf8_lossy(&output.stdout).to_string())
+    };
+    let outcomes = |report: &str| report.lines()
+        .map(|line| line.split_whitespace().next().unwrap().to_string())
+        .collect::<Vec<_>>();
+
+    let (code, report) = doctor(clone.working_dir.as_ref().as_ref(), &[]);
+    assert_eq!(code, Some(0), "{}", report);
+    assert_eq!(outcomes(&report), ["ok"; 6]);
+
+    let (code, report) = doctor(clone.working_dir.as_ref().as_ref(), &["--trunk", "main"]);
+    assert_eq!(code, Some(libgitpr::exit::REFUSED));
+    assert_eq!(outcomes(&report), ["ok", "ok", "ok", "ok", "FAIL", "skip"]);
+    assert!(report.contains("has no branch named main (command line --trunk)"));
+
+    let (_, report) = doctor(origin.working_dir.as_ref().as_ref(), &[]);
+    assert_eq!(outcomes(&report), ["ok", "ok", "ok", "FAIL", "skip", "skip"]);
+
+    let (code, report) = doctor(nowhere.path(), &[]);
+    assert_eq!(code, Some(libgitpr::exit::REFUSED));
+    assert_eq!(outcomes(&report), ["ok", "FAIL", "skip", "skip", "skip", "skip"]);
+}
+
+// git pr timeline puts a PR's commits and metadata in order, and can draw them as a graph.
+#[test]
+fn timeline_of_a_pr() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","-b","work"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","First step"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Second step"]);
+    printed(git_pr(dir, &["create","feature"]));
+    let tip = clone.rev_parse_head().unwrap();
+    metadata::append(&clone, "origin", &tip, "approved-by Bob <bob@example.com>").unwrap();
+    printed(git_pr(dir, &["take","feature"]));
+
+    let timeline = printed(git_pr(dir, &["timeline","feature"]));
+    let texts: Vec<&str> = timeline.lines().map(|line| line[15..].trim()).collect();
+    assert_eq!(texts.len(), 4);
+    assert!(texts[0].starts_with("First step (Your Name <you@example.com>, "));
+    assert!(texts[1].starts_with("Second step"));
+    assert_eq!(&texts[2..], ["approved by Bob <bob@example.com>",
+                             "claimed by Your Name <you@example.com>"]);
+
+    let graph = printed(git_pr(dir, &["timeline","--graph","feature"]));
+    let nodes: Vec<&str> = graph.lines().map(|line| line.get(..3).unwrap_or(line)).collect();
+    assert_eq!(nodes, ["*  ", "*  ", "|\\", "| o", "| o"]);
+}
+
+// git pr watch reports PRs as they come and go, and stops cleanly on Ctrl-C.
+#[test]
+fn watch_reports_changes() {
+    use std::io::{BufRead, BufReader};
+
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let dir = origin.working_dir.as_ref().as_ref();
+    let mut watch = Command::new(env!("CARGO_BIN_EXE_git-pr"))
+        .current_dir(clone.working_dir.as_ref().as_ref())
+        .args(["watch","--interval","1"])
+        .stdout(Stdio::piped()).stderr(Stdio::piped())
+        .spawn().unwrap();
+    let mut stdout = BufReader::new(watch.stdout.take().unwrap()).lines();
+    let mut stderr = BufReader::new(watch.stderr.take().unwrap()).lines();
+    assert!(stderr.next().unwrap().unwrap().starts_with("Watching origin, which has 0 PRs"));
+
+    git(dir, &["branch", "idea/abc123"]);
+    assert_eq!(stdout.next().unwrap().unwrap(), "idea opened");
+    git(dir, &["branch","-D","idea/abc123"]);
+    assert_eq!(stdout.next().unwrap().unwrap(), "idea closed");
+
+    let status = Command::new("kill").args(["-INT",&watch.id().to_string()]).status().unwrap();
+    assert!(status.success());
+    assert!(watch.wait().unwrap().success());
+}
+
+// git pr digest sums up the day's PRs, and what is waiting for whom.
+#[test]
+fn digest_of_the_day() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","trunk"]);
+    git(dir, &["checkout","--quiet","-b","work"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Fix the login page"]);
+    assert!(git_pr(dir, &["create","login"]).status.success());
+
+    let output = git_pr(dir, &["digest", "--daily", "--to", "team@example.com"]);
+    assert!(output.status.success());
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.starts_with("To: team@example.com\nSubject: PR digest for origin, "));
+    assert!(text.contains("\nNew PRs (1)\n  - login (Your Name <you@example.com>, "));
+    assert!(text.contains("\nMerged (0)\n  Nothing\n"));
+    assert!(text.contains("\nWaiting for you (0)\n  Nothing\n"));
+
+    let output = git_pr(dir, &["digest", "--markdown", "--for", "Someone Else <else@example.com>"]);
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.starts_with("# PR digest for origin"));
+    assert!(text.contains("\n## Waiting for you (1)\n\n- `login` (Your Name <you@example.com>, "));
+    assert_eq!(git_pr(dir, &["digest", "--for", "nobody"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+}
+
+// Files attached with git pr attach can be listed and saved from another clone.
+#[test]
+fn attach_and_save_files() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let author = clone_repo(&origin);
+    let reviewer = clone_repo(&origin);
+    let run = |clone: &Git, program: &str, args: &[&str]| Command::new(program)
+        .current_dir(clone.working_dir.as_ref().as_ref()).args(args)
+        .stderr(Stdio::null()).output().unwrap();
+    let git_pr = env!("CARGO_BIN_EXE_git-pr");
+
+    assert!(run(&author, "git", &["checkout","--quiet","trunk"]).status.success());
+    assert!(run(&author, "git", &["commit","--quiet","--allow-empty","-m","Restyle the login page"])
+        .status.success());
+    assert!(run(&author, git_pr, &["create","login"]).status.success());
+    let shot = author.working_dir.as_ref().as_ref().join("shot.png");
+    std::fs::write(&shot, [0x89, b'P', b'N', b'G', 0, 255, 1, 2]).unwrap();
+    let output = run(&author, git_pr, &["attach","login","shot.png"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Attached shot.png (8 bytes)"));
+
+    // Too large a file is refused, and nothing is attached
+    std::fs::write(&shot, vec![0; 2048]).unwrap();
+    assert!(run(&author, "git", &["config","pr.maxAttachmentKiB","1"]).status.success());
+    assert_eq!(run(&author, git_pr, &["attach","login","shot.png"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    assert_eq!(run(&author, git_pr, &["attach","login","missing.png"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+
+    let saved = TempDir::new("git-pr-attachments").unwrap();
+    let output = run(&reviewer, git_pr, &["attachments","login","--save",
+                                          saved.path().to_str().unwrap()]);
+    assert!(output.status.success());
+    let listing = String::from_utf8_lossy(&output.stdout);
+    assert!(listing.ends_with("  shot.png (8 bytes)\n"));
+    assert_eq!(listing.lines().count(), 1);
+    assert_eq!(std::fs::read(saved.path().join("shot.png")).unwrap(),
+               [0x89, b'P', b'N', b'G', 0, 255, 1, 2]);
+    let timeline = run(&reviewer, git_pr, &["timeline","login"]);
+    assert!(String::from_utf8_lossy(&timeline.stdout).contains("attached shot.png (8 bytes)"));
+}
+
+// git pr export writes a PR out as a numbered series of patches, or as an mbox.
+#[test]
+fn export_a_patch_series() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","trunk"]);
+    assert_eq!(git_pr(dir, &["export", "login"]).status.code(), Some(libgitpr::exit::REFUSED));
+    for subject in ["Fix the login page", "Test the login page"] {
+        std::fs::write(dir.join("login.txt"), subject).unwrap();
+        git(dir, &["add","login.txt"]);
+        git(dir, &["commit","--quiet","-m",subject]);
+    }
+    assert!(git_pr(dir, &["create","login"]).status.success());
+    git(dir, &["reset","--quiet","--hard","origin/trunk"]);
+
+    let output = git_pr(dir, &["export", "login"]);
+    assert!(output.status.success());
+    let listing = String::from_utf8_lossy(&output.stdout);
+    assert!(listing.contains("login/0001-Fix-the-login-page.patch\n"));
+    assert!(listing.contains("login/0002-Test-the-login-page.patch\n"));
+    let patch = std::fs::read_to_string(dir.join("login/0002-Test-the-login-page.patch")).unwrap();
+    assert!(patch.contains("Subject: [PATCH 2/2] Test the login page"));
+
+    assert!(git_pr(dir, &["export","login","--mbox","--reroll-count","2","--cover-letter"])
+        .status.success());
+    let mbox = std::fs::read_to_string(dir.join("login-v2.mbox")).unwrap();
+    assert!(mbox.contains("Subject: [PATCH v2 0/2]"));
+    assert!(mbox.contains("Subject: [PATCH v2 2/2] Test the login page"));
+}
+
+// git pr send hands the PR's series to git send-email, with the cover letter filled in.
+#[test]
+fn send_a_pr_by_email() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    // Stand in for git send-email, which records what it would have sent
+    let bin = TempDir::new("git-pr-bin").unwrap();
+    let sent = bin.path().join("sent");
+    let script = bin.path().join("git-send-email");
+    std::fs::write(&script, "#!/bin/sh\nfor arg; do case \"$arg\" in\n\
+        *.patch) cat \"$arg\" >> \"$SENT\";;\n*) echo \"$arg\" >> \"$SENT.args\";;\nesac; done\n")
+        .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.path().display(), std::env::var("PATH").unwrap());
+
+    git(dir, &["checkout","--quiet","trunk"]);
+    for message in ["Fix the login page\n\nIt hung on Safari.", "Test the login page"] {
+        std::fs::write(dir.join("login.txt"), message).unwrap();
+        git(dir, &["add","login.txt"]);
+        git(dir, &["commit","--quiet","-m",message]);
+    }
+    assert!(git_pr(dir, &["create","login"]).status.success());
+    git(dir, &["reset","--quiet","--hard","origin/trunk"]);
+
+    let status = Command::new(env!("CARGO_BIN_EXE_git-pr")).current_dir(dir)
+        .args(["send","login","--to","list@example.com","--reroll-count","2","--","--dry-run"])
+        .env("PATH", path).env("SENT", &sent).status().unwrap();
+    assert!(status.success());
+    let args = std::fs::read_to_string(bin.path().join("sent.args")).unwrap();
+    assert_eq!(args, "--to=list@example.com\n--dry-run\n--\n");
+    let mail = std::fs::read_to_string(&sent).unwrap();
+    assert!(mail.contains("Subject: [PATCH v2 0/2] login\n\nIt hung on Safari.\n"), "{}", mail);
+    assert!(mail.contains("Subject: [PATCH v2 2/2] Test the login page"), "{}", mail);
+    assert!(std::fs::read_dir(clone.git_dir().unwrap().join("git-pr")).unwrap()
+        .all(|entry| !entry.unwrap().file_name().to_string_lossy().starts_with("send-")));
+}
+
+// git pr describe-request writes the summary git request-pull would, pointing at the PR's branch.
+#[test]
+fn describe_a_pull_request() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","trunk"]);
+    std::fs::write(dir.join("login.txt"), "login").unwrap();
+    git(dir, &["add","login.txt"]);
+    git(dir, &["commit","--quiet","-m","Fix the login page"]);
+    assert!(git_pr(dir, &["create","login"]).status.success());
+    let branch = format!("login/{}", clone.rev_parse_head().unwrap());
+    git(dir, &["reset","--quiet","--hard","origin/trunk"]);
+
+    let output = git_pr(dir, &["describe-request", "login"]);
+    assert!(output.status.success());
+    let summary = String::from_utf8_lossy(&output.stdout);
+    let url = clone.config_get("remote.origin.url").unwrap().unwrap();
+    assert!(summary.contains(&format!("  {} {}\n", url, branch)), "{}", summary);
+    assert!(summary.contains("      Fix the login page\n"), "{}", summary);
+    assert!(summary.contains(" 1 file changed"), "{}", summary);
+    assert!(!summary.contains("diff --git"));
+
+    let output = git_pr(dir, &["describe-request","login","--patch",
+                               "--url","https://example.com/mirror.git"]);
+    let summary = String::from_utf8_lossy(&output.stdout);
+    assert!(summary.contains(&format!("  https://example.com/mirror.git {}\n", branch)));
+    assert!(summary.contains("diff --git a/login.txt b/login.txt"), "{}", summary);
+}
+
+// With pr.encrypt set, comments are stored encrypted, and only key holders can read them.
+#[test]
+fn encrypted_comments() {
+    if Command::new("gpg").arg("--version").output().is_err() {
+        return;
+    }
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let keys = TempDir::new("git-pr-gnupg").unwrap();
+    let strangers = TempDir::new("git-pr-gnupg").unwrap();
+    let run = |program: &str, args: &[&str], home: &std::path::Path| Command::new(program)
+        .current_dir(dir).env("GNUPGHOME", home).args(args)
+        .stderr(Stdio::null()).output().unwrap();
+    let git_pr = env!("CARGO_BIN_EXE_git-pr");
+
+    assert!(run("gpg", &["--batch","--passphrase","","--quick-gen-key",
+                         "Reviewer <reviewer@example.com>","future-default","default","never"],
+                keys.path()).status.success());
+    assert!(run("git", &["checkout","--quiet","trunk"], keys.path()).status.success());
+    assert!(run("git", &["commit","--quiet","--allow-empty","-m","Plan"], keys.path())
+        .status.success());
+    assert!(run(git_pr, &["create","plan"], keys.path()).status.success());
+    assert!(run("git", &["config","pr.encrypt","gpg"], keys.path()).status.success());
+    assert_eq!(run(git_pr, &["comment","plan","The","launch","is","Tuesday"], keys.path())
+               .status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(run("git", &["config","pr.encryptTo","reviewer@example.com"], keys.path())
+        .status.success());
+    assert!(run(git_pr, &["comment","plan","The","launch","is","Tuesday"], keys.path())
+        .status.success());
+
+    let stored = run("git", &["notes","--ref=pr","show","HEAD"], keys.path());
+    let stored = String::from_utf8_lossy(&stored.stdout).to_string();
+    assert!(stored.contains("comment encrypted:gpg:"));
+    assert!(!stored.contains("Tuesday"));
+
+    let timeline = run(git_pr, &["timeline","plan"], keys.path());
+    assert!(String::from_utf8_lossy(&timeline.stdout).contains("comment: The launch is Tuesday"));
+    let timeline = run(git_pr, &["timeline","plan"], strangers.path());
+    assert!(String::from_utf8_lossy(&timeline.stdout)
+        .contains("comment: (encrypted, and you hold none of its keys)"));
+    for home in [keys.path(), strangers.path()] {
+        run("gpgconf", &["--kill","gpg-agent"], home);
+    }
+}
+
+// git pr import publishes a patch series as a PR, without touching the working tree.
+#[test]
+fn import_a_patch_series() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","-b","emailed","origin/trunk"]);
+    for subject in ["Fix the login page", "Test the login page"] {
+        std::fs::write(dir.join("login.txt"), subject).unwrap();
+        git(dir, &["add","login.txt"]);
+        git(dir, &["commit","--quiet","-m",subject]);
+    }
+    git(dir, &["format-patch","--quiet","--cover-letter","-o","series","origin/trunk"]);
+    let mbox = git(dir, &["format-patch","--cover-letter","--stdout","origin/trunk"]);
+    std::fs::write(dir.join("series.mbox"), mbox).unwrap();
+    git(dir, &["checkout","--quiet","trunk"]);
+
+    let output = git_pr(dir, &["import","login","series"]);
+    assert!(output.status.success());
+    let branch = String::from_utf8_lossy(&output.stdout).lines()
+        .find_map(|line| line.strip_prefix("Created ")?.strip_suffix(" on origin"))
+        .unwrap().to_string();
+    assert!(branch.starts_with("login/"));
+    assert_eq!(git(dir, &["log","--format=%s","origin/trunk.."]), "");
+    let log = git(dir, &["log","--format=%s",&format!("origin/trunk..origin/{}", branch)]);
+    assert_eq!(log, "Test the login page\nFix the login page\n");
+    assert_eq!(git(dir, &["worktree","list"]).lines().count(), 1);
+    assert_eq!(clone.current_branch().unwrap().as_deref(), Some("refs/heads/trunk"));
+
+    assert!(git_pr(dir, &["import","again","series.mbox"]).status.success());
+    assert_eq!(git_pr(dir, &["import","empty","."]).status.code(), Some(libgitpr::exit::REFUSED));
+
+    // Without the first patch, the second has nothing to apply to, and nothing is created
+    std::fs::remove_file(dir.join("series/0001-Fix-the-login-page.patch")).unwrap();
+    assert_eq!(git_pr(dir, &["import","broken","series"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    assert!(!git(dir, &["ls-remote","origin"]).contains("broken/"));
+    assert_eq!(git(dir, &["worktree","list"]).lines().count(), 1);
+}
+
+// git pr serve shows the open PRs, and each one's timeline and diff, to a browser.
+#[test]
+fn serve_prs_over_http() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    git(dir, &["checkout","--quiet","trunk"]);
+    std::fs::write(dir.join("page.html"), "<b>bold</b>").unwrap();
+    git(dir, &["add","page.html"]);
+    git(dir, &["commit","--quiet","-m","Add <b> to the page"]);
+    assert!(git_pr(dir, &["create","bold"]).status.success());
+    let branch = format!("bold/{}", clone.rev_parse_head().unwrap());
+    assert!(git_pr(dir, &["ci","set","bold","--artifact","log=https://ci.example.com/1?a&b",
+               "--artifact","sneaky=javascript:alert(1)"]).status.success());
+
+    let mut server = Command::new(env!("CARGO_BIN_EXE_git-pr")).current_dir(dir)
+        .args(["serve","--listen","127.0.0.1:0"]).stdout(Stdio::piped()).stderr(Stdio::null())
+        .spawn().unwrap();
+    let mut banner = String::new();
+    BufReader::new(server.stdout.take().unwrap()).read_line(&mut banner).unwrap();
+    let address = banner.trim().trim_end_matches('/').rsplit("//").next().unwrap().to_string();
+    let get = |method: &str, path: &str| {
+        let mut stream = std::net::TcpStream::connect(&address).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: test\r\n\r\n", method, path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+
+    let front = get("GET", "/");
+    let link = format!("<a href=\"/pr/{}\">bold</a>", branch.replace('/', "%2F"));
+    let pr_page = get("GET", &format!("/pr/{}", branch.replace('/', "%2F")));
+    let missing = get("GET", "/pr/nope");
+    let posted = get("POST", "/");
+    let metrics = get("GET", "/metrics");
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    assert!(front.starts_with("HTTP/1.0 200 OK\r\n"), "{}", front);
+    assert!(front.contains(&link), "{}", front);
+    assert!(front.contains("Add &lt;b&gt; to the page"), "{}", front);
+    assert!(pr_page.starts_with("HTTP/1.0 200 OK\r\n"), "{}", pr_page);
+    assert!(pr_page.contains("+&lt;b&gt;bold&lt;/b&gt;"), "{}", pr_page);
+    assert!(pr_page.contains("<a href=\"https://ci.example.com/1?a&amp;b\">log</a>"));
+    assert!(pr_page.contains("<li>sneaky: javascript:alert(1)</li>"), "{}", pr_page);
+    assert!(missing.starts_with("HTTP/1.0 404 "), "{}", missing);
+    assert!(posted.starts_with("HTTP/1.0 405 "), "{}", posted);
+    assert!(metrics.contains("Content-Type: text/plain; version=0.0.4\r\n"), "{}", metrics);
+    assert!(metrics.contains("\ngitpr_open_pull_requests 1\n"), "{}", metrics);
+    assert!(metrics.contains("\ngitpr_claimed_pull_requests 0\n"), "{}", metrics);
+}
+
+// git pr squash-preview shows the commit git pr land would make, and --edit changes its message
+// for the real thing.
+#[test]
+fn preview_and_edit_a_squash() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(origin_dir, &["checkout","--quiet","--detach"]);
+    git(dir, &["checkout","--quiet","-b","work","trunk"]);
+    std::fs::write(dir.join("one"), "one").unwrap();
+    git(dir, &["add","one"]);
+    git(dir, &["commit","--quiet","-m","Add one\n\nIt was missing.\n\nSigned-off-by: A <a@x>"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Tidy up\n\nSigned-off-by: A <a@x>"]);
+    let status = git_pr(dir, &["create","feature"]).status;
+    assert!(status.success());
+    git(dir, &["checkout","--quiet","trunk"]);
+
+    let output = git_pr(dir, &["squash-preview","feature"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let preview = String::from_utf8(output.stdout).unwrap();
+    assert!(preview.contains("\n\nIt was missing.\n\n"), "{}", preview);
+    assert!(preview.contains("Add one\n") && preview.contains("Tidy up\n"));
+    assert_eq!(preview.matches("Signed-off-by: A <a@x>").count(), 1, "{}", preview);
+    assert!(preview.contains("1 file changed"), "{}", preview);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-pr")).current_dir(dir)
+        .args(["squash-preview","--edit","feature"])
+        .env("GIT_EDITOR", "sed -i -e '1s/.*/Add the one file/'").output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let preview = String::from_utf8(output.stdout).unwrap();
+    assert!(preview.starts_with("Add the one file\n"), "{}", preview);
+    assert!(!preview.contains('#'), "{}", preview);
+
+    let status = git_pr(dir, &["land","feature"]).status;
+    assert!(status.success());
+    let landed = origin.resolve_ref("refs/heads/trunk").unwrap().unwrap();
+    let message = clone.log(&landed, &["-1","--format=%B"]).unwrap();
+    assert!(message.starts_with("Add the one file\n"), "{}", message);
+    assert!(std::fs::read_dir(clone.git_common_dir().unwrap().join("git-pr").join("squash"))
+        .unwrap().next().is_none());
+}
+
+// git pr status follows a PR from creation, through unpushed work, to being merged.
+#[test]
+fn status_of_the_current_pr() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let format = ["status", "--format", "{name} {ahead} {behind} {unpushed} {merged}"];
+
+    git(dir, &["checkout","--quiet","trunk"]);
+    printed(git_pr(dir, &["create", "feature"]));
+    git(dir, &["commit","--quiet","--allow-empty","-m","Work"]);
+    assert_eq!(printed(git_pr(dir, &format)), "feature 1 0 1 false\n");
+    assert!(printed(git_pr(dir, &["status"])).contains("1 commits not pushed to origin/feature/"));
+
+    // Someone else merges it on the remote; we don't know until we fetch
+    git(dir, &["push","--quiet"]);
+    let tip = clone.resolve_ref("HEAD").unwrap().unwrap();
+    origin.update_ref("refs/heads/trunk", &tip).unwrap();
+    assert_eq!(printed(git_pr(dir, &format)), "feature 1 0 0 false\n");
+    assert_eq!(printed(git_pr(dir, &["status", "--fetch", "--format", "{ahead} {merged}"])),
+               "0 true\n");
+}
+
+// A PR claimed with git pr take is shown in git pr list, and can't be taken by someone else until
+// the claim is released or expires.
+#[test]
+fn take_a_pr() {
+    let origin = temp_repo();
+    let alice = clone_repo(&origin);
+    let alice_dir = alice.working_dir.as_ref().as_ref();
+    let bob = clone_repo(&origin);
+    let bob_dir = bob.working_dir.as_ref().as_ref();
+    git(bob_dir, &["config","user.name","Bob"]);
+    git(bob_dir, &["config","user.email","bob@example.com"]);
+    assert!(git_pr(alice_dir, &["create","feature"]).status.success());
+
+    assert!(git_pr(bob_dir, &["take","feature"]).status.success());
+    let list = git_pr(alice_dir, &["list","--format","{name} {claimed}"]);
+    assert_eq!(String::from_utf8_lossy(&list.stdout), "feature Bob <bob@example.com>\n");
+    let taken = git_pr(alice_dir, &["take","feature"]);
+    assert_eq!(taken.status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(String::from_utf8_lossy(&taken.stderr).contains("Bob <bob@example.com>"));
+    assert_eq!(git_pr(alice_dir, &["take","--release","feature"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+
+    assert!(git_pr(bob_dir, &["take","--release","feature"]).status.success());
+    assert!(git_pr(alice_dir, &["take","feature"]).status.success());
+    assert!(git_pr(bob_dir, &["take","--force","feature"]).status.success());
+
+    // A claim which has expired gets in nobody's way
+    git(bob_dir, &["config","pr.claimHours","0"]);
+    assert!(git_pr(bob_dir, &["take","--release","feature"]).status.success());
+    assert!(git_pr(bob_dir, &["take","feature"]).status.success());
+    let list = git_pr(alice_dir, &["list","--porcelain"]);
+    assert_eq!(String::from_utf8_lossy(&list.stdout), "feature\t\n");
+}
+
+// git pr handoff gives a PR a new owner, which git pr list shows, and releases the new owner's
+// claim to review it.
+#[test]
+fn hand_a_pr_off() {
+    let origin = temp_repo();
+    let alice = clone_repo(&origin);
+    let alice_dir = alice.working_dir.as_ref().as_ref();
+    let bob = clone_repo(&origin);
+    let bob_dir = bob.working_dir.as_ref().as_ref();
+    git(bob_dir, &["config","user.name","Bob"]);
+    git(bob_dir, &["config","user.email","bob@example.com"]);
+    assert!(git_pr(alice_dir, &["create","feature"]).status.success());
+    let owner = |repo: &Git| String::from_utf8_lossy(
+        &git_pr(repo.working_dir.as_ref().as_ref(), &["list","--format","{owner} {claimed}"]).stdout
+    ).to_string();
+    assert_eq!(owner(&bob), "Your Name <you@example.com> \n");
+
+    assert!(git_pr(bob_dir, &["take","feature"]).status.success());
+    assert_eq!(git_pr(alice_dir, &["handoff","feature","Bob"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    let handoff = git_pr(alice_dir, &["handoff","feature","Bob <bob@example.com>"]);
+    assert!(handoff.status.success());
+    assert!(String::from_utf8_lossy(&handoff.stderr).contains("claim on"));
+    assert_eq!(owner(&bob), "Bob <bob@example.com> \n");
+    assert_eq!(git_pr(bob_dir, &["handoff","feature","Bob <bob@example.com>"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+}
+
+// git pr assign --auto picks whichever reviewer has the fewest open assignments, and git pr
+// unassign takes people off again.
+#[test]
+fn assign_and_unassign_reviewers() {
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    const BOB: &str = "Bob <bob@example.com>";
+    const CAROL: &str = "Carol <carol@example.com>";
+    for name in ["one", "two"] {
+        git(dir, &["commit","--quiet","--allow-empty","-m",name]);
+        assert!(git_pr(dir, &["create",name]).status.success());
+    }
+    let stderr = |output: std::process::Output| String::from_utf8_lossy(&output.stderr).to_string();
+
+    assert_eq!(git_pr(dir, &["assign","--auto","one"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    git(dir, &["config","--add","pr.reviewer",BOB]);
+    git(dir, &["config","--add","pr.reviewer",CAROL]);
+    assert_eq!(git_pr(dir, &["assign","one","Bob"]).status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(git_pr(dir, &["assign","one",BOB]).status.success());
+    assert_eq!(git_pr(dir, &["assign","one",BOB]).status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(stderr(git_pr(dir, &["assign","--auto","two"])).contains(&format!("to {}", CAROL)));
+    assert!(stderr(git_pr(dir, &["assign","--auto","one"])).contains(&format!("to {}", CAROL)));
+    assert_eq!(git_pr(dir, &["assign","--auto","one"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+
+    assert!(git_pr(dir, &["unassign","one",BOB]).status.success());
+    assert_eq!(git_pr(dir, &["unassign","one",BOB]).status.code(), Some(libgitpr::exit::REFUSED));
+    let timeline = String::from_utf8_lossy(&git_pr(dir, &["timeline","one"]).stdout).to_string();
+    assert!(timeline.contains(&format!("assigned to {}", BOB)), "{}", timeline);
+    assert!(timeline.contains(&format!("{} unassigned", BOB)), "{}", timeline);
+    assert!(stderr(git_pr(dir, &["assign","--auto","two"])).contains(&format!("to {}", BOB)));
+}
+
+// Reviewers who are away are passed over by git pr assign --auto until they are back.
+#[test]
+fn away_reviewers_are_passed_over() {
+    let origin = temp_repo();
+    let alice = clone_repo(&origin);
+    let alice_dir = alice.working_dir.as_ref().as_ref();
+    let bob = clone_repo(&origin);
+    let bob_dir = bob.working_dir.as_ref().as_ref();
+    const BOB: &str = "Bob <bob@example.com>";
+    const CAROL: &str = "Carol <carol@example.com>";
+    git(bob_dir, &["config","user.name","Bob"]);
+    git(bob_dir, &["config","user.email","bob@example.com"]);
+    git(alice_dir, &["config","--add","pr.reviewer",BOB]);
+    git(alice_dir, &["config","--add","pr.reviewer",CAROL]);
+    for name in ["one", "two"] {
+        git(alice_dir, &["commit","--quiet","--allow-empty","-m",name]);
+        assert!(git_pr(alice_dir, &["create",name]).status.success());
+    }
+    let stderr = |output: std::process::Output| String::from_utf8_lossy(&output.stderr).to_string();
+
+    assert_eq!(git_pr(bob_dir, &["away","--until","2000-01-01"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    assert!(git_pr(bob_dir, &["away","--until","2099-01-01"]).status.success());
+    let reviewers = String::from_utf8_lossy(&git_pr(alice_dir, &["reviewers"]).stdout).to_string();
+    assert!(reviewers.starts_with(&format!("{}\t0 assigned\taway until ", BOB)), "{}", reviewers);
+    assert!(reviewers.ends_with(&format!("{}\t0 assigned\n", CAROL)), "{}", reviewers);
+    assert!(stderr(git_pr(alice_dir, &["assign","--auto","one"])).contains(CAROL));
+    assert!(stderr(git_pr(alice_dir, &["assign","--auto","two"])).contains(CAROL));
+
+    assert!(git_pr(bob_dir, &["away","--back"]).status.success());
+    assert!(stderr(git_pr(alice_dir, &["assign","--auto","two"])).contains(BOB));
+    let reviewers = String::from_utf8_lossy(&git_pr(alice_dir, &["reviewers"]).stdout).to_string();
+    assert_eq!(reviewers, format!("{}\t1 assigned\n{}\t2 assigned\n", BOB, CAROL));
+}
+
+// git pr label sorts PRs, and git pr batch abandons, rebases, or merges every PR with a label.
+#[test]
+fn batch_operations_by_label() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","trunk"]);
+    let mut branches = vec![];
+    for name in ["old", "older", "docs"] {
+        std::fs::write(dir.join(name), name).unwrap();
+        git(dir, &["add",name]);
+        git(dir, &["commit","--quiet","-m",name]);
+        assert!(git_pr(dir, &["create",name]).status.success());
+        branches.push(format!("{}/{}", name, clone.rev_parse_head().unwrap()));
+        git(dir, &["checkout","--quiet","trunk"]);
+        git(dir, &["reset","--quiet","--hard","origin/trunk"]);
+    }
+    assert!(git_pr(dir, &["label","old","superseded"]).status.success());
+    assert!(git_pr(dir, &["label","older","superseded","cleanup"]).status.success());
+    assert!(git_pr(dir, &["label","older","cleanup","--remove"]).status.success());
+    assert!(git_pr(dir, &["label","docs","cleanup"]).status.success());
+    assert_eq!(git_pr(dir, &["label","docs","two words"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    let labels = git_pr(dir, &["list","--format","{name} {labels}"]);
+    let labels = String::from_utf8_lossy(&labels.stdout)
+        .to_string();
+    assert_eq!(labels, "docs cleanup\nold superseded\nolder superseded\n");
+
+    // Nobody is there to confirm, so nothing happens without --yes
+    let output = git_pr(dir, &["batch","--label","superseded","abandon"]);
+    assert_eq!(output.status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(origin.resolve_ref(&format!("refs/heads/{}", branches[0])).unwrap().is_some());
+    let output = git_pr(dir, &["batch","--label","superseded","abandon","--yes"]);
+    assert!(output.status.success());
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(report, format!("{}: abandoned\n{}: abandoned\n", branches[0], branches[1]));
+    for branch in &branches[..2] {
+        assert!(origin.resolve_ref(&format!("refs/heads/{}", branch)).unwrap().is_none());
+    }
+
+    std::fs::write(dir.join("trunk"), "trunk").unwrap();
+    git(dir, &["add","trunk"]);
+    git(dir, &["commit","--quiet","-m","Move trunk on"]);
+    git(dir, &["push","--quiet","origin","trunk"]);
+    let output = git_pr(dir, &["batch","--label","cleanup","rebase","--yes"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("rebased onto origin/trunk"));
+    let docs = origin.resolve_ref(&format!("refs/heads/{}", branches[2])).unwrap().unwrap();
+    let trunk = origin.resolve_ref("refs/heads/trunk").unwrap().unwrap();
+    assert_eq!(origin.merge_base(&trunk, &docs).unwrap(), Some(trunk));
+    assert!(String::from_utf8_lossy(&git_pr(dir, &["timeline","docs"]).stdout)
+        .contains("labeled cleanup"));
+
+    assert!(git_pr(dir, &["batch","--label","cleanup","merge","--yes"]).status.success());
+    assert!(origin.file_at("trunk", "docs").unwrap().is_some());
+}
+
+// git pr conflicts tries the merge away from the working tree, and lists the paths which clash.
+#[test]
+fn conflicts_are_found_without_merging() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","trunk"]);
+    for (name, path) in [("clash", "shared.txt"), ("fine", "other.txt")] {
+        std::fs::write(dir.join(path), name).unwrap();
+        git(dir, &["add",path]);
+        git(dir, &["commit","--quiet","-m",name]);
+        assert!(git_pr(dir, &["create",name]).status.success());
+        git(dir, &["checkout","--quiet","trunk"]);
+        git(dir, &["reset","--quiet","--hard","origin/trunk"]);
+    }
+    std::fs::write(dir.join("shared.txt"), "trunk").unwrap();
+    git(dir, &["add","shared.txt"]);
+    git(dir, &["commit","--quiet","-m","Trunk's version"]);
+    git(dir, &["push","--quiet","origin","trunk"]);
+    let head = clone.rev_parse_head().unwrap();
+
+    let output = git_pr(dir, &["conflicts","clash"]);
+    assert_eq!(output.status.code(), Some(libgitpr::exit::REFUSED));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "shared.txt\n");
+    let output = git_pr(dir, &["conflicts","fine"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    assert_eq!(clone.rev_parse_head().unwrap(), head);
+    let status = Command::new("git").arg("-C").arg(dir).args(["status","--porcelain"])
+        .output().unwrap();
+    assert!(status.stdout.is_empty());
+}
+
+// git pr ci set records how a PR's build went and where its artifacts are, for git pr show.
+#[test]
+fn ci_results_and_artifacts() {
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(clone.working_dir.as_ref().as_ref(), &["commit","--quiet","--allow-empty","-m","Work"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    assert!(git_pr(dir, &["ci","set","feature","--status","pass",
+                     "--artifact","log=https://ci.example.com/1/log",
+                     "--artifact","coverage=https://ci.example.com/1/coverage"]).status.success());
+    assert!(git_pr(dir, &["ci","set","feature","--artifact","log=https://ci.example.com/2/log",
+                     "--check","tests=pass","--check","coverage=fail,lines=61.5"])
+        .status.success());
+    assert_eq!(git_pr(dir, &["ci","set","feature","--check","coverage=pass,lines=most"])
+                   .status.code(),
+               Some(libgitpr::exit::REFUSED));
+    assert_eq!(git_pr(dir, &["ci","set","feature","--artifact","log"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    assert!(!git_pr(dir, &["ci","set","feature","--status","exploded"]).status.success());
+
+    let shown = String::from_utf8_lossy(&git_pr(dir, &["show","feature"]).stdout).to_string();
+    assert!(shown.starts_with("CI: pass\n    \
+                               Check     Status  Metrics\n    \
+                               coverage  fail    lines=61.5\n    \
+                               tests     pass\n    \
+                               coverage: https://ci.example.com/1/coverage\n    \
+                               log: https://ci.example.com/2/log\n\ncommit "), "{}", shown);
+    let timeline = git_pr(dir, &["timeline","feature"]);
+    let timeline = String::from_utf8_lossy(&timeline.stdout).to_string();
+    assert!(timeline.contains("CI published log: https://ci.example.com/2/log"), "{}", timeline);
+}
+
+// git pr show prints the PR itself for scripts, like git pr list does, instead of its changes.
+#[test]
+fn show_for_scripts() {
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    let tip = clone.resolve_ref("HEAD").unwrap().unwrap();
+
+    let output = git_pr(dir, &["show","feature","--format","{name} {branch} {state}"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+               format!("feature feature/{} open\n", &tip[..7]));
+    let output = git_pr(dir, &["show","feature","--porcelain"]);
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with(
+        &format!("feature\tfeature/{}\t{}\t", &tip[..7], tip)));
+    let output = git_pr(dir, &["show","feature","--json"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"author\":\"Your Name"));
+    assert_eq!(git_pr(dir, &["show","feature","--format","{nope}"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    assert!(!git_pr(dir, &["show","feature","--stat","--json"]).status.success());
+}
+
+// In a partial clone, git pr show fetches the files a PR's diff needs at once, and if it can't,
+// shows a summary of the diff instead.
+#[test]
+fn show_in_a_partial_clone() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    git(origin_dir, &["config","uploadpack.allowFilter","true"]);
+    git(dir, &["checkout","--quiet","-b","work-feature"]);
+    std::fs::write(dir.join("feature.txt"), "A feature\n").unwrap();
+    git(dir, &["add","feature.txt"]);
+    git(dir, &["commit","--quiet","-m","Add a feature"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+
+    let url = format!("file://{}", origin.working_dir.as_ref().as_ref().display());
+    let partial_clone = || {
+        let partial = TempDir::new("git-pr-partial").unwrap();
+        git(partial.path(), &["clone","--quiet","--filter=blob:none",&url,"."]);
+        partial
+    };
+    let show = |partial: &TempDir| {
+        let output = git_pr(partial.path(), &["show","feature"]);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        (String::from_utf8_lossy(&output.stdout).to_string(),
+         String::from_utf8_lossy(&output.stderr).to_string())
+    };
+
+    let partial = partial_clone();
+    let (stdout, stderr) = show(&partial);
+    assert!(stderr.contains("Fetching 1 missing objects"), "{}", stderr);
+    assert!(stdout.contains("+A feature"), "{}", stdout);
+
+    // The promised files are somewhere which can't be reached, though git can still get them
+    let partial = partial_clone();
+    git(partial.path(), &["config","--unset","remote.origin.promisor"]);
+    git(partial.path(), &["config","remote.attic.url","/nonexistent"]);
+    git(partial.path(), &["config","remote.attic.promisor","true"]);
+    let (stdout, stderr) = show(&partial);
+    assert!(stderr.contains("only a summary is shown"), "{}", stderr);
+    assert!(stdout.contains(" feature.txt | 1 +"), "{}", stdout);
+    assert!(!stdout.contains("+A feature"), "{}", stdout);
+}
+
+// git pr amend publishes a new revision of the current PR, and can archive the old one.
+#[test]
+fn amend_publishes_a_new_revision() {
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    assert_eq!(git_pr(dir, &["amend"]).status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    let first = format!("feature/{}", clone.rev_parse_head().unwrap());
+    assert_eq!(git_pr(dir, &["amend"]).status.code(), Some(libgitpr::exit::REFUSED));
+
+    assert!(git_pr(dir, &["describe",&first,"-m","Make it faster"]).status.success());
+    git(dir, &["commit","--quiet","--allow-empty","-m","Address review"]);
+    assert!(git_pr(dir, &["amend"]).status.success());
+    let second = format!("feature/{}", clone.rev_parse_head().unwrap());
+    assert_eq!(clone.current_branch().unwrap(), Some(format!("refs/heads/{}", second)));
+    assert!(origin.resolve_ref(&format!("refs/heads/{}", first)).unwrap().is_some());
+    assert!(origin.resolve_ref(&format!("refs/heads/{}", second)).unwrap().is_some());
+
+    git(dir, &["commit","--quiet","--amend","--allow-empty","-m","Address review again"]);
+    assert!(git_pr(dir, &["amend","--retire"]).status.success());
+    let third = format!("feature/{}", clone.rev_parse_head().unwrap());
+    assert!(origin.resolve_ref(&format!("refs/heads/{}", second)).unwrap().is_none());
+    assert!(origin.resolve_ref(&format!("refs/tags/pr-archive/{}", second)).unwrap().is_some());
+    assert_eq!(clone.upstream(&third).unwrap(), Some(format!("origin/{}", third)));
+
+    let shown = String::from_utf8_lossy(&git_pr(dir, &["show",&third]).stdout).to_string();
+    assert!(shown.starts_with(&format!("Make it faster\n\nRevises: {}\n\ncommit ", second)),
+            "{}", shown);
+    let timeline = String::from_utf8_lossy(&git_pr(dir, &["timeline",&third]).stdout).to_string();
+    assert!(timeline.contains(&format!("new revision of {}", second)), "{}", timeline);
+}
+
+// Merges wait for the CI checks named by pr.requiredCheck to pass on the PR's tip.
+#[test]
+fn required_checks_gate_merges() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(origin_dir, &["checkout","--quiet","--detach"]);
+    git(dir, &["config","--add","pr.requiredCheck","tests"]);
+    git(dir, &["config","--add","pr.requiredCheck","coverage"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Work"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    let tip = clone.resolve_ref("HEAD").unwrap().unwrap();
+    git(dir, &["checkout","--quiet","trunk"]);
+    git(dir, &["reset","--quiet","--hard","origin/trunk"]);
+
+    assert_eq!(git_pr(dir, &["merge","feature"]).status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(git_pr(dir, &["ci","set","feature","--check","tests=pass",
+                     "--check","coverage=fail,lines=12"]).status.success());
+    let output = git_pr(dir, &["merge","feature"]);
+    assert_eq!(output.status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("coverage is fail"));
+    assert_ne!(origin.resolve_ref("refs/heads/trunk").unwrap(), Some(tip.clone()));
+
+    assert!(git_pr(dir, &["ci","set","feature","--check","coverage=pass,lines=90"])
+        .status.success());
+    assert!(git_pr(dir, &["merge","feature"]).status.success());
+    assert_eq!(origin.resolve_ref("refs/heads/trunk").unwrap(), Some(tip));
+}
+
+// CI reports as a bot with --as, and with pr.signMetadata merges only go by the reports which
+// were signed by whoever they say made them.
+#[test]
+fn signed_ci_reports_gate_merges() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    const BOT: &str = "CI Bot <ci@example.com>";
+
+    // The key is the bot's, so it can't vouch for reports made as anyone else
+    let _keys = sign_metadata(&clone, &["ci@example.com"]);
+    git(origin_dir, &["checkout","--quiet","--detach"]);
+    git(dir, &["config","pr.botIdentity",BOT]);
+    git(dir, &["config","pr.requiredCheck","tests"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Work"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    let tip = clone.resolve_ref("HEAD").unwrap().unwrap();
+    git(dir, &["checkout","--quiet","trunk"]);
+    git(dir, &["reset","--quiet","--hard","origin/trunk"]);
+
+    assert_eq!(git_pr(dir, &["ci","set","feature","--as","Mallory","--check","tests=pass"])
+        .status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(git_pr(dir, &["ci","set","feature","--check","tests=pass"]).status.success());
+    assert_eq!(git_pr(dir, &["merge","feature"]).status.code(), Some(libgitpr::exit::REFUSED));
+
+    assert!(git_pr(dir, &["ci","set","feature","--as","CI Bot","--check","tests=pass"])
+        .status.success());
+    let lines = metadata::lines(&clone, &tip).unwrap();
+    assert!(lines.iter().any(|line| line.contains(&format!(" by {} signed:", BOT))), "{:?}", lines);
+    assert!(git_pr(dir, &["merge","feature"]).status.success());
+    assert_eq!(origin.resolve_ref("refs/heads/trunk").unwrap(), Some(tip));
+}
+
+// git pr describe publishes a PR's description, which other clones see in git pr show.
+#[test]
+fn descriptions_are_shared() {
+    let origin = temp_repo();
+    let mine = clone_repo(&origin);
+    let mine_dir = mine.working_dir.as_ref().as_ref();
+    let theirs = clone_repo(&origin);
+    let theirs_dir = theirs.working_dir.as_ref().as_ref();
+    let shown = |dir: &Path| String::from_utf8_lossy(&git_pr(dir, &["show","feature"]).stdout)
+        .to_string();
+
+    git(mine_dir, &["commit","--quiet","--allow-empty","-m","Work"]);
+    assert!(git_pr(mine_dir, &["create","feature"]).status.success());
+    assert!(shown(theirs_dir).starts_with("commit "));
+    assert_eq!(git_pr(mine_dir, &["describe","feature","-m","  "]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+
+    assert!(git_pr(mine_dir, &["describe","feature","-m","Fix the widget\n\nIt was broken."])
+        .status.success());
+    assert!(shown(theirs_dir).starts_with("Fix the widget\n\nIt was broken.\n\ncommit "),
+            "{}", shown(theirs_dir));
+    assert!(Command::new(env!("CARGO_BIN_EXE_git-pr")).current_dir(theirs_dir)
+        .args(["describe","feature"]).env("GIT_EDITOR", "sed -i -e 's/^Fix/Fixes/'")
+        .output().unwrap().status.success());
+    assert!(shown(mine_dir).starts_with("Fixes the widget\n"), "{}", shown(mine_dir));
+}
+
+// git pr env-diff reports settings which differ from those recommended in trunk, and can apply
+// the recommended ones.
+#[test]
+fn env_diff_finds_and_fixes_drift() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    std::fs::create_dir(origin_dir.join(".git-pr")).unwrap();
+    std::fs::write(origin_dir.join(".git-pr/recommended-config"),
+                   "[pr]\n\tmergeRetries = 5\n\tclaimHours = 24\n\
+                    [pr \"role.contractor\"]\n\tmember = bob@example.com\n\tallow = list\n")
+        .unwrap();
+    git(origin_dir, &["add",".git-pr"]);
+    git(origin_dir, &["commit","--quiet","-m","Recommend settings"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    git(dir, &["config","pr.mergeRetries","5"]);
+    git(dir, &["config","pr.claimHours","48"]);
+    git(dir, &["config","pr.role.lead.member","alice@example.com"]);
+
+    let output = git_pr(dir, &["env-diff"]);
+    assert_eq!(output.status.code(), Some(libgitpr::exit::REFUSED));
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+               "pr.claimhours: 48 here; 24 recommended\n\
+                pr.role.contractor.allow: not set here; list recommended\n\
+                pr.role.contractor.member: not set here; bob@example.com recommended\n\
+                pr.role.lead.member: alice@example.com here; not recommended\n");
+    assert_eq!(git_pr(dir, &["env-diff","--apply","--read-only"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+
+    assert!(git_pr(dir, &["env-diff","--apply"]).status.success());
+    assert_eq!(clone.config_get("pr.claimHours").unwrap().as_deref(), Some("24"));
+    assert_eq!(clone.config_get("pr.role.lead.member").unwrap(), None);
+    let output = git_pr(dir, &["env-diff"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+// PRs' states are recorded under refs/pr-state, so merged and abandoned PRs can still be listed.
+#[test]
+fn states_are_recorded() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let mut branches = vec![];
+    for name in ["merged", "dropped", "shelved", "open"] {
+        git(dir, &["checkout","--quiet","-b",&format!("work-{}", name),"origin/trunk"]);
+        git(dir, &["commit","--quiet","--allow-empty","-m",name]);
+        printed(git_pr(dir, &["create",name]));
+        branches.push(format!("{}/{}", name, clone.rev_parse_head().unwrap()));
+    }
+    git(dir, &["checkout","--quiet","--detach"]);
+    printed(git_pr(dir, &["merge",&branches[0],"--delete"]));
+    printed(git_pr(dir, &["abandon","dropped"]));
+    printed(git_pr(dir, &["archive","shelved"]));
+
+    assert_eq!(printed(git_pr(dir, &["list","--format","{name}"])), "open\n");
+    assert_eq!(printed(git_pr(dir, &["list","--state","merged","--format","{name} {state}"])),
+               "merged merged\n");
+    assert_eq!(printed(git_pr(dir, &["list","--state","abandoned","--format","{name}"])),
+               "dropped\nshelved\n");
+    assert_eq!(printed(git_pr(dir, &["list","--state","all","--format","{name} {state}"])),
+               "open open\ndropped abandoned\nmerged merged\nshelved abandoned\n");
+
+    // Each change is a commit on the PR's own ref, on the remote too
+    let log = Command::new("git").arg("-C").arg(origin.working_dir.as_ref().as_ref())
+        .args(["log","--format=%s",&format!("refs/pr-state/{}", branches[1])]).output().unwrap();
+    let log = String::from_utf8_lossy(&log.stdout).to_string();
+    assert!(log.starts_with(&format!("abandoned {}", branches[1])), "{}", log);
+    assert!(log.lines().nth(1).is_some_and(|line| line.starts_with("open ")), "{}", log);
+}
+
+// Commands of a team's own are run from git-pr-<command> on the PATH, with git-pr's settings.
+#[test]
+fn other_programs_get_the_settings() {
+    use std::os::unix::fs::PermissionsExt;
+    let repo = temp_repo();
+    let dir = repo.working_dir.as_ref().as_ref();
+    let bin = TempDir::new("git-pr-bin").unwrap();
+    let script = bin.path().join("git-pr-hello");
+    std::fs::write(&script, "#!/bin/sh\necho \"$GIT_PR_REMOTE $GIT_PR_TRUNK $GIT_PR_ROOT $*\"\n")
+        .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.path().display(), std::env::var("PATH").unwrap());
+    let hello = |start: &std::path::Path, args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_git-pr")).current_dir(start).args(args)
+            .env("PATH", &path).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    std::fs::create_dir(dir.join("nested")).unwrap();
+    let root = std::fs::canonicalize(dir).unwrap();
+    assert_eq!(hello(&dir.join("nested"), &["hello", "world"]),
+               format!("origin trunk {} world\n", root.display()));
+    assert_eq!(hello(dir, &["--remote", "upstream", "--trunk", "main", "hello"]),
+               format!("upstream main {} \n", root.display()));
+
+    // Outside a repository, the command still runs
+    let nowhere = TempDir::new("git-pr-nowhere").unwrap();
+    assert_eq!(hello(nowhere.path(), &["hello"]), "   \n");
+}
+
+// Approvals stay with the revision that was reviewed, and git pr reviews says which that was.
+#[test]
+fn approvals_follow_revisions() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let stderr = |output: Output| {
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stderr).to_string()
+    };
+    let me = "Your Name <you@example.com>";
+
+    git(dir, &["checkout","--quiet","-b","work","origin/trunk"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Work"]);
+    printed(git_pr(dir, &["create","feature"]));
+    let first = clone.abbreviate("HEAD").unwrap();
+    let old = format!("feature/{}", first);
+    assert!(stderr(git_pr(dir, &["reviews","feature"])).contains("Nobody has approved"));
+    assert!(stderr(git_pr(dir, &["approve","feature"])).contains("Approved"));
+    assert!(stderr(git_pr(dir, &["approve","feature"])).contains("already approved"));
+    assert_eq!(printed(git_pr(dir, &["reviews","feature"])),
+               format!("{}\t{}\t{}\n", old, first, me));
+
+    // A new revision needs approving again, but the old approval is still shown
+    git(dir, &["commit","--quiet","--allow-empty","-m","Address review"]);
+    printed(git_pr(dir, &["amend"]));
+    let second = clone.abbreviate("HEAD").unwrap();
+    let new = format!("feature/{}", second);
+    let reviews = printed(git_pr(dir, &["reviews",&new]));
+    assert_eq!(reviews, format!("{}\t{}\t{}\t(earlier revision)\n", new, first, me));
+    printed(git_pr(dir, &["approve",&new]));
+    let reviews = printed(git_pr(dir, &["reviews"]));
+    assert!(reviews.contains(&format!("{}\t{}\t{}\n", new, second, me)), "{}", reviews);
+    assert!(reviews.contains(&format!("{}\t{}\t{}\n", old, first, me)), "{}", reviews);
+}
+
+// A bot approves as itself with --as, and with pr.signMetadata the approval carries a signed record
+// which any clone can check.
+#[test]
+fn signed_approvals() {
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    const BOT: &str = "CI Bot <ci@example.com>";
+    let _keys = sign_metadata(&clone, &["ci@example.com"]);
+    git(dir, &["config","pr.botIdentity",BOT]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+
+    assert_eq!(git_pr(dir, &["approve","feature","--as","Mallory"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    let output = git_pr(dir, &["approve","feature","--as","CI Bot"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // Another clone gets the record along with the metadata, and can check it
+    let other = clone_repo(&origin);
+    assert!(git_pr(other.working_dir.as_ref().as_ref(), &["reviews","feature"]).status.success());
+    let tip = clone.resolve_ref("HEAD").unwrap().unwrap();
+    let lines = metadata::lines(&other, &tip).unwrap();
+    let line = lines.iter().find(|line| line.starts_with(&format!("approved-by {} ", BOT)))
+        .unwrap();
+    let record = line.rsplit_once(' ').unwrap().1;
+    let allowed = clone.config_get("gpg.ssh.allowedSignersFile").unwrap().unwrap();
+    git(other.working_dir.as_ref().as_ref(), &["config","gpg.ssh.allowedSignersFile",&allowed]);
+    let (signature, payload) = libgitpr::signing::import(&other, record).unwrap().unwrap();
+    assert!(signature.is_trusted());
+    assert_eq!(payload, format!("approved-by {}\non {}", BOT, tip));
+}
+
+// git pr list --query picks PRs out by their age, author, labels, state, paths, and CI results.
+#[test]
+fn list_by_query() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let names = |query: &str| printed(git_pr(dir, &["list","--query",query,"--format","{name}"]));
+
+    for (name, path) in [("docs", "docs/guide.md"), ("build", "build/ci.yml"), ("old", "src/x")] {
+        git(dir, &["checkout","--quiet","-b",&format!("work-{}", name),"origin/trunk"]);
+        std::fs::create_dir_all(dir.join(path).parent().unwrap()).unwrap();
+        std::fs::write(dir.join(path), name).unwrap();
+        git(dir, &["add",path]);
+        assert!(Command::new("git").arg("-C").arg(dir)
+            .args(["-c","user.name=Someone Else","-c","user.email=else@example.com"])
+            .args(["commit","--quiet","-m",name]).env("GIT_AUTHOR_DATE", "2000-01-01T00:00:00")
+            .env("GIT_COMMITTER_DATE", "2000-01-01T00:00:00").status().unwrap().success());
+        printed(git_pr(dir, &["create",name]));
+    }
+    printed(git_pr(dir, &["label","build","infra"]));
+    printed(git_pr(dir, &["ci","set","build","--status","fail","--check","tests=pass"]));
+    printed(git_pr(dir, &["abandon","old"]));
+
+    assert_eq!(names("path:docs"), "docs\n");
+    assert_eq!(names("label:infra and ci:fail"), "build\n");
+    assert_eq!(names("check:tests or path:docs/guide.md"), "build\ndocs\n");
+    assert_eq!(names("not label:infra"), "docs\n");
+    assert_eq!(names("author:\"someone else\" age>1w"), "build\ndocs\n");
+    assert_eq!(names("age<1d"), "");
+    assert_eq!(names("state:abandoned"), "old\n");
+    assert_eq!(names("state:abandoned or state:open and path:build"), "build\nold\n");
+}
+
+// With pr.minApprovals, a PR is only merged once enough people besides its author approve its tip.
+#[test]
+fn approvals_gate_merges() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let approve_as = |who: &str, email: &str| assert!(Command::new(env!("CARGO_BIN_EXE_git-pr"))
+        .current_dir(dir).args(["approve","feature"]).env("GIT_CONFIG_COUNT", "2")
+        .env("GIT_CONFIG_KEY_0", "user.name").env("GIT_CONFIG_VALUE_0", who)
+        .env("GIT_CONFIG_KEY_1", "user.email").env("GIT_CONFIG_VALUE_1", email)
+        .stderr(Stdio::null()).status().unwrap().success());
+
+    git(origin_dir, &["checkout","--quiet","--detach"]);
+    git(dir, &["config","pr.minApprovals","2"]);
+    std::fs::write(dir.join(".mailmap"), "Alice <alice@example.com> <alice@old.example.com>\n")
+        .unwrap();
+    git(dir, &["commit","--quiet","--allow-empty","-m","Work"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    let tip = clone.resolve_ref("HEAD").unwrap();
+    git(dir, &["checkout","--quiet","trunk"]);
+    git(dir, &["reset","--quiet","--hard","origin/trunk"]);
+
+    // The author's own approval doesn't count, nor does anyone's second address
+    assert!(git_pr(dir, &["approve","feature"]).status.success());
+    approve_as("Alice", "alice@example.com");
+    approve_as("Alice", "alice@old.example.com");
+    let output = git_pr(dir, &["merge","feature"]);
+    assert_eq!(output.status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 have so far"));
+    assert_ne!(origin.resolve_ref("refs/heads/trunk").unwrap(), tip);
+
+    approve_as("Bob", "bob@example.com");
+    assert!(git_pr(dir, &["merge","feature"]).status.success());
+    assert_eq!(origin.resolve_ref("refs/heads/trunk").unwrap(), tip);
+}
+
+// With pr.signMetadata, only approvals signed by the approver count towards pr.minApprovals.
+#[test]
+fn signed_approvals_gate_merges() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let approve_as = |who: &str, email: &str, sign: &str| assert!(
+        Command::new(env!("CARGO_BIN_EXE_git-pr"))
+            .current_dir(dir).args(["approve","feature"]).env("GIT_CONFIG_COUNT", "3")
+            .env("GIT_CONFIG_KEY_0", "user.name").env("GIT_CONFIG_VALUE_0", who)
+            .env("GIT_CONFIG_KEY_1", "user.email").env("GIT_CONFIG_VALUE_1", email)
+            .env("GIT_CONFIG_KEY_2", "pr.signMetadata").env("GIT_CONFIG_VALUE_2", sign)
+            .stderr(Stdio::null()).status().unwrap().success());
+    let refused = || {
+        let output = git_pr(dir, &["merge","feature"]);
+        assert_eq!(output.status.code(), Some(libgitpr::exit::REFUSED));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("0 have so far"));
+    };
+
+    // The key is Alice's, so it can't vouch for anyone else
+    let _keys = sign_metadata(&clone, &["alice@example.com"]);
+    git(origin_dir, &["checkout","--quiet","--detach"]);
+    git(dir, &["config","pr.minApprovals","1"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Work"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    let tip = clone.resolve_ref("HEAD").unwrap();
+    git(dir, &["checkout","--quiet","trunk"]);
+    git(dir, &["reset","--quiet","--hard","origin/trunk"]);
+
+    approve_as("Bob", "bob@example.com", "false");
+    refused();
+    approve_as("Carol", "carol@example.com", "true");
+    refused();
+    approve_as("Alice", "alice@example.com", "true");
+    let output = git_pr(dir, &["merge","feature"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(origin.resolve_ref("refs/heads/trunk").unwrap(), tip);
+}
+
+// A snapshot carries a remote's PRs, metadata, states, and archives to a new, empty remote.
+#[test]
+fn snapshot_and_restore() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let refs = |dir: &std::path::Path| {
+        git(dir, &["for-each-ref","--format=%(objectname) %(refname)"]).lines()
+            .filter(|line| !line.ends_with("refs/heads/hotfix"))
+            .map(String::from).collect::<Vec<_>>()
+    };
+
+    for name in ["feature", "old"] {
+        git(dir, &["checkout","--quiet","-b",&format!("work-{}", name),"origin/trunk"]);
+        git(dir, &["commit","--quiet","--allow-empty","-m",name]);
+        assert!(git_pr(dir, &["create",name]).status.success());
+    }
+    assert!(git_pr(dir, &["comment","feature","Looks good"]).status.success());
+    assert!(git_pr(dir, &["describe","feature","-m","Adds the feature"]).status.success());
+    assert!(git_pr(dir, &["archive","old"]).status.success());
+    let saved = refs(origin.working_dir.as_ref().as_ref());
+    assert!(saved.iter().any(|line| line.contains("refs/notes/pr-descriptions")));
+    assert!(saved.iter().any(|line| line.contains("refs/pr-state/old/")));
+    assert!(saved.iter().any(|line| line.contains("refs/tags/pr-archive/old/")));
+
+    assert!(git_pr(dir, &["snapshot","create","review.bundle"]).status.success());
+    assert!(refs(dir).iter().all(|line| !line.contains("refs/pr-snapshot/")));
+
+    // The remote is lost, and replaced with an empty one
+    let replacement = TempDir::new("git-pr-replacement").unwrap();
+    git(replacement.path(), &["init","--quiet","--bare"]);
+    git(dir, &["remote","set-url","origin",replacement.path().to_str().unwrap()]);
+    assert!(git_pr(dir, &["snapshot","restore","review.bundle"]).status.success());
+    assert_eq!(refs(replacement.path()), saved);
+    assert!(refs(dir).iter().all(|line| !line.contains("refs/pr-snapshot/")));
+
+    // Restoring again changes nothing, but a snapshot can't undo later work without --force
+    assert!(git_pr(dir, &["snapshot","restore","review.bundle"]).status.success());
+    assert!(git_pr(dir, &["comment","feature","One more thing"]).status.success());
+    assert_eq!(git_pr(dir, &["snapshot","restore","review.bundle"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    assert!(git_pr(dir, &["snapshot","restore","review.bundle","--force"]).status.success());
+    assert_eq!(refs(replacement.path()), saved);
+}
+
+// Without a name, git pr create suggests one from the commit and the files it changes.
+#[test]
+fn create_suggests_a_name() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let fix_typo = |branch: &str, file: &str| {
+        git(dir, &["checkout","--quiet","-b",branch,"origin/trunk"]);
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(dir.join("docs").join(file), "Fixed\n").unwrap();
+        git(dir, &["add","docs"]);
+        git(dir, &["commit","--quiet","-m","Fix typo in guide"]);
+    };
+
+    // Nobody can be asked, so the suggestion needs --yes
+    fix_typo("work-first", "guide.md");
+    let output = git_pr(dir, &["create"]);
+    assert_eq!(output.status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("docs-fix-typo-in-guide"));
+    assert!(git_pr(dir, &["create","--yes"]).status.success());
+    let first = format!("refs/heads/docs-fix-typo-in-guide/{}", clone.rev_parse_head().unwrap());
+    assert!(origin.resolve_ref(&first).unwrap().is_some());
+
+    // A name which is taken is numbered
+    fix_typo("work-second", "faq.md");
+    assert!(git_pr(dir, &["create","--yes"]).status.success());
+    let second = format!("refs/heads/docs-fix-typo-in-guide-2/{}",
+                         clone.rev_parse_head().unwrap());
+    assert!(origin.resolve_ref(&second).unwrap().is_some());
+}
+
+// git pr list shows who PRs are assigned to, and can list just the ones assigned to you.
+#[test]
+fn list_assigned_prs() {
+    let origin = temp_repo();
+    let alice = clone_repo(&origin);
+    let alice_dir = alice.working_dir.as_ref().as_ref();
+    let bob = clone_repo(&origin);
+    let bob_dir = bob.working_dir.as_ref().as_ref();
+    const BOB: &str = "Bob <bob@example.com>";
+    const CAROL: &str = "Carol <carol@example.com>";
+    git(bob_dir, &["config","user.name","Bob"]);
+    git(bob_dir, &["config","user.email","bob@example.com"]);
+    for name in ["one", "two", "three"] {
+        git(alice_dir, &["commit","--quiet","--allow-empty","-m",name]);
+        assert!(git_pr(alice_dir, &["create",name]).status.success());
+    }
+    assert!(git_pr(alice_dir, &["assign","one",BOB,CAROL]).status.success());
+    assert!(git_pr(alice_dir, &["assign","two",CAROL]).status.success());
+
+    assert_eq!(printed(git_pr(alice_dir, &["list", "--assignees"])),
+               format!("one\t{}, {}\nthree\ntwo\t{}\n", BOB, CAROL, CAROL));
+    assert_eq!(printed(git_pr(alice_dir, &["list","--format","{name} {assigned}"])),
+               format!("one {},{}\nthree \ntwo {}\n", BOB, CAROL, CAROL));
+    assert_eq!(printed(git_pr(bob_dir, &["list", "--assigned-to-me"])), "one\n");
+    assert_eq!(printed(git_pr(alice_dir, &["list", "--assigned-to-me"])), "");
+
+    // Taking someone off a PR takes it off their list
+    assert!(git_pr(alice_dir, &["unassign","one",BOB]).status.success());
+    assert_eq!(printed(git_pr(bob_dir, &["list", "--assigned-to-me"])), "");
+}
+
+// git pr update rebases the current PR onto the remote's trunk and pushes it, and stops with the
+// rebase in progress when there are conflicts.
+#[test]
+fn update_onto_trunk() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    // Someone else commits `text` to `file` on trunk
+    let elsewhere = |file: &str, text: &str| {
+        std::fs::write(origin_dir.join(file), text).unwrap();
+        git(origin_dir, &["add",file]);
+        git(origin_dir, &["commit","--quiet","-m",file]);
+        git(origin_dir, &["update-ref","refs/heads/trunk","HEAD"]);
+    };
+
+    git(origin_dir, &["checkout","--quiet","--detach"]);
+    git(dir, &["checkout","--quiet","trunk"]);
+    std::fs::write(dir.join("mine.txt"), "mine").unwrap();
+    git(dir, &["add","mine.txt"]);
+    git(dir, &["commit","--quiet","-m","Mine"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    let branch = format!("refs/heads/feature/{}", clone.rev_parse_head().unwrap());
+
+    elsewhere("theirs.txt", "theirs");
+    assert!(git_pr(dir, &["update"]).status.success());
+    assert_eq!(clone.divergence("origin/trunk", "HEAD").unwrap(), (0, 1));
+    assert_eq!(origin.resolve_ref(&branch).unwrap(), clone.resolve_ref("HEAD").unwrap());
+    assert!(dir.join("theirs.txt").exists());
+    assert!(git_pr(dir, &["update"]).status.success());
+
+    elsewhere("mine.txt", "theirs");
+    let output = git_pr(dir, &["update"]);
+    assert_eq!(output.status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("\tmine.txt"));
+    assert_eq!(clone.conflicted_paths().unwrap(), vec!["mine.txt"]);
+    git(dir, &["rebase","--abort"]);
+}
+
+// git pr merge fast-forwards trunk when it can (and must, with --ff-only), makes a merge commit
+// otherwise, and pushes trunk. The working tree follows trunk when trunk is checked out.
+#[test]
+fn merge_into_trunk() {
+    let origin = temp_repo();
+    // The origin can't have trunk checked out, or it would refuse our pushes
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    git(dir, &["checkout","--quiet","trunk"]);
+    // Start a PR called `name` which adds `file`, then go back to trunk
+    let propose = |name: &str, file: &str| {
+        git(dir, &["checkout","--quiet","-b",&format!("work-on-{}", name),"trunk"]);
+        std::fs::write(dir.join(file), name).unwrap();
+        git(dir, &["add",file]);
+        git(dir, &["commit","--quiet","-m",name]);
+        assert!(git_pr(dir, &["create", name]).status.success());
+        let branch = format!("{}/{}", name, clone.rev_parse_head().unwrap());
+        git(dir, &["checkout","--quiet","trunk"]);
+        branch
+    };
+    let trunk_on = |repo: &Git| repo.resolve_ref("refs/heads/trunk").unwrap().unwrap();
+
+    let first = propose("first", "first.txt");
+    let second = propose("second", "second.txt");
+    let second_tip = clone.resolve_ref(&second).unwrap().unwrap();
+
+    assert!(git_pr(dir, &["merge", "--ff-only", "first"]).status.success());
+    assert_eq!(trunk_on(&clone), clone.resolve_ref(&first).unwrap().unwrap());
+    assert_eq!(trunk_on(&origin), trunk_on(&clone));
+    assert!(dir.join("first.txt").exists());
+
+    // Trunk has moved on since the second PR was started
+    assert_eq!(git_pr(dir, &["merge", "--ff-only", "second"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    assert!(git_pr(dir, &["merge", "--delete", "second"]).status.success());
+    let merge = trunk_on(&clone);
+    assert_eq!(clone.resolve_ref(&format!("{}^2", merge)).unwrap(), Some(second_tip));
+    assert_eq!(trunk_on(&origin), merge);
+    assert!(dir.join("second.txt").exists());
+    assert!(clone.resolve_ref(&format!("refs/heads/{}", second)).unwrap().is_none());
+    assert!(origin.resolve_ref(&format!("refs/heads/{}", second)).unwrap().is_none());
+}
+
+// git pr land squashes a PR into one commit on top of the remote's trunk, and deletes the PR.
+#[test]
+fn land_a_pr() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(origin_dir, &["checkout","--quiet","--detach"]);
+    git(dir, &["checkout","--quiet","-b","work","trunk"]);
+    for name in ["one", "two"] {
+        std::fs::write(dir.join(name), name).unwrap();
+        git(dir, &["add",name]);
+        git(dir, &["commit","--quiet","-m",&format!("Add {}", name)]);
+    }
+    let status = git_pr(dir, &["create","feature"]).status;
+    assert!(status.success());
+    let branch = format!("feature/{}", clone.rev_parse_head().unwrap());
+    git(dir, &["checkout","--quiet","trunk"]);
+    git(origin_dir, &["commit","--quiet","--allow-empty","-m","Elsewhere"]);
+    git(origin_dir, &["update-ref","refs/heads/trunk","HEAD"]);
+    let elsewhere = origin.resolve_ref("HEAD").unwrap();
+
+    let status = git_pr(dir, &["land","feature"]).status;
+    assert!(status.success());
+    let landed = origin.resolve_ref("refs/heads/trunk").unwrap().unwrap();
+    assert_eq!(origin.resolve_ref(&format!("{}^", landed)).unwrap(), elsewhere);
+    assert!(origin.resolve_ref(&format!("{}^2", landed)).unwrap().is_none());
+    let message = clone.log(&landed, &["-1","--format=%B"]).unwrap();
+    assert!(message.starts_with(&format!("feature ({})\n", branch)), "{}", message);
+    assert!(message.contains("Add one\n") && message.contains("Add two\n"));
+    assert!(origin.resolve_ref(&format!("refs/heads/{}", branch)).unwrap().is_none());
+    assert!(clone.resolve_ref(&format!("refs/heads/{}", branch)).unwrap().is_none());
+    assert_eq!(clone.resolve_ref("refs/heads/trunk").unwrap(), Some(landed));
+    assert!(dir.join("two").exists());
+}
+
+// A merge message template committed to trunk decides how merges are written.
+#[test]
+fn merge_with_the_team_template() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    std::fs::create_dir(origin_dir.join(".git-pr")).unwrap();
+    std::fs::write(origin_dir.join(".git-pr/merge-message.tmpl"),
+                   "{subject}\n\n{ticket}: {description}\n\nCommits:\n{commits}\n").unwrap();
+    git(origin_dir, &["add",".git-pr"]);
+    git(origin_dir, &["commit","--quiet","-m","Add a merge message template"]);
+    git(origin_dir, &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","-b","work","trunk"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m",
+               "Fix PAY-7\n\nRefunds were lost.\nNow kept."]);
+    let status = git_pr(dir, &["create","refunds"]).status;
+    assert!(status.success());
+    let branch = format!("refunds/{}", clone.rev_parse_head().unwrap());
+    let tip = clone.rev_parse_head().unwrap();
+    git(dir, &["checkout","--quiet","trunk"]);
+
+    let status = git_pr(dir, &["merge","--no-ff","refunds"]).status;
+    assert!(status.success());
+    let merged = origin.resolve_ref("refs/heads/trunk").unwrap().unwrap();
+    assert_eq!(clone.log(&merged, &["-1","--format=%B"]).unwrap(),
+               format!("Merge branch '{}'\n\nPAY-7: Refunds were lost.\n\n\
+                        Commits:\n* {} Fix PAY-7\n\n", branch, &tip[..7]));
+}
+
+// If someone pushes to trunk while a PR is being merged, the merge is redone on top of their work.
+// Once the retries run out, the push is refused, and the PR branch isn't deleted from the remote.
+#[test]
+fn merge_retries_when_trunk_moves() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","-b","work"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Work"]);
+    let status = git_pr(dir, &["create","feature"]).status;
+    assert!(status.success());
+    let branch = format!("refs/heads/feature/{}", clone.rev_parse_head().unwrap());
+    let tip = clone.resolve_ref("HEAD").unwrap();
+    git(dir, &["checkout","--quiet","trunk"]);
+
+    // Someone else pushes to trunk just before each of our pushes, after we have fetched
+    git(origin_dir, &["checkout","--quiet","--detach"]);
+    let hook = dir.join(".git/hooks/pre-push");
+    std::fs::write(&hook, format!(
+        "#!/bin/sh
+         unset GIT_DIR GIT_INDEX_FILE
+         test -e .git/race || exit 0
+         git -C '{0}' commit --quiet --allow-empty -m Elsewhere
+         git -C '{0}' update-ref refs/heads/trunk HEAD
+         echo >>.git/race
+", origin_dir.display()
+    )).unwrap();
+    std::fs::set_permissions(&hook, std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+    let race = dir.join(".git/race");
+
+    std::fs::write(&race, "").unwrap();
+    git(dir, &["config","pr.mergeRetries","1"]);
+    let output = git_pr(dir, &["merge","--delete","feature"]);
+    assert_eq!(output.status.code(), Some(libgitpr::exit::REFUSED));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("refs/heads/trunk") && stderr.contains(&branch), "{}", stderr);
+    assert!(origin.resolve_ref(&branch).unwrap().is_some());
+    assert!(clone.resolve_ref(&branch).unwrap().is_some(), "the local branch was deleted");
+    assert_eq!(std::fs::read_to_string(&race).unwrap().lines().count(), 2);
+
+    // This time the race only happens once, so the second try gets through
+    std::fs::write(&race, "").unwrap();
+    std::fs::write(&hook, std::fs::read_to_string(&hook).unwrap()
+        .replace("test -e .git/race", "test -s .git/race && exit 0; test -e .git/race")).unwrap();
+    let trunk_on = |repo: &Git| repo.resolve_ref("refs/heads/trunk").unwrap().unwrap();
+    let output = git_pr(dir, &["merge","--no-ff","feature"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let merge = trunk_on(&origin);
+    assert_eq!(origin.resolve_ref(&format!("{}^2", merge)).unwrap(), tip);
+    assert_eq!(origin.resolve_ref(&format!("{}^1", merge)).unwrap(),
+               origin.resolve_ref("HEAD").unwrap());
+    assert_eq!(trunk_on(&clone), merge);
+}
+
+// git pr archive tags a PR on the remote, and in a bundle, before deleting its branch.
+#[test]
+fn archive_a_pr() {
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","-b","work"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Work"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    let tip = clone.resolve_ref("HEAD").unwrap();
+    let branch = format!("feature/{}", clone.rev_parse_head().unwrap());
+    let tag = format!("refs/tags/pr-archive/{}", branch);
+
+    assert!(git_pr(dir, &["archive","feature","--bundle","feature.bundle"]).status.success());
+    assert!(origin.resolve_ref(&format!("refs/heads/{}", branch)).unwrap().is_none());
+    assert_eq!(origin.resolve_ref(&tag).unwrap(), tip);
+    assert_eq!(clone.resolve_ref(&tag).unwrap(), tip);
+
+    // The bundle holds the PR's commits, for any clone of the repository
+    let restored = clone_repo(&origin);
+    let restored_dir = restored.working_dir.as_ref().as_ref();
+    git(restored_dir, &["fetch","--quiet",dir.join("feature.bundle").to_str().unwrap(),
+                        &format!("{}:{}", tag, tag)]);
+    assert_eq!(restored.resolve_ref(&tag).unwrap(), tip);
+    assert_eq!(git_pr(dir, &["archive","feature"]).status.code(), Some(libgitpr::exit::REFUSED));
+}
+
+// A PR deleted from the remote can be reopened from an old remote-tracking branch, or its archive.
+#[test]
+fn reopen_a_pr() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let on_origin = |branch: &str| origin.resolve_ref(&format!("refs/heads/{}", branch)).unwrap();
+
+    git(dir, &["checkout","--quiet","-b","work"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    let old = format!("feature/{}", clone.rev_parse_head().unwrap());
+    git(dir, &["commit","--quiet","--allow-empty","-m","More work"]);
+    git(dir, &["push","--quiet","origin",&format!("HEAD:{}", old)]);
+    let tip = clone.resolve_ref("HEAD").unwrap();
+
+    // Someone deletes the PR, but this clone still remembers it
+    let other = clone_repo(&origin);
+    let other_dir = other.working_dir.as_ref().as_ref();
+    assert_eq!(git_pr(other_dir, &["reopen","feature"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    git(origin_dir, &["branch","-D",&old]);
+    assert!(git_pr(other_dir, &["reopen","feature"]).status.success());
+    let new = format!("feature/{}", clone.abbreviate("HEAD").unwrap());
+    assert_ne!(new, old);
+    assert_eq!(on_origin(&new), tip);
+    assert_eq!(other.upstream(&new).unwrap(), Some(format!("origin/{}", new)));
+
+    // An archived PR comes back under the same name, reusing the local branch
+    git(dir, &["checkout","--quiet","-b","more"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Second"]);
+    assert!(git_pr(dir, &["create","second"]).status.success());
+    let second = format!("second/{}", clone.rev_parse_head().unwrap());
+    assert!(git_pr(dir, &["archive","second"]).status.success());
+    assert!(on_origin(&second).is_none());
+    assert!(git_pr(dir, &["reopen","second"]).status.success());
+    assert_eq!(on_origin(&second), clone.resolve_ref("HEAD").unwrap());
+}
+
+// Local PR branches whose remote branch was deleted elsewhere are listed, and can be pushed again
+// or deleted.
+#[test]
+fn find_orphaned_branches() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","-b","work"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Work"]);
+    assert!(git_pr(dir, &["create","feature"]).status.success());
+    let branch = format!("feature/{}", clone.rev_parse_head().unwrap());
+    git(dir, &["checkout","--quiet","trunk"]);
+    assert_eq!(git_pr(dir, &["orphans"]).stdout, b"");
+
+    git(origin_dir, &["branch","-D",&branch]);
+    assert_eq!(String::from_utf8_lossy(&git_pr(dir, &["orphans"]).stdout), format!("{}\n", branch));
+    assert!(git_pr(dir, &["orphans", "--push"]).status.success());
+    assert!(origin.resolve_ref(&format!("refs/heads/{}", branch)).unwrap().is_some());
+    assert_eq!(git_pr(dir, &["orphans"]).stdout, b"");
+
+    git(origin_dir, &["branch","-D",&branch]);
+    assert_eq!(git_pr(dir, &["orphans","--delete","--read-only"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    assert!(git_pr(dir, &["orphans", "--delete"]).status.success());
+    assert!(clone.resolve_ref(&format!("refs/heads/{}", branch)).unwrap().is_none());
+}
+
+// git pr sync fetches, fast-forwards trunk, and reports what it found.
+#[test]
+fn sync_with_the_remote() {
+    let origin = temp_repo();
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","-b","work"]);
+    git(dir, &["commit","--quiet","--allow-empty","-m","Work"]);
+    printed(git_pr(dir, &["create","feature"]));
+    let branch = format!("feature/{}", clone.rev_parse_head().unwrap());
+    git(dir, &["checkout","--quiet","trunk"]);
+    assert!(printed(git_pr(dir, &["sync"])).contains("trunk is up to date"));
+
+    // Elsewhere, the PR is abandoned, another is published, and trunk moves on
+    git(origin_dir, &["branch","-D",&branch]);
+    git(origin_dir, &["branch","other/abc123"]);
+    git(origin_dir, &["branch","third/def456"]);
+    git(origin_dir, &["commit","--quiet","--allow-empty","-m","Trunk moves"]);
+    git(dir, &["fetch","--quiet"]);
+    let summary = printed(git_pr(dir, &["sync"]));
+    assert!(summary.contains("origin has 2 PRs: 2 new, 0 updated, and 1 gone"));
+    assert!(summary.contains("\n  feature closed\n  other opened\n  third opened\n"));
+    assert!(summary.contains("Fast-forwarded trunk"));
+    assert!(summary.contains("1 local PR branches are no longer on origin"));
+    assert_eq!(clone.resolve_ref("trunk").unwrap(), origin.resolve_ref("trunk").unwrap());
+    assert!(clone.resolve_ref(&format!("refs/heads/{}", branch)).unwrap().is_some());
+
+    // A PR which moves is updated, and nothing is reported twice
+    git(origin_dir, &["branch","-f","third/def456","trunk"]);
+    let summary = printed(git_pr(dir, &["sync"]));
+    assert!(summary.contains("origin has 2 PRs: 0 new, 1 updated, and 0 gone"));
+    assert!(summary.contains("\n  third updated to "));
+    assert!(printed(git_pr(dir, &["sync"])).contains("0 new, 0 updated, and 0 gone"));
+}
+
+// git pr stats counts the open PRs per author, and the PRs merged this week.
+#[test]
+fn stats_on_open_and_merged_prs() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","--quiet","--detach"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    git(dir, &["checkout","--quiet","trunk"]);
+    for name in ["first", "second"] {
+        git(dir, &["checkout","--quiet","-b",&format!("work-on-{}", name),"trunk"]);
+        git(dir, &["commit","--quiet","--allow-empty","-m",name]);
+        printed(git_pr(dir, &["create", name]));
+    }
+    git(dir, &["checkout","--quiet","trunk"]);
+    printed(git_pr(dir, &["merge", "first", "--no-ff", "--delete"]));
+
+    let report = printed(git_pr(dir, &["stats"]));
+    assert!(report.contains("Open PRs: 1\n  Your Name <you@example.com>  1\n"));
+    assert!(report.contains("Average age of open PRs: "));
+    assert!(report.contains("Merged this week: 1\n"));
+    assert!(report.contains("Merged PRs: 1\n"));
+    assert!(printed(git_pr(dir, &["stats", "--csv"]))
+        .starts_with("name,started,merged,hours_to_merge\nfirst,"));
+}
+
+// Renaming a PR moves its branch on the remote in one step, and the local branch follows.
+#[test]
+fn rename_a_pr() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["branch","taken/abc123"]);
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let on_origin = |branch: &str| {
+        origin.resolve_ref(&format!("refs/heads/{}", branch)).unwrap().is_some()
+    };
+
+    assert!(git_pr(dir, &["create", "feture"]).status.success());
+    let hash = clone.rev_parse_head().unwrap();
+    let (old, new) = (format!("feture/{}", hash), format!("feature/{}", hash));
+
+    assert_eq!(git_pr(dir, &["rename", "feture", "bad name"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    assert!(git_pr(dir, &["rename", "feture", "feature"]).status.success());
+    assert!(!on_origin(&old) && on_origin(&new));
+    assert_eq!(clone.current_branch().unwrap(), Some(format!("refs/heads/{}", new)));
+    assert_eq!(clone.upstream(&new).unwrap(), Some(format!("origin/{}", new)));
+
+    // Nothing is renamed onto a PR which already exists
+    git(origin.working_dir.as_ref().as_ref(), &["branch",&format!("taken/{}", hash)]);
+    assert_eq!(git_pr(dir, &["rename", "feature", "taken"]).status.code(),
+               Some(libgitpr::exit::REFUSED));
+    assert!(on_origin(&new));
+}
+
+// Completion lists the PRs already fetched, without going back to the remote for more.
+#[test]
+fn complete_pr_names_without_fetching() {
+    let origin = temp_repo();
+    for branch in ["feature/abc123", "feature/def456", "fix/abc123"] {
+        git(origin.working_dir.as_ref().as_ref(), &["branch",branch]);
+    }
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+
+    // Take the remote away; a fetch would now fail
+    git(clone.working_dir.as_ref().as_ref(), &["remote","set-url","origin","/nonexistent"]);
+    assert_eq!(printed(git_pr(dir, &["__complete-pr-names"])), "feature\nfix\n");
+    assert_eq!(printed(git_pr(dir, &["__complete-pr-names", "fe"])), "feature\n");
+
+    let script = printed(git_pr(dir, &["completions", "bash", "--dynamic"]));
+    assert!(script.contains("_git_pr ()") && script.contains("__complete-pr-names"));
+    assert!(!printed(git_pr(dir, &["completions", "bash"])).contains("__complete-pr-names"));
+    assert!(!printed(git_pr(dir, &["--help"])).contains("__complete-pr-names"));
+}
+
+// --remote and --trunk reach commands which live in programs of their own, as git config.
+#[test]
+fn shared_flags_reach_other_programs() {
+    let git = temp_repo();
+    let output = git_pr(git.working_dir.as_ref().as_ref(),
+                        &["--remote", "upstream", "--trunk", "main", "env"]);
+    assert!(output.status.success());
+    let settings: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(settings["remote"]["value"], "upstream");
+    assert_eq!(settings["trunk"]["value"], "main");
+}
+
+// Old metadata can be upgraded in place, but metadata from a newer git-pr is left alone.
+#[test]
+fn migrate_metadata_schema() {
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let commit = clone.rev_parse_head().unwrap();
+    let migrate = || Command::new(env!("CARGO_BIN_EXE_git-pr-migrate-metadata"))
+        .current_dir(dir).stdout(Stdio::null()).status().unwrap();
+
+    clone.append_note(metadata::NOTES_REF, &commit, "approved-by alice").unwrap();
+    assert!(migrate().success());
+    assert_eq!(origin.show_note(metadata::NOTES_REF, &commit).unwrap().unwrap(),
+               "v1 approved-by alice\n");
+
+    clone.append_note(metadata::NOTES_REF, &commit, "v99 something new").unwrap();
+    assert!(metadata::lines(&clone, &commit).is_err());
+    assert!(!migrate().success());
+    assert_eq!(origin.show_note(metadata::NOTES_REF, &commit).unwrap().unwrap(),
+               "v1 approved-by alice\n");
+}
+
+// Branches pushed by bots belong to the bots, so pr-clean leaves them alone unless told otherwise.
+#[test]
+fn clean_spares_other_tools_branches() {
+    let repo = temp_repo();
+    let dir = repo.working_dir.as_ref().as_ref();
+    git(dir, &["branch","dependabot/cargo/serde-1.0.1"]);
+
+    assert!(git_pr(dir, &["clean"]).status.success());
+    assert!(repo.resolve_ref("refs/heads/dependabot/cargo/serde-1.0.1").unwrap().is_some());
+    assert!(repo.resolve_ref("refs/heads/hotfix").unwrap().is_none());
+
+    git(dir, &["config","pr.allowConvention","dependabot"]);
+    assert!(git_pr(dir, &["clean"]).status.success());
+    assert!(repo.resolve_ref("refs/heads/dependabot/cargo/serde-1.0.1").unwrap().is_none());
+}
+
+// With release branches, a branch merged into only one of them is kept, unless that is its base.
+#[test]
+fn clean_waits_for_every_base() {
+    let repo = temp_repo();
+    let dir = repo.working_dir.as_ref().as_ref();
+    git(dir, &["config","pr.base","release/1.x"]);
+    git(dir, &["checkout","-q","-b","release/1.x"]);
+    git(dir, &["commit","-q","--allow-empty","-m","backport"]);
+    git(dir, &["branch","backport"]);
+    git(dir, &["branch","fix/abc123"]);
+    git(dir, &["config","branch.fix/abc123.prBase","release/1.x"]);
+    git(dir, &["checkout","-q","trunk"]);
+
+    let status = git_pr(dir, &["clean"]).status;
+    assert!(status.success());
+    assert!(repo.resolve_ref("refs/heads/hotfix").unwrap().is_none());
+    assert!(repo.resolve_ref("refs/heads/fix/abc123").unwrap().is_none());
+    assert!(repo.resolve_ref("refs/heads/backport").unwrap().is_some());
+    assert!(repo.resolve_ref("refs/heads/release/1.x").unwrap().is_some());
+}
+
+// A Gerrit change with two patch sets becomes a PR with two iterations, and its +2 an approval.
+#[test]
+fn import_gerrit_changes() {
+    let origin = temp_repo();
+    let clone = clone_repo(&origin);
+    let dir = clone.working_dir.as_ref().as_ref();
+    let mut commits = vec![];
+    for message in ["patch set 1", "patch set 2"] {
+        git(dir, &["commit","--quiet","--allow-empty","-m",message]);
+        commits.push(clone.resolve_ref("HEAD").unwrap().unwrap());
+    }
+
+    let export = format!(r#")]}}'
+        [{{"_number": 42, "subject": "Fix it", "status": "NEW",
+          "revisions": {{"{}": {{"_number": 1}}, "{}": {{"_number": 2}}}},
+          "labels": {{"Code-Review": {{"all": [{{"value": 2, "name": "Alice"}}]}}}}}}]"#,
+        commits[0], commits[1]);
+    let export_path = dir.join("changes.json");
+    std::fs::write(&export_path, export).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_git-pr-import-gerrit"))
+        .current_dir(dir).arg(&export_path).stdout(Stdio::null()).status().unwrap();
+    assert!(status.success());
+
+    let branch = format!("refs/heads/gerrit-42/{}", &commits[1][..7]);
+    assert_eq!(origin.resolve_ref(&branch).unwrap(), Some(commits[1].clone()));
+    assert_eq!(metadata::lines(&clone, &commits[1]).unwrap(),
+               vec!["iteration 2 of gerrit change 42: Fix it", "approved-by Alice"]);
+}
+
+// Each commit of the PR becomes one patch of the Patchwork series.
+#[test]
+fn export_patchwork_series() {
+    let repo = temp_repo();
+    let dir = repo.working_dir.as_ref().as_ref();
+    git(dir, &["checkout","-q","-b","work"]);
+    for file in ["a.txt", "b.txt"] {
+        std::fs::write(dir.join(file), "hello\n").unwrap();
+        git(dir, &["add",file]);
+        git(dir, &["commit","-q","-m",&format!("Add {}", file)]);
+    }
+    git(dir, &["update-ref","refs/remotes/origin/work/1234567","HEAD"]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-pr-export-patchwork"))
+        .current_dir(dir).args(["work","-v","2"]).output().unwrap();
+    assert!(output.status.success());
+    let series: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(series["version"], 2);
+    assert_eq!(series["patches"][1]["name"], "[PATCH v2 2/2] Add b.txt");
+    assert!(series["patches"][0]["diff"].as_str().unwrap().contains("+++ b/a.txt"));
+}
+
+// A mirror ends up with the same PR branches and metadata as its source.
+#[test]
+fn mirror_pr_branches_between_remotes() {
+    let origin = temp_repo();
+    let local = clone_repo(&origin);
+    let backup_dir = TempDir::new("git-pr-backup").unwrap();
+    let status = Command::new("git").args(["init","--quiet","--bare"]).arg(backup_dir.path())
+        .status().unwrap();
+    assert!(status.success());
+    let backup = Git{
+        program: "git".to_string(), working_dir: Box::new(backup_dir),
+        git_dir: None, work_tree: None,
+    };
+
+    let dir = local.working_dir.as_ref().as_ref();
+    let backup_path = backup.working_dir.as_ref().as_ref().display().to_string();
+    git(dir, &["remote","add","backup",&backup_path]);
+    git(dir, &["push","origin","HEAD:refs/heads/idea/abc123"]);
+    git(dir, &["push","backup","HEAD:refs/heads/stale/def456"]);
+    let head = local.resolve_ref("HEAD").unwrap().unwrap();
+    metadata::append(&local, "origin", &head, "approved-by alice").unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_git-pr-mirror"))
+        .current_dir(dir).args(["origin","backup","--prune"]).stdout(Stdio::null())
+        .status().unwrap();
+    assert!(status.success());
+
+    assert_eq!(backup.resolve_ref("refs/heads/idea/abc123").unwrap(), Some(head.clone()));
+    assert!(backup.resolve_ref("refs/heads/stale/def456").unwrap().is_none());
+    assert_eq!(backup.show_note(metadata::NOTES_REF, &head).unwrap().unwrap(),
+               "v1 approved-by alice\n");
+}
+
+// A read-only account must not be able to delete anything, however pr-clean is invoked.
+#[test]
+fn read_only_mode_refuses_to_clean() {
+    let repo = temp_repo();
+    let dir = repo.working_dir.as_ref().as_ref();
+
+    assert!(!git_pr(dir, &["clean", "--read-only"]).status.success());
+    git(dir, &["config","pr.readOnly","true"]);
+    assert!(!git_pr(dir, &["clean"]).status.success());
+    assert!(repo.resolve_ref("refs/heads/hotfix").unwrap().is_some());
+}
+
+// Members of a restricted role can run what their role allows, and nothing else.
+#[test]
+fn roles_limit_commands() {
+    let repo = temp_repo();
+    let dir = repo.working_dir.as_ref().as_ref();
+    for (key, value) in [("pr.role.contractor.member", "you@example.com"),
+                         ("pr.role.contractor.allow", "env")] {
+        git(dir, &["config","--add",key,value]);
+    }
+
+    assert!(git_pr(dir, &["env"]).status.success());
+    assert_eq!(git_pr(dir, &["clean"]).status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(repo.resolve_ref("refs/heads/hotfix").unwrap().is_some());
+}
+
+// An emergency merge lands on trunk, but leaves its justification in the commit and the audit log.
+#[test]
+fn emergency_merge_is_justified() {
+    let origin = temp_repo();
+    let local = clone_repo(&origin);
+    let dir = local.working_dir.as_ref().as_ref();
+    // Trunk is checked out in origin, so let the clone push to it
+    git(origin.working_dir.as_ref().as_ref(), &["checkout","-q","--detach"]);
+    git(dir, &["commit","-q","--allow-empty","-m","fix prod"]);
+    git(dir, &["push","origin","HEAD:refs/heads/fix/abc123"]);
+    git(dir, &["reset","-q","--hard","HEAD~1"]);
+
+    // Trunk has moved on since the clone last looked, and approvals would hold up a normal merge
+    let origin_dir = origin.working_dir.as_ref().as_ref();
+    git(origin_dir, &["commit","-q","--allow-empty","-m","elsewhere"]);
+    git(origin_dir, &["update-ref","refs/heads/trunk","HEAD"]);
+    let elsewhere = origin.resolve_ref("HEAD").unwrap();
+    git(dir, &["config","pr.minApprovals","1"]);
+
+    let webhook = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    git(dir, &["config","pr.webhookUrl",&format!("http://{}/", webhook.local_addr().unwrap())]);
+    let received = std::thread::spawn(move || {
+        use std::io::{Read, Write};
+        let (mut stream, _) = webhook.accept().unwrap();
+        stream.set_read_timeout(Some(std::time::Duration::from_secs(1))).unwrap();
+        let mut request = vec![];
+        let mut buffer = [0; 4096];
+        while let Ok(n @ 1..) = stream.read(&mut buffer) {
+            request.extend_from_slice(&buffer[..n]);
+            if String::from_utf8_lossy(&request).ends_with('}') {
+                break;
+            }
+        }
+        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        String::from_utf8_lossy(&request).to_string()
+    });
+
+    let merge = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_git-pr-emergency-merge"))
+        .current_dir(dir).args(args).stdin(Stdio::null()).output().unwrap();
+    let output = merge(&["fix"]);
+    assert_eq!(output.status.code(), Some(libgitpr::exit::REFUSED));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("requires a justification"));
+    let output = merge(&["fix","--reason","prod is down"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    if cfg!(feature = "webhook") {
+        let request = received.join().unwrap();
+        assert!(request.contains(r#""event":"emergency-merge""#), "{}", request);
+        assert!(request.contains(r#""reason":"prod is down""#), "{}", request);
+        assert!(request.contains(r#""who":"Your Name <you@example.com>""#), "{}", request);
+    } else {
+        assert!(String::from_utf8_lossy(&output.stderr).contains("without the webhook feature"));
+    }
+    let trunk = origin.resolve_ref("refs/heads/trunk").unwrap().unwrap();
+    assert_eq!(origin.resolve_ref(&format!("{}^1", trunk)).unwrap(), elsewhere);
+    assert_eq!(local.resolve_ref("refs/heads/trunk").unwrap(), Some(trunk));
+    assert!(origin.resolve_ref("refs/pr-state/fix/abc123").unwrap().is_some());
+
+    let message = Command::new("git").arg("-C").arg(origin.working_dir.as_ref().as_ref())
+        .args(["log","-1","--format=%B","trunk"]).output().unwrap();
+    let message = String::from_utf8_lossy(&message.stdout);
+    assert!(message.starts_with("Merge branch 'fix/abc123'"));
+    assert!(message.contains("Emergency-Merge: prod is down"));
+
+    let entries = AuditLog::open(&local).unwrap().entries().unwrap();
+    let entry = entries.last().unwrap();
+    assert_eq!(entry.command, "emergency-merge");
+    assert_eq!(entry.note.as_deref(), Some("EMERGENCY: prod is down"));
+}
+
+// Scripts can pick exactly the fields they want out of git pr-list.
+#[test]
+fn list_with_format_template() {
+    let origin = temp_repo();
+    git(origin.working_dir.as_ref().as_ref(), &["branch","feature/abc123"]);
+    let clone = clone_repo(&origin);
+    let tip = clone.resolve_ref("HEAD").unwrap().unwrap();
+
+    let dir = clone.working_dir.as_ref().as_ref();
+    let output = git_pr(dir, &["list", "--format", r"{name}\t{author}\t{tip}\t{age}"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+               format!("feature\tYour Name <you@example.com>\t{}\tjust now\n", tip));
+
+    assert!(!git_pr(dir, &["list", "--format", "{nope}"]).status.success());
+
+    let output = git_pr(dir, &["list", "--json"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains(r#""branch":"feature/abc123""#));
+}
+
+// With nobody at a terminal to pick a PR, leaving out the name is still a usage error.
+#[test]
+fn missing_pr_name_without_a_terminal() {
+    let git = temp_repo();
+    let output = Command::new(env!("CARGO_BIN_EXE_git-pr-export-patchwork"))
+        .current_dir(git.working_dir.as_ref().as_ref())
+        .stdin(Stdio::null()).output().unwrap();
+    assert_eq!(output.status.code(), Some(libgitpr::exit::USAGE));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Usage:"));
+}
+
+// Aliases defined with git pr-alias are expanded by git pr.
+#[test]
+fn aliases_expand_through_git_pr() {
+    let git = temp_repo();
+    let dir = git.working_dir.as_ref().as_ref();
+    let status = Command::new(env!("CARGO_BIN_EXE_git-pr-alias"))
+        .current_dir(dir).arg("cfg=env").status().unwrap();
+    assert!(status.success());
+
+    let output = git_pr(dir, &["cfg"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"trunk\""));
+
+    let output = git_pr(dir, &["cgf"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("The most similar command is\n\tcfg"));
+}
+
+// Aliases still expand after the shared flags, --profile among them.
+#[test]
+fn aliases_expand_after_a_profile() {
+    let repo = temp_repo();
+    let dir = repo.working_dir.as_ref().as_ref();
+    let status = Command::new(env!("CARGO_BIN_EXE_git-pr-alias"))
+        .current_dir(dir).arg("cfg=env").status().unwrap();
+    assert!(status.success());
+    git(dir, &["config","pr.profile.work.remote","corp"]);
+
+    for flags in [&["--profile", "work"][..], &["--profile=work"], &["-v", "--profile", "work"]] {
+        let output = git_pr(dir, &[flags, &["cfg"]].concat());
+        assert!(output.status.success(), "{:?}: {}", flags,
+                String::from_utf8_lossy(&output.stderr));
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(json["remote"]["value"], "corp");
+    }
+}
+
+// The self-test takes a PR through its whole life with the repo installed here.
+#[test]
+fn selftest_passes() {
+    let output = Command::new(env!("CARGO_BIN_EXE_git-pr-selftest")).output().unwrap();
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", report);
+    assert!(report.contains("ok    clean up merged branches"));
+    assert!(report.contains("8 of 8 steps passed"));
+}
@@ -0,0 +1,50 @@
+//! Measure listing PRs, from git's output through to the index, on a repository with 10k refs.
+//!
+//! Parser throughput is measured on synthetic text, so it doesn't depend on how fast git is. List
+//! latency and `git diff --stat` are measured against a real repository (see
+//! [`libgitpr::bench::synthetic_repo`]), so they include the cost of running git. The repeated
+//! diffstat shows what git's own caching (and the OS's) buys us, which is the baseline that
+//! [`PrIndex::diff_stat`]'s cache has to beat.
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use libgitpr::bench;
+use libgitpr::pull_request::PrIndex;
+use tempdir::TempDir;
+
+
+const REFS: usize = 10_000;
+
+fn parsers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+
+    let branches = bench::branch_listing(REFS);
+    group.throughput(Throughput::Bytes(branches.len() as u64));
+    group.bench_function("git branch -a, 10k PRs", |b| {
+        b.iter(|| libgitpr::extract_pr_names(black_box(&branches), "origin"))
+    });
+
+    let refs = bench::ref_listing(REFS);
+    group.throughput(Throughput::Bytes(refs.len() as u64));
+    group.bench_function("git for-each-ref, 10k PRs", |b| {
+        b.iter(|| PrIndex::from_refs("origin", black_box(&refs)))
+    });
+    group.finish();
+}
+
+fn repository(c: &mut Criterion) {
+    let dir = TempDir::new("git-pr-bench").unwrap();
+    let repo = bench::synthetic_repo(dir.path(), REFS).unwrap();
+
+    c.bench_function("list 10k PRs", |b| {
+        b.iter(|| PrIndex::load(&repo.git, "origin").unwrap())
+    });
+    c.bench_function("repeated diff --stat", |b| {
+        b.iter(|| repo.git.diff_stat(&repo.base, &repo.tip).unwrap())
+    });
+    let mut index = PrIndex::load(&repo.git, "origin").unwrap();
+    c.bench_function("cached diffstat", |b| {
+        b.iter(|| index.diff_stat(&repo.git, "pr-0", &repo.base).unwrap())
+    });
+}
+
+criterion_group!(benches, parsers, repository);
+criterion_main!(benches);
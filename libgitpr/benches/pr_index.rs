@@ -0,0 +1,29 @@
+//! Compare rebuilding the PR index from scratch against updating a single ref.
+//!
+//! The daemon keeps its index current by applying one ref change at a time. This only pays off if
+//! an incremental update is substantially cheaper than a rebuild, even when there are thousands of
+//! refs.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use libgitpr::bench;
+use libgitpr::pull_request::PrIndex;
+
+
+fn rebuild_vs_update(c: &mut Criterion) {
+    let refs = bench::ref_listing(10_000);
+
+    c.bench_function("rebuild 10k refs", |b| {
+        b.iter(|| PrIndex::from_refs("origin", black_box(&refs)))
+    });
+
+    let mut index = PrIndex::from_refs("origin", &refs);
+    let mut tip = 0u64;
+    c.bench_function("update 1 of 10k refs", |b| {
+        b.iter(|| {
+            tip += 1;
+            index.update(black_box("refs/remotes/origin/pr-5000/0001388"), Some(&tip.to_string()))
+        })
+    });
+}
+
+criterion_group!(benches, rebuild_vs_update);
+criterion_main!(benches);
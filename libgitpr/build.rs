@@ -0,0 +1,31 @@
+//! Embed every message catalog in `locales/` into the library
+//!
+//! Each `locales/<language>.txt` becomes an entry in `CATALOGS` (see `src/i18n.rs`), so adding a
+//! translation needs no code changes.
+use std::env;
+use std::fs;
+use std::path::Path;
+
+
+fn main() {
+    let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("locales");
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    let mut catalogs: Vec<(String, String)> = fs::read_dir(&dir).unwrap()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .map(|path| {
+            println!("cargo:rerun-if-changed={}", path.display());
+            let language = path.file_stem().unwrap().to_string_lossy().to_string();
+            (language, path.display().to_string())
+        })
+        .collect();
+    catalogs.sort();
+
+    let mut code = String::from("pub static CATALOGS: &[(&str, &str)] = &[\n");
+    for (language, path) in catalogs {
+        code.push_str(&format!("    ({:?}, include_str!({:?})),\n", language, path));
+    }
+    code.push_str("];\n");
+    fs::write(Path::new(&env::var("OUT_DIR").unwrap()).join("catalogs.rs"), code).unwrap();
+}
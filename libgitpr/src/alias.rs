@@ -0,0 +1,140 @@
+//! Shorthands for git-pr commands
+//!
+//! Like git's own `alias.*` settings, `pr.alias.<name>` defines a command of your own for `git pr`:
+//!
+//! ```console
+//! $ git pr-alias ls=list
+//! $ git pr-alias mine="list --format '{name}\t{age}'"
+//! $ git pr ls
+//! ```
+//!
+//! An alias stands for a command followed by any number of arguments, which are split up the way a
+//! shell would split them. Whatever follows the alias on the command line is passed along after
+//! them. Aliases may refer to other aliases, but not (directly or indirectly) to themselves. As in
+//! git, an alias with the same name as a real command is ignored.
+use crate::{tr, Git, GitError};
+use std::collections::BTreeMap;
+
+
+/// Every alias configured for `git`'s repository (and globally), by name.
+pub fn load(git: &Git) -> Result<BTreeMap<String, String>,GitError> {
+    let mut aliases = BTreeMap::new();
+    for line in git.config_get_regexp(r"^pr\.alias\.")?.lines() {
+        if let Some((key, value)) = line.split_once(' ') {
+            if let Some(name) = key.strip_prefix("pr.alias.") {
+                aliases.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+    Ok(aliases)
+}
+
+/// Split an alias into words, honoring quotes and backslashes the way a shell does.
+pub fn split(text: &str) -> Result<Vec<String>,GitError> {
+    let mut words = vec![];
+    let mut word = None::<String>;
+    let mut quote = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => match chars.next() {
+                Some(escaped) => word.get_or_insert_with(String::new).push(escaped),
+                None => word.get_or_insert_with(String::new).push('\\'),
+            },
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'') | (None, '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            },
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err(GitError::Refused(tr!("alias-unterminated-quote", alias = text)));
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Expand any alias at the start of a command line.
+///
+/// `is_command` says whether a name belongs to a real git-pr command, which aliases cannot
+/// override. An alias that leads back to itself is refused, with the chain of aliases that led
+/// there.
+pub fn expand<F>(args: &[String], aliases: &BTreeMap<String, String>, is_command: F)
+    -> Result<Vec<String>,GitError>
+    where F: Fn(&str) -> bool {
+    let mut args = args.to_vec();
+    let mut seen: Vec<String> = vec![];
+    loop {
+        let name = match args.first() {
+            Some(name) if !is_command(name) => name.clone(),
+            _ => return Ok(args)
+        };
+        let expansion = match aliases.get(&name) {
+            Some(expansion) => expansion,
+            None => return Ok(args)
+        };
+        if seen.contains(&name) {
+            seen.push(name);
+            return Err(GitError::Refused(tr!("alias-loop", chain = seen.join(" -> "))));
+        }
+        if expansion.starts_with('!') {
+            return Err(GitError::Refused(tr!("alias-shell", alias = name)));
+        }
+
+        let mut words = split(expansion)?;
+        if words.is_empty() {
+            return Err(GitError::Refused(tr!("alias-empty", alias = name)));
+        }
+        words.extend(args.drain(1..));
+        args = words;
+        seen.push(name);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn split_like_a_shell() {
+        assert_eq!(split("list --authors").unwrap(), strings(&["list", "--authors"]));
+        assert_eq!(split(r"list --format '{name}\t{age}'").unwrap(),
+                   strings(&["list", "--format", r"{name}\t{age}"]));
+        assert_eq!(split(r#"a "b \"c\"" d\ e ''"#).unwrap(), strings(&["a", "b \"c\"", "d e", ""]));
+        assert!(split("list 'oops").is_err());
+    }
+
+    #[test]
+    fn expand_chains_of_aliases() {
+        let aliases = aliases(&[("ls", "list"), ("la", "ls --authors"), ("list", "clean")]);
+        let is_command = |name: &str| name == "list" || name == "clean";
+
+        assert_eq!(expand(&strings(&["la", "--json"]), &aliases, is_command).unwrap(),
+                   strings(&["list", "--authors", "--json"]));
+        // Real commands win over aliases of the same name
+        assert_eq!(expand(&strings(&["list"]), &aliases, is_command).unwrap(), strings(&["list"]));
+        assert_eq!(expand(&strings(&["nope"]), &aliases, is_command).unwrap(), strings(&["nope"]));
+    }
+
+    #[test]
+    fn refuse_alias_loops() {
+        let aliases = aliases(&[("a", "b"), ("b", "c --x"), ("c", "a")]);
+        match expand(&strings(&["a"]), &aliases, |_| false) {
+            Err(GitError::Refused(message)) => assert_eq!(message, "alias loop: a -> b -> c -> a"),
+            other => panic!("expected a loop, got {:?}", other),
+        }
+    }
+}
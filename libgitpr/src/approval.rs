@@ -0,0 +1,137 @@
+//! Approving pull requests
+//!
+//! An approval is a reviewer's "looks good to me". It is a line of metadata (see
+//! [`crate::metadata`]), `approved-by <Name <email>>`, on the commit which was reviewed, so it
+//! stays with that revision of the PR: once the PR's branch moves on, the new tip has to be
+//! approved again, while the approvals of earlier commits still show who looked at what.
+//!
+//! A team can require approvals before a PR is merged, with `pr.minApprovals`. Only approvals of
+//! the tip count, each person once however many addresses they approved from (see [`reviewers`]),
+//! and never the PR's author.
+//!
+//! Anyone can write any name into an approval, so with `pr.signMetadata` set, approvals are signed
+//! (see [`crate::signing`]): the line ends with a record, `approved-by <who>` followed by `on
+//! <commit>`, which the approver signed (see [`signing::seal_line`]). Only approvals whose records
+//! git trusts, signed by the approver, then count (see [`verified`]).
+use crate::identity::{self, Identity};
+use crate::metadata;
+use crate::signing;
+use crate::{Git, GitError};
+
+
+/// The metadata line recording that `who` approved the commit it is attached to.
+pub fn approve_line(who: &str) -> String {
+    format!("approved-by {}", who)
+}
+
+/// The metadata line recording that `who` approved `commit`, with a signed record vouching for it.
+pub fn sealed_approve_line(git: &Git, who: &str, commit: &str) -> Result<String,GitError> {
+    signing::seal_line(git, &approve_line(who), commit)
+}
+
+/// Record that `who` approves `commit`, and publish the approval to `remote`, signing it if
+/// `pr.signMetadata` is set.
+///
+/// Returns false, without recording anything, if `who` had already approved `commit`.
+pub fn approve(git: &Git, remote: &str, commit: &str, who: &str) -> Result<bool,GitError> {
+    let sign = signing::enabled(git)?;
+
+    // Check again on every attempt, in case the same approval has just arrived from elsewhere
+    let mut applied = false;
+    metadata::update(git, remote, |git| {
+        let already = approvers(&metadata::lines(git, commit)?).iter().any(|known| known == who);
+        if !already && !applied {
+            let line = match sign {
+                true => sealed_approve_line(git, who, commit)?,
+                false => approve_line(who),
+            };
+            git.append_note(metadata::NOTES_REF, commit, &metadata::format_line(&line))?;
+            applied = true;
+        }
+        Ok(())
+    })?;
+    Ok(applied)
+}
+
+/// Who has approved a commit with metadata `lines`, in the order the lines are stored, each once.
+pub fn approvers(lines: &[String]) -> Vec<String> {
+    let mut approvers: Vec<String> = vec![];
+    for line in lines.iter().filter(|line| line.starts_with("approved-by ")) {
+        let who = &signing::split_line(line).0["approved-by ".len()..];
+        if !approvers.iter().any(|known| known == who) {
+            approvers.push(who.to_string());
+        }
+    }
+    approvers
+}
+
+/// Who has approved `commit`, given its metadata `lines`, with a signed record git trusts.
+///
+/// A record only counts if it is for `commit`, and the key it was signed with belongs to the
+/// approver (see [`signing::Signature::is_by`]).
+pub fn verified(git: &Git, lines: &[String], commit: &str) -> Result<Vec<String>,GitError> {
+    let mut verified: Vec<String> = vec![];
+    for line in lines.iter().filter(|line| line.starts_with("approved-by ")) {
+        let who = &signing::split_line(line).0["approved-by ".len()..];
+        let email = match Identity::parse(who) {
+            Some(identity) if !verified.iter().any(|known| known == who) => identity.email,
+            _ => continue,
+        };
+        match signing::check_line(git, line, commit)? {
+            Some(signature) if signature.is_trusted() && signature.is_by(&email) => {
+                verified.push(who.to_string());
+            },
+            _ => {},
+        }
+    }
+    Ok(verified)
+}
+
+/// The distinct people other than `author` among `approvers`, after mapping everyone through the
+/// repository's mailmap, so that one person approving under two addresses counts once.
+pub fn reviewers(git: &Git, approvers: &[String], author: &str)
+    -> Result<Vec<Identity>,GitError> {
+    let mut people: Vec<Identity> = approvers.iter()
+        .filter_map(|who| Identity::parse(who))
+        .collect();
+    people.extend(Identity::parse(author));
+    let mut people = identity::canonicalize(git, &people)?;
+    let author = match Identity::parse(author) {
+        Some(_) => people.pop(),
+        None => None,
+    };
+    let same = |a: &Identity, b: &Identity| a.email.eq_ignore_ascii_case(&b.email);
+
+    let mut reviewers: Vec<Identity> = vec![];
+    for person in people {
+        let known = reviewers.iter().any(|reviewer| same(reviewer, &person));
+        if !known && author.as_ref().is_none_or(|author| !same(author, &person)) {
+            reviewers.push(person);
+        }
+    }
+    Ok(reviewers)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approvers_are_listed_once() {
+        assert!(approvers(&[]).is_empty());
+        let lines = vec![
+            approve_line("Bob <b@example.com>"),
+            "comment approved-by Mallory".to_string(),
+            approve_line("Alice <a@example.com>"),
+            approve_line("Bob <b@example.com>"),
+        ];
+        assert_eq!(approvers(&lines), vec!["Bob <b@example.com>", "Alice <a@example.com>"]);
+    }
+
+    #[test]
+    fn records_are_not_part_of_the_name() {
+        let lines = vec![format!("{} signed:00ff", approve_line("Bob <b@example.com>"))];
+        assert_eq!(approvers(&lines), vec!["Bob <b@example.com>"]);
+    }
+}
@@ -0,0 +1,106 @@
+//! Routing PRs to reviewers
+//!
+//! Where a claim (see [`crate::claim`]) is someone volunteering to review a PR, an assignment is
+//! someone being asked to, usually by a lead sharing out the team's reviews. A PR may be assigned
+//! to several people at once. Each change is a line of metadata (see [`crate::metadata`]) on the
+//! PR's tip: `assigned <at> <Name <email>>`, or `unassigned <at> <Name <email>>` when the
+//! assignment is withdrawn, with the time in seconds since the Unix epoch. Like claims,
+//! assignments are decided by the times written in them, so new lines should be written with
+//! [`crate::claim::next_time`].
+//!
+//! To share reviews out mechanically, [`least_loaded`] picks whichever of the team's reviewers has
+//! the fewest open PRs assigned to them.
+use std::collections::BTreeMap;
+
+
+/// The metadata line recording that a PR was assigned to `who` at time `at`.
+pub fn assign_line(who: &str, at: i64) -> String {
+    format!("assigned {} {}", at, who)
+}
+
+/// The metadata line recording that `who` was taken off a PR at time `at`.
+pub fn unassign_line(who: &str, at: i64) -> String {
+    format!("unassigned {} {}", at, who)
+}
+
+/// Who a PR is assigned to, according to its metadata `lines`, in the order they were assigned.
+pub fn current(lines: &[String]) -> Vec<String> {
+    let mut changes: Vec<(i64, bool, &str)> = lines.iter()
+        .filter_map(|line| {
+            let (kind, rest) = line.split_once(' ')?;
+            let assigned = match kind {
+                "assigned" => true,
+                "unassigned" => false,
+                _ => return None,
+            };
+            let (at, who) = rest.split_once(' ')?;
+            Some((at.parse().ok()?, assigned, who))
+        })
+        .collect();
+    changes.sort_by_key(|(at, _, _)| *at);
+
+    let mut assignees: Vec<String> = vec![];
+    for (_, assigned, who) in changes {
+        assignees.retain(|assignee| assignee != who);
+        if assigned {
+            assignees.push(who.to_string());
+        }
+    }
+    assignees
+}
+
+/// Count how many PRs each person is assigned to, given the metadata lines of every open PR.
+pub fn load<'a, I: IntoIterator<Item = &'a [String]>>(prs: I) -> BTreeMap<String, usize> {
+    let mut load = BTreeMap::new();
+    for lines in prs {
+        for who in current(lines) {
+            *load.entry(who).or_insert(0) += 1;
+        }
+    }
+    load
+}
+
+/// The candidate with the fewest assignments in `load`, or the first such candidate if several tie.
+pub fn least_loaded<'a>(candidates: &'a [String], load: &BTreeMap<String, usize>)
+    -> Option<&'a String> {
+    candidates.iter().min_by_key(|who| load.get(who.as_str()).copied().unwrap_or(0))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: &str = "Alice <a@example.com>";
+    const BOB: &str = "Bob <b@example.com>";
+
+    #[test]
+    fn latest_change_wins() {
+        assert!(current(&[]).is_empty());
+        let lines = vec![
+            unassign_line(ALICE, 300),
+            assign_line(BOB, 200),
+            assign_line(ALICE, 100),
+            "assigned soon Mallory <m@example.com>".to_string(),
+        ];
+        assert_eq!(current(&lines), vec![BOB.to_string()]);
+
+        let lines = vec![assign_line(ALICE, 400), unassign_line(ALICE, 300),
+                         assign_line(BOB, 200)];
+        assert_eq!(current(&lines), vec![BOB.to_string(), ALICE.to_string()]);
+    }
+
+    #[test]
+    fn fewest_assignments_wins() {
+        let first = [assign_line(ALICE, 100), assign_line(BOB, 100)];
+        let second = [assign_line(ALICE, 100)];
+        let load = load([&first[..], &second[..]]);
+        assert_eq!(load.get(ALICE), Some(&2));
+
+        let candidates = [ALICE.to_string(), BOB.to_string(), "Carol <c@example.com>".to_string()];
+        assert_eq!(least_loaded(&candidates, &load).map(String::as_str),
+                   Some("Carol <c@example.com>"));
+        assert_eq!(least_loaded(&candidates[..2], &load).map(String::as_str), Some(BOB));
+        assert_eq!(least_loaded(&[], &load), None);
+    }
+}
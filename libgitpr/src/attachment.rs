@@ -0,0 +1,117 @@
+//! Files attached to PRs
+//!
+//! Screenshots of a UI change, or the log of a failing test, help a review along, and without a
+//! forge there is nowhere else to put them. An attachment is stored as a blob, and published under
+//! its own notes ref, [`NOTES_REF`], as a note attached to the blob itself: that keeps the blob
+//! reachable, so it travels to and from the remote like any other note, and two people attaching
+//! at once merge cleanly. The PR then gets a line of metadata (see [`crate::metadata`]) on its tip,
+//! `attachment <blob> <size> <file name>`, saying what is attached to it.
+//!
+//! Attachments are kept to a modest size (`pr.maxAttachmentKiB`), since every clone that syncs
+//! metadata downloads all of them.
+use crate::metadata;
+use crate::{tr, Git, GitError};
+use std::fmt;
+use std::path::Path;
+
+
+/// Where attachments' contents are kept.
+pub const NOTES_REF: &str = "refs/notes/pr-attachments";
+
+
+/// A file attached to a PR.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    /// The hash of the blob holding the file's contents.
+    pub blob: String,
+
+    /// The file's size in bytes.
+    pub size: u64,
+
+    /// The file's name, without any directories.
+    pub name: String,
+}
+
+impl Attachment {
+    /// The metadata line recording this attachment.
+    pub fn line(&self) -> String {
+        format!("attachment {} {} {}", self.blob, self.size, self.name)
+    }
+
+    /// Parse a line of metadata, if it records an attachment.
+    pub fn parse(line: &str) -> Option<Attachment> {
+        let mut fields = line.strip_prefix("attachment ")?.splitn(3, ' ');
+        let blob = fields.next()?.to_string();
+        let size = fields.next()?.parse().ok()?;
+        let name = fields.next().filter(|name| !name.is_empty())?.to_string();
+        Some(Attachment{ blob, size, name })
+    }
+}
+
+impl fmt::Display for Attachment {
+    /// Render the size the way people write it, like "12.5 KiB".
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.size {
+            size if size < 1024 => write!(f, "{} ({} bytes)", self.name, size),
+            size if size < 1024 * 1024 => write!(f, "{} ({:.1} KiB)", self.name,
+                                                 size as f64 / 1024.0),
+            size => write!(f, "{} ({:.1} MiB)", self.name, size as f64 / (1024.0 * 1024.0)),
+        }
+    }
+}
+
+/// Every attachment recorded in some metadata `lines`, without repeats, in the order they appear.
+pub fn find(lines: &[String]) -> Vec<Attachment> {
+    let mut found: Vec<Attachment> = vec![];
+    for attachment in lines.iter().filter_map(|line| Attachment::parse(line)) {
+        if !found.contains(&attachment) {
+            found.push(attachment);
+        }
+    }
+    found
+}
+
+/// Store the file at `path` and publish it to `remote`, returning the hash of its blob.
+///
+/// This doesn't say which PR the file belongs to; record [`Attachment::line`] for that.
+pub fn store(git: &Git, remote: &str, path: &Path) -> Result<String,GitError> {
+    let blob = git.hash_object(path)?;
+    metadata::update_ref(git, remote, NOTES_REF, |git| git.attach_note(NOTES_REF, &blob, &blob))?;
+    Ok(blob)
+}
+
+/// Refuse a file of `size` bytes called `name` if it is bigger than `limit_kib` KiB.
+pub fn check_size(name: &str, size: u64, limit_kib: u32) -> Result<(),GitError> {
+    match size > u64::from(limit_kib) * 1024 {
+        true => Err(GitError::Refused(tr!("attach-too-large", file = name, limit = limit_kib))),
+        false => Ok(()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_round_trip() {
+        let attachment = Attachment{
+            blob: "abc123".to_string(), size: 2048, name: "login page.png".to_string()
+        };
+        assert_eq!(attachment.line(), "attachment abc123 2048 login page.png");
+        assert_eq!(Attachment::parse(&attachment.line()), Some(attachment.clone()));
+        assert_eq!(attachment.to_string(), "login page.png (2.0 KiB)");
+        assert_eq!(Attachment::parse("attachment abc123 many x.png"), None);
+        assert_eq!(Attachment::parse("attachment abc123 12 "), None);
+        assert_eq!(Attachment::parse("approved-by Bob"), None);
+
+        let lines = vec![attachment.line(), "comment hi".to_string(), attachment.line()];
+        assert_eq!(find(&lines), vec![attachment]);
+    }
+
+    #[test]
+    fn size_limits() {
+        assert!(check_size("log.txt", 1024, 1).is_ok());
+        assert!(matches!(check_size("log.txt", 1025, 1), Err(GitError::Refused(_))));
+    }
+}
@@ -0,0 +1,173 @@
+//! A record of everything git-pr has changed
+//!
+//! Teams working in regulated environments need to be able to say who deleted a branch, and when.
+//! Every git-pr program that modifies refs appends an [`Entry`] to an audit log once it has
+//! finished, whether or not it succeeded. The log is one JSON object per line, stored in
+//! `.git/git-pr/audit.log`. It is shared by every worktree of the repository, but it is not
+//! pushed anywhere; `git pr-audit` prints it.
+use crate::identity::Identity;
+use crate::render::{self, Record};
+use crate::{Git, GitError};
+use serde_json::{json, Value};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The fields shown for an entry, for `--json` and `--format` (see [`crate::render`]).
+pub const FIELDS: &[(&str, &str)] = &[
+    ("time", "when the command finished"),
+    ("who", "who ran it, as \"Name <email>\""),
+    ("command", "which git-pr program ran, like \"clean\""),
+    ("refs", "the refs that were (or would have been) changed"),
+    ("outcome", "\"ok\", or a description of what went wrong"),
+    ("note", "why an emergency merge skipped the usual checks, and the like"),
+];
+
+
+/// One mutating operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Seconds since the Unix epoch.
+    pub time: u64,
+
+    /// Who ran the command, as `Name <email>`.
+    pub who: String,
+
+    /// Which git-pr program ran, like "create" or "clean".
+    pub command: String,
+
+    /// The refs that were (or would have been) changed.
+    pub refs: Vec<String>,
+
+    /// "ok", or a description of what went wrong.
+    pub outcome: String,
+
+    /// Anything else a reviewer of the log ought to know, such as why an emergency merge skipped
+    /// the usual checks.
+    pub note: Option<String>,
+}
+
+impl Entry {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "time": self.time,
+            "who": self.who,
+            "command": self.command,
+            "refs": self.refs,
+            "outcome": self.outcome,
+            "note": self.note,
+        })
+    }
+
+    /// The entry as something to show (see [`FIELDS`]).
+    pub fn to_record(&self) -> Record {
+        Record::new()
+            .with("time", render::Value::Time(self.time as i64))
+            .with("who", self.who.as_str())
+            .with("command", self.command.as_str())
+            .with("refs", self.refs.clone())
+            .with("outcome", self.outcome.as_str())
+            .with("note", self.note.clone())
+    }
+
+    /// Read an entry back from a line of the log, or `None` if the line is damaged.
+    pub(crate) fn from_json(line: &str) -> Option<Entry> {
+        let value: Value = serde_json::from_str(line).ok()?;
+        Some(Entry{
+            time: value["time"].as_u64()?,
+            who: value["who"].as_str()?.to_string(),
+            command: value["command"].as_str()?.to_string(),
+            refs: value["refs"].as_array()?.iter()
+                .filter_map(|r| r.as_str().map(|r| r.to_string()))
+                .collect(),
+            outcome: value["outcome"].as_str()?.to_string(),
+            note: value["note"].as_str().map(|note| note.to_string()),
+        })
+    }
+}
+
+
+/// The audit log for one repository.
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn open(git: &Git) -> Result<AuditLog,GitError> {
+        Ok(AuditLog{ path: git.git_common_dir()?.join("git-pr").join("audit.log") })
+    }
+
+    /// Add an entry to the end of the log.
+    ///
+    /// The log is only ever appended to, never rewritten.
+    pub fn append(&self, entry: &Entry) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", entry.to_json())
+    }
+
+    /// Every entry in the log, oldest first. Lines that cannot be parsed are skipped.
+    pub fn entries(&self) -> io::Result<Vec<Entry>> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => Ok(text.lines().filter_map(Entry::from_json).collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(e)
+        }
+    }
+}
+
+
+/// Append an entry describing how `command` turned out.
+///
+/// This is meant to be called at the very end of a mutating program, with the result it is about
+/// to return. If we can't work out who the user is, we still record the operation.
+pub fn record<T>(git: &Git, command: &str, refs: &[String], result: &Result<T,GitError>)
+    -> Result<(),GitError> {
+    record_with_note(git, command, refs, result, None)
+}
+
+/// Like [`record`], but with a note attached to the entry.
+pub fn record_with_note<T>(git: &Git, command: &str, refs: &[String],
+                           result: &Result<T,GitError>, note: Option<&str>)
+    -> Result<(),GitError> {
+    let who = Identity::current(git).map_or("unknown".to_string(), |i| i.to_string());
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let outcome = match result {
+        Ok(_) => "ok".to_string(),
+        Err(e) => format!("{:?}", e)
+    };
+
+    let entry = Entry{
+        time, who, command: command.to_string(), refs: refs.to_vec(), outcome,
+        note: note.map(|note| note.to_string())
+    };
+    AuditLog::open(git)?.append(&entry)?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_round_trip() {
+        let entry = Entry{
+            time: 1_600_000_000,
+            who: "Your Name <you@example.com>".to_string(),
+            command: "clean".to_string(),
+            refs: vec!["hotfix/0".to_string()],
+            outcome: "ok".to_string(),
+            note: None,
+        };
+        let line = entry.to_json().to_string();
+        assert_eq!(Entry::from_json(&line), Some(entry.clone()));
+
+        let noted = Entry{ note: Some("EMERGENCY: prod is down".to_string()), ..entry };
+        assert_eq!(Entry::from_json(&noted.to_json().to_string()), Some(noted));
+        assert_eq!(Entry::from_json("{\"time\": 5}"), None);
+    }
+}
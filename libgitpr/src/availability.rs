@@ -0,0 +1,88 @@
+//! Who is away
+//!
+//! People going on holiday say so with `git pr away`, so that PRs stop being routed to them (see
+//! [`crate::assignment::least_loaded`]) until they are back. Unlike most metadata, this is about a
+//! person rather than a PR, so it is attached to an object every repository has: the empty tree
+//! (see [`Git::empty_tree`]). Each line is `away <at> <until> <Name <email>>`, with times in
+//! seconds since the Unix epoch; the latest line for each person is the one that counts, and
+//! coming back early records one whose `until` is `at`.
+use crate::claim;
+use crate::{metadata, Git, GitError};
+use std::collections::BTreeMap;
+
+
+/// The metadata line recording, at time `at`, that `who` is away until `until`.
+pub fn away_line(who: &str, at: i64, until: i64) -> String {
+    format!("away {} {} {}", at, until, who)
+}
+
+/// Everyone who is away at time `now`, and when they will be back, according to `lines`.
+pub fn away(lines: &[String], now: i64) -> BTreeMap<String, i64> {
+    let mut latest: BTreeMap<&str, (i64, i64)> = BTreeMap::new();
+    for line in lines {
+        let parsed = line.strip_prefix("away ")
+            .and_then(|rest| rest.split_once(' '))
+            .and_then(|(at, rest)| Some((at.parse::<i64>().ok()?, rest.split_once(' ')?)))
+            .and_then(|(at, (until, who))| Some((at, until.parse::<i64>().ok()?, who)));
+        if let Some((at, until, who)) = parsed {
+            if latest.get(who).is_none_or(|(known, _)| at > *known) {
+                latest.insert(who, (at, until));
+            }
+        }
+    }
+    latest.into_iter()
+        .filter(|(_, (at, until))| *until > *at && *until > now)
+        .map(|(who, (_, until))| (who.to_string(), until))
+        .collect()
+}
+
+/// The lines recording who is away, from the metadata.
+pub fn lines(git: &Git) -> Result<Vec<String>,GitError> {
+    metadata::lines(git, &git.empty_tree()?)
+}
+
+/// Publish that `who` is away until `until`, as of `now`. An `until` of `now` means they are back.
+pub fn record(git: &Git, remote: &str, who: &str, until: i64, now: i64)
+    -> Result<(),GitError> {
+    let anchor = git.empty_tree()?;
+    let mut applied = false;
+    metadata::update(git, remote, |git| {
+        let lines = metadata::lines(git, &anchor)?;
+        let at = claim::next_time(&lines, now);
+        if !applied {
+            let line = away_line(who, at, until.max(at));
+            git.append_note(metadata::NOTES_REF, &anchor, &metadata::format_line(&line))?;
+            applied = true;
+        }
+        Ok(())
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: &str = "Alice <a@example.com>";
+    const BOB: &str = "Bob <b@example.com>";
+
+    #[test]
+    fn latest_line_counts() {
+        assert!(away(&[], 100).is_empty());
+        let lines = vec![
+            away_line(ALICE, 100, 500),
+            away_line(BOB, 100, 300),
+            away_line(ALICE, 200, 200),
+            "away soon 300 Mallory <m@example.com>".to_string(),
+        ];
+        assert!(!away(&lines, 250).contains_key(ALICE));
+        assert_eq!(away(&lines, 250).get(BOB), Some(&300));
+        assert!(away(&lines, 300).is_empty());
+
+        let lines = vec![away_line(ALICE, 200, 200), away_line(ALICE, 300, 900)];
+        assert_eq!(away(&lines, 400).get(ALICE), Some(&900));
+
+        // Coming back is coming back, even if the line had to be written a little in the future
+        assert!(away(&[away_line(ALICE, 100, 900), away_line(ALICE, 101, 101)], 100).is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+//! Synthetic workloads for measuring performance
+//!
+//! Most repositories are small enough that git-pr's speed never comes up, but a busy shared remote
+//! can carry tens of thousands of refs. The criterion benchmarks in `benches/` and the hidden
+//! `git pr-bench` command both measure the workloads built here, so that a change which claims to
+//! make listing faster can show its numbers before and after, and so that a user's machine can be
+//! compared with a developer's.
+//!
+//! There are two kinds of workload: the text git would print for a given number of PR branches,
+//! for timing the parsers on their own, and a real repository with that many PR refs, for timing
+//! whole operations including git itself.
+use crate::{Git, GitError};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+
+/// How many files the synthetic repository's trunk has.
+pub(crate) const FILES: usize = 200;
+
+/// Every how many files the synthetic PR changes one.
+pub(crate) const CHANGE_EVERY: usize = 10;
+
+
+/// `git for-each-ref` output (as from [`Git::remote_refs`]) for `count` PR branches on origin.
+pub fn ref_listing(count: usize) -> String {
+    (0..count)
+        .map(|i| format!("{:040x} refs/remotes/origin/pr-{}/{:07x}\n", i, i, i))
+        .collect()
+}
+
+/// `git branch -a` output (as from [`Git::all_branches`]) for `count` PR branches on origin.
+pub fn branch_listing(count: usize) -> String {
+    let mut listing = String::from("* trunk\n");
+    for i in 0..count {
+        listing.push_str(&format!("  remotes/origin/pr-{}/{:07x}\n", i, i));
+    }
+    listing
+}
+
+
+/// A repository built by [`synthetic_repo`].
+pub struct Repo {
+    pub git: Git,
+
+    /// The commit at the tip of trunk.
+    pub base: String,
+
+    /// The commit every PR branch points to: trunk, with every `CHANGE_EVERY`th file changed.
+    pub tip: String,
+}
+
+// Write the files of a commit, and commit them on top of `parents`.
+fn commit_files(git: &Git, dir: &Path, changed: bool, parents: &[&str])
+    -> Result<String,GitError> {
+    for i in 0..FILES {
+        let mut text: String = (0..20).map(|line| format!("file {} line {}\n", i, line)).collect();
+        if changed && i % CHANGE_EVERY == 0 {
+            text.push_str("a change\n");
+        }
+        fs::write(dir.join(format!("file-{}.txt", i)), text)?;
+    }
+    git.add_all()?;
+    let tree = git.write_tree()?;
+    git.commit_tree(&tree, parents, "Synthetic commit", false)
+}
+
+/// Build a repository in `dir` (which must exist) with `refs` PR branches on origin.
+///
+/// The branches are written straight into `packed-refs`, which is what a large remote's refs
+/// would look like after `git gc`, and takes a moment rather than minutes.
+pub fn synthetic_repo(dir: &Path, refs: usize) -> Result<Repo,GitError> {
+    let git = Git{
+        program: String::from("git"), working_dir: Box::new(dir.to_path_buf()),
+        git_dir: None, work_tree: None,
+    };
+    git.init(false, "trunk")?;
+    git.config_set("user.name", "git-pr bench", false)?;
+    git.config_set("user.email", "bench@example.invalid", false)?;
+
+    let base = commit_files(&git, dir, false, &[])?;
+    git.update_ref("refs/heads/trunk", &base)?;
+    let tip = commit_files(&git, dir, true, &[&base])?;
+
+    let packed: String = (0..refs)
+        .map(|i| format!("{} refs/remotes/origin/pr-{}/{}\n", tip, i, &tip[..7]))
+        .collect();
+    fs::write(git.git_dir()?.join("packed-refs"), packed)?;
+
+    Ok(Repo{ git, base, tip })
+}
+
+/// The median time `f` takes, over `runs` runs.
+pub fn median<F: FnMut()>(runs: usize, mut f: F) -> Duration {
+    let mut times: Vec<Duration> = (0..runs.max(1))
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    times[times.len() / 2]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pull_request::PrIndex;
+
+    #[test]
+    fn listings_parse_back() {
+        assert_eq!(crate::extract_pr_names(&branch_listing(100), "origin").len(), 100);
+        assert_eq!(PrIndex::from_refs("origin", &ref_listing(100)).len(), 100);
+    }
+}
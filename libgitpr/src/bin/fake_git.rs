@@ -67,6 +67,9 @@ fn main() {
                         Some("HEAD") => println!("1234567"),
                         Some(_) => exit(1)
                     },
+
+                    // git rev-parse --is-shallow-repository
+                    Some("--is-shallow-repository") => println!("false"),
                     Some(_) => exit(1)
                 },
 
@@ -0,0 +1,138 @@
+//! A git that misbehaves on cue.
+//!
+//! Runs the real git (or `$FLAKY_GIT_REAL`, if set), except where a script says to do something
+//! else. This lets tests make git fail partway through an operation, or lose a race, or print
+//! nonsense, and check that git-pr copes. Like `fake_git`, it is only meant for tests.
+//!
+//! The script lives in the repository's git directory, in a file called `flaky-git`, so that tests
+//! running side by side don't interfere with each other. Each line is a rule:
+//!
+//! ```text
+//! # <command> <nth> <action>
+//! push 2 exit 128                  the second push fails
+//! fetch * garbage                  every fetch prints invalid UTF-8
+//! * 5 hang 3                       the fifth git call of any kind takes three seconds longer
+//! push * exit 1 !\trefs/x:refs/x   every push prints this (with \n and \t unescaped) and fails
+//! ```
+//!
+//! `<command>` is git's subcommand, like `push`, or `*` for any. `<nth>` counts calls of that
+//! command (or of any command, for `*`) from 1, or is `*` for all of them. The first matching rule
+//! wins, and calls no rule matches go to the real git. Calls are counted in `flaky-git.count`, next
+//! to the script.
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command};
+use std::thread::sleep;
+use std::time::Duration;
+
+
+fn real_git() -> String {
+    env::var("FLAKY_GIT_REAL").unwrap_or_else(|_| String::from("git"))
+}
+
+// Pass the call on to the real git, and exit however it did.
+fn pass_through(args: &[String]) -> ! {
+    match Command::new(real_git()).args(args).status() {
+        Ok(status) => exit(status.code().unwrap_or(1)),
+        Err(_) => exit(1)
+    }
+}
+
+// The repository's git directory, if `-C <dir>` names one.
+fn git_dir(args: &[String]) -> Option<PathBuf> {
+    let dir = match args {
+        [flag, dir, ..] if flag == "-C" => dir,
+        _ => return None
+    };
+    let output = Command::new(real_git())
+        .args(["-C", dir, "rev-parse", "--absolute-git-dir"]).output().ok()?;
+    match output.status.success() {
+        true => Some(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim_end())),
+        false => None
+    }
+}
+
+// The git subcommand being run: the first argument that isn't a global option.
+fn subcommand(args: &[String]) -> String {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-C" | "-c" => { args.next(); },
+            "--version" => return arg.clone(),
+            _ if arg.starts_with('-') => {},
+            _ => return arg.clone()
+        }
+    }
+    String::new()
+}
+
+// Count this call, returning how many calls of `command`, and of any command, there have been.
+fn count(path: &Path, command: &str) -> (usize, usize) {
+    let mut counts: BTreeMap<String, usize> = fs::read_to_string(path).unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter_map(|(name, n)| Some((name.to_string(), n.parse().ok()?)))
+        .collect();
+    *counts.entry(command.to_string()).or_default() += 1;
+    *counts.entry(String::from("*")).or_default() += 1;
+
+    let text: String = counts.iter().map(|(name, n)| format!("{} {}\n", name, n)).collect();
+    let _ = fs::write(path, text);
+    (counts[command], counts["*"])
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("\\n", "\n").replace("\\t", "\t")
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let dir = match git_dir(&args) {
+        Some(dir) => dir,
+        None => pass_through(&args)
+    };
+    let script = match fs::read_to_string(dir.join("flaky-git")) {
+        Ok(script) => script,
+        Err(_) => pass_through(&args)
+    };
+
+    let command = subcommand(&args);
+    let (nth_command, nth_any) = count(&dir.join("flaky-git.count"), &command);
+    let rule = script.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.splitn(3, ' ').collect::<Vec<&str>>())
+        .find(|rule| match rule.as_slice() {
+            ["*", nth, _] => *nth == "*" || nth.parse() == Ok(nth_any),
+            [name, nth, _] => *name == command && (*nth == "*" || nth.parse() == Ok(nth_command)),
+            _ => false
+        });
+    let action = match rule {
+        Some(rule) => rule[2],
+        None => pass_through(&args)
+    };
+
+    let (verb, rest) = action.split_once(' ').unwrap_or((action, ""));
+    match verb {
+        "exit" => {
+            let (code, output) = rest.split_once(' ').unwrap_or((rest, ""));
+            print!("{}", unescape(output));
+            exit(code.parse().unwrap_or(1))
+        },
+        "garbage" => {
+            let _ = io::stdout().write_all(b"\xff\xfe not \xc3\x28 utf-8 \x80\n");
+            exit(0)
+        },
+        "hang" => {
+            sleep(Duration::from_secs(rest.parse().unwrap_or(60)));
+            pass_through(&args)
+        },
+        _ => {
+            eprintln!("flaky_git: unknown action '{}'", action);
+            exit(2)
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! What continuous integration made of a PR
+//!
+//! A CI job reports on a PR with `git pr ci set`: whether the build passed, how each of its named
+//! checks went, with numbers like the percentage of lines covered, and where to find what it
+//! produced, like its log, a coverage report, or binaries. Each report is a line of metadata (see
+//! [`crate::metadata`]) on the PR's tip, with the time in seconds since the Unix epoch, and who
+//! reported it:
+//!
+//! ```text
+//! ci-status <at> <status> by <Name <email>>
+//! ci-check <at> <name> <status> [<metric>=<value> ...] by <Name <email>>
+//! ci-artifact <at> <name> <url> by <Name <email>>
+//! ```
+//!
+//! Anyone can report anything as anyone, so with `pr.signMetadata` set, each line ends with a
+//! record of it which the reporter signed (see [`crate::signing::seal_line`]), and merges only go
+//! by the reports whose records git trusts (see [`verified`]).
+//!
+//! Since a build is of one commit, results stay with the tip they were reported for: a PR pushed
+//! again has none until CI reports on it. The latest status counts, and the latest report of each
+//! check, and URL of each artifact, by name. Merges can be made to wait for checks to pass with
+//! `pr.requiredCheck` (see [`Report::unpassed`]).
+use crate::identity::Identity;
+use crate::signing;
+use crate::{Git, GitError};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+
+/// How a build went.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Pending,
+    Pass,
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Pending => "pending",
+            Status::Pass => "pass",
+            Status::Fail => "fail",
+        })
+    }
+}
+
+impl FromStr for Status {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Status,String> {
+        match text {
+            "pending" => Ok(Status::Pending),
+            "pass" => Ok(Status::Pass),
+            "fail" => Ok(Status::Fail),
+            _ => Err(format!("expected pending, pass, or fail, not '{}'", text)),
+        }
+    }
+}
+
+
+/// How one of a build's checks went, like its tests, or its coverage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub status: Status,
+
+    /// Numbers the check measured, like a coverage percentage, by name.
+    pub metrics: BTreeMap<String, f64>,
+}
+
+impl fmt::Display for Check {
+    /// The check's status, followed by its metrics as `name=value`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.status)?;
+        for (name, value) in &self.metrics {
+            write!(f, " {}={}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Everything CI has reported about one tip.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Report {
+    pub status: Option<Status>,
+
+    /// How each check went, by name.
+    pub checks: BTreeMap<String, Check>,
+
+    /// The URL of each artifact, by name.
+    pub artifacts: BTreeMap<String, String>,
+}
+
+impl Report {
+    /// Gather the CI reports from a tip's metadata `lines`.
+    pub fn from_lines(lines: &[String]) -> Report {
+        let mut status: Option<(i64, Status)> = None;
+        let mut checks: BTreeMap<String, (i64, Check)> = BTreeMap::new();
+        let mut artifacts: BTreeMap<String, (i64, String)> = BTreeMap::new();
+        for line in lines {
+            let (line, _) = split_reporter(line);
+            let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let (at, rest) = match rest.split_once(' ') {
+                Some((at, rest)) => match at.parse::<i64>() {
+                    Ok(at) => (at, rest),
+                    Err(_) => continue,
+                },
+                None => continue,
+            };
+            match kind {
+                "ci-status" => if let Ok(new) = rest.parse() {
+                    if status.is_none_or(|(known, _)| at > known) {
+                        status = Some((at, new));
+                    }
+                },
+                "ci-check" => if let Some((name, check)) = rest.split_once(' ')
+                    .and_then(|(name, rest)| Some((name, parse_check_fields(rest)?))) {
+                    if checks.get(name).is_none_or(|(known, _)| at > *known) {
+                        checks.insert(name.to_string(), (at, check));
+                    }
+                },
+                "ci-artifact" => if let Some((name, url)) = rest.split_once(' ') {
+                    if artifacts.get(name).is_none_or(|(known, _)| at > *known) {
+                        artifacts.insert(name.to_string(), (at, url.to_string()));
+                    }
+                },
+                _ => {},
+            }
+        }
+        Report{
+            status: status.map(|(_, status)| status),
+            checks: checks.into_iter().map(|(name, (_, check))| (name, check)).collect(),
+            artifacts: artifacts.into_iter().map(|(name, (_, url))| (name, url)).collect(),
+        }
+    }
+
+    /// Whether CI has reported anything at all.
+    pub fn is_empty(&self) -> bool {
+        self.status.is_none() && self.checks.is_empty() && self.artifacts.is_empty()
+    }
+
+    /// Those of the `required` checks which have not passed, with how they went, if they ran.
+    pub fn unpassed<'a>(&self, required: &'a [String]) -> Vec<(&'a str, Option<Status>)> {
+        required.iter()
+            .map(|name| (name.as_str(), self.checks.get(name).map(|check| check.status)))
+            .filter(|(_, status)| *status != Some(Status::Pass))
+            .collect()
+    }
+}
+
+
+/// The metadata line recording that `by` reported the build had `status` at time `at`.
+pub fn status_line(status: Status, at: i64, by: &str) -> String {
+    format!("ci-status {} {} by {}", at, status, by)
+}
+
+/// The metadata line recording that `by` reported the build's check `name` went as `check` at
+/// time `at`.
+pub fn check_line(name: &str, check: &Check, at: i64, by: &str) -> String {
+    format!("ci-check {} {} {} by {}", at, name, check, by)
+}
+
+/// The metadata line recording that `by` reported, at time `at`, that the build's artifact `name`
+/// is at `url`.
+pub fn artifact_line(name: &str, url: &str, at: i64, by: &str) -> String {
+    format!("ci-artifact {} {} {} by {}", at, name, url, by)
+}
+
+/// Those of a tip's metadata `lines` which are CI reports with records git trusts, signed by who
+/// reported them (see [`signing::check_line`]). `commit` is the tip.
+pub fn verified(git: &Git, lines: &[String], commit: &str) -> Result<Vec<String>,GitError> {
+    let mut verified = vec![];
+    for line in lines.iter().filter(|line| line.starts_with("ci-")) {
+        let email = match split_reporter(line).1.and_then(Identity::parse) {
+            Some(identity) => identity.email,
+            None => continue,
+        };
+        match signing::check_line(git, line, commit)? {
+            Some(signature) if signature.is_trusted() && signature.is_by(&email) => {
+                verified.push(line.clone());
+            },
+            _ => {},
+        }
+    }
+    Ok(verified)
+}
+
+/// Split a CI report into what it says and who reported it, leaving out any signed record. Older
+/// reports say nothing about who made them.
+pub(crate) fn split_reporter(line: &str) -> (&str, Option<&str>) {
+    // The reporter follows the fixed fields (the status's, or the name and status or URL), and
+    // any metrics, which can't be "by", as they have an "="
+    let (line, _) = signing::split_line(line);
+    let fixed = match line.starts_with("ci-status ") {
+        true => 3,
+        false => 4,
+    };
+    let start: usize = line.split(' ').take(fixed).map(|field| field.len() + 1).sum::<usize>() - 1;
+    match line.get(start..).and_then(|rest| rest.find(" by ")) {
+        Some(at) => (&line[..start + at], Some(&line[start + at + 4..])),
+        None => (line, None),
+    }
+}
+
+/// Parse an artifact given as `name=url`. Neither may be empty or contain whitespace.
+pub fn parse_artifact(text: &str) -> Option<(String, String)> {
+    let (name, url) = text.split_once('=')?;
+    let word = |text: &str| !text.is_empty() && !text.contains(char::is_whitespace);
+    match word(name) && word(url) {
+        true => Some((name.to_string(), url.to_string())),
+        false => None,
+    }
+}
+
+/// Parse a check given as `name=status`, followed by any metrics as `,metric=value`, like
+/// `coverage=pass,lines=87.5`. Names may not be empty or contain whitespace, and values must be
+/// numbers.
+pub fn parse_check(text: &str) -> Option<(String, Check)> {
+    let (name, rest) = text.split_once('=')?;
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    let check = parse_check_fields(&rest.replace(',', " "))?;
+    Some((name.to_string(), check))
+}
+
+// Parse a check as it is written in metadata: its status, then its metrics, between spaces.
+fn parse_check_fields(text: &str) -> Option<Check> {
+    let mut fields = text.split(' ');
+    let status = fields.next()?.parse().ok()?;
+    let mut metrics = BTreeMap::new();
+    for field in fields {
+        let (name, value) = field.split_once('=')?;
+        let value: f64 = value.parse().ok()?;
+        if name.is_empty() || !value.is_finite() {
+            return None;
+        }
+        metrics.insert(name.to_string(), value);
+    }
+    Some(Check{ status, metrics })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOT: &str = "CI Bot <ci@example.com>";
+
+    #[test]
+    fn latest_reports_count() {
+        assert!(Report::from_lines(&[]).is_empty());
+        let lines = vec![
+            status_line(Status::Pass, 300, BOT),
+            status_line(Status::Pending, 100, BOT),
+            artifact_line("log", "https://ci.example.com/1/log", 100, BOT),
+            artifact_line("log", "https://ci.example.com/2/log", 200, BOT),
+            artifact_line("coverage", "https://ci.example.com/2/coverage", 200, BOT),
+            "ci-status soon fail".to_string(),
+            "ci-status 400 exploded".to_string(),
+        ];
+        let report = Report::from_lines(&lines);
+        assert_eq!(report.status, Some(Status::Pass));
+        assert_eq!(report.artifacts.get("log").map(String::as_str),
+                   Some("https://ci.example.com/2/log"));
+        assert_eq!(report.artifacts.len(), 2);
+    }
+
+    #[test]
+    fn checks_and_required_checks() {
+        let (_, tests) = parse_check("tests=fail").unwrap();
+        let (_, coverage) = parse_check("coverage=pass,lines=87.5,branches=80").unwrap();
+        assert_eq!(coverage.to_string(), "pass branches=80 lines=87.5");
+        let lines = vec![
+            check_line("tests", &tests, 100, BOT),
+            check_line("tests", &Check{ status: Status::Pass, metrics: BTreeMap::new() }, 200,
+                       BOT),
+            check_line("coverage", &coverage, 100, BOT),
+            "ci-check 300 lint pass warnings=lots".to_string(),
+        ];
+        let report = Report::from_lines(&lines);
+        assert_eq!(report.checks.len(), 2);
+        assert_eq!(report.checks["coverage"], coverage);
+        let required = vec!["tests".to_string(), "coverage".to_string(), "lint".to_string()];
+        assert_eq!(report.unpassed(&required), vec![("lint", None)]);
+        assert_eq!(Report::from_lines(&lines[..1]).unpassed(&required[..1]),
+                   vec![("tests", Some(Status::Fail))]);
+    }
+
+    #[test]
+    fn reporters() {
+        let by = Check{ status: Status::Pass, metrics: BTreeMap::new() };
+        let lines = vec![
+            "ci-status 100 fail".to_string(),
+            status_line(Status::Pass, 200, BOT),
+            format!("{} signed:00ff", check_line("by", &by, 200, BOT)),
+            artifact_line("by", "by", 200, BOT),
+            artifact_line("log", "https://ci.example.com/log", 200, BOT),
+        ];
+        assert_eq!(split_reporter(&lines[0]), ("ci-status 100 fail", None));
+        assert_eq!(split_reporter(&lines[2]), ("ci-check 200 by pass", Some(BOT)));
+        assert_eq!(split_reporter(&lines[3]), ("ci-artifact 200 by by", Some(BOT)));
+        assert_eq!(split_reporter("ci-check 200 by"), ("ci-check 200 by", None));
+        let report = Report::from_lines(&lines);
+        assert_eq!(report.status, Some(Status::Pass));
+        assert_eq!(report.checks["by"], by);
+        assert_eq!(report.artifacts["log"], "https://ci.example.com/log");
+        assert_eq!(report.artifacts["by"], "by");
+    }
+
+    #[test]
+    fn parsing_artifacts() {
+        assert_eq!(parse_artifact("log=https://ci.example.com/log?a=b"),
+                   Some(("log".to_string(), "https://ci.example.com/log?a=b".to_string())));
+        assert_eq!(parse_artifact("log"), None);
+        assert_eq!(parse_artifact("=https://ci.example.com"), None);
+        assert_eq!(parse_artifact("build log=https://ci.example.com"), None);
+        assert_eq!(parse_check("tests"), None);
+        assert_eq!(parse_check("tests=maybe"), None);
+        assert_eq!(parse_check("coverage=pass,lines=most"), None);
+        assert_eq!(parse_check("coverage=pass,lines=inf"), None);
+        assert_eq!("fail".parse::<Status>(), Ok(Status::Fail));
+        assert!("broken".parse::<Status>().is_err());
+    }
+}
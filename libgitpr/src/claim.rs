@@ -0,0 +1,118 @@
+//! Claiming a PR for review, or to carry on with it
+//!
+//! Someone about to review a PR, or to take over one whose author has moved on, claims it with
+//! `git pr take`, so that nobody else spends their time on the same thing. A claim is a line of
+//! metadata (see [`crate::metadata`]) on the PR's tip commit, saying who made it, when, and when
+//! it expires: `claimed-by <since> <until> <Name <email>>`, with times in seconds since the Unix
+//! epoch.
+//! Claims lapse by themselves once `until` has passed, so a forgotten one never gets in anyone's
+//! way for long; they can also be given up early, which records `released-by <at> <Name <email>>`.
+//! New commits on the PR start it afresh, without a claim.
+//!
+//! Merging metadata sorts its lines, so which claim is current is decided by the times written in
+//! them, not by the order they appear in. To keep that order clear when two changes are made in the
+//! same second, or by people whose clocks disagree, new lines are written with [`next_time`].
+
+
+/// Someone's claim on a PR.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Claim {
+    /// Who claimed the PR, as `Name <email>`.
+    pub who: String,
+
+    /// When the claim was made, and when it expires, in seconds since the Unix epoch.
+    pub since: i64,
+    pub until: i64,
+}
+
+/// The metadata line recording `claim`.
+pub fn claim_line(claim: &Claim) -> String {
+    format!("claimed-by {} {} {}", claim.since, claim.until, claim.who)
+}
+
+/// The metadata line recording that `who` gave up their claim at time `at`.
+pub fn release_line(who: &str, at: i64) -> String {
+    format!("released-by {} {}", at, who)
+}
+
+// Parse `<time> <rest>`.
+fn timed(text: &str) -> Option<(i64, &str)> {
+    let (time, rest) = text.split_once(' ')?;
+    Some((time.parse().ok()?, rest))
+}
+
+/// The time to write in a new line, which is `now`, unless that would not come after every line in
+/// `lines`.
+pub fn next_time(lines: &[String], now: i64) -> i64 {
+    let latest = lines.iter().filter_map(|line| {
+        let (_, rest) = line.split_once(' ')?;
+        let (time, _) = timed(rest)?;
+        Some(time)
+    }).max();
+    latest.map_or(now, |latest| now.max(latest + 1))
+}
+
+/// The claim in force at time `now`, according to a PR's metadata `lines`, if there is one.
+///
+/// Each claim replaces any before it, so this is the most recent claim, unless it has expired or
+/// been released by whoever made it.
+pub fn current(lines: &[String], now: i64) -> Option<Claim> {
+    let latest = lines.iter()
+        .filter_map(|line| {
+            let (since, rest) = timed(line.strip_prefix("claimed-by ")?)?;
+            let (until, who) = timed(rest)?;
+            Some(Claim{ who: who.to_string(), since, until })
+        })
+        .max_by_key(|claim| claim.since)?;
+
+    let released = lines.iter()
+        .filter_map(|line| timed(line.strip_prefix("released-by ")?))
+        .any(|(at, who)| who == latest.who && at >= latest.since);
+    Some(latest).filter(|claim| now < claim.until && !released)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claim(who: &str, since: i64, until: i64) -> Claim {
+        Claim{ who: who.to_string(), since, until }
+    }
+
+    #[test]
+    fn lines_round_trip() {
+        let alice = claim("Alice <alice@example.com>", 100, 200);
+        assert_eq!(claim_line(&alice), "claimed-by 100 200 Alice <alice@example.com>");
+        assert_eq!(current(&[claim_line(&alice)], 150), Some(alice));
+    }
+
+    #[test]
+    fn latest_claim_wins() {
+        let lines = vec![
+            claim_line(&claim("Alice <a@example.com>", 100, 200)),
+            claim_line(&claim("Bob <b@example.com>", 120, 300)),
+            "approved-by alice".to_string(),
+            "claimed-by garbage".to_string(),
+        ];
+        assert_eq!(current(&lines, 150).unwrap().who, "Bob <b@example.com>");
+        assert_eq!(current(&lines, 250).unwrap().who, "Bob <b@example.com>");
+        assert_eq!(current(&lines, 300), None);
+        assert_eq!(next_time(&lines, 110), 121);
+        assert_eq!(next_time(&lines, 500), 500);
+    }
+
+    #[test]
+    fn releases_end_claims() {
+        let mut lines = vec![
+            claim_line(&claim("Alice <a@example.com>", 100, 200)),
+            claim_line(&claim("Bob <b@example.com>", 120, 300)),
+            release_line("Alice <a@example.com>", 125),
+        ];
+        assert_eq!(current(&lines, 150).unwrap().who, "Bob <b@example.com>");
+        lines.push(release_line("Bob <b@example.com>", 130));
+        assert_eq!(current(&lines, 150), None);
+        lines.push(claim_line(&claim("Bob <b@example.com>", 140, 300)));
+        assert_eq!(current(&lines, 150).unwrap().who, "Bob <b@example.com>");
+    }
+}
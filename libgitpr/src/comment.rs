@@ -0,0 +1,37 @@
+//! Commenting on pull requests
+//!
+//! A comment is a line of metadata (see [`crate::metadata`]), `comment <text>`, on the PR's tip,
+//! where `git pr timeline` shows it. Since it is a single line, line breaks in the text become
+//! spaces. In a repository which encrypts metadata (see [`crate::encryption`]), the text is
+//! encrypted for everyone in `pr.encryptTo` before it leaves this clone.
+use crate::{encryption, metadata, tr, Git, GitError};
+
+
+/// `text` as it will be stored: on one line, with runs of whitespace squeezed to single spaces.
+///
+/// Refuses text with nothing in it but whitespace.
+pub fn normalize(text: &str) -> Result<String,GitError> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.is_empty() {
+        true => Err(GitError::Refused(tr!("comment-empty"))),
+        false => Ok(text),
+    }
+}
+
+/// Comment `text` on `commit`, and publish the comment to `remote`.
+pub fn add(git: &Git, remote: &str, commit: &str, text: &str) -> Result<(),GitError> {
+    let line = format!("comment {}", encryption::seal(git, &normalize(text)?)?);
+    metadata::append(git, remote, commit, &line)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_fit_on_one_line() {
+        assert_eq!(normalize("  Looks\n\tgood  to me ").unwrap(), "Looks good to me");
+        assert!(normalize(" \n ").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Comparing two pull requests
+//!
+//! Sometimes two people set out to fix the same problem without realizing it. Before deciding
+//! which PR to keep (or how to combine them), a maintainer wants to know how far apart they are:
+//! which files both of them touched, and which files only one of them did.
+use std::collections::BTreeSet;
+
+
+/// How the changed paths of two PRs relate to one another.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PathOverlap {
+    /// Paths changed by both PRs.
+    pub shared: Vec<String>,
+
+    /// Paths changed only by the first PR.
+    pub only_left: Vec<String>,
+
+    /// Paths changed only by the second PR.
+    pub only_right: Vec<String>,
+}
+
+impl PathOverlap {
+    /// The fraction of all changed paths which both PRs touched, from 0.0 to 1.0.
+    pub fn similarity(&self) -> f64 {
+        let total = self.shared.len() + self.only_left.len() + self.only_right.len();
+        match total {
+            0 => 0.0,
+            _ => self.shared.len() as f64 / total as f64
+        }
+    }
+}
+
+
+/// Split two lists of changed paths into those they share and those unique to each.
+///
+/// Each list comes back sorted, with duplicates removed.
+pub fn overlap(left: &[String], right: &[String]) -> PathOverlap {
+    let left: BTreeSet<&String> = left.iter().collect();
+    let right: BTreeSet<&String> = right.iter().collect();
+    let owned = |paths: Vec<&&String>| paths.into_iter().map(|p| p.to_string()).collect();
+
+    PathOverlap{
+        shared: owned(left.intersection(&right).collect()),
+        only_left: owned(left.difference(&right).collect()),
+        only_right: owned(right.difference(&left).collect()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_shared_and_unique_paths() {
+        let paths = |ps: &[&str]| ps.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let result = overlap(&paths(&["src/b.rs", "src/a.rs", "README"]),
+                             &paths(&["src/a.rs", "tests/t.rs", "src/b.rs"]));
+        assert_eq!(result.shared, vec!["src/a.rs", "src/b.rs"]);
+        assert_eq!(result.only_left, vec!["README"]);
+        assert_eq!(result.only_right, vec!["tests/t.rs"]);
+        assert_eq!(result.similarity(), 0.5);
+
+        assert_eq!(overlap(&[], &[]).similarity(), 0.0);
+    }
+}
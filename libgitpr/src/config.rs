@@ -0,0 +1,345 @@
+//! git-pr's view of how this repository is set up
+//!
+//! Every setting has a built-in default, which can be overridden with git config (under the `pr.`
+//! prefix, so `git config pr.trunk main` changes the name of the trunk branch). Each resolved
+//! [`Setting`] remembers where its value came from, so that `git pr-env` can explain *why* git-pr
+//! believes what it does. A profile (see [`crate::profile`]) can override any of them at once.
+use crate::interop::Guard;
+use crate::profile;
+use crate::{tr, Git, GitError};
+use regex::escape;
+#[cfg(feature = "serde")]
+use serde_json::{json, Value};
+use std::fmt;
+
+
+/// Where a setting's value came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// Nobody configured this, so we used git-pr's built-in value.
+    Default,
+
+    /// The value was read from this git config key.
+    GitConfig(String),
+
+    /// The value was given with this flag on the command line, like `--trunk`.
+    CommandLine(String),
+
+    /// The value was computed from other settings.
+    Derived,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::GitConfig(key) => write!(f, "git config {}", key),
+            Source::CommandLine(flag) => write!(f, "command line {}", flag),
+            Source::Derived => write!(f, "derived"),
+        }
+    }
+}
+
+
+/// A resolved value, along with its provenance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+    fn default(value: T) -> Setting<T> {
+        Setting{ value, source: Source::Default }
+    }
+}
+
+
+/// Every setting git-pr cares about.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// The branch into which PRs are merged (`pr.trunk`).
+    pub trunk: Setting<String>,
+
+    /// The shared remote on which PR branches are published (`pr.remote`).
+    pub remote: Setting<String>,
+
+    /// Identities which may be claimed with `--as` (`pr.botIdentity`, multi-valued).
+    pub bot_identities: Setting<Vec<String>>,
+
+    /// How many days to keep archived PRs before `git pr-maintain` expires them
+    /// (`pr.archiveRetentionDays`). `None` keeps them forever.
+    pub archive_retention_days: Setting<Option<u64>>,
+
+    /// Other tools' branch conventions which should be treated like ordinary branches
+    /// (`pr.allowConvention`, multi-valued). See [`crate::interop`].
+    pub allowed_conventions: Setting<Vec<String>>,
+
+    /// Refuse to run anything that modifies the repository or the remote (`pr.readOnly`).
+    pub read_only: Setting<bool>,
+
+    /// How dates are shown to people (`pr.dateFormat`). See [`crate::date`].
+    pub date_format: Setting<String>,
+
+    /// How many times `git pr merge` tries again when trunk moves on the remote while it is
+    /// merging (`pr.mergeRetries`).
+    pub merge_retries: Setting<u32>,
+
+    /// How many hours a claim made with `git pr take` lasts (`pr.claimHours`). See
+    /// [`crate::claim`].
+    pub claim_hours: Setting<u32>,
+
+    /// The largest file `git pr attach` accepts, in KiB (`pr.maxAttachmentKiB`). See
+    /// [`crate::attachment`].
+    pub max_attachment_kib: Setting<u32>,
+
+    /// Branches besides trunk, like release branches, which every PR must be merged into before
+    /// `git pr clean` deletes it (`pr.base`, multi-valued).
+    pub bases: Setting<Vec<String>>,
+
+    /// CI checks which must have passed on a PR's tip before it is merged or landed
+    /// (`pr.requiredCheck`, multi-valued). See [`crate::ci`].
+    pub required_checks: Setting<Vec<String>>,
+
+    /// How many reviewers other than its author must have approved a PR's tip before it is merged
+    /// or landed (`pr.minApprovals`). See [`crate::approval`].
+    pub min_approvals: Setting<u32>,
+
+    /// The profile whose settings override the rest (`pr.profile`). See [`crate::profile`].
+    pub profile: Setting<Option<String>>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config{
+            trunk: Setting::default("trunk".to_string()),
+            remote: Setting::default("origin".to_string()),
+            bot_identities: Setting::default(vec![]),
+            archive_retention_days: Setting::default(None),
+            allowed_conventions: Setting::default(vec![]),
+            read_only: Setting::default(false),
+            date_format: Setting::default("relative".to_string()),
+            merge_retries: Setting::default(3),
+            claim_hours: Setting::default(48),
+            max_attachment_kib: Setting::default(1024),
+            bases: Setting::default(vec![]),
+            required_checks: Setting::default(vec![]),
+            min_approvals: Setting::default(0),
+            profile: Setting::default(None),
+        }
+    }
+}
+
+impl Config {
+    /// Resolve every setting for the repository `git` points at.
+    pub fn load(git: &Git) -> Result<Config,GitError> {
+        let mut config = Config::default();
+        let profile = profile::apply(git)?;
+        // Settings from the profile say which of its keys they came from (lists, by their last
+        // value, since the profile's values come after everyone else's)
+        let source = |key: &str, value: &str| {
+            Source::GitConfig(profile.as_ref().map_or(key.to_string(), |p| p.origin(key, value)))
+        };
+        if let Some(profile) = &profile {
+            config.profile = Setting{
+                value: Some(profile.name.clone()), source: Source::GitConfig("pr.profile".into())
+            };
+        }
+
+        if let Some(trunk) = git.config_get("pr.trunk")? {
+            config.trunk = Setting{ source: source("pr.trunk", &trunk), value: trunk };
+        }
+        if let Some(remote) = git.config_get("pr.remote")? {
+            config.remote = Setting{ source: source("pr.remote", &remote), value: remote };
+        }
+        let bots = git.config_get_all("pr.botIdentity")?;
+        if !bots.is_empty() {
+            config.bot_identities = Setting{
+                source: source("pr.botIdentity", &bots[bots.len() - 1]), value: bots
+            };
+        }
+
+        if let Some(text) = git.config_get("pr.archiveRetentionDays")? {
+            let days = text.trim().parse()
+                .map_err(|_| GitError::Refused(tr!("config-not-days", value = text)))?;
+            config.archive_retention_days = Setting{
+                value: Some(days), source: source("pr.archiveRetentionDays", &text)
+            };
+        }
+        let conventions = git.config_get_all("pr.allowConvention")?;
+        if !conventions.is_empty() {
+            // Catch typos now, rather than silently ignoring them
+            Guard::new(&conventions)?;
+            config.allowed_conventions = Setting{
+                source: source("pr.allowConvention", &conventions[conventions.len() - 1]),
+                value: conventions,
+            };
+        }
+        if let Some(read_only) = git.config_get("pr.readOnly")? {
+            config.read_only = Setting{
+                value: parse_bool("pr.readOnly", &read_only)?,
+                source: source("pr.readOnly", &read_only)
+            };
+        }
+        if let Some(format) = git.config_get("pr.dateFormat")? {
+            config.date_format = Setting{
+                source: source("pr.dateFormat", &format), value: format
+            };
+        }
+
+        if let Some(text) = git.config_get("pr.mergeRetries")? {
+            let retries = text.trim().parse().map_err(|_| GitError::Refused(
+                tr!("config-not-count", key = "pr.mergeRetries", value = text)
+            ))?;
+            config.merge_retries = Setting{
+                value: retries, source: source("pr.mergeRetries", &text)
+            };
+        }
+        if let Some(text) = git.config_get("pr.claimHours")? {
+            let hours = text.trim().parse().map_err(|_| GitError::Refused(
+                tr!("config-not-count", key = "pr.claimHours", value = text)
+            ))?;
+            config.claim_hours = Setting{
+                value: hours, source: source("pr.claimHours", &text)
+            };
+        }
+        if let Some(text) = git.config_get("pr.maxAttachmentKiB")? {
+            let kib = text.trim().parse().map_err(|_| GitError::Refused(
+                tr!("config-not-count", key = "pr.maxAttachmentKiB", value = text)
+            ))?;
+            config.max_attachment_kib = Setting{
+                value: kib, source: source("pr.maxAttachmentKiB", &text)
+            };
+        }
+
+        let bases = git.config_get_all("pr.base")?;
+        if !bases.is_empty() {
+            config.bases = Setting{
+                source: source("pr.base", &bases[bases.len() - 1]), value: bases
+            };
+        }
+        let checks = git.config_get_all("pr.requiredCheck")?;
+        if !checks.is_empty() {
+            config.required_checks = Setting{
+                source: source("pr.requiredCheck", &checks[checks.len() - 1]), value: checks
+            };
+        }
+        if let Some(text) = git.config_get("pr.minApprovals")? {
+            let count = text.trim().parse().map_err(|_| GitError::Refused(
+                tr!("config-not-count", key = "pr.minApprovals", value = text)
+            ))?;
+            config.min_approvals = Setting{
+                value: count, source: source("pr.minApprovals", &text)
+            };
+        }
+
+        Ok(config)
+    }
+
+    /// Stop a mutating command before it does anything, if this is a read-only account.
+    ///
+    /// Commands which modify the repository call this first, passing along whether `--read-only`
+    /// was given on the command line.
+    pub fn ensure_writable(&self, command: &str, read_only_flag: bool) -> Result<(),GitError> {
+        let reason = match (read_only_flag, self.read_only.value) {
+            (true, _) => tr!("read-only-flag"),
+            (false, true) => tr!("read-only-config", source = self.read_only.source),
+            (false, false) => return Ok(())
+        };
+        Err(GitError::Refused(tr!("read-only-refused", command = command, reason = reason)))
+    }
+
+    /// The guard which keeps git-pr away from other tools' branches.
+    pub fn guard(&self) -> Guard {
+        // Names were validated by `Config::load`; anything else falls back to excluding everything
+        Guard::new(&self.allowed_conventions.value).unwrap_or_default()
+    }
+
+    /// The pattern a remote-tracking branch must match to count as a PR.
+    ///
+    /// This mirrors the rules in [`crate::extract_pr_names`].
+    pub fn pr_branch_pattern(&self) -> Setting<String> {
+        Setting{
+            value: format!(r"^remotes/{}/.+/[a-f\d]+$", escape(&self.remote.value)),
+            source: Source::Derived
+        }
+    }
+
+    /// Describe every setting as JSON, for consumption by scripts and editor plugins.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Value {
+        fn entry<T: Clone + Into<Value>>(setting: &Setting<T>) -> Value {
+            json!({ "value": setting.value.clone().into(), "source": setting.source.to_string() })
+        }
+
+        json!({
+            "trunk": entry(&self.trunk),
+            "remote": entry(&self.remote),
+            "prBranchPattern": entry(&self.pr_branch_pattern()),
+            "botIdentities": entry(&self.bot_identities),
+            "archiveRetentionDays": entry(&self.archive_retention_days),
+            "allowedConventions": entry(&self.allowed_conventions),
+            "readOnly": entry(&self.read_only),
+            "dateFormat": entry(&self.date_format),
+            "mergeRetries": entry(&self.merge_retries),
+            "claimHours": entry(&self.claim_hours),
+            "maxAttachmentKiB": entry(&self.max_attachment_kib),
+            "bases": entry(&self.bases),
+            "requiredChecks": entry(&self.required_checks),
+            "minApprovals": entry(&self.min_approvals),
+            "profile": entry(&self.profile),
+        })
+    }
+}
+
+
+/// Interpret a boolean the way git does.
+fn parse_bool(key: &str, value: &str) -> Result<bool,GitError> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" | "" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(GitError::Refused(tr!("config-not-bool", key = key, value = value)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn defaults_explain_themselves() {
+        let json = Config::default().to_json();
+        assert_eq!(json["trunk"]["value"], "trunk");
+        assert_eq!(json["trunk"]["source"], "default");
+        assert_eq!(json["remote"]["value"], "origin");
+        assert_eq!(json["prBranchPattern"]["source"], "derived");
+    }
+
+    #[test]
+    fn read_only_refuses_writes() {
+        let config = Config::default();
+        assert!(config.ensure_writable("clean", false).is_ok());
+        assert!(config.ensure_writable("clean", true).is_err());
+
+        let config = Config{
+            read_only: Setting{ value: true, source: Source::GitConfig("pr.readOnly".into()) },
+            ..Config::default()
+        };
+        assert!(config.ensure_writable("clean", false).is_err());
+        assert!(parse_bool("pr.readOnly", "Yes").unwrap());
+        assert!(parse_bool("pr.readOnly", "maybe").is_err());
+    }
+
+    #[test]
+    fn pattern_follows_remote() {
+        let config = Config{
+            remote: Setting{
+                value: "up.stream".to_string(), source: Source::GitConfig("pr.remote".into())
+            },
+            ..Config::default()
+        };
+        assert_eq!(config.pr_branch_pattern().value, r"^remotes/up\.stream/.+/[a-f\d]+$");
+    }
+}
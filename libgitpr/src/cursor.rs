@@ -0,0 +1,101 @@
+//! Resumable progress through long lists of work
+//!
+//! Bulk operations like `git pr clean` may have hundreds of branches to get through on an old
+//! repository. Rather than attempting everything at once, they can be asked to process a limited
+//! chunk per run. A [`Cursor`] remembers the last item that was handled, so the next run picks up
+//! where the previous one left off -- even if that run was interrupted halfway through its chunk.
+//!
+//! Cursors live under `.git/git-pr/`, so they are private to the local clone. In a repository with
+//! several worktrees, each worktree keeps its own cursors (see [`Git::git_dir`]), since what there
+//! is left to do depends on what is checked out.
+use crate::{Git, GitError};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+
+/// A bookmark into an ordered list of work items.
+pub struct Cursor {
+    path: PathBuf,
+}
+
+impl Cursor {
+    /// Open (but do not create) the cursor for the named operation.
+    pub fn open(git: &Git, operation: &str) -> Result<Cursor,GitError> {
+        let path = git.git_dir()?.join("git-pr").join(format!("{}.cursor", operation));
+        Ok(Cursor{ path })
+    }
+
+    /// The last item successfully processed by a previous run, if any.
+    pub fn position(&self) -> Option<String> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) if !text.trim().is_empty() => Some(text.trim().to_string()),
+            _ => None
+        }
+    }
+
+    /// Record that `item` has been processed.
+    ///
+    /// This is called after every item (not once per chunk), so an interrupted run loses no more
+    /// than the item it was working on.
+    pub fn advance(&self, item: &str) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, item)
+    }
+
+    /// Forget our position, so that the next run starts from the beginning.
+    pub fn finish(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            other => other
+        }
+    }
+}
+
+
+/// Select the next chunk of work.
+///
+/// `items` must be sorted. We skip every item up to and including `after` (the cursor position),
+/// and then take at most `limit` of the remainder. With no limit, everything remaining is taken.
+pub fn next_chunk<'a>(items: &'a [String], after: Option<&str>, limit: Option<usize>)
+    -> &'a [String] {
+    let start = match after {
+        None => 0,
+        Some(after) => items.iter().position(|i| i.as_str() > after).unwrap_or(items.len())
+    };
+    let end = match limit {
+        None => items.len(),
+        Some(limit) => items.len().min(start + limit)
+    };
+
+    &items[start..end]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<String> {
+        ["a/1", "b/2", "c/3", "d/4"].iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn chunks_start_after_the_cursor() {
+        let items = items();
+        assert_eq!(next_chunk(&items, None, Some(2)), &items[0..2]);
+        assert_eq!(next_chunk(&items, Some("b/2"), Some(2)), &items[2..4]);
+        assert_eq!(next_chunk(&items, Some("c/3"), Some(2)), &items[3..4]);
+        assert!(next_chunk(&items, Some("d/4"), Some(2)).is_empty());
+    }
+
+    // If the item under the cursor has disappeared since the last run (say, somebody deleted it by
+    // hand), we should still resume from the right place.
+    #[test]
+    fn cursor_need_not_be_present() {
+        let items = items();
+        assert_eq!(next_chunk(&items, Some("b/9"), None), &items[2..4]);
+    }
+}
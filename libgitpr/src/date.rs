@@ -0,0 +1,219 @@
+//! Rendering dates and ages for people
+//!
+//! Git hands us timestamps as seconds since the Unix epoch. How they are shown is up to
+//! `pr.dateFormat`:
+//!
+//! * `relative` (the default) shows ages, like "3 days ago"
+//! * `local` shows the date and time in the user's time zone, with month names in their locale
+//!   (taken from `LC_ALL`, `LC_TIME` or `LANG`)
+//! * `iso` shows ISO-8601 timestamps in UTC
+//! * anything else is taken as a `strftime`-style pattern, like `%Y-%m-%d %H:%M`, rendered in the
+//!   user's time zone and locale
+//!
+//! Output meant for scripts (`--porcelain`) always uses `iso`, whatever is configured.
+use chrono::{DateTime, FixedOffset, Local, Locale, Offset, SecondsFormat, TimeZone, Utc};
+use std::convert::TryFrom;
+use std::env;
+
+
+pub(crate) const SECONDS_PER_DAY: i64 = 86_400;
+
+
+/// How dates should be shown.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Style {
+    Relative,
+    Local,
+    Iso,
+    Pattern(String),
+}
+
+impl Style {
+    /// Interpret a `pr.dateFormat` value.
+    pub fn parse(text: &str) -> Style {
+        match text.trim() {
+            "relative" => Style::Relative,
+            "local" => Style::Local,
+            "iso" => Style::Iso,
+            pattern => Style::Pattern(pattern.to_string()),
+        }
+    }
+}
+
+
+/// Describe how long ago something happened, like "5 minutes ago".
+pub fn relative(seconds_ago: i64) -> String {
+    let plural = |n: i64, unit: &str| match n {
+        1 => format!("1 {} ago", unit),
+        n => format!("{} {}s ago", n, unit),
+    };
+    match seconds_ago {
+        s if s < 0 => "in the future".to_string(),
+        s if s < 60 => "just now".to_string(),
+        s if s < 3_600 => plural(s / 60, "minute"),
+        s if s < SECONDS_PER_DAY => plural(s / 3_600, "hour"),
+        s if s < 14 * SECONDS_PER_DAY => plural(s / SECONDS_PER_DAY, "day"),
+        s if s < 60 * SECONDS_PER_DAY => plural(s / (7 * SECONDS_PER_DAY), "week"),
+        s if s < 365 * SECONDS_PER_DAY => plural(s / (30 * SECONDS_PER_DAY), "month"),
+        s => plural(s / (365 * SECONDS_PER_DAY), "year"),
+    }
+}
+
+/// The locale dates should be written in, from the usual environment variables.
+///
+/// Falls back to the POSIX locale if none is set, or the one that is set isn't known.
+pub(crate) fn system_locale() -> Locale {
+    ["LC_ALL", "LC_TIME", "LANG"].iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| {
+            // "de_DE.UTF-8@euro" -> "de_DE"
+            let name = value.split(['.', '@']).next().unwrap_or_default().to_string();
+            Locale::try_from(name.as_str()).ok()
+        })
+        .unwrap_or(Locale::POSIX)
+}
+
+
+/// Renders timestamps according to a [`Style`].
+#[derive(Debug, Clone)]
+pub struct DateFormat {
+    pub style: Style,
+    pub locale: Locale,
+}
+
+impl DateFormat {
+    /// A format for the given style, in the user's locale.
+    pub fn new(style: Style) -> DateFormat {
+        DateFormat{ style, locale: system_locale() }
+    }
+
+    /// The format to use given `pr.dateFormat`, unless the output is for scripts.
+    pub fn configured(setting: &str, porcelain: bool) -> DateFormat {
+        match porcelain {
+            true => DateFormat::new(Style::Iso),
+            false => DateFormat::new(Style::parse(setting)),
+        }
+    }
+
+    /// Render a moment in time, as seen at time `now`.
+    pub fn render(&self, timestamp: i64, now: i64) -> String {
+        self.render_in(timestamp, now, local_offset(timestamp))
+    }
+
+    /// Render just the date part of a moment in time.
+    pub fn render_date(&self, timestamp: i64) -> String {
+        self.render_date_in(timestamp, local_offset(timestamp))
+    }
+
+    /// Like [`DateFormat::render`], in a particular time zone rather than the user's.
+    pub fn render_in(&self, timestamp: i64, now: i64, offset: FixedOffset) -> String {
+        match &self.style {
+            Style::Relative => relative(now - timestamp),
+            Style::Local => self.pattern_in(timestamp, "%-d %b %Y %H:%M", offset),
+            Style::Iso => at(timestamp, Utc.fix()).to_rfc3339_opts(SecondsFormat::Secs, true),
+            Style::Pattern(pattern) => self.pattern_in(timestamp, pattern, offset),
+        }
+    }
+
+    /// Like [`DateFormat::render_date`], in a particular time zone rather than the user's.
+    pub fn render_date_in(&self, timestamp: i64, offset: FixedOffset) -> String {
+        match &self.style {
+            Style::Relative | Style::Local => self.pattern_in(timestamp, "%-d %b %Y", offset),
+            Style::Iso => iso_date(timestamp),
+            Style::Pattern(pattern) => self.pattern_in(timestamp, pattern, offset),
+        }
+    }
+
+    fn pattern_in(&self, timestamp: i64, pattern: &str, offset: FixedOffset) -> String {
+        at(timestamp, offset).format_localized(pattern, self.locale).to_string()
+    }
+}
+
+fn at(timestamp: i64, offset: FixedOffset) -> DateTime<FixedOffset> {
+    offset.timestamp_opt(timestamp, 0).single()
+        .unwrap_or_else(|| offset.from_utc_datetime(&DateTime::UNIX_EPOCH.naive_utc()))
+}
+
+// The user's UTC offset at a given moment, which depends on daylight saving time.
+fn local_offset(timestamp: i64) -> FixedOffset {
+    match Local.timestamp_opt(timestamp, 0).single() {
+        Some(local) => local.offset().fix(),
+        None => Utc.fix()
+    }
+}
+
+
+/// Convert a count of days since the Unix epoch into a (year, month, day) date.
+///
+/// This is Howard Hinnant's `civil_from_days` algorithm, which is exact for the proleptic
+/// Gregorian calendar.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Render a timestamp as an ISO-8601 date (UTC).
+pub fn iso_date(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(SECONDS_PER_DAY));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// The Monday (as a timestamp at midnight UTC) starting the week that contains `timestamp`.
+pub(crate) fn week_start(timestamp: i64) -> i64 {
+    let days = timestamp.div_euclid(SECONDS_PER_DAY);
+    // The Unix epoch was a Thursday, three days after a Monday.
+    (days - (days + 3).rem_euclid(7)) * SECONDS_PER_DAY
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_and_weeks() {
+        assert_eq!(iso_date(0), "1970-01-01");
+        assert_eq!(iso_date(951_782_400), "2000-02-29");
+        assert_eq!(iso_date(-1), "1969-12-31");
+
+        // 2021-06-02 was a Wednesday; its week began on Monday 2021-05-31
+        assert_eq!(iso_date(week_start(1_622_635_200)), "2021-05-31");
+    }
+
+    #[test]
+    fn relative_ages() {
+        assert_eq!(relative(-5), "in the future");
+        assert_eq!(relative(30), "just now");
+        assert_eq!(relative(60), "1 minute ago");
+        assert_eq!(relative(3 * 3_600), "3 hours ago");
+        assert_eq!(relative(3 * SECONDS_PER_DAY), "3 days ago");
+        assert_eq!(relative(21 * SECONDS_PER_DAY), "3 weeks ago");
+        assert_eq!(relative(400 * SECONDS_PER_DAY), "1 year ago");
+    }
+
+    #[test]
+    fn styles_and_zones() {
+        // 2021-06-02 12:00:00 UTC
+        let noon = 1_622_635_200;
+        let tokyo = FixedOffset::east_opt(9 * 3_600).unwrap();
+        let posix = |style| DateFormat{ style, locale: Locale::POSIX };
+
+        assert_eq!(posix(Style::Iso).render_in(noon, noon, tokyo), "2021-06-02T12:00:00Z");
+        assert_eq!(posix(Style::Local).render_in(noon, noon, tokyo), "2 Jun 2021 21:00");
+        assert_eq!(posix(Style::parse("%Y/%m/%d")).render_date_in(noon, tokyo), "2021/06/02");
+        assert_eq!(posix(Style::Relative).render_in(noon, noon + 7_200, tokyo), "2 hours ago");
+
+        let german = DateFormat{ style: Style::Local, locale: Locale::de_DE };
+        assert_eq!(german.render_date_in(noon - 30 * SECONDS_PER_DAY, tokyo), "3 Mai 2021");
+        assert_eq!(DateFormat::configured("relative", true).style, Style::Iso);
+    }
+}
@@ -0,0 +1,38 @@
+//! What a PR is for, in its author's words
+//!
+//! A PR's branch name only says so much. Its description, set with `git pr describe`, explains the
+//! change: why it is needed, what to look at first, how it was tested. Descriptions are kept as git
+//! notes under their own ref, [`NOTES_REF`], attached to the PR's tip, and published to the shared
+//! remote like the rest of a PR's metadata (see [`crate::metadata`]). Unlike metadata, which only
+//! ever gains lines, a description is replaced whole when it is edited, so where two people edit
+//! the same one at once, whoever publishes last wins.
+//!
+//! Like metadata, a description stays with the tip it was written for; `git pr amend` carries it
+//! over to the new revision.
+use crate::metadata;
+use crate::{Git, GitError};
+
+
+/// Where descriptions are attached to PRs' tips.
+pub const NOTES_REF: &str = "refs/notes/pr-descriptions";
+
+
+/// The description of the PR whose tip is `tip`, if it has one, as of the last sync.
+pub fn get(git: &Git, tip: &str) -> Result<Option<String>,GitError> {
+    Ok(git.show_note(NOTES_REF, tip)?
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty()))
+}
+
+/// Bring our descriptions up to date with `remote`'s.
+pub fn sync(git: &Git, remote: &str) -> Result<(),GitError> {
+    metadata::sync_replaced(git, remote, NOTES_REF)?;
+    Ok(())
+}
+
+/// Describe the PR whose tip is `tip` as `text`, replacing any description it had, and publish the
+/// description to `remote`.
+pub fn set(git: &Git, remote: &str, tip: &str, text: &str) -> Result<(),GitError> {
+    let text = text.trim();
+    metadata::update_replaced(git, remote, NOTES_REF, |git| git.replace_note(NOTES_REF, tip, text))
+}
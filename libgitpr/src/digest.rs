@@ -0,0 +1,173 @@
+//! A summary of recent PR activity, for people who'd rather read it than watch for it
+//!
+//! `git pr digest` gathers what happened over a period into a body of text fit for an email: the
+//! PRs opened and merged, the open ones which have gone quiet, and those waiting on the person
+//! reading it. Teams without webhooks can send it from cron with `sendmail`.
+//!
+//! A PR counts as opened when its first commit was authored, and as merged when its merge commit
+//! was made (see [`crate::stats`]). It is stale once its tip has gone unchanged for long enough.
+//! It awaits someone when they don't own it, haven't approved it, and nobody else has claimed it.
+use crate::approval;
+use crate::claim;
+use crate::date;
+use crate::owner;
+use crate::pull_request::PrIndex;
+use crate::stats;
+use crate::tr;
+use crate::{Git, GitError};
+
+
+/// A PR mentioned in a digest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub name: String,
+
+    /// Who the PR belongs to (or, once merged, who wrote it), as `Name <email>`.
+    pub who: String,
+
+    /// When the PR last changed, in seconds since the Unix epoch.
+    pub at: i64,
+}
+
+/// What happened to the PRs between `since` and `until`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Digest {
+    pub remote: String,
+
+    /// The period covered, in seconds since the Unix epoch.
+    pub since: i64,
+    pub until: i64,
+
+    pub opened: Vec<Item>,
+    pub merged: Vec<Item>,
+    pub stale: Vec<Item>,
+    pub awaiting: Vec<Item>,
+}
+
+/// Whether a PR owned by `owner`, with metadata `lines`, is waiting on `me` at time `now`.
+pub fn awaits(me: &str, owner: &str, lines: &[String], now: i64) -> bool {
+    let approved = approval::approvers(lines).iter().any(|who| who == me);
+    let claimed = claim::current(lines, now).is_some_and(|claim| claim.who != me);
+    owner != me && !approved && !claimed
+}
+
+impl Digest {
+    /// Gather the activity on the `index`'s remote from `since` until `now`, on behalf of `me`.
+    ///
+    /// Open PRs whose tips are older than `stale_after` seconds are stale.
+    pub fn gather(git: &Git, index: &PrIndex, trunk: &str, me: &str, since: i64, now: i64,
+                  stale_after: i64) -> Result<Digest,GitError> {
+        let remote = index.remote();
+        let mut digest = Digest{
+            remote: remote.to_string(), since, until: now,
+            opened: vec![], merged: vec![], stale: vec![], awaiting: vec![],
+        };
+
+        for (pr, open) in index.iter().zip(stats::open_prs(git, index, trunk)?) {
+            let lines = crate::metadata::lines(git, &pr.tip)?;
+            let item = Item{
+                name: pr.name.clone(),
+                who: owner::current(&lines).unwrap_or(open.author),
+                at: git.commit_time(&pr.tip)?,
+            };
+            if open.started_at >= since {
+                digest.opened.push(item.clone());
+            }
+            if now - item.at >= stale_after {
+                digest.stale.push(item.clone());
+            }
+            if awaits(me, &item.who, &lines, now) {
+                digest.awaiting.push(item);
+            }
+        }
+
+        let since_date = format!("@{}", since);
+        for pr in stats::merged_prs(git, trunk, remote, Some(&since_date), None)? {
+            let who = git.author_of(&pr.tip)?;
+            digest.merged.push(Item{ name: pr.name, who, at: pr.merged_at });
+        }
+        Ok(digest)
+    }
+
+    /// The digest's title, suitable for an email's subject.
+    pub fn title(&self) -> String {
+        tr!("digest-title", remote = self.remote, since = date::iso_date(self.since),
+            until = date::iso_date(self.until))
+    }
+
+    /// The digest as plain text, or as Markdown.
+    pub fn render(&self, markdown: bool) -> String {
+        let mut out = match markdown {
+            true => format!("# {}\n", self.title()),
+            false => format!("{}\n", self.title()),
+        };
+        let sections = [
+            (tr!("digest-opened", count = self.opened.len()), &self.opened),
+            (tr!("digest-merged", count = self.merged.len()), &self.merged),
+            (tr!("digest-stale", count = self.stale.len()), &self.stale),
+            (tr!("digest-awaiting", count = self.awaiting.len()), &self.awaiting),
+        ];
+        for (heading, items) in sections {
+            match markdown {
+                true => out.push_str(&format!("\n## {}\n\n", heading)),
+                false => out.push_str(&format!("\n{}\n", heading)),
+            }
+            if items.is_empty() {
+                let indent = if markdown { "" } else { "  " };
+                out.push_str(&format!("{}{}\n", indent, tr!("digest-none")));
+            }
+            for item in items {
+                let line = tr!("digest-item", who = item.who,
+                               age = date::relative(self.until - item.at));
+                match markdown {
+                    true => out.push_str(&format!("- `{}` {}\n", item.name, line)),
+                    false => out.push_str(&format!("  - {} {}\n", item.name, line)),
+                }
+            }
+        }
+        out
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ME: &str = "Me <me@example.com>";
+    const YOU: &str = "You <you@example.com>";
+
+    #[test]
+    fn what_awaits_whom() {
+        assert!(awaits(ME, YOU, &[], 100));
+        assert!(!awaits(ME, ME, &[], 100));
+        assert!(!awaits(ME, YOU, &[format!("approved-by {}", ME)], 100));
+        assert!(awaits(ME, YOU, &[format!("approved-by {}", YOU)], 100));
+
+        let claimed = |who: &str| claim::claim_line(&claim::Claim{
+            who: who.to_string(), since: 50, until: 150
+        });
+        assert!(!awaits(ME, YOU, &[claimed(YOU)], 100));
+        assert!(awaits(ME, YOU, &[claimed(ME)], 100));
+        assert!(awaits(ME, YOU, &[claimed(YOU)], 200));
+    }
+
+    #[test]
+    fn rendering() {
+        let digest = Digest{
+            remote: "origin".to_string(), since: 0, until: 86_400,
+            opened: vec![Item{ name: "login".to_string(), who: YOU.to_string(), at: 82_800 }],
+            merged: vec![], stale: vec![], awaiting: vec![],
+        };
+        let text = digest.render(false);
+        assert!(text.starts_with("PR digest for origin, 1970-01-01 to 1970-01-02\n\n\
+                                  New PRs (1)\n"));
+        assert!(text.contains("  - login (You <you@example.com>, last updated 1 hour ago)\n"));
+        assert!(text.contains("\nMerged (0)\n  Nothing\n"));
+
+        let markdown = digest.render(true);
+        assert!(markdown.starts_with("# PR digest for origin"));
+        assert!(markdown.contains("\n## New PRs (1)\n\n- `login` (You <you@example.com>, "));
+        assert!(markdown.contains("\n## Merged (0)\n\nNothing\n"));
+    }
+}
@@ -0,0 +1,169 @@
+//! Diagnosing a setup which doesn't work
+//!
+//! The commonest reasons git-pr fails for a new user are mundane: git is too old, the command was
+//! run outside a repository, there is no `origin`, trunk is called something else, or the remote
+//! won't take pushes. Each of those tends to surface as an unhelpful message from git, halfway
+//! through something else. `git pr doctor` checks for them one by one, and says what to do about
+//! any it finds.
+//!
+//! Unlike the self-test (see [`crate::selftest`]), this looks at the user's own repository and
+//! remote, and changes nothing in either; at most it fetches trunk, so that the push check has a
+//! commit to offer. Each check depends on the ones before it, apart from the first, so after a
+//! failure the rest are skipped.
+use crate::config::{Config, Setting, Source};
+use crate::selftest::{Outcome, Step};
+use crate::{tr, Git, GitError};
+
+
+/// The oldest git which has everything git-pr uses (`merge-tree --write-tree` arrived in 2.38).
+pub const MINIMUM_GIT: (u32, u32) = (2, 38);
+
+/// Find the version number in `git --version` output, like `(2, 43)` for "git version 2.43.0".
+pub fn parse_version(text: &str) -> Option<(u32, u32)> {
+    let number = text.split_whitespace()
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))?;
+    let mut parts = number.split('.').map(|part| part.parse::<u32>().ok());
+    Some((parts.next()??, parts.next()??))
+}
+
+// What the checks have found out so far.
+#[derive(Default)]
+struct Patient {
+    remote: Option<String>,
+    trunk: Option<String>,
+    git: Option<Git>,
+    config: Option<Config>,
+    trunk_commit: Option<String>,
+}
+
+type Check = fn(&mut Patient) -> Result<String,GitError>;
+
+impl Patient {
+    fn git(&self) -> &Git {
+        // Nothing after the repository check runs unless it passed
+        self.git.as_ref().expect("the repository is known once it has been found")
+    }
+
+    fn config(&self) -> &Config {
+        self.config.as_ref().expect("the configuration is known once it has been read")
+    }
+
+    fn version(&mut self) -> Result<String,GitError> {
+        let version = Git::new().version()?.trim().to_string();
+        match parse_version(&version) {
+            Some(found) if found < MINIMUM_GIT => Err(GitError::Refused(tr!(
+                "doctor-old-git", version = version,
+                minimum = format!("{}.{}", MINIMUM_GIT.0, MINIMUM_GIT.1)
+            ))),
+            _ => Ok(version),
+        }
+    }
+
+    fn repository(&mut self) -> Result<String,GitError> {
+        let git = Git::discover().map_err(|_| GitError::Refused(tr!("doctor-no-repository")))?;
+        let path = git.working_dir.as_ref().as_ref().display().to_string();
+        self.git = Some(git);
+        Ok(path)
+    }
+
+    // Read the configuration, with the same overrides the command line would apply.
+    fn configuration(&mut self) -> Result<String,GitError> {
+        let mut config = Config::load(self.git())?;
+        if let Some(remote) = self.remote.take() {
+            config.remote = Setting{
+                value: remote, source: Source::CommandLine("--remote".into())
+            };
+        }
+        if let Some(trunk) = self.trunk.take() {
+            config.trunk = Setting{ value: trunk, source: Source::CommandLine("--trunk".into()) };
+        }
+        self.config = Some(config);
+        Ok(String::new())
+    }
+
+    fn remote(&mut self) -> Result<String,GitError> {
+        let remote = &self.config().remote;
+        match self.git().config_get(&format!("remote.{}.url", remote.value))? {
+            Some(url) => Ok(url),
+            None => Err(GitError::Refused(tr!("doctor-no-remote", remote = remote.value,
+                                              source = remote.source))),
+        }
+    }
+
+    fn trunk(&mut self) -> Result<String,GitError> {
+        let config = self.config();
+        let (remote, trunk) = (&config.remote.value, &config.trunk.value);
+        let heads = self.git().ls_remote_heads(remote, trunk)
+            .map_err(|_| GitError::Refused(tr!("doctor-unreachable", remote = remote)))?;
+        let commit = match heads.split_whitespace().next() {
+            Some(commit) => commit.to_string(),
+            None => return Err(GitError::Refused(tr!("doctor-no-trunk", remote = remote,
+                                                     trunk = trunk,
+                                                     source = config.trunk.source))),
+        };
+        self.trunk_commit = Some(commit.clone());
+        Ok(commit)
+    }
+
+    // Offer the remote its own trunk back, which changes nothing if it is accepted.
+    fn push(&mut self) -> Result<String,GitError> {
+        let config = self.config();
+        let (remote, trunk) = (&config.remote.value, &config.trunk.value);
+        let commit = self.trunk_commit.clone().unwrap_or_default();
+        if self.git().resolve_ref(&commit)?.is_none() {
+            self.git().fetch_refs(remote, &[trunk.to_string()])?;
+        }
+        self.git().push_dry_run(remote, &format!("{}:refs/heads/{}", commit, trunk))
+            .map_err(|_| GitError::Refused(tr!("doctor-no-push", remote = remote)))?;
+        Ok(String::new())
+    }
+}
+
+/// Run every check, in the repository containing the current directory. `remote` and `trunk`
+/// override the configured ones, as `--remote` and `--trunk` do.
+pub fn run(remote: Option<&str>, trunk: Option<&str>) -> Vec<Step> {
+    let mut patient = Patient{
+        remote: remote.map(String::from),
+        trunk: trunk.map(String::from),
+        ..Patient::default()
+    };
+    // Whether the checks after each one depend on it
+    let checks: Vec<(String, Check, bool)> = vec![
+        (tr!("doctor-version"), Patient::version, false),
+        (tr!("doctor-repository"), Patient::repository, true),
+        (tr!("doctor-configuration"), Patient::configuration, true),
+        (tr!("doctor-remote"), Patient::remote, true),
+        (tr!("doctor-trunk"), Patient::trunk, true),
+        (tr!("doctor-push"), Patient::push, true),
+    ];
+
+    let mut failed = false;
+    checks.into_iter().map(|(name, check, needed)| {
+        let outcome = match failed {
+            true => Outcome::Skipped,
+            false => match check(&mut patient) {
+                Ok(detail) => Outcome::Passed(detail),
+                Err(e) => {
+                    failed = needed;
+                    Outcome::Failed(e.to_string())
+                }
+            }
+        };
+        Step{ name, outcome }
+    }).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert_eq!(parse_version("git version 2.43.0\n"), Some((2, 43)));
+        assert_eq!(parse_version("git version 2.39.3 (Apple Git-146)"), Some((2, 39)));
+        assert_eq!(parse_version("git version 2.9.5"), Some((2, 9)));
+        assert!(parse_version("git version 2.9.5").unwrap() < MINIMUM_GIT);
+        assert_eq!(parse_version("no version here"), None);
+    }
+}
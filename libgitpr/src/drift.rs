@@ -0,0 +1,98 @@
+//! Keeping a team's settings in step
+//!
+//! git-pr's behavior depends on `pr.*` settings, and roles (see [`crate::policy`]) are settings
+//! too, so two people with different configs are, in effect, running different workflows. A team
+//! can commit the settings it recommends to trunk at [`PATH`], as an ordinary git config file:
+//!
+//! ```text
+//! [pr]
+//!     trunk = main
+//!     mergeRetries = 5
+//!     requiredCheck = tests
+//! [pr "role.contractor"]
+//!     member = bob@example.com
+//!     allow = list
+//! ```
+//!
+//! `git pr env-diff` compares it with the settings in effect, and reports every key whose values
+//! differ, or which is recommended but not set. Roles are part of the workflow, so a `pr.role.*`
+//! key that is set but not recommended counts as drift too; other keys the file doesn't mention
+//! are left to each person.
+use std::collections::BTreeMap;
+
+
+/// Where the recommended settings live, relative to the top of trunk's tree.
+pub const PATH: &str = ".git-pr/recommended-config";
+
+/// The keys [`compare`] looks at, as a pattern for `git config --get-regexp`.
+pub const PATTERN: &str = r"^pr\.";
+
+
+/// A key whose values differ from those recommended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drift {
+    /// The key, as git lists it, like `pr.mergeretries`.
+    pub key: String,
+
+    /// The values recommended, which is none for a key that shouldn't be set.
+    pub recommended: Vec<String>,
+
+    /// The values in effect, which is none for a key that isn't set.
+    pub actual: Vec<String>,
+}
+
+
+/// Compare the `recommended` settings with the `actual` ones, both given as `git config
+/// --get-regexp` output, returning each key that differs, in order.
+pub fn compare(recommended: &str, actual: &str) -> Vec<Drift> {
+    let recommended = values(recommended);
+    let mut actual = values(actual);
+    let mut drift = vec![];
+    for (key, wanted) in recommended {
+        let have = actual.remove(&key).unwrap_or_default();
+        if have != wanted {
+            drift.push(Drift{ key, recommended: wanted, actual: have });
+        }
+    }
+    for (key, have) in actual {
+        if key.starts_with("pr.role.") {
+            drift.push(Drift{ key, recommended: vec![], actual: have });
+        }
+    }
+    drift.sort_by(|a, b| a.key.cmp(&b.key));
+    drift
+}
+
+// The values of each key in `git config --get-regexp` output. A key listed without a value is a
+// boolean set to true, as git reads it.
+fn values(listing: &str) -> BTreeMap<String, Vec<String>> {
+    let mut values: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for line in listing.lines().filter(|line| !line.is_empty()) {
+        let (key, value) = line.split_once(' ').unwrap_or((line, "true"));
+        values.entry(key.to_string()).or_default().push(value.to_string());
+    }
+    values
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drift_is_found() {
+        let recommended = "pr.trunk main\npr.mergeretries 5\npr.readonly\n\
+                           pr.role.contractor.allow list\npr.role.contractor.allow compare\n";
+        let actual = "pr.trunk main\npr.mergeretries 3\npr.readonly true\npr.claimhours 12\n\
+                      pr.role.contractor.allow list\npr.role.lead.member alice@example.com\n";
+        let drift = compare(recommended, actual);
+        let keys: Vec<&str> = drift.iter().map(|d| d.key.as_str()).collect();
+        assert_eq!(keys, vec!["pr.mergeretries", "pr.role.contractor.allow",
+                              "pr.role.lead.member"]);
+        assert_eq!(drift[0].recommended, vec!["5"]);
+        assert_eq!(drift[0].actual, vec!["3"]);
+        assert_eq!(drift[1].recommended, vec!["list", "compare"]);
+        assert!(drift[2].recommended.is_empty());
+        assert!(compare(recommended, recommended).is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+//! Confidential metadata
+//!
+//! Some teams keep their bare repository with a hosting company, but can't let that company read
+//! what reviewers say. For them, the text of comments can be encrypted before it is stored as
+//! metadata (see [`crate::metadata`]). As with signing (see [`crate::signing`]), the
+//! cryptography is left to a program people already trust: [age](https://age-encryption.org) or
+//! GPG.
+//!
+//! Encryption is configured per repository:
+//!
+//! - `pr.encrypt` names the program, `age` or `gpg`. Without it, metadata is stored in the clear.
+//! - `pr.encryptTo` (multi-valued) lists the recipients: age public keys, or GPG key IDs. Everyone
+//!   on the team should be listed, including whoever is writing.
+//! - `pr.decryptIdentity` is the age identity file to decrypt with. GPG finds its own keys, and
+//!   honors `gpg.program`, as git does.
+//!
+//! Only the text is encrypted; the kind of line (`comment`, and so on) stays readable, so that
+//! metadata can still be merged and sorted. Encrypted text is written as `encrypted:<program>:`
+//! followed by the ciphertext in hex, which keeps it on a single line.
+use crate::{tr, Git, GitError};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+
+/// What encrypted text starts with.
+pub const MARKER: &str = "encrypted:";
+
+
+/// A program which can encrypt metadata.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scheme {
+    Age,
+    Gpg,
+}
+
+impl Scheme {
+    pub fn parse(text: &str) -> Option<Scheme> {
+        match text {
+            "age" => Some(Scheme::Age),
+            "gpg" => Some(Scheme::Gpg),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scheme::Age => "age",
+            Scheme::Gpg => "gpg",
+        }
+    }
+
+    // The program to run, and the arguments which make it read from stdin and write to stdout.
+    fn command(&self, git: &Git, recipients: &[String], encrypt: bool)
+        -> Result<(String, Vec<String>),GitError> {
+        let mut args = vec![];
+        let program = match self {
+            Scheme::Age => {
+                match encrypt {
+                    true => for recipient in recipients {
+                        args.extend(["--recipient".to_string(), recipient.clone()]);
+                    },
+                    false => {
+                        args.push("--decrypt".to_string());
+                        if let Some(identity) = git.config_get("pr.decryptIdentity")? {
+                            args.extend(["--identity".to_string(), identity]);
+                        }
+                    }
+                }
+                "age".to_string()
+            }
+            Scheme::Gpg => {
+                args.extend(["--batch".to_string(), "--quiet".to_string()]);
+                match encrypt {
+                    true => {
+                        args.push("--encrypt".to_string());
+                        for recipient in recipients {
+                            args.extend(["--recipient".to_string(), recipient.clone()]);
+                        }
+                    }
+                    false => args.push("--decrypt".to_string()),
+                }
+                git.config_get("gpg.program")?.unwrap_or_else(|| "gpg".to_string())
+            }
+        };
+        Ok((program, args))
+    }
+}
+
+
+/// How this repository encrypts metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keys {
+    pub scheme: Scheme,
+    pub recipients: Vec<String>,
+}
+
+impl Keys {
+    /// The repository's encryption settings, or `None` if metadata is stored in the clear.
+    pub fn configured(git: &Git) -> Result<Option<Keys>,GitError> {
+        let name = match git.config_get("pr.encrypt")? {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        let scheme = Scheme::parse(name.trim()).ok_or_else(|| {
+            GitError::Refused(tr!("encryption-unknown", program = name))
+        })?;
+        let recipients = git.config_get_all("pr.encryptTo")?;
+        if recipients.is_empty() {
+            return Err(GitError::Refused(tr!("encryption-no-recipients")));
+        }
+        Ok(Some(Keys{ scheme, recipients }))
+    }
+
+    /// Encrypt `text` for every recipient.
+    pub fn encrypt(&self, git: &Git, text: &str) -> Result<String,GitError> {
+        let (program, args) = self.scheme.command(git, &self.recipients, true)?;
+        let ciphertext = pipe(&program, &args, text.as_bytes())?;
+        Ok(format!("{}{}:{}", MARKER, self.scheme.name(), to_hex(&ciphertext)))
+    }
+}
+
+/// Encrypt `text` if the repository says to, or return it as it is.
+pub fn seal(git: &Git, text: &str) -> Result<String,GitError> {
+    match Keys::configured(git)? {
+        Some(keys) => keys.encrypt(git, text),
+        None => Ok(text.to_string()),
+    }
+}
+
+/// Decrypt `text`, if it was encrypted.
+pub fn decrypt(git: &Git, text: &str) -> Result<String,GitError> {
+    let (scheme, hex) = match split(text) {
+        Some(parts) => parts,
+        None => return Ok(text.to_string()),
+    };
+    let ciphertext = from_hex(hex).ok_or_else(|| GitError::Refused(tr!("encryption-garbled")))?;
+    let (program, args) = scheme.command(git, &[], false)?;
+    Ok(String::from_utf8_lossy(&pipe(&program, &args, &ciphertext)?).to_string())
+}
+
+/// The text to show someone: decrypted if it can be, or a note saying it couldn't.
+pub fn reveal(git: &Git, text: &str) -> String {
+    decrypt(git, text).unwrap_or_else(|_| tr!("encryption-unreadable"))
+}
+
+/// A line of metadata as it should be shown: with its text revealed, if it was encrypted.
+pub fn reveal_line(git: &Git, line: &str) -> String {
+    match line.split_once(' ') {
+        Some((kind, text)) if text.starts_with(MARKER) => format!("{} {}", kind, reveal(git, text)),
+        _ => line.to_string(),
+    }
+}
+
+// Split encrypted text into its scheme and hex ciphertext.
+fn split(text: &str) -> Option<(Scheme, &str)> {
+    let (name, hex) = text.strip_prefix(MARKER)?.split_once(':')?;
+    Some((Scheme::parse(name)?, hex))
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub(crate) fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// Run `program`, feeding it `input`, and return what it prints.
+fn pipe(program: &str, args: &[String], input: &[u8]) -> Result<Vec<u8>,GitError> {
+    let mut child = Command::new(program).args(args)
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| GitError::Refused(tr!("encryption-failed", program = program, error = e)))?;
+    // Metadata is small, so all of it fits in the pipe before the program has to answer
+    child.stdin.take().map(|mut stdin| stdin.write_all(input)).transpose()?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(GitError::Refused(tr!("encryption-failed", program = program, error = error)));
+    }
+    Ok(output.stdout)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trip() {
+        let bytes = [0, 1, 0x7f, 0x80, 0xff];
+        assert_eq!(to_hex(&bytes), "00017f80ff");
+        assert_eq!(from_hex("00017f80ff").unwrap(), bytes);
+        assert_eq!(from_hex("0"), None);
+        assert_eq!(from_hex("zz"), None);
+        assert_eq!(from_hex("é1"), None);
+    }
+
+    #[test]
+    fn spotting_encrypted_text() {
+        assert_eq!(split("encrypted:age:00ff"), Some((Scheme::Age, "00ff")));
+        assert_eq!(split("encrypted:gpg:"), Some((Scheme::Gpg, "")));
+        assert_eq!(split("encrypted:rot13:00ff"), None);
+        assert_eq!(split("Looks good to me"), None);
+        assert_eq!(decrypt(&Git::new(), "Looks good to me").unwrap(), "Looks good to me");
+    }
+}
@@ -0,0 +1,18 @@
+//! Exit statuses for git-pr commands
+//!
+//! These follow git's own commands, so that scripts can treat `git pr` like any other part of git.
+//! [`crate::GitError::exit_code`] picks the right one for an error.
+
+
+/// Everything went as asked.
+pub const SUCCESS: i32 = 0;
+
+/// git-pr refused to go on: a role or `--read-only` forbids the command, no PR has the given name,
+/// and so on. The message says why.
+pub const REFUSED: i32 = 1;
+
+/// git itself failed, or could not be run at all. git uses this status for its fatal errors.
+pub const FATAL: i32 = 128;
+
+/// The command line didn't make sense. git uses this status for its usage errors.
+pub const USAGE: i32 = 129;
@@ -0,0 +1,80 @@
+//! Guessing what the user meant
+//!
+//! When a name doesn't match anything, git suggests the closest matches ("The most similar command
+//! is ..."). We do the same for PR names, using the edit distance between what was typed and what
+//! exists: the number of single-character insertions, deletions, substitutions, and swaps of
+//! neighbouring characters it takes to turn one into the other.
+
+
+/// The edit distance between two strings, counting a swap of neighbouring characters as one edit.
+pub(crate) fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // rows[i][j] is the distance between the first i characters of a and the first j of b
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// The candidates closest to `word`, best first, or nothing if none are close enough to be worth
+/// suggesting.
+///
+/// A candidate is close enough if it is within a third of `word`'s length (but always allowing
+/// two edits). Only the candidates tied for the smallest distance are returned, in alphabetical
+/// order, so a clear winner is suggested on its own.
+pub fn closest<'a>(word: &str, candidates: &[&'a str]) -> Vec<&'a str> {
+    let limit = (word.chars().count() / 3).max(2);
+    let mut scored: Vec<(usize, &str)> = candidates.iter()
+        .map(|candidate| (distance(word, candidate), *candidate))
+        .filter(|(score, _)| *score <= limit)
+        .collect();
+    scored.sort_unstable();
+    scored.dedup();
+
+    let best = match scored.first() {
+        Some((score, _)) => *score,
+        None => return vec![]
+    };
+    scored.into_iter().take_while(|(score, _)| *score == best).map(|(_, c)| c).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(distance("hotfix", "hotfix"), 0);
+        assert_eq!(distance("hotfx", "hotfix"), 1);
+        assert_eq!(distance("hotifx", "hotfix"), 1);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn suggest_only_the_closest() {
+        let names = ["hotfix", "hotfox", "feature", "docs"];
+        assert_eq!(closest("fetaure", &names), vec!["feature"]);
+        assert_eq!(closest("hotfex", &names), vec!["hotfix", "hotfox"]);
+        assert!(closest("refactor", &names).is_empty());
+    }
+}
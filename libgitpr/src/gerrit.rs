@@ -0,0 +1,144 @@
+//! Importing changes from Gerrit
+//!
+//! Teams moving from Gerrit to a plain bare repository can bring their open reviews with them.
+//! The input is the JSON that Gerrit's REST API returns for
+//! `/changes/?o=ALL_REVISIONS&o=DETAILED_LABELS` (with or without the `)]}'` line Gerrit puts in
+//! front of it), and the patch set commits must already have been fetched, for instance with
+//! `git fetch <gerrit> 'refs/changes/*:refs/gerrit/changes/*'`.
+//!
+//! Each open change becomes a PR named `gerrit-<number>`. Every patch set becomes one iteration of
+//! that PR: a branch `gerrit-<number>/<short hash>`. Votes are recorded as metadata on the latest
+//! patch set, with a Code-Review +2 becoming an approval. Merged and abandoned changes are skipped.
+use crate::approval;
+use serde_json::Value;
+
+
+/// One revision of a change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchSet {
+    pub number: u64,
+    pub commit: String,
+}
+
+/// A Gerrit change, reduced to what git-pr can represent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub number: u64,
+    pub subject: String,
+
+    /// "NEW", "MERGED" or "ABANDONED".
+    pub status: String,
+
+    /// Every patch set, oldest first.
+    pub patch_sets: Vec<PatchSet>,
+
+    /// Metadata lines describing the votes on the change.
+    pub votes: Vec<String>,
+}
+
+impl Change {
+    /// Is the change still under review?
+    pub fn is_open(&self) -> bool {
+        self.status == "NEW"
+    }
+
+    /// The PR name this change is imported as.
+    pub fn pr_name(&self) -> String {
+        format!("gerrit-{}", self.number)
+    }
+
+    /// The branch for one patch set.
+    pub fn branch(&self, patch_set: &PatchSet) -> String {
+        let short = &patch_set.commit[..patch_set.commit.len().min(7)];
+        format!("{}/{}", self.pr_name(), short)
+    }
+}
+
+
+// Turn one entry of a label's "all" list into a metadata line, if it is an actual vote.
+fn vote_line(label: &str, vote: &Value) -> Option<String> {
+    let value = vote["value"].as_i64().filter(|v| *v != 0)?;
+    let name = vote["name"].as_str()?;
+    let who = match vote["email"].as_str() {
+        Some(email) => format!("{} <{}>", name, email),
+        None => name.to_string()
+    };
+    match (label, value) {
+        ("Code-Review", 2) => Some(approval::approve_line(&who)),
+        _ => Some(format!("vote {} {:+} by {}", label, value, who))
+    }
+}
+
+/// Parse Gerrit's JSON list of changes.
+pub fn parse_changes(text: &str) -> Result<Vec<Change>,String> {
+    // Gerrit guards its JSON against XSSI by prefixing it with a line that isn't valid JSON.
+    let text = text.trim_start().strip_prefix(")]}'").unwrap_or(text);
+    let changes: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let changes = changes.as_array().ok_or("expected a JSON list of changes")?;
+
+    let mut parsed = vec![];
+    for change in changes {
+        let number = change["_number"].as_u64().ok_or("change without a _number")?;
+        let mut patch_sets: Vec<PatchSet> = change["revisions"].as_object()
+            .map(|revisions| revisions.iter()
+                .filter_map(|(commit, revision)| Some(PatchSet{
+                    number: revision["_number"].as_u64()?, commit: commit.clone()
+                }))
+                .collect())
+            .unwrap_or_default();
+        patch_sets.sort_by_key(|p| p.number);
+
+        let mut votes = vec![];
+        if let Some(labels) = change["labels"].as_object() {
+            for (label, detail) in labels {
+                for vote in detail["all"].as_array().into_iter().flatten() {
+                    votes.extend(vote_line(label, vote));
+                }
+            }
+        }
+
+        parsed.push(Change{
+            number,
+            subject: change["subject"].as_str().unwrap_or_default().to_string(),
+            status: change["status"].as_str().unwrap_or_default().to_string(),
+            patch_sets,
+            votes,
+        });
+    }
+    Ok(parsed)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rest_export() {
+        let text = r#")]}'
+            [{"_number": 42, "subject": "Fix the frobnicator", "status": "NEW",
+              "revisions": {
+                "bbbbbbbbbbbb": {"_number": 2},
+                "aaaaaaaaaaaa": {"_number": 1}
+              },
+              "labels": {"Code-Review": {"all": [
+                {"value": 2, "name": "Alice", "email": "alice@example.com"},
+                {"value": -1, "name": "Bob"},
+                {"value": 0, "name": "Carol"}
+              ]}}},
+             {"_number": 7, "status": "MERGED", "revisions": {}}]"#;
+
+        let changes = parse_changes(text).unwrap();
+        assert_eq!(changes.len(), 2);
+        let change = &changes[0];
+        assert!(change.is_open());
+        assert!(!changes[1].is_open());
+        assert_eq!(change.patch_sets.iter().map(|p| p.number).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(change.branch(&change.patch_sets[1]), "gerrit-42/bbbbbbb");
+        assert_eq!(change.votes, vec![
+            "approved-by Alice <alice@example.com>", "vote Code-Review -1 by Bob"
+        ]);
+
+        assert!(parse_changes("{}").is_err());
+    }
+}
@@ -0,0 +1,170 @@
+//! A bridge to Gitea and Forgejo
+//!
+//! Self-hosters often run Forgejo (or Gitea, which it forked from; their APIs are the same) next to
+//! plain bare repositories, and while migrating between the two want a single place to review.
+//! This module talks to the server's REST API so that `git pr-gitea` can copy open pull requests
+//! from the forge into git-pr, and publish git-pr's PRs on the forge.
+//!
+//! It is only built with the `gitea` feature, since it needs an HTTP client. Configure it with:
+//!
+//! ```console
+//! $ git config pr.giteaUrl https://forge.example.com/api/v1/repos/<owner>/<repo>
+//! $ git config pr.giteaRemote forge   # the git remote for the same repository; default "gitea"
+//! $ export GITEA_TOKEN=...            # an access token, if the repository isn't public
+//! ```
+//!
+//! Which PRs have already been mirrored is recorded in metadata, as a `gitea-pr <number>` line on
+//! the PR's tip, so running the bridge repeatedly doesn't create duplicates in either direction.
+use crate::{tr, Git, GitError};
+use serde_json::{json, Value};
+use std::env;
+use std::io;
+
+
+/// How many PRs to ask for per page. Gitea caps this at 50 by default.
+const PAGE_SIZE: usize = 50;
+
+
+/// An open pull request on the forge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForgePr {
+    pub number: u64,
+    pub title: String,
+
+    /// The forge user who opened it.
+    pub author: String,
+
+    /// The commit at the tip of the PR.
+    pub head_sha: String,
+}
+
+impl ForgePr {
+    /// The git-pr branch this PR is mirrored as, like "gitea-12/1234567".
+    pub fn branch(&self) -> String {
+        format!("gitea-{}/{}", self.number, &self.head_sha[..self.head_sha.len().min(7)])
+    }
+
+    /// The ref under which the forge publishes this PR's commits.
+    pub fn head_ref(&self) -> String {
+        format!("refs/pull/{}/head", self.number)
+    }
+}
+
+
+/// Parse a page of the forge's `GET /pulls` response.
+pub(crate) fn parse_pulls(text: &str) -> Result<Vec<ForgePr>,String> {
+    let pulls: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let pulls = pulls.as_array().ok_or("expected a JSON list of pull requests")?;
+    pulls.iter()
+        .map(|pull| Some(ForgePr{
+            number: pull["number"].as_u64()?,
+            title: pull["title"].as_str().unwrap_or_default().to_string(),
+            author: pull["user"]["login"].as_str().unwrap_or_default().to_string(),
+            head_sha: pull["head"]["sha"].as_str()?.to_string(),
+        }))
+        .collect::<Option<Vec<ForgePr>>>()
+        .ok_or_else(|| "pull request without a number or head".to_string())
+}
+
+/// The metadata line marking a commit as mirrored to or from forge PR `number`.
+pub fn mirror_line(number: u64) -> String {
+    format!("gitea-pr {}", number)
+}
+
+/// Find which forge PR, if any, a commit's metadata says it is mirrored as.
+pub fn mirrored_as(lines: &[String]) -> Option<u64> {
+    lines.iter().find_map(|line| line.strip_prefix("gitea-pr ")?.parse().ok())
+}
+
+
+/// Talks to one repository on the forge.
+pub struct Client {
+    url: String,
+    token: Option<String>,
+
+    /// The git remote for the same repository.
+    pub remote: String,
+}
+
+impl Client {
+    /// Set up a client from `pr.giteaUrl`, `pr.giteaRemote` and `$GITEA_TOKEN`.
+    pub fn from_config(git: &Git) -> Result<Client,GitError> {
+        let url = git.config_get("pr.giteaUrl")?
+            .ok_or_else(|| GitError::Refused(tr!("gitea-no-url")))?;
+        Ok(Client{
+            url: url.trim_end_matches('/').to_string(),
+            token: env::var("GITEA_TOKEN").ok(),
+            remote: git.config_get("pr.giteaRemote")?.unwrap_or_else(|| "gitea".to_string()),
+        })
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = ureq::request(method, &format!("{}{}", self.url, path))
+            .set("Accept", "application/json");
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("token {}", token)),
+            None => request
+        }
+    }
+
+    /// Every open pull request on the forge.
+    pub fn open_pulls(&self) -> Result<Vec<ForgePr>,GitError> {
+        let mut pulls = vec![];
+        for page in 1.. {
+            let response = self.request("GET", "/pulls")
+                .query("state", "open")
+                .query("page", &page.to_string())
+                .query("limit", &PAGE_SIZE.to_string())
+                .call().map_err(http_error)?;
+            let batch = parse_pulls(&response.into_string()?).map_err(GitError::Refused)?;
+            let done = batch.len() < PAGE_SIZE;
+            pulls.extend(batch);
+            if done {
+                break;
+            }
+        }
+        Ok(pulls)
+    }
+
+    /// Open a pull request on the forge, returning its number.
+    pub fn create_pull(&self, head: &str, base: &str, title: &str) -> Result<u64,GitError> {
+        let body = json!({ "head": head, "base": base, "title": title });
+        let response = self.request("POST", "/pulls")
+            .set("Content-Type", "application/json")
+            .send_string(&body.to_string())
+            .map_err(http_error)?;
+        let created: Value = serde_json::from_str(&response.into_string()?)
+            .map_err(|e| GitError::Refused(e.to_string()))?;
+        created["number"].as_u64().ok_or_else(|| GitError::Refused(tr!("gitea-no-number")))
+    }
+}
+
+// Failures talking to the forge are reported like any other I/O failure.
+fn http_error(error: ureq::Error) -> GitError {
+    GitError::Io(io::Error::other(error.to_string()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_forge_pulls() {
+        let text = r#"[{"number": 12, "title": "Fix it", "user": {"login": "alice"},
+                        "head": {"ref": "fix", "sha": "0123456789abcdef"}}]"#;
+        let pulls = parse_pulls(text).unwrap();
+        assert_eq!(pulls[0].author, "alice");
+        assert_eq!(pulls[0].branch(), "gitea-12/0123456");
+        assert_eq!(pulls[0].head_ref(), "refs/pull/12/head");
+
+        assert!(parse_pulls(r#"[{"title": "no number"}]"#).is_err());
+    }
+
+    #[test]
+    fn find_mirror_markers() {
+        let lines = vec!["approved-by Alice".to_string(), mirror_line(12)];
+        assert_eq!(mirrored_as(&lines), Some(12));
+        assert_eq!(mirrored_as(&lines[..1]), None);
+    }
+}
@@ -0,0 +1,189 @@
+//! Just enough HTTP to show PRs in a browser
+//!
+//! `git pr serve` is meant for a LAN with no forge, where a few people without the command line
+//! want to look at PRs. That needs nothing like a full web server: requests are read one at a time,
+//! only their first line matters, and every response closes the connection. This module holds
+//! those pieces, and the HTML escaping every page needs.
+use std::io::{self, BufRead, Read, Write};
+
+
+/// How much of a request is read, at most: anything longer isn't from a browser, and mustn't be
+/// allowed to use up the server's memory.
+pub const MAX_REQUEST_BYTES: u64 = 64 * 1024;
+
+
+/// What a browser asked for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+
+    /// The path, with percent-escapes decoded and any query string dropped.
+    pub path: String,
+}
+
+impl Request {
+    /// Read a request's headers from `reader`, returning `None` if it isn't HTTP, or if its
+    /// headers go on for more than [`MAX_REQUEST_BYTES`].
+    pub fn read<R: BufRead>(reader: &mut R) -> io::Result<Option<Request>> {
+        let mut reader = reader.take(MAX_REQUEST_BYTES);
+        let mut first = String::new();
+        reader.read_line(&mut first)?;
+        // Headers say nothing we need, but they must be read before the browser will listen
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+            header.clear();
+        }
+        if reader.limit() == 0 {
+            return Ok(None);
+        }
+
+        let mut words = first.split_whitespace();
+        let (method, target) = match (words.next(), words.next(), words.next()) {
+            (Some(method), Some(target), Some(version)) if version.starts_with("HTTP/") => {
+                (method, target)
+            }
+            _ => return Ok(None),
+        };
+        let path = target.split('?').next().unwrap_or_default();
+        Ok(Some(Request{ method: method.to_string(), path: percent_decode(path) }))
+    }
+}
+
+
+/// What to send back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+}
+
+impl Response {
+    /// A page of HTML.
+    pub fn html(body: String) -> Response {
+        Response::of_type(HTML, body)
+    }
+
+    /// Something other than HTML, like plain text, of type `content_type`.
+    pub fn of_type(content_type: &str, body: String) -> Response {
+        Response{ status: 200, content_type: content_type.to_string(), body }
+    }
+
+    /// An error page, with `message` as its text.
+    pub fn error(status: u16, message: &str) -> Response {
+        let body = page(message, &format!("<p>{}</p>", escape(message)));
+        Response{ status, content_type: HTML.to_string(), body }
+    }
+
+    /// Send the response, and close the connection.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        };
+        write!(writer, "HTTP/1.0 {} {}\r\nContent-Type: {}\r\n\
+                        Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+               self.status, reason, self.content_type, self.body.len(), self.body)?;
+        writer.flush()
+    }
+}
+
+
+const HTML: &str = "text/html; charset=utf-8";
+
+
+/// A complete HTML page called `title`, around `body`, which should already be escaped.
+pub fn page(title: &str, body: &str) -> String {
+    format!("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title>\
+             <style>body{{font-family:sans-serif;margin:2em}}td{{padding:0 1em 0 0}}\
+             pre{{background:#f6f6f6;padding:1em;overflow:auto}}</style></head>\n\
+             <body>\n{}</body></html>\n", escape(title), body)
+}
+
+/// Make `text` safe to put in HTML, as content or in a quoted attribute.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape `text` for use as one segment of a URL path.
+pub fn percent_encode(text: &str) -> String {
+    text.bytes().map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+            (byte as char).to_string()
+        }
+        _ => format!("%{:02X}", byte),
+    }).collect()
+}
+
+// Undo percent-escapes. Malformed escapes are left as they are.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes[i] {
+            b'%' => text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => { decoded.push(byte); i += 3 },
+            None => { decoded.push(bytes[i]); i += 1 },
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reading_requests() {
+        let mut text = "GET /pr/fix%2Fabc?x=1 HTTP/1.1\r\nHost: here\r\n\r\n".as_bytes();
+        assert_eq!(Request::read(&mut text).unwrap(),
+                   Some(Request{ method: "GET".to_string(), path: "/pr/fix/abc".to_string() }));
+        assert_eq!(Request::read(&mut "hello\r\n\r\n".as_bytes()).unwrap(), None);
+        assert_eq!(Request::read(&mut "".as_bytes()).unwrap(), None);
+        let endless = format!("GET / HTTP/1.1\r\n{}", "X-Padding: 0\r\n".repeat(10_000));
+        assert_eq!(Request::read(&mut endless.as_bytes()).unwrap(), None);
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape("<a href=\"x\">Tom & Jerry's</a>"),
+                   "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;");
+        assert_eq!(percent_encode("fix/abc 1"), "fix%2Fabc%201");
+        assert_eq!(percent_decode(&percent_encode("naïve/ü")), "naïve/ü");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn writing_responses() {
+        let mut written = vec![];
+        Response::html("<p>hi</p>".to_string()).write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(written.contains("Content-Length: 9\r\n"));
+        assert!(written.ends_with("\r\n\r\n<p>hi</p>"));
+        assert!(written.contains("Content-Type: text/html; charset=utf-8\r\n"));
+
+        let mut written = vec![];
+        Response::of_type("text/plain", "up 1\n".to_string()).write_to(&mut written).unwrap();
+        assert!(String::from_utf8(written).unwrap().contains("Content-Type: text/plain\r\n"));
+    }
+}
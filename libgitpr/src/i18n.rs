@@ -0,0 +1,215 @@
+//! Messages in the user's language
+//!
+//! Everything git-pr says to a person (errors, prompts, and summaries, but not output meant for
+//! scripts) is looked up by key in a message catalog, using the [`tr!`](crate::tr) macro:
+//!
+//! ```
+//! # use libgitpr::tr;
+//! let message = tr!("pr-not-found", name = "hotfx");
+//! ```
+//!
+//! Catalogs are plain text files in the `locales/` directory, named after their language (`en.txt`,
+//! `pt_BR.txt`, and so on), and each line is `key = message`. Placeholders are written `{name}`,
+//! and `\n` and `\t` stand for a newline and a tab. Lines starting with `#` are comments. Every
+//! catalog is built into git-pr, so a translation is just a new file: no code needs to change.
+//! English is the reference catalog; any message a translation lacks is shown in English.
+//!
+//! The language is chosen the way gettext chooses it: from `LANGUAGE` (a colon-separated list of
+//! preferences), then `LC_ALL`, `LC_MESSAGES`, and `LANG`. A preference like "pt_BR.UTF-8" is
+//! satisfied by a `pt_BR` catalog, or failing that a `pt` catalog.
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+
+// Generated by build.rs: (language, catalog text) pairs
+include!(concat!(env!("OUT_DIR"), "/catalogs.rs"));
+
+/// The language every message is written in first.
+pub(crate) const REFERENCE: &str = "en";
+
+
+/// Read a catalog's `key = message` lines.
+pub fn parse(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, message)| {
+            let message = message.trim().replace("\\n", "\n").replace("\\t", "\t");
+            (key.trim().to_string(), message)
+        })
+        .collect()
+}
+
+/// The text of a built-in catalog, if there is one for `language`.
+pub(crate) fn catalog(language: &str) -> Option<&'static str> {
+    CATALOGS.iter().find(|(name, _)| *name == language).map(|(_, text)| *text)
+}
+
+/// The languages the user would like to read, most preferred first, as catalog names to try.
+pub(crate) fn preferences() -> Vec<String> {
+    let mut wanted: Vec<String> = env::var("LANGUAGE").unwrap_or_default()
+        .split(':')
+        .map(|language| language.to_string())
+        .collect();
+    wanted.extend(["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty()));
+
+    let mut names = vec![];
+    for language in wanted {
+        // "pt_BR.UTF-8@euro" -> "pt_BR", then "pt"
+        let full = language.split(['.', '@']).next().unwrap_or_default().to_string();
+        let short = full.split('_').next().unwrap_or_default().to_string();
+        for name in [full, short] {
+            if !name.is_empty() && name != "C" && name != "POSIX" && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// Fill in a message's `{name}` placeholders.
+///
+/// Values are inserted as they are, even if they happen to contain something that looks like a
+/// placeholder. Braces that don't name one of `args` are left alone.
+pub fn format(message: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = String::new();
+    let mut rest = message;
+    while let Some(open) = rest.find('{') {
+        text.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after.find('}')
+            .and_then(|close| args.iter().find(|(name, _)| *name == &after[..close]))
+            .map(|(name, value)| (name.len(), value.to_string()));
+        match value {
+            Some((length, value)) => {
+                text.push_str(&value);
+                rest = &after[length + 1..];
+            },
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+
+/// The messages for one language, backed by the reference catalog.
+pub struct Messages {
+    preferred: BTreeMap<String, String>,
+    reference: BTreeMap<String, String>,
+}
+
+impl Messages {
+    /// Messages in the first of `languages` that has a catalog, or in English if none do.
+    pub fn new(languages: &[String]) -> Messages {
+        let preferred = languages.iter().find_map(|language| catalog(language)).unwrap_or("");
+        Messages{
+            preferred: parse(preferred),
+            reference: parse(catalog(REFERENCE).unwrap_or("")),
+        }
+    }
+
+    /// Look up a message and fill in its placeholders. An unknown key is shown as itself, so that
+    /// a missing message is noticed but doesn't hide what went wrong.
+    pub fn get(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let message = self.preferred.get(key).or_else(|| self.reference.get(key));
+        format(message.map_or(key, |m| m.as_str()), args)
+    }
+}
+
+/// The messages for the user's language, as chosen by `preferences`.
+pub fn messages() -> &'static Messages {
+    static MESSAGES: OnceLock<Messages> = OnceLock::new();
+    MESSAGES.get_or_init(|| Messages::new(&preferences()))
+}
+
+/// Look up a message in the user's language, filling in any placeholders.
+///
+/// ```
+/// # use libgitpr::tr;
+/// assert_eq!(tr!("pr-not-found", name = "hotfx"), "No such PR: hotfx");
+/// ```
+#[macro_export]
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::i18n::messages().get($key, &[])
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::messages()
+            .get($key, &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+])
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::path::Path;
+
+    fn placeholders(message: &str) -> BTreeSet<String> {
+        Regex::new(r"\{(\w+)\}").unwrap().captures_iter(message).map(|c| c[1].to_string()).collect()
+    }
+
+    #[test]
+    fn parse_catalogs() {
+        let catalog = parse("# comment\n\ngreeting = Hello, {name}!\nlist = a\\n\\tb\n");
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog["list"], "a\n\tb");
+        assert_eq!(format(&catalog["greeting"], &[("name", &"world")]), "Hello, world!");
+        assert_eq!(format("'{a}' or {b} {", &[("a", &"{b}"), ("b", &2)]), "'{b}' or 2 {");
+    }
+
+    #[test]
+    fn fall_back_to_the_reference() {
+        let messages = Messages::new(&["xx".to_string()]);
+        assert_eq!(messages.get("pr-not-found", &[("name", &"x")]), "No such PR: x");
+        assert_eq!(messages.get("no-such-key", &[]), "no-such-key");
+    }
+
+    // Translations must not invent keys or placeholders the code doesn't know about.
+    #[test]
+    fn translations_match_the_reference() {
+        let reference = parse(catalog(REFERENCE).unwrap());
+        for (language, text) in CATALOGS {
+            for (key, message) in parse(text) {
+                let original = reference.get(&key)
+                    .unwrap_or_else(|| panic!("{} has unknown key {}", language, key));
+                assert_eq!(placeholders(&message), placeholders(original), "{}: {}", language, key);
+            }
+        }
+    }
+
+    // Every key the code asks for must be in the reference catalog.
+    #[test]
+    fn every_key_is_in_the_reference() {
+        let reference = parse(catalog(REFERENCE).unwrap());
+        let uses = Regex::new(r#"tr!\(\s*"([\w-]+)""#).unwrap();
+        // The commands live in the git-pr-cli crate, but their messages are kept here.
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let cli = root.join("../git-pr-cli/src/bin");
+        for dir in [root.join("src"), root.join("src/bin"), cli.clone(), cli.join("git-pr")] {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().is_none_or(|ext| ext != "rs") {
+                    continue;
+                }
+                let source = fs::read_to_string(&path).unwrap();
+                for key in uses.captures_iter(&source) {
+                    assert!(reference.contains_key(&key[1]),
+                            "{} uses {}, which isn't in en.txt", path.display(), &key[1]);
+                }
+            }
+        }
+    }
+}
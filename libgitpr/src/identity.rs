@@ -0,0 +1,172 @@
+//! Who is responsible for an action
+//!
+//! Anything git-pr records on behalf of a person (or a robot) needs to say who that was. By default
+//! this is whoever git itself thinks you are (`user.name` and `user.email`), but automated systems
+//! such as CI runners often execute under some human's credentials. Those systems may instead
+//! claim a *bot identity* with `--as <identity>`, provided the repository has opted in to that bot
+//! by listing it in the multi-valued `pr.botIdentity` config key:
+//!
+//! ```console
+//! $ git config --add pr.botIdentity "CI Bot <ci@example.com>"
+//! ```
+//!
+//! Restricting `--as` to configured bots keeps people from casually impersonating one another.
+//!
+//! People also tend to commit under more than one name or address. Before identities are displayed
+//! or counted, they should be passed through [`canonicalize`], which applies the repository's
+//! mailmap (the same `.mailmap` that `git shortlog` uses), so that each person appears only once.
+use crate::{tr, Git, GitError};
+use std::fmt;
+
+
+/// A person or bot to whom an action can be attributed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+
+    /// True if this identity was claimed via `--as`, rather than taken from the user's config.
+    pub bot: bool,
+}
+
+impl Identity {
+    /// Parse an identity written in git's usual `Name <email>` form.
+    ///
+    /// Returns `None` if the angle brackets are missing or either half is empty.
+    pub fn parse(text: &str) -> Option<Identity> {
+        let text = text.trim();
+        let open = text.find('<')?;
+        let close = text.rfind('>')?;
+        if close < open || close != text.len() - 1 {
+            return None;
+        }
+
+        let name = text[..open].trim();
+        let email = text[open + 1..close].trim();
+        if name.is_empty() || email.is_empty() {
+            return None;
+        }
+
+        Some(Identity{ name: name.to_string(), email: email.to_string(), bot: false })
+    }
+
+    /// Does this look like an identity nobody would really use, such as git's own guess from the
+    /// hostname or an example from a tutorial?
+    ///
+    /// Actions attributed to such an identity can't be traced back to anyone.
+    pub fn is_placeholder(&self) -> bool {
+        let email = self.email.to_lowercase();
+        let domain = email.rsplit_once('@').map_or("", |(_, domain)| domain);
+        ["Your Name", "John Doe", "Jane Doe"].contains(&self.name.as_str())
+            || !email.contains('@')
+            || ["example.com", "example.org", "example.net", "localhost", "(none)"]
+                .contains(&domain)
+            || domain.ends_with(".localdomain") || domain.ends_with(".(none)")
+    }
+
+    /// Does `requested` (as typed after `--as`) refer to this identity?
+    ///
+    /// We accept the full `Name <email>` form, or just the name, or just the email, so that CI
+    /// configuration can stay short.
+    pub(crate) fn answers_to(&self, requested: &str) -> bool {
+        let requested = requested.trim();
+        requested == self.name || requested == self.email || requested == self.to_string()
+    }
+
+    /// The identity git would use for a commit made right now.
+    pub fn current(git: &Git) -> Result<Identity,GitError> {
+        let name = git.config_get("user.name")?;
+        let email = git.config_get("user.email")?;
+        match (name, email) {
+            (Some(name), Some(email)) => Ok(Identity{ name, email, bot: false }),
+            _ => Err(GitError::Refused(tr!("identity-unconfigured")))
+        }
+    }
+
+    /// Decide who an action should be attributed to.
+    ///
+    /// With no `requested` identity, this is the [`Identity::current`] user. Otherwise the request
+    /// must match one of the `pr.botIdentity` entries, and the matching bot is returned.
+    pub fn resolve(git: &Git, requested: Option<&str>) -> Result<Identity,GitError> {
+        let requested = match requested {
+            None => return Identity::current(git),
+            Some(requested) => requested,
+        };
+
+        for entry in git.config_get_all("pr.botIdentity")? {
+            if let Some(mut bot) = Identity::parse(&entry) {
+                if bot.answers_to(requested) {
+                    bot.bot = true;
+                    return Ok(bot);
+                }
+            }
+        }
+
+        Err(GitError::Refused(tr!("identity-not-a-bot", name = requested)))
+    }
+}
+
+/// Replace each identity with its canonical form, according to the repository's mailmap.
+///
+/// Identities the mailmap doesn't mention are returned as they are. Whether an identity is a bot is
+/// preserved.
+pub fn canonicalize(git: &Git, identities: &[Identity]) -> Result<Vec<Identity>,GitError> {
+    let contacts: Vec<String> = identities.iter().map(|i| i.to_string()).collect();
+    let mapped = git.check_mailmap(&contacts)?;
+
+    Ok(identities.iter().zip(mapped)
+        .map(|(original, mapped)| match Identity::parse(&mapped) {
+            Some(canonical) => Identity{ bot: original.bot, ..canonical },
+            None => original.clone()
+        })
+        .collect())
+}
+
+impl fmt::Display for Identity {
+    /// Render as `Name <email>`, the same way git shows authors.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} <{}>", self.name, self.email)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_name_and_email() {
+        let ident = Identity::parse("  CI Bot <ci@example.com> ").unwrap();
+        assert_eq!(ident.name, "CI Bot");
+        assert_eq!(ident.email, "ci@example.com");
+        assert_eq!(ident.to_string(), "CI Bot <ci@example.com>");
+    }
+
+    #[test]
+    fn reject_malformed_identities() {
+        assert!(Identity::parse("CI Bot").is_none());
+        assert!(Identity::parse("<ci@example.com>").is_none());
+        assert!(Identity::parse("CI Bot <>").is_none());
+        assert!(Identity::parse("CI Bot <ci@example.com> trailing").is_none());
+    }
+
+    #[test]
+    fn spot_placeholders() {
+        let placeholder = |text| Identity::parse(text).unwrap().is_placeholder();
+        assert!(placeholder("Your Name <you@example.com>"));
+        assert!(placeholder("Alice <alice@Example.org>"));
+        assert!(placeholder("root <root@buildhost.(none)>"));
+        assert!(placeholder("alice <alice@laptop.localdomain>"));
+        assert!(placeholder("Alice <alice>"));
+        assert!(!placeholder("Alice <alice@example.co.uk>"));
+    }
+
+    #[test]
+    fn bots_answer_to_name_or_email() {
+        let ident = Identity::parse("CI Bot <ci@example.com>").unwrap();
+        assert!(ident.answers_to("CI Bot"));
+        assert!(ident.answers_to("ci@example.com"));
+        assert!(ident.answers_to("CI Bot <ci@example.com>"));
+        assert!(!ident.answers_to("Mallory"));
+    }
+}
@@ -0,0 +1,118 @@
+//! Living alongside other tools' branches
+//!
+//! Bots and review systems push branches of their own, and some of them look enough like PR
+//! branches to confuse git-pr: Dependabot pushes `dependabot/<ecosystem>/<package>-<version>`,
+//! Renovate pushes `renovate/<package>`, and Gerrit mirrors can expose `for/<branch>` and
+//! `changes/<nn>/<change>/<patchset>`. By default such branches are never treated as PRs, and
+//! `git pr clean` never deletes them, since the tool that created them is responsible for them.
+//!
+//! A repository which *does* want one of these conventions handled like ordinary branches can opt
+//! in with the multi-valued `pr.allowConvention` key:
+//!
+//! ```console
+//! $ git config --add pr.allowConvention dependabot
+//! ```
+use crate::{tr, GitError};
+
+
+/// A branch naming scheme belonging to some other tool.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Convention {
+    Gerrit,
+    Dependabot,
+    Renovate,
+}
+
+impl Convention {
+    pub(crate) const ALL: [Convention; 3] =
+        [Convention::Gerrit, Convention::Dependabot, Convention::Renovate];
+
+    /// The name used for this convention in `pr.allowConvention`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Convention::Gerrit => "gerrit",
+            Convention::Dependabot => "dependabot",
+            Convention::Renovate => "renovate",
+        }
+    }
+
+    /// The branch prefixes this convention uses.
+    fn prefixes(self) -> &'static [&'static str] {
+        match self {
+            Convention::Gerrit => &["for/", "changes/"],
+            Convention::Dependabot => &["dependabot/"],
+            Convention::Renovate => &["renovate/"],
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Convention> {
+        Convention::ALL.iter().copied().find(|c| c.name() == name.trim().to_lowercase())
+    }
+
+    /// Work out which tool (if any) a branch belongs to.
+    ///
+    /// `branch` is relative to its remote or to `refs/heads/`, like "dependabot/cargo/serde-1".
+    pub fn detect(branch: &str) -> Option<Convention> {
+        Convention::ALL.iter().copied()
+            .find(|c| c.prefixes().iter().any(|prefix| branch.starts_with(prefix)))
+    }
+}
+
+
+/// Decides which branches git-pr should keep its hands off.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Guard {
+    allowed: Vec<Convention>,
+}
+
+impl Guard {
+    /// A guard which lets the named conventions through, as listed in `pr.allowConvention`.
+    pub fn new(allowed: &[String]) -> Result<Guard,GitError> {
+        let mut conventions = vec![];
+        for name in allowed {
+            match Convention::parse(name) {
+                Some(convention) => conventions.push(convention),
+                None => {
+                    let known: Vec<&str> = Convention::ALL.iter().map(|c| c.name()).collect();
+                    return Err(GitError::Refused(tr!(
+                        "config-unknown-convention", name = name, known = known.join(", ")
+                    )))
+                }
+            }
+        }
+        Ok(Guard{ allowed: conventions })
+    }
+
+    /// Is this branch owned by another tool that hasn't been opted in?
+    pub fn excludes(&self, branch: &str) -> bool {
+        Convention::detect(branch).is_some_and(|c| !self.allowed.contains(&c))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_other_tools() {
+        assert_eq!(Convention::detect("dependabot/cargo/serde-1"), Some(Convention::Dependabot));
+        assert_eq!(Convention::detect("renovate/abc123"), Some(Convention::Renovate));
+        assert_eq!(Convention::detect("for/trunk"), Some(Convention::Gerrit));
+        assert_eq!(Convention::detect("hotfix/1234567"), None);
+        assert_eq!(Convention::detect("my-renovate/1234567"), None);
+    }
+
+    #[test]
+    fn guard_respects_opt_in() {
+        let guard = Guard::default();
+        assert!(guard.excludes("renovate/abc123"));
+        assert!(!guard.excludes("hotfix/1234567"));
+
+        let guard = Guard::new(&["Renovate".to_string()]).unwrap();
+        assert!(!guard.excludes("renovate/abc123"));
+        assert!(guard.excludes("dependabot/npm/left-pad-1"));
+
+        assert!(Guard::new(&["phabricator".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,80 @@
+//! Labels on pull requests
+//!
+//! A label is a short word a team hangs on PRs to sort them, like "cleanup" or "superseded", so
+//! that they can later be listed or dealt with together (see `git pr batch`). Each change is a line
+//! of metadata (see [`crate::metadata`]) on the PR's tip: `labeled <at> <label>`, or `unlabeled
+//! <at> <label>` when it is taken off again, with the time in seconds since the Unix epoch. New
+//! lines should be written with [`crate::claim::next_time`], so that the latest change wins.
+use std::collections::BTreeMap;
+
+
+/// The metadata line recording that `label` was put on a PR at time `at`.
+pub fn label_line(label: &str, at: i64) -> String {
+    format!("labeled {} {}", at, label)
+}
+
+/// The metadata line recording that `label` was taken off a PR at time `at`.
+pub fn unlabel_line(label: &str, at: i64) -> String {
+    format!("unlabeled {} {}", at, label)
+}
+
+/// Whether `label` can be used as a label: one word, which can be written on a command line.
+pub fn is_valid(label: &str) -> bool {
+    !label.is_empty() && !label.starts_with('-')
+        && label.chars().all(|c| c.is_alphanumeric() || "-_.:/".contains(c))
+}
+
+/// A PR's labels, according to its metadata `lines`, in alphabetical order.
+pub fn current(lines: &[String]) -> Vec<String> {
+    let mut latest: BTreeMap<&str, (i64, bool)> = BTreeMap::new();
+    for line in lines {
+        let parsed = line.split_once(' ')
+            .and_then(|(kind, rest)| match kind {
+                "labeled" => Some((true, rest)),
+                "unlabeled" => Some((false, rest)),
+                _ => None,
+            })
+            .and_then(|(on, rest)| {
+                let (at, label) = rest.split_once(' ')?;
+                Some((at.parse::<i64>().ok()?, on, label))
+            });
+        if let Some((at, on, label)) = parsed {
+            if latest.get(label).is_none_or(|(known, _)| at > *known) {
+                latest.insert(label, (at, on));
+            }
+        }
+    }
+    latest.into_iter()
+        .filter(|(_, (_, on))| *on)
+        .map(|(label, _)| label.to_string())
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_change_wins() {
+        assert!(current(&[]).is_empty());
+        let lines = vec![
+            label_line("superseded", 100),
+            unlabel_line("cleanup", 300),
+            label_line("cleanup", 200),
+            label_line("docs", 100),
+            unlabel_line("docs", 50),
+            "labeled soon wip".to_string(),
+        ];
+        assert_eq!(current(&lines), vec!["docs".to_string(), "superseded".to_string()]);
+    }
+
+    #[test]
+    fn valid_labels() {
+        assert!(is_valid("cleanup"));
+        assert!(is_valid("area/docs"));
+        assert!(!is_valid(""));
+        assert!(!is_valid("two words"));
+        assert!(!is_valid("--force"));
+    }
+}
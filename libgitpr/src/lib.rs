@@ -0,0 +1,1476 @@
+//! Pull request management for bare repos
+//!
+//! This is the library behind the `git pr` commands, which live in the `git-pr-cli` crate. With no
+//! features enabled, it provides the domain model: pull requests and their metadata, merging,
+//! configuration, and so on. Optional features add the rest:
+//!
+//! * `serde`: the `audit` log, JSON output, and the `gerrit` and `patchwork` bridges
+//! * `tui`: the interactive `picker`
+//! * `serve`: watching refs, the JSON-RPC protocol in `rpc`, and the web view's `http`
+//! * `forge`: bridges to forges, such as `gitea`
+//! * `webhook`: announcing events to other systems, with `webhook`
+//!
+//! # Stability
+//!
+//! The types most programs need are re-exported here: [`Git`], which stands for a repository, and
+//! [`PullRequest`], [`PrIndex`], [`Config`], and the errors [`GitError`] and [`LookupError`].
+//! Everything public, here and in the modules, follows semver: it changes incompatibly only
+//! with the minor version while libgitpr is below 1.0, and with the major version after that.
+//! Error enums are `#[non_exhaustive]`, so new kinds of failure are not breaking changes; match
+//! them with a `_` arm. The `bench` module exists for git-pr's own benchmarks and is exempt.
+
+
+pub mod alias;
+pub mod approval;
+pub mod assignment;
+pub mod attachment;
+#[cfg(feature = "serde")]
+pub mod audit;
+pub mod availability;
+#[doc(hidden)]
+pub mod bench;
+pub mod ci;
+pub mod claim;
+pub mod comment;
+pub mod compare;
+pub mod config;
+pub mod cursor;
+pub mod date;
+pub mod description;
+pub mod digest;
+pub mod doctor;
+pub mod drift;
+pub mod encryption;
+pub mod exit;
+pub mod fuzzy;
+#[cfg(feature = "serde")]
+pub mod gerrit;
+pub mod i18n;
+#[cfg(feature = "forge")]
+pub mod gitea;
+#[cfg(feature = "serve")]
+pub mod http;
+pub mod identity;
+pub mod label;
+pub mod merge;
+pub mod merge_template;
+pub mod interop;
+pub mod metadata;
+pub mod metrics;
+pub mod mirror;
+pub mod naming;
+pub mod owner;
+pub mod parse;
+pub mod partial;
+#[cfg(feature = "serde")]
+pub mod patchwork;
+#[cfg(feature = "tui")]
+pub mod picker;
+pub mod policy;
+pub mod profile;
+pub mod pull_request;
+pub mod query;
+pub mod render;
+pub mod retention;
+pub mod revision;
+#[cfg(feature = "serve")]
+pub mod rpc;
+pub mod search;
+pub mod selftest;
+pub mod signing;
+pub mod snapshot;
+pub mod state;
+pub mod stats;
+pub mod status;
+pub mod template;
+pub mod timeline;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "serve")]
+pub mod watch;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+
+use std::env;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::process::Stdio;
+
+pub use config::Config;
+pub use parse::{extract_deletable_branches, extract_pr_names, parse_push_status};
+pub use pull_request::{LookupError, PrIndex, PullRequest};
+
+
+/// Wrapper for the git command line program
+///
+/// If you think of git's command line interface as a sortof API, then this type is our API client.
+/// It provides only those features that we need from git in order to set up our PR workflow. It is
+/// intentionally bare-bones: for testing purposes, we want to do as much logic as possible without
+/// relying on an external tool or service. 
+pub struct Git {
+    // The path to the version of git we'd like to use. Nominally, this would always be "git", but
+    // we allow it to be specified in tests (see the unit tests for this module) so that we can
+    // test some functionality against mock implementations of git. This makes it easier to
+    // exercise edge cases without having to make real git jump through hoops.
+    pub program: String,
+
+    // Path to the repository. This is `.` by default in production, but for tests we want to be
+    // able to invoke git as though we were in a temporary, test-specific directory.
+    pub working_dir: Box<dyn AsRef<Path>>,
+
+    // Where the repository and its working tree are, if the user chose them with `GIT_DIR` or
+    // `GIT_WORK_TREE` (see `Git::discover_from`). These are passed to every git command, so that
+    // they win over the environment, which may have given them relative to another directory.
+    pub git_dir: Option<PathBuf>,
+    pub work_tree: Option<PathBuf>,
+}
+
+
+/// Custom Error Type for Git Problems
+///
+/// Lower-level errors are wrapped into this type so that we can return a uniform error type
+/// without losing any of the original context.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GitError {
+
+    /// We encountered an error while launching or waiting on the child process.
+    Io(io::Error),
+
+    /// The child process ran, but returned a non-zero exit code.
+    Exit(ExitStatus),
+
+    /// Git would have been happy to proceed, but git-pr's own rules forbid it. The message explains
+    /// which rule was violated.
+    Refused(String)
+}
+
+impl GitError {
+    /// The remote refused a push; `rejections` are the refs it rejected, with git's reasons.
+    pub fn push_rejected(remote: &str, rejections: &[(String, String)]) -> GitError {
+        let mut message = tr!("push-rejected", remote = remote);
+        for (refname, reason) in rejections {
+            message.push_str(&format!("\n\t{}\t{}", refname, reason));
+        }
+        GitError::Refused(message)
+    }
+
+    /// The status a command should exit with after this error (see [`exit`]).
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GitError::Refused(_) => exit::REFUSED,
+            GitError::Io(_) | GitError::Exit(_) => exit::FATAL,
+        }
+    }
+}
+
+impl fmt::Display for GitError {
+    /// Explain the error to a person, without the `Debug` noise.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitError::Io(e) => write!(f, "{}", e),
+            GitError::Exit(status) => write!(f, "{}", tr!("git-failed", status = status)),
+            GitError::Refused(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for GitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GitError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GitError {
+    /// Wrap an [`io::Error`] in a [`GitError::Io`]
+    fn from(other: io::Error) -> GitError {
+        GitError::Io(other)
+    }
+}
+
+fn assert_success(status: ExitStatus) -> Result<(),GitError> {
+    match status.success() {
+        true => Ok(()),
+        false => Err(GitError::Exit(status))
+    }
+}
+
+impl Default for Git {
+    fn default() -> Git {
+        Git::new()
+    }
+}
+
+impl Git {
+    /// Create a new "git client".
+    ///
+    /// This will rely on the operating system to infer the appropriate path to git, based on the
+    /// current environment (just like your shell does it).
+    pub fn new() -> Git {
+        Git{
+            program: String::from("git"), working_dir: Box::new(String::from(".")),
+            git_dir: None, work_tree: None,
+        }
+    }
+
+    /// Create a "git client" for the repository containing the current directory.
+    ///
+    /// This is what the `git-pr-*` programs should use, so that they behave the same whether they
+    /// are run from the top of the repository or from some nested directory.
+    pub fn discover() -> Result<Git,GitError> {
+        Git::discover_from(".")
+    }
+
+    /// Create a "git client" for the repository containing `start`.
+    ///
+    /// We operate from the top of the working tree, or from the git directory itself if there is
+    /// no working tree (as in a bare repo). If the user has set `GIT_DIR` or `GIT_WORK_TREE`, those
+    /// may be relative to the directory we started in, so we resolve them to absolute paths and
+    /// pass those to git ourselves, rather than letting git read them from the environment.
+    pub fn discover_from<P: AsRef<Path>>(start: P) -> Result<Git,GitError> {
+        let start = start.as_ref();
+        let output = Command::new("git")
+            .arg("-C").arg(start)
+            .args(["rev-parse","--absolute-git-dir","--is-bare-repository"]).output()?;
+        assert_success(output.status)?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let mut lines = stdout.lines();
+        let git_dir = PathBuf::from(lines.next().unwrap_or_default());
+        let bare = lines.next() == Some("true");
+
+        let root = match bare {
+            true => git_dir.clone(),
+            false => {
+                let output = Command::new("git")
+                    .arg("-C").arg(start)
+                    .args(["rev-parse","--show-toplevel"]).output()?;
+                assert_success(output.status)?;
+                PathBuf::from(String::from_utf8_lossy(&output.stdout).trim_end())
+            }
+        };
+
+        let git_dir = env::var_os("GIT_DIR").map(|_| git_dir);
+        let work_tree = match env::var_os("GIT_WORK_TREE") {
+            Some(_) if !bare => Some(root.clone()),
+            _ => None,
+        };
+        Ok(Git{ program: String::from("git"), working_dir: Box::new(root), git_dir, work_tree })
+    }
+
+    // Start a git command which runs in this repository.
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.arg("-C").arg(self.working_dir.as_ref().as_ref());
+        if let Some(git_dir) = &self.git_dir {
+            command.arg("--git-dir").arg(git_dir);
+        }
+        if let Some(work_tree) = &self.work_tree {
+            command.arg("--work-tree").arg(work_tree);
+        }
+        command
+    }
+
+    /// Report the version of the underlying git binary.
+    ///
+    /// This is equivalent to invoking `git --version` on the command line. Making this transparent
+    /// to users of `git-pr` may help them begin to debug unexpected issues; For example, `git-pr`
+    /// may not work correctly with very old versions of git.
+    pub fn version(&self) -> Result<String,GitError> {
+        let output = self.command().arg("--version").output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Update the local branch list.
+    ///
+    /// This asks git to download the current list of branches from the remote server, cleaning up
+    /// local references to any that have been deleted. This ensures that the user is able to see
+    /// the same set of "current PRs" as their collaborators.
+    pub fn fetch_prune(&self) -> Result<(),GitError> {
+        let status = self.command().args(["fetch","--prune"]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Check whether this is a shallow clone.
+    ///
+    /// Shallow clones are missing some history, so questions like "has this branch been merged?"
+    /// may be answered incorrectly: git cannot see a merge that happened before the cutoff.
+    pub fn is_shallow(&self) -> Result<bool,GitError> {
+        let output = self.command().args(["rev-parse","--is-shallow-repository"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end() == "true")
+    }
+
+    /// Download the remainder of history for a shallow clone.
+    ///
+    /// This can be expensive on large repositories, so we only do it when the user asks.
+    pub fn unshallow(&self) -> Result<(),GitError> {
+        let status = self.command().args(["fetch","--unshallow"]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Produce a list of branch names.
+    ///
+    /// This asks the configured `git` binary to produce a list of *all* known branches, including
+    /// references to remote branches. It is from this list that we can produce the list of
+    /// "current PRs".
+    pub fn all_branches(&self) -> Result<String,GitError> {
+        let output = self.command().args(["branch","-a"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Produce a list of local branches, as `git branch` prints them (see
+    /// [`parse::LocalBranch`]).
+    pub fn local_branches(&self) -> Result<String,GitError> {
+        let output = self.command().arg("branch").output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// List every remote-tracking ref for `remote`, along with the commit it points to.
+    ///
+    /// Each line of output is `<hash> <refname>`, where refname is fully qualified (for example,
+    /// `refs/remotes/origin/hotfix/0`). This is the raw material for a
+    /// [`pull_request::PrIndex`].
+    pub fn remote_refs(&self, remote: &str) -> Result<String,GitError> {
+        let output = self.command().args(["for-each-ref","--format=%(objectname) %(refname)"])
+            .arg(format!("refs/remotes/{}/", remote)).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// List the refs under `prefix` along with when each was created.
+    ///
+    /// Each line of output is `<unix time> <refname>`. For annotated tags the time is when the tag
+    /// was made; for anything else it is the commit time of whatever the ref points to.
+    pub fn ref_dates(&self, prefix: &str) -> Result<String,GitError> {
+        let output = self.command()
+            .args(["for-each-ref","--format=%(creatordate:unix) %(refname)",prefix]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// List the refs under `prefix` with the subject of the commit each points to, as `<refname>
+    /// <subject>` lines.
+    pub fn ref_subjects(&self, prefix: &str) -> Result<String,GitError> {
+        let output = self.command()
+            .args(["for-each-ref","--format=%(refname) %(subject)",prefix]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Find the commit a ref points to, or `None` if the ref does not exist.
+    pub fn resolve_ref(&self, refname: &str) -> Result<Option<String>,GitError> {
+        let output = self.command().args(["rev-parse","--verify","--quiet"])
+            .arg(format!("{}^{{commit}}", refname)).output()?;
+        if output.status.code() == Some(1) {
+            return Ok(None);
+        }
+        assert_success(output.status)?;
+
+        Ok(Some(String::from_utf8_lossy(&output.stdout).trim_end().to_string()))
+    }
+
+    /// Produce a list of PRs which are elligible for deletion.
+    ///
+    /// These are the local branches which have already been merged into `trunk`.
+    pub fn merged_branches(&self, trunk: &str) -> Result<String,GitError> {
+        let output = self.command().args(["branch","--merged",trunk]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Get the hash of the HEAD commit.
+    ///
+    /// This is useful for creating new PR branches, since we can use this value as a way to
+    /// indicate the "base" of the current work. This function takes advantage of the `core.abbrev`
+    /// config value, and will return a hash of the indicated length. If this value is not
+    /// specificed, git will return the shortest hash necessary to uniquely identify the commit.
+    pub fn rev_parse_head(&self) -> Result<String,GitError> {
+        let output = self.command().args(["rev-parse","--short","HEAD"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+
+    /// Abbreviate a commit's hash, the way [`rev_parse_head`](Git::rev_parse_head) abbreviates
+    /// HEAD's.
+    pub fn abbreviate(&self, commit: &str) -> Result<String,GitError> {
+        let output = self.command().args(["rev-parse","--short",commit]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+
+    /// Create a new branch
+    ///
+    /// Used with [`rev_parse_head`] as part of `git pr create`. Pull requests are
+    /// expressed as branches with a certain naming pattern (`pr-name/hash`). So in our system,
+    /// creating a branch and creating a pull request are the same operation!
+    pub fn create_branch(&self, name: &str) -> Result<(), GitError> {
+        let status = self.command().args(["checkout","-b",name]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Create a branch at `commit`, without checking it out.
+    pub fn create_branch_at(&self, name: &str, commit: &str) -> Result<(), GitError> {
+        let status = self.command().args(["branch","--no-track",name,commit]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Check `commit` out, detached, in a new worktree at `path`.
+    pub fn add_worktree(&self, path: &Path, commit: &str) -> Result<(),GitError> {
+        let status = self.command()
+            .args(["worktree","add","--quiet","--detach"]).arg(path).arg(commit).status()?;
+        assert_success(status)
+    }
+
+    /// Remove the worktree at `path`, along with anything left in it.
+    pub fn remove_worktree(&self, path: &Path) -> Result<(),GitError> {
+        let status = self.command().args(["worktree","remove","--force"]).arg(path).status()?;
+        assert_success(status)
+    }
+
+    /// Apply a series of patches, from mbox files or single patch files, as commits on HEAD.
+    ///
+    /// Patches which turn out to change nothing, like a series' cover letter, are skipped. If a
+    /// patch doesn't apply, the whole series is abandoned, and HEAD is left where it was.
+    pub fn am(&self, patches: &[PathBuf]) -> Result<(),GitError> {
+        let status = self.command().args(["am","--quiet","--empty=drop"]).args(patches).status()?;
+        if !status.success() {
+            self.command().args(["am","--abort"]).status()?;
+        }
+        assert_success(status)
+    }
+
+    /// The branch checked out in this worktree, as a full ref like `refs/heads/trunk`.
+    ///
+    /// Returns `None` if HEAD is detached.
+    pub fn current_branch(&self) -> Result<Option<String>,GitError> {
+        let output = self.command().args(["symbolic-ref","--quiet","HEAD"]).output()?;
+        if output.status.code() == Some(1) {
+            return Ok(None);
+        }
+        assert_success(output.status)?;
+
+        Ok(Some(String::from_utf8_lossy(&output.stdout).trim_end().to_string()))
+    }
+
+    /// The upstream of a local branch, like `origin/hotfix/1234567`, or `None` if it has none.
+    pub fn upstream(&self, branch: &str) -> Result<Option<String>,GitError> {
+        let output = self.command()
+            .args(["rev-parse","--abbrev-ref",&format!("{}@{{upstream}}", branch)])
+            .stderr(Stdio::null()).output()?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        Ok(Some(String::from_utf8_lossy(&output.stdout).trim_end().to_string()))
+    }
+
+    /// Fast-forward the local branch `branch` to `to`, creating it there if it doesn't exist.
+    ///
+    /// Returns false, and leaves the branch alone, if that isn't a fast-forward: the branch has
+    /// commits that `to` lacks. If the branch is checked out here, the working tree is brought
+    /// along too, failing rather than overwrite local changes that the fast-forward would touch.
+    /// Otherwise only the ref moves, and only if nobody else moves it in the meantime. A branch
+    /// checked out in another worktree is refused, since that worktree would be left behind.
+    pub fn fast_forward(&self, branch: &str, to: &str) -> Result<bool, GitError> {
+        let refname = format!("refs/heads/{}", branch);
+        let from = match self.resolve_ref(&refname)? {
+            Some(from) => from,
+            None => {
+                self.update_ref(&refname, to)?;
+                return Ok(true);
+            }
+        };
+        match self.divergence(&from, to)? {
+            (0, 0) => return Ok(true),
+            (0, _) => {},
+            _ => return Ok(false),
+        }
+
+        let local = self.local_branches()?.lines().filter_map(parse::LocalBranch::parse)
+            .find(|b| b.name == branch);
+        match local {
+            Some(b) if b.elsewhere => {
+                Err(GitError::Refused(tr!("fast-forward-elsewhere", branch = branch)))
+            },
+            Some(b) if b.current => {
+                let status = self.command().args(["merge","--ff-only","--quiet",to]).status()?;
+                assert_success(status)?;
+                Ok(true)
+            },
+            _ => self.move_ref(&refname, to, &from).map(|_| true),
+        }
+    }
+
+    /// Rebase the checked out branch onto `onto`.
+    ///
+    /// Returns false if the rebase stopped because of conflicts, leaving it in progress for the
+    /// user to resolve (see [`Git::conflicted_paths`]). Any other failure is an error, with git's
+    /// explanation passed along.
+    pub fn rebase(&self, onto: &str) -> Result<bool, GitError> {
+        let output = self.command().args(["rebase","--quiet",onto]).output()?;
+        if output.status.success() {
+            return Ok(true);
+        }
+        if !self.conflicted_paths()?.is_empty() {
+            return Ok(false);
+        }
+
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        Err(GitError::Exit(output.status))
+    }
+
+    /// The paths with unresolved conflicts in the index.
+    pub fn conflicted_paths(&self) -> Result<Vec<String>, GitError> {
+        let output = self.command().args(["diff","--name-only","--diff-filter=U"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).lines().map(|l| l.to_string()).collect())
+    }
+
+    /// Switch to an existing branch.
+    pub fn checkout(&self, name: &str) -> Result<(), GitError> {
+        let status = self.command().args(["checkout","--quiet",name]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Create a local branch `name` which tracks the branch of the same name on `remote`.
+    ///
+    /// Used in `git pr checkout` to start reviewing someone else's PR.
+    pub fn create_tracking_branch(&self, remote: &str, name: &str) -> Result<(), GitError> {
+        let status = self.command().args(["branch","--quiet","--track",name])
+            .arg(format!("{}/{}", remote, name)).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Rename a local branch, along with its config (such as its upstream).
+    pub fn rename_branch(&self, old: &str, new: &str) -> Result<(), GitError> {
+        let status = self.command().args(["branch","--move",old,new]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Check whether git would accept `name` as the name of a branch.
+    pub fn is_valid_branch_name(&self, name: &str) -> Result<bool, GitError> {
+        let status = self.command().args(["check-ref-format","--branch",name])
+            .stdout(Stdio::null()).stderr(Stdio::null()).status()?;
+
+        Ok(status.success())
+    }
+
+    /// Make local branch `name` track the branch of the same name on `remote`.
+    pub fn set_upstream(&self, remote: &str, name: &str) -> Result<(), GitError> {
+        let status = self.command()
+            .args(["branch","--quiet"]).arg(format!("--set-upstream-to={}/{}", remote, name))
+            .arg(name).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Create an empty repository in the working directory, whose first branch will be `branch`.
+    pub fn init(&self, bare: bool, branch: &str) -> Result<(), GitError> {
+        let mut command = self.command();
+        command.args(["init","--quiet"]);
+        if bare {
+            command.arg("--bare");
+        }
+        let status = command.arg(format!("--initial-branch={}", branch)).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Add a remote called `name`, fetching from and pushing to `url`.
+    pub fn add_remote(&self, name: &str, url: &str) -> Result<(), GitError> {
+        let status = self.command().args(["remote","add",name,url]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Delete a branch
+    ///
+    /// Won't delete unmerged branches.
+    pub fn delete_branch(&self, name: &str) -> Result<(), GitError> {
+        let status = self.command().args(["branch","-d",name]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Delete a branch, even if git doesn't think it has been merged.
+    ///
+    /// For callers which have already checked that it was merged somewhere other than HEAD.
+    pub fn force_delete_branch(&self, name: &str) -> Result<(), GitError> {
+        let status = self.command().args(["branch","-D",name]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Point a fully-qualified ref at `commit`, creating it if necessary.
+    pub fn update_ref(&self, refname: &str, commit: &str) -> Result<(), GitError> {
+        let status = self.command().args(["update-ref",refname,commit]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Push several branches (or any other refspecs) to `remote` at once.
+    pub fn push_branches(&self, remote: &str, names: &[String]) -> Result<(), GitError> {
+        let status = self.command().args(["push","--quiet",remote]).args(names).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Push several refspecs to `remote` as a single transaction: either every ref is updated, or
+    /// none are.
+    ///
+    /// If the remote rejects the push, the error lists each ref and the reason git gave for it.
+    pub fn push_atomic(&self, remote: &str, refspecs: &[String]) -> Result<(), GitError> {
+        let rejections = self.try_push_atomic(remote, refspecs, &[])?;
+        match rejections.is_empty() {
+            true => Ok(()),
+            false => Err(GitError::push_rejected(remote, &rejections)),
+        }
+    }
+
+    /// Like [`Git::push_atomic`], but only if each `(refname, commit)` lease still holds: the
+    /// remote must have `refname` at `commit`, or the whole push is rejected.
+    ///
+    /// Rather than failing, returns the refs the remote rejected along with git's reasons (as from
+    /// [`parse::parse_push_rejections`]), so the caller can decide whether to try again. An empty
+    /// list means the push went through.
+    pub fn try_push_atomic(&self, remote: &str, refspecs: &[String], leases: &[(&str, &str)])
+        -> Result<Vec<(String, String)>,GitError> {
+        let output = self.command().args(["push","--atomic","--porcelain",remote])
+            .args(leases.iter().map(|(refname, commit)| {
+                format!("--force-with-lease={}:{}", refname, commit)
+            }))
+            .args(refspecs).output()?;
+        if output.status.success() {
+            return Ok(vec![]);
+        }
+
+        let rejections = parse::parse_push_rejections(&String::from_utf8_lossy(&output.stdout));
+        match rejections.is_empty() {
+            true => Err(GitError::Exit(output.status)),
+            false => Ok(rejections),
+        }
+    }
+
+    /// Move a ref from `old` to `new`, failing if someone else has moved it in the meantime.
+    pub fn move_ref(&self, refname: &str, new: &str, old: &str) -> Result<(), GitError> {
+        let status = self.command().args(["update-ref",refname,new,old]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Merge two commits without touching the working tree, returning the resulting tree.
+    ///
+    /// Returns `None` if the merge has conflicts.
+    pub fn merge_tree(&self, ours: &str, theirs: &str) -> Result<Option<String>,GitError> {
+        let output = self.command()
+            .args(["merge-tree","--write-tree","--no-messages",ours,theirs]).output()?;
+        if output.status.code() == Some(1) {
+            return Ok(None);
+        }
+        assert_success(output.status)?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout.lines().next().map(|tree| tree.to_string()))
+    }
+
+    /// The paths which conflict when `theirs` is merged into `ours`, without touching the working
+    /// tree, the index, or any ref. An empty list means they merge cleanly.
+    pub fn merge_conflicts(&self, ours: &str, theirs: &str) -> Result<Vec<String>,GitError> {
+        let output = self.command()
+            .args(["merge-tree","--write-tree","--name-only","--no-messages",ours,theirs])
+            .output()?;
+        if output.status.code() != Some(1) {
+            assert_success(output.status)?;
+            return Ok(vec![]);
+        }
+
+        // The merged tree comes first, then each conflicted path
+        let mut paths: Vec<String> = String::from_utf8_lossy(&output.stdout).lines()
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .map(|line| line.to_string())
+            .collect();
+        paths.dedup();
+        Ok(paths)
+    }
+
+    /// Make an annotated tag `name` (like `pr-archive/hotfix/1234567`) on `commit`.
+    pub fn create_tag(&self, name: &str, commit: &str, message: &str) -> Result<(), GitError> {
+        let status = self.command()
+            .args(["tag","--annotate","--no-sign","--message",message,name,commit]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Write the commits in `revisions` (like `refs/tags/x ^origin/trunk`) to a bundle at `path`,
+    /// along with the refs named there.
+    pub fn bundle_create(&self, path: &Path, revisions: &[&str]) -> Result<(), GitError> {
+        let status = self.command()
+            .args(["bundle","create","--quiet"]).arg(path).args(revisions).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// List the refs a bundle holds.
+    ///
+    /// Each line of output is `<hash> <refname>`.
+    pub fn bundle_list_heads(&self, path: &Path) -> Result<String,GitError> {
+        let output = self.command().args(["bundle","list-heads"]).arg(path).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Delete a fully-qualified ref, whatever kind it is.
+    pub fn delete_ref(&self, refname: &str) -> Result<(), GitError> {
+        let status = self.command().args(["update-ref","-d",refname]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Remove notes attached to objects which no longer exist, returning the orphaned objects.
+    ///
+    /// With `dry_run`, the notes are only reported, not removed.
+    pub fn prune_notes(&self, notes_ref: &str, dry_run: bool) -> Result<Vec<String>,GitError> {
+        let mut command = self.command();
+        command
+            .arg("notes").arg(format!("--ref={}", notes_ref)).args(["prune","--verbose"]);
+        if dry_run {
+            command.arg("--dry-run");
+        }
+        let output = command.output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).lines().map(|l| l.to_string()).collect())
+    }
+
+    /// Push a branch to `remote` and set upstream tracking
+    ///
+    /// Used in `git pr create` to notify other developers that a new PR has been created.
+    pub fn push_upstream(&self, remote: &str, name: &str) -> Result<(), GitError> {
+        let status = self.command().args(["push","-u",remote,name]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Check whether `remote` would accept a push of `refspec`, without changing anything there.
+    ///
+    /// This still connects to the remote and authenticates, so it tells whether we may push at all.
+    /// Git's messages are swallowed; a refusal is simply an error.
+    pub fn push_dry_run(&self, remote: &str, refspec: &str) -> Result<(), GitError> {
+        let output = self.command().args(["push","--dry-run","--quiet",remote,refspec]).output()?;
+        assert_success(output.status)?;
+
+        Ok(())
+    }
+
+    /// Delete a branch from `remote`
+    ///
+    /// Used in `git pr abandon` to withdraw a PR. Local branches are left alone.
+    pub fn delete_remote_branch(&self, remote: &str, name: &str) -> Result<(), GitError> {
+        let status = self.command().args(["push","--quiet","--delete",remote,name]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Fetch particular refs (or refspecs) from `remote`.
+    pub fn fetch_refs(&self, remote: &str, refspecs: &[String]) -> Result<(),GitError> {
+        let status = self.command().args(["fetch","--quiet",remote]).args(refspecs).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// List the refs `remote` has under `prefix`, without fetching anything.
+    ///
+    /// Each line of output is `<hash> <refname>`, in the same form as [`Git::remote_refs`].
+    pub fn ls_remote(&self, remote: &str, prefix: &str) -> Result<String,GitError> {
+        let output = self.command().args(["ls-remote",remote,&format!("{}*", prefix)]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).replace('\t', " "))
+    }
+
+    /// List the branches `remote` has whose names match `pattern`, without fetching anything.
+    ///
+    /// The pattern is a glob like `hotfix/*`, matched against whole branch names; `*` lists every
+    /// branch. Each line of output is `<hash> refs/heads/<branch>`. This is a single round trip, so
+    /// it is a cheap way to check what the remote has right now, even in a shallow clone.
+    pub fn ls_remote_heads(&self, remote: &str, pattern: &str) -> Result<String,GitError> {
+        let output = self.command()
+            .args(["ls-remote","--heads",remote,&format!("refs/heads/{}", pattern)]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).replace('\t', " "))
+    }
+
+    /// Fetch `remote`'s notes into `refs/notes/remotes/<remote>/`, without touching our own.
+    pub fn fetch_notes(&self, remote: &str) -> Result<(),GitError> {
+        let status = self.command().args(["fetch","--quiet","--prune",remote])
+            .arg(format!("+refs/notes/*:refs/notes/remotes/{}/*", remote)).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Push a ref to the same name on `remote`, but only if the remote still has it at `expected`
+    /// (`None` meaning it must not exist yet).
+    ///
+    /// Returns false if the push was rejected because someone else got there first; any other
+    /// failure is an error.
+    pub fn push_with_lease(&self, remote: &str, refname: &str, expected: Option<&str>)
+        -> Result<bool,GitError> {
+        let output = self.command().args(["push","--porcelain",remote])
+            .arg(format!("--force-with-lease={}:{}", refname, expected.unwrap_or_default()))
+            .arg(format!("{}:{}", refname, refname)).output()?;
+
+        match parse_push_status(&String::from_utf8_lossy(&output.stdout), refname) {
+            Some(accepted) => Ok(accepted),
+            None => Err(GitError::Exit(output.status))
+        }
+    }
+
+    /// Merge another notes ref into `notes_ref`, reconciling notes changed on both sides with one
+    /// of git's notes merge `strategy`s: "cat_sort_uniq" keeps the lines from both, and "theirs"
+    /// takes the other ref's note.
+    pub fn merge_notes(&self, notes_ref: &str, other: &str, strategy: &str)
+        -> Result<(),GitError> {
+        let status = self.command().arg("notes").arg(format!("--ref={}", notes_ref))
+            .args(["merge","--quiet",&format!("--strategy={}", strategy),other]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Add a line to the note attached to `object`, creating the note if necessary.
+    pub fn append_note(&self, notes_ref: &str, object: &str, text: &str) -> Result<(),GitError> {
+        let status = self.command().arg("notes").arg(format!("--ref={}", notes_ref))
+            .args(["append","-m",text,object]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Replace the note attached to `object` entirely.
+    pub fn replace_note(&self, notes_ref: &str, object: &str, text: &str)
+        -> Result<(),GitError> {
+        let status = self.command().arg("notes").arg(format!("--ref={}", notes_ref))
+            .args(["add","--force","-m",text,object]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// Attach the blob `note` to `object` as its note, as it is, replacing any note it had.
+    ///
+    /// Unlike [`Git::replace_note`], the note may hold anything, including binary data.
+    pub fn attach_note(&self, notes_ref: &str, object: &str, note: &str) -> Result<(),GitError> {
+        let status = self.command().arg("notes").arg(format!("--ref={}", notes_ref))
+            .args(["add","--force","-C",note,object]).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+
+    /// List every object that has a note, as `<note blob> <annotated object>` lines.
+    pub fn list_notes(&self, notes_ref: &str) -> Result<String,GitError> {
+        let output = self.command()
+            .arg("notes").arg(format!("--ref={}", notes_ref)).arg("list").output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// The note attached to `object`, or `None` if there isn't one.
+    pub fn show_note(&self, notes_ref: &str, object: &str) -> Result<Option<String>,GitError> {
+        let output = self.command().arg("notes").arg(format!("--ref={}", notes_ref))
+            .args(["show",object]).stderr(Stdio::null()).output()?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+    }
+
+    /// Find the `.git` directory for this worktree
+    ///
+    /// This is where git-pr keeps any local bookkeeping that should never be shared with
+    /// collaborators, such as the progress of a long-running `git pr clean`.
+    ///
+    /// When a repository has several worktrees, each one gets its own git directory (under
+    /// `.git/worktrees/`), so anything stored here is private to the worktree we are running in.
+    /// That is the right place for state that depends on what is checked out. State that is about
+    /// the repository as a whole belongs in [`Git::git_common_dir`] instead.
+    pub fn git_dir(&self) -> Result<PathBuf,GitError> {
+        let output = self.command().args(["rev-parse","--absolute-git-dir"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim_end()))
+    }
+
+    /// Find the `.git` directory shared by every worktree of this repository
+    ///
+    /// Refs and config live here. Outside of a linked worktree, this is the same as
+    /// [`Git::git_dir`].
+    pub fn git_common_dir(&self) -> Result<PathBuf,GitError> {
+        let output = self.command().args(["rev-parse","--git-common-dir"]).output()?;
+        assert_success(output.status)?;
+
+        // Unlike --absolute-git-dir, this may be relative to the directory we ran git in.
+        let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim_end());
+        Ok(self.working_dir.as_ref().as_ref().join(path))
+    }
+
+    /// Write an empty tree object and return its hash.
+    ///
+    /// The hash depends on the repository's object format, so we ask git rather than hard-coding
+    /// the well-known SHA-1 value.
+    pub fn empty_tree(&self) -> Result<String,GitError> {
+        let output = self.command().arg("mktree").stdin(Stdio::null()).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+
+    /// Stage every change in the working tree, including new and deleted files.
+    pub fn add_all(&self) -> Result<(),GitError> {
+        let status = self.command().args(["add","--all"]).status()?;
+        assert_success(status)
+    }
+
+    /// Write the index out as a tree object, returning its hash.
+    pub fn write_tree(&self) -> Result<String,GitError> {
+        let output = self.command().arg("write-tree").output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+
+    /// Store a file's contents in the object database, returning the blob's hash.
+    pub fn hash_object(&self, path: &Path) -> Result<String,GitError> {
+        let output = self.command().args(["hash-object","-w","--"]).arg(path).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+
+    /// The raw contents of an object of type `kind` (`commit`, say), byte for byte.
+    pub fn read_object(&self, kind: &str, object: &str) -> Result<Vec<u8>,GitError> {
+        let output = self.command().args(["cat-file",kind,object]).output()?;
+        assert_success(output.status)?;
+
+        Ok(output.stdout)
+    }
+
+    /// Store `contents` in the object database as an object of type `kind`, returning its hash.
+    ///
+    /// This is the inverse of [`Git::read_object`], so an object read from one repository can be
+    /// written into another exactly as it was, signature and all.
+    pub fn write_object(&self, kind: &str, contents: &[u8]) -> Result<String,GitError> {
+        use std::io::Write;
+        let mut child = self.command().args(["hash-object","-w","--stdin","-t",kind])
+            .stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        child.stdin.take().expect("stdin is piped").write_all(contents)?;
+        let output = child.wait_with_output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+
+    /// The contents of a blob, byte for byte.
+    pub fn read_blob(&self, blob: &str) -> Result<Vec<u8>,GitError> {
+        let output = self.command().args(["cat-file","blob",blob]).output()?;
+        assert_success(output.status)?;
+
+        Ok(output.stdout)
+    }
+
+    /// The contents of the file at `path` in commit `rev`, or `None` if it has no such file.
+    pub fn file_at(&self, rev: &str, path: &str) -> Result<Option<Vec<u8>>,GitError> {
+        let output = self.command().args(["ls-tree","--full-tree",rev,"--",path]).output()?;
+        assert_success(output.status)?;
+
+        // Each line is "<mode> <type> <hash>\t<path>"
+        let listing = String::from_utf8_lossy(&output.stdout);
+        match listing.split_whitespace().collect::<Vec<_>>()[..] {
+            [_, "blob", blob, ..] => Ok(Some(self.read_blob(blob)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Create a commit object (without updating any ref) and return its hash.
+    ///
+    /// With `sign`, the commit is signed exactly as `git commit -S` would sign it, honoring
+    /// `gpg.format`, `gpg.program` and `user.signingKey`.
+    pub fn commit_tree(&self, tree: &str, parents: &[&str], message: &str, sign: bool)
+        -> Result<String,GitError> {
+        let mut command = self.command();
+        command.arg("commit-tree");
+        for parent in parents {
+            command.arg("-p").arg(parent);
+        }
+        if sign {
+            command.arg("-S");
+        }
+        let output = command.args(["-m",message,tree]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+
+    /// Find the subject line of a commit's message.
+    pub fn subject(&self, commit: &str) -> Result<String,GitError> {
+        let output = self.command().args(["log","-1","--format=%s",commit,"--"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+
+    /// Report who wrote a commit, as `Name <email>`.
+    ///
+    /// Git applies the repository's mailmap (`.mailmap`, `mailmap.file` or `mailmap.blob`) when
+    /// producing this, so people who commit under several addresses are reported consistently.
+    pub fn author_of(&self, commit: &str) -> Result<String,GitError> {
+        let output = self.command().args(["log","-1","--format=%aN <%aE>",commit]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+
+    /// List the merge commits on `trunk`'s first-parent history, newest first.
+    ///
+    /// Each line is `<commit time> <parent> <parent>...`, a tab, and then the subject. The optional
+    /// `since` and `until` bounds accept any date format `git log` understands.
+    pub fn merges(&self, trunk: &str, since: Option<&str>, until: Option<&str>)
+        -> Result<String,GitError> {
+        let mut command = self.command();
+        command
+            .args(["log","--merges","--first-parent","--format=%ct %P%x09%s"]);
+        if let Some(since) = since {
+            command.arg(format!("--since={}", since));
+        }
+        if let Some(until) = until {
+            command.arg(format!("--until={}", until));
+        }
+        let output = command.arg(trunk).arg("--").output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// List the paths changed between the merge base of `base` and `tip`, and `tip` itself.
+    ///
+    /// This is the same set of files a reviewer would see for a PR branch, regardless of how far
+    /// trunk has moved since the branch was created.
+    pub fn changed_paths(&self, base: &str, tip: &str) -> Result<Vec<String>,GitError> {
+        let output = self.command()
+            .args(["diff","--name-only",&format!("{}...{}", base, tip),"--"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).lines().map(|l| l.to_string()).collect())
+    }
+
+    /// Find the best common ancestor of two commits, or `None` if their histories are unrelated.
+    pub fn merge_base(&self, left: &str, right: &str) -> Result<Option<String>,GitError> {
+        let output = self.command().args(["merge-base",left,right]).output()?;
+        if output.status.code() == Some(1) {
+            return Ok(None);
+        }
+        assert_success(output.status)?;
+
+        Ok(Some(String::from_utf8_lossy(&output.stdout).trim_end().to_string()))
+    }
+
+    /// The differences between two commits' trees, as `git diff` shows them.
+    ///
+    /// `flags` are passed along to `git diff`, so `--stat` or `--name-only` change what is shown.
+    pub fn diff(&self, from: &str, to: &str, flags: &[&str]) -> Result<String,GitError> {
+        let output = self.command().arg("diff").args(flags).args([from,to,"--"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// The commits in `range`, as `git log` shows them.
+    ///
+    /// `flags` are passed along to `git log`, ahead of the range.
+    pub fn log(&self, range: &str, flags: &[&str]) -> Result<String,GitError> {
+        let output = self.command().arg("log").args(flags).args([range,"--"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Count the commits reachable from only `left`, and from only `right`.
+    pub fn divergence(&self, left: &str, right: &str) -> Result<(usize, usize),GitError> {
+        let output = self.command()
+            .args(["rev-list","--left-right","--count",&format!("{}...{}", left, right),"--"])
+            .output()?;
+        assert_success(output.status)?;
+
+        let text = String::from_utf8_lossy(&output.stdout);
+        let mut counts = text.split_whitespace().map(|n| n.parse().unwrap_or(0));
+        Ok((counts.next().unwrap_or(0), counts.next().unwrap_or(0)))
+    }
+
+    /// Summarize the differences between two commits' trees, as `git diff --stat` does.
+    pub fn diff_stat(&self, from: &str, to: &str) -> Result<String,GitError> {
+        let output = self.command().args(["diff","--stat",from,to,"--"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Describe each commit in `range`, oldest first.
+    ///
+    /// Each line is `<hash>\t<author name> <author email>\t<subject>`.
+    pub fn commit_summaries(&self, range: &str) -> Result<String,GitError> {
+        let output = self.command()
+            .args(["log","--reverse","--no-merges","--format=%H%x09%aN <%aE>%x09%s",range,"--"])
+            .output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// The full message of each commit in `range`, oldest first, as `(hash, subject, body)`.
+    pub fn commit_messages(&self, range: &str) -> Result<Vec<(String, String, String)>,GitError> {
+        let output = self.command()
+            .args(["log","--reverse","--no-merges","--format=%H%x1f%s%x1f%b%x1e",range,"--"])
+            .output()?;
+        assert_success(output.status)?;
+
+        let text = String::from_utf8_lossy(&output.stdout);
+        Ok(text.split('\x1e')
+            .filter_map(|record| {
+                let mut fields = record.trim_start_matches('\n').splitn(3, '\x1f');
+                Some((fields.next()?.to_string(), fields.next()?.to_string(),
+                      fields.next()?.trim().to_string()))
+            })
+            .collect())
+    }
+
+    /// Run `git format-patch` on the commits in `range`, returning what it prints.
+    ///
+    /// That is the name of each file written, one per line, unless `options` include `--stdout`,
+    /// in which case it is the patches themselves, as an mbox.
+    pub fn format_patch(&self, range: &str, options: &[String]) -> Result<Vec<u8>,GitError> {
+        let output = self.command()
+            .arg("format-patch").args(options).arg(range).arg("--").output()?;
+        assert_success(output.status)?;
+
+        Ok(output.stdout)
+    }
+
+    /// Mail `patches` with `git send-email`, passing it `options`.
+    ///
+    /// send-email talks to the user (to confirm each message, for instance), so it shares our
+    /// terminal.
+    pub fn send_email(&self, options: &[String], patches: &[PathBuf]) -> Result<(),GitError> {
+        let status = self.command()
+            .arg("send-email").args(options).arg("--").args(patches).status()?;
+        assert_success(status)
+    }
+
+    /// Run `git request-pull`, summarizing the changes from `start` to `end` for someone who will
+    /// fetch them from `url`, and return the summary.
+    ///
+    /// `end` may be `<commit>:<branch>`, naming the branch on `url` the commit is published as.
+    /// With `patch`, the summary ends with the full diff.
+    ///
+    /// If `url` can't be reached, or doesn't have the branch yet, git warns about it on stderr but
+    /// still writes the summary, which is returned all the same.
+    pub fn request_pull(&self, start: &str, url: &str, end: &str, patch: bool)
+        -> Result<String,GitError> {
+        let output = self.command()
+            .arg("request-pull").args(patch.then_some("-p")).args([start,url,end])
+            .stderr(Stdio::inherit()).output()?;
+        if output.status.code() != Some(1) || output.stdout.is_empty() {
+            assert_success(output.status)?;
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// The changes a single commit makes, as a unified diff.
+    pub fn commit_diff(&self, commit: &str) -> Result<String,GitError> {
+        let output = self.command().args(["show","--format=","--patch",commit,"--"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// When `commit` was committed, in seconds since the epoch.
+    pub fn commit_time(&self, commit: &str) -> Result<i64,GitError> {
+        let output = self.command().args(["log","-1","--format=%ct",commit,"--"]).output()?;
+        assert_success(output.status)?;
+
+        String::from_utf8_lossy(&output.stdout).trim().parse()
+            .map_err(|_| GitError::Refused(crate::tr!("commit-time-unreadable", commit = commit)))
+    }
+
+    /// The author timestamps (seconds since the epoch) of every commit in `range`.
+    pub fn author_times(&self, range: &str) -> Result<Vec<i64>,GitError> {
+        let output = self.command().args(["log","--format=%at",range,"--"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).lines()
+            .filter_map(|l| l.trim().parse().ok())
+            .collect())
+    }
+
+    /// Describe each commit in `range` along with when it was written and committed, oldest first.
+    ///
+    /// Each line is `<hash>\t<author time>\t<commit time>\t<author>\t<subject>`, with times in
+    /// seconds since the epoch, and the author as `<name> <email>`.
+    pub fn commit_history(&self, range: &str) -> Result<String,GitError> {
+        let output = self.command()
+            .args(["log","--reverse","--no-merges","--format=%H%x09%at%x09%ct%x09%aN <%aE>%x09%s",
+                   range,"--"])
+            .output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Map `Name <email>` contacts through the repository's mailmap.
+    ///
+    /// Returns one canonical contact per input, in the same order. Contacts which the mailmap does
+    /// not mention are returned unchanged.
+    pub fn check_mailmap(&self, contacts: &[String]) -> Result<Vec<String>,GitError> {
+        if contacts.is_empty() {
+            return Ok(vec![]);
+        }
+        let output = self.command().arg("check-mailmap").args(contacts).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).lines().map(|l| l.to_string()).collect())
+    }
+
+    /// Check the signature on a commit.
+    ///
+    /// Returns three lines: git's one-letter verdict (`%G?`, where "G" means a good signature from
+    /// a trusted key), the signer (`%GS`), and then the commit message.
+    pub fn commit_signature(&self, commit: &str) -> Result<String,GitError> {
+        let output = self.command().args(["log","-1","--format=%G?%n%GS%n%B",commit]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// The program git pages its output through, honoring `GIT_PAGER`, `core.pager` and `PAGER`.
+    ///
+    /// This is a shell command line, and `cat` means not to page at all.
+    pub fn pager(&self) -> Result<String,GitError> {
+        let output = self.command().args(["var","GIT_PAGER"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+
+    /// The editor git would open for a commit message, honoring `GIT_EDITOR`, `core.editor`,
+    /// `VISUAL` and `EDITOR`.
+    ///
+    /// Like [`Git::pager`], this is a shell command line.
+    pub fn editor(&self) -> Result<String,GitError> {
+        let output = self.command().args(["var","GIT_EDITOR"]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+
+    /// Understand `text` as a moment in time, the way git does for `--until`, like "2026-10-24" or
+    /// "2026-10-24 09:00". Returns seconds since the Unix epoch.
+    ///
+    /// Like git, this makes the best of whatever it is given, rather than refusing it; text it
+    /// can't make sense of usually means the present moment.
+    pub fn parse_date(&self, text: &str) -> Result<i64,GitError> {
+        let output = self.command().arg("rev-parse").arg(format!("--until={}", text)).output()?;
+        assert_success(output.status)?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        stdout.trim().strip_prefix("--min-age=").and_then(|time| time.parse().ok())
+            .ok_or_else(|| GitError::Refused(tr!("date-unparsable", date = text)))
+    }
+
+    /// Read a single config value
+    ///
+    /// Git reports a missing key by exiting with status 1, which we translate into `None` rather
+    /// than an error. Any other failure (such as a malformed config file) is still an error.
+    pub fn config_get(&self, key: &str) -> Result<Option<String>,GitError> {
+        let output = self.command().args(["config","--get",key]).output()?;
+        if output.status.code() == Some(1) {
+            return Ok(None);
+        }
+        assert_success(output.status)?;
+
+        Ok(Some(String::from_utf8_lossy(&output.stdout).trim_end().to_string()))
+    }
+
+    /// Read a config value along with where it was set, like `global file:/home/me/.gitconfig`.
+    ///
+    /// The location is git's scope, followed by the file (or "command line:" for `git -c`).
+    pub fn config_get_with_origin(&self, key: &str)
+        -> Result<Option<(String, String)>,GitError> {
+        let output = self.command()
+            .args(["config","--show-scope","--show-origin","--get",key]).output()?;
+        if output.status.code() == Some(1) {
+            return Ok(None);
+        }
+        assert_success(output.status)?;
+
+        let text = String::from_utf8_lossy(&output.stdout).trim_end().to_string();
+        let mut fields = text.splitn(3, '\t');
+        let scope = fields.next().unwrap_or_default();
+        let origin = fields.next().unwrap_or_default();
+        let value = fields.next().unwrap_or_default().to_string();
+        Ok(Some((value, format!("{} {}", scope, origin))))
+    }
+
+    /// Read every value of a multi-valued config key
+    ///
+    /// Like [`Git::config_get`], a missing key is not an error; it simply yields an empty list.
+    pub fn config_get_all(&self, key: &str) -> Result<Vec<String>,GitError> {
+        let output = self.command().args(["config","--get-all",key]).output()?;
+        if output.status.code() == Some(1) {
+            return Ok(vec![]);
+        }
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).lines().map(|l| l.to_string()).collect())
+    }
+
+    /// Set a config value, in the user's global config if `global` is set and in the repository's
+    /// otherwise.
+    pub fn config_set(&self, key: &str, value: &str, global: bool) -> Result<(),GitError> {
+        let scope = if global { "--global" } else { "--local" };
+        let status = self.command().args(["config",scope,key,value]).status()?;
+        assert_success(status)
+    }
+
+    /// Remove a config value. Removing one that isn't set is not an error.
+    pub fn config_unset(&self, key: &str, global: bool) -> Result<(),GitError> {
+        let scope = if global { "--global" } else { "--local" };
+        let status = self.command().args(["config",scope,"--unset",key]).status()?;
+        if status.code() == Some(5) {
+            return Ok(());
+        }
+        assert_success(status)
+    }
+
+    /// Read every config entry whose key matches a regex
+    ///
+    /// Produces the raw `key value` lines from `git config --get-regexp`. No matches is not an
+    /// error.
+    pub fn config_get_regexp(&self, pattern: &str) -> Result<String,GitError> {
+        let output = self.command().args(["config","--get-regexp",pattern]).output()?;
+        if output.status.code() == Some(1) {
+            return Ok(String::new());
+        }
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Like [`Git::config_get_regexp`], for the config file held in `blob`, which may be given as
+    /// `<rev>:<path>`, rather than the repository's own config.
+    pub fn config_get_regexp_in_blob(&self, blob: &str, pattern: &str)
+        -> Result<String,GitError> {
+        let output = self.command()
+            .args(["config",&format!("--blob={}", blob),"--get-regexp",pattern]).output()?;
+        if output.status.code() == Some(1) {
+            return Ok(String::new());
+        }
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Replace every value of a multi-valued key in the repository's config with `values`,
+    /// removing the key altogether if there are none.
+    pub fn config_replace_all(&self, key: &str, values: &[String]) -> Result<(),GitError> {
+        let status = self.command().args(["config","--local","--unset-all",key]).status()?;
+        if status.code() != Some(5) {
+            assert_success(status)?;
+        }
+        for value in values {
+            let status = self.command().args(["config","--local","--add",key,value]).status()?;
+            assert_success(status)?;
+        }
+        Ok(())
+    }
+
+    /// List objects reachable from `range` which are not present locally.
+    ///
+    /// In a partial clone, these are the objects git would otherwise fetch lazily -- one at a time
+    /// -- the moment something like `git diff` needs them.
+    pub fn missing_objects(&self, range: &str) -> Result<Vec<String>,GitError> {
+        let output = self.command()
+            .args(["rev-list","--objects","--missing=print",range]).output()?;
+        assert_success(output.status)?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).lines()
+            .filter_map(|l| l.strip_prefix('?'))
+            .map(|oid| oid.to_string())
+            .collect())
+    }
+
+    /// Fetch specific objects from a promisor remote in a single round trip.
+    pub fn fetch_objects(&self, remote: &str, oids: &[String]) -> Result<(),GitError> {
+        let status = self.command()
+            .args(["fetch","--no-tags","--no-write-fetch-head","--filter=blob:none",remote])
+            .args(oids).status()?;
+        assert_success(status)?;
+
+        Ok(())
+    }
+}
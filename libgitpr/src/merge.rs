@@ -0,0 +1,335 @@
+//! Merging PRs into trunk
+//!
+//! Merges are made without touching the working tree: git computes the merged tree, we wrap it in a
+//! merge commit, and trunk is moved forward only if nobody has moved it since we looked. Nothing is
+//! checked out, so a merge can't disturb whatever the user was in the middle of. The exception is
+//! when trunk itself is checked out; then the working tree is fast-forwarded along with it, as
+//! `git merge --ff-only` would.
+//!
+//! Like `git merge`, a PR which already contains all of trunk may be merged by fast-forwarding
+//! trunk to it instead (see [`FastForward`]).
+//!
+//! The merge commit's subject is the one `git merge` would write ("Merge branch 'hotfix/1234567'"),
+//! so that [`crate::stats`] recognizes it. Extra information goes in trailers at the end of the
+//! message.
+//!
+//! A PR can also be squashed instead (see [`squash_commit`]): its changes become a single commit on
+//! trunk, with the PR's commits summarized in the message, so trunk's history stays linear. The
+//! message can be edited ahead of time and kept for the squash to use (see
+//! [`store_squash_message`]).
+//!
+//! Whichever way the commit is made, [`push_onto_trunk`] makes it on top of the remote's trunk and
+//! publishes it, retrying if someone else moves trunk first. Afterwards, [`catch_up_trunk`] brings
+//! the local trunk along.
+use crate::config::Config;
+use crate::pull_request::PullRequest;
+use crate::state::{self, State};
+use crate::{tr, Git, GitError};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+
+/// Compose a merge commit message for `pr`, with `trailers` as `(key, value)` pairs.
+pub fn merge_message(pr: &PullRequest, trailers: &[(&str, String)]) -> String {
+    let mut message = format!("Merge branch '{}'\n", pr.branch);
+    if !trailers.is_empty() {
+        message.push('\n');
+        for (key, value) in trailers {
+            // Trailers are one line each, so fold anything multi-line
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            message.push_str(&format!("{}: {}\n", key, value));
+        }
+    }
+    message
+}
+
+/// Compose the message for squashing `pr` into one commit, given the PR's commits as `(hash,
+/// subject, body)`, oldest first (see [`Git::commit_messages`]).
+///
+/// The subject names the PR. The commits' bodies follow as its description, then a list of the
+/// commits themselves. Their trailers (`Signed-off-by` and the like) are gathered at the end,
+/// without repeats, along with a `Squashed-From` trailer recording the commit it was squashed
+/// from.
+pub fn squash_message(pr: &PullRequest, commits: &[(String, String, String)]) -> String {
+    let mut message = format!("{} ({})\n\n", pr.name, pr.branch);
+    let description = description(commits);
+    if !description.is_empty() {
+        message.push_str(&description);
+        message.push_str("\n\n");
+    }
+    let mut trailers: Vec<&str> = vec![];
+    for (_, _, body) in commits {
+        for line in split_trailers(body).1 {
+            if !trailers.contains(&line) {
+                trailers.push(line);
+            }
+        }
+    }
+    message.push_str("Squashed commit of the following:\n\n");
+    for (hash, subject, _) in commits {
+        message.push_str(&format!("* {} {}\n", hash.chars().take(7).collect::<String>(), subject));
+    }
+    message.push('\n');
+    for line in trailers {
+        message.push_str(line);
+        message.push('\n');
+    }
+    message.push_str(&format!("Squashed-From: {}\n", pr.tip));
+    message
+}
+
+/// Describe a PR by its commits, given as `(hash, subject, body)`: their bodies, without
+/// trailers, one paragraph after another.
+pub fn description(commits: &[(String, String, String)]) -> String {
+    let texts: Vec<&str> = commits.iter()
+        .map(|(_, _, body)| split_trailers(body).0)
+        .filter(|text| !text.is_empty())
+        .collect();
+    texts.join("\n\n")
+}
+
+// Split a commit message body into its text and its trailers: the lines of its last paragraph, if
+// every one of them looks like `Key: value`.
+fn split_trailers(body: &str) -> (&str, Vec<&str>) {
+    let body = body.trim();
+    let (text, last) = match body.rsplit_once("\n\n") {
+        Some((text, last)) => (text.trim_end(), last),
+        None => ("", body),
+    };
+    let is_trailer = |line: &str| line.split_once(": ").is_some_and(|(key, _)| {
+        !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    match !last.is_empty() && last.lines().all(is_trailer) {
+        true => (text, last.lines().collect()),
+        false => (body, vec![]),
+    }
+}
+
+/// The squash message stored for the PR whose tip is `tip` by [`store_squash_message`], if any.
+pub fn stored_squash_message(git: &Git, tip: &str) -> Result<Option<String>,GitError> {
+    match fs::read_to_string(squash_message_path(git, tip)?) {
+        Ok(message) => Ok(Some(message)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Keep `message` to squash the PR whose tip is `tip` with, instead of composing one.
+///
+/// Messages are kept by tip, so one is forgotten as soon as the PR gains another commit; it would
+/// no longer describe what is being squashed.
+pub fn store_squash_message(git: &Git, tip: &str, message: &str) -> Result<(),GitError> {
+    let path = squash_message_path(git, tip)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, message)?;
+    Ok(())
+}
+
+/// Throw away the squash message stored for `tip`, once it has been used.
+pub fn forget_squash_message(git: &Git, tip: &str) -> Result<(),GitError> {
+    match fs::remove_file(squash_message_path(git, tip)?) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+fn squash_message_path(git: &Git, tip: &str) -> Result<PathBuf,GitError> {
+    Ok(git.git_common_dir()?.join("git-pr").join("squash").join(tip))
+}
+
+/// Whether a merge may simply move trunk forward to the PR, as with `git merge`'s flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FastForward {
+    /// Fast-forward if the PR already contains all of trunk; otherwise make a merge commit.
+    #[default]
+    Allowed,
+
+    /// Fast-forward, or refuse if that isn't possible (`--ff-only`).
+    Only,
+
+    /// Always make a merge commit (`--no-ff`).
+    Never,
+}
+
+/// Merge `pr` into the local `trunk` branch with a merge commit, returning the new commit.
+///
+/// Refuses if the PR conflicts with trunk. Trunk is only updated if it still points where it did
+/// when the merge began.
+pub fn merge(git: &Git, trunk: &str, pr: &PullRequest, message: &str) -> Result<String,GitError> {
+    merge_with(git, trunk, pr, message, FastForward::Never)
+}
+
+/// Merge `pr` into the local `trunk` branch, returning the commit trunk now points at.
+///
+/// `message` is only used if a merge commit is made. Otherwise this works like [`merge`].
+pub fn merge_with(git: &Git, trunk: &str, pr: &PullRequest, message: &str, ff: FastForward)
+    -> Result<String,GitError> {
+    let trunk_ref = format!("refs/heads/{}", trunk);
+    let base = git.resolve_ref(&trunk_ref)?
+        .ok_or_else(|| GitError::Refused(tr!("merge-no-trunk", trunk = trunk)))?;
+    let commit = merge_commit(git, trunk, &base, pr, message, ff)?;
+    // The merge was made on top of trunk, so trunk can only fail to fast-forward if it has moved
+    if git.resolve_ref(&trunk_ref)?.as_deref() != Some(base.as_str())
+        || !git.fast_forward(trunk, &commit)? {
+        return Err(GitError::Refused(tr!("merge-trunk-moved", trunk = trunk)));
+    }
+    Ok(commit)
+}
+
+/// Work out what trunk would point at after merging `pr` into `base`, without moving any ref.
+///
+/// This is how a merge is prepared against the remote's trunk, to be pushed before the local
+/// trunk is touched. `trunk` only names trunk in error messages.
+pub fn merge_commit(git: &Git, trunk: &str, base: &str, pr: &PullRequest, message: &str,
+                    ff: FastForward) -> Result<String,GitError> {
+    let (trunk_only, _) = git.divergence(base, &pr.tip)?;
+    match (ff, trunk_only) {
+        (FastForward::Allowed | FastForward::Only, 0) => Ok(pr.tip.clone()),
+        (FastForward::Only, _) => Err(GitError::Refused(
+            tr!("merge-not-fast-forward", branch = pr.branch, trunk = trunk)
+        )),
+        _ => {
+            let tree = git.merge_tree(base, &pr.tip)?.ok_or_else(|| GitError::Refused(
+                tr!("merge-conflict", branch = pr.branch, trunk = trunk)
+            ))?;
+            git.commit_tree(&tree, &[base, &pr.tip], message, false)
+        }
+    }
+}
+
+/// Make a single commit on top of `base` with all of `pr`'s changes, without moving any ref.
+///
+/// Refuses if the PR conflicts with trunk, as [`merge_commit`] does. `trunk` only names trunk in
+/// error messages.
+pub fn squash_commit(git: &Git, trunk: &str, base: &str, pr: &PullRequest, message: &str)
+    -> Result<String,GitError> {
+    let tree = git.merge_tree(base, &pr.tip)?.ok_or_else(|| GitError::Refused(
+        tr!("merge-conflict", branch = pr.branch, trunk = trunk)
+    ))?;
+    git.commit_tree(&tree, &[base], message, false)
+}
+
+/// Push the commit `make` builds on top of the remote's trunk to trunk, and return it.
+///
+/// `make` is given the commit trunk is at on the remote, as of the last fetch. The push only lands
+/// if the remote's trunk is still there, and records `pr` as merged (see [`crate::state`]) in the
+/// same atomic push; with `delete`, the PR's branch is deleted from the remote in it too. If trunk
+/// moves on the remote first, the remote is fetched and `make` is asked again, up to
+/// `pr.mergeRetries` times. Nothing else is checked, so callers decide what a merge must pass.
+pub fn push_onto_trunk<F>(git: &Git, config: &Config, pr: &PullRequest, delete: bool,
+                          mut make: F) -> Result<String,GitError>
+    where F: FnMut(&str) -> Result<String,GitError> {
+    let remote = &config.remote.value;
+    let trunk = &config.trunk.value;
+    let retries = config.merge_retries.value;
+    let remote_trunk = format!("refs/remotes/{}/{}", remote, trunk);
+    let trunk_ref = format!("refs/heads/{}", trunk);
+    let mut attempt = 0;
+    loop {
+        let base = git.resolve_ref(&remote_trunk)?.ok_or_else(|| GitError::Refused(
+            tr!("merge-no-trunk", trunk = format!("{}/{}", remote, trunk))
+        ))?;
+        let commit = make(&base)?;
+
+        let mut refspecs = vec![format!("{}:{}", commit, trunk_ref)];
+        if delete {
+            refspecs.push(format!(":refs/heads/{}", pr.branch));
+        }
+        refspecs.extend(state::transition(git, remote, &pr.branch, &pr.tip, State::Merged)?);
+        let rejections = git.try_push_atomic(remote, &refspecs, &[(&trunk_ref, &base)])?;
+        if rejections.is_empty() {
+            return Ok(commit);
+        }
+
+        // Only a trunk which has moved is worth another try; anything else would fail again
+        git.fetch_prune()?;
+        let moved = git.resolve_ref(&remote_trunk)?.as_deref() != Some(base.as_str());
+        if !moved || attempt == retries {
+            return Err(GitError::push_rejected(remote, &rejections));
+        }
+        attempt += 1;
+        eprintln!("{}", tr!("merge-retrying", trunk = trunk, remote = remote,
+                            attempt = attempt, retries = retries));
+    }
+}
+
+/// Fast-forward the local trunk to `commit`, which has just been pushed to `remote`.
+///
+/// The push is what matters, so a local trunk which can't follow is only mentioned.
+pub fn catch_up_trunk(git: &Git, trunk: &str, commit: &str, remote: &str)
+    -> Result<(),GitError> {
+    match git.fast_forward(trunk, commit) {
+        Ok(true) => {},
+        Ok(false) => {
+            eprintln!("{}", tr!("merge-local-trunk-diverged", trunk = trunk, remote = remote))
+        },
+        Err(GitError::Refused(reason)) => eprintln!("{}", reason),
+        Err(e) => return Err(e),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_keep_the_merge_subject() {
+        let pr = PullRequest{
+            name: "hotfix".to_string(), branch: "hotfix/1234567".to_string(),
+            tip: "1234567".to_string()
+        };
+        assert_eq!(merge_message(&pr, &[]), "Merge branch 'hotfix/1234567'\n");
+        assert_eq!(
+            merge_message(&pr, &[("Emergency-Merge", "prod\nis down".to_string())]),
+            "Merge branch 'hotfix/1234567'\n\nEmergency-Merge: prod is down\n"
+        );
+    }
+
+    #[test]
+    fn squash_messages_list_the_commits() {
+        let pr = PullRequest{
+            name: "hotfix".to_string(), branch: "hotfix/1234567".to_string(),
+            tip: "89abcdef".to_string()
+        };
+        let commits = [("1234567890".to_string(), "Fix it".to_string(), String::new()),
+                       ("89abcdef".to_string(), "Test it".to_string(), String::new())];
+        assert_eq!(squash_message(&pr, &commits),
+                   "hotfix (hotfix/1234567)\n\nSquashed commit of the following:\n\n\
+                    * 1234567 Fix it\n* 89abcde Test it\n\nSquashed-From: 89abcdef\n");
+    }
+
+    #[test]
+    fn squash_messages_gather_descriptions_and_trailers() {
+        let pr = PullRequest{
+            name: "hotfix".to_string(), branch: "hotfix/1234567".to_string(),
+            tip: "89abcdef".to_string()
+        };
+        let commits = [
+            ("1234567890".to_string(), "Fix it".to_string(),
+             "The cache was stale.\n\nSigned-off-by: A <a@example.com>".to_string()),
+            ("89abcdef".to_string(), "Test it".to_string(),
+             "Signed-off-by: A <a@example.com>\nReviewed-by: B <b@example.com>".to_string()),
+        ];
+        assert_eq!(squash_message(&pr, &commits),
+                   "hotfix (hotfix/1234567)\n\nThe cache was stale.\n\n\
+                    Squashed commit of the following:\n\n* 1234567 Fix it\n* 89abcde Test it\n\n\
+                    Signed-off-by: A <a@example.com>\nReviewed-by: B <b@example.com>\n\
+                    Squashed-From: 89abcdef\n");
+    }
+
+    #[test]
+    fn trailers_are_the_last_paragraph() {
+        assert_eq!(split_trailers(""), ("", vec![]));
+        assert_eq!(split_trailers("Just text."), ("Just text.", vec![]));
+        assert_eq!(split_trailers("Text.\n\nFixes: #12\nAcked-by: C"),
+                   ("Text.", vec!["Fixes: #12", "Acked-by: C"]));
+        assert_eq!(split_trailers("Text.\n\nNot: a trailer\nbecause of this line"),
+                   ("Text.\n\nNot: a trailer\nbecause of this line", vec![]));
+        assert_eq!(split_trailers("Note the time: noon"), ("Note the time: noon", vec![]));
+        assert_eq!(split_trailers("Fixes: #12"), ("", vec!["Fixes: #12"]));
+    }
+}
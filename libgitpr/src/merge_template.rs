@@ -0,0 +1,133 @@
+//! Team formats for merge and squash commit messages
+//!
+//! Out of the box, merging a PR writes the message `git merge` would, and squashing it writes the
+//! one [`crate::merge::squash_message`] composes. A team that wants trunk's history in its own
+//! format commits a template to trunk at [`PATH`], and every merge and squash is written with it
+//! instead. It is an ordinary [`crate::template::Template`], with the placeholders in [`FIELDS`]:
+//!
+//! ```text
+//! {subject}
+//!
+//! {ticket}: {description}
+//!
+//! Approved-by: {approvers}
+//! ```
+//!
+//! The template is read from the commit the PR is being merged into, so changing it is a PR like
+//! any other, and everybody merges with the same one. `git pr stats` recognizes merges by the
+//! subject `git merge` writes, so templates should start with `{subject}` to keep them counted.
+use crate::approval;
+use crate::merge;
+use crate::metadata;
+use crate::pull_request::PullRequest;
+use crate::template::Template;
+use crate::{tr, Git, GitError};
+use regex::Regex;
+
+
+/// Where templates live, relative to the top of trunk's tree.
+pub const PATH: &str = ".git-pr/merge-message.tmpl";
+
+/// The placeholders a template may use.
+pub const FIELDS: &[(&str, &str)] = &[
+    ("subject", "the subject git pr would otherwise have written"),
+    ("name", "the PR's name"),
+    ("branch", "the PR's branch"),
+    ("tip", "the commit the PR's branch points at"),
+    ("description", "the first line of the PR's description"),
+    ("approvers", "everyone who approved the PR, separated by commas"),
+    ("ticket", "the first ticket ID (like ABC-123 or #123) in the PR's name or commits"),
+    ("commits", "a line for each of the PR's commits, with its abbreviated hash and subject"),
+];
+
+
+/// Whether a commit joins a PR to trunk, or replaces it with a single commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Merge,
+    Squash,
+}
+
+
+/// The team's template, as of commit `base`, or `None` if it doesn't have one.
+pub fn load(git: &Git, base: &str) -> Result<Option<Template>,GitError> {
+    match git.file_at(base, PATH)? {
+        Some(text) => {
+            let text = String::from_utf8_lossy(&text);
+            Template::parse(&text, FIELDS).map(Some).map_err(|e| GitError::Refused(
+                tr!("merge-template-invalid", path = PATH, error = e)
+            ))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Compose the message for merging or squashing `pr` into `base`: with the template `base` has, if
+/// it has one, or as git-pr does by default.
+pub fn compose(git: &Git, pr: &PullRequest, base: &str, kind: Kind) -> Result<String,GitError> {
+    let commits = git.commit_messages(&format!("{}..{}", base, pr.tip))?;
+    let default = match kind {
+        Kind::Merge => merge::merge_message(pr, &[]),
+        Kind::Squash => merge::squash_message(pr, &commits),
+    };
+    let template = match load(git, base)? {
+        Some(template) => template,
+        None => return Ok(default),
+    };
+
+    let approvers = approval::approvers(&metadata::lines(git, &pr.tip)?);
+    let message = template.render(|field| match field {
+        "subject" => default.lines().next().unwrap_or_default().to_string(),
+        "name" => pr.name.clone(),
+        "branch" => pr.branch.clone(),
+        "tip" => pr.tip.clone(),
+        "description" => {
+            merge::description(&commits).lines().next().unwrap_or_default().to_string()
+        }
+        "approvers" => approvers.join(", "),
+        "ticket" => ticket(pr, &commits).unwrap_or_default(),
+        "commits" => commits.iter()
+            .map(|(hash, subject, _)| {
+                format!("* {} {}", hash.chars().take(7).collect::<String>(), subject)
+            })
+            .collect::<Vec<_>>().join("\n"),
+        _ => String::new(),
+    });
+    Ok(format!("{}\n", message.trim_end()))
+}
+
+// The first ticket ID mentioned by the PR's name, or failing that by its commits' messages.
+fn ticket(pr: &PullRequest, commits: &[(String, String, String)]) -> Option<String> {
+    let pattern = Regex::new(r"\b[A-Z][A-Z0-9]+-[0-9]+\b|#[0-9]+\b").ok()?;
+    std::iter::once(pr.name.as_str())
+        .chain(commits.iter().map(|(_, subject, _)| subject.as_str()))
+        .chain(commits.iter().map(|(_, _, body)| body.as_str()))
+        .find_map(|text| pattern.find(text))
+        .map(|found| found.as_str().to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(subject: &str, body: &str) -> (String, String, String) {
+        ("1234567890".to_string(), subject.to_string(), body.to_string())
+    }
+
+    #[test]
+    fn tickets_come_from_the_name_first() {
+        let pr = PullRequest{
+            name: "PAY-42-refunds".to_string(), branch: "PAY-42-refunds/1234567".to_string(),
+            tip: "1234567".to_string()
+        };
+        assert_eq!(ticket(&pr, &[commit("Fix #7", "")]).as_deref(), Some("PAY-42"));
+
+        let pr = PullRequest{
+            name: "refunds".to_string(), ..pr
+        };
+        assert_eq!(ticket(&pr, &[commit("Tidy", "See OPS-9."), commit("Fix #7", "")]).as_deref(),
+                   Some("#7"));
+        assert_eq!(ticket(&pr, &[commit("Tidy", "")]), None);
+    }
+}
@@ -0,0 +1,247 @@
+//! Metadata shared between collaborators
+//!
+//! Comments, approvals and the like are stored as git notes under [`NOTES_REF`], attached to the
+//! commits they are about, and published to the shared remote alongside PR branches. Each note is
+//! a list of lines, and every change appends a line rather than editing an existing one.
+//!
+//! Two people recording metadata at the same moment would race to push the notes ref, and a plain
+//! force-push would silently throw away whichever update landed first. Instead, every update is a
+//! compare-and-swap: we fetch the remote's notes, fold them into ours, apply our change, and push
+//! only if the remote hasn't moved in the meantime. If it has, we go round again. Because notes are
+//! merged with git's `cat_sort_uniq` strategy, concurrent lines on the same commit are all kept.
+//!
+//! # Schema versions
+//!
+//! Every line starts with the version of the format it was written in, like `v1 approved-by
+//! alice`. (Merging sorts the lines of a note, so a version can't be recorded once per note.) When
+//! the format changes, [`CURRENT_VERSION`] goes up and a migration is added to bring older
+//! lines forward; `git pr-migrate-metadata` applies them in place. A client that finds a line
+//! newer than it understands refuses to go any further, rather than misreading or clobbering it.
+use crate::{tr, Git, GitError};
+
+
+/// Where PR metadata is attached to commits.
+pub const NOTES_REF: &str = "refs/notes/pr";
+
+/// How many times to retry an update that keeps losing races before giving up.
+pub const MAX_ATTEMPTS: usize = 5;
+
+/// The schema version this client writes.
+///
+/// Lines without a version prefix predate versioning, and count as version 0.
+pub const CURRENT_VERSION: u32 = 1;
+
+
+/// An upgrade from one schema version to the next.
+pub(crate) struct Migration {
+    /// The version this migration upgrades from; it produces `from + 1`.
+    pub from: u32,
+
+    /// Rewrite one line's payload, or drop it by returning `None`.
+    pub apply: fn(&str) -> Option<String>,
+}
+
+/// Every migration, in order. Version 0 lines only needed their version prefix adding.
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration{ from: 0, apply: |payload| Some(payload.to_string()) },
+];
+
+
+/// Split a stored line into its schema version and payload.
+pub(crate) fn parse_line(line: &str) -> (u32, &str) {
+    let versioned = line.strip_prefix('v')
+        .and_then(|rest| rest.split_once(' '))
+        .and_then(|(version, payload)| Some((version.parse().ok()?, payload)));
+    versioned.unwrap_or((0, line))
+}
+
+/// Prefix a payload with the current schema version, ready to be stored.
+pub fn format_line(payload: &str) -> String {
+    format!("v{} {}", CURRENT_VERSION, payload)
+}
+
+fn too_new(version: u32) -> GitError {
+    GitError::Refused(tr!("metadata-too-new", version = version, supported = CURRENT_VERSION))
+}
+
+/// Bring a single stored line up to the current schema version.
+///
+/// Returns `Ok(None)` if a migration dropped the line, and an error if the line was written by a
+/// newer client.
+pub(crate) fn migrate_line(line: &str) -> Result<Option<String>,GitError> {
+    let (written, payload) = parse_line(line);
+    if written > CURRENT_VERSION {
+        return Err(too_new(written));
+    }
+
+    let mut version = written;
+    let mut payload = payload.to_string();
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= written) {
+        payload = match (migration.apply)(&payload) {
+            Some(payload) => payload,
+            None => return Ok(None)
+        };
+        version = migration.from + 1;
+    }
+    Ok(Some(format!("v{} {}", version, payload)))
+}
+
+
+/// The remote-tracking copy of [`NOTES_REF`] for `remote`.
+pub fn tracking_ref(remote: &str) -> String {
+    tracking_ref_of(remote, NOTES_REF)
+}
+
+fn tracking_ref_of(remote: &str, notes_ref: &str) -> String {
+    format!("refs/notes/remotes/{}/{}", remote, notes_ref.trim_start_matches("refs/notes/"))
+}
+
+/// Bring our notes up to date with `remote`'s, returning the remote's current notes commit.
+pub fn sync(git: &Git, remote: &str) -> Result<Option<String>,GitError> {
+    sync_ref(git, remote, NOTES_REF)
+}
+
+/// Like [`sync`], for notes kept under some other ref, like [`crate::attachment::NOTES_REF`].
+pub fn sync_ref(git: &Git, remote: &str, notes_ref: &str) -> Result<Option<String>,GitError> {
+    sync_with(git, remote, notes_ref, "cat_sort_uniq")
+}
+
+/// Like [`sync_ref`], for notes which are replaced whole rather than added to, like
+/// [`crate::description::NOTES_REF`]: where a note was changed on both sides, the remote's wins.
+pub fn sync_replaced(git: &Git, remote: &str, notes_ref: &str)
+    -> Result<Option<String>,GitError> {
+    sync_with(git, remote, notes_ref, "theirs")
+}
+
+fn sync_with(git: &Git, remote: &str, notes_ref: &str, strategy: &str)
+    -> Result<Option<String>,GitError> {
+    git.fetch_notes(remote)?;
+    let theirs = git.resolve_ref(&tracking_ref_of(remote, notes_ref))?;
+    if let Some(theirs) = &theirs {
+        if git.resolve_ref(notes_ref)?.as_ref() != Some(theirs) {
+            git.merge_notes(notes_ref, theirs, strategy)?;
+        }
+    }
+    Ok(theirs)
+}
+
+/// Apply `change` to our notes and publish the result to `remote`.
+///
+/// `change` runs once per attempt, after the remote's latest notes have been merged in, so it
+/// must be safe to repeat. Attempts continue until the push goes through without overwriting
+/// anyone else's work, or until [`MAX_ATTEMPTS`] races have been lost.
+pub fn update<F>(git: &Git, remote: &str, change: F) -> Result<(),GitError>
+    where F: FnMut(&Git) -> Result<(),GitError> {
+    update_ref(git, remote, NOTES_REF, change)
+}
+
+/// Like [`update`], for notes kept under some other ref.
+pub fn update_ref<F>(git: &Git, remote: &str, notes_ref: &str, change: F)
+    -> Result<(),GitError> where F: FnMut(&Git) -> Result<(),GitError> {
+    update_with(git, remote, notes_ref, "cat_sort_uniq", change)
+}
+
+/// Like [`update_ref`], for notes which are replaced whole (see [`sync_replaced`]). `change` runs
+/// after the remote's notes have been taken, so whatever it writes wins.
+pub fn update_replaced<F>(git: &Git, remote: &str, notes_ref: &str, change: F)
+    -> Result<(),GitError> where F: FnMut(&Git) -> Result<(),GitError> {
+    update_with(git, remote, notes_ref, "theirs", change)
+}
+
+fn update_with<F>(git: &Git, remote: &str, notes_ref: &str, strategy: &str, mut change: F)
+    -> Result<(),GitError> where F: FnMut(&Git) -> Result<(),GitError> {
+    for _ in 0..MAX_ATTEMPTS {
+        let expected = sync_with(git, remote, notes_ref, strategy)?;
+        change(git)?;
+        if git.push_with_lease(remote, notes_ref, expected.as_deref())? {
+            return Ok(());
+        }
+    }
+
+    Err(GitError::Refused(tr!("metadata-gave-up", attempts = MAX_ATTEMPTS)))
+}
+
+/// Record a line of metadata about `object`, and publish it to `remote`.
+pub fn append(git: &Git, remote: &str, object: &str, payload: &str) -> Result<(),GitError> {
+    let mut applied = false;
+    update(git, remote, |git| {
+        // Refuse to add to a note that a newer client has already written to.
+        lines(git, object)?;
+        // Once our line is in the local notes, merging keeps it, so it must not be added twice.
+        if !applied {
+            git.append_note(NOTES_REF, object, &format_line(payload))?;
+            applied = true;
+        }
+        Ok(())
+    })
+}
+
+/// The payload of every line of metadata recorded about `object`.
+///
+/// Lines from older schema versions are migrated as they are read; lines from newer versions are
+/// an error.
+pub fn lines(git: &Git, object: &str) -> Result<Vec<String>,GitError> {
+    let note = git.show_note(NOTES_REF, object)?.unwrap_or_default();
+    let mut payloads = vec![];
+    for line in note.lines().filter(|l| !l.is_empty()) {
+        if let Some(migrated) = migrate_line(line)? {
+            payloads.push(parse_line(&migrated).1.to_string());
+        }
+    }
+    Ok(payloads)
+}
+
+/// Rewrite every note in our local metadata at the current schema version.
+///
+/// Returns the number of notes that changed. Nothing is modified if any line is too new.
+pub fn migrate_local(git: &Git) -> Result<usize,GitError> {
+    let mut rewrites = vec![];
+    for listing in git.list_notes(NOTES_REF)?.lines() {
+        let object = match listing.split_once(' ') {
+            Some((_, object)) => object,
+            None => continue
+        };
+        let note = git.show_note(NOTES_REF, object)?.unwrap_or_default();
+        let mut migrated = vec![];
+        for line in note.lines().filter(|l| !l.is_empty()) {
+            migrated.extend(migrate_line(line)?);
+        }
+        let migrated = migrated.join("\n");
+        if migrated != note.trim_end() {
+            rewrites.push((object.to_string(), migrated));
+        }
+    }
+
+    for (object, note) in &rewrites {
+        git.replace_note(NOTES_REF, object, note)?;
+    }
+    Ok(rewrites.len())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracking_ref_per_remote() {
+        assert_eq!(tracking_ref("origin"), "refs/notes/remotes/origin/pr");
+    }
+
+    #[test]
+    fn versioned_lines() {
+        assert_eq!(parse_line("v1 approved-by alice"), (1, "approved-by alice"));
+        assert_eq!(parse_line("approved-by alice"), (0, "approved-by alice"));
+        assert_eq!(parse_line("very old"), (0, "very old"));
+        assert_eq!(parse_line(&format_line("x")), (CURRENT_VERSION, "x"));
+    }
+
+    #[test]
+    fn migrate_old_lines_and_refuse_new_ones() {
+        assert_eq!(migrate_line("approved-by alice").unwrap(),
+                   Some("v1 approved-by alice".to_string()));
+        assert_eq!(migrate_line("v1 approved-by alice").unwrap(),
+                   Some("v1 approved-by alice".to_string()));
+        assert!(migrate_line("v99 from the future").is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! Prometheus-style metrics
+//!
+//! Platform teams want to alert on review backlog and on maintenance that has stopped making
+//! progress, without running a forge. We write metrics in the Prometheus text exposition format,
+//! which the node exporter's "textfile" collector can pick up from disk.
+use std::fs;
+use std::io;
+use std::path::Path;
+
+
+/// How a metric's value behaves over time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// Can go up or down, like the number of open PRs.
+    Gauge,
+
+    /// Only ever goes up, like the number of branches ever deleted.
+    Counter,
+}
+
+/// A single named measurement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metric {
+    pub name: String,
+    pub help: String,
+    pub kind: Kind,
+    pub value: f64,
+}
+
+impl Metric {
+    pub fn gauge(name: &str, help: &str, value: f64) -> Metric {
+        Metric{ name: name.to_string(), help: help.to_string(), kind: Kind::Gauge, value }
+    }
+
+    pub fn counter(name: &str, help: &str, value: f64) -> Metric {
+        Metric{ name: name.to_string(), help: help.to_string(), kind: Kind::Counter, value }
+    }
+}
+
+/// Render metrics in the Prometheus text exposition format.
+pub fn render(metrics: &[Metric]) -> String {
+    let mut text = String::new();
+    for metric in metrics {
+        let kind = match metric.kind {
+            Kind::Gauge => "gauge",
+            Kind::Counter => "counter",
+        };
+        text.push_str(&format!("# HELP {} {}\n", metric.name, metric.help));
+        text.push_str(&format!("# TYPE {} {}\n", metric.name, kind));
+        text.push_str(&format!("{} {}\n", metric.name, metric.value));
+    }
+    text
+}
+
+/// Replace the contents of `path` with the rendered metrics.
+///
+/// The textfile collector may read the file at any moment, so we write to a temporary file and
+/// rename it into place rather than letting a scrape see half-written output.
+pub fn write_file(path: &Path, metrics: &[Metric]) -> io::Result<()> {
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, render(metrics))?;
+    fs::rename(&temp, path)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_exposition_format() {
+        let text = render(&[
+            Metric::gauge("gitpr_open_pull_requests", "Open PRs on the remote", 3.0),
+            Metric::counter("gitpr_clean_deleted_branches", "Branches deleted", 2.0),
+        ]);
+        assert_eq!(text, "\
+            # HELP gitpr_open_pull_requests Open PRs on the remote\n\
+            # TYPE gitpr_open_pull_requests gauge\n\
+            gitpr_open_pull_requests 3\n\
+            # HELP gitpr_clean_deleted_branches Branches deleted\n\
+            # TYPE gitpr_clean_deleted_branches counter\n\
+            gitpr_clean_deleted_branches 2\n");
+    }
+}
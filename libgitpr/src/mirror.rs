@@ -0,0 +1,72 @@
+//! Copying review state from one remote to another
+//!
+//! The shared remote holds everything git-pr knows: PR branches, plus metadata under
+//! [`crate::metadata::NOTES_REF`]. Mirroring pushes all of that to a second remote, such as an
+//! offsite backup, so that the review state survives the loss of the primary. The mirror is a
+//! copy, not a collaborator: its PR branches are overwritten with the source's, and (with
+//! `--prune`) PR branches the source no longer has are deleted.
+use crate::interop::Guard;
+use crate::metadata::NOTES_REF;
+use crate::pull_request::PrIndex;
+
+
+/// Work out the refspecs to push to make `destination` match `source`.
+///
+/// Both indexes describe PR branches; `destination` is the remote's current state, as read by
+/// [`destination_index`]. Branches the destination already has at the right commit are skipped.
+/// `notes` is our copy of the source's metadata, if it has any; it is always pushed, since that is
+/// cheap when nothing has changed.
+pub fn refspecs(source: &PrIndex, destination: &PrIndex, notes: Option<&str>, prune: bool)
+    -> Vec<String> {
+    let mut refspecs: Vec<String> = source.iter()
+        .filter(|pr| !destination.iter().any(|d| d.branch == pr.branch && d.tip == pr.tip))
+        .map(|pr| format!("+{}:refs/heads/{}", pr.tip, pr.branch))
+        .collect();
+    if prune {
+        refspecs.extend(destination.iter()
+            .filter(|d| !source.iter().any(|pr| pr.branch == d.branch))
+            .map(|d| format!(":refs/heads/{}", d.branch)));
+    }
+    refspecs.extend(notes.map(|notes| format!("+{}:{}", notes, NOTES_REF)));
+    refspecs
+}
+
+/// Build an index of the PR branches another remote has, from [`crate::Git::ls_remote`] output
+/// for `refs/heads/`.
+pub fn destination_index(remote: &str, heads: &str, guard: Guard) -> PrIndex {
+    let mut index = PrIndex::with_guard(remote, guard);
+    for line in heads.lines() {
+        if let Some((tip, branch)) = line.trim().split_once(' ') {
+            if let Some(branch) = branch.strip_prefix("refs/heads/") {
+                index.update(&format!("refs/remotes/{}/{}", remote, branch), Some(tip));
+            }
+        }
+    }
+    index
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_changes_and_prune_leftovers() {
+        let source = PrIndex::from_refs("origin", "aaaa refs/remotes/origin/same/1\n\
+                                                   bbbb refs/remotes/origin/moved/2\n\
+                                                   cccc refs/remotes/origin/new/3\n");
+        let destination = destination_index("backup", "aaaa refs/heads/same/1\n\
+                                                        dddd refs/heads/moved/2\n\
+                                                        eeee refs/heads/gone/4\n\
+                                                        ffff refs/heads/trunk\n", Guard::default());
+
+        let notes = Some("refs/notes/remotes/origin/pr");
+        assert_eq!(refspecs(&source, &destination, notes, false), vec![
+            "+bbbb:refs/heads/moved/2", "+cccc:refs/heads/new/3",
+            "+refs/notes/remotes/origin/pr:refs/notes/pr",
+        ]);
+        assert_eq!(refspecs(&source, &destination, None, true), vec![
+            "+bbbb:refs/heads/moved/2", "+cccc:refs/heads/new/3", ":refs/heads/gone/4",
+        ]);
+    }
+}
@@ -0,0 +1,90 @@
+//! Suggesting names for pull requests
+//!
+//! `git pr create` can be run without a name, for a quick fix that isn't worth thinking one up
+//! for. It then suggests one, made from the subject of the commit being published, in lowercase
+//! with dashes between its first few words, like `fix-the-login-timeout`. If every file the PR
+//! changes is in the same top-level directory, the name starts with that, like
+//! `docs-fix-a-typo`, unless the subject already does. A name some other PR on the remote has
+//! already is made unique by numbering it, like `fix-a-typo-2`.
+
+
+/// How many words of the subject a suggestion uses.
+const WORDS: usize = 5;
+
+/// The name suggested for a PR whose latest commit has the subject `subject`, and which changes
+/// the files at `paths`.
+pub fn suggest(subject: &str, paths: &[String]) -> String {
+    let words: Vec<String> = subject.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .take(WORDS)
+        .map(|word| word.to_ascii_lowercase())
+        .collect();
+    let area = area(paths).map(slug).filter(|area| !area.is_empty());
+
+    match (area, words.is_empty()) {
+        (Some(area), true) => area,
+        (Some(area), false) if words[0] != area => format!("{}-{}", area, words.join("-")),
+        (_, false) => words.join("-"),
+        (None, true) => "change".to_string(),
+    }
+}
+
+/// Number `name` so that it is different from every name `taken` says is in use, or leave it be
+/// if it is already.
+pub fn unique<F: Fn(&str) -> bool>(name: &str, taken: F) -> String {
+    if !taken(name) {
+        return name.to_string();
+    }
+    (2..).map(|n| format!("{}-{}", name, n)).find(|numbered| !taken(numbered))
+        .unwrap_or_else(|| name.to_string())
+}
+
+// The top-level directory every one of `paths` is in, if they share one.
+fn area(paths: &[String]) -> Option<&str> {
+    let mut dirs = paths.iter().map(|path| path.split_once('/').map(|(dir, _)| dir));
+    let first = dirs.next()??;
+    match dirs.all(|dir| dir == Some(first)) {
+        true => Some(first),
+        false => None,
+    }
+}
+
+// `text` in lowercase, with a dash for each run of anything but letters and digits.
+fn slug(text: &str) -> String {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[test]
+    fn suggestions() {
+        assert_eq!(suggest("Fix the login timeout on Safari, again", &[]),
+                   "fix-the-login-timeout-on");
+        assert_eq!(suggest("Fix a typo", &paths(&["docs/guide.md", "docs/faq.md"])),
+                   "docs-fix-a-typo");
+        assert_eq!(suggest("docs: fix a typo", &paths(&["docs/guide.md"])), "docs-fix-a-typo");
+        assert_eq!(suggest("Fix a typo", &paths(&["docs/guide.md", "README"])), "fix-a-typo");
+        assert_eq!(suggest("Fix a typo", &paths(&["README"])), "fix-a-typo");
+        assert_eq!(suggest("Fix a typo", &paths(&["Web_UI/x.js"])), "web-ui-fix-a-typo");
+        assert_eq!(suggest("!!!", &paths(&["src/main.rs"])), "src");
+        assert_eq!(suggest("", &[]), "change");
+    }
+
+    #[test]
+    fn numbering() {
+        let taken = |name: &str| ["fix", "fix-2"].contains(&name);
+        assert_eq!(unique("fix", taken), "fix-3");
+        assert_eq!(unique("docs", taken), "docs");
+    }
+}
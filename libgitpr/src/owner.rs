@@ -0,0 +1,42 @@
+//! Who a PR belongs to
+//!
+//! A PR belongs to whoever wrote its tip commit, until it is handed to someone else with `git pr
+//! handoff`, usually because its author has left partway through. That is recorded as a line of
+//! metadata (see [`crate::metadata`]) on the PR's tip: `owned-by <at> <Name <email>>`, with the
+//! time in seconds since the Unix epoch. The most recent such line says who owns the PR now.
+
+
+/// The metadata line recording that `who` took over a PR at time `at`.
+///
+/// Write `at` with [`crate::claim::next_time`], so that it comes after every earlier line.
+pub fn owner_line(who: &str, at: i64) -> String {
+    format!("owned-by {} {}", at, who)
+}
+
+/// Who a PR has been handed to, according to its metadata `lines`, if anyone.
+pub fn current(lines: &[String]) -> Option<String> {
+    lines.iter()
+        .filter_map(|line| {
+            let (at, who) = line.strip_prefix("owned-by ")?.split_once(' ')?;
+            Some((at.parse::<i64>().ok()?, who))
+        })
+        .max_by_key(|(at, _)| *at)
+        .map(|(_, who)| who.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_owner_wins() {
+        assert_eq!(current(&[]), None);
+        let lines = vec![
+            owner_line("Bob <b@example.com>", 200),
+            owner_line("Alice <a@example.com>", 100),
+            "owned-by soon Mallory <m@example.com>".to_string(),
+        ];
+        assert_eq!(current(&lines).as_deref(), Some("Bob <b@example.com>"));
+    }
+}
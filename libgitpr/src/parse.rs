@@ -0,0 +1,365 @@
+//! Reading git's output
+//!
+//! Everything git-pr knows about branches, it learns by reading text that git prints. The functions
+//! here turn that text into data, and nothing else: they don't run git, so they can be tested (and
+//! fuzzed, see `fuzz/`) on any input at all. Whatever they are given, they must not panic, and they
+//! must not mistake something else for a PR branch.
+//!
+//! Git won't create a branch whose name contains whitespace or control characters, but the text
+//! we read may still contain them: `git branch` describes a detached HEAD as `(HEAD detached at
+//! 1234567)`, and a symbolic ref as `remotes/origin/HEAD -> origin/trunk`. Lines like those are
+//! never branches.
+use std::collections::{BTreeMap, BTreeSet};
+
+
+/// A line of `git branch` output describing a local branch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalBranch {
+    pub name: String,
+
+    /// This is the branch checked out here (git marks it with `*`).
+    pub current: bool,
+
+    /// This branch is checked out in another worktree (git marks it with `+`).
+    pub elsewhere: bool,
+}
+
+impl LocalBranch {
+    /// Read one line of `git branch` output, or `None` if it doesn't describe a branch.
+    pub fn parse(line: &str) -> Option<LocalBranch> {
+        let line = line.trim();
+        let (current, elsewhere, name) = match (line.strip_prefix("* "), line.strip_prefix("+ ")) {
+            (Some(name), _) => (true, false, name),
+            (_, Some(name)) => (false, true, name),
+            _ => (false, false, line)
+        };
+        let unprintable = |c: char| c.is_whitespace() || c.is_control();
+        if name.is_empty() || name.contains(unprintable) {
+            return None;
+        }
+        Some(LocalBranch{ name: name.to_string(), current, elsewhere })
+    }
+}
+
+
+/// A line of `git branch -a` output describing a remote-tracking branch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteBranch {
+    pub remote: String,
+
+    /// The branch's name on the remote, like "hotfix/1234567".
+    pub name: String,
+}
+
+impl RemoteBranch {
+    /// Read one line of `git branch -a` output, or `None` if it isn't a branch on `remote`.
+    pub fn parse(line: &str, remote: &str) -> Option<RemoteBranch> {
+        let local = LocalBranch::parse(line)?;
+        let name = local.name.strip_prefix("remotes/")?.strip_prefix(remote)?.strip_prefix('/')?;
+        if name.is_empty() {
+            return None;
+        }
+        Some(RemoteBranch{ remote: remote.to_string(), name: name.to_string() })
+    }
+
+    /// The name of the PR on this branch, if it is one (see [`pr_branch`]).
+    pub fn pr_name(&self) -> Option<&str> {
+        pr_branch(&self.name).map(|(name, _)| name)
+    }
+}
+
+
+/// Split a branch name into the PR name and hash, if it is a PR branch.
+///
+/// PR branches are named `<name>/<hash>`, where the name is not empty and the hash is made of
+/// lowercase hex digits. The name may contain slashes of its own.
+pub fn pr_branch(branch: &str) -> Option<(&str, &str)> {
+    let (name, hash) = branch.rsplit_once('/')?;
+    let is_hex = |c: char| c.is_ascii_digit() || ('a'..='f').contains(&c);
+    if name.is_empty() || name.split('/').any(str::is_empty) || hash.is_empty()
+        || !hash.chars().all(is_hex) {
+        return None;
+    }
+    Some((name, hash))
+}
+
+/// Search a string for names matching our PR Pattern.
+///
+/// Given a string like the following (ostensibly the output of `git branch -a`):
+///
+/// ```console
+///   cool-branch
+/// * trunk
+///   remotes/origin/new-idea/5
+///   remotes/origin/hotfix/0
+/// ```
+///
+/// this function will return a vector of two strings: "new-idea" and "hotfix" (assuming `remote` is
+/// "origin"). That's because our criteria for pull request names is:
+///
+/// * must begin with "remotes/<remote>/"
+/// * must end with a slash and one or more hex digits, with a non-empty name before them
+pub fn extract_pr_names(branches: &str, remote: &str) -> Vec<String> {
+    branches.lines()
+        .filter_map(|line| RemoteBranch::parse(line, remote))
+        .filter_map(|branch| branch.pr_name().map(String::from))
+        .collect()
+}
+
+/// Pick out branches which `git pr clean` may delete.
+///
+/// Given the output of `git branch --merged <trunk>`, this returns everything except trunk itself
+/// and any branch that is checked out: git marks the current branch with `*`, and branches checked
+/// out in *other* worktrees with `+`. Git refuses to delete either kind.
+pub fn extract_deletable_branches(branches: &str, trunk: &str) -> Vec<String> {
+    branches.lines()
+        .filter_map(LocalBranch::parse)
+        .filter(|b| !b.current && !b.elsewhere && b.name != trunk)
+        .map(|b| b.name)
+        .collect()
+}
+
+/// Pick out branches which `git pr clean` may delete, on a repository where PRs are merged into
+/// more than one branch (trunk, and release branches, say).
+///
+/// `merged` holds the output of `git branch --merged <target>` for each target. A branch may be
+/// deleted once it is merged into every one of the `configured` targets, or, if it recorded a base
+/// of its own (in `recorded`, as from [`parse_recorded_bases`]), into that base alone. As with
+/// [`extract_deletable_branches`], the targets themselves, and branches which are checked out, are
+/// never deletable. The result is sorted.
+pub fn extract_fully_merged_branches(merged: &BTreeMap<String, String>, configured: &[String],
+                                     recorded: &[(String, String)]) -> Vec<String> {
+    let merged_into: BTreeMap<&str, BTreeSet<String>> = merged.iter()
+        .map(|(target, branches)| {
+            let branches = branches.lines().filter_map(LocalBranch::parse)
+                .filter(|b| !b.current && !b.elsewhere)
+                .map(|b| b.name)
+                .collect();
+            (target.as_str(), branches)
+        })
+        .collect();
+    let candidates: BTreeSet<&String> = merged_into.values().flatten()
+        .filter(|branch| !merged.contains_key(*branch) && !configured.contains(branch))
+        .collect();
+
+    candidates.into_iter()
+        .filter(|branch| {
+            let base = recorded.iter().find(|(name, _)| name == *branch).map(|(_, base)| base);
+            let required = base.map_or(configured, std::slice::from_ref);
+            required.iter().all(|target| {
+                merged_into.get(target.as_str()).is_some_and(|set| set.contains(*branch))
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// Read the bases PRs were created against (see `git pr create --base`), as `(branch, base)` pairs,
+/// from `git config --get-regexp` output for the `branch.<name>.prbase` keys.
+pub fn parse_recorded_bases(config: &str) -> Vec<(String, String)> {
+    config.lines()
+        .filter_map(|line| {
+            let (key, base) = line.split_once(' ')?;
+            let branch = key.strip_prefix("branch.")?.strip_suffix(".prbase")?;
+            Some((branch.to_string(), base.trim().to_string()))
+        })
+        .filter(|(branch, base)| !branch.is_empty() && !base.is_empty())
+        .collect()
+}
+
+/// Find out whether `git push --porcelain` updated `refname`.
+///
+/// Returns `Some(false)` if the push was rejected, and `None` if the ref isn't mentioned at all
+/// (which means the push failed before it got that far).
+pub fn parse_push_status(porcelain: &str, refname: &str) -> Option<bool> {
+    let destination = format!(":{}", refname);
+    porcelain.lines()
+        .map(|line| line.split('\t').collect::<Vec<&str>>())
+        .find(|fields| fields.len() >= 2 && fields[1].ends_with(&destination))
+        .map(|fields| fields[0] != "!")
+}
+
+/// List the refs `git push --porcelain` reports as rejected, each with git's reason.
+///
+/// The reason is the summary git printed, like `[rejected] (non-fast-forward)`. After a failed
+/// `--atomic` push, refs which were fine in themselves are reported as `[rejected] (atomic push
+/// failed)`.
+pub fn parse_push_rejections(porcelain: &str) -> Vec<(String, String)> {
+    porcelain.lines()
+        .map(|line| line.split('\t').collect::<Vec<&str>>())
+        .filter(|fields| fields.len() >= 3 && fields[0] == "!")
+        .filter_map(|fields| {
+            let (_, destination) = fields[1].rsplit_once(':')?;
+            Some((destination.to_string(), fields[2].to_string()))
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Show that we can extract a list of pr names from the output of `git branch -a`.
+    #[test]
+    fn parse_branches_into_pr_list() {
+        let branches: &'static str = "
+          local-junk
+        * stuff/I/wrote
+          trunk
+          remotes/origin/first-pr/000000
+          remotes/origin/second/f3f3f3
+          remotes/origin/not-being-tracked
+          remotes/origin/has-a-directory-but/still-not-being-tracked
+        ";
+
+        let pr_names = extract_pr_names(branches, "origin");
+        assert_eq!(pr_names.len(), 2);
+        assert_eq!(pr_names[0], "first-pr");
+        assert_eq!(pr_names[1], "second");
+    }
+
+    // Lines which merely look like PR branches at either end aren't PR branches.
+    #[test]
+    fn reject_impostors() {
+        let branches = "
+          remotes/origin/HEAD -> origin/feature/abc1234
+          remotes/origin/cafe
+          remotes/origin//1234567
+          remotes/origin-fork/feature/1234567
+          remotes/origin/feature/ABC1234
+        * (HEAD detached at 1234567)
+          feature/1234567
+        ";
+        assert!(extract_pr_names(branches, "origin").is_empty());
+    }
+
+    #[test]
+    fn identify_branches_for_deletion() {
+        let merged_branches = [
+            "  one",
+            "* two",
+            "  trunk",
+            "  three",
+            "+ four",
+            "* (HEAD detached at 1234567)",
+            "  +five",
+            ""
+        ].join("\n");
+
+        let pr_names = extract_deletable_branches(&merged_branches, "trunk");
+        assert_eq!(pr_names, vec!["one", "three", "+five"]);
+    }
+
+    // With release branches, a branch has to be merged into all of them, or into its own base.
+    #[test]
+    fn require_every_base() {
+        let merged: BTreeMap<String, String> = [
+            ("trunk", "  trunk\n  done\n  trunk-only\n  fix/abc\n* here\n"),
+            ("release/1.x", "  release/1.x\n  done\n  fix/abc\n  release-only\n* here\n"),
+            ("release/2.x", "  fix/abc\n  backport\n"),
+        ].iter().map(|(target, branches)| (target.to_string(), branches.to_string())).collect();
+        let configured = ["trunk".to_string(), "release/1.x".to_string()];
+        let recorded = parse_recorded_bases("branch.backport.prbase release/2.x\n\
+                                             branch.release-only.prbase release/1.x\n\
+                                             branch.odd.prbase\n");
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(extract_fully_merged_branches(&merged, &configured, &recorded),
+                   vec!["backport", "done", "fix/abc", "release-only"]);
+        assert_eq!(extract_fully_merged_branches(&merged, &configured[..1], &[]),
+                   vec!["done", "fix/abc", "trunk-only"]);
+    }
+
+    // Show that we can tell an accepted push from one that lost a race.
+    #[test]
+    fn parse_push_porcelain() {
+        let accepted = "To /tmp/o\n*\trefs/notes/pr:refs/notes/pr\t[new reference]\nDone\n";
+        let rejected = "To /tmp/o\n!\trefs/notes/pr:refs/notes/pr\t[rejected] (stale info)\nDone\n";
+        assert_eq!(parse_push_status(accepted, "refs/notes/pr"), Some(true));
+        assert_eq!(parse_push_status(rejected, "refs/notes/pr"), Some(false));
+        assert_eq!(parse_push_status("", "refs/notes/pr"), None);
+    }
+
+    // Show that every rejected ref is found, along with why.
+    #[test]
+    fn parse_atomic_push_rejections() {
+        let report = "To /tmp/o\n\
+                      !\t(delete):refs/heads/fix/abc\t[rejected] (atomic push failed)\n\
+                      !\tHEAD:refs/heads/trunk\t[rejected] (non-fast-forward)\n\
+                      *\trefs/notes/pr:refs/notes/pr\t[new reference]\n\
+                      Done\n";
+        assert_eq!(parse_push_rejections(report), vec![
+            ("refs/heads/fix/abc".to_string(), "[rejected] (atomic push failed)".to_string()),
+            ("refs/heads/trunk".to_string(), "[rejected] (non-fast-forward)".to_string()),
+        ]);
+        assert!(parse_push_rejections("To /tmp/o\nDone\n").is_empty());
+    }
+
+    // A path component git would accept in a branch name, drawn from all of Unicode.
+    fn component() -> impl Strategy<Value = String> {
+        "[^/\\s\\p{Cc}*+~^:?\\[\\\\]{1,12}"
+    }
+
+    // A PR name, possibly nested in several directories.
+    fn pr_name() -> impl Strategy<Value = String> {
+        prop::collection::vec(component(), 1..8).prop_map(|parts| parts.join("/"))
+    }
+
+    proptest! {
+        // Nothing we might be given makes a parser panic.
+        #[test]
+        fn never_panic(text in "\\PC*|(?s:.*)", remote in "\\PC*") {
+            extract_pr_names(&text, &remote);
+            extract_deletable_branches(&text, &remote);
+            parse_push_status(&text, &remote);
+            parse_push_rejections(&text);
+            parse_recorded_bases(&text);
+            for line in text.lines() {
+                LocalBranch::parse(line);
+                RemoteBranch::parse(line, &remote);
+                pr_branch(line);
+            }
+        }
+
+        // Every PR branch on the remote is found, whatever its name, under whatever marker.
+        #[test]
+        fn find_every_pr(name in pr_name(), hash in "[0-9a-f]{1,40}", marker in "[ *+] ") {
+            let line = format!("{}remotes/origin/{}/{}", marker, name, hash);
+            prop_assert_eq!(extract_pr_names(&line, "origin"), vec![name]);
+        }
+
+        // Branches which don't end in a lowercase hex hash are never PRs.
+        #[test]
+        fn hashes_must_be_hex(name in pr_name(), tail in component()) {
+            prop_assume!(!tail.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)));
+            let line = format!("  remotes/origin/{}/{}", name, tail);
+            prop_assert!(extract_pr_names(&line, "origin").is_empty());
+        }
+
+        // Branches on another remote are never PRs, even if that remote's name starts the same.
+        #[test]
+        fn only_the_named_remote(remote in component(), suffix in component(),
+                                 name in pr_name(), hash in "[0-9a-f]{7}") {
+            let line = format!("  remotes/{}{}/{}/{}", remote, suffix, name, hash);
+            prop_assert!(extract_pr_names(&line, &remote).is_empty());
+        }
+
+        // Symbolic refs and other annotated lines are never branches.
+        #[test]
+        fn lines_with_spaces_are_not_branches(name in pr_name(), target in pr_name()) {
+            let line = format!("  remotes/origin/{} -> origin/{}/1234567", name, target);
+            prop_assert_eq!(LocalBranch::parse(&line), None);
+            prop_assert_eq!(RemoteBranch::parse(&line, "origin"), None);
+        }
+
+        // Local branches survive the round trip through `git branch` output.
+        #[test]
+        fn local_branches_round_trip(name in pr_name(), current: bool) {
+            let line = format!("{} {}", if current { '*' } else { ' ' }, name);
+            let branch = LocalBranch::parse(&line).unwrap();
+            prop_assert_eq!(branch.name, name);
+            prop_assert_eq!(branch.current, current);
+        }
+    }
+}
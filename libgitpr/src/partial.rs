@@ -0,0 +1,68 @@
+//! Support for partial clones
+//!
+//! Repositories cloned with `--filter=blob:none` download file contents only when something asks
+//! for them, and git does so one object at a time. Showing a PR's diff in such a clone can stall
+//! for a long while as each blob is requested separately. Instead, we find everything a PR range
+//! needs up front and fetch it in one go. If that fails (the server is unreachable, say), callers
+//! should fall back to output that needs no file contents, such as `--stat`.
+use crate::{tr, Git, GitError};
+
+
+/// Pick the names of promisor remotes out of `git config --get-regexp` output.
+///
+/// Each line looks like `remote.origin.promisor true`.
+pub(crate) fn parse_promisor_remotes(config: &str) -> Vec<String> {
+    config.lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(' ')?;
+            let name = key.strip_prefix("remote.")?.strip_suffix(".promisor")?;
+            match value.trim() {
+                "true" => Some(name.to_string()),
+                _ => None
+            }
+        })
+        .collect()
+}
+
+/// Names of the remotes from which missing objects can be fetched later.
+///
+/// An empty list means that this is not a partial clone.
+pub fn promisor_remotes(git: &Git) -> Result<Vec<String>,GitError> {
+    let config = git.config_get_regexp(r"^remote\..*\.promisor$")?;
+    Ok(parse_promisor_remotes(&config))
+}
+
+/// Make sure every object reachable from `range` is available locally.
+///
+/// Reports what it is doing on stderr, since a large PR can take a moment. Returns the number of
+/// objects that had to be fetched, which is always zero outside of partial clones.
+pub fn prefetch(git: &Git, range: &str) -> Result<usize,GitError> {
+    let remotes = promisor_remotes(git)?;
+    let remote = match remotes.first() {
+        None => return Ok(0),
+        Some(remote) => remote
+    };
+
+    let missing = git.missing_objects(range)?;
+    if !missing.is_empty() {
+        eprintln!("{}", tr!("fetching-missing",
+                            count = missing.len(), range = range, remote = remote));
+        git.fetch_objects(remote, &missing)?;
+    }
+
+    Ok(missing.len())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_promisor_remotes() {
+        let config = "remote.origin.promisor true\n\
+                      remote.backup.promisor false\n\
+                      remote.with.dots.promisor true\n";
+        assert_eq!(parse_promisor_remotes(config), vec!["origin", "with.dots"]);
+    }
+}
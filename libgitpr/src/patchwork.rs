@@ -0,0 +1,117 @@
+//! Exporting PRs in Patchwork's format
+//!
+//! Projects that track patches with [Patchwork](https://patchwork.readthedocs.io) normally feed it
+//! by mailing patches to a list. Instead, a PR can be exported as JSON shaped like the series
+//! objects in Patchwork's REST API (`/api/series/`), with each commit as one of the series'
+//! patches, so that it can be loaded without a round trip through email.
+//!
+//! Patchwork identifies patches by Message-ID. Since nothing is mailed, each patch is given a
+//! stable pseudo Message-ID derived from its commit hash, so exporting the same PR twice yields the
+//! same identifiers.
+use crate::{Git, GitError};
+use serde_json::{json, Value};
+
+
+/// One commit of a PR, as a patch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Patch {
+    pub commit: String,
+
+    /// `Name <email>`
+    pub author: String,
+    pub subject: String,
+    pub diff: String,
+}
+
+impl Patch {
+    /// A Message-ID for this patch, stable across exports.
+    pub fn msgid(&self) -> String {
+        format!("<{}@git-pr>", self.commit)
+    }
+}
+
+
+/// The subject line `git format-patch` would give a patch, like "[PATCH v2 1/3] Fix it".
+pub(crate) fn patch_name(subject: &str, version: u32, index: usize, total: usize) -> String {
+    let mut tag = "PATCH".to_string();
+    if version > 1 {
+        tag.push_str(&format!(" v{}", version));
+    }
+    if total > 1 {
+        tag.push_str(&format!(" {}/{}", index, total));
+    }
+    format!("[{}] {}", tag, subject)
+}
+
+/// Split `Name <email>` into its parts for Patchwork's person objects.
+fn person(identity: &str) -> Value {
+    match identity.rsplit_once(" <") {
+        Some((name, email)) => json!({ "name": name, "email": email.trim_end_matches('>') }),
+        None => json!({ "name": identity, "email": Value::Null })
+    }
+}
+
+/// Describe a PR as a Patchwork series.
+pub fn series(name: &str, version: u32, patches: &[Patch]) -> Value {
+    let submitter = patches.first().map_or(Value::Null, |p| person(&p.author));
+    json!({
+        "name": name,
+        "version": version,
+        "total": patches.len(),
+        "received_total": patches.len(),
+        "received_all": true,
+        "submitter": submitter,
+        "cover_letter": Value::Null,
+        "patches": patches.iter().enumerate().map(|(i, patch)| json!({
+            "name": patch_name(&patch.subject, version, i + 1, patches.len()),
+            "msgid": patch.msgid(),
+            "commit_ref": patch.commit,
+            "submitter": person(&patch.author),
+            "diff": patch.diff,
+        })).collect::<Vec<Value>>(),
+    })
+}
+
+/// Gather the patches that make up `tip` on top of `trunk`.
+pub fn patches(git: &Git, trunk: &str, tip: &str) -> Result<Vec<Patch>,GitError> {
+    let mut patches = vec![];
+    for line in git.commit_summaries(&format!("{}..{}", trunk, tip))?.lines() {
+        let mut fields = line.splitn(3, '\t');
+        if let (Some(commit), Some(author), Some(subject)) =
+            (fields.next(), fields.next(), fields.next()) {
+            patches.push(Patch{
+                commit: commit.to_string(),
+                author: author.to_string(),
+                subject: subject.to_string(),
+                diff: git.commit_diff(commit)?,
+            });
+        }
+    }
+    Ok(patches)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_patches_like_format_patch() {
+        assert_eq!(patch_name("Fix it", 1, 1, 1), "[PATCH] Fix it");
+        assert_eq!(patch_name("Fix it", 1, 2, 3), "[PATCH 2/3] Fix it");
+        assert_eq!(patch_name("Fix it", 2, 1, 1), "[PATCH v2] Fix it");
+    }
+
+    #[test]
+    fn series_shape() {
+        let patch = Patch{
+            commit: "abc".to_string(), author: "Your Name <you@example.com>".to_string(),
+            subject: "Fix it".to_string(), diff: "diff --git ...".to_string()
+        };
+        let series = series("hotfix", 1, &[patch]);
+        assert_eq!(series["total"], 1);
+        assert_eq!(series["submitter"]["email"], "you@example.com");
+        assert_eq!(series["patches"][0]["msgid"], "<abc@git-pr>");
+        assert_eq!(series["patches"][0]["name"], "[PATCH] Fix it");
+    }
+}
@@ -0,0 +1,175 @@
+//! Choosing a PR interactively
+//!
+//! Commands that act on one PR can be run without naming it. When a person is at the terminal,
+//! they are shown a list of open PRs which narrows as they type, in the style of fzf and skim:
+//! the letters typed must appear in a PR's branch name in order, though not necessarily next to
+//! each other. The arrow keys (or Ctrl-P and Ctrl-N) move the selection, Enter picks it, and
+//! Escape or Ctrl-C gives up.
+//!
+//! The picker is drawn on stderr, so it doesn't end up in output that has been redirected. When
+//! stdin or stderr isn't a terminal, there is nobody to ask, and commands should fail with their
+//! usual usage message instead.
+use crate::pull_request::{PrIndex, PullRequest};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, queue, style::Print, terminal};
+use std::io::{self, IsTerminal, Write};
+
+
+/// How many matches are shown at once.
+const ROWS: usize = 10;
+
+/// Can we ask the user to choose?
+pub fn interactive() -> bool {
+    io::stdin().is_terminal() && io::stderr().is_terminal()
+}
+
+/// How well `query` matches `candidate`, or `None` if it doesn't match at all.
+///
+/// Each character of the query must appear in the candidate, in order, ignoring case. Higher
+/// scores are better: runs of consecutive characters, and matches near the start, count for more.
+pub(crate) fn score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.to_lowercase().chars() {
+        let found = next + candidate[next..].iter().position(|&c| c == wanted)?;
+        score += match previous {
+            Some(p) if p + 1 == found => 3,
+            _ => 1
+        };
+        if previous.is_none() {
+            score -= found as i64;
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+/// The positions of the `items` matching `query`, best match first.
+///
+/// Items that match equally well keep their original order. An empty query matches everything.
+pub fn filter(query: &str, items: &[String]) -> Vec<usize> {
+    let mut matches: Vec<(i64, usize)> = items.iter().enumerate()
+        .filter_map(|(i, item)| score(query, item).map(|score| (score, i)))
+        .collect();
+    matches.sort_by_key(|&(score, i)| (std::cmp::Reverse(score), i));
+    matches.into_iter().map(|(_, i)| i).collect()
+}
+
+
+// Leaves raw mode however the picker exits.
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> io::Result<RawMode> {
+        terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// Draw the prompt and the visible matches below it, leaving the cursor after the query.
+fn draw(out: &mut impl Write, prompt: &str, query: &str, items: &[String], matches: &[usize],
+        selected: usize) -> io::Result<()> {
+    // Some terminals don't report their size; assume the traditional width for those
+    let width = match terminal::size() {
+        Ok((columns, _)) if columns > 0 => (columns as usize).max(4),
+        _ => 80
+    };
+    queue!(out, cursor::MoveToColumn(0), terminal::Clear(terminal::ClearType::FromCursorDown))?;
+    queue!(out, Print(format!("{} {}  ({}/{})", prompt, query, matches.len(), items.len())))?;
+
+    let shown = matches.len().min(ROWS);
+    for (row, &i) in matches.iter().take(shown).enumerate() {
+        let marker = if row == selected { '>' } else { ' ' };
+        // Long lines would wrap, and throw off our idea of where the prompt is
+        let line: String = format!("{} {}", marker, items[i]).chars().take(width - 1).collect();
+        queue!(out, Print("\r\n"), Print(line))?;
+    }
+    if shown > 0 {
+        queue!(out, cursor::MoveUp(shown as u16))?;
+    }
+    let column = prompt.chars().count() + 1 + query.chars().count();
+    queue!(out, cursor::MoveToColumn(column.min(width - 1) as u16))?;
+    out.flush()
+}
+
+/// Ask the user to choose one of `items`, returning its position, or `None` if they gave up.
+pub fn pick(prompt: &str, items: &[String]) -> io::Result<Option<usize>> {
+    let mut stderr = io::stderr();
+    let mut query = String::new();
+    let mut selected = 0;
+    let raw = RawMode::enable()?;
+
+    let choice = loop {
+        let matches = filter(&query, items);
+        selected = selected.min(matches.len().min(ROWS).saturating_sub(1));
+        draw(&mut stderr, prompt, &query, items, &matches, selected)?;
+
+        let key = match event::read()? {
+            Event::Key(key @ KeyEvent{ kind: KeyEventKind::Press, .. }) => key,
+            _ => continue
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => break matches.get(selected).copied(),
+            KeyCode::Esc => break None,
+            KeyCode::Char('c') if ctrl => break None,
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Char('p') if ctrl => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected += 1,
+            KeyCode::Char('n') if ctrl => selected += 1,
+            KeyCode::Backspace => {
+                query.pop();
+                selected = 0;
+            },
+            KeyCode::Char(c) if !ctrl => {
+                query.push(c);
+                selected = 0;
+            },
+            _ => {}
+        }
+    };
+
+    queue!(stderr, cursor::MoveToColumn(0), terminal::Clear(terminal::ClearType::FromCursorDown))?;
+    stderr.flush()?;
+    drop(raw);
+    Ok(choice)
+}
+
+/// Ask the user to choose one of the PRs in `index`, or `None` if they gave up.
+pub fn pick_pr(index: &PrIndex) -> io::Result<Option<&PullRequest>> {
+    let prs: Vec<&PullRequest> = index.iter().collect();
+    let branches: Vec<String> = prs.iter().map(|pr| pr.branch.clone()).collect();
+    Ok(pick("PR>", &branches)?.map(|i| prs[i]))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subsequences_match() {
+        assert!(score("htfx", "hotfix/1234567").is_some());
+        assert!(score("HOT", "hotfix/1234567").is_some());
+        assert!(score("xif", "hotfix/1234567").is_none());
+        assert_eq!(score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn best_matches_first() {
+        let items: Vec<String> = ["docs/fix-typo/1", "fix/2", "refactor/3", "fix-ci/4"].iter()
+            .map(|i| i.to_string()).collect();
+        assert_eq!(filter("fix", &items), vec![1, 3, 0]);
+        assert_eq!(filter("", &items), vec![0, 1, 2, 3]);
+        assert!(filter("zzz", &items).is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+//! Which commands each person may run
+//!
+//! Some teams let contractors list and review PRs, but not merge or abandon them. A repository
+//! can describe that with roles: each role lists its members and the commands they may run.
+//!
+//! ```console
+//! $ git config --add pr.role.contractor.member bob@example.com
+//! $ git config --add pr.role.contractor.allow list
+//! $ git config --add pr.role.contractor.allow compare
+//! ```
+//!
+//! Members are matched against the current git identity, by email, by name, or as `Name <email>`.
+//! Someone who belongs to several roles may run anything any of their roles allows, and `*` allows
+//! everything. People who belong to no role at all are not restricted.
+//!
+//! Like [read-only mode](crate::config::Config::ensure_writable), this is a guard rail rather than
+//! access control: anyone able to edit the repository's config can change it. Real restrictions
+//! belong on the server.
+use crate::identity::Identity;
+use crate::profile;
+use crate::{tr, Git, GitError};
+use std::collections::BTreeMap;
+
+
+/// What one role may do.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Role {
+    pub members: Vec<String>,
+    pub allow: Vec<String>,
+}
+
+
+/// Every role configured for the repository.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Policy {
+    pub roles: BTreeMap<String, Role>,
+}
+
+impl Policy {
+    /// Build a policy from `git config --get-regexp` output for `pr.role.*` keys.
+    pub fn parse(config: &str) -> Policy {
+        let mut policy = Policy::default();
+        for line in config.lines() {
+            let (key, value) = match line.split_once(' ') {
+                Some(entry) => entry,
+                None => continue
+            };
+            let parts = key.strip_prefix("pr.role.").and_then(|key| key.rsplit_once('.'));
+            let (role, setting) = match parts {
+                Some(parts) => parts,
+                None => continue
+            };
+            let role = policy.roles.entry(role.to_string()).or_default();
+            match setting {
+                "member" => role.members.push(value.trim().to_string()),
+                "allow" => role.allow.push(value.trim().to_string()),
+                _ => {}
+            }
+        }
+        policy
+    }
+
+    pub fn load(git: &Git) -> Result<Policy,GitError> {
+        Ok(Policy::parse(&git.config_get_regexp(r"^pr\.role\.")?))
+    }
+
+    /// May `who` run `command` (like "list" for `git pr list`)?
+    pub fn permits(&self, who: &Identity, command: &str) -> bool {
+        let roles: Vec<&Role> = self.roles.values()
+            .filter(|role| role.members.iter().any(|member| who.answers_to(member)))
+            .collect();
+        roles.is_empty() || roles.iter()
+            .any(|role| role.allow.iter().any(|allowed| allowed == "*" || allowed == command))
+    }
+}
+
+
+/// Refuse to go any further if the current user's roles don't allow `command`.
+pub fn enforce(git: &Git, command: &str) -> Result<(),GitError> {
+    // The profile may add roles, so it must be in place before they are read
+    profile::apply(git)?;
+    let policy = Policy::load(git)?;
+    if policy.roles.is_empty() {
+        return Ok(());
+    }
+    let who = Identity::current(git)?;
+    match policy.permits(&who, command) {
+        true => Ok(()),
+        false => Err(GitError::Refused(tr!("policy-denied", who = who, command = command)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_restrict_their_members() {
+        let policy = Policy::parse("pr.role.contractor.member bob@example.com\n\
+                                    pr.role.contractor.allow list\n\
+                                    pr.role.contractor.allow compare\n\
+                                    pr.role.Lead.member Alice\n\
+                                    pr.role.Lead.allow *\n");
+        let bob = Identity::parse("Bob <bob@example.com>").unwrap();
+        let alice = Identity::parse("Alice <alice@example.com>").unwrap();
+        let carol = Identity::parse("Carol <carol@example.com>").unwrap();
+
+        assert!(policy.permits(&bob, "list"));
+        assert!(!policy.permits(&bob, "clean"));
+        assert!(policy.permits(&alice, "clean"));
+        assert!(policy.permits(&carol, "clean"));
+    }
+}
@@ -0,0 +1,151 @@
+//! Named bundles of settings
+//!
+//! People who contribute to several organizations tend to need different settings for each: a
+//! different remote to publish PRs on, a different trunk, a different signing key, different
+//! roles. Rather than repeating them in every repository, a *profile* collects them under
+//! `pr.profile.<name>.`, usually in the global config:
+//!
+//! ```console
+//! $ git config --global pr.profile.work.remote corp
+//! $ git config --global pr.profile.work.trunk main
+//! $ git config --global pr.profile.work.signingKey ~/.ssh/work.pub
+//! $ git config --global pr.profile.work.role.lead.member alice@corp.example
+//! ```
+//!
+//! A repository then chooses a profile with `git config pr.profile work`, and a single command
+//! can choose one with `git pr --profile work ...`. Each of a profile's keys stands for the `pr.`
+//! key of the same name, so `pr.profile.work.trunk` overrides `pr.trunk`, and
+//! `pr.profile.work.role.lead.member` adds a member to a role (see [`crate::policy`]). The
+//! exceptions are `signingKey` and `gpgFormat`, which stand for git's own `user.signingKey` and
+//! `gpg.format` (see [`crate::signing`]).
+//!
+//! A profile is applied by handing its settings to git as if they had been given with `git -c`,
+//! through `GIT_CONFIG_COUNT` and friends. Every git command git-pr runs afterwards sees them,
+//! and so does every git-pr program it starts. Settings given on the command line, with flags like
+//! `--trunk` or with `git -c`, still take precedence.
+use crate::{tr, Git, GitError};
+use regex::escape;
+use std::env;
+
+// Set once a profile has been applied, so that the programs we start don't apply it again.
+const APPLIED: &str = "GIT_PR_PROFILE_APPLIED";
+
+
+/// One of a profile's settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    /// Where it was set, like `pr.profile.work.trunk`.
+    pub key: String,
+
+    /// The key it stands for, like `pr.trunk`.
+    pub overrides: String,
+
+    pub value: String,
+}
+
+/// A profile, and everything it sets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub overrides: Vec<Override>,
+}
+
+impl Profile {
+    /// Build profile `name` from `git config --get-regexp` output for its `pr.profile.<name>.`
+    /// keys.
+    pub fn parse(name: &str, config: &str) -> Profile {
+        let prefix = format!("pr.profile.{}.", name);
+        let overrides = config.lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+                let setting = key.strip_prefix(&prefix)?;
+                let overrides = match setting.to_lowercase().as_str() {
+                    "signingkey" => "user.signingKey".to_string(),
+                    "gpgformat" => "gpg.format".to_string(),
+                    _ => format!("pr.{}", setting),
+                };
+                Some(Override{ key: key.to_string(), overrides, value: value.to_string() })
+            })
+            .collect();
+        Profile{ name: name.to_string(), overrides }
+    }
+
+    /// Read profile `name` from git's config, refusing if it sets nothing.
+    pub fn load(git: &Git, name: &str) -> Result<Profile,GitError> {
+        let config = git.config_get_regexp(&format!(r"^pr\.profile\.{}\.", escape(name)))?;
+        let profile = Profile::parse(name, &config);
+        match profile.overrides.is_empty() {
+            true => Err(GitError::Refused(tr!("profile-unknown", name = name))),
+            false => Ok(profile),
+        }
+    }
+
+    /// The key which set `key` to `value`: the profile's own key if it gave `key` that value, or
+    /// `key` itself.
+    pub fn origin(&self, key: &str, value: &str) -> String {
+        self.overrides.iter().rev()
+            .find(|o| o.overrides.eq_ignore_ascii_case(key) && o.value == value)
+            .map_or(key.to_string(), |o| o.key.clone())
+    }
+}
+
+
+/// Use profile `name` for the rest of this process, and the programs it starts, instead of
+/// whatever `pr.profile` says.
+pub fn select(name: &str) {
+    push_config("pr.profile", name);
+}
+
+/// Apply the profile `pr.profile` names, if it names one, and return it.
+///
+/// This is safe to call more than once, and in programs started by one which has already done it.
+pub fn apply(git: &Git) -> Result<Option<Profile>,GitError> {
+    let name = match git.config_get("pr.profile")? {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    let profile = Profile::load(git, &name)?;
+    if env::var(APPLIED).ok().as_deref() != Some(name.as_str()) {
+        for o in &profile.overrides {
+            // Leave alone whatever was given on the command line, like --trunk
+            let given = git.config_get_with_origin(&o.overrides)?;
+            if !given.is_some_and(|(_, origin)| origin.starts_with("command ")) {
+                push_config(&o.overrides, &o.value);
+            }
+        }
+        env::set_var(APPLIED, &name);
+    }
+    Ok(Some(profile))
+}
+
+// Give git a config setting, as `git -c` would, through the environment.
+fn push_config(key: &str, value: &str) {
+    let count = env::var("GIT_CONFIG_COUNT").ok()
+        .and_then(|count| count.parse::<usize>().ok())
+        .unwrap_or(0);
+    env::set_var(format!("GIT_CONFIG_KEY_{}", count), key);
+    env::set_var(format!("GIT_CONFIG_VALUE_{}", count), value);
+    env::set_var("GIT_CONFIG_COUNT", (count + 1).to_string());
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_stand_for_pr_keys() {
+        let profile = Profile::parse("work", "pr.profile.work.remote corp\n\
+                                              pr.profile.work.signingkey ~/.ssh/work.pub\n\
+                                              pr.profile.work.role.lead.member alice@corp.example\n\
+                                              pr.profile.home.remote origin\n");
+        let overrides: Vec<(&str, &str)> = profile.overrides.iter()
+            .map(|o| (o.overrides.as_str(), o.value.as_str()))
+            .collect();
+        assert_eq!(overrides, [("pr.remote", "corp"), ("user.signingKey", "~/.ssh/work.pub"),
+                               ("pr.role.lead.member", "alice@corp.example")]);
+        assert_eq!(profile.origin("pr.remote", "corp"), "pr.profile.work.remote");
+        assert_eq!(profile.origin("pr.remote", "upstream"), "pr.remote");
+        assert_eq!(profile.origin("pr.trunk", "main"), "pr.trunk");
+    }
+}
@@ -0,0 +1,483 @@
+//! Pull requests as data
+//!
+//! A pull request is a branch on the shared remote named `<pr-name>/<hash>`. A [`PrIndex`] holds
+//! every such branch we know about, keyed by its remote-tracking ref. It can be built from scratch
+//! (see [`PrIndex::load`]), but long-running programs can also keep one up to date as individual
+//! refs change (see [`PrIndex::update`]), which is far cheaper on repositories with thousands of
+//! refs. The index also caches each PR's diffstat (see [`PrIndex::diff_stat`]) until its ref moves.
+//!
+//! Branches that belong to other tools (see [`crate::interop`]) are left out of the index.
+use crate::config::Config;
+use crate::fuzzy;
+use crate::interop::Guard;
+use crate::parse;
+use crate::{tr, Git, GitError};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+
+/// A single pull request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PullRequest {
+    /// The name the author chose, like "hotfix".
+    pub name: String,
+
+    /// The branch on the remote, like "hotfix/1234567".
+    pub branch: String,
+
+    /// The commit at the tip of the branch.
+    pub tip: String,
+}
+
+impl PullRequest {
+    /// Where the PR left `trunk`: the merge base of the two, so that changes made to trunk since
+    /// then aren't counted as part of the PR.
+    pub fn base(&self, git: &Git, trunk: &str) -> Result<String,GitError> {
+        git.merge_base(trunk, &self.tip)?.ok_or_else(|| GitError::Refused(
+            tr!("show-no-merge-base", branch = self.branch, trunk = trunk)
+        ))
+    }
+}
+
+/// The fields shown for a pull request, for `--json` and `--format` (see [`crate::render`]).
+pub const FIELDS: &[(&str, &str)] = &[
+    ("name", "the PR's name, like \"hotfix\""),
+    ("branch", "the branch on the remote, like \"hotfix/1234567\""),
+    ("tip", "the full hash of the commit at the tip of the branch"),
+    ("short", "the tip's hash, abbreviated to seven characters"),
+    ("author", "who wrote the tip commit, as \"Name <email>\" after applying the mailmap"),
+    ("age", "how long ago the tip commit was made, like \"3 days ago\""),
+    ("date", "when the tip commit was made"),
+    ("claimed", "who has claimed the PR with git pr take, as \"Name <email>\", if anyone"),
+    ("claimed_until", "when that claim expires"),
+    ("owner", "who the PR belongs to: whoever it was handed to with git pr handoff, or the author"),
+    ("labels", "the PR's labels, from git pr label, separated by commas"),
+    ("assigned", "who the PR is assigned to for review, from git pr assign"),
+    ("state", "whether the PR is open, merged, or abandoned (see crate::state)"),
+];
+
+
+/// Decide whether a fully-qualified ref is a PR branch on `remote`.
+///
+/// Returns the PR name and branch name, following the same rules as
+/// [`crate::extract_pr_names`]: the ref must live under `refs/remotes/<remote>/`, and its last path
+/// component must be made of hex digits.
+pub(crate) fn parse_pr_ref(remote: &str, refname: &str) -> Option<(String, String)> {
+    let prefix = format!("refs/remotes/{}/", remote);
+    let branch = refname.strip_prefix(&prefix)?;
+    let (name, _) = parse::pr_branch(branch)?;
+    Some((name.to_string(), branch.to_string()))
+}
+
+
+/// Where [`PrIndex::remember`] keeps the PRs last seen on `remote`.
+///
+/// This is under `.git/git-pr/seen/`, shared by every worktree, as remote-tracking refs are.
+fn seen_path(git: &Git, remote: &str) -> Result<PathBuf,GitError> {
+    Ok(git.git_common_dir()?.join("git-pr").join("seen").join(remote))
+}
+
+
+/// Every pull request on one remote, keyed by remote-tracking ref.
+#[derive(Debug, Clone)]
+pub struct PrIndex {
+    remote: String,
+    guard: Guard,
+    prs: BTreeMap<String, PullRequest>,
+
+    // `git diff --stat` for each tip we've been asked about, with the base it was measured from
+    diff_stats: BTreeMap<String, (String, String)>,
+}
+
+// Two indexes are the same if they have the same PRs, whatever either has cached about them.
+impl PartialEq for PrIndex {
+    fn eq(&self, other: &PrIndex) -> bool {
+        self.remote == other.remote && self.guard == other.guard && self.prs == other.prs
+    }
+}
+
+impl PrIndex {
+    /// An index with no PRs in it, which ignores every other tool's branches.
+    pub fn new(remote: &str) -> PrIndex {
+        PrIndex::with_guard(remote, Guard::default())
+    }
+
+    /// An index with no PRs in it, which ignores the branches `guard` excludes.
+    pub fn with_guard(remote: &str, guard: Guard) -> PrIndex {
+        PrIndex{
+            remote: remote.to_string(), guard, prs: BTreeMap::new(), diff_stats: BTreeMap::new()
+        }
+    }
+
+    /// Build an index from `<hash> <refname>` lines, as produced by [`Git::remote_refs`].
+    pub fn from_refs(remote: &str, refs: &str) -> PrIndex {
+        PrIndex::new(remote).with_refs(refs)
+    }
+
+    fn with_refs(self, refs: &str) -> PrIndex {
+        let mut index = self;
+        for line in refs.lines() {
+            if let Some((tip, refname)) = line.trim().split_once(' ') {
+                index.update(refname, Some(tip));
+            }
+        }
+        index
+    }
+
+    /// Build an index from the current state of the repository.
+    pub fn load(git: &Git, remote: &str) -> Result<PrIndex,GitError> {
+        let guard = Config::load(git)?.guard();
+        Ok(PrIndex::with_guard(remote, guard).with_refs(&git.remote_refs(remote)?))
+    }
+
+    /// The PRs on `remote` when [`PrIndex::remember`] was last called, if it ever was.
+    pub fn last_seen(git: &Git, remote: &str) -> Result<Option<PrIndex>,GitError> {
+        match fs::read_to_string(seen_path(git, remote)?) {
+            Ok(refs) => {
+                let guard = Config::load(git)?.guard();
+                Ok(Some(PrIndex::with_guard(remote, guard).with_refs(&refs)))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save this index, so that a later [`PrIndex::last_seen`] can tell what has changed since.
+    pub fn remember(&self, git: &Git) -> Result<(),GitError> {
+        let path = seen_path(git, &self.remote)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let refs: String = self.prs.iter()
+            .map(|(refname, pr)| format!("{} {}\n", pr.tip, refname))
+            .collect();
+        fs::write(path, refs)?;
+        Ok(())
+    }
+
+    /// Build an index of the PR branches `remote` has right now whose names match `pattern`.
+    ///
+    /// Nothing is fetched (see [`Git::ls_remote_heads`]), so this is much cheaper than a fetch
+    /// followed by [`PrIndex::load`] when only a few PRs matter.
+    pub fn probe(git: &Git, remote: &str, pattern: &str) -> Result<PrIndex,GitError> {
+        let guard = Config::load(git)?.guard();
+        let prefix = format!("refs/remotes/{}/", remote);
+        let refs: String = git.ls_remote_heads(remote, pattern)?.lines()
+            .filter_map(|line| line.trim().split_once(" refs/heads/"))
+            .map(|(tip, branch)| format!("{} {}{}\n", tip, prefix, branch))
+            .collect();
+        Ok(PrIndex::with_guard(remote, guard).with_refs(&refs))
+    }
+
+    /// Account for a single ref having changed.
+    ///
+    /// `tip` is the ref's new value, or `None` if it has been deleted. Refs which aren't PR
+    /// branches are ignored. Returns true if the index changed, in which case the diffstat cached
+    /// for the ref's old tip is forgotten.
+    pub fn update(&mut self, refname: &str, tip: Option<&str>) -> bool {
+        let (name, branch) = match parse_pr_ref(&self.remote, refname) {
+            Some((_, branch)) if self.guard.excludes(&branch) => return false,
+            None => return false,
+            Some(parsed) => parsed
+        };
+
+        let old = match tip {
+            None => self.prs.remove(refname),
+            Some(tip) => {
+                let pr = PullRequest{ name, branch, tip: tip.to_string() };
+                match self.prs.insert(refname.to_string(), pr.clone()) {
+                    Some(old) if old == pr => return false,
+                    Some(old) => Some(old),
+                    None => return true,
+                }
+            }
+        };
+        match old {
+            Some(old) => {
+                self.diff_stats.remove(&old.tip);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Summarize what the PR `name` changes since it left `trunk`, as `git diff --stat` does.
+    ///
+    /// The summary is cached along with the base it was measured from, so asking again only costs
+    /// finding the base, until the PR's ref moves (see [`PrIndex::update`]) or the base does.
+    pub fn diff_stat(&mut self, git: &Git, name: &str, trunk: &str) -> Result<String,GitError> {
+        let pr = self.lookup(name)?;
+        let base = pr.base(git, trunk)?;
+        let tip = pr.tip.clone();
+        if let Some((cached_base, stat)) = self.diff_stats.get(&tip) {
+            if *cached_base == base {
+                return Ok(stat.clone());
+            }
+        }
+        let stat = git.diff_stat(&base, &tip)?;
+        self.diff_stats.insert(tip, (base, stat.clone()));
+        Ok(stat)
+    }
+
+    /// Re-read a single ref from the repository and update the index accordingly.
+    pub fn refresh(&mut self, git: &Git, refname: &str) -> Result<bool,GitError> {
+        if parse_pr_ref(&self.remote, refname).is_none() {
+            return Ok(false);
+        }
+        let tip = git.resolve_ref(refname)?;
+        Ok(self.update(refname, tip.as_deref()))
+    }
+
+    /// All known PRs, ordered by branch.
+    pub fn iter(&self) -> impl Iterator<Item = &PullRequest> {
+        self.prs.values()
+    }
+
+    /// Just the PR names, in the same order as [`PrIndex::iter`].
+    pub fn names(&self) -> Vec<String> {
+        self.iter().map(|pr| pr.name.clone()).collect()
+    }
+
+    /// The PRs that `name` could refer to.
+    ///
+    /// A full branch name ("hotfix/1234567") picks out exactly one PR, but several PRs may share a
+    /// bare name ("hotfix"), so callers should check for ambiguity.
+    pub fn find(&self, name: &str) -> Vec<&PullRequest> {
+        self.iter().filter(|pr| pr.name == name || pr.branch == name).collect()
+    }
+
+    /// The one PR that `name` refers to.
+    ///
+    /// If there isn't one, the error explains why, suggesting similarly named PRs if `name` looks
+    /// like a typo.
+    pub fn lookup(&self, name: &str) -> Result<&PullRequest,LookupError> {
+        match self.find(name).as_slice() {
+            [pr] => Ok(pr),
+            [] => {
+                let mut candidates: Vec<&str> = self.iter()
+                    .flat_map(|pr| [pr.name.as_str(), pr.branch.as_str()])
+                    .collect();
+                candidates.sort_unstable();
+                candidates.dedup();
+                let suggestions = fuzzy::closest(name, &candidates).into_iter()
+                    .map(|s| s.to_string())
+                    .collect();
+                Err(LookupError::Missing{ name: name.to_string(), suggestions })
+            },
+            several => Err(LookupError::Ambiguous{
+                name: name.to_string(),
+                branches: several.iter().map(|pr| pr.branch.clone()).collect()
+            })
+        }
+    }
+
+    /// How the PRs have changed since `before`, ordered by branch.
+    pub fn changes_since(&self, before: &PrIndex) -> Vec<Change> {
+        let mut changes: Vec<Change> = before.prs.iter()
+            .filter(|(refname, _)| !self.prs.contains_key(*refname))
+            .map(|(_, pr)| Change::Closed(pr.clone()))
+            .collect();
+        for (refname, pr) in &self.prs {
+            match before.prs.get(refname) {
+                None => changes.push(Change::Opened(pr.clone())),
+                Some(old) if old.tip != pr.tip => changes.push(Change::Updated(pr.clone())),
+                Some(_) => (),
+            }
+        }
+        changes.sort_by(|a, b| a.pr().branch.cmp(&b.pr().branch));
+        changes
+    }
+
+    /// The remote these PRs are on.
+    pub fn remote(&self) -> &str {
+        &self.remote
+    }
+
+    pub fn len(&self) -> usize {
+        self.prs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prs.is_empty()
+    }
+}
+
+
+/// How one PR differs between two [`PrIndex`]es (see [`PrIndex::changes_since`]).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The PR is new.
+    Opened(PullRequest),
+
+    /// The PR's branch has moved. This is the PR as it is now.
+    Updated(PullRequest),
+
+    /// The PR is gone, whether it was merged, abandoned, or archived.
+    Closed(PullRequest),
+}
+
+impl Change {
+    /// The PR that changed.
+    pub fn pr(&self) -> &PullRequest {
+        match self {
+            Change::Opened(pr) | Change::Updated(pr) | Change::Closed(pr) => pr,
+        }
+    }
+}
+
+
+/// Why [`PrIndex::lookup`] couldn't settle on a PR.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum LookupError {
+    /// No PR has this name. `suggestions` holds the closest existing names, if any are close.
+    Missing{ name: String, suggestions: Vec<String> },
+
+    /// Several PRs share this name, so the full branch name is needed.
+    Ambiguous{ name: String, branches: Vec<String> },
+}
+
+impl fmt::Display for LookupError {
+    /// Explain the problem the way git explains an unknown command.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LookupError::Missing{ name, suggestions } => {
+                write!(f, "{}", tr!("pr-not-found", name = name))?;
+                match suggestions.len() {
+                    0 => return Ok(()),
+                    1 => write!(f, "\n\n{}", tr!("pr-most-similar"))?,
+                    _ => write!(f, "\n\n{}", tr!("pr-most-similar-many"))?,
+                }
+                for suggestion in suggestions {
+                    write!(f, "\n\t{}", suggestion)?;
+                }
+                Ok(())
+            },
+            LookupError::Ambiguous{ name, branches } => {
+                write!(f, "{}", tr!("pr-ambiguous", name = name))?;
+                for branch in branches {
+                    write!(f, "\n\t{}", branch)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LookupError {}
+
+impl From<LookupError> for GitError {
+    /// A PR that can't be found is one more reason for git-pr to refuse to go on.
+    fn from(other: LookupError) -> GitError {
+        GitError::Refused(other.to_string())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognize_pr_refs() {
+        assert_eq!(
+            parse_pr_ref("origin", "refs/remotes/origin/first-pr/000000"),
+            Some(("first-pr".to_string(), "first-pr/000000".to_string()))
+        );
+        assert_eq!(
+            parse_pr_ref("origin", "refs/remotes/origin/has/a/directory/f3f3f3"),
+            Some(("has/a/directory".to_string(), "has/a/directory/f3f3f3".to_string()))
+        );
+        assert_eq!(parse_pr_ref("origin", "refs/remotes/origin/not-being-tracked"), None);
+        assert_eq!(parse_pr_ref("origin", "refs/remotes/origin/not/hex-at-all"), None);
+        assert_eq!(parse_pr_ref("origin", "refs/remotes/upstream/hotfix/0"), None);
+        assert_eq!(parse_pr_ref("origin", "refs/heads/hotfix/0"), None);
+    }
+
+    #[test]
+    fn incremental_updates_match_a_rebuild() {
+        let refs = "aaaa refs/remotes/origin/one/1\n\
+                    bbbb refs/remotes/origin/two/2\n\
+                    cccc refs/remotes/origin/trunk\n";
+        let mut index = PrIndex::from_refs("origin", refs);
+        assert_eq!(index.names(), vec!["one", "two"]);
+
+        // Move one PR, delete another, add a third, and touch a ref that isn't a PR at all
+        assert!(index.update("refs/remotes/origin/one/1", Some("dddd")));
+        assert!(index.update("refs/remotes/origin/two/2", None));
+        assert!(index.update("refs/remotes/origin/three/3", Some("eeee")));
+        assert!(!index.update("refs/remotes/origin/trunk", Some("ffff")));
+        assert!(!index.update("refs/remotes/origin/one/1", Some("dddd")));
+
+        let rebuilt = PrIndex::from_refs("origin", "dddd refs/remotes/origin/one/1\n\
+                                                   eeee refs/remotes/origin/three/3\n");
+        assert_eq!(index, rebuilt);
+    }
+
+    #[test]
+    fn updates_forget_cached_diffstats() {
+        let mut index = PrIndex::from_refs("origin", "aaaa refs/remotes/origin/one/1\n\
+                                                     bbbb refs/remotes/origin/two/2\n");
+        for tip in ["aaaa", "bbbb"] {
+            index.diff_stats.insert(tip.to_string(), ("base".to_string(), "stat".to_string()));
+        }
+        assert!(!index.update("refs/remotes/origin/one/1", Some("aaaa")));
+        assert!(index.update("refs/remotes/origin/one/1", Some("cccc")));
+        assert!(index.update("refs/remotes/origin/two/2", None));
+        assert!(index.diff_stats.is_empty());
+    }
+
+    #[test]
+    fn changes_between_indexes() {
+        let before = PrIndex::from_refs("origin", "aaaa refs/remotes/origin/one/1\n\
+                                                  bbbb refs/remotes/origin/two/2\n\
+                                                  cccc refs/remotes/origin/three/3\n");
+        let after = PrIndex::from_refs("origin", "aaaa refs/remotes/origin/one/1\n\
+                                                 dddd refs/remotes/origin/three/3\n\
+                                                 eeee refs/remotes/origin/four/4\n");
+        let changes: Vec<String> = after.changes_since(&before).iter().map(|change| {
+            match change {
+                Change::Opened(pr) => format!("+{}", pr.branch),
+                Change::Updated(pr) => format!("~{} {}", pr.branch, pr.tip),
+                Change::Closed(pr) => format!("-{}", pr.branch),
+            }
+        }).collect();
+        assert_eq!(changes, ["+four/4", "~three/3 dddd", "-two/2"]);
+        assert!(after.changes_since(&after).is_empty());
+    }
+
+    #[test]
+    fn other_tools_are_not_prs() {
+        let refs = "aaaa refs/remotes/origin/renovate/abc123\n\
+                    bbbb refs/remotes/origin/fix/1\n";
+        assert_eq!(PrIndex::from_refs("origin", refs).names(), vec!["fix"]);
+
+        let guard = Guard::new(&["renovate".to_string()]).unwrap();
+        assert_eq!(PrIndex::with_guard("origin", guard).with_refs(refs).len(), 2);
+    }
+
+    #[test]
+    fn find_by_name_or_branch() {
+        let index = PrIndex::from_refs("origin", "aaaa refs/remotes/origin/fix/1\n\
+                                                 bbbb refs/remotes/origin/fix/2\n\
+                                                 cccc refs/remotes/origin/docs/3\n");
+        assert_eq!(index.find("fix").len(), 2);
+        assert_eq!(index.find("fix/2")[0].tip, "bbbb");
+        assert!(index.find("nope").is_empty());
+    }
+
+    #[test]
+    fn lookup_suggests_near_misses() {
+        let index = PrIndex::from_refs("origin", "aaaa refs/remotes/origin/fix/1\n\
+                                                 bbbb refs/remotes/origin/fix/2\n\
+                                                 cccc refs/remotes/origin/docs/3\n");
+        assert_eq!(index.lookup("docs").unwrap().tip, "cccc");
+        assert_eq!(index.lookup("dcos").unwrap_err().to_string(),
+                   "No such PR: dcos\n\nThe most similar PR is\n\tdocs");
+        assert!(matches!(index.lookup("fix"), Err(LookupError::Ambiguous{ .. })));
+        assert_eq!(index.lookup("refactor").unwrap_err().to_string(), "No such PR: refactor");
+    }
+}
@@ -0,0 +1,390 @@
+//! Picking out pull requests with a query
+//!
+//! `git pr list --query` takes a small query language, so that finding (say) the stale PRs which
+//! touch the build doesn't take a pipeline of greps over `--porcelain` output:
+//!
+//! ```text
+//! age>30d and label:infra
+//! author:alice (ci:fail or not check:tests)
+//! state:merged path:docs/
+//! ```
+//!
+//! A query is made of conditions, combined with `and`, `or`, `not`, and parentheses. `not` binds
+//! tightest, then `and`, then `or`; conditions written one after another must all hold, as if
+//! joined by `and`. A value with spaces in it can be quoted, like `author:"Alice Smith"`. The
+//! conditions are:
+//!
+//! - `age>30d`, `age<12h`, and so on, with `<`, `<=`, `>`, `>=`, or `=`, compare how long ago the
+//!   tip commit was made, in seconds (`s`), minutes (`m`), hours (`h`), days (`d`), or weeks (`w`).
+//! - `author:<text>` holds if the tip's author, as `Name <email>`, contains the text, ignoring
+//!   case.
+//! - `label:<label>` holds if the PR has the label (see [`crate::label`]).
+//! - `state:<state>` holds if the PR is `open`, `merged`, or `abandoned` (see [`crate::state`]).
+//! - `path:<path>` holds if the PR changes a file at that path, or under it.
+//! - `ci:<status>` holds if CI's latest status for the tip is `pass`, `fail`, or `pending`, or
+//!   if CI hasn't reported one, for `ci:none` (see [`crate::ci`]).
+//! - `check:<name>` holds if the named check passed, and `check:<name>=<status>` if it went as
+//!   given.
+//!
+//! Looking facts up costs git calls, so [`Query::uses`] says which ones a query needs, and only
+//! those need filling in before [`Query::matches`].
+use crate::ci::{Report, Status};
+use crate::state::State;
+use crate::{tr, GitError};
+
+
+/// Something about a PR which a query can ask about.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Age,
+    Author,
+    Label,
+    State,
+    Path,
+    Ci,
+}
+
+/// What is known about a PR, for [`Query::matches`].
+#[derive(Debug, Clone, Default)]
+pub struct Facts {
+    /// How long ago the tip commit was made, in seconds.
+    pub age: i64,
+
+    /// Who made the tip commit, as `Name <email>`.
+    pub author: String,
+
+    pub labels: Vec<String>,
+    pub state: State,
+
+    /// The paths of the files the PR changes.
+    pub paths: Vec<String>,
+
+    /// What CI has reported about the tip.
+    pub ci: Report,
+}
+
+
+/// A parsed query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Condition(Condition),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+/// One condition of a query, like `label:infra`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// Compare the age, in seconds, with a comparison like `>`.
+    Age(String, i64),
+    Author(String),
+    Label(String),
+    State(State),
+    Path(String),
+
+    /// The latest CI status, or `None` for none.
+    Ci(Option<Status>),
+
+    /// How a named check went.
+    Check(String, Status),
+}
+
+impl Query {
+    /// Parse a query, like `age>30d and label:infra`.
+    pub fn parse(text: &str) -> Result<Query,GitError> {
+        let mut parser = Parser{ tokens: tokenize(text)?, at: 0 };
+        let query = parser.or()?;
+        match parser.tokens.get(parser.at) {
+            None => Ok(query),
+            Some(_) => Err(GitError::Refused(tr!("query-unmatched-close"))),
+        }
+    }
+
+    /// Whether the query asks about `field`.
+    pub fn uses(&self, field: Field) -> bool {
+        match self {
+            Query::Condition(condition) => condition.field() == field,
+            Query::Not(query) => query.uses(field),
+            Query::And(left, right) | Query::Or(left, right) => {
+                left.uses(field) || right.uses(field)
+            },
+        }
+    }
+
+    /// Whether a PR with these `facts` matches the query.
+    pub fn matches(&self, facts: &Facts) -> bool {
+        match self {
+            Query::Condition(condition) => condition.holds(facts),
+            Query::Not(query) => !query.matches(facts),
+            Query::And(left, right) => left.matches(facts) && right.matches(facts),
+            Query::Or(left, right) => left.matches(facts) || right.matches(facts),
+        }
+    }
+}
+
+impl Condition {
+    fn field(&self) -> Field {
+        match self {
+            Condition::Age(..) => Field::Age,
+            Condition::Author(_) => Field::Author,
+            Condition::Label(_) => Field::Label,
+            Condition::State(_) => Field::State,
+            Condition::Path(_) => Field::Path,
+            Condition::Ci(_) | Condition::Check(..) => Field::Ci,
+        }
+    }
+
+    fn holds(&self, facts: &Facts) -> bool {
+        match self {
+            Condition::Age(comparison, seconds) => match comparison.as_str() {
+                "<" => facts.age < *seconds,
+                "<=" => facts.age <= *seconds,
+                ">" => facts.age > *seconds,
+                ">=" => facts.age >= *seconds,
+                _ => facts.age == *seconds,
+            },
+            Condition::Author(text) => {
+                facts.author.to_lowercase().contains(&text.to_lowercase())
+            },
+            Condition::Label(label) => facts.labels.contains(label),
+            Condition::State(state) => facts.state == *state,
+            Condition::Path(path) => {
+                let path = path.trim_end_matches('/');
+                facts.paths.iter().any(|changed| {
+                    changed == path || changed.strip_prefix(path)
+                        .is_some_and(|rest| rest.starts_with('/') || path.is_empty())
+                })
+            },
+            Condition::Ci(status) => facts.ci.status == *status,
+            Condition::Check(name, status) => {
+                facts.ci.checks.get(name).is_some_and(|check| check.status == *status)
+            },
+        }
+    }
+
+    // Parse one condition, like `label:infra`.
+    fn parse(word: &str) -> Result<Condition,GitError> {
+        if let Some(rest) = word.strip_prefix("age") {
+            let split = rest.find(|c: char| !"<>=".contains(c)).unwrap_or(rest.len());
+            let (comparison, age) = rest.split_at(split);
+            return match (["<", "<=", ">", ">=", "="].contains(&comparison), seconds(age)) {
+                (true, Some(seconds)) => Ok(Condition::Age(comparison.to_string(), seconds)),
+                _ => Err(GitError::Refused(tr!("query-bad-age", condition = word))),
+            };
+        }
+        let (field, value) = word.split_once(':')
+            .ok_or_else(|| GitError::Refused(tr!("query-unknown", condition = word)))?;
+        if value.is_empty() {
+            return Err(GitError::Refused(tr!("query-no-value", condition = word)));
+        }
+        let bad = || GitError::Refused(tr!("query-bad-value", condition = word));
+        match field {
+            "author" => Ok(Condition::Author(value.to_string())),
+            "label" => Ok(Condition::Label(value.to_string())),
+            "state" => Ok(Condition::State(value.parse().map_err(|_| bad())?)),
+            "path" => Ok(Condition::Path(value.to_string())),
+            "ci" => match value {
+                "none" => Ok(Condition::Ci(None)),
+                _ => Ok(Condition::Ci(Some(value.parse().map_err(|_| bad())?))),
+            },
+            "check" => match value.split_once('=') {
+                Some((name, status)) if !name.is_empty() => {
+                    Ok(Condition::Check(name.to_string(), status.parse().map_err(|_| bad())?))
+                },
+                Some(_) => Err(bad()),
+                None => Ok(Condition::Check(value.to_string(), Status::Pass)),
+            },
+            _ => Err(GitError::Refused(tr!("query-unknown", condition = word))),
+        }
+    }
+}
+
+// Parse an age like `30d` into seconds.
+fn seconds(age: &str) -> Option<i64> {
+    let unit = match age.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    let count: i64 = age[..age.len() - 1].parse().ok().filter(|count| *count >= 0)?;
+    count.checked_mul(unit)
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+}
+
+// Split a query into parentheses and words, taking quotes out of the words.
+fn tokenize(text: &str) -> Result<Vec<Token>,GitError> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => { chars.next(); },
+            '(' => { chars.next(); tokens.push(Token::Open); },
+            ')' => { chars.next(); tokens.push(Token::Close); },
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    if c != '"' {
+                        word.push(c);
+                        continue;
+                    }
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some(c) => word.push(c),
+                            None => return Err(GitError::Refused(tr!("query-unmatched-quote"))),
+                        }
+                    }
+                }
+                tokens.push(Token::Word(word));
+            },
+        }
+    }
+    Ok(tokens)
+}
+
+// A recursive descent parser, with one function for each level of precedence.
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn or(&mut self) -> Result<Query,GitError> {
+        let mut query = self.and()?;
+        while self.keyword("or") {
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query,GitError> {
+        let mut query = self.not()?;
+        loop {
+            let next = match self.tokens.get(self.at) {
+                Some(Token::Word(word)) => !word.eq_ignore_ascii_case("or"),
+                Some(Token::Open) => true,
+                _ => false,
+            };
+            if !next {
+                return Ok(query);
+            }
+            self.keyword("and");
+            query = Query::And(Box::new(query), Box::new(self.not()?));
+        }
+    }
+
+    fn not(&mut self) -> Result<Query,GitError> {
+        match self.keyword("not") {
+            true => Ok(Query::Not(Box::new(self.not()?))),
+            false => self.condition(),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Query,GitError> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        match token {
+            Some(Token::Word(word)) => Ok(Query::Condition(Condition::parse(&word)?)),
+            Some(Token::Open) => {
+                let query = self.or()?;
+                match self.tokens.get(self.at) {
+                    Some(Token::Close) => {
+                        self.at += 1;
+                        Ok(query)
+                    },
+                    _ => Err(GitError::Refused(tr!("query-unmatched-open"))),
+                }
+            },
+            Some(Token::Close) => Err(GitError::Refused(tr!("query-unmatched-close"))),
+            None => Err(GitError::Refused(tr!("query-incomplete"))),
+        }
+    }
+
+    // Take the keyword `word` next, if it is next.
+    fn keyword(&mut self, word: &str) -> bool {
+        match self.tokens.get(self.at) {
+            Some(Token::Word(next)) if next.eq_ignore_ascii_case(word) => {
+                self.at += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ci::Check;
+
+    fn facts() -> Facts {
+        let mut ci = Report{ status: Some(Status::Fail), ..Report::default() };
+        let check = Check{ status: Status::Pass, metrics: Default::default() };
+        ci.checks.insert("tests".to_string(), check);
+        Facts{
+            age: 40 * 24 * 60 * 60,
+            author: "Alice Smith <alice@example.com>".to_string(),
+            labels: vec!["infra".to_string()],
+            state: State::Open,
+            paths: vec!["build/ci.yml".to_string(), "README".to_string()],
+            ci,
+        }
+    }
+
+    fn matches(query: &str) -> bool {
+        Query::parse(query).unwrap().matches(&facts())
+    }
+
+    #[test]
+    fn conditions() {
+        assert!(matches("age>30d"));
+        assert!(!matches("age<=4w"));
+        assert!(matches("author:alice"));
+        assert!(matches("author:\"alice smith\""));
+        assert!(!matches("author:bob"));
+        assert!(matches("label:infra") && !matches("label:docs"));
+        assert!(matches("state:open") && !matches("state:merged"));
+        assert!(matches("path:build") && matches("path:build/") && matches("path:README"));
+        assert!(!matches("path:bui"));
+        assert!(matches("ci:fail") && !matches("ci:none"));
+        assert!(matches("check:tests") && matches("check:tests=pass"));
+        assert!(!matches("check:tests=fail") && !matches("check:lint"));
+    }
+
+    #[test]
+    fn combinations() {
+        assert!(matches("age>30d and label:infra"));
+        assert!(matches("age>30d label:infra"));
+        assert!(!matches("label:docs or state:merged"));
+        assert!(matches("label:docs or state:open and not ci:pass"));
+        assert!(!matches("(label:docs or state:open) and not ci:fail"));
+        assert!(matches("NOT (label:docs)"));
+        let query = Query::parse("label:x or (age>1d and not check:tests)").unwrap();
+        assert!(query.uses(Field::Label) && query.uses(Field::Age) && query.uses(Field::Ci));
+        assert!(!query.uses(Field::Path));
+    }
+
+    #[test]
+    fn bad_queries() {
+        for query in ["", "age>30", "age~30d", "colour:red", "label:", "state:closed", "ci:ok",
+                      "check:=pass", "(label:x", "label:x)", "not", "author:\"alice", "and"] {
+            assert!(Query::parse(query).is_err(), "{}", query);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Output for people and for scripts
+//!
+//! Programs that print a line per thing (per PR, per audit entry, and so on) describe each one as
+//! a [`Record`], and leave the formatting to a [`Renderer`] chosen once from the command line:
+//!
+//! * By default, [`Pretty`] prints the program's usual columns, separated by tabs, with dates
+//!   written according to `pr.dateFormat`. Empty columns are left out.
+//! * `--porcelain` prints the same columns in a form scripts can rely on: every column is always
+//!   present, dates are ISO-8601, and lists are separated by commas.
+//! * `--json` prints every field of each record as a JSON object on its own line. (This needs the
+//!   `serde` feature.)
+//! * `--format <template>` fills in a [`crate::template::Template`].
+//!
+//! A program only has to say what its records contain and which columns it shows by default; it
+//! then supports every mode. See [`Output::renderer`].
+use crate::date::{DateFormat, Style};
+use crate::template::Template;
+use crate::GitError;
+#[cfg(feature = "serde")]
+use serde_json::{json, Map};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// The value of one field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+
+    /// A moment in time, in seconds since the Unix epoch.
+    Time(i64),
+
+    List(Vec<String>),
+
+    /// Nothing to show, like an audit entry with no note.
+    Absent,
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Value {
+        Value::Text(text)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Value {
+        Value::Text(text.to_string())
+    }
+}
+
+impl From<Vec<String>> for Value {
+    fn from(items: Vec<String>) -> Value {
+        Value::List(items)
+    }
+}
+
+impl From<Option<String>> for Value {
+    fn from(text: Option<String>) -> Value {
+        text.map_or(Value::Absent, Value::Text)
+    }
+}
+
+
+/// One thing to print, as named fields in a fixed order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Record {
+    fields: Vec<(String, Value)>,
+}
+
+impl Record {
+    pub fn new() -> Record {
+        Record::default()
+    }
+
+    /// Add a field.
+    pub fn with<V: Into<Value>>(mut self, name: &str, value: V) -> Record {
+        self.fields.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// The value of a field, or [`Value::Absent`] if the record doesn't have it.
+    pub fn get(&self, name: &str) -> &Value {
+        self.fields.iter().find(|(field, _)| field == name).map_or(&Value::Absent, |(_, v)| v)
+    }
+
+    /// Every field as a JSON object. Times are seconds since the Unix epoch.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut object = Map::new();
+        for (name, value) in &self.fields {
+            let value = match value {
+                Value::Text(text) => json!(text),
+                Value::Time(time) => json!(time),
+                Value::List(items) => json!(items),
+                Value::Absent => serde_json::Value::Null,
+            };
+            object.insert(name.clone(), value);
+        }
+        serde_json::Value::Object(object)
+    }
+}
+
+
+/// Turns records into lines of output.
+pub trait Renderer {
+    /// Will `field` appear in the output? Callers can skip looking up fields that are costly to
+    /// find out (such as a commit's author) when they won't be shown.
+    fn shows(&self, field: &str) -> bool;
+
+    /// Render one record as a single line, without the trailing newline.
+    fn render(&self, record: &Record) -> String;
+}
+
+// Write a single value as text.
+fn text(value: &Value, dates: &DateFormat, now: i64, separator: &str) -> String {
+    match value {
+        Value::Text(text) => text.clone(),
+        Value::Time(time) => dates.render(*time, now),
+        Value::List(items) => items.join(separator),
+        Value::Absent => String::new(),
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+
+/// Tab-separated columns for people to read.
+pub struct Pretty {
+    columns: Vec<String>,
+    dates: DateFormat,
+    now: i64,
+}
+
+impl Renderer for Pretty {
+    fn shows(&self, field: &str) -> bool {
+        self.columns.iter().any(|column| column == field)
+    }
+
+    fn render(&self, record: &Record) -> String {
+        let cells: Vec<String> = self.columns.iter()
+            .map(|column| record.get(column))
+            .filter(|value| **value != Value::Absent)
+            .map(|value| text(value, &self.dates, self.now, ", "))
+            .collect();
+        cells.join("\t")
+    }
+}
+
+
+/// Tab-separated columns in a stable form, for scripts.
+pub struct Porcelain {
+    columns: Vec<String>,
+}
+
+impl Renderer for Porcelain {
+    fn shows(&self, field: &str) -> bool {
+        self.columns.iter().any(|column| column == field)
+    }
+
+    fn render(&self, record: &Record) -> String {
+        let iso = DateFormat::new(Style::Iso);
+        let cells: Vec<String> = self.columns.iter()
+            .map(|column| text(record.get(column), &iso, 0, ","))
+            .collect();
+        cells.join("\t")
+    }
+}
+
+
+/// One JSON object per record, with every field.
+#[cfg(feature = "serde")]
+pub struct Json;
+
+#[cfg(feature = "serde")]
+impl Renderer for Json {
+    fn shows(&self, _field: &str) -> bool {
+        true
+    }
+
+    fn render(&self, record: &Record) -> String {
+        record.to_json().to_string()
+    }
+}
+
+
+/// Whatever a `--format` template asks for.
+pub struct Templated {
+    template: Template,
+    dates: DateFormat,
+    now: i64,
+}
+
+impl Renderer for Templated {
+    fn shows(&self, field: &str) -> bool {
+        self.template.uses(field)
+    }
+
+    fn render(&self, record: &Record) -> String {
+        self.template.render(|field| text(record.get(field), &self.dates, self.now, ","))
+    }
+}
+
+
+/// Which kind of output was asked for on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    Pretty,
+    Porcelain,
+    #[cfg(feature = "serde")]
+    Json,
+    Template(String),
+}
+
+impl Output {
+    /// Build the renderer for this kind of output.
+    ///
+    /// `columns` are the fields shown by default (and under `--porcelain`). `fields` lists every
+    /// field a record may have, which is what a template may refer to. `date_format` is the value
+    /// of `pr.dateFormat`.
+    pub fn renderer(&self, columns: &[&str], fields: &[(&str, &str)], date_format: &str)
+        -> Result<Box<dyn Renderer>,GitError> {
+        let columns = columns.iter().map(|c| c.to_string()).collect();
+        let dates = DateFormat::configured(date_format, false);
+        Ok(match self {
+            Output::Pretty => Box::new(Pretty{ columns, dates, now: now() }),
+            Output::Porcelain => Box::new(Porcelain{ columns }),
+            #[cfg(feature = "serde")]
+            Output::Json => Box::new(Json),
+            Output::Template(text) => Box::new(Templated{
+                template: Template::parse(text, fields)?, dates, now: now()
+            }),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: &[(&str, &str)] = &[("name", ""), ("time", ""), ("refs", ""), ("note", "")];
+
+    fn record() -> Record {
+        Record::new()
+            .with("name", "hotfix")
+            .with("time", Value::Time(1_622_635_200))
+            .with("refs", vec!["a".to_string(), "b".to_string()])
+            .with("note", None)
+    }
+
+    fn render(output: Output) -> String {
+        output.renderer(&["name", "refs", "note"], FIELDS, "iso").unwrap().render(&record())
+    }
+
+    #[test]
+    fn every_mode_renders_the_same_record() {
+        assert_eq!(render(Output::Pretty), "hotfix\ta, b");
+        assert_eq!(render(Output::Porcelain), "hotfix\ta,b\t");
+        assert_eq!(render(Output::Template("{name} at {time}".to_string())),
+                   "hotfix at 2021-06-02T12:00:00Z");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_has_every_field() {
+        assert_eq!(render(Output::Json),
+                   r#"{"name":"hotfix","note":null,"refs":["a","b"],"time":1622635200}"#);
+        assert!(Output::Json.renderer(&["name"], FIELDS, "iso").unwrap().shows("time"));
+    }
+
+    #[test]
+    fn which_fields_are_shown() {
+        let pretty = Output::Pretty.renderer(&["name"], FIELDS, "iso").unwrap();
+        assert!(pretty.shows("name") && !pretty.shows("time"));
+    }
+}
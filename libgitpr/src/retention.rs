@@ -0,0 +1,51 @@
+//! Getting rid of old PR residue
+//!
+//! Long-lived repositories accumulate leftovers from PRs that were merged or abandoned long ago:
+//! archive tags under [`ARCHIVE_PREFIX`], and metadata notes under [`NOTES_REF`] that describe
+//! commits which have since been garbage collected. Archive tags older than
+//! `pr.archiveRetentionDays` are expired, and orphaned notes are pruned, by `git pr-maintain`.
+use crate::{Git, GitError};
+pub use crate::metadata::NOTES_REF;
+
+
+/// Where archived PRs are kept, one annotated tag per PR branch.
+pub const ARCHIVE_PREFIX: &str = "refs/tags/pr-archive/";
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+
+/// Pick out the refs which are at least `retention_days` old at time `now`.
+///
+/// `refs` is `<unix time> <refname>` lines, as produced by [`Git::ref_dates`]. Lines that can't be
+/// parsed are skipped rather than expired.
+pub fn expired(refs: &str, now: u64, retention_days: u64) -> Vec<String> {
+    let cutoff = now.saturating_sub(retention_days * SECONDS_PER_DAY);
+    refs.lines()
+        .filter_map(|line| line.trim().split_once(' '))
+        .filter_map(|(time, refname)| Some((time.parse::<u64>().ok()?, refname)))
+        .filter(|(time, _)| *time <= cutoff)
+        .map(|(_, refname)| refname.to_string())
+        .collect()
+}
+
+/// Archive tags which have outlived the retention period.
+pub fn expired_archives(git: &Git, now: u64, retention_days: u64)
+    -> Result<Vec<String>,GitError> {
+    Ok(expired(&git.ref_dates(ARCHIVE_PREFIX)?, now, retention_days))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expire_only_old_refs() {
+        let refs = "100000 refs/tags/pr-archive/old/1\n\
+                    900000 refs/tags/pr-archive/new/2\n\
+                    garbage refs/tags/pr-archive/odd/3\n";
+        assert_eq!(expired(refs, 1_000_000, 10), vec!["refs/tags/pr-archive/old/1"]);
+        assert_eq!(expired(refs, 1_000_000, 0).len(), 2);
+        assert!(expired(refs, 1_000_000, 365).is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+//! Revisions of a pull request
+//!
+//! `git pr amend` publishes a new revision of a PR as a new `<name>/<hash>` branch. So that the
+//! revisions can be found from one another, the new revision's tip gets a line of metadata (see
+//! [`crate::metadata`]) naming the branch it replaces: `revises <at> <branch>`, with the time in
+//! seconds since the Unix epoch.
+
+
+/// The metadata line recording that a PR's tip replaced the revision on `branch`, at time `at`.
+pub fn revises_line(branch: &str, at: i64) -> String {
+    format!("revises {} {}", at, branch)
+}
+
+/// The branches of the revisions which a tip with metadata `lines` replaced, oldest first.
+pub fn previous(lines: &[String]) -> Vec<String> {
+    let mut revised: Vec<(i64, &str)> = lines.iter()
+        .filter_map(|line| {
+            let (at, branch) = line.strip_prefix("revises ")?.split_once(' ')?;
+            Some((at.parse().ok()?, branch))
+        })
+        .collect();
+    revised.sort();
+    revised.into_iter().map(|(_, branch)| branch.to_string()).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previous_revisions() {
+        assert!(previous(&[]).is_empty());
+        let lines = vec![
+            revises_line("hotfix/bbbbbbb", 200),
+            "comment looks good".to_string(),
+            revises_line("hotfix/aaaaaaa", 100),
+            "revises later hotfix/ccccccc".to_string(),
+        ];
+        assert_eq!(previous(&lines), vec!["hotfix/aaaaaaa", "hotfix/bbbbbbb"]);
+    }
+}
@@ -0,0 +1,318 @@
+//! JSON-RPC interface for editors and IDEs
+//!
+//! `git pr-daemon --stdio` stays running and answers [JSON-RPC 2.0] requests on stdin, writing
+//! responses to stdout. Messages are framed the same way the Language Server Protocol frames them:
+//! a `Content-Length` header, a blank line, and then exactly that many bytes of JSON. This lets
+//! editor plugins reuse their existing LSP client libraries rather than spawning a new `git-pr-*`
+//! process every time they want to know something.
+//!
+//! Supported methods:
+//!
+//! * `list` -- names of the currently known PRs (does not fetch)
+//! * `fetch` -- update remote-tracking branches, as `git pr list` does
+//! * `env` -- the same JSON printed by `git pr-env`
+//! * `diff` -- what the PR `name` changes since it left trunk, as text (does not fetch)
+//! * `diffstat` -- the same, summarized as `git diff --stat` does (does not fetch)
+//! * `comment` -- comment `text` on the PR `name`, as `git pr comment` does
+//! * `approve` -- approve the PR `name`, as `git pr approve` does, as the bot identity `as` if
+//!   given; the result is false if it was already approved
+//! * `exit` -- stop the daemon
+//!
+//! Parameters are passed by name, as in `{"name": "hotfix", "text": "Looks good"}`. `comment` and
+//! `approve` are refused in read-only mode (see `pr.readOnly`), and like every method are subject
+//! to the repository's roles (see [`crate::policy`]).
+//!
+//! The PR list is cached between requests, along with the diffstats asked for. When refs change on
+//! disk, only the affected PRs are updated, and the client is sent a `pullRequestsChanged`
+//! notification so that it knows to ask again.
+//!
+//! [JSON-RPC 2.0]: https://www.jsonrpc.org/specification
+use crate::config::Config;
+use crate::identity::Identity;
+use crate::pull_request::PrIndex;
+use crate::watch::RefChange;
+use crate::{approval, audit, comment, metadata, partial, policy, Git, GitError};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex};
+
+
+// Standard JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+
+/// Read one framed message, returning `None` at end of input.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(String::from_utf8_lossy(&body).to_string()))
+}
+
+/// Write one framed message.
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+
+/// What the daemon should do after handling a message.
+pub enum Outcome {
+    /// Send this response (if any) and wait for the next message.
+    Continue(Option<Value>),
+
+    /// The client asked us to stop.
+    Exit,
+}
+
+fn error(id: Value, code: i64, message: &str) -> Option<Value> {
+    Some(json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }))
+}
+
+/// The notification sent to clients when the set of PRs may have changed.
+pub fn changed_notification() -> Value {
+    json!({ "jsonrpc": "2.0", "method": "pullRequestsChanged" })
+}
+
+
+/// A conversation with a single client.
+pub struct Session {
+    git: Git,
+
+    // Every PR we know about, as of the last time we were asked
+    index: Option<PrIndex>,
+
+    // Refs which have changed since then, reported from another thread (see [`Session::changes`])
+    pending: Arc<Mutex<Vec<RefChange>>>,
+}
+
+impl Session {
+    pub fn new(git: Git) -> Session {
+        Session{ git, index: None, pending: Arc::new(Mutex::new(vec![])) }
+    }
+
+    /// The repository this session answers questions about.
+    pub fn git(&self) -> &Git {
+        &self.git
+    }
+
+    /// A queue of ref changes to be applied to the PR list before the next request for it.
+    pub fn changes(&self) -> Arc<Mutex<Vec<RefChange>>> {
+        Arc::clone(&self.pending)
+    }
+
+    /// Answer a single JSON-RPC message.
+    ///
+    /// Notifications (requests without an `id`) are carried out but never answered, as the
+    /// JSON-RPC specification requires.
+    pub fn handle(&mut self, message: &str) -> Outcome {
+        let request: Value = match serde_json::from_str(message) {
+            Ok(request) => request,
+            Err(_) => return Outcome::Continue(error(Value::Null, PARSE_ERROR, "Parse error"))
+        };
+
+        let id = request.get("id").cloned();
+        let method = match request.get("method").and_then(Value::as_str) {
+            Some(method) => method,
+            None => return Outcome::Continue(
+                error(id.unwrap_or(Value::Null), INVALID_REQUEST, "Invalid Request")
+            )
+        };
+
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let param = |name: &str| params.get(name).and_then(Value::as_str);
+        let result: Result<Value,GitError> = match (method, param("name"), param("text")) {
+            ("exit", _, _) => return Outcome::Exit,
+            ("list", _, _) => self.list(),
+            ("fetch", _, _) => self.git.fetch_prune().map(|_| Value::Null),
+            ("env", _, _) => Config::load(&self.git).map(|config| config.to_json()),
+            ("diff", Some(name), _) => self.diff(name),
+            ("diffstat", Some(name), _) => self.diff_stat(name),
+            ("comment", Some(name), Some(text)) => self.comment(name, text),
+            ("approve", Some(name), _) => self.approve(name, param("as")),
+            ("diff" | "diffstat" | "comment" | "approve", _, _) => {
+                return Outcome::Continue(id.and_then(|id| {
+                    error(id, INVALID_PARAMS, "Invalid params")
+                }))
+            },
+            _ => {
+                return Outcome::Continue(id.and_then(|id| {
+                    error(id, METHOD_NOT_FOUND, &format!("Method not found: {}", method))
+                }))
+            }
+        };
+
+        Outcome::Continue(id.and_then(|id| match result {
+            Ok(result) => Some(json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+            Err(e) => error(id, INTERNAL_ERROR, &e.to_string())
+        }))
+    }
+
+    fn list(&mut self) -> Result<Value,GitError> {
+        let (_, index) = self.index()?;
+        Ok(json!(index.names()))
+    }
+
+    // The cached index, brought up to date with whatever refs have changed since it was last used,
+    // along with the repository it describes.
+    fn index(&mut self) -> Result<(&Git, &mut PrIndex),GitError> {
+        let changes: Vec<RefChange> = match self.pending.lock() {
+            Ok(mut pending) => pending.drain(..).collect(),
+            Err(_) => vec![RefChange::All]
+        };
+
+        let index = match self.index.take() {
+            Some(index) if !changes.contains(&RefChange::All) => {
+                let mut index = index;
+                for change in &changes {
+                    if let RefChange::Ref(refname) = change {
+                        index.refresh(&self.git, refname)?;
+                    }
+                }
+                index
+            },
+            _ => {
+                let config = Config::load(&self.git)?;
+                PrIndex::load(&self.git, &config.remote.value)?
+            }
+        };
+
+        Ok((&self.git, self.index.insert(index)))
+    }
+
+    fn diff(&self, name: &str) -> Result<Value,GitError> {
+        policy::enforce(&self.git, "show")?;
+        let config = Config::load(&self.git)?;
+        let trunk = format!("{}/{}", config.remote.value, config.trunk.value);
+        let index = PrIndex::load(&self.git, &config.remote.value)?;
+        let pr = index.lookup(name)?;
+        let base = pr.base(&self.git, &trunk)?;
+        partial::prefetch(&self.git, &format!("{}..{}", base, pr.tip))?;
+        Ok(json!(self.git.diff(&base, &pr.tip, &["--color=never"])?))
+    }
+
+    fn diff_stat(&mut self, name: &str) -> Result<Value,GitError> {
+        policy::enforce(&self.git, "show")?;
+        let config = Config::load(&self.git)?;
+        let trunk = format!("{}/{}", config.remote.value, config.trunk.value);
+        let (git, index) = self.index()?;
+        Ok(json!(index.diff_stat(git, name, &trunk)?))
+    }
+
+    // Writes go to the remote, so like the commands they stand in for, they work on the PR as it
+    // is there now, rather than as it was when the list was cached.
+    fn comment(&self, name: &str, text: &str) -> Result<Value,GitError> {
+        policy::enforce(&self.git, "comment")?;
+        let config = Config::load(&self.git)?;
+        config.ensure_writable("comment", false)?;
+        let text = comment::normalize(text)?;
+        let remote = &config.remote.value;
+        self.git.fetch_prune()?;
+        let index = PrIndex::load(&self.git, remote)?;
+        let pr = index.lookup(name)?;
+
+        let result = comment::add(&self.git, remote, &pr.tip, &text);
+        audit::record(&self.git, "comment", &[metadata::NOTES_REF.to_string()], &result)?;
+        result.map(|_| Value::Null)
+    }
+
+    fn approve(&self, name: &str, bot: Option<&str>) -> Result<Value,GitError> {
+        policy::enforce(&self.git, "approve")?;
+        let config = Config::load(&self.git)?;
+        config.ensure_writable("approve", false)?;
+        let remote = &config.remote.value;
+        self.git.fetch_prune()?;
+        let index = PrIndex::load(&self.git, remote)?;
+        let pr = index.lookup(name)?;
+        let me = Identity::resolve(&self.git, bot)?.to_string();
+
+        let result = approval::approve(&self.git, remote, &pr.tip, &me);
+        audit::record(&self.git, "approve", std::slice::from_ref(&pr.branch), &result)?;
+        result.map(|applied| json!(applied))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn messages_survive_framing() {
+        let mut buffer = vec![];
+        write_message(&mut buffer, &json!({ "id": 1, "method": "list" })).unwrap();
+        write_message(&mut buffer, &json!({ "id": 2, "method": "exit" })).unwrap();
+
+        let mut reader = Cursor::new(buffer);
+        let first: Value = serde_json::from_str(&read_message(&mut reader).unwrap().unwrap())
+            .unwrap();
+        assert_eq!(first["method"], "list");
+        let second: Value = serde_json::from_str(&read_message(&mut reader).unwrap().unwrap())
+            .unwrap();
+        assert_eq!(second["id"], 2);
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn unknown_methods_are_reported() {
+        let mut session = Session::new(Git::new());
+        match session.handle(r#"{"jsonrpc":"2.0","id":7,"method":"merge"}"#) {
+            Outcome::Continue(Some(response)) => {
+                assert_eq!(response["id"], 7);
+                assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+            },
+            _ => panic!("expected an error response")
+        }
+    }
+
+    #[test]
+    fn parameters_are_checked() {
+        let mut session = Session::new(Git::new());
+        for request in [r#"{"jsonrpc":"2.0","id":8,"method":"approve"}"#,
+                        r#"{"jsonrpc":"2.0","id":8,"method":"comment","params":{"name":"x"}}"#] {
+            match session.handle(request) {
+                Outcome::Continue(Some(response)) => {
+                    assert_eq!(response["error"]["code"], INVALID_PARAMS);
+                },
+                _ => panic!("expected an error response")
+            }
+        }
+    }
+
+    #[test]
+    fn garbage_is_a_parse_error() {
+        let mut session = Session::new(Git::new());
+        match session.handle("{not json") {
+            Outcome::Continue(Some(response)) => {
+                assert_eq!(response["error"]["code"], PARSE_ERROR);
+            },
+            _ => panic!("expected an error response")
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Finding PRs by what they say
+//!
+//! `git pr search` matches a pattern against each PR's name, its branch, the author of its tip,
+//! its owner, and the subjects of its commits, which are the closest thing a branch has to a
+//! description. A pattern is a case-insensitive substring, unless it is asked to be a regular
+//! expression, in which case it is case-sensitive unless it says otherwise (`(?i)`).
+use crate::{tr, GitError};
+use regex::Regex;
+
+
+/// The field `git pr search` adds to those of `pull_request::FIELDS`.
+pub const MATCHED: (&str, &str) =
+    ("matched", "which fields the pattern matched, like \"name,commits\"");
+
+/// Something to look for.
+pub enum Pattern {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Match `text` anywhere, ignoring case.
+    pub fn substring(text: &str) -> Pattern {
+        Pattern::Substring(text.to_lowercase())
+    }
+
+    /// Match the regular expression `text`, refusing if it isn't one.
+    pub fn regex(text: &str) -> Result<Pattern,GitError> {
+        Regex::new(text).map(Pattern::Regex)
+            .map_err(|e| GitError::Refused(tr!("search-bad-regex", pattern = text, error = e)))
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            Pattern::Substring(needle) => text.to_lowercase().contains(needle.as_str()),
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// The names of the `fields` (pairs of a field's name and its text) that `pattern` matches, each
+/// named once, in order.
+pub fn matching_fields<'a>(pattern: &Pattern, fields: &[(&'a str, String)]) -> Vec<&'a str> {
+    let mut matched: Vec<&str> = vec![];
+    for (name, text) in fields {
+        if !matched.contains(name) && pattern.matches(text) {
+            matched.push(name);
+        }
+    }
+    matched
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substrings_ignore_case() {
+        let pattern = Pattern::substring("Login");
+        assert!(pattern.matches("fix-login-page"));
+        assert!(pattern.matches("LOGIN"));
+        assert!(!pattern.matches("logout"));
+    }
+
+    #[test]
+    fn regexes_are_case_sensitive() {
+        let pattern = Pattern::regex("^fix-.*page$").unwrap();
+        assert!(pattern.matches("fix-login-page"));
+        assert!(!pattern.matches("Fix-login-page"));
+        assert!(Pattern::regex("(?i)^fix").unwrap().matches("Fix-login-page"));
+    }
+
+    #[test]
+    fn bad_regexes_are_refused() {
+        assert!(matches!(Pattern::regex("(unclosed"), Err(GitError::Refused(_))));
+    }
+
+    #[test]
+    fn each_field_is_named_once() {
+        let fields = [
+            ("name", "login".to_string()),
+            ("commits", "Fix the login page".to_string()),
+            ("commits", "Log in faster".to_string()),
+            ("commits", "Test login".to_string()),
+            ("author", "Alice <alice@example.com>".to_string()),
+        ];
+        assert_eq!(matching_fields(&Pattern::substring("login"), &fields), ["name", "commits"]);
+        assert!(matching_fields(&Pattern::substring("bob"), &fields).is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+//! Checking that git-pr works in this environment
+//!
+//! git-pr leans on a fairly recent git (`merge-tree --write-tree`, for instance, needs 2.38), and
+//! on whatever global configuration, hooks, and credentials the user has set up. Rather than find
+//! out halfway through merging a real PR, `git pr-selftest` takes a throwaway PR through its whole
+//! life: it is created, listed, commented on, approved, merged, and cleaned up, in a scratch
+//! repository whose remote is a bare repository next to it. Each step is reported as it would be
+//! by a test runner, and the first failure stops the run, since later steps depend on it.
+//!
+//! Both repositories live in a directory chosen by the caller, and nothing outside it is touched.
+use crate::identity::Identity;
+use crate::pull_request::{PrIndex, PullRequest};
+use crate::{approval, merge, metadata, tr, Git, GitError};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+
+const TRUNK: &str = "trunk";
+const REMOTE: &str = "origin";
+const NAME: &str = "selftest";
+
+
+/// How one step of the self-test went.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// The step worked. The text says anything worth knowing about how, and may be empty.
+    Passed(String),
+
+    /// The step failed, for the reason given.
+    Failed(String),
+
+    /// An earlier step failed, so this one was not attempted.
+    Skipped,
+}
+
+/// A step of the self-test, and how it went.
+#[derive(Debug)]
+pub struct Step {
+    pub name: String,
+    pub outcome: Outcome,
+}
+
+impl fmt::Display for Step {
+    /// Report the step as a test runner would, on a line beginning "ok", "FAIL", or "skip".
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            Outcome::Passed(detail) if detail.is_empty() => write!(f, "ok    {}", self.name),
+            Outcome::Passed(detail) => write!(f, "ok    {} ({})", self.name, detail),
+            Outcome::Failed(reason) => write!(f, "FAIL  {}: {}", self.name, reason),
+            Outcome::Skipped => write!(f, "skip  {}", self.name),
+        }
+    }
+}
+
+// The repositories under test, and what we know about the PR so far.
+struct Sandbox {
+    remote: Git,
+    work: Git,
+    pr: Option<PullRequest>,
+}
+
+type Action = fn(&mut Sandbox) -> Result<String,GitError>;
+
+fn git_in(path: PathBuf) -> Git {
+    Git{ program: String::from("git"), working_dir: Box::new(path), git_dir: None, work_tree: None }
+}
+
+impl Sandbox {
+    fn pr(&self) -> &PullRequest {
+        // The list step fills this in, and nothing after it runs unless it passed
+        self.pr.as_ref().expect("the PR is known once it has been listed")
+    }
+
+    fn version(&mut self) -> Result<String,GitError> {
+        Ok(Git::new().version()?.trim().to_string())
+    }
+
+    // A bare remote, and a clone of sorts with an initial commit on trunk.
+    fn repositories(&mut self) -> Result<String,GitError> {
+        for git in [&self.remote, &self.work] {
+            fs::create_dir(git.working_dir.as_ref())?;
+        }
+        self.remote.init(true, TRUNK)?;
+        self.work.init(false, TRUNK)?;
+        self.work.config_set("user.name", "git-pr selftest", false)?;
+        self.work.config_set("user.email", "selftest@example.invalid", false)?;
+        let url = self.remote.working_dir.as_ref().as_ref().to_string_lossy().to_string();
+        self.work.add_remote(REMOTE, &url)?;
+
+        let tree = self.work.empty_tree()?;
+        let start = self.work.commit_tree(&tree, &[], "Start the selftest", false)?;
+        self.work.update_ref("HEAD", &start)?;
+        self.work.push_upstream(REMOTE, TRUNK)?;
+        Ok(String::new())
+    }
+
+    // What `git pr create` does, plus a commit for the PR to be about.
+    fn create(&mut self) -> Result<String,GitError> {
+        let branch = format!("{}/{}", NAME, self.work.rev_parse_head()?);
+        self.work.create_branch(&branch)?;
+
+        let base = self.work.resolve_ref("HEAD")?.unwrap_or_default();
+        let tree = self.work.empty_tree()?;
+        let change = self.work.commit_tree(&tree, &[&base], "Make a change", false)?;
+        self.work.update_ref("HEAD", &change)?;
+        self.work.push_upstream(REMOTE, &branch)?;
+        Ok(branch)
+    }
+
+    fn list(&mut self) -> Result<String,GitError> {
+        self.work.fetch_prune()?;
+        let index = PrIndex::load(&self.work, REMOTE)?;
+        let pr = index.lookup(NAME).map_err(|e| GitError::Refused(e.to_string()))?;
+        self.pr = Some(pr.clone());
+        Ok(String::new())
+    }
+
+    fn comment(&mut self) -> Result<String,GitError> {
+        metadata::append(&self.work, REMOTE, &self.pr().tip, "comment Looks good to me")?;
+        Ok(String::new())
+    }
+
+    // Approve, then make sure the approval reached the remote, where reviewers would see it.
+    fn approve(&mut self) -> Result<String,GitError> {
+        let approval = approval::approve_line(&Identity::current(&self.work)?.to_string());
+        metadata::append(&self.work, REMOTE, &self.pr().tip, &approval)?;
+        if !metadata::lines(&self.remote, &self.pr().tip)?.contains(&approval) {
+            return Err(GitError::Refused(tr!("selftest-not-published")));
+        }
+        Ok(String::new())
+    }
+
+    fn merge(&mut self) -> Result<String,GitError> {
+        let pr = self.pr();
+        merge::merge(&self.work, TRUNK, pr, &merge::merge_message(pr, &[]))?;
+        self.work.push_branches(REMOTE, &[TRUNK.to_string()])?;
+        Ok(String::new())
+    }
+
+    // What `git pr clean` does, once the user is back on trunk.
+    fn clean(&mut self) -> Result<String,GitError> {
+        self.work.checkout(TRUNK)?;
+        let merged = self.work.merged_branches(TRUNK)?;
+        let deletable = crate::extract_deletable_branches(&merged, TRUNK);
+        if !deletable.contains(&self.pr().branch) {
+            return Err(GitError::Refused(tr!("selftest-not-merged", branch = self.pr().branch)));
+        }
+        for branch in &deletable {
+            self.work.delete_branch(branch)?;
+        }
+        Ok(String::new())
+    }
+}
+
+/// Run every step of the self-test, using repositories created inside `dir`, which must exist.
+pub fn run(dir: &Path) -> Vec<Step> {
+    let mut sandbox = Sandbox{
+        remote: git_in(dir.join("remote.git")),
+        work: git_in(dir.join("work")),
+        pr: None,
+    };
+    let actions: Vec<(String, Action)> = vec![
+        (tr!("selftest-version"), Sandbox::version),
+        (tr!("selftest-repositories"), Sandbox::repositories),
+        (tr!("selftest-create"), Sandbox::create),
+        (tr!("selftest-list"), Sandbox::list),
+        (tr!("selftest-comment"), Sandbox::comment),
+        (tr!("selftest-approve"), Sandbox::approve),
+        (tr!("selftest-merge"), Sandbox::merge),
+        (tr!("selftest-clean"), Sandbox::clean),
+    ];
+
+    let mut failed = false;
+    actions.into_iter().map(|(name, action)| {
+        let outcome = match failed {
+            true => Outcome::Skipped,
+            false => match action(&mut sandbox) {
+                Ok(detail) => Outcome::Passed(detail),
+                Err(e) => {
+                    failed = true;
+                    Outcome::Failed(e.to_string())
+                }
+            }
+        };
+        Step{ name, outcome }
+    }).collect()
+}
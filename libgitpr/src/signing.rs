@@ -0,0 +1,161 @@
+//! Tamper-evident metadata
+//!
+//! Anyone who can push to a bare repository can write whatever metadata they like, so a merge gate
+//! that trusts (say) an approval record is only as strong as the repository's access control. To do
+//! better, records can be signed. Rather than reimplementing GPG and SSH signing, we let git do it:
+//! a signed record is a commit object (attached to no branch) whose message is the record itself.
+//! This means signing and verification honor exactly the same configuration as `git commit -S` and
+//! `git verify-commit` -- `gpg.format`, `user.signingKey`, `gpg.ssh.allowedSignersFile`, and so
+//! on.
+//!
+//! Whether records should be signed at all is controlled by `pr.signMetadata`.
+//!
+//! A record lives in the object database, which metadata isn't pushed with, so a record which
+//! vouches for a line of metadata goes in the line itself (see [`export`]). Every clone which has
+//! the line can then check the signature.
+use crate::encryption::{from_hex, to_hex};
+use crate::{Git, GitError};
+
+
+/// What a record written into a line of metadata starts with (see [`export`]).
+pub const MARKER: &str = "signed:";
+
+
+/// Git's verdict on a signed record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    /// The one-letter status from git's `%G?` format: "G" for good, "N" for no signature, "B" for
+    /// bad, and so on (see `git help log`).
+    pub status: String,
+
+    /// Who signed it: the key's user ID for GPG, or the principal for SSH.
+    pub signer: String,
+}
+
+impl Signature {
+    /// True only for a good signature made with a key git trusts.
+    pub fn is_trusted(&self) -> bool {
+        self.status == "G"
+    }
+
+    /// Was this signed with a key belonging to whoever has `email`? The signer (an SSH principal,
+    /// or a GPG user ID) must be the email, or mention it.
+    pub fn is_by(&self, email: &str) -> bool {
+        let signer = self.signer.to_ascii_lowercase();
+        let email = email.to_ascii_lowercase();
+        signer == email || signer.contains(&format!("<{}>", email))
+    }
+}
+
+
+/// Parse the output of [`Git::commit_signature`] into a signature and the signed payload.
+pub(crate) fn parse_signature(text: &str) -> (Signature, String) {
+    let mut lines = text.splitn(3, '\n');
+    let status = lines.next().unwrap_or_default().to_string();
+    let signer = lines.next().unwrap_or_default().to_string();
+    let payload = lines.next().unwrap_or_default().trim_end().to_string();
+
+    (Signature{ status, signer }, payload)
+}
+
+/// Should new records be signed in this repository?
+pub fn enabled(git: &Git) -> Result<bool,GitError> {
+    Ok(git.config_get("pr.signMetadata")?.as_deref() == Some("true"))
+}
+
+/// Store `payload` as a record, signing it if `sign` is set. Returns the record's hash.
+pub fn seal(git: &Git, payload: &str, sign: bool) -> Result<String,GitError> {
+    let tree = git.empty_tree()?;
+    git.commit_tree(&tree, &[], payload, sign)
+}
+
+/// Read a record back, along with git's verdict on its signature.
+pub fn open(git: &Git, record: &str) -> Result<(Signature, String),GitError> {
+    Ok(parse_signature(&git.commit_signature(record)?))
+}
+
+/// Write the record `record` out as a single word, to travel in a line of metadata: [`MARKER`]
+/// followed by the record's commit object in hex.
+pub fn export(git: &Git, record: &str) -> Result<String,GitError> {
+    Ok(format!("{}{}", MARKER, to_hex(&git.read_object("commit", record)?)))
+}
+
+/// Sign the metadata `line` about `commit`: the line, followed by a record of what it says and
+/// which commit it is about (see [`export`]), so that it can't be moved to another commit.
+pub fn seal_line(git: &Git, line: &str, commit: &str) -> Result<String,GitError> {
+    let record = seal(git, &line_payload(line, commit), true)?;
+    Ok(format!("{} {}", line, export(git, &record)?))
+}
+
+/// Split a line of metadata into what it says and its record, if [`seal_line`] gave it one.
+pub fn split_line(line: &str) -> (&str, Option<&str>) {
+    match line.rsplit_once(' ') {
+        Some((line, record)) if record.starts_with(MARKER) => (line, Some(record)),
+        _ => (line, None),
+    }
+}
+
+/// Check the record on a line of metadata about `commit`, as [`seal_line`] wrote it. Returns the
+/// signature if the record says what the line does, about `commit`, and `None` otherwise, or if
+/// the line has no record, or a garbled one.
+pub fn check_line(git: &Git, line: &str, commit: &str) -> Result<Option<Signature>,GitError> {
+    let (said, record) = match split_line(line) {
+        (said, Some(record)) => (said, record),
+        (_, None) => return Ok(None),
+    };
+    match import(git, record) {
+        Ok(Some((signature, payload))) if payload == line_payload(said, commit) => {
+            Ok(Some(signature))
+        },
+        Ok(_) | Err(GitError::Exit(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// What the record of metadata `line` about `commit` says.
+fn line_payload(line: &str, commit: &str) -> String {
+    format!("{}\non {}", line, commit)
+}
+
+/// Store a record written out by [`export`] in this repository, and read it back as [`open`]
+/// does. Returns `None` if `text` isn't an exported record.
+pub fn import(git: &Git, text: &str) -> Result<Option<(Signature, String)>,GitError> {
+    let contents = match text.strip_prefix(MARKER).and_then(from_hex) {
+        Some(contents) => contents,
+        None => return Ok(None),
+    };
+    let record = git.write_object("commit", &contents)?;
+    open(git, &record).map(Some)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_verdicts() {
+        let (signature, payload) = parse_signature("G\nreviewer@example.com\napproved\n\n");
+        assert!(signature.is_trusted());
+        assert_eq!(signature.signer, "reviewer@example.com");
+        assert_eq!(payload, "approved");
+
+        let (signature, payload) = parse_signature("N\n\napproved\n");
+        assert!(!signature.is_trusted());
+        assert_eq!(payload, "approved");
+    }
+
+    #[test]
+    fn signers() {
+        let ssh = Signature{ status: "G".to_string(), signer: "Bob@example.com".to_string() };
+        assert!(ssh.is_by("bob@example.com"));
+        assert!(!ssh.is_by("rob@example.com"));
+        let gpg = Signature{ status: "G".to_string(), signer: "Bob <bob@example.com>".to_string() };
+        assert!(gpg.is_by("bob@example.com"));
+        assert!(!gpg.is_by("ob@example.com"));
+        assert_eq!(split_line("approved-by Bob <b@example.com> signed:00ff"),
+                   ("approved-by Bob <b@example.com>", Some("signed:00ff")));
+        assert_eq!(split_line("approved-by Bob <b@example.com>"),
+                   ("approved-by Bob <b@example.com>", None));
+    }
+}
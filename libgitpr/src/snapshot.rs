@@ -0,0 +1,90 @@
+//! Moving a remote's whole review state at once
+//!
+//! Everything git-pr knows lives in refs on the shared remote: PR branches and trunk, metadata,
+//! descriptions and attachments under `refs/notes/`, states under [`crate::state::PREFIX`], and
+//! archived PRs under [`ARCHIVE_PREFIX`]. A snapshot is a single git bundle holding all of them,
+//! so that the review state can be moved to a new server, or put back after the remote is lost,
+//! with `git pr snapshot restore`.
+//!
+//! Inside the bundle, each ref is kept under [`NAMESPACE`], like `refs/pr-snapshot/heads/trunk`
+//! for `refs/heads/trunk`, so that restoring it can't touch the refs of the repository doing the
+//! restoring, and the snapshot doesn't depend on what the remote was called.
+use crate::retention::ARCHIVE_PREFIX;
+use crate::{attachment, description, metadata, parse, state};
+
+
+/// Where a snapshot's refs are kept, followed by each ref's own name without its leading `refs/`.
+pub const NAMESPACE: &str = "refs/pr-snapshot/";
+
+
+/// The refs of a remote which belong in a snapshot, given `git ls-remote` output for its refs and
+/// the name of its trunk.
+pub fn refs(listing: &str, trunk: &str) -> Vec<String> {
+    let notes = [metadata::NOTES_REF, description::NOTES_REF, attachment::NOTES_REF];
+    listing.lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter(|refname| {
+            notes.contains(refname) || refname.starts_with(state::PREFIX)
+                || refname.starts_with(ARCHIVE_PREFIX)
+                || refname.strip_prefix("refs/heads/")
+                    .is_some_and(|branch| branch == trunk || parse::pr_branch(branch).is_some())
+        })
+        // Peeled tags, like refs/tags/pr-archive/x^{}, are the same refs again
+        .filter(|refname| !refname.ends_with("^{}"))
+        .map(String::from)
+        .collect()
+}
+
+/// Where `refname` is kept in a snapshot.
+pub fn private(refname: &str) -> String {
+    format!("{}{}", NAMESPACE, refname.strip_prefix("refs/").unwrap_or(refname))
+}
+
+/// The refs a snapshot restores, as refspecs which push them from where they have been fetched to
+/// their own names, given `git bundle list-heads` output for the snapshot. Forced refspecs
+/// overwrite whatever the remote already has.
+pub fn restore_refspecs(heads: &str, force: bool) -> Vec<String> {
+    heads.lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter_map(|refname| {
+            let original = format!("refs/{}", refname.strip_prefix(NAMESPACE)?);
+            Some(format!("{}{}:{}", if force { "+" } else { "" }, refname, original))
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_refs() {
+        let listing = "aaaa\trefs/heads/trunk\n\
+                       bbbb\trefs/heads/feature/1234567\n\
+                       cccc\trefs/heads/scratch\n\
+                       dddd\trefs/notes/pr\n\
+                       eeee\trefs/notes/commits\n\
+                       ffff\trefs/pr-state/feature/89abcde\n\
+                       1111\trefs/tags/pr-archive/old/abcdef0\n\
+                       2222\trefs/tags/pr-archive/old/abcdef0^{}\n\
+                       3333\trefs/tags/v1.0\n";
+        assert_eq!(refs(listing, "trunk"), vec![
+            "refs/heads/trunk", "refs/heads/feature/1234567", "refs/notes/pr",
+            "refs/pr-state/feature/89abcde", "refs/tags/pr-archive/old/abcdef0",
+        ]);
+        assert_eq!(private("refs/heads/trunk"), "refs/pr-snapshot/heads/trunk");
+    }
+
+    #[test]
+    fn restoring() {
+        let heads = "aaaa refs/pr-snapshot/heads/trunk\nbbbb refs/pr-snapshot/notes/pr\n\
+                     cccc refs/heads/unrelated\n";
+        assert_eq!(restore_refspecs(heads, false), vec![
+            "refs/pr-snapshot/heads/trunk:refs/heads/trunk",
+            "refs/pr-snapshot/notes/pr:refs/notes/pr",
+        ]);
+        assert_eq!(restore_refspecs(heads, true)[0],
+                   "+refs/pr-snapshot/heads/trunk:refs/heads/trunk");
+    }
+}
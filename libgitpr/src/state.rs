@@ -0,0 +1,189 @@
+//! Whether a PR is open, merged, or abandoned
+//!
+//! Once a PR's branch is gone from the remote, the branch alone can't say whether it was merged or
+//! given up on. So every change in a PR's state is recorded as a commit on a ref of its own,
+//! `refs/pr-state/<branch>`, which is pushed to the remote in the same atomic push as the change
+//! itself. Each commit's message is `<state> <branch> <tip>`, its author says who made the change
+//! and when, and its parent is the record of the state before, so `git log
+//! refs/pr-state/<branch>` tells the story of the PR. The commits have an empty tree, and don't
+//! keep the PR's own commits reachable.
+//!
+//! A PR is opened by `git pr create`, and then merged, or abandoned (or archived); reopening it
+//! opens it again. A merged PR stays merged even if its branch is deleted later. PRs created before
+//! states were recorded get their first record when their state next changes.
+use crate::{Git, GitError};
+use std::fmt;
+use std::str::FromStr;
+
+
+/// Where states are recorded, followed by each PR's branch.
+pub const PREFIX: &str = "refs/pr-state/";
+
+/// What fetches the states from the remote.
+const REFSPEC: &str = "+refs/pr-state/*:refs/pr-state/*";
+
+
+/// Where a PR stands.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum State {
+    #[default]
+    Open,
+    Merged,
+    Abandoned,
+}
+
+impl State {
+    /// The state a PR which is in state `from` (or has no record) is in after `event`, or `None`
+    /// if that changes nothing, as when a merged PR's branch is deleted.
+    pub fn after(from: Option<State>, event: State) -> Option<State> {
+        match (from, event) {
+            (Some(from), event) if from == event => None,
+            (Some(State::Merged), State::Abandoned) => None,
+            (_, event) => Some(event),
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            State::Open => "open",
+            State::Merged => "merged",
+            State::Abandoned => "abandoned",
+        })
+    }
+}
+
+impl FromStr for State {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<State,String> {
+        match text {
+            "open" => Ok(State::Open),
+            "merged" => Ok(State::Merged),
+            "abandoned" => Ok(State::Abandoned),
+            _ => Err(format!("expected open, merged, or abandoned, not '{}'", text)),
+        }
+    }
+}
+
+
+/// The latest state recorded for a PR.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub state: State,
+
+    /// The PR's branch, like "hotfix/1234567".
+    pub branch: String,
+
+    /// The commit the PR's branch was at when its state changed.
+    pub tip: String,
+}
+
+impl Record {
+    /// The message of the commit recording this.
+    pub fn message(&self) -> String {
+        format!("{} {} {}", self.state, self.branch, self.tip)
+    }
+
+    /// Parse the subject of a commit recording a state.
+    pub fn parse(subject: &str) -> Option<Record> {
+        let mut fields = subject.split(' ');
+        let state = fields.next()?.parse().ok()?;
+        let branch = fields.next()?.to_string();
+        let tip = fields.next()?.to_string();
+        match fields.next() {
+            None => Some(Record{ state, branch, tip }),
+            Some(_) => None,
+        }
+    }
+}
+
+
+/// The ref recording the state of the PR on `branch`.
+pub fn refname(branch: &str) -> String {
+    format!("{}{}", PREFIX, branch)
+}
+
+/// Bring our records of PRs' states up to date with `remote`'s.
+pub fn fetch(git: &Git, remote: &str) -> Result<(),GitError> {
+    git.fetch_refs(remote, &[REFSPEC.to_string()])
+}
+
+/// The latest state recorded for every PR, as of the last [`fetch`].
+pub fn all(git: &Git) -> Result<Vec<Record>,GitError> {
+    Ok(git.ref_subjects(PREFIX)?.lines()
+        .filter_map(|line| Record::parse(line.split_once(' ')?.1))
+        .collect())
+}
+
+/// The latest state recorded for the PR on `branch`, as of the last [`fetch`].
+pub fn current(git: &Git, branch: &str) -> Result<Option<Record>,GitError> {
+    let refname = refname(branch);
+    Ok(git.ref_subjects(&refname)?.lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(name, _)| *name == refname)
+        .and_then(|(_, subject)| Record::parse(subject)))
+}
+
+/// Record that `event` happened to the PR on `branch`, whose tip is `tip`, after fetching the
+/// latest records from `remote`.
+///
+/// Returns the refspec which publishes the record, to be pushed atomically with whatever made the
+/// change, or `None` if the PR's state doesn't change.
+pub fn transition(git: &Git, remote: &str, branch: &str, tip: &str, event: State)
+    -> Result<Option<String>,GitError> {
+    fetch(git, remote)?;
+    let refname = refname(branch);
+    let previous = git.resolve_ref(&refname)?;
+    let from = current(git, branch)?.map(|record| record.state);
+    let state = match State::after(from, event) {
+        Some(state) => state,
+        None => return Ok(None),
+    };
+
+    let record = Record{ state, branch: branch.to_string(), tip: tip.to_string() };
+    let parents: Vec<&str> = previous.iter().map(String::as_str).collect();
+    let commit = git.commit_tree(&git.empty_tree()?, &parents, &record.message(), false)?;
+    Ok(Some(format!("{}:{}", commit, refname)))
+}
+
+/// Push `refspecs` to `remote` atomically, along with the record that `event` happened to the PR on
+/// `branch`, whose tip is `tip` (see [`transition`]).
+pub fn push_with(git: &Git, remote: &str, branch: &str, tip: &str, event: State,
+                 refspecs: &[String]) -> Result<(),GitError> {
+    let mut refspecs = refspecs.to_vec();
+    refspecs.extend(transition(git, remote, branch, tip, event)?);
+    match refspecs.is_empty() {
+        true => Ok(()),
+        false => git.push_atomic(remote, &refspecs),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn states_move_on() {
+        assert_eq!(State::after(None, State::Merged), Some(State::Merged));
+        assert_eq!(State::after(Some(State::Open), State::Abandoned), Some(State::Abandoned));
+        assert_eq!(State::after(Some(State::Abandoned), State::Open), Some(State::Open));
+        assert_eq!(State::after(Some(State::Merged), State::Open), Some(State::Open));
+        assert_eq!(State::after(Some(State::Merged), State::Abandoned), None);
+        assert_eq!(State::after(Some(State::Open), State::Open), None);
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let record = Record{
+            state: State::Merged, branch: "hotfix/1234567".to_string(),
+            tip: "89abcdef".to_string(),
+        };
+        assert_eq!(Record::parse(&record.message()), Some(record));
+        assert_eq!(Record::parse("closed hotfix/1234567 89abcdef"), None);
+        assert_eq!(Record::parse("open hotfix/1234567"), None);
+        assert_eq!(Record::parse("open hotfix/1234567 89abcdef and more"), None);
+    }
+}
@@ -0,0 +1,282 @@
+//! Numbers about how pull requests move through review
+//!
+//! git-pr stores nothing beyond branches, so everything here is reconstructed from history. A
+//! merged PR is recognized by the merge commit git wrote when its branch was merged into trunk
+//! ("Merge branch 'hotfix/1234567'"). The PR is taken to have started when its first commit was
+//! authored, and to have finished when the merge was committed. Open PRs are measured the same
+//! way: their age is the time since their first commit was authored.
+use crate::date::week_start;
+use crate::pull_request::PrIndex;
+use crate::{Git, GitError};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+
+
+/// A PR which has been merged into trunk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedPr {
+    pub name: String,
+
+    /// Trunk's commit just before the merge.
+    pub base: String,
+
+    /// The PR branch's commit at the time it was merged.
+    pub tip: String,
+
+    /// When the PR's first commit was authored (seconds since the Unix epoch).
+    pub started_at: i64,
+
+    /// When the merge commit was made (seconds since the Unix epoch).
+    pub merged_at: i64,
+}
+
+impl MergedPr {
+    /// How long the PR took, in seconds.
+    pub fn time_to_merge(&self) -> i64 {
+        self.merged_at - self.started_at
+    }
+}
+
+
+/// Recover the PR name from the subject of a merge commit, if it merged a PR branch.
+///
+/// Both local ("Merge branch 'fix/abc123'") and remote-tracking ("Merge remote-tracking branch
+/// 'origin/fix/abc123'") merges are recognized.
+pub(crate) fn parse_merge_subject(subject: &str, remote: &str) -> Option<String> {
+    // The remote name is escaped, so it can't break the pattern; everything else is fixed.
+    let pattern = format!(
+        r"^Merge (?:remote-tracking )?branch '(?:{}/)?(.+)/[a-f\d]+'", regex::escape(remote)
+    );
+    let captures = Regex::new(&pattern).unwrap().captures(subject)?;
+    Some(captures[1].to_string())
+}
+
+/// Find the PRs merged into `trunk`, optionally limited to a time window.
+///
+/// `since` and `until` are passed straight to `git log`, so they accept anything git does ("2
+/// weeks ago", "2021-06-01", and so on).
+pub fn merged_prs(git: &Git, trunk: &str, remote: &str, since: Option<&str>, until: Option<&str>)
+    -> Result<Vec<MergedPr>,GitError> {
+    let mut merged = vec![];
+    for line in git.merges(trunk, since, until)?.lines() {
+        // Each line is "<commit time> <first parent> <second parent>\t<subject>"
+        let (header, subject) = match line.split_once('\t') {
+            Some(parts) => parts,
+            None => continue
+        };
+        let fields: Vec<&str> = header.split(' ').collect();
+        let (merged_at, base, tip) = match fields.as_slice() {
+            [time, base, tip, ..] => match time.parse::<i64>() {
+                Ok(time) => (time, *base, *tip),
+                Err(_) => continue
+            },
+            _ => continue
+        };
+        let name = match parse_merge_subject(subject, remote) {
+            Some(name) => name,
+            None => continue
+        };
+
+        let started_at = git.author_times(&format!("{}..{}", base, tip))?
+            .into_iter().min().unwrap_or(merged_at);
+        merged.push(MergedPr{
+            name, base: base.to_string(), tip: tip.to_string(), started_at, merged_at
+        });
+    }
+
+    Ok(merged)
+}
+
+/// Find the PRs merged into `trunk` since the start of the week containing `now` (Monday, UTC).
+pub fn merged_this_week(git: &Git, trunk: &str, remote: &str, now: i64)
+    -> Result<Vec<MergedPr>,GitError> {
+    merged_prs(git, trunk, remote, Some(&format!("@{}", week_start(now))), None)
+}
+
+
+/// A PR which is still open.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenPr {
+    pub name: String,
+
+    /// Who wrote the tip commit, as `Name <email>` after applying the mailmap.
+    pub author: String,
+
+    /// When the PR's first commit was authored (seconds since the Unix epoch).
+    pub started_at: i64,
+}
+
+/// Gather the PRs in `index`, along with who wrote each and when it was started.
+///
+/// A PR's commits are the ones not on `trunk`. One with none of its own (because it was just
+/// created, say) started when its tip was committed.
+pub fn open_prs(git: &Git, index: &PrIndex, trunk: &str) -> Result<Vec<OpenPr>,GitError> {
+    let mut open = vec![];
+    for pr in index.iter() {
+        let times = git.author_times(&format!("{}..{}", trunk, pr.tip))?;
+        let started_at = match times.into_iter().min() {
+            Some(time) => time,
+            None => git.commit_time(&pr.tip)?,
+        };
+        open.push(OpenPr{ name: pr.name.clone(), author: git.author_of(&pr.tip)?, started_at });
+    }
+    Ok(open)
+}
+
+/// Count open PRs per author, busiest first, and alphabetically among equals.
+pub fn per_author(open: &[OpenPr]) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for pr in open {
+        *counts.entry(pr.author.as_str()).or_default() += 1;
+    }
+    let mut rows: Vec<(String, usize)> = counts.into_iter()
+        .map(|(author, count)| (author.to_string(), count))
+        .collect();
+    rows.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    rows
+}
+
+/// The mean age of some open PRs at `now`, in seconds, or `None` if there aren't any.
+pub fn average_age(open: &[OpenPr], now: i64) -> Option<i64> {
+    if open.is_empty() {
+        return None;
+    }
+    Some(open.iter().map(|pr| now - pr.started_at).sum::<i64>() / open.len() as i64)
+}
+
+
+/// The top-level directory a path belongs to, like "src/". Files at the root are grouped as "/".
+pub(crate) fn top_level(path: &str) -> String {
+    match path.split_once('/') {
+        Some((dir, _)) => format!("{}/", dir),
+        None => "/".to_string()
+    }
+}
+
+/// One row of the path heatmap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathActivity {
+    pub path: String,
+
+    /// How many open PRs touch this path.
+    pub open: usize,
+
+    /// How many merged PRs touched this path.
+    pub merged: usize,
+}
+
+/// Count how many PRs touch each top-level path.
+///
+/// Each argument holds the changed paths of one PR per entry. A PR touching several files in the
+/// same directory counts once for that directory. Rows are ordered busiest first.
+pub fn path_heatmap(open: &[Vec<String>], merged: &[Vec<String>]) -> Vec<PathActivity> {
+    fn count(prs: &[Vec<String>], counts: &mut BTreeMap<String, (usize, usize)>, open: bool) {
+        for paths in prs {
+            let dirs: BTreeSet<String> = paths.iter().map(|p| top_level(p)).collect();
+            for dir in dirs {
+                let entry = counts.entry(dir).or_default();
+                match open {
+                    true => entry.0 += 1,
+                    false => entry.1 += 1,
+                }
+            }
+        }
+    }
+
+    let mut counts = BTreeMap::new();
+    count(open, &mut counts, true);
+    count(merged, &mut counts, false);
+
+    let mut rows: Vec<PathActivity> = counts.into_iter()
+        .map(|(path, (open, merged))| PathActivity{ path, open, merged })
+        .collect();
+    rows.sort_by_key(|row| std::cmp::Reverse(row.open + row.merged));
+    rows
+}
+
+/// The median of some durations, or `None` if there aren't any.
+pub fn median(values: &[i64]) -> Option<i64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    match sorted.len() % 2 {
+        0 => Some((sorted[mid - 1] + sorted[mid]) / 2),
+        _ => Some(sorted[mid])
+    }
+}
+
+/// Count merges per week, returning (week start, count) pairs in chronological order.
+pub fn weekly_throughput(merged: &[MergedPr]) -> Vec<(i64, usize)> {
+    let mut weeks: Vec<(i64, usize)> = vec![];
+    let mut starts: Vec<i64> = merged.iter().map(|pr| week_start(pr.merged_at)).collect();
+    starts.sort_unstable();
+    for start in starts {
+        match weeks.last_mut() {
+            Some((week, count)) if *week == start => *count += 1,
+            _ => weeks.push((start, 1))
+        }
+    }
+    weeks
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognize_pr_merges() {
+        assert_eq!(parse_merge_subject("Merge branch 'hotfix/1234567'", "origin"),
+                   Some("hotfix".to_string()));
+        assert_eq!(parse_merge_subject("Merge branch 'a/b/c0ffee' into trunk", "origin"),
+                   Some("a/b".to_string()));
+        assert_eq!(parse_merge_subject("Merge remote-tracking branch 'origin/fix/abc'", "origin"),
+                   Some("fix".to_string()));
+        assert_eq!(parse_merge_subject("Merge branch 'not-a-pr'", "origin"), None);
+        assert_eq!(parse_merge_subject("Fix the thing", "origin"), None);
+    }
+
+    #[test]
+    fn open_prs_by_author_and_age() {
+        let pr = |author: &str, started_at| OpenPr{
+            name: "x".to_string(), author: author.to_string(), started_at
+        };
+        let open = [pr("Bob <b@x>", 100), pr("Alice <a@x>", 300), pr("Bob <b@x>", 200)];
+        assert_eq!(per_author(&open), vec![("Bob <b@x>".to_string(), 2),
+                                           ("Alice <a@x>".to_string(), 1)]);
+        assert_eq!(average_age(&open, 400), Some(200));
+        assert_eq!(average_age(&[], 400), None);
+    }
+
+    #[test]
+    fn heatmap_counts_each_pr_once_per_directory() {
+        let paths = |ps: &[&str]| ps.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let open = [paths(&["src/a.rs", "src/b.rs"]), paths(&["README.md"])];
+        let merged = [paths(&["src/a.rs", "tests/t.rs"])];
+
+        let heatmap = path_heatmap(&open, &merged);
+        assert_eq!(heatmap[0], PathActivity{ path: "src/".to_string(), open: 1, merged: 1 });
+        assert_eq!(heatmap.len(), 3);
+        assert!(heatmap.contains(&PathActivity{ path: "/".to_string(), open: 1, merged: 0 }));
+    }
+
+    #[test]
+    fn medians() {
+        assert_eq!(median(&[]), None);
+        assert_eq!(median(&[5, 1, 3]), Some(3));
+        assert_eq!(median(&[4, 1, 3, 2]), Some(2));
+    }
+
+    #[test]
+    fn throughput_groups_by_week() {
+        let pr = |merged_at| MergedPr{
+            name: "x".to_string(), base: "a".to_string(), tip: "b".to_string(),
+            started_at: 0, merged_at
+        };
+        let merged = [pr(1_622_635_200), pr(1_622_505_600), pr(1_623_110_400)];
+        assert_eq!(weekly_throughput(&merged), vec![(1_622_419_200, 2), (1_623_024_000, 1)]);
+    }
+}
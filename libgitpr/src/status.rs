@@ -0,0 +1,163 @@
+//! Where the current branch stands
+//!
+//! A [`Status`] answers the questions someone asks before doing anything else with a PR: which PR
+//! is checked out, how far it has drifted from trunk, whether everything has been pushed, and
+//! whether it has been merged yet. Trunk means the remote's trunk, as of the last fetch.
+//!
+//! It can be described to a person (see [`Status::describe`]) or turned into a [`Record`] for
+//! `--porcelain`, `--json`, and `--format` (see [`crate::render`]).
+use crate::parse;
+use crate::render::Record;
+use crate::{tr, Git, GitError};
+
+
+/// The fields of a status, for `--json` and `--format` (see [`crate::render`]).
+pub const FIELDS: &[(&str, &str)] = &[
+    ("branch", "the branch checked out, or nothing if HEAD is detached"),
+    ("name", "the PR's name, if the branch is a PR"),
+    ("trunk", "the remote's trunk, like \"origin/trunk\""),
+    ("ahead", "how many commits HEAD has which trunk doesn't"),
+    ("behind", "how many commits trunk has which HEAD doesn't"),
+    ("upstream", "the branch's upstream, like \"origin/hotfix/1234567\", if it has one"),
+    ("unpushed", "how many commits the branch has which its upstream doesn't"),
+    ("unpulled", "how many commits the upstream has which the branch doesn't"),
+    ("merged", "\"true\" if trunk contains HEAD, and \"false\" otherwise"),
+];
+
+
+/// Everything `git pr status` reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Status {
+    /// The branch checked out, like "hotfix/1234567", or `None` if HEAD is detached.
+    pub branch: Option<String>,
+
+    /// The PR's name, if the branch is a PR branch.
+    pub name: Option<String>,
+
+    /// The remote-tracking trunk everything is compared with, like "origin/trunk".
+    pub trunk: String,
+
+    /// Commits on HEAD but not trunk, and on trunk but not HEAD.
+    pub ahead: usize,
+    pub behind: usize,
+
+    /// The branch's upstream, like "origin/hotfix/1234567", if it has one.
+    pub upstream: Option<String>,
+
+    /// Commits on the branch but not its upstream, and on the upstream but not the branch.
+    pub unpushed: usize,
+    pub unpulled: usize,
+
+    /// Trunk contains everything on HEAD.
+    pub merged: bool,
+}
+
+impl Status {
+    /// Find out where HEAD stands relative to `trunk` on `remote`.
+    pub fn gather(git: &Git, remote: &str, trunk: &str) -> Result<Status,GitError> {
+        let trunk = format!("{}/{}", remote, trunk);
+        let branch = git.current_branch()?
+            .map(|branch| branch.strip_prefix("refs/heads/").unwrap_or(&branch).to_string());
+        let name = branch.as_deref().and_then(parse::pr_branch).map(|(name, _)| name.to_string());
+
+        let (ahead, behind) = match git.resolve_ref(&trunk)? {
+            Some(_) => git.divergence("HEAD", &trunk)?,
+            None => return Err(GitError::Refused(tr!("status-no-trunk", trunk = trunk))),
+        };
+        let upstream = match &branch {
+            Some(branch) => git.upstream(branch)?,
+            None => None,
+        };
+        let (unpushed, unpulled) = match &upstream {
+            Some(upstream) => git.divergence("HEAD", upstream)?,
+            None => (0, 0),
+        };
+
+        Ok(Status{ branch, name, trunk, ahead, behind, upstream, unpushed, unpulled,
+                   merged: ahead == 0 })
+    }
+
+    /// The status as lines for a person to read.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![match (&self.branch, &self.name) {
+            (Some(branch), Some(name)) => tr!("status-on-pr", name = name, branch = branch),
+            (Some(branch), None) => tr!("status-not-a-pr", branch = branch),
+            (None, _) => tr!("status-detached"),
+        }];
+        lines.push(tr!("status-trunk", ahead = self.ahead, behind = self.behind,
+                       trunk = self.trunk));
+        if self.branch.is_some() {
+            lines.push(match (&self.upstream, self.unpushed, self.unpulled) {
+                (None, _, _) => tr!("status-not-pushed"),
+                (Some(upstream), 0, 0) => tr!("status-pushed", upstream = upstream),
+                (Some(upstream), unpushed, unpulled) => tr!(
+                    "status-diverged", unpushed = unpushed, unpulled = unpulled,
+                    upstream = upstream
+                ),
+            });
+        }
+        lines.push(match self.merged {
+            true => tr!("status-merged", trunk = self.trunk),
+            false => tr!("status-unmerged", trunk = self.trunk),
+        });
+        lines
+    }
+
+    /// The status as a record, with the fields in [`FIELDS`].
+    pub fn record(&self) -> Record {
+        Record::new()
+            .with("branch", self.branch.clone())
+            .with("name", self.name.clone())
+            .with("trunk", self.trunk.as_str())
+            .with("ahead", self.ahead.to_string())
+            .with("behind", self.behind.to_string())
+            .with("upstream", self.upstream.clone())
+            .with("unpushed", self.unpushed.to_string())
+            .with("unpulled", self.unpulled.to_string())
+            .with("merged", self.merged.to_string())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Value;
+
+    fn status() -> Status {
+        Status{
+            branch: Some("fix/abc123".to_string()), name: Some("fix".to_string()),
+            trunk: "origin/trunk".to_string(), ahead: 2, behind: 1,
+            upstream: Some("origin/fix/abc123".to_string()), unpushed: 1, unpulled: 0,
+            merged: false,
+        }
+    }
+
+    #[test]
+    fn describe_a_pr_in_progress() {
+        assert_eq!(status().describe(), vec![
+            "On PR fix (branch fix/abc123)",
+            "2 commits ahead of origin/trunk, and 1 commits behind",
+            "1 commits not pushed to origin/fix/abc123, and 0 commits not pulled",
+            "Not merged into origin/trunk",
+        ]);
+
+        let detached = Status{ branch: None, name: None, upstream: None, ahead: 0, merged: true,
+                               ..status() };
+        assert_eq!(detached.describe(), vec![
+            "HEAD is detached",
+            "0 commits ahead of origin/trunk, and 1 commits behind",
+            "Merged into origin/trunk",
+        ]);
+    }
+
+    #[test]
+    fn records_have_every_field() {
+        let record = status().record();
+        for (field, _) in FIELDS {
+            assert_ne!(record.get(field), &Value::Absent, "{} is missing", field);
+        }
+        assert_eq!(record.get("unpushed"), &"1".into());
+        assert_eq!(record.get("merged"), &"false".into());
+    }
+}
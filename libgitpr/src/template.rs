@@ -0,0 +1,119 @@
+//! Output templates for scripts
+//!
+//! Programs that print records (pull requests, audit entries, and so on) accept `--format
+//! <template>`, so that scripts can ask for exactly the fields they need instead of parsing JSON. A
+//! template is ordinary text with placeholders in braces:
+//!
+//! ```console
+//! $ git pr list --format '{name}\t{author}\t{age}'
+//! hotfix    Your Name <you@example.com>    3 days ago
+//! ```
+//!
+//! Each kind of record documents its own placeholders, such as
+//! [`crate::pull_request::FIELDS`]. `\t` and `\n` stand for a tab and a newline, since shells
+//! don't make those easy to type, and `{{` and `}}` stand for literal braces.
+use crate::{tr, GitError};
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Text(String),
+    Field(String),
+}
+
+/// A parsed `--format` template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl Template {
+    /// Parse a template, refusing unbalanced braces and placeholders not listed in `fields`.
+    ///
+    /// `fields` pairs each placeholder's name with a description of what it stands for.
+    pub fn parse(text: &str, fields: &[(&str, &str)]) -> Result<Template,GitError> {
+        let mut pieces = vec![];
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('\\', Some('t')) => { chars.next(); literal.push('\t') },
+                ('\\', Some('n')) => { chars.next(); literal.push('\n') },
+                ('{', Some('{')) => { chars.next(); literal.push('{') },
+                ('}', Some('}')) => { chars.next(); literal.push('}') },
+                ('}', _) => return Err(GitError::Refused(tr!("template-unmatched-close"))),
+                ('{', _) => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => return Err(GitError::Refused(tr!("template-unmatched-open"))),
+                        }
+                    }
+                    if !fields.iter().any(|(name, _)| *name == field) {
+                        let known: Vec<&str> = fields.iter().map(|(name, _)| *name).collect();
+                        return Err(GitError::Refused(tr!(
+                            "template-unknown-placeholder",
+                            placeholder = format!("{{{}}}", field), known = known.join(", ")
+                        )));
+                    }
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Field(field));
+                },
+                (c, _) => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Text(literal));
+        }
+        Ok(Template{ pieces })
+    }
+
+    /// Does the template mention `field`?
+    pub fn uses(&self, field: &str) -> bool {
+        self.pieces.iter().any(|piece| *piece == Piece::Field(field.to_string()))
+    }
+
+    /// Fill in the template, asking `value` for each placeholder in turn.
+    pub fn render<F: FnMut(&str) -> String>(&self, mut value: F) -> String {
+        let mut text = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(literal) => text.push_str(literal),
+                Piece::Field(field) => text.push_str(&value(field)),
+            }
+        }
+        text
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: &[(&str, &str)] = &[("name", ""), ("branch", ""), ("author", ""), ("age", "")];
+
+    fn fill(template: &str) -> String {
+        Template::parse(template, FIELDS).unwrap().render(|field| field.to_uppercase())
+    }
+
+    #[test]
+    fn placeholders_and_escapes() {
+        assert_eq!(fill(r"{name}\t{author}\t{age}"), "NAME\tAUTHOR\tAGE");
+        assert_eq!(fill(r"{{{branch}}}\n"), "{BRANCH}\n");
+        assert_eq!(fill("plain text"), "plain text");
+        assert!(Template::parse("{name} {age}", FIELDS).unwrap().uses("age"));
+        assert!(!Template::parse("{name}", FIELDS).unwrap().uses("author"));
+    }
+
+    #[test]
+    fn reject_bad_templates() {
+        assert!(Template::parse("{nope}", FIELDS).is_err());
+        assert!(Template::parse("{name", FIELDS).is_err());
+        assert!(Template::parse("name}", FIELDS).is_err());
+    }
+}
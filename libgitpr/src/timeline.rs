@@ -0,0 +1,252 @@
+//! The story of a PR
+//!
+//! `git pr timeline` tells what happened to a PR in order, like the conversation on a forge's PR
+//! page: the commits it is made of, when they were rebased, and what was recorded about them as
+//! metadata (see [`crate::metadata`]): approvals, comments, claims, handoffs, assignments, labels,
+//! CI results, earlier revisions, and anything else.
+//!
+//! Times come from wherever they can be had. Commits have their author times, and a commit whose
+//! committer time is later was rewritten then, by a rebase or an amendment; commits rewritten at
+//! the same moment make a single event. Claims, releases, handoffs, assignments, labels, CI results
+//! and revisions carry their own times. Other metadata has none, so it is placed at the time of the
+//! commit it is attached to. Metadata is attached to whatever was the PR's tip when it was
+//! written, so that is when the PR looked the way it was written about.
+use crate::attachment::Attachment;
+use crate::ci;
+use crate::date;
+use crate::signing;
+use crate::tr;
+
+
+/// What sort of thing happened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// A commit was written.
+    Commit,
+
+    /// Commits were rebased or amended.
+    Rewrite,
+
+    /// Someone recorded metadata about the PR.
+    Metadata,
+}
+
+/// Something that happened to a PR, and when.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// Seconds since the Unix epoch.
+    pub time: i64,
+    pub kind: Kind,
+    pub text: String,
+}
+
+/// One of a PR's commits, as described by [`crate::Git::commit_history`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commit {
+    pub hash: String,
+    pub authored: i64,
+    pub committed: i64,
+    pub author: String,
+    pub subject: String,
+}
+
+/// Parse the output of [`crate::Git::commit_history`], skipping anything malformed.
+pub fn parse_commits(text: &str) -> Vec<Commit> {
+    text.lines().filter_map(|line| {
+        let mut fields = line.splitn(5, '\t');
+        Some(Commit{
+            hash: fields.next()?.to_string(),
+            authored: fields.next()?.parse().ok()?,
+            committed: fields.next()?.parse().ok()?,
+            author: fields.next()?.to_string(),
+            subject: fields.next().unwrap_or_default().to_string(),
+        })
+    }).collect()
+}
+
+// Describe a line of metadata, along with the time written in it, if it has one.
+fn describe(line: &str) -> (Option<i64>, String) {
+    let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let rest = match kind.starts_with("ci-") {
+        true => ci::split_reporter(line).0.split_once(' ').map_or("", |(_, rest)| rest),
+        false => rest,
+    };
+    let timed = || {
+        let (time, rest) = rest.split_once(' ')?;
+        Some((time.parse::<i64>().ok()?, rest))
+    };
+    match kind {
+        "approved-by" => {
+            (None, tr!("timeline-approved", who = signing::split_line(rest).0))
+        },
+        "comment" => (None, tr!("timeline-comment", text = rest)),
+        "attachment" => match Attachment::parse(line) {
+            Some(attachment) => (None, tr!("timeline-attached", attachment = attachment)),
+            None => (None, line.to_string()),
+        },
+        "claimed-by" => match rest.split_once(' ').and_then(|(since, rest)| {
+            Some((since.parse::<i64>().ok()?, rest.split_once(' ')?.1))
+        }) {
+            Some((since, who)) => (Some(since), tr!("timeline-claimed", who = who)),
+            None => (None, line.to_string()),
+        },
+        "released-by" => match timed() {
+            Some((at, who)) => (Some(at), tr!("timeline-released", who = who)),
+            None => (None, line.to_string()),
+        },
+        "owned-by" => match timed() {
+            Some((at, who)) => (Some(at), tr!("timeline-handed", who = who)),
+            None => (None, line.to_string()),
+        },
+        "assigned" => match timed() {
+            Some((at, who)) => (Some(at), tr!("timeline-assigned", who = who)),
+            None => (None, line.to_string()),
+        },
+        "unassigned" => match timed() {
+            Some((at, who)) => (Some(at), tr!("timeline-unassigned", who = who)),
+            None => (None, line.to_string()),
+        },
+        "ci-status" => match timed() {
+            Some((at, status)) => (Some(at), tr!("timeline-ci-status", status = status)),
+            None => (None, line.to_string()),
+        },
+        "ci-artifact" => match timed().and_then(|(at, rest)| Some((at, rest.split_once(' ')?))) {
+            Some((at, (name, url))) => {
+                (Some(at), tr!("timeline-ci-artifact", name = name, url = url))
+            },
+            None => (None, line.to_string()),
+        },
+        "labeled" => match timed() {
+            Some((at, label)) => (Some(at), tr!("timeline-labeled", label = label)),
+            None => (None, line.to_string()),
+        },
+        "unlabeled" => match timed() {
+            Some((at, label)) => (Some(at), tr!("timeline-unlabeled", label = label)),
+            None => (None, line.to_string()),
+        },
+        "revises" => match timed() {
+            Some((at, branch)) => (Some(at), tr!("timeline-revises", branch = branch)),
+            None => (None, line.to_string()),
+        },
+        _ => (None, line.to_string()),
+    }
+}
+
+/// Put together everything that happened to a PR made of `commits` (oldest first), given the
+/// metadata `notes` attached to each commit, by hash. Events come out in the order they happened.
+pub fn events(commits: &[Commit], notes: &[(String, Vec<String>)]) -> Vec<Event> {
+    let mut events = vec![];
+    let mut rewrites: Vec<(i64, usize)> = vec![];
+    for commit in commits {
+        let short: String = commit.hash.chars().take(7).collect();
+        events.push(Event{
+            time: commit.authored,
+            kind: Kind::Commit,
+            text: tr!("timeline-commit", subject = commit.subject, who = commit.author,
+                      commit = short),
+        });
+        if commit.committed > commit.authored {
+            match rewrites.iter_mut().find(|(time, _)| *time == commit.committed) {
+                Some((_, count)) => *count += 1,
+                None => rewrites.push((commit.committed, 1)),
+            }
+        }
+        let lines = notes.iter().filter(|(hash, _)| *hash == commit.hash).flat_map(|(_, l)| l);
+        for line in lines {
+            let (time, text) = describe(line);
+            events.push(Event{
+                time: time.unwrap_or(commit.committed), kind: Kind::Metadata, text
+            });
+        }
+    }
+    for (time, count) in rewrites {
+        let text = tr!("timeline-rewritten", count = count);
+        events.push(Event{ time, kind: Kind::Rewrite, text });
+    }
+
+    // Stable, so a commit stays ahead of what was written about it at the same moment
+    events.sort_by_key(|event| event.time);
+    events
+}
+
+/// Draw `events` as a graph, one line per event plus the lines joining them: commits and
+/// rewrites lie along the PR, and metadata branches off to one side of it. Times are shown
+/// relative to `now`.
+pub fn graph(events: &[Event], now: i64) -> Vec<String> {
+    let mut lines = vec![];
+    let mut aside = false;
+    for event in events {
+        let when = date::relative(now - event.time);
+        match (event.kind, aside) {
+            (Kind::Metadata, false) => lines.push("|\\".to_string()),
+            (Kind::Commit | Kind::Rewrite, true) => lines.push("|/".to_string()),
+            _ => (),
+        }
+        aside = event.kind == Kind::Metadata;
+        let node = match event.kind {
+            Kind::Commit => "*  ",
+            Kind::Rewrite => "=  ",
+            Kind::Metadata => "| o",
+        };
+        lines.push(format!("{} {:<15} {}", node, when, event.text));
+    }
+    lines
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(hash: &str, authored: i64, committed: i64, subject: &str) -> Commit {
+        Commit{
+            hash: hash.to_string(), authored, committed,
+            author: "Alice <alice@example.com>".to_string(), subject: subject.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_history() {
+        let commits = parse_commits("abc\t100\t200\tAlice <alice@example.com>\tFix\tit\nbroken\n");
+        assert_eq!(commits, vec![commit("abc", 100, 200, "Fix\tit")]);
+    }
+
+    #[test]
+    fn events_in_order() {
+        let commits = vec![commit("aaa", 100, 5_000, "First"), commit("bbb", 200, 5_000, "Second")];
+        let notes = vec![
+            ("aaa".to_string(), vec!["approved-by Bob".to_string()]),
+            ("bbb".to_string(), vec!["claimed-by 300 400 Carol".to_string(),
+                                     "comment Looks good".to_string()]),
+        ];
+        let events: Vec<(i64, Kind)> = events(&commits, &notes).iter()
+            .map(|event| (event.time, event.kind))
+            .collect();
+        assert_eq!(events, [(100, Kind::Commit), (200, Kind::Commit), (300, Kind::Metadata),
+                            (5_000, Kind::Metadata), (5_000, Kind::Metadata),
+                            (5_000, Kind::Rewrite)]);
+    }
+
+    #[test]
+    fn signed_metadata() {
+        let by = "CI Bot <ci@example.com>";
+        assert_eq!(describe(&format!("ci-status 100 pass by {} signed:00ff", by)),
+                   (Some(100), tr!("timeline-ci-status", status = "pass")));
+        assert_eq!(describe(&format!("ci-artifact 100 log https://ci.example.com by {}", by)),
+                   (Some(100), tr!("timeline-ci-artifact", name = "log",
+                                   url = "https://ci.example.com")));
+        assert_eq!(describe("approved-by Bob <b@example.com> signed:00ff"),
+                   (None, tr!("timeline-approved", who = "Bob <b@example.com>")));
+        assert_eq!(describe("ci-status 100 fail"),
+                   (Some(100), tr!("timeline-ci-status", status = "fail")));
+    }
+
+    #[test]
+    fn metadata_branches_off() {
+        let event = |time, kind| Event{ time, kind, text: "x".to_string() };
+        let drawn = graph(&[event(0, Kind::Commit), event(0, Kind::Metadata),
+                            event(0, Kind::Metadata), event(0, Kind::Commit)], 30);
+        let nodes: Vec<&str> = drawn.iter().map(|line| line.get(..3).unwrap_or(line)).collect();
+        assert_eq!(nodes, ["*  ", "|\\", "| o", "| o", "|/", "*  "]);
+    }
+}
@@ -0,0 +1,345 @@
+//! Browsing PRs in the terminal
+//!
+//! `git pr ui` fills the terminal with two panes: the open PRs at the top, and the selected PR's
+//! commits and diff below. The arrow keys (or `j` and `k`) move through the PRs; Page Up and Page
+//! Down (or `J` and `K`, a line at a time) scroll the PR's details. `c` checks the PR out, `m`
+//! merges it, and `a` abandons it, after asking; `r` fetches again, and `q` or Escape leaves. In
+//! between, the caller may keep the list up to date (see [`run`]).
+//!
+//! A [`Screen`] holds what is shown and decides what each key does, with no terminal involved, so
+//! that it can be tested; [`run`] puts it on the terminal, and leaves carrying out the [`Action`]s
+//! to the caller. Like the picker (see [`crate::picker`]), it is drawn on stderr.
+use crate::pull_request::PullRequest;
+use crate::{tr, GitError};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, queue, style::Print, terminal};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::Duration;
+
+
+/// Something the person asked for. [`run`] leaves on `Quit`, and has the caller carry out the
+/// rest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Checkout,
+    Merge,
+    Abandon,
+
+    /// Fetch again, and show what there is now.
+    Refresh,
+    Quit,
+}
+
+
+/// What the terminal shows, and where the person is in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Screen {
+    prs: Vec<PullRequest>,
+    selected: usize,
+
+    // The first PR shown, once there are more than fit
+    top: usize,
+    detail: Vec<String>,
+    scroll: usize,
+    status: String,
+
+    // A merge or abandon waiting to be confirmed
+    pending: Option<Action>,
+}
+
+impl Screen {
+    /// A screen listing `prs`, with the first one selected.
+    pub fn new(prs: Vec<PullRequest>) -> Screen {
+        Screen{ prs, selected: 0, top: 0, detail: vec![], scroll: 0, status: String::new(),
+                pending: None }
+    }
+
+    /// The PR the keys act on, if there are any PRs.
+    pub fn selected(&self) -> Option<&PullRequest> {
+        self.prs.get(self.selected)
+    }
+
+    /// Show `prs` instead, keeping the same PR selected if it is still there.
+    pub fn set_prs(&mut self, prs: Vec<PullRequest>) {
+        let branch = self.selected().map(|pr| pr.branch.clone());
+        self.selected = prs.iter().position(|pr| Some(&pr.branch) == branch.as_ref())
+            .unwrap_or(self.selected)
+            .min(prs.len().saturating_sub(1));
+        self.prs = prs;
+    }
+
+    /// Show `text` as the selected PR's details, from the top.
+    pub fn set_detail(&mut self, text: &str) {
+        // Tabs are as wide as the terminal likes, which would throw out our idea of line widths
+        self.detail = text.lines().map(|line| line.replace('\t', "    ")).collect();
+        self.scroll = 0;
+    }
+
+    /// Show `status` on the bottom line.
+    pub fn set_status(&mut self, status: String) {
+        self.status = status;
+    }
+
+    // How many rows of the terminal's `height` go to the PRs, and how many to the details.
+    fn panes(height: usize) -> (usize, usize) {
+        let list = (height / 3).max(3);
+        (list, height.saturating_sub(list + 2))
+    }
+
+    /// Act on a key pressed in a terminal `height` rows high, returning what else to do, if
+    /// anything.
+    pub fn key(&mut self, key: KeyEvent, height: usize) -> Option<Action> {
+        let (list, page) = Screen::panes(height);
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if let Some(action) = self.pending.take() {
+            self.status = match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => return Some(action),
+                _ => tr!("ui-cancelled"),
+            };
+            return None;
+        }
+
+        let before = self.selected;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Action::Quit),
+            KeyCode::Char('c') if ctrl => return Some(Action::Quit),
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.prs.len().saturating_sub(1));
+            },
+            KeyCode::Char('K') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Char('J') => self.scroll += 1,
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(page.max(1)),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll += page.max(1),
+            KeyCode::Char('r') => return Some(Action::Refresh),
+            KeyCode::Char(c @ ('c' | 'm' | 'a')) => {
+                let pr = self.selected()?;
+                match c {
+                    'c' => return Some(Action::Checkout),
+                    'm' => {
+                        self.status = tr!("ui-confirm-merge", branch = pr.branch);
+                        self.pending = Some(Action::Merge);
+                    },
+                    _ => {
+                        self.status = tr!("ui-confirm-abandon", branch = pr.branch);
+                        self.pending = Some(Action::Abandon);
+                    },
+                }
+            },
+            _ => {},
+        }
+        self.scroll = self.scroll.min(self.detail.len().saturating_sub(1));
+        if self.selected != before {
+            self.status.clear();
+        }
+        // Keep the selection in view
+        self.top = self.top.min(self.selected).max((self.selected + 1).saturating_sub(list));
+        None
+    }
+
+    /// The lines to show on a terminal `width` columns wide and `height` rows high.
+    pub fn lines(&self, width: usize, height: usize) -> Vec<String> {
+        let (list, page) = Screen::panes(height);
+        let fit = |line: String| line.chars().take(width.saturating_sub(1)).collect::<String>();
+        let mut lines = vec![];
+        for row in 0..list {
+            let line = match self.prs.get(self.top + row) {
+                Some(pr) => {
+                    let marker = if self.top + row == self.selected { '>' } else { ' ' };
+                    format!("{} {}", marker, pr.branch)
+                },
+                None if row == 0 && self.prs.is_empty() => tr!("ui-no-prs"),
+                None => String::new(),
+            };
+            lines.push(fit(line));
+        }
+        lines.push(fit(format!("-- {}/{} -- {}", (self.selected + 1).min(self.prs.len()),
+                               self.prs.len(), tr!("ui-help"))));
+        for row in 0..page {
+            lines.push(fit(self.detail.get(self.scroll + row).cloned().unwrap_or_default()));
+        }
+        lines.push(fit(self.status.clone()));
+        lines.truncate(height);
+        lines
+    }
+}
+
+
+// Leaves the alternate screen and raw mode however the interface exits.
+struct Terminal;
+
+impl Terminal {
+    fn enter() -> io::Result<Terminal> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stderr(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stderr(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn draw(out: &mut impl Write, screen: &Screen, width: usize, height: usize) -> io::Result<()> {
+    queue!(out, terminal::Clear(terminal::ClearType::All))?;
+    for (row, line) in screen.lines(width, height).into_iter().enumerate() {
+        queue!(out, cursor::MoveTo(0, row as u16), Print(line))?;
+    }
+    out.flush()
+}
+
+/// Show `screen` on the terminal until the person leaves.
+///
+/// `detail` describes a PR for the lower pane; it is asked once for each tip shown. `act` carries
+/// out an [`Action`] on the selected PR, if there is one, returning what to say about it. The
+/// terminal is given back while it runs, so that anything it prints is seen; if it fails, the
+/// error is shown on the status line instead. While no key is being pressed, `refresh` is asked
+/// every so often for the PRs to show, if they have changed.
+pub fn run<D, A, R>(screen: &mut Screen, mut detail: D, mut act: A, mut refresh: R)
+    -> Result<(),GitError>
+    where D: FnMut(&PullRequest) -> Result<String,GitError>,
+          A: FnMut(Action, Option<&PullRequest>) -> Result<String,GitError>,
+          R: FnMut() -> Result<Option<Vec<PullRequest>>,GitError> {
+    let mut details: HashMap<String, String> = HashMap::new();
+    let mut shown: Option<String> = None;
+    let mut terminal = Some(Terminal::enter()?);
+    let mut redraw = true;
+    loop {
+        if terminal.is_none() {
+            terminal = Some(Terminal::enter()?);
+        }
+        let tip = screen.selected().map(|pr| pr.tip.clone());
+        if tip != shown {
+            let text = match screen.selected() {
+                Some(pr) if !details.contains_key(&pr.tip) => {
+                    let text = detail(pr).unwrap_or_else(|e| e.to_string());
+                    details.insert(pr.tip.clone(), text.clone());
+                    text
+                },
+                Some(pr) => details[&pr.tip].clone(),
+                None => String::new(),
+            };
+            screen.set_detail(&text);
+            shown = tip;
+            redraw = true;
+        }
+        // Some terminals don't report their size; assume the traditional one for those
+        let (width, height) = match terminal::size() {
+            Ok((columns, rows)) if columns > 0 && rows > 0 => (columns as usize, rows as usize),
+            _ => (80, 24),
+        };
+        if redraw {
+            draw(&mut io::stderr(), screen, width, height)?;
+            redraw = false;
+        }
+
+        if !event::poll(Duration::from_millis(100))? {
+            match refresh() {
+                Ok(None) => {},
+                Ok(Some(prs)) => {
+                    screen.set_prs(prs);
+                    redraw = true;
+                },
+                Err(e) => {
+                    screen.set_status(e.to_string());
+                    redraw = true;
+                },
+            }
+            continue;
+        }
+        redraw = true;
+        let key = match event::read()? {
+            Event::Key(key @ KeyEvent{ kind: KeyEventKind::Press, .. }) => key,
+            _ => continue,
+        };
+        let action = match screen.key(key, height) {
+            Some(Action::Quit) => break,
+            Some(action) => action,
+            None => continue,
+        };
+        terminal = None;
+        match act(action, screen.selected()) {
+            Ok(status) => screen.set_status(status),
+            Err(e) => screen.set_status(e.to_string()),
+        }
+        if action == Action::Refresh {
+            details.clear();
+            shown = None;
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prs(names: &[&str]) -> Vec<PullRequest> {
+        names.iter().enumerate().map(|(i, name)| PullRequest{
+            name: name.to_string(),
+            branch: format!("{}/{:07}", name, i),
+            tip: format!("{:040}", i),
+        }).collect()
+    }
+
+    fn press(screen: &mut Screen, c: char) -> Option<Action> {
+        screen.key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE), 12)
+    }
+
+    #[test]
+    fn moving_and_scrolling() {
+        let mut screen = Screen::new(prs(&["a", "b", "c", "d", "e"]));
+        assert_eq!(press(&mut screen, 'k'), None);
+        assert_eq!(screen.selected().map(|pr| pr.name.as_str()), Some("a"));
+        for _ in 0..10 {
+            press(&mut screen, 'j');
+        }
+        assert_eq!(screen.selected().map(|pr| pr.name.as_str()), Some("e"));
+
+        // Four rows for PRs, so the last four are shown
+        let lines = screen.lines(20, 12);
+        assert_eq!(lines.len(), 12);
+        assert_eq!(lines[0], "  b/0000001");
+        assert_eq!(lines[3], "> e/0000004");
+        assert!(lines[4].starts_with("-- 5/5 --"));
+
+        screen.set_detail("one\ntwo\tthree\nfour");
+        assert_eq!(screen.lines(20, 12)[5], "one");
+        press(&mut screen, 'J');
+        assert_eq!(screen.lines(20, 12)[5], "two    three");
+        press(&mut screen, ' ');
+        assert_eq!(screen.lines(20, 12)[5], "four");
+        assert_eq!(press(&mut screen, 'q'), Some(Action::Quit));
+    }
+
+    #[test]
+    fn changes_are_confirmed() {
+        let mut screen = Screen::new(prs(&["a", "b"]));
+        assert_eq!(press(&mut screen, 'c'), Some(Action::Checkout));
+        assert_eq!(press(&mut screen, 'm'), None);
+        assert_eq!(press(&mut screen, 'y'), Some(Action::Merge));
+        assert_eq!(press(&mut screen, 'a'), None);
+        assert_eq!(press(&mut screen, 'n'), None);
+        assert_eq!(press(&mut screen, 'y'), None);
+
+        let mut screen = Screen::new(vec![]);
+        assert_eq!(press(&mut screen, 'm'), None);
+        assert_eq!(press(&mut screen, 'y'), None);
+        assert_eq!(screen.lines(40, 12)[0], tr!("ui-no-prs"));
+    }
+
+    #[test]
+    fn selection_follows_the_pr() {
+        let mut screen = Screen::new(prs(&["a", "b", "c"]));
+        press(&mut screen, 'j');
+        screen.set_prs(prs(&["a", "b", "c"])[1..].to_vec());
+        assert_eq!(screen.selected().map(|pr| pr.name.as_str()), Some("b"));
+        screen.set_prs(prs(&["a"]));
+        assert_eq!(screen.selected().map(|pr| pr.name.as_str()), Some("a"));
+    }
+}
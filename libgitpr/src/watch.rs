@@ -0,0 +1,107 @@
+//! Notice when refs change
+//!
+//! Long-running modes such as `git pr-daemon` want to keep their picture of the open PRs current
+//! without re-running `git branch -a` on every request. Since a PR is just a branch, the set of PRs
+//! can only change when a ref changes, so we ask the operating system to tell us when anything
+//! under `.git/refs` (or the `packed-refs` file) is modified.
+use crate::{Git, GitError};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::io;
+use std::path::Path;
+
+
+/// Keeps a filesystem watch on a repository's refs for as long as it is alive.
+pub struct RefWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+fn watch_error(e: notify::Error) -> GitError {
+    GitError::Io(io::Error::other(e))
+}
+
+/// What changed, as far as refs are concerned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RefChange {
+    /// A single loose ref, like `refs/remotes/origin/hotfix/0`, was created, moved or deleted.
+    Ref(String),
+
+    /// The packed-refs file changed, or a directory of refs appeared, so any number of refs may
+    /// have changed.
+    All,
+}
+
+/// Work out which ref (if any) a change to `path` in `common_dir` corresponds to.
+///
+/// Git updates refs by writing a `.lock` file and renaming it into place, so we ignore the lock
+/// files themselves and wait for the rename. A new directory is only watched once we hear about it,
+/// by which time git may already have written refs into it, so it counts as everything changing.
+pub(crate) fn ref_change(common_dir: &Path, path: &Path) -> Option<RefChange> {
+    if path.extension().is_some_and(|e| e == "lock") {
+        return None;
+    }
+    if path == common_dir.join("packed-refs") {
+        return Some(RefChange::All);
+    }
+
+    let relative = path.strip_prefix(common_dir).ok()?;
+    if !relative.starts_with("refs") {
+        return None;
+    }
+    if path.is_dir() {
+        return Some(RefChange::All);
+    }
+    let components: Vec<_> = relative.iter().map(|c| c.to_string_lossy()).collect();
+    Some(RefChange::Ref(components.join("/")))
+}
+
+impl RefWatcher {
+    /// Call `on_change` (from a background thread) whenever a ref in this repository changes.
+    ///
+    /// Refs are shared between worktrees, so we watch the common git directory.
+    pub fn start<F>(git: &Git, on_change: F) -> Result<RefWatcher,GitError>
+    where F: Fn(RefChange) + Send + 'static {
+        let common_dir = git.git_common_dir()?.canonicalize()?;
+        let watched = common_dir.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                for change in event.paths.iter().filter_map(|p| ref_change(&watched, p)) {
+                    on_change(change);
+                }
+            }
+        }).map_err(watch_error)?;
+
+        // packed-refs may not exist yet, so we watch the directory that will hold it and filter
+        // the events ourselves.
+        watcher.watch(&common_dir.join("refs"), RecursiveMode::Recursive).map_err(watch_error)?;
+        watcher.watch(&common_dir, RecursiveMode::NonRecursive).map_err(watch_error)?;
+
+        Ok(RefWatcher{ _watcher: watcher })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_refs_count() {
+        let git_dir = Path::new("/repo/.git");
+        assert_eq!(
+            ref_change(git_dir, Path::new("/repo/.git/refs/remotes/origin/fix/abc123")),
+            Some(RefChange::Ref("refs/remotes/origin/fix/abc123".to_string()))
+        );
+        assert_eq!(ref_change(git_dir, Path::new("/repo/.git/packed-refs")), Some(RefChange::All));
+        assert_eq!(ref_change(git_dir, Path::new("/repo/.git/refs/heads/trunk.lock")), None);
+        assert_eq!(ref_change(git_dir, Path::new("/repo/.git/index")), None);
+        assert_eq!(ref_change(git_dir, Path::new("/repo/.git/git-pr/clean.cursor")), None);
+    }
+
+    #[test]
+    fn new_directories_may_hold_anything() {
+        let git_dir = tempdir::TempDir::new("git-pr-watch").unwrap();
+        let remotes = git_dir.path().join("refs").join("remotes");
+        std::fs::create_dir_all(&remotes).unwrap();
+        assert_eq!(ref_change(git_dir.path(), &remotes), Some(RefChange::All));
+    }
+}
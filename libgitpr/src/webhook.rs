@@ -0,0 +1,52 @@
+//! Telling other systems what happened
+//!
+//! Teams which want to hear about some events (in chat, say, or a dashboard) can point git-pr at a
+//! URL which accepts a JSON POST for each one:
+//!
+//! ```console
+//! $ git config pr.webhookUrl https://hooks.example.com/git-pr
+//! ```
+//!
+//! Each event is an object with an `event` field naming what happened, `"handoff"` or
+//! `"emergency-merge"`, and further fields depending on the event. It is only built with the
+//! `webhook` feature, since it needs an HTTP client.
+use crate::{Git, GitError};
+use serde_json::{Map, Value};
+use std::io;
+
+
+/// An event called `kind`, with `fields` as `(name, value)` pairs.
+pub fn event(kind: &str, fields: &[(&str, &str)]) -> Value {
+    let mut object = Map::new();
+    object.insert("event".to_string(), Value::from(kind));
+    for (name, value) in fields {
+        object.insert(name.to_string(), Value::from(*value));
+    }
+    Value::Object(object)
+}
+
+/// Send `event` to `pr.webhookUrl`, returning false if no webhook is configured.
+pub fn notify(git: &Git, event: &Value) -> Result<bool,GitError> {
+    let url = match git.config_get("pr.webhookUrl")? {
+        Some(url) => url,
+        None => return Ok(false),
+    };
+    ureq::post(&url)
+        .set("Content-Type", "application/json")
+        .send_string(&event.to_string())
+        .map_err(|e| GitError::Io(io::Error::other(e.to_string())))?;
+    Ok(true)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_flat_objects() {
+        let event = event("handoff", &[("branch", "fix/abc123"), ("to", "Bob <b@example.com>")]);
+        assert_eq!(event.to_string(),
+                   r#"{"branch":"fix/abc123","event":"handoff","to":"Bob <b@example.com>"}"#);
+    }
+}
@@ -0,0 +1,74 @@
+//! Test the git "client" wrapper against the mock gits in src/bin, which answer with canned
+//! output instead of touching a repository.
+use libgitpr::Git;
+
+
+fn mock(program: &str) -> Git {
+    Git{ program: program.to_string(), working_dir: Box::new("."), git_dir: None, work_tree: None }
+}
+
+
+// Verify that we out Git "client" can query the underlying git for its version info. The
+// `fake_git` program (defined in src/bin/fake_git.rs) will respond with a known string if
+// invoked with the "--version" argument.
+#[test]
+fn query_version_info() {
+    let fake_git = mock(env!("CARGO_BIN_EXE_fake_git"));
+    let version = fake_git.version().unwrap();
+    assert!(version.starts_with("fake_git version 1"));
+}
+
+
+// Test how we handle failure when invoking git.
+//
+// In any reasonable scenario, `git --version` will not fail. We check this path to validate
+// the error handling pattern used in `Git::version` so that we might use this pattern
+// elsewhere.
+#[test]
+#[should_panic]
+fn query_version_failure() {
+    let failing_git = mock(env!("CARGO_BIN_EXE_failing_git"));
+    failing_git.version().unwrap();
+}
+
+
+#[test]
+fn can_detect_merged_branches() {
+    let fake_git = mock(env!("CARGO_BIN_EXE_fake_git"));
+    let merged_branches = fake_git.merged_branches("trunk").unwrap();
+    assert!(merged_branches.contains("already-been-merged"));
+}
+
+
+#[test]
+fn can_issue_delete_statement() {
+    let fake_git = mock(env!("CARGO_BIN_EXE_fake_git"));
+    fake_git.delete_branch("already-been-merged").unwrap();
+}
+
+
+// fake_git always claims to be a complete clone.
+#[test]
+fn detect_shallow_clone() {
+    let fake_git = mock(env!("CARGO_BIN_EXE_fake_git"));
+    assert!(!fake_git.is_shallow().unwrap());
+}
+
+
+// fake_git returns a constant, known hash, so we check for that.
+#[test]
+fn get_hash_of_current_commit() {
+    let fake_git = mock(env!("CARGO_BIN_EXE_fake_git"));
+    let hash = fake_git.rev_parse_head().unwrap();
+    assert_eq!(hash, "1234567");
+}
+
+
+// We call `create_branch` to ensure it doesn't throw an error, but we don't have enough
+// tooling in `fake_git` to warrant checking for a change in state afterwards -- this is more
+// appropriate for an integration test with real git.
+#[test]
+fn create_new_branch() {
+    let fake_git = mock(env!("CARGO_BIN_EXE_fake_git"));
+    fake_git.create_branch("hotfix").unwrap();
+}
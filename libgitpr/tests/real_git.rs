@@ -0,0 +1,529 @@
+//! Test the git "client" wrapper against the real git binary.
+use libgitpr::Git;
+use libgitpr::config::{Config, Source};
+use libgitpr::cursor::Cursor;
+use libgitpr::partial;
+use libgitpr::pull_request::PrIndex;
+use libgitpr::signing;
+use libgitpr::stats;
+use libgitpr::identity::{self, Identity};
+use libgitpr::metadata;
+use std::process::Command;
+use std::process::Stdio;
+use tempdir::TempDir;
+
+// Implementing this above produces a warning, since the function is (by design) never used by
+// other application code. Since it is only used in this module, we implement this function
+// local to this module, thus eliminating the dead code warning.
+fn temp_repo() -> Git {
+    let working_dir = Box::new(TempDir::new("git-pr").unwrap());
+
+    // git init in new unique dir
+    let status = Command::new("git")
+        .stdout(Stdio::null())
+        .arg("-C").arg(working_dir.as_ref().as_ref())
+        .args(["init"]).status().unwrap();
+    assert!(status.success());
+
+    // Setup git config for email
+    let status = Command::new("git")
+        .arg("-C").arg(working_dir.as_ref().as_ref())
+        .args(["config","user.email","you@example.com"]).status().unwrap();
+    assert!(status.success());
+
+    // Setup git config for name
+    let status = Command::new("git")
+        .arg("-C").arg(working_dir.as_ref().as_ref())
+        .args(["config","user.name","Your Name"]).status().unwrap();
+    assert!(status.success());
+
+    // create trunk branch
+    let status = Command::new("git")
+        .arg("-C").arg(working_dir.as_ref().as_ref())
+        .args(["checkout","-b","trunk"]).status().unwrap();
+    assert!(status.success());
+
+    // empty commit to actually create trunk branch
+    let status = Command::new("git")
+        .arg("-C").arg(working_dir.as_ref().as_ref())
+        .args(["commit","--allow-empty","-m","hello"]).status().unwrap();
+    assert!(status.success());
+
+    // create a fake branch to test deletion
+    let status = Command::new("git")
+        .arg("-C").arg(working_dir.as_ref().as_ref())
+        .args(["branch","hotfix"]).status().unwrap();
+    assert!(status.success());
+
+    Git{ program: "git".to_string(), working_dir, git_dir: None, work_tree: None }
+}
+
+
+#[test]
+fn version() {
+    let git = temp_repo();
+    let version = git.version().unwrap();
+    assert!(version.starts_with("git version 2"));
+}
+
+#[test]
+fn fetch_and_prune() {
+    let git = temp_repo();
+    git.fetch_prune().unwrap();
+}
+
+#[test]
+fn can_list_all_branches() {
+    let git = temp_repo();
+    let branches = git.all_branches().unwrap();
+    assert!(branches.contains("trunk"));
+}
+
+// Cleaning PRs requires that we identify "old" branches (those which have been merged into trunk),
+// and that we delete those branches. Because the tests run in parallel, we need to ensure that our
+// check for the existence of the "hotfix" branch always happens *before* our attempt to delete the
+// "hotfix" branch. So this test case exercises all the git client functionality we would need in
+// order to implement the "pr-clean" subcommand.
+#[test]
+fn could_clean() {
+    let git = temp_repo();
+    let branches = git.merged_branches("trunk").unwrap();
+    assert!(branches.contains("hotfix"));
+
+    git.delete_branch("hotfix").unwrap();
+    let branches = git.all_branches().unwrap();
+    assert!(!branches.contains("hotfix"));
+}
+
+#[test]
+fn can_get_hash_of_head() {
+    // The hash will change every time, but this is one of the few git commands for which we can
+    // know the exact length of the output. Weak, but best we can do until we add more capabilities
+    // to the client.
+    let git = temp_repo();
+    let hash = git.rev_parse_head().unwrap();
+    assert_eq!(hash.len(), 7);
+}
+
+#[test]
+fn can_create_new_branch() {
+    // Show that we can create a new branch in this repo, and verify its existence by querying the
+    // list of branches and showing that this new branch is among them.
+    let git = temp_repo();
+    git.create_branch("knurt").unwrap();
+    let branches = git.all_branches().unwrap();
+    assert!(branches.contains("knurt"));
+}
+
+// Fast-forwarding moves a branch whether or not it is checked out, but never past commits of its
+// own, and never underneath another worktree.
+#[test]
+fn fast_forward_branches() {
+    let git = temp_repo();
+    let dir = git.working_dir.as_ref().as_ref();
+    let run = |args: &[&str]| assert!(Command::new("git").arg("-C").arg(dir).args(args)
+        .stdout(Stdio::null()).stderr(Stdio::null()).status().unwrap().success());
+    let head = |refname: &str| git.resolve_ref(refname).unwrap().unwrap();
+
+    let start = head("trunk");
+    std::fs::write(dir.join("new.txt"), "new").unwrap();
+    run(&["add","new.txt"]);
+    run(&["commit","--quiet","-m","New"]);
+    let next = head("trunk");
+
+    // Not checked out, then missing altogether
+    assert!(git.fast_forward("hotfix", &next).unwrap());
+    assert_eq!(head("hotfix"), next);
+    assert!(git.fast_forward("fresh", &next).unwrap());
+    assert_eq!(head("fresh"), next);
+
+    // Checked out here: the working tree follows
+    run(&["checkout","--quiet","-b","behind",&start]);
+    assert!(git.fast_forward("behind", &next).unwrap());
+    assert!(dir.join("new.txt").exists());
+
+    // Diverged
+    run(&["commit","--quiet","--allow-empty","-m","Mine"]);
+    let mine = head("behind");
+    assert!(!git.fast_forward("behind", &start).unwrap());
+    assert_eq!(head("behind"), mine);
+
+    // Checked out in another worktree
+    let other = TempDir::new("git-pr-worktree").unwrap();
+    run(&["branch","elsewhere",&start]);
+    run(&["worktree","add","--quiet",other.path().join("w").to_str().unwrap(),"elsewhere"]);
+    assert!(matches!(git.fast_forward("elsewhere", &next),
+                     Err(libgitpr::GitError::Refused(_))));
+    assert_eq!(head("elsewhere"), start);
+}
+
+#[test]
+fn bot_identities_must_be_configured() {
+    let git = temp_repo();
+    let me = Identity::resolve(&git, None).unwrap();
+    assert_eq!(me.to_string(), "Your Name <you@example.com>");
+    assert!(!me.bot);
+
+    // Nobody may claim to be a bot until the repo says that bot exists
+    assert!(Identity::resolve(&git, Some("CI Bot")).is_err());
+
+    let status = Command::new("git")
+        .arg("-C").arg(git.working_dir.as_ref().as_ref())
+        .args(["config","--add","pr.botIdentity","CI Bot <ci@example.com>"]).status().unwrap();
+    assert!(status.success());
+
+    let bot = Identity::resolve(&git, Some("CI Bot")).unwrap();
+    assert_eq!(bot.email, "ci@example.com");
+    assert!(bot.bot);
+}
+
+// Cursors are stored inside the repository's .git directory, and must survive between runs.
+#[test]
+fn cursor_remembers_position() {
+    let git = temp_repo();
+    let cursor = Cursor::open(&git, "clean").unwrap();
+    assert_eq!(cursor.position(), None);
+
+    cursor.advance("hotfix").unwrap();
+    let cursor = Cursor::open(&git, "clean").unwrap();
+    assert_eq!(cursor.position().as_deref(), Some("hotfix"));
+
+    cursor.finish().unwrap();
+    assert_eq!(cursor.position(), None);
+}
+
+// Make a shallow clone of a repo with two commits, and show that we can detect (and fix) the
+// missing history.
+#[test]
+fn detect_and_repair_shallow_clone() {
+    let origin = temp_repo();
+    assert!(!origin.is_shallow().unwrap());
+
+    let status = Command::new("git")
+        .arg("-C").arg(origin.working_dir.as_ref().as_ref())
+        .args(["commit","--allow-empty","-m","second"]).status().unwrap();
+    assert!(status.success());
+
+    let clone_dir = TempDir::new("git-pr-shallow").unwrap();
+    let url = format!("file://{}", origin.working_dir.as_ref().as_ref().display());
+    let status = Command::new("git")
+        .args(["clone","--quiet","--depth","1",&url]).arg(clone_dir.path())
+        .status().unwrap();
+    assert!(status.success());
+
+    let clone = Git{
+        program: "git".to_string(), working_dir: Box::new(clone_dir),
+        git_dir: None, work_tree: None,
+    };
+    assert!(clone.is_shallow().unwrap());
+
+    clone.unshallow().unwrap();
+    assert!(!clone.is_shallow().unwrap());
+}
+
+// Blobless clones must be able to fetch everything a range needs in one go.
+#[test]
+fn prefetch_blobs_in_partial_clone() {
+    let origin = temp_repo();
+    assert!(partial::promisor_remotes(&origin).unwrap().is_empty());
+
+    let dir = origin.working_dir.as_ref().as_ref();
+    std::fs::write(dir.join("README"), "hello\n").unwrap();
+    for args in [
+        vec!["add","README"],
+        vec!["commit","-m","add a blob"],
+        vec!["config","uploadpack.allowFilter","true"],
+        vec!["config","uploadpack.allowAnySHA1InWant","true"],
+    ] {
+        let status = Command::new("git").arg("-C").arg(dir).args(args).status().unwrap();
+        assert!(status.success());
+    }
+
+    let clone_dir = TempDir::new("git-pr-partial").unwrap();
+    let url = format!("file://{}", dir.display());
+    let status = Command::new("git")
+        .args(["clone","--quiet","--no-checkout","--filter=blob:none",&url]).arg(clone_dir.path())
+        .status().unwrap();
+    assert!(status.success());
+
+    let clone = Git{
+        program: "git".to_string(), working_dir: Box::new(clone_dir),
+        git_dir: None, work_tree: None,
+    };
+    assert_eq!(partial::promisor_remotes(&clone).unwrap(), vec!["origin"]);
+    assert_eq!(clone.missing_objects("HEAD").unwrap().len(), 1);
+
+    assert_eq!(partial::prefetch(&clone, "HEAD").unwrap(), 1);
+    assert!(clone.missing_objects("HEAD").unwrap().is_empty());
+}
+
+// No matter how deep in the working tree we start, we should end up operating on the same repo.
+#[test]
+fn discover_repo_from_nested_directory() {
+    let git = temp_repo();
+    let top = git.working_dir.as_ref().as_ref().canonicalize().unwrap();
+    let nested = top.join("a").join("b");
+    std::fs::create_dir_all(&nested).unwrap();
+
+    let discovered = Git::discover_from(&nested).unwrap();
+    assert_eq!(discovered.working_dir.as_ref().as_ref(), top.as_path());
+    assert!(discovered.all_branches().unwrap().contains("hotfix"));
+}
+
+// Settings come from git config when present, and report where they came from.
+#[test]
+fn load_config_with_sources() {
+    let git = temp_repo();
+    let status = Command::new("git")
+        .arg("-C").arg(git.working_dir.as_ref().as_ref())
+        .args(["config","pr.trunk","main"]).status().unwrap();
+    assert!(status.success());
+
+    let config = Config::load(&git).unwrap();
+    assert_eq!(config.trunk.value, "main");
+    assert_eq!(config.trunk.source, Source::GitConfig("pr.trunk".to_string()));
+    assert_eq!(config.remote.value, "origin");
+    assert_eq!(config.remote.source, Source::Default);
+}
+
+// The PR index must notice new PR branches, whether we rebuild it or refresh a single ref.
+#[test]
+fn index_remote_pr_branches() {
+    let git = temp_repo();
+    assert!(PrIndex::load(&git, "origin").unwrap().is_empty());
+
+    let status = Command::new("git")
+        .arg("-C").arg(git.working_dir.as_ref().as_ref())
+        .args(["update-ref","refs/remotes/origin/hotfix/1234567","HEAD"]).status().unwrap();
+    assert!(status.success());
+
+    let rebuilt = PrIndex::load(&git, "origin").unwrap();
+    assert_eq!(rebuilt.names(), vec!["hotfix"]);
+
+    let mut refreshed = PrIndex::new("origin");
+    assert!(refreshed.refresh(&git, "refs/remotes/origin/hotfix/1234567").unwrap());
+    assert_eq!(refreshed, rebuilt);
+    assert!(!refreshed.refresh(&git, "refs/remotes/origin/gone/1234567").unwrap());
+}
+
+// Sign a record with a throwaway SSH key, and show that git vouches for it. An unsigned record
+// with the same content must not be trusted.
+#[test]
+fn ssh_signed_records() {
+    let git = temp_repo();
+    let keys = TempDir::new("git-pr-keys").unwrap();
+    let key = keys.path().join("id_ed25519");
+
+    let status = Command::new("ssh-keygen")
+        .args(["-q","-t","ed25519","-N","","-C","reviewer@example.com","-f"]).arg(&key)
+        .status().unwrap();
+    assert!(status.success());
+    let public_key = std::fs::read_to_string(key.with_extension("pub")).unwrap();
+    let allowed = keys.path().join("allowed_signers");
+    std::fs::write(&allowed, format!("you@example.com {}", public_key)).unwrap();
+
+    for (name, value) in [
+        ("gpg.format", "ssh".to_string()),
+        ("user.signingKey", key.display().to_string()),
+        ("gpg.ssh.allowedSignersFile", allowed.display().to_string()),
+        ("pr.signMetadata", "true".to_string()),
+    ] {
+        let status = Command::new("git")
+            .arg("-C").arg(git.working_dir.as_ref().as_ref())
+            .args(["config",name,&value]).status().unwrap();
+        assert!(status.success());
+    }
+    assert!(signing::enabled(&git).unwrap());
+
+    let signed = signing::seal(&git, "approved", true).unwrap();
+    let (signature, payload) = signing::open(&git, &signed).unwrap();
+    assert!(signature.is_trusted());
+    assert_eq!(signature.signer, "you@example.com");
+    assert_eq!(payload, "approved");
+
+    let unsigned = signing::seal(&git, "approved", false).unwrap();
+    let (signature, _) = signing::open(&git, &unsigned).unwrap();
+    assert!(!signature.is_trusted());
+}
+
+// Authors who appear in .mailmap are shown under their canonical identity.
+#[test]
+fn mailmap_merges_identities() {
+    let git = temp_repo();
+    std::fs::write(
+        git.working_dir.as_ref().as_ref().join(".mailmap"),
+        "Canonical Name <canonical@example.com> <you@example.com>\n"
+    ).unwrap();
+
+    assert_eq!(git.author_of("HEAD").unwrap(), "Canonical Name <canonical@example.com>");
+
+    let me = Identity::current(&git).unwrap();
+    let stranger = Identity::parse("Stranger <stranger@example.com>").unwrap();
+    let canonical = identity::canonicalize(&git, &[me, stranger.clone()]).unwrap();
+    assert_eq!(canonical[0].to_string(), "Canonical Name <canonical@example.com>");
+    assert_eq!(canonical[1], stranger);
+}
+
+// Merge a PR branch into trunk, and show that stats can find it again. The commit dates are fixed
+// so that we know exactly how long the PR took.
+#[test]
+fn stats_find_merged_prs() {
+    let git = temp_repo();
+    let dir = git.working_dir.as_ref().as_ref();
+    let at = |date: &str, args: &[&str]| {
+        let status = Command::new("git").arg("-C").arg(dir).args(args)
+            .env("GIT_AUTHOR_DATE", date).env("GIT_COMMITTER_DATE", date)
+            .stdout(Stdio::null()).status().unwrap();
+        assert!(status.success());
+    };
+
+    at("2021-06-01T00:00:00Z", &["checkout","-q","-b","feature/abc123"]);
+    at("2021-06-01T00:00:00Z", &["commit","--allow-empty","-m","work"]);
+    at("2021-06-01T00:00:00Z", &["checkout","-q","trunk"]);
+    at("2021-06-03T00:00:00Z", &["merge","--no-ff","--no-edit","feature/abc123"]);
+
+    let merged = stats::merged_prs(&git, "trunk", "origin", None, None).unwrap();
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].name, "feature");
+    assert_eq!(merged[0].time_to_merge(), 2 * 86_400);
+
+    assert!(stats::merged_prs(&git, "trunk", "origin", Some("2021-06-04"), None)
+        .unwrap().is_empty());
+    assert_eq!(stats::merged_prs(&git, "trunk", "origin", None, Some("2021-06-04"))
+        .unwrap().len(), 1);
+}
+
+// Changed paths are measured from the merge base, so changes on trunk don't leak into a PR.
+#[test]
+fn changed_paths_ignore_trunk_progress() {
+    let git = temp_repo();
+    let dir = git.working_dir.as_ref().as_ref();
+    let run = |args: &[&str]| {
+        let status = Command::new("git").arg("-C").arg(dir).args(args)
+            .stdout(Stdio::null()).status().unwrap();
+        assert!(status.success());
+    };
+
+    std::fs::create_dir(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src").join("lib.rs"), "// pr\n").unwrap();
+    run(&["add","src"]);
+    run(&["commit","-m","pr work"]);
+    run(&["checkout","-q","-b","pr/abc","HEAD"]);
+    run(&["checkout","-q","trunk"]);
+    run(&["reset","-q","--hard","HEAD~1"]);
+    std::fs::write(dir.join("README"), "trunk\n").unwrap();
+    run(&["add","README"]);
+    run(&["commit","-m","trunk work"]);
+
+    assert_eq!(git.changed_paths("trunk", "pr/abc").unwrap(), vec!["src/lib.rs"]);
+}
+
+// Clone `origin` into a fresh directory, with an identity so that notes can be committed.
+fn clone_repo(origin: &Git) -> Git {
+    let url = format!("file://{}", origin.working_dir.as_ref().as_ref().display());
+    let dir = TempDir::new("git-pr-clone").unwrap();
+    let status = Command::new("git").args(["clone","--quiet",&url]).arg(dir.path())
+        .status().unwrap();
+    assert!(status.success());
+    for (key, value) in [("user.name", "Your Name"), ("user.email", "you@example.com")] {
+        let status = Command::new("git").arg("-C").arg(dir.path())
+            .args(["config",key,value]).status().unwrap();
+        assert!(status.success());
+    }
+    Git{ program: "git".to_string(), working_dir: Box::new(dir), git_dir: None, work_tree: None }
+}
+
+// Two clones recording metadata about the same commit must not lose each other's updates, even
+// when one of them is working from stale information.
+#[test]
+fn concurrent_metadata_is_not_lost() {
+    let origin = temp_repo();
+    let (alice, bob) = (clone_repo(&origin), clone_repo(&origin));
+    let commit = alice.rev_parse_head().unwrap();
+
+    // Bob looks at the remote, then Alice publishes before Bob does
+    let stale = metadata::sync(&bob, "origin").unwrap();
+    metadata::append(&alice, "origin", &commit, "approved-by alice").unwrap();
+    bob.append_note(metadata::NOTES_REF, &commit, &metadata::format_line("approved-by bob"))
+        .unwrap();
+    assert!(!bob.push_with_lease("origin", metadata::NOTES_REF, stale.as_deref()).unwrap());
+
+    // Going through the metadata layer, Bob merges Alice's note rather than clobbering it
+    metadata::append(&bob, "origin", &commit, "comment by bob").unwrap();
+    metadata::sync(&alice, "origin").unwrap();
+    assert_eq!(metadata::lines(&alice, &commit).unwrap(),
+               vec!["approved-by alice", "approved-by bob", "comment by bob"]);
+}
+
+// Run `git` through flaky_git, following `script` (see src/bin/flaky_git.rs).
+fn misbehave(git: &mut Git, script: &str) {
+    std::fs::write(git.git_dir().unwrap().join("flaky-git"), script).unwrap();
+    git.program = env!("CARGO_BIN_EXE_flaky_git").to_string();
+}
+
+// How many times flaky_git has seen `command` run.
+fn calls(git: &Git, command: &str) -> usize {
+    let counts = std::fs::read_to_string(git.git_dir().unwrap().join("flaky-git.count")).unwrap();
+    counts.lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(name, _)| *name == command)
+        .map_or(0, |(_, n)| n.parse().unwrap())
+}
+
+const LOST_RACE: &str =
+    r"exit 1 To origin\n!\trefs/notes/pr:refs/notes/pr\t[rejected] (stale info)";
+
+// Losing a race to publish metadata means trying again, until the push goes through.
+#[test]
+fn metadata_retries_lost_races() {
+    let origin = temp_repo();
+    let mut alice = clone_repo(&origin);
+    let commit = alice.rev_parse_head().unwrap();
+    misbehave(&mut alice, &format!("push 1 {}\npush 2 {}\n", LOST_RACE, LOST_RACE));
+
+    metadata::append(&alice, "origin", &commit, "approved-by alice").unwrap();
+    assert_eq!(calls(&alice, "push"), 3);
+    assert_eq!(metadata::lines(&origin, &commit).unwrap(), vec!["approved-by alice"]);
+}
+
+// Retrying has its limits.
+#[test]
+fn metadata_gives_up_after_too_many_races() {
+    let origin = temp_repo();
+    let mut alice = clone_repo(&origin);
+    let commit = alice.rev_parse_head().unwrap();
+    misbehave(&mut alice, &format!("push * {}\n", LOST_RACE));
+
+    match metadata::append(&alice, "origin", &commit, "approved-by alice") {
+        Err(libgitpr::GitError::Refused(_)) => {},
+        other => panic!("expected to give up, got {:?}", other),
+    }
+    assert_eq!(calls(&alice, "push"), metadata::MAX_ATTEMPTS);
+}
+
+// A push that fails for any reason other than a race isn't retried.
+#[test]
+fn metadata_does_not_retry_failures() {
+    let origin = temp_repo();
+    let mut alice = clone_repo(&origin);
+    let commit = alice.rev_parse_head().unwrap();
+    misbehave(&mut alice, "push 1 exit 128\n");
+
+    match metadata::append(&alice, "origin", &commit, "approved-by alice") {
+        Err(libgitpr::GitError::Exit(status)) => assert_eq!(status.code(), Some(128)),
+        other => panic!("expected git's failure, got {:?}", other),
+    }
+    assert_eq!(calls(&alice, "push"), 1);
+}
+
+// Output that isn't even UTF-8 is never mistaken for branches.
+#[test]
+fn garbage_is_not_a_branch() {
+    let mut git = temp_repo();
+    misbehave(&mut git, "branch * garbage\n");
+
+    assert!(libgitpr::extract_pr_names(&git.all_branches().unwrap(), "origin").is_empty());
+    let merged = git.merged_branches("trunk").unwrap();
+    assert!(libgitpr::extract_deletable_branches(&merged, "trunk").is_empty());
+    assert_eq!(calls(&git, "branch"), 2);
+}